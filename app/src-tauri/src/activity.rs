@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{groups, model};
+
+// Activity review for long-running boards. Notes carry `createdAt` and
+// `updatedAt`, stamped by the frontend's command stack whenever a command
+// creates or changes them. `recently_edited` lists what changed since a given
+// moment; `split_by_activity` moves the branches nobody has touched since a
+// cutoff into a document of their own, to be archived.
+//
+// A branch is a group of notes joined by connections or stacks, so a stale
+// note next to an active one stays. Notes without timestamps predate activity
+// tracking and count as stale.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecentNote {
+  pub id: model::ID,
+  pub text: String,
+  pub changed_at: DateTime<Utc>,
+  // Created, rather than edited, since the given moment
+  pub created: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ActivitySplit {
+  // The board without the stale branches
+  pub active: model::BoardDocument,
+  // The stale branches, with the board's styles, for saving separately
+  pub archived: model::BoardDocument,
+  pub archived_note_ids: Vec<model::ID>,
+}
+
+pub fn last_activity(note: &model::Note) -> Option<DateTime<Utc>> {
+  note.updated_at.max(note.created_at)
+}
+
+fn first_line(text: &str) -> String {
+  text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("").to_string()
+}
+
+// Notes created or edited since `since`, newest first
+pub fn recently_edited(doc: &model::BoardDocument, since: DateTime<Utc>) -> Vec<RecentNote> {
+  let mut recent: Vec<RecentNote> = doc.notes.iter()
+    .filter_map(|note| {
+      let changed_at = last_activity(note).filter(|at| *at >= since)?;
+      Some(RecentNote {
+        id: note.id.clone(),
+        text: first_line(&note.text),
+        changed_at,
+        created: note.created_at.is_some_and(|at| at >= since),
+      })
+    })
+    .collect();
+  recent.sort_by_key(|r| std::cmp::Reverse(r.changed_at));
+  recent
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+  let mut root = i;
+  while parent[root] != root {
+    root = parent[root];
+  }
+  let mut i = i;
+  while parent[i] != root {
+    let next = parent[i];
+    parent[i] = root;
+    i = next;
+  }
+  root
+}
+
+// Branch of each note, as an index into the document's notes
+fn branches(doc: &model::BoardDocument) -> Vec<usize> {
+  let index: HashMap<&str, usize> = doc.notes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+  let mut parent: Vec<usize> = (0..doc.notes.len()).collect();
+  let pairs = doc.connections.iter()
+    .map(|c| (c.src_note_id.as_str(), c.dst_note_id.as_str()))
+    .chain(doc.stacks.iter().flat_map(|s| s.note_ids.windows(2).map(|w| (w[0].as_str(), w[1].as_str()))));
+  for (a, b) in pairs {
+    if let (Some(&a), Some(&b)) = (index.get(a), index.get(b)) {
+      let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+      parent[a] = b;
+    }
+  }
+  (0..doc.notes.len()).map(|i| find(&mut parent, i)).collect()
+}
+
+// Copy of `doc` with only the notes in `keep`, and what refers to them
+fn subset(doc: &model::BoardDocument, keep: &HashSet<&str>) -> model::BoardDocument {
+  let mut subset = doc.clone();
+  subset.notes.retain(|n| keep.contains(n.id.as_str()));
+  subset.connections.retain(|c| keep.contains(c.src_note_id.as_str()) && keep.contains(c.dst_note_id.as_str()));
+  for stack in &mut subset.stacks {
+    stack.note_ids.retain(|id| keep.contains(id.as_str()));
+  }
+  subset.stacks.retain(|s| !s.note_ids.is_empty());
+  groups::prune_groups(&mut subset);
+  subset
+}
+
+pub fn split_by_activity(doc: &model::BoardDocument, cutoff: DateTime<Utc>) -> ActivitySplit {
+  let branch = branches(doc);
+  let mut active_branches = HashSet::new();
+  for (i, note) in doc.notes.iter().enumerate() {
+    if last_activity(note).is_some_and(|at| at >= cutoff) {
+      active_branches.insert(branch[i]);
+    }
+  }
+
+  let (active_notes, archived_notes): (Vec<_>, Vec<_>) = doc.notes.iter().enumerate()
+    .partition(|(i, _)| active_branches.contains(&branch[*i]));
+  let active_ids: HashSet<&str> = active_notes.iter().map(|(_, n)| n.id.as_str()).collect();
+  let archived_ids: HashSet<&str> = archived_notes.iter().map(|(_, n)| n.id.as_str()).collect();
+
+  let active = subset(doc, &active_ids);
+  let mut archived = subset(doc, &archived_ids);
+  // Board furniture stays with the board being worked on
+  archived.shapes.clear();
+  archived.strokes = None;
+  archived.trash = None;
+  archived.templates = None;
+
+  ActivitySplit {
+    archived_note_ids: archived_notes.iter().map(|(_, n)| n.id.clone()).collect(),
+    active,
+    archived,
+  }
+}
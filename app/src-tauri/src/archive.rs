@@ -0,0 +1,297 @@
+use std::io::{Read, Seek};
+use zip::ZipArchive;
+
+// Limits applied to every .fim container before anything is decompressed.
+// They are generous for real boards but stop zip bombs from exhausting memory.
+pub const MAX_ENTRIES: usize = 10_000;
+pub const MAX_TOTAL_UNCOMPRESSED: u64 = 2 * 1024 * 1024 * 1024; // 2 GiB
+pub const MAX_BOARD_JSON_SIZE: u64 = 100 * 1024 * 1024; // 100 MiB
+pub const MAX_COMPRESSION_RATIO: u64 = 200;
+// Small entries compress extremely well (e.g. whitespace-heavy JSON), so the
+// ratio check only kicks in above this size.
+const RATIO_CHECK_THRESHOLD: u64 = 1024 * 1024;
+// Entries are decompressed in pieces of this size
+const READ_CHUNK_SIZE: usize = 256 * 1024;
+
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ArchiveError {
+  Io { message: String },
+  InvalidArchive { message: String },
+  UnsafeEntryPath { entry: String },
+  TooManyEntries { count: usize, limit: usize },
+  EntryTooLarge { entry: String, size: u64, limit: u64 },
+  TotalSizeExceeded { size: u64, limit: u64 },
+  SuspiciousCompressionRatio { entry: String, ratio: u64, limit: u64 },
+  MissingEntry { entry: String },
+}
+
+impl std::fmt::Display for ArchiveError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ArchiveError::Io { message } => write!(f, "I/O error while reading archive: {}", message),
+      ArchiveError::InvalidArchive { message } => write!(f, "Invalid .fim archive: {}", message),
+      ArchiveError::UnsafeEntryPath { entry } =>
+        write!(f, "Archive entry '{}' has an unsafe path (absolute or containing '..')", entry),
+      ArchiveError::TooManyEntries { count, limit } =>
+        write!(f, "Archive contains {} entries (limit is {})", count, limit),
+      ArchiveError::EntryTooLarge { entry, size, limit } =>
+        write!(f, "Archive entry '{}' is {} bytes when decompressed (limit is {})", entry, size, limit),
+      ArchiveError::TotalSizeExceeded { size, limit } =>
+        write!(f, "Archive decompresses to {} bytes (limit is {})", size, limit),
+      ArchiveError::SuspiciousCompressionRatio { entry, ratio, limit } =>
+        write!(f, "Archive entry '{}' has a compression ratio of {}:1 (limit is {}:1)", entry, ratio, limit),
+      ArchiveError::MissingEntry { entry } => write!(f, "Archive is missing required entry '{}'", entry),
+    }
+  }
+}
+
+impl From<ArchiveError> for String {
+  fn from(err: ArchiveError) -> String {
+    err.to_string()
+  }
+}
+
+// Reject entry names that could escape the extraction directory.
+pub fn is_safe_entry_name(name: &str) -> bool {
+  if name.is_empty() || name.contains('\0') {
+    return false;
+  }
+  if name.starts_with('/') || name.starts_with('\\') {
+    return false;
+  }
+  // Windows drive letters such as `C:` are absolute as well
+  if name.chars().nth(1) == Some(':') {
+    return false;
+  }
+  !name.split(['/', '\\']).any(|segment| segment == "..")
+}
+
+// Inspect the central directory of an archive and fail fast on anything that
+// looks malicious, before any entry is decompressed.
+pub fn validate_archive<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<(), ArchiveError> {
+  if archive.len() > MAX_ENTRIES {
+    return Err(ArchiveError::TooManyEntries { count: archive.len(), limit: MAX_ENTRIES });
+  }
+
+  let mut total: u64 = 0;
+  for index in 0..archive.len() {
+    let entry = archive.by_index_raw(index)
+      .map_err(|e| ArchiveError::InvalidArchive { message: e.to_string() })?;
+    let name = entry.name().to_string();
+
+    if !is_safe_entry_name(&name) {
+      return Err(ArchiveError::UnsafeEntryPath { entry: name });
+    }
+
+    let size = entry.size();
+    total = total.saturating_add(size);
+    if total > MAX_TOTAL_UNCOMPRESSED {
+      return Err(ArchiveError::TotalSizeExceeded { size: total, limit: MAX_TOTAL_UNCOMPRESSED });
+    }
+
+    if size > RATIO_CHECK_THRESHOLD {
+      let ratio = size / entry.compressed_size().max(1);
+      if ratio > MAX_COMPRESSION_RATIO {
+        return Err(ArchiveError::SuspiciousCompressionRatio { entry: name, ratio, limit: MAX_COMPRESSION_RATIO });
+      }
+    }
+  }
+
+  Ok(())
+}
+
+// Read a single entry fully, enforcing `limit` on the bytes actually produced
+// by the decompressor (the sizes in the central directory can lie).
+pub fn read_entry_limited<R: Read + Seek>(
+  archive: &mut ZipArchive<R>,
+  name: &str,
+  limit: u64,
+) -> Result<Vec<u8>, ArchiveError> {
+  read_entry_with_progress(archive, name, limit, &mut |_, _| {})
+}
+
+// Like read_entry_limited, calling `on_progress(read, total)` as the entry is
+// decompressed (total is the size the archive declares)
+pub fn read_entry_with_progress<R: Read + Seek>(
+  archive: &mut ZipArchive<R>,
+  name: &str,
+  limit: u64,
+  on_progress: &mut dyn FnMut(u64, u64),
+) -> Result<Vec<u8>, ArchiveError> {
+  let entry = archive.by_name(name).map_err(|e| match e {
+    zip::result::ZipError::FileNotFound => ArchiveError::MissingEntry { entry: name.to_string() },
+    other => ArchiveError::InvalidArchive { message: other.to_string() },
+  })?;
+
+  let total = entry.size();
+  if total > limit {
+    return Err(ArchiveError::EntryTooLarge { entry: name.to_string(), size: total, limit });
+  }
+
+  let mut data = Vec::with_capacity(total as usize);
+  let mut reader = entry.take(limit + 1);
+  let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+  loop {
+    let read = reader.read(&mut buffer)
+      .map_err(|e| ArchiveError::Io { message: e.to_string() })?;
+    if read == 0 {
+      break;
+    }
+    data.extend_from_slice(&buffer[..read]);
+    on_progress(data.len() as u64, total);
+  }
+
+  if data.len() as u64 > limit {
+    return Err(ArchiveError::EntryTooLarge { entry: name.to_string(), size: data.len() as u64, limit });
+  }
+
+  Ok(data)
+}
+
+// An entry read as it is decompressed rather than into memory first, so it
+// can be parsed as a stream; `limit` is enforced as in read_entry_limited
+pub struct EntryReader<'a> {
+  entry: zip::read::ZipFile<'a>,
+  limit: u64,
+  read: u64,
+  on_progress: &'a mut dyn FnMut(u64, u64),
+}
+
+pub fn open_entry<'a, R: Read + Seek>(
+  archive: &'a mut ZipArchive<R>,
+  name: &str,
+  limit: u64,
+  on_progress: &'a mut dyn FnMut(u64, u64),
+) -> Result<EntryReader<'a>, ArchiveError> {
+  let entry = archive.by_name(name).map_err(|e| match e {
+    zip::result::ZipError::FileNotFound => ArchiveError::MissingEntry { entry: name.to_string() },
+    other => ArchiveError::InvalidArchive { message: other.to_string() },
+  })?;
+  if entry.size() > limit {
+    return Err(ArchiveError::EntryTooLarge { entry: name.to_string(), size: entry.size(), limit });
+  }
+  Ok(EntryReader { entry, limit, read: 0, on_progress })
+}
+
+impl Read for EntryReader<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let read = self.entry.read(buf)?;
+    self.read += read as u64;
+    if self.read > self.limit {
+      let name = self.entry.name().to_string();
+      return Err(std::io::Error::other(ArchiveError::EntryTooLarge { entry: name, size: self.read, limit: self.limit }.to_string()));
+    }
+    (self.on_progress)(self.read, self.entry.size());
+    Ok(read)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::{Cursor, Write};
+  use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+  fn build(entries: &[(&str, &[u8])], method: CompressionMethod) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(method);
+    for (name, data) in entries {
+      zip.start_file(*name, options).unwrap();
+      zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+  }
+
+  fn open(bytes: Vec<u8>) -> ZipArchive<Cursor<Vec<u8>>> {
+    ZipArchive::new(Cursor::new(bytes)).unwrap()
+  }
+
+  // Overwrite the uncompressed size recorded in the local and central headers
+  fn declare_size(bytes: &mut [u8], size: u32) {
+    for (signature, offset) in [(0x04034b50u32, 22), (0x02014b50u32, 24)] {
+      let at = (0..bytes.len() - 4)
+        .find(|&i| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap()) == signature)
+        .unwrap();
+      bytes[at + offset..at + offset + 4].copy_from_slice(&size.to_le_bytes());
+    }
+  }
+
+  #[test]
+  fn entry_names_that_escape_are_unsafe() {
+    assert!(is_safe_entry_name("board.json"));
+    assert!(is_safe_entry_name("media/photo..png"));
+    for name in ["", "../board.json", "media/../../evil", "media\\..\\evil", "/etc/passwd", "\\evil", "C:\\evil", "nul\0byte"] {
+      assert!(!is_safe_entry_name(name), "{:?} should be unsafe", name);
+    }
+  }
+
+  #[test]
+  fn archives_with_unsafe_entries_are_rejected() {
+    for name in ["../board.json", "/board.json"] {
+      let mut archive = open(build(&[("board.json", b"{}"), (name, b"x")], CompressionMethod::Stored));
+      match validate_archive(&mut archive) {
+        Err(ArchiveError::UnsafeEntryPath { entry }) => assert_eq!(entry, name),
+        other => panic!("expected an unsafe path error, got {:?}", other),
+      }
+    }
+  }
+
+  #[test]
+  fn highly_compressed_entries_are_rejected() {
+    let zeros = vec![0u8; 4 * 1024 * 1024];
+    let mut archive = open(build(&[("board.json", &zeros)], CompressionMethod::Deflated));
+    match validate_archive(&mut archive) {
+      Err(ArchiveError::SuspiciousCompressionRatio { ratio, .. }) => assert!(ratio > MAX_COMPRESSION_RATIO),
+      other => panic!("expected a compression ratio error, got {:?}", other),
+    }
+
+    // Below the threshold the ratio doesn't matter
+    let mut archive = open(build(&[("board.json", &zeros[..1024])], CompressionMethod::Deflated));
+    assert!(validate_archive(&mut archive).is_ok());
+  }
+
+  #[test]
+  fn archives_with_too_many_entries_are_rejected() {
+    let names: Vec<String> = (0..=MAX_ENTRIES).map(|i| format!("media/{}", i)).collect();
+    let entries: Vec<(&str, &[u8])> = names.iter().map(|name| (name.as_str(), &b""[..])).collect();
+    let mut archive = open(build(&entries, CompressionMethod::Stored));
+    match validate_archive(&mut archive) {
+      Err(ArchiveError::TooManyEntries { count, limit }) => {
+        assert_eq!(count, MAX_ENTRIES + 1);
+        assert_eq!(limit, MAX_ENTRIES);
+      }
+      other => panic!("expected a too many entries error, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn entries_larger_than_declared_are_cut_off() {
+    let data = vec![b'x'; 1000];
+    let mut bytes = build(&[("board.json", &data)], CompressionMethod::Stored);
+    declare_size(&mut bytes, 10);
+    let mut archive = open(bytes);
+    assert!(validate_archive(&mut archive).is_ok());
+    match read_entry_limited(&mut archive, "board.json", 100) {
+      Err(ArchiveError::EntryTooLarge { size, limit, .. }) => {
+        assert!(size > limit);
+        assert_eq!(limit, 100);
+      }
+      other => panic!("expected an entry too large error, got {:?}", other.map(|data| data.len())),
+    }
+  }
+
+  #[test]
+  fn entries_within_the_limit_are_read() {
+    let mut archive = open(build(&[("board.json", b"{\"notes\":[]}")], CompressionMethod::Deflated));
+    assert_eq!(read_entry_limited(&mut archive, "board.json", 100).unwrap(), b"{\"notes\":[]}");
+    assert!(matches!(
+      read_entry_limited(&mut archive, "board.json", 4),
+      Err(ArchiveError::EntryTooLarge { size: 12, limit: 4, .. })
+    ));
+    assert!(matches!(
+      read_entry_limited(&mut archive, "missing.json", 100),
+      Err(ArchiveError::MissingEntry { .. })
+    ));
+  }
+}
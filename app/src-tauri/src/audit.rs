@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::model;
+use crate::settings;
+
+const AUDIT_LOG_FILE: &str = "audit.log";
+
+// One line of the append-only audit log (stored as JSON Lines).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+  pub timestamp: chrono::DateTime<chrono::Utc>,
+  pub operation: String,
+  pub path: String,
+  #[serde(rename = "documentHash")]
+  pub document_hash: Option<String>,
+  pub success: bool,
+  pub error: Option<String>,
+}
+
+impl AuditEntry {
+  pub fn new<T>(operation: &str, path: &Path, document_hash: Option<String>, result: &Result<T, String>) -> Self {
+    AuditEntry {
+      timestamp: chrono::Utc::now(),
+      operation: operation.to_string(),
+      path: path.to_string_lossy().to_string(),
+      document_hash,
+      success: result.is_ok(),
+      error: result.as_ref().err().cloned(),
+    }
+  }
+}
+
+fn audit_log_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(AUDIT_LOG_FILE))
+}
+
+pub fn hash_bytes(data: &[u8]) -> String {
+  hex(&Sha256::digest(data))
+}
+
+fn hex(digest: &[u8]) -> String {
+  digest.iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect()
+}
+
+// Hashes what is read through it, for data that is parsed as it is read;
+// `finish` gives the same as hash_bytes on all of it
+pub struct HashingReader<R> {
+  inner: R,
+  hasher: Sha256,
+}
+
+impl<R: std::io::Read> HashingReader<R> {
+  pub fn new(inner: R) -> Self {
+    HashingReader { inner, hasher: Sha256::new() }
+  }
+
+  pub fn finish(self) -> String {
+    hex(&self.hasher.finalize())
+  }
+}
+
+impl<R: std::io::Read> std::io::Read for HashingReader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let read = self.inner.read(buf)?;
+    self.hasher.update(&buf[..read]);
+    Ok(read)
+  }
+}
+
+// SHA-256 of the document's canonical JSON serialization
+pub fn hash_document(doc: &model::BoardDocument) -> Option<String> {
+  serde_json::to_vec(doc).ok().map(|json| hash_bytes(&json))
+}
+
+pub fn append_entry(entry: &AuditEntry) -> Result<(), String> {
+  let path = audit_log_path().ok_or("Could not determine the application data directory")?;
+
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|e| format!("Failed to create audit log directory '{}': {}", parent.display(), e))?;
+  }
+
+  let line = serde_json::to_string(entry)
+    .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+
+  let mut file = std::fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&path)
+    .map_err(|e| format!("Failed to open audit log '{}': {}", path.display(), e))?;
+
+  writeln!(file, "{}", line)
+    .map_err(|e| format!("Failed to write audit log '{}': {}", path.display(), e))
+}
+
+// Read the most recent entries (newest first). Malformed lines are skipped so
+// a single torn write can't hide the rest of the log.
+pub fn read_entries(limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+  let Some(path) = audit_log_path() else {
+    return Ok(Vec::new());
+  };
+  if !path.exists() {
+    return Ok(Vec::new());
+  }
+
+  let file = std::fs::File::open(&path)
+    .map_err(|e| format!("Failed to open audit log '{}': {}", path.display(), e))?;
+
+  let mut entries: Vec<AuditEntry> = std::io::BufReader::new(file)
+    .lines()
+    .map_while(Result::ok)
+    .filter_map(|line| serde_json::from_str(&line).ok())
+    .collect();
+
+  entries.reverse();
+  if let Some(limit) = limit {
+    entries.truncate(limit);
+  }
+
+  Ok(entries)
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::model;
+
+// Coalescing of autosave requests. The frontend autosaves after edits, which
+// comes in bursts while someone is dragging or typing, and every autosave is
+// a full zip write. Requests only replace the snapshot waiting for a board;
+// it is written once the board has been quiet for `QUIET_PERIOD`, and at the
+// latest `MAX_STALENESS` after the oldest unwritten change, so continuous
+// editing still reaches the disk. One writer runs per board while it has an
+// entry here.
+
+pub const QUIET_PERIOD: Duration = Duration::from_secs(2);
+pub const MAX_STALENESS: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+struct Pending {
+  // Latest snapshot not yet written; None once the writer took it
+  doc: Option<model::BoardDocument>,
+  // When the oldest unwritten change was queued
+  since: Instant,
+  // When the latest snapshot was queued
+  updated: Instant,
+  writing: bool,
+  // The board was saved while its snapshot was being written, so the
+  // recovery file just written is stale
+  discarded: bool,
+}
+
+pub enum Next {
+  Write(Box<model::BoardDocument>),
+  Wait(Duration),
+  Done,
+}
+
+#[derive(Debug, Default)]
+pub struct AutosaveQueue {
+  pending: HashMap<PathBuf, Pending>,
+}
+
+impl AutosaveQueue {
+  // Queues `doc` in place of any earlier snapshot of the board; true when no
+  // writer is running for it and the caller has to start one
+  pub fn submit(&mut self, path: &Path, doc: model::BoardDocument) -> bool {
+    let now = Instant::now();
+    match self.pending.get_mut(path) {
+      Some(pending) => {
+        if pending.doc.is_none() {
+          pending.since = now;
+        }
+        pending.doc = Some(doc);
+        pending.updated = now;
+        pending.discarded = false;
+        false
+      },
+      None => {
+        self.pending.insert(path.to_path_buf(), Pending { doc: Some(doc), since: now, updated: now, writing: false, discarded: false });
+        true
+      },
+    }
+  }
+
+  // What the board's writer does next. The entry goes away with the writer,
+  // once there is nothing left to write.
+  pub fn next(&mut self, path: &Path) -> Next {
+    let Some(pending) = self.pending.get_mut(path) else {
+      return Next::Done;
+    };
+    if pending.doc.is_none() {
+      self.pending.remove(path);
+      return Next::Done;
+    }
+    let due = (pending.updated + QUIET_PERIOD).min(pending.since + MAX_STALENESS);
+    let now = Instant::now();
+    if now < due {
+      return Next::Wait(due - now);
+    }
+    pending.writing = true;
+    match pending.doc.take() {
+      Some(doc) => Next::Write(Box::new(doc)),
+      None => Next::Done,
+    }
+  }
+
+  // The writer wrote the snapshot it took; true when the board was saved
+  // meanwhile and the recovery file should go
+  pub fn finished(&mut self, path: &Path) -> bool {
+    match self.pending.get_mut(path) {
+      Some(pending) => {
+        pending.writing = false;
+        std::mem::take(&mut pending.discarded)
+      },
+      None => false,
+    }
+  }
+
+  // Drops the snapshot waiting for a board that was just saved
+  pub fn discard(&mut self, path: &Path) {
+    if let Some(pending) = self.pending.get_mut(path) {
+      pending.doc = None;
+      pending.discarded = pending.writing;
+    }
+  }
+
+  // Snapshots not yet written, for writing out before the app quits
+  pub fn take_all(&mut self) -> Vec<(PathBuf, model::BoardDocument)> {
+    self.pending.iter_mut()
+      .filter_map(|(path, pending)| Some((path.clone(), pending.doc.take()?)))
+      .collect()
+  }
+}
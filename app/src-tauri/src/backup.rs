@@ -0,0 +1,164 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::settings;
+
+// Scheduled backups to a folder of the user's choosing, such as an external
+// drive. Unlike autosave, which keeps one recovery copy of unsaved changes to
+// survive a crash, backups keep a history of saved boards somewhere else.
+// Every `intervalMinutes`, each board opened or saved this session whose file
+// changed since its last backup is copied as `<name> <date> <time>.<ext>`;
+// only the newest `keep` copies of each board are kept.
+
+// How often the scheduler wakes up to see whether backups are due
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const BACKUPS_DIR: &str = "backups";
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H%M%S";
+// Length of a formatted timestamp, plus the space before it
+const SUFFIX_LEN: usize = 18;
+
+#[derive(Debug, Default)]
+pub struct BackupTracker {
+  // Boards to back up, with the modification time of their last backup
+  documents: HashMap<PathBuf, Option<SystemTime>>,
+  last_run: Option<SystemTime>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupRecord {
+  pub source: String,
+  pub backup: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BackupEntry {
+  pub path: String,
+  // File name of the board it is a copy of
+  pub source_name: String,
+  pub created_at: DateTime<Utc>,
+  pub size: u64,
+}
+
+impl BackupTracker {
+  pub fn track(&mut self, path: &Path) {
+    self.documents.entry(path.to_path_buf()).or_insert(None);
+  }
+
+  // Whether the interval has passed since the last run
+  pub fn is_due(&self, interval: Duration) -> bool {
+    self.last_run.map_or(true, |last| last.elapsed().map_or(true, |elapsed| elapsed >= interval))
+  }
+
+  // Boards whose file changed since their last backup, with its current
+  // modification time
+  pub fn changed(&self) -> Vec<(PathBuf, SystemTime)> {
+    self.documents.iter()
+      .filter_map(|(path, backed_up)| {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        (*backed_up != Some(modified)).then(|| (path.clone(), modified))
+      })
+      .collect()
+  }
+
+  pub fn finished(&mut self, backed_up: &[(PathBuf, SystemTime)]) {
+    for (path, modified) in backed_up {
+      self.documents.insert(path.clone(), Some(*modified));
+    }
+    self.last_run = Some(SystemTime::now());
+  }
+}
+
+// The configured backup folder, or `backups` in the app data folder
+pub fn backup_dir(settings: &settings::BackupSettings) -> Option<PathBuf> {
+  settings.directory.as_deref()
+    .map(str::trim)
+    .filter(|d| !d.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| settings::data_dir().map(|dir| dir.join(BACKUPS_DIR)))
+}
+
+fn split_name(path: &Path) -> (String, String) {
+  let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+  let extension = path.extension().map(|e| e.to_string_lossy().to_string()).unwrap_or_default();
+  (stem, extension)
+}
+
+// The source stem and time of a backup file name, if it is one
+fn parse_backup_name(path: &Path) -> Option<(String, DateTime<Utc>)> {
+  let (stem, _) = split_name(path);
+  let split = stem.len().checked_sub(SUFFIX_LEN)?;
+  let (source, suffix) = (stem.get(..split)?, stem.get(split + 1..)?);
+  let time = NaiveDateTime::parse_from_str(suffix, TIMESTAMP_FORMAT).ok()?;
+  let time = Local.from_local_datetime(&time).earliest()?.with_timezone(&Utc);
+  Some((source.to_string(), time))
+}
+
+// Backups of `source` in `dir`, newest first
+fn backups_of(dir: &Path, source: &Path) -> Vec<(PathBuf, DateTime<Utc>)> {
+  let (stem, extension) = split_name(source);
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut found: Vec<(PathBuf, DateTime<Utc>)> = entries
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter(|path| split_name(path).1 == extension)
+    .filter_map(|path| {
+      let (source_stem, time) = parse_backup_name(&path)?;
+      (source_stem == stem).then_some((path, time))
+    })
+    .collect();
+  found.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+  found
+}
+
+// Copy `source` into `dir` under a timestamped name and drop the copies
+// beyond the newest `keep`
+pub fn backup_file(source: &Path, dir: &Path, keep: usize) -> Result<PathBuf, String> {
+  std::fs::create_dir_all(dir)
+    .map_err(|e| format!("Failed to create backup folder '{}': {}", dir.display(), e))?;
+  let (stem, extension) = split_name(source);
+  let mut name = format!("{} {}", stem, Local::now().format(TIMESTAMP_FORMAT));
+  if !extension.is_empty() {
+    name = format!("{}.{}", name, extension);
+  }
+  let target = dir.join(name);
+  std::fs::copy(source, &target)
+    .map_err(|e| format!("Failed to back up '{}' to '{}': {}", source.display(), target.display(), e))?;
+
+  for (old, _) in backups_of(dir, source).into_iter().skip(keep.max(1)) {
+    if let Err(e) = std::fs::remove_file(&old) {
+      log::warn!("Failed to remove old backup '{}': {}", old.display(), e);
+    }
+  }
+  Ok(target)
+}
+
+// Backups in `dir`, newest first; only those of `source` when given
+pub fn list_backups(dir: &Path, source: Option<&Path>) -> Vec<BackupEntry> {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let source = source.map(split_name);
+  let mut found: Vec<BackupEntry> = entries
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter_map(|path| {
+      let (stem, time) = parse_backup_name(&path)?;
+      let extension = split_name(&path).1;
+      if source.as_ref().is_some_and(|(s, e)| *s != stem || *e != extension) {
+        return None;
+      }
+      let source_name = if extension.is_empty() { stem } else { format!("{}.{}", stem, extension) };
+      Some(BackupEntry {
+        size: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+        path: path.to_string_lossy().to_string(),
+        source_name,
+        created_at: time,
+      })
+    })
+    .collect();
+  found.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+  found
+}
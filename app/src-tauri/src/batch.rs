@@ -0,0 +1,95 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::{cli, TextExportOptions};
+
+// Exporting every board in a folder at once, for archiving a project's
+// boards without a dialog per board. Each .fim file directly inside the
+// folder is opened as the app opens it and exported with the same code as
+// `fim export`, named after its board ("Plot.fim" becomes "Plot.pdf"). A
+// board that fails (damaged, or protected by a password that isn't unlocked)
+// is reported and the others carry on.
+
+pub const FORMATS: &[&str] = &["txt", "md", "opml", "pdf", "png"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BatchExportItem {
+  pub board: String,
+  // The file written; none when the board failed
+  pub output: Option<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BatchExport {
+  pub format: String,
+  pub output_dir: String,
+  // One per board, by file name
+  pub items: Vec<BatchExportItem>,
+  pub exported: usize,
+  pub failed: usize,
+}
+
+// .fim files directly inside `dir`, by name
+fn boards(dir: &Path) -> Result<Vec<PathBuf>, String> {
+  let mut boards: Vec<PathBuf> = std::fs::read_dir(dir)
+    .map_err(|e| format!("Failed to read folder '{}': {}", dir.display(), e))?
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("fim")))
+    .collect();
+  boards.sort();
+  Ok(boards)
+}
+
+// Export the boards in `input_dir` into `output_dir`. Every file goes
+// through `check_write` first; `on_board` is called before each board with
+// its index and the number of boards, and stops the export when it fails.
+pub fn export(
+  input_dir: &Path,
+  output_dir: &Path,
+  format: &str,
+  options: &TextExportOptions,
+  check_write: &dyn Fn(&Path) -> Result<PathBuf, String>,
+  on_board: &mut dyn FnMut(usize, usize) -> Result<(), String>,
+) -> Result<BatchExport, String> {
+  if !FORMATS.contains(&format) {
+    return Err(format!("Unsupported batch export format '{}'. Must be one of: {}", format, FORMATS.join(", ")));
+  }
+  for dir in [input_dir, output_dir] {
+    if !dir.is_dir() {
+      return Err(format!("'{}' is not a folder", dir.display()));
+    }
+  }
+  let boards = boards(input_dir)?;
+  if boards.is_empty() {
+    return Err(format!("No .fim boards in '{}'", input_dir.display()));
+  }
+
+  let board_export = cli::BoardExport::new(format);
+  let mut items = Vec::with_capacity(boards.len());
+  for (index, board) in boards.iter().enumerate() {
+    on_board(index, boards.len())?;
+    let stem = board.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+    let target = output_dir.join(format!("{}.{}", stem, cli::extension(format)));
+    let result = check_write(&target)
+      .and_then(|target| cli::export_board(board, &target, &board_export, options).map(|_| target));
+    if let Err(e) = &result {
+      log::warn!("Batch export of '{}' failed: {}", board.display(), e);
+    }
+    items.push(BatchExportItem {
+      board: board.to_string_lossy().to_string(),
+      output: result.as_ref().ok().map(|target| target.to_string_lossy().to_string()),
+      error: result.err(),
+    });
+  }
+
+  let failed = items.iter().filter(|item| item.error.is_some()).count();
+  Ok(BatchExport {
+    format: format.to_string(),
+    output_dir: output_dir.to_string_lossy().to_string(),
+    exported: items.len() - failed,
+    failed,
+    items,
+  })
+}
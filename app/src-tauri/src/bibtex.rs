@@ -0,0 +1,377 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{model, outline};
+
+// BibTeX import (Zotero, Mendeley, JabRef and most reference managers export
+// it). Each entry becomes a note whose text is a formatted citation, with the
+// DOI and URL as links and every field kept as a property.
+
+const NOTE_WIDTH: f64 = 300.0;
+const NOTE_HEIGHT: f64 = 100.0;
+const GAP: f64 = 30.0;
+const COLUMNS: usize = 4;
+// Authors listed before switching to "et al."
+const MAX_AUTHORS: usize = 5;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct BibtexImport {
+  pub notes: Vec<model::Note>,
+  // Entries that couldn't be parsed
+  pub warnings: Vec<String>,
+}
+
+#[derive(Debug)]
+struct Entry {
+  kind: String,
+  key: String,
+  // Lowercased field names, values with LaTeX markup removed
+  fields: Vec<(String, String)>,
+}
+
+impl Entry {
+  fn field(&self, name: &str) -> Option<&str> {
+    self.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str()).filter(|v| !v.is_empty())
+  }
+}
+
+struct Parser<'a> {
+  chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+  // @string abbreviations
+  strings: HashMap<String, String>,
+}
+
+impl Parser<'_> {
+  fn skip_whitespace(&mut self) {
+    while self.chars.peek().is_some_and(|(_, c)| c.is_whitespace()) {
+      self.chars.next();
+    }
+  }
+
+  fn identifier(&mut self) -> String {
+    let mut name = String::new();
+    while let Some((_, c)) = self.chars.peek() {
+      if c.is_alphanumeric() || "_-:./+".contains(*c) {
+        name.push(*c);
+        self.chars.next();
+      } else {
+        break;
+      }
+    }
+    name
+  }
+
+  // `{...}` with nested braces, without the outer pair
+  fn braced(&mut self) -> Result<String, String> {
+    let mut depth = 1;
+    let mut value = String::new();
+    for (_, c) in self.chars.by_ref() {
+      match c {
+        '{' => depth += 1,
+        '}' => {
+          depth -= 1;
+          if depth == 0 {
+            return Ok(value);
+          }
+        },
+        _ => {},
+      }
+      value.push(c);
+    }
+    Err("unbalanced braces".to_string())
+  }
+
+  fn quoted(&mut self) -> Result<String, String> {
+    let mut depth = 0;
+    let mut value = String::new();
+    for (_, c) in self.chars.by_ref() {
+      match c {
+        '{' => depth += 1,
+        '}' => depth -= 1,
+        '"' if depth == 0 => return Ok(value),
+        _ => {},
+      }
+      value.push(c);
+    }
+    Err("unterminated quoted value".to_string())
+  }
+
+  // A value: pieces joined with `#`, each braced, quoted, a number or an @string name
+  fn value(&mut self) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+      self.skip_whitespace();
+      match self.chars.peek().map(|(_, c)| *c) {
+        Some('{') => {
+          self.chars.next();
+          value += &self.braced()?;
+        },
+        Some('"') => {
+          self.chars.next();
+          value += &self.quoted()?;
+        },
+        Some(_) => {
+          let name = self.identifier();
+          if name.is_empty() {
+            return Err("expected a value".to_string());
+          }
+          value += self.strings.get(&name.to_lowercase()).map(String::as_str).unwrap_or(&name);
+        },
+        None => return Err("unexpected end of file".to_string()),
+      }
+      self.skip_whitespace();
+      if self.chars.peek().map(|(_, c)| *c) == Some('#') {
+        self.chars.next();
+      } else {
+        return Ok(value);
+      }
+    }
+  }
+
+  // The fields after the citation key, up to the closing brace
+  fn fields(&mut self, close: char) -> Result<Vec<(String, String)>, String> {
+    let mut fields = Vec::new();
+    loop {
+      self.skip_whitespace();
+      match self.chars.next().map(|(_, c)| c) {
+        Some(',') => {},
+        Some(c) if c == close => return Ok(fields),
+        Some(_) => return Err("expected ',' between fields".to_string()),
+        None => return Err("unexpected end of file".to_string()),
+      }
+      self.skip_whitespace();
+      if self.chars.peek().map(|(_, c)| *c) == Some(close) {
+        self.chars.next();
+        return Ok(fields);
+      }
+      let name = self.identifier().to_lowercase();
+      self.skip_whitespace();
+      if name.is_empty() || self.chars.next().map(|(_, c)| c) != Some('=') {
+        return Err("expected 'field = value'".to_string());
+      }
+      let value = self.value()?;
+      fields.push((name, value));
+    }
+  }
+
+  // Skip to the end of a block we don't read (@comment, @preamble)
+  fn skip_block(&mut self, close: char) {
+    let open = if close == ')' { '(' } else { '{' };
+    let mut depth = 1;
+    for (_, c) in self.chars.by_ref() {
+      if c == open {
+        depth += 1;
+      } else if c == close {
+        depth -= 1;
+        if depth == 0 {
+          return;
+        }
+      }
+    }
+  }
+
+  fn entry(&mut self) -> Result<Option<Entry>, String> {
+    let kind = self.identifier().to_lowercase();
+    self.skip_whitespace();
+    let close = match self.chars.next().map(|(_, c)| c) {
+      Some('{') => '}',
+      Some('(') => ')',
+      _ => return Err(format!("expected '{{' after @{}", kind)),
+    };
+    match kind.as_str() {
+      "comment" | "preamble" => {
+        self.skip_block(close);
+        Ok(None)
+      },
+      "string" => {
+        self.skip_whitespace();
+        let name = self.identifier().to_lowercase();
+        self.skip_whitespace();
+        if self.chars.next().map(|(_, c)| c) != Some('=') {
+          return Err("expected '=' in @string".to_string());
+        }
+        let value = self.value()?;
+        self.strings.insert(name, value);
+        self.skip_block(close);
+        Ok(None)
+      },
+      _ => {
+        self.skip_whitespace();
+        let key = self.identifier();
+        let fields = self.fields(close)?
+          .into_iter()
+          .map(|(name, value)| (name, clean_latex(&value)))
+          .collect();
+        Ok(Some(Entry { kind, key, fields }))
+      },
+    }
+  }
+}
+
+fn parse(text: &str) -> (Vec<Entry>, Vec<String>) {
+  let mut parser = Parser { chars: text.char_indices().peekable(), strings: HashMap::new() };
+  let mut entries = Vec::new();
+  let mut warnings = Vec::new();
+  // Anything outside an @entry is a comment
+  while let Some((offset, c)) = parser.chars.next() {
+    if c != '@' {
+      continue;
+    }
+    match parser.entry() {
+      Ok(Some(entry)) => entries.push(entry),
+      Ok(None) => {},
+      Err(e) => {
+        let line = text[..offset].lines().count().max(1);
+        warnings.push(format!("Skipped the entry on line {}: {}", line, e));
+      },
+    }
+  }
+  (entries, warnings)
+}
+
+// Accent commands and escapes common in BibTeX values, as plain Unicode
+fn clean_latex(value: &str) -> String {
+  const ACCENTS: &[(char, char)] = &[('"', '\u{308}'), ('\'', '\u{301}'), ('`', '\u{300}'), ('^', '\u{302}'), ('~', '\u{303}'), ('c', '\u{327}')];
+  let mut cleaned = String::with_capacity(value.len());
+  let mut chars = value.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '{' | '}' => {},
+      '\\' => {
+        let Some(&next) = chars.peek() else {
+          break;
+        };
+        if let Some((_, mark)) = ACCENTS.iter().find(|(command, _)| *command == next) {
+          chars.next();
+          // \"o, \"{o} and \c{c} all name the letter after the command
+          while chars.peek().is_some_and(|c| *c == '{' || *c == ' ') {
+            chars.next();
+          }
+          if let Some(letter) = chars.next() {
+            cleaned.push(letter);
+            cleaned.push(*mark);
+          }
+        } else if next.is_alphabetic() {
+          // Other commands (\textit, \emph, ...) keep only their argument
+          while chars.peek().is_some_and(|c| c.is_alphabetic()) {
+            chars.next();
+          }
+        } else {
+          // \&, \%, \$, \_, \#
+          cleaned.push(next);
+          chars.next();
+        }
+      },
+      '~' => cleaned.push('\u{a0}'),
+      _ => cleaned.push(c),
+    }
+  }
+  let cleaned = cleaned.replace("---", "—").replace("--", "–");
+  cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// "Last, First" or "First Last" → "Last"
+fn surname(author: &str) -> &str {
+  match author.split_once(',') {
+    Some((last, _)) => last.trim(),
+    None => author.split_whitespace().last().unwrap_or(author),
+  }
+}
+
+fn format_authors(authors: &str) -> String {
+  let names: Vec<&str> = authors.split(" and ").map(str::trim).filter(|a| !a.is_empty()).map(surname).collect();
+  match names.len() {
+    0 => String::new(),
+    1 => names[0].to_string(),
+    n if n > MAX_AUTHORS => format!("{} et al.", names[0]),
+    n => format!("{} & {}", names[..n - 1].join(", "), names[n - 1]),
+  }
+}
+
+// Author-year citation: "Surname & Surname (2020). Title. Venue."
+fn citation(entry: &Entry) -> String {
+  let mut parts = Vec::new();
+  let authors = entry.field("author").or(entry.field("editor")).map(format_authors).unwrap_or_default();
+  let year = entry.field("year").or_else(|| entry.field("date").map(|d| d.get(..4).unwrap_or(d)));
+  match (authors.is_empty(), year) {
+    (false, Some(year)) => parts.push(format!("{} ({}).", authors, year)),
+    (false, None) => parts.push(format!("{}.", authors)),
+    (true, Some(year)) => parts.push(format!("({}).", year)),
+    (true, None) => {},
+  }
+  if let Some(title) = entry.field("title") {
+    parts.push(format!("{}.", title.trim_end_matches('.')));
+  }
+  let venue = entry.field("journal")
+    .or(entry.field("journaltitle"))
+    .or(entry.field("booktitle"))
+    .or(entry.field("publisher"))
+    .or(entry.field("school"))
+    .or(entry.field("institution"));
+  if let Some(venue) = venue {
+    let mut venue = venue.to_string();
+    if let Some(volume) = entry.field("volume") {
+      venue += &format!(", {}", volume);
+      if let Some(number) = entry.field("number") {
+        venue += &format!("({})", number);
+      }
+    }
+    if let Some(pages) = entry.field("pages") {
+      venue += &format!(", {}", pages);
+    }
+    parts.push(format!("{}.", venue));
+  }
+  if parts.is_empty() {
+    entry.key.clone()
+  } else {
+    parts.join(" ")
+  }
+}
+
+fn links(entry: &Entry) -> Vec<String> {
+  let mut links = Vec::new();
+  if let Some(doi) = entry.field("doi") {
+    let doi = doi.trim_start_matches("https://doi.org/").trim_start_matches("doi:");
+    links.push(format!("https://doi.org/{}", doi));
+  }
+  if let Some(url) = entry.field("url") {
+    if !links.iter().any(|l| l == url) {
+      links.push(url.to_string());
+    }
+  }
+  links
+}
+
+pub fn import(text: &str, origin: Option<model::Point>) -> Result<BibtexImport, String> {
+  let (entries, warnings) = parse(text);
+  if entries.is_empty() {
+    return Err(match warnings.first() {
+      Some(reason) => format!("No BibTeX entries could be read: {}", reason),
+      None => "The file has no BibTeX entries".to_string(),
+    });
+  }
+
+  let origin = origin.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  let notes = entries.iter().enumerate()
+    .map(|(index, entry)| {
+      let frame = model::Rect {
+        x: origin.x + (index % COLUMNS) as f64 * (NOTE_WIDTH + GAP),
+        y: origin.y + (index / COLUMNS) as f64 * (NOTE_HEIGHT + GAP),
+        w: NOTE_WIDTH,
+        h: NOTE_HEIGHT,
+      };
+      let mut note = outline::imported_note(&citation(entry), frame);
+      let links = links(entry);
+      note.links = (!links.is_empty()).then_some(links);
+      let mut properties: HashMap<String, serde_json::Value> = entry.fields.iter()
+        .map(|(name, value)| (name.clone(), serde_json::Value::String(value.clone())))
+        .collect();
+      properties.insert("entryType".to_string(), serde_json::Value::String(entry.kind.clone()));
+      properties.insert("citationKey".to_string(), serde_json::Value::String(entry.key.clone()));
+      note.properties = Some(properties);
+      note
+    })
+    .collect();
+
+  Ok(BibtexImport { notes, warnings })
+}
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::{confidential, groups, model, settings, trash};
+
+// Board templates are whole boards kept as .fim files in the `templates`
+// folder of the app data folder, for boards set up the same way again and
+// again (a weekly retro, a kanban board). Unlike note templates (templates.rs),
+// which live in a board and insert a few notes into it, a board template is
+// the starting point for a new board. Saving one can blank the notes' content
+// while keeping their styles, frames, stacks and layers, the shapes and the
+// connections between them.
+
+const TEMPLATES_DIR: &str = "templates";
+const TEMPLATE_EXTENSION: &str = "fim";
+const MAX_NAME_LENGTH: usize = 100;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct BoardTemplate {
+  pub name: String,
+  pub path: String,
+  pub modified_at: Option<DateTime<Utc>>,
+  pub size: u64,
+}
+
+pub fn templates_dir() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(TEMPLATES_DIR))
+}
+
+// The template's name as a file name, without the characters file systems
+// don't allow
+fn file_stem(name: &str) -> Result<String, String> {
+  let stem: String = name.chars()
+    .filter(|c| !"/\\:*?\"<>|".contains(*c) && !c.is_control())
+    .collect();
+  let stem = stem.trim().trim_start_matches('.').trim().to_string();
+  if stem.is_empty() {
+    return Err("Template name is empty".to_string());
+  }
+  if stem.chars().count() > MAX_NAME_LENGTH {
+    return Err(format!("Template name is longer than {} characters", MAX_NAME_LENGTH));
+  }
+  Ok(stem)
+}
+
+pub fn template_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+  Ok(dir.join(format!("{}.{}", file_stem(name)?, TEMPLATE_EXTENSION)))
+}
+
+fn describe(path: &Path) -> Option<BoardTemplate> {
+  let metadata = std::fs::metadata(path).ok()?;
+  Some(BoardTemplate {
+    name: path.file_stem()?.to_string_lossy().to_string(),
+    path: path.to_string_lossy().to_string(),
+    modified_at: metadata.modified().ok().map(DateTime::<Utc>::from),
+    size: metadata.len(),
+  })
+}
+
+pub fn describe_template(path: &Path) -> Result<BoardTemplate, String> {
+  describe(path).ok_or_else(|| format!("Failed to read template '{}'", path.display()))
+}
+
+// Templates in `dir`, by name
+pub fn list_templates(dir: &Path) -> Vec<BoardTemplate> {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut found: Vec<BoardTemplate> = entries
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(TEMPLATE_EXTENSION))
+    .filter_map(|path| describe(&path))
+    .collect();
+  found.sort_by_key(|template| template.name.to_lowercase());
+  found
+}
+
+// The board as kept for a template. What was deleted and confidential notes
+// are left out, and with them the board's passphrase; so are its info and
+// when notes were made. Without `keep_content` notes keep only their layout
+// and look: text, links, images, checklist items, table rows, tags and
+// properties go, and so do connection labels.
+pub fn prepare(doc: &model::BoardDocument, keep_content: bool) -> model::BoardDocument {
+  let mut template = confidential::without_confidential(&trash::without_trash(doc));
+  template.note_encryption = None;
+  template.info = None;
+  for note in template.notes.iter_mut() {
+    note.created_at = None;
+    note.updated_at = None;
+    note.confidential = None;
+    note.sealed_text = None;
+    if keep_content {
+      continue;
+    }
+    note.text = String::new();
+    note.spans = None;
+    note.rich_attrs = None;
+    note.links = None;
+    note.images = None;
+    note.checklist = None;
+    note.tags = None;
+    note.properties = None;
+    note.starred = None;
+    if let Some(table) = note.table.as_mut() {
+      table.rows.clear();
+    }
+  }
+  if !keep_content {
+    for connection in template.connections.iter_mut() {
+      connection.label = None;
+    }
+  }
+
+  // Images no note shows any more
+  let shown: HashSet<&model::ID> = template.notes.iter().flat_map(|note| note.images.iter().flatten()).collect();
+  let images = template.images.take().map(|images| images.into_iter().filter(|image| shown.contains(&image.id)).collect::<Vec<_>>());
+  template.images = images.filter(|images| !images.is_empty());
+  groups::prune_groups(&mut template);
+  template
+}
+
+// A new board from a template: the template's board without what ties it to
+// the file it was read from
+pub fn instantiate(template: model::BoardDocument) -> model::BoardDocument {
+  model::BoardDocument { info: None, trash: None, ..template }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{html, model};
+use crate::outline::{self, OutlineImport, OutlineImportOptions, OutlineNode};
+
+// Browser bookmark exports (the Netscape bookmark file every browser writes).
+// The format is loose HTML: `<DT><H3>` names a folder whose contents follow
+// in a `<DL>`, `<DT><A HREF>` is a bookmark. Folders become parent notes
+// connected to their contents, or background shapes around them.
+
+const SHAPE_PADDING: f64 = 20.0;
+// Room for the shape's label above its contents
+const SHAPE_LABEL_HEIGHT: f64 = 32.0;
+const FOLDER_GAP: f64 = 40.0;
+
+#[derive(Debug, Clone, Default)]
+struct Folder {
+  title: String,
+  bookmarks: Vec<(String, String)>,
+  folders: Vec<Folder>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct BookmarksImportOptions {
+  // "connections" (default) or "stack" make folders parent notes, as in an
+  // outline import; "shapes" draws each folder as a background shape
+  pub mode: Option<String>,
+  pub origin: Option<model::Point>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct BookmarksImport {
+  #[serde(flatten)]
+  pub import: OutlineImport,
+  pub shapes: Vec<model::BackgroundShape>,
+}
+
+fn parse(html: &str) -> Folder {
+  // The root, then the chain of open folders
+  let mut open = vec![Folder::default()];
+  // A folder title waiting for its <DL>
+  let mut pending: Option<String> = None;
+  let mut rest = html;
+
+  while let Some(start) = rest.find('<') {
+    rest = &rest[start + 1..];
+    let end = rest.find('>').unwrap_or(rest.len());
+    let tag = &rest[..end];
+    rest = rest.get(end + 1..).unwrap_or("");
+    let name = tag.split(|c: char| c.is_whitespace()).next().unwrap_or("").to_ascii_lowercase();
+
+    match name.as_str() {
+      "h3" | "a" => {
+        let close = format!("</{}", name);
+        let text_end = rest.to_ascii_lowercase().find(&close).unwrap_or(rest.len());
+        let text = html::decode_entities(rest[..text_end].trim());
+        rest = &rest[text_end..];
+        if name == "h3" {
+          pending = Some(text);
+        } else if let Some(href) = html::attribute(tag, "href") {
+          // Bookmarklets aren't links anyone wants on a board
+          if !href.to_ascii_lowercase().starts_with("javascript:") {
+            let title = if text.is_empty() { href.clone() } else { text };
+            if let Some(folder) = open.last_mut() {
+              folder.bookmarks.push((title, href));
+            }
+          }
+        }
+      },
+      "dl" => {
+        // The root list has no title; any other untitled list is a folder too
+        if let Some(title) = pending.take() {
+          open.push(Folder { title, ..Default::default() });
+        } else if open.len() > 1 || !open[0].bookmarks.is_empty() || !open[0].folders.is_empty() {
+          open.push(Folder::default());
+        }
+      },
+      "/dl" if open.len() > 1 => {
+        if let Some(folder) = open.pop() {
+          if let Some(parent) = open.last_mut() {
+            parent.folders.push(folder);
+          }
+        }
+      },
+      _ => {},
+    }
+  }
+
+  while open.len() > 1 {
+    if let Some(folder) = open.pop() {
+      if let Some(parent) = open.last_mut() {
+        parent.folders.push(folder);
+      }
+    }
+  }
+  open.pop().unwrap_or_default()
+}
+
+fn outline_nodes(folder: &Folder) -> Vec<OutlineNode> {
+  let bookmarks = folder.bookmarks.iter().map(|(title, _)| OutlineNode { text: title.clone(), ..Default::default() });
+  let folders = folder.folders.iter().map(|sub| OutlineNode {
+    text: sub.title.clone(),
+    children: outline_nodes(sub),
+    ..Default::default()
+  });
+  bookmarks.chain(folders).collect()
+}
+
+// URLs in the order `outline_nodes` lists notes (depth-first), None for folders
+fn outline_links(folder: &Folder, links: &mut Vec<Option<String>>) {
+  links.extend(folder.bookmarks.iter().map(|(_, url)| Some(url.clone())));
+  for sub in &folder.folders {
+    links.push(None);
+    outline_links(sub, links);
+  }
+}
+
+// Lay a folder out at (x, y): its bookmarks in a column, then its subfolders
+// below. Returns the size used.
+fn place_in_shapes(folder: &Folder, x: f64, y: f64, result: &mut BookmarksImport) -> (f64, f64) {
+  let gap = outline::IMPORT_ROW_GAP;
+  let mut width: f64 = 0.0;
+  let mut height = 0.0;
+  for (title, url) in &folder.bookmarks {
+    let mut note = outline::imported_note(title, model::Rect {
+      x,
+      y: y + height,
+      w: outline::IMPORT_NOTE_WIDTH,
+      h: outline::IMPORT_NOTE_HEIGHT,
+    });
+    note.links = Some(vec![url.clone()]);
+    result.import.notes.push(note);
+    width = width.max(outline::IMPORT_NOTE_WIDTH);
+    height += outline::IMPORT_NOTE_HEIGHT + gap;
+  }
+
+  for sub in &folder.folders {
+    let top = y + height;
+    // Shapes go first so nested folders paint above their parents
+    let index = result.shapes.len();
+    result.shapes.push(model::BackgroundShape {
+      id: model::new_id("shape"),
+      kind: None,
+      frame: model::Rect { x, y: top, w: 0.0, h: 0.0 },
+      points: None,
+      radius: None,
+      magnetic: None,
+      style_id: None,
+      label: Some(sub.title.clone()),
+      z_index: None,
+      locked: None,
+      layer_id: None,
+    });
+    let (w, h) = place_in_shapes(sub, x + SHAPE_PADDING, top + SHAPE_LABEL_HEIGHT, result);
+    let frame = model::Rect {
+      x,
+      y: top,
+      w: w.max(outline::IMPORT_NOTE_WIDTH) + SHAPE_PADDING * 2.0,
+      h: h + SHAPE_LABEL_HEIGHT + SHAPE_PADDING,
+    };
+    width = width.max(frame.w);
+    height += frame.h + gap;
+    result.shapes[index].frame = frame;
+  }
+
+  (width, (height - gap).max(0.0))
+}
+
+pub fn import(html: &str, options: &BookmarksImportOptions) -> Result<BookmarksImport, String> {
+  if !html.to_ascii_uppercase().contains("NETSCAPE-BOOKMARK-FILE") && !html.to_ascii_lowercase().contains("<dt>") {
+    return Err("Not a bookmarks file: expected an HTML bookmarks export".to_string());
+  }
+  let root = parse(html);
+  if root.bookmarks.is_empty() && root.folders.is_empty() {
+    return Err("The bookmarks file has no bookmarks".to_string());
+  }
+  let origin = options.origin.clone().unwrap_or(model::Point { x: 0.0, y: 0.0 });
+
+  if options.mode.as_deref() == Some("shapes") {
+    let mut result = BookmarksImport::default();
+    // Top-level folders side by side, loose bookmarks in the first column
+    let mut x = origin.x;
+    if !root.bookmarks.is_empty() {
+      let loose = Folder { bookmarks: root.bookmarks.clone(), ..Default::default() };
+      x += place_in_shapes(&loose, x, origin.y, &mut result).0 + FOLDER_GAP;
+    }
+    for folder in &root.folders {
+      let top_level = Folder { folders: vec![folder.clone()], ..Default::default() };
+      x += place_in_shapes(&top_level, x, origin.y, &mut result).0 + FOLDER_GAP;
+    }
+    return Ok(result);
+  }
+
+  let outline_options = OutlineImportOptions { mode: options.mode.clone(), origin: Some(origin) };
+  let mut import = outline::to_notes(&outline_nodes(&root), &outline_options)?;
+  let mut links = Vec::new();
+  outline_links(&root, &mut links);
+  for (note, link) in import.notes.iter_mut().zip(links) {
+    note.links = link.map(|url| vec![url]);
+  }
+  Ok(BookmarksImport { import, shapes: Vec::new() })
+}
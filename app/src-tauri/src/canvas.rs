@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::{markdown, model, outline, relations, shapes};
+
+// JSON Canvas (https://jsoncanvas.org), the `.canvas` format of Obsidian and
+// other tools. Notes map to text nodes (link nodes for notes that are only a
+// web link), background shapes to group nodes and connections to edges, with
+// their label, color and arrow ends. Canvas text is Markdown, so imported
+// text nodes become Markdown notes; file nodes become notes naming the file.
+// Colors are kept as hex, except that the six preset colors map to their
+// numbers both ways. Styles other than the fill, stacks, images and
+// everything else the format has no place for are dropped on export.
+
+// Obsidian's red, orange, yellow, green, cyan and purple, presets "1" to "6"
+const PRESET_COLORS: [&str; 6] = ["#fb464c", "#e9973f", "#e0de71", "#44cf6e", "#53dfdd", "#a882ff"];
+const STYLE_FONT: &str = "system-ui";
+const STYLE_SIZE: f64 = 14.0;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Canvas {
+  #[serde(default)]
+  nodes: Vec<CanvasNode>,
+  #[serde(default)]
+  edges: Vec<CanvasEdge>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CanvasNode {
+  id: String,
+  #[serde(rename = "type")]
+  kind: String,
+  #[serde(serialize_with = "integer")]
+  x: f64,
+  #[serde(serialize_with = "integer")]
+  y: f64,
+  #[serde(serialize_with = "integer")]
+  width: f64,
+  #[serde(serialize_with = "integer")]
+  height: f64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  color: Option<String>,
+  // Text nodes
+  #[serde(skip_serializing_if = "Option::is_none")]
+  text: Option<String>,
+  // File nodes
+  #[serde(skip_serializing_if = "Option::is_none")]
+  file: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  subpath: Option<String>,
+  // Link nodes
+  #[serde(skip_serializing_if = "Option::is_none")]
+  url: Option<String>,
+  // Group nodes
+  #[serde(skip_serializing_if = "Option::is_none")]
+  label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CanvasEdge {
+  id: String,
+  #[serde(rename = "fromNode")]
+  from_node: String,
+  #[serde(rename = "fromSide", skip_serializing_if = "Option::is_none")]
+  from_side: Option<String>,
+  // "none" (default) or "arrow"
+  #[serde(rename = "fromEnd", skip_serializing_if = "Option::is_none")]
+  from_end: Option<String>,
+  #[serde(rename = "toNode")]
+  to_node: String,
+  #[serde(rename = "toSide", skip_serializing_if = "Option::is_none")]
+  to_side: Option<String>,
+  // "arrow" (default) or "none"
+  #[serde(rename = "toEnd", skip_serializing_if = "Option::is_none")]
+  to_end: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  color: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  label: Option<String>,
+}
+
+// The spec has positions and sizes in whole pixels
+fn integer<S: serde::Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+  serializer.serialize_i64(value.round() as i64)
+}
+
+fn is_hex_color(color: &str) -> bool {
+  color.strip_prefix('#')
+    .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn color_from_canvas(color: Option<&str>) -> Option<String> {
+  let color = color?.trim();
+  match color.parse::<usize>() {
+    Ok(preset @ 1..=6) => Some(PRESET_COLORS[preset - 1].to_string()),
+    _ if is_hex_color(color) => Some(color.to_lowercase()),
+    _ => None,
+  }
+}
+
+fn color_to_canvas(color: Option<&str>) -> Option<String> {
+  let color = color?.trim().to_lowercase();
+  if !is_hex_color(&color) {
+    return None;
+  }
+  match PRESET_COLORS.iter().position(|preset| *preset == color) {
+    Some(index) => Some((index + 1).to_string()),
+    None => Some(color),
+  }
+}
+
+fn is_web_link(link: &str) -> bool {
+  link.starts_with("http://") || link.starts_with("https://")
+}
+
+// The document as JSON Canvas: groups first, larger ones before the groups
+// inside them, as later nodes are drawn on top
+pub fn export(doc: &model::BoardDocument) -> Result<String, String> {
+  let mut canvas = Canvas::default();
+
+  let mut groups: Vec<&model::BackgroundShape> = doc.shapes.iter().filter(|s| shapes::kind(s) != "line").collect();
+  groups.sort_by(|a, b| (b.frame.w * b.frame.h).total_cmp(&(a.frame.w * a.frame.h)));
+  for shape in groups {
+    let style = shape.style_id.as_ref().and_then(|id| doc.note_styles.iter().find(|s| &s.id == id));
+    canvas.nodes.push(CanvasNode {
+      id: shape.id.clone(),
+      kind: "group".to_string(),
+      x: shape.frame.x,
+      y: shape.frame.y,
+      width: shape.frame.w,
+      height: shape.frame.h,
+      color: color_to_canvas(style.and_then(|s| s.fill.as_deref())),
+      text: None,
+      file: None,
+      subpath: None,
+      url: None,
+      label: shape.label.clone().filter(|l| !l.trim().is_empty()),
+    });
+  }
+
+  for note in &doc.notes {
+    let text = if note.is_markdown() { note.text.clone() } else { markdown::note_rich_text(note).0 };
+    let link = note.links.iter().flatten().find(|l| is_web_link(l)).filter(|_| text.trim().is_empty());
+    canvas.nodes.push(CanvasNode {
+      id: note.id.clone(),
+      kind: if link.is_some() { "link" } else { "text" }.to_string(),
+      x: note.frame.x,
+      y: note.frame.y,
+      width: note.frame.w,
+      height: note.frame.h,
+      color: color_to_canvas(doc.note_style(note).and_then(|s| s.fill.as_deref())),
+      text: link.is_none().then_some(text),
+      file: None,
+      subpath: None,
+      url: link.cloned(),
+      label: None,
+    });
+  }
+
+  for connection in &doc.connections {
+    let style = relations::effective_style(doc, connection);
+    let arrows = style.as_ref().and_then(|s| s.arrows.as_deref()).unwrap_or("none");
+    let end = |arrow: bool| if arrow { "arrow" } else { "none" }.to_string();
+    canvas.edges.push(CanvasEdge {
+      id: connection.id.clone(),
+      from_node: connection.src_note_id.clone(),
+      from_side: None,
+      from_end: Some(end(matches!(arrows, "src" | "both"))),
+      to_node: connection.dst_note_id.clone(),
+      to_side: None,
+      to_end: Some(end(matches!(arrows, "dst" | "both"))),
+      color: color_to_canvas(style.as_ref().and_then(|s| s.color.as_deref())),
+      label: connection.label.clone().filter(|l| !l.trim().is_empty()),
+    });
+  }
+
+  serde_json::to_string_pretty(&canvas).map_err(|e| format!("Failed to serialize canvas: {}", e))
+}
+
+// A style that only sets the fill, shared by everything of that color
+fn color_style(doc: &mut model::BoardDocument, color: &str) -> model::ID {
+  let id = format!("style_canvas_{}", color.trim_start_matches('#'));
+  if !doc.note_styles.iter().any(|s| s.id == id) {
+    doc.note_styles.push(model::NoteStyle {
+      id: id.clone(),
+      name: None,
+      text_style: model::TextStyle {
+        font: STYLE_FONT.to_string(),
+        size: STYLE_SIZE,
+        weight: None,
+        italic: None,
+        underline: None,
+        strike: None,
+        color: None,
+        align: None,
+      },
+      fill: Some(color.to_string()),
+      border: None,
+      corner_radius: None,
+      shadow: None,
+    });
+  }
+  id
+}
+
+pub fn import(json: &str) -> Result<model::BoardDocument, String> {
+  let canvas: Canvas = serde_json::from_str(json).map_err(|e| format!("Invalid JSON Canvas file: {}", e))?;
+  let mut doc = model::BoardDocument::empty();
+  let mut ids: HashSet<String> = HashSet::new();
+
+  for node in &canvas.nodes {
+    if ![node.x, node.y, node.width, node.height].iter().all(|v| v.is_finite()) {
+      continue;
+    }
+    let frame = model::Rect { x: node.x, y: node.y, w: node.width.max(1.0), h: node.height.max(1.0) };
+    let style_id = color_from_canvas(node.color.as_deref()).map(|color| color_style(&mut doc, &color));
+    let id = match node.id.as_str() {
+      id if !id.is_empty() && !ids.contains(id) => id.to_string(),
+      _ => model::new_id(if node.kind == "group" { "shape" } else { "note" }),
+    };
+    ids.insert(id.clone());
+
+    match node.kind.as_str() {
+      "group" => doc.shapes.push(model::BackgroundShape {
+        id,
+        kind: None,
+        frame,
+        points: None,
+        radius: None,
+        magnetic: None,
+        style_id,
+        label: node.label.clone(),
+        z_index: None,
+        locked: None,
+        layer_id: None,
+      }),
+      "link" => {
+        let url = node.url.clone().unwrap_or_default();
+        doc.notes.push(model::Note {
+          id,
+          style_id,
+          links: (!url.is_empty()).then(|| vec![url.clone()]),
+          ..outline::imported_note(&url, frame)
+        });
+      },
+      "file" => {
+        let file = node.file.clone().unwrap_or_default();
+        let text = format!("{}{}", file, node.subpath.as_deref().unwrap_or(""));
+        doc.notes.push(model::Note { id, style_id, ..outline::imported_note(&text, frame) });
+      },
+      // Text nodes, and kinds from later versions of the format as far as
+      // they have text
+      _ => doc.notes.push(model::Note {
+        id,
+        style_id,
+        content_format: Some("markdown".to_string()),
+        ..outline::imported_note(node.text.as_deref().unwrap_or(""), frame)
+      }),
+    }
+  }
+
+  let note_ids: HashSet<&str> = doc.notes.iter().map(|n| n.id.as_str()).collect();
+  let mut connections = Vec::new();
+  for edge in &canvas.edges {
+    // Edges to groups have no counterpart
+    if !note_ids.contains(edge.from_node.as_str()) || !note_ids.contains(edge.to_node.as_str()) {
+      continue;
+    }
+    let from_arrow = edge.from_end.as_deref() == Some("arrow");
+    let to_arrow = edge.to_end.as_deref() != Some("none");
+    let arrows = match (from_arrow, to_arrow) {
+      (true, true) => "both",
+      (true, false) => "src",
+      (false, true) => "dst",
+      (false, false) => "none",
+    };
+    let id = if edge.id.is_empty() || ids.contains(&edge.id) { model::new_id("conn") } else { edge.id.clone() };
+    ids.insert(id.clone());
+    connections.push(model::Connection {
+      id,
+      src_note_id: edge.from_node.clone(),
+      dst_note_id: edge.to_node.clone(),
+      style: Some(model::ConnectionStyle {
+        kind: Some("solid".to_string()),
+        arrows: Some(arrows.to_string()),
+        color: color_from_canvas(edge.color.as_deref()),
+        width: None,
+        curve_type: None,
+      }),
+      label: edge.label.clone().filter(|l| !l.trim().is_empty()),
+      bend_points: None,
+      control_points: None,
+      relation_type: None,
+    });
+  }
+  doc.connections = connections;
+  Ok(doc)
+}
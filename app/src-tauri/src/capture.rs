@@ -0,0 +1,102 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::{checklist, markdown, model, raster};
+
+// Label of the small always-on-top window opened by the capture shortcut
+pub const QUICK_CAPTURE_WINDOW: &str = "quick-capture";
+pub const DEFAULT_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+const INBOX_FILE_NAME: &str = "Inbox.fim";
+
+const NOTE_WIDTH: f64 = 200.0;
+const NOTE_HEIGHT: f64 = 80.0;
+const NOTE_GAP: f64 = 20.0;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CapturedNote {
+  pub path: String,
+  pub note_id: model::ID,
+  // The board is open in a window, which was handed the note instead of the
+  // file being written
+  pub delivered: bool,
+}
+
+pub fn default_inbox_path() -> Option<PathBuf> {
+  dirs::document_dir().map(|dir| dir.join(INBOX_FILE_NAME))
+}
+
+fn overlaps(a: &model::Rect, b: &model::Rect) -> bool {
+  a.x < b.x + b.w + NOTE_GAP && b.x < a.x + a.w + NOTE_GAP && a.y < b.y + b.h + NOTE_GAP && b.y < a.y + a.h + NOTE_GAP
+}
+
+// Where a new note fits without touching any note or shape: below the note
+// added last (so captured ideas form a column in the order they were written
+// down) or beside it, else below or beside the other notes, newest first,
+// and as a last resort below everything
+fn free_position(doc: &model::BoardDocument) -> model::Point {
+  let taken: Vec<&model::Rect> = doc.notes.iter().map(|n| &n.frame).chain(doc.shapes.iter().map(|s| &s.frame)).collect();
+  let fits = |x: f64, y: f64| {
+    let frame = model::Rect { x, y, w: NOTE_WIDTH, h: NOTE_HEIGHT };
+    !taken.iter().any(|other| overlaps(&frame, other))
+  };
+
+  let mut notes: Vec<(usize, &model::Note)> = doc.notes.iter().enumerate().collect();
+  notes.sort_by_key(|(index, note)| std::cmp::Reverse((note.created_at, *index)));
+  for (_, note) in notes {
+    let f = &note.frame;
+    let candidates = [(f.x, f.y + f.h + NOTE_GAP), (f.x + f.w + NOTE_GAP, f.y)];
+    if let Some(&(x, y)) = candidates.iter().find(|(x, y)| fits(*x, *y)) {
+      return model::Point { x, y };
+    }
+  }
+  match raster::content_bounds(doc) {
+    Some(bounds) => model::Point { x: bounds.x, y: bounds.y + bounds.h + NOTE_GAP },
+    None => model::Point { x: NOTE_GAP, y: NOTE_GAP },
+  }
+}
+
+// Append a note at a free spot near the notes already on the board
+pub fn append_note(doc: &mut model::BoardDocument, text: &str) -> model::ID {
+  let position = free_position(doc);
+
+  // `- [ ] item` lines become checklist items rather than note text
+  let (text, checklist) = checklist::split_checklist(text);
+
+  let content_format = markdown::looks_like_markdown(&text).then(|| "markdown".to_string());
+
+  let id = model::new_id("note");
+  doc.notes.push(model::Note {
+    id: id.clone(),
+    text,
+    content_format,
+    spans: None,
+    rich_attrs: None,
+    frame: model::Rect {
+      x: position.x,
+      y: position.y,
+      w: NOTE_WIDTH,
+      h: NOTE_HEIGHT,
+    },
+    style_id: None,
+    faded: None,
+    stack_id: None,
+    links: None,
+    images: None,
+    connections: None,
+    z_index: None,
+    locked: None,
+    layer_id: None,
+    checklist: if checklist.is_empty() { None } else { Some(checklist) },
+    labels: None,
+    tags: None,
+    table: None,
+    icon: None,
+    properties: None,
+    starred: None,
+    created_at: Some(chrono::Utc::now()),
+    updated_at: None,
+    confidential: None,
+    sealed_text: None,
+  });
+  id
+}
@@ -0,0 +1,106 @@
+use serde::Serialize;
+
+use crate::{model, shapes};
+
+// Recognise a Markdown task item (`- [ ] text`, `* [x] text`, ...) and return
+// whether it is done along with its text.
+pub fn parse_checklist_line(line: &str) -> Option<(bool, &str)> {
+  let rest = line.trim_start()
+    .strip_prefix(['-', '*', '+'])?
+    .strip_prefix(' ')?;
+  let (done, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+    (false, text)
+  } else if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+    (true, text)
+  } else {
+    return None;
+  };
+  if !text.is_empty() && !text.starts_with(char::is_whitespace) {
+    return None;
+  }
+  Some((done, text.trim()))
+}
+
+// Split imported note text into its prose and its task items.
+pub fn split_checklist(text: &str) -> (String, Vec<model::ChecklistItem>) {
+  let mut body = Vec::new();
+  let mut items = Vec::new();
+  for line in text.lines() {
+    match parse_checklist_line(line) {
+      Some((done, item)) if !item.is_empty() => items.push(model::ChecklistItem {
+        text: item.to_string(),
+        done,
+        order: items.len() as u32,
+      }),
+      Some(_) => {},
+      None => body.push(line),
+    }
+  }
+  (body.join("\n").trim().to_string(), items)
+}
+
+// Checklist items of a note in display order
+pub fn ordered_items(note: &model::Note) -> Vec<&model::ChecklistItem> {
+  let mut items: Vec<&model::ChecklistItem> = note.checklist.iter().flatten().collect();
+  items.sort_by_key(|item| item.order);
+  items
+}
+
+pub fn checkbox(done: bool) -> &'static str {
+  if done { "[x]" } else { "[ ]" }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TaskCounts {
+  pub open: usize,
+  pub done: usize,
+}
+
+// Open/done counts for the notes inside one shape or stack
+#[derive(Serialize, Debug, Clone)]
+pub struct ContainerTasks {
+  pub id: model::ID,
+  pub label: Option<String>,
+  pub open: usize,
+  pub done: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskSummary {
+  pub total: TaskCounts,
+  pub shapes: Vec<ContainerTasks>,
+  pub stacks: Vec<ContainerTasks>,
+}
+
+fn count_tasks<'a>(notes: impl Iterator<Item = &'a model::Note>) -> TaskCounts {
+  let mut counts = TaskCounts::default();
+  for item in notes.flat_map(|n| n.checklist.iter().flatten()) {
+    if item.done {
+      counts.done += 1;
+    } else {
+      counts.open += 1;
+    }
+  }
+  counts
+}
+
+// A note counts towards a shape when its center lies inside the shape.
+pub fn task_summary(doc: &model::BoardDocument) -> TaskSummary {
+  let shape_tasks = doc.shapes.iter()
+    .map(|shape| {
+      let counts = count_tasks(doc.notes.iter().filter(|n| shapes::contains_note(shape, n)));
+      ContainerTasks { id: shape.id.clone(), label: shape.label.clone(), open: counts.open, done: counts.done }
+    })
+    .filter(|c| c.open + c.done > 0)
+    .collect();
+
+  let stack_tasks = doc.stacks.iter()
+    .map(|stack| {
+      let counts = count_tasks(doc.notes.iter().filter(|n| stack.note_ids.contains(&n.id)));
+      ContainerTasks { id: stack.id.clone(), label: None, open: counts.open, done: counts.done }
+    })
+    .filter(|c| c.open + c.done > 0)
+    .collect();
+
+  TaskSummary { total: count_tasks(doc.notes.iter()), shapes: shape_tasks, stacks: stack_tasks }
+}
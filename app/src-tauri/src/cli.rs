@@ -0,0 +1,326 @@
+use std::path::{Path, PathBuf};
+
+use crate::{canvas, confidential, layers, locale, pdf, progress, raster, render, settings, static_html, sync_write, TextExportOptions};
+
+// Headless conversions for scripts and cron jobs, run instead of the app when
+// the first argument is a command:
+//
+//   fim export <board or folder>... --format <format> [--output <path>]
+//
+// Boards are read and written with the same code as in the app, but no window
+// is opened and nothing is recorded in recent files, usage or the audit log.
+// Progress goes to stdout, errors to stderr; the exit code is 0 when every
+// board was exported, 1 when some failed and 2 for invalid arguments.
+
+const USAGE: &str = "\
+Usage: fim export <board or folder>... --format <format> [options]
+
+Converts .fim, .json and .canvas boards, or every board in a folder.
+
+Formats: txt, md, rtf, opml, dot, mermaid, csv, tsv, canvas, json, fim, svg, png, pdf,
+         html
+
+Options:
+  -f, --format <format>     Format to write
+  -o, --output <path>       Output file for a single board, or output folder;
+                            by default each export goes next to its board
+      --ordering <order>    Note order in text formats: spatial (default),
+                            connections or hierarchical
+      --layers <ids>        Comma-separated layer IDs to include in exports
+                            other than json and fim; by default the visible
+                            ones
+      --locale <tag>        Language of headers and dates, e.g. de
+      --timezone <zone>     local, UTC or an offset such as +02:00
+      --scale <factor>      Pixels per board unit in PNG exports (default 1),
+                            or the print scale of tiled PDFs
+      --page-size <size>    PDF page: a3, a4 (default), a5, letter or legal
+      --orientation <o>     PDF orientation: auto (default), portrait or
+                            landscape
+      --tile                Print PDFs at true scale across several pages
+                            with crop marks instead of fitting one page
+      --dpi <dpi>           Resolution of PDF pages (default 150)
+  -h, --help                Show this help";
+
+const FORMATS: &[&str] = &["txt", "md", "rtf", "opml", "dot", "mermaid", "csv", "tsv", "canvas", "json", "fim", "svg", "png", "pdf", "html"];
+const BOARD_EXTENSIONS: &[&str] = &["fim", "json", "canvas", "scap"];
+
+struct ExportCommand {
+  inputs: Vec<PathBuf>,
+  output: Option<PathBuf>,
+  ordering: String,
+  locale: Option<String>,
+  timezone: Option<String>,
+  board: BoardExport,
+}
+
+// What to write for each board; `batch_export` in the app exports with it too
+pub struct BoardExport {
+  pub format: String,
+  pub layers: Option<Vec<String>>,
+  pub scale: Option<f64>,
+  pub page_size: String,
+  pub orientation: String,
+  pub pdf: pdf::PdfOptions,
+}
+
+impl BoardExport {
+  pub fn new(format: &str) -> BoardExport {
+    BoardExport {
+      format: format.to_string(),
+      layers: None,
+      scale: None,
+      page_size: "a4".to_string(),
+      orientation: "auto".to_string(),
+      pdf: pdf::PdfOptions::default(),
+    }
+  }
+}
+
+// Release builds on Windows have no console of their own; write to the one
+// the command was run from
+#[cfg(windows)]
+fn attach_console() {
+  extern "system" {
+    fn AttachConsole(process_id: u32) -> i32;
+  }
+  const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+  // SAFETY: plain Win32 call without pointers; failing leaves output unseen
+  unsafe {
+    AttachConsole(ATTACH_PARENT_PROCESS);
+  }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+// Run the command in `args` (without the program name); None when they aren't
+// a command, and the app should start as usual
+pub fn run(args: &[String]) -> Option<i32> {
+  let command = args.first()?;
+  if command != "export" && command != "--help" && command != "-h" {
+    return None;
+  }
+  attach_console();
+  if command != "export" {
+    println!("{}", USAGE);
+    return Some(0);
+  }
+  Some(match parse_export(&args[1..]) {
+    Ok(Some(export_command)) => export(&export_command),
+    Ok(None) => {
+      println!("{}", USAGE);
+      0
+    },
+    Err(e) => {
+      eprintln!("{}\n\n{}", e, USAGE);
+      2
+    },
+  })
+}
+
+// None when help was asked for
+fn parse_export(args: &[String]) -> Result<Option<ExportCommand>, String> {
+  let mut command = ExportCommand {
+    inputs: Vec::new(),
+    output: None,
+    ordering: "spatial".to_string(),
+    locale: None,
+    timezone: None,
+    board: BoardExport::new(""),
+  };
+
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    if !arg.starts_with('-') || arg == "-" {
+      command.inputs.push(PathBuf::from(arg));
+      continue;
+    }
+    // `--name value` or `--name=value`
+    let (name, inline_value) = match arg.split_once('=') {
+      Some((name, value)) => (name, Some(value.to_string())),
+      None => (arg.as_str(), None),
+    };
+    match name {
+      "-h" | "--help" => return Ok(None),
+      "--tile" => {
+        command.board.pdf.mode = Some("tile".to_string());
+        continue;
+      },
+      _ => {},
+    }
+    let mut value = || inline_value.clone()
+      .or_else(|| args.next().cloned())
+      .ok_or_else(|| format!("Missing value for {}", name));
+    match name {
+      "-f" | "--format" => command.board.format = value()?.to_lowercase(),
+      "-o" | "--output" => command.output = Some(PathBuf::from(value()?)),
+      "--ordering" => command.ordering = value()?,
+      "--layers" => command.board.layers = Some(value()?.split(',').map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect()),
+      "--locale" => command.locale = Some(value()?),
+      "--timezone" => command.timezone = Some(value()?),
+      "--scale" => command.board.scale = Some(number(&value()?, "scale")?),
+      "--page-size" => command.board.page_size = value()?.to_lowercase(),
+      "--orientation" => command.board.orientation = value()?.to_lowercase(),
+      "--dpi" => command.board.pdf.dpi = Some(number(&value()?, "DPI")?),
+      other => return Err(format!("Unknown option '{}'", other)),
+    }
+  }
+
+  if command.inputs.is_empty() {
+    return Err("No boards given".to_string());
+  }
+  let board = &mut command.board;
+  if board.format.is_empty() {
+    return Err("No format given; use --format".to_string());
+  }
+  board.pdf.scale = board.scale.filter(|_| board.format == "pdf");
+  if !FORMATS.contains(&board.format.as_str()) {
+    return Err(format!("Unsupported format '{}'. Must be one of: {}", board.format, FORMATS.join(", ")));
+  }
+  if !matches!(command.ordering.as_str(), "spatial" | "connections" | "hierarchical") {
+    return Err(format!("Unsupported ordering '{}'. Must be one of: spatial, connections, hierarchical", command.ordering));
+  }
+  Ok(Some(command))
+}
+
+fn number(value: &str, name: &str) -> Result<f64, String> {
+  value.parse().map_err(|_| format!("Invalid {} '{}'", name, value))
+}
+
+fn is_board(path: &Path) -> bool {
+  path.extension()
+    .and_then(|ext| ext.to_str())
+    .is_some_and(|ext| BOARD_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+// The boards named, with folders replaced by the boards directly inside them
+fn collect_boards(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+  let mut boards = Vec::new();
+  for input in inputs {
+    if input.is_dir() {
+      let mut found: Vec<PathBuf> = std::fs::read_dir(input)
+        .map_err(|e| format!("Failed to read folder '{}': {}", input.display(), e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_board(path))
+        .collect();
+      found.sort();
+      boards.extend(found);
+    } else if input.is_file() {
+      boards.push(input.clone());
+    } else {
+      return Err(format!("'{}' does not exist", input.display()));
+    }
+  }
+  Ok(boards)
+}
+
+pub fn extension(format: &str) -> &str {
+  match format {
+    "mermaid" => "mmd",
+    other => other,
+  }
+}
+
+fn export(command: &ExportCommand) -> i32 {
+  let boards = match collect_boards(&command.inputs) {
+    Ok(boards) if boards.is_empty() => {
+      eprintln!("No boards found");
+      return 1;
+    },
+    Ok(boards) => boards,
+    Err(e) => {
+      eprintln!("{}", e);
+      return 2;
+    },
+  };
+
+  let settings = settings::load_settings();
+  let locale = locale::ExportLocale::new(
+    command.locale.as_deref().unwrap_or(&settings.export_locale),
+    command.timezone.as_deref().unwrap_or(&settings.export_timezone),
+  );
+  let options = match locale {
+    Ok(locale) => TextExportOptions {
+      ordering: command.ordering.clone(),
+      scrub_metadata: false,
+      include_properties: false,
+      include_stats: false,
+      locale,
+    },
+    Err(e) => {
+      eprintln!("{}", e);
+      return 2;
+    },
+  };
+
+  // A single board may go to a named file; otherwise `--output` is a folder
+  let output_file = command.output.as_ref()
+    .filter(|output| boards.len() == 1 && !output.is_dir() && output.extension().is_some());
+  if let Some(dir) = command.output.as_ref().filter(|_| output_file.is_none()) {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+      eprintln!("Failed to create output folder '{}': {}", dir.display(), e);
+      return 1;
+    }
+  }
+
+  let mut failed = 0;
+  for board in &boards {
+    let target = match (output_file, &command.output) {
+      (Some(file), _) => file.clone(),
+      (None, dir) => {
+        let dir = dir.clone().unwrap_or_else(|| board.parent().map(Path::to_path_buf).unwrap_or_default());
+        let stem = board.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+        dir.join(format!("{}.{}", stem, extension(&command.board.format)))
+      },
+    };
+    match export_board(board, &target, &command.board, &options) {
+      Ok(()) => println!("{} -> {}", board.display(), target.display()),
+      Err(e) => {
+        eprintln!("{}: {}", board.display(), e);
+        failed += 1;
+      },
+    }
+  }
+  if failed > 0 {
+    eprintln!("{} of {} boards failed", failed, boards.len());
+    1
+  } else {
+    0
+  }
+}
+
+pub fn export_board(board: &Path, target: &Path, export: &BoardExport, options: &TextExportOptions) -> Result<(), String> {
+  let same_file = std::fs::canonicalize(board).ok()
+    .is_some_and(|board| std::fs::canonicalize(target).is_ok_and(|target| target == board));
+  if same_file {
+    return Err(format!("Refusing to overwrite the board itself with '{}'", target.display()));
+  }
+
+  let none = progress::Progress::none();
+  let doc = crate::load_document(board, &none)?;
+  match export.format.as_str() {
+    // Conversions keep the whole board, like saving it under another name
+    "fim" | "json" => {
+      let target = target.with_extension(&export.format);
+      crate::write_document(&doc, &target, &[], sync_write::WriteOptions::default(), &none)
+    },
+    format => {
+      let included_layers = export.layers.clone().unwrap_or_else(|| layers::visible_layers(&doc));
+      let doc = layers::filter_layers(&confidential::without_locked(&doc), &included_layers);
+      let content = match format {
+        "canvas" => canvas::export(&doc)?.into_bytes(),
+        "svg" | "png" => {
+          let plan = raster::plan(&doc, &raster::RasterOptions { scale: export.scale, ..Default::default() })?;
+          if format == "svg" { render::to_svg(&doc, &plan.region).into_bytes() } else { render::to_png(&doc, &plan)? }
+        },
+        "html" => static_html::to_html(&doc, None).into_bytes(),
+        "pdf" => pdf::generate(&doc, &export.page_size, &export.orientation, &export.pdf, &none)?,
+        _ => crate::generate_text_export(&doc, format, options)?.into_bytes(),
+      };
+      sync_write::write_with(target, sync_write::WriteOptions::default(), |path| {
+        std::fs::write(path, &content).map_err(|e| format!("Failed to write '{}': {}", target.display(), e))
+      })
+    },
+  }
+}
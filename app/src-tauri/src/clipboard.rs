@@ -0,0 +1,179 @@
+use serde::Serialize;
+
+use crate::markdown;
+use crate::model;
+use crate::outline::{self, OutlineImport, OutlineImportOptions, OutlineNode};
+
+// What the clipboard currently holds, classified for insertion on the board
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ClipboardContent {
+  Image { image: model::EmbeddedImage },
+  Url { url: String },
+  Outline { items: Vec<OutlineNode> },
+  Text {
+    text: String,
+    // "markdown" when the text uses Markdown syntax, else "plain"
+    #[serde(rename = "contentFormat")]
+    content_format: String,
+  },
+  Empty,
+}
+
+pub fn classify_text(text: &str) -> ClipboardContent {
+  let trimmed = text.trim();
+  if trimmed.is_empty() {
+    return ClipboardContent::Empty;
+  }
+
+  if !trimmed.contains(char::is_whitespace) {
+    if let Ok(url) = tauri::Url::parse(trimmed) {
+      if matches!(url.scheme(), "http" | "https") {
+        return ClipboardContent::Url { url: url.to_string() };
+      }
+    }
+  }
+
+  if outline::looks_like_outline(text) {
+    return ClipboardContent::Outline { items: outline::parse_indented_text(text) };
+  }
+
+  let content_format = if markdown::looks_like_markdown(trimmed) { "markdown" } else { "plain" };
+  ClipboardContent::Text { text: trimmed.to_string(), content_format: content_format.to_string() }
+}
+
+// Clipboard flavors to write for a set of notes
+pub struct ClipboardText {
+  pub plain: String,
+  pub html: Option<String>,
+}
+
+// Render notes for the clipboard. `ordered` holds the selected notes in
+// reading order; connections between them become outline nesting.
+pub fn notes_to_clipboard_text(
+  doc: &model::BoardDocument,
+  ordered: &[model::Note],
+  format: &str,
+) -> Result<ClipboardText, String> {
+  match format {
+    "plain" => Ok(ClipboardText {
+      plain: ordered.iter().map(|n| n.text.as_str()).collect::<Vec<_>>().join("\n\n"),
+      html: None,
+    }),
+    "markdown" => Ok(ClipboardText {
+      plain: outline::to_markdown(&outline::build_outline(doc, ordered)),
+      html: None,
+    }),
+    // The system clipboard libraries have no portable RTF flavor; word
+    // processors read the HTML flavor for rich paste instead.
+    "rtf" => {
+      let items = outline::build_outline(doc, ordered);
+      Ok(ClipboardText {
+        plain: outline::to_markdown(&items),
+        html: Some(outline::to_html(&items)),
+      })
+    },
+    _ => Err(format!("Unsupported clipboard format '{}'. Must be one of: plain, rtf, markdown", format)),
+  }
+}
+
+// Pasted text split into notes, with the structure that was detected in it
+#[derive(Serialize, Debug, Clone)]
+pub struct ClipboardOutline {
+  // "numbered", "bullets", "indented", "tsv" or "paragraphs"
+  pub format: String,
+  #[serde(flatten)]
+  pub import: OutlineImport,
+}
+
+const BULLETS: &[&str] = &["- ", "* ", "+ ", "• "];
+
+// `1.`, `1)`, `2.3` or `2.3.1.`: the depth implied by the number and the item text
+fn numbered_item(line: &str) -> Option<(usize, &str)> {
+  let line = line.trim_start();
+  let number_end = line.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+  let (number, rest) = line.split_at(number_end);
+  let rest = rest.strip_prefix(')').unwrap_or(rest);
+  let parts: Vec<&str> = number.trim_end_matches('.').split('.').collect();
+  let well_formed = !number.is_empty() && parts.iter().all(|p| !p.is_empty());
+  // A bare `12` followed by text is just text; `12.` and `1.2` are numbering
+  let marked = number.contains('.') || line[number_end..].starts_with(')');
+  if !well_formed || !marked || !rest.starts_with(char::is_whitespace) {
+    return None;
+  }
+  Some((parts.len() - 1, rest.trim_start()))
+}
+
+// Tab-separated rows with the same number of cells, as copied from a spreadsheet
+fn tsv_rows(lines: &[&str]) -> Option<Vec<Vec<String>>> {
+  let rows: Vec<Vec<String>> = lines.iter()
+    .map(|line| line.split('\t').map(|cell| cell.trim().to_string()).collect())
+    .collect();
+  let columns = rows.first()?.len();
+  // Leading tabs are indentation, not empty cells
+  let indented = lines.iter().any(|line| line.starts_with('\t'));
+  (columns > 1 && !indented && rows.iter().all(|row| row.len() == columns)).then_some(rows)
+}
+
+// One note per cell, laid out like the sheet; empty cells are skipped
+fn tsv_notes(rows: &[Vec<String>], origin: &model::Point) -> OutlineImport {
+  let mut import = OutlineImport::default();
+  for (row_index, row) in rows.iter().enumerate() {
+    for (column_index, cell) in row.iter().enumerate().filter(|(_, cell)| !cell.is_empty()) {
+      import.notes.push(outline::imported_note(cell, model::Rect {
+        x: origin.x + column_index as f64 * outline::IMPORT_COLUMN_WIDTH,
+        y: origin.y + row_index as f64 * (outline::IMPORT_NOTE_HEIGHT + outline::IMPORT_ROW_GAP),
+        w: outline::IMPORT_NOTE_WIDTH,
+        h: outline::IMPORT_NOTE_HEIGHT,
+      }));
+    }
+  }
+  import
+}
+
+// Split pasted text into ready-to-insert notes instead of one large note.
+// Numbered lists (including `1.2`-style nesting), bullets and indentation
+// become a tree (or stack, per `options.mode`); spreadsheet cells become a
+// grid; anything else becomes one note per paragraph.
+pub fn parse_outline(text: &str, options: &OutlineImportOptions) -> Result<ClipboardOutline, String> {
+  let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+  if lines.is_empty() {
+    return Err("The clipboard has no text".to_string());
+  }
+
+  if lines.len() > 1 {
+    if let Some(rows) = tsv_rows(&lines) {
+      let origin = options.origin.clone().unwrap_or(model::Point { x: 0.0, y: 0.0 });
+      return Ok(ClipboardOutline { format: "tsv".to_string(), import: tsv_notes(&rows, &origin) });
+    }
+  }
+
+  let numbered = lines.iter().filter(|l| numbered_item(l).is_some()).count();
+  let bulleted = lines.iter().filter(|l| BULLETS.iter().any(|b| l.trim_start().starts_with(b))).count();
+  let (format, nodes) = if numbered * 2 >= lines.len() && numbered >= bulleted {
+    // Rewrite dotted numbering as indentation, keeping any existing indentation
+    let indented: Vec<String> = text.lines()
+      .map(|line| match numbered_item(line) {
+        Some((depth, item)) => {
+          let indent = &line[..line.len() - line.trim_start().len()];
+          format!("{}{}{}", indent, "\t".repeat(depth), item)
+        },
+        None => line.to_string(),
+      })
+      .collect();
+    ("numbered", outline::parse_indented_text(&indented.join("\n")))
+  } else if bulleted * 2 >= lines.len() {
+    ("bullets", outline::parse_indented_text(text))
+  } else if outline::looks_like_outline(text) {
+    ("indented", outline::parse_indented_text(text))
+  } else {
+    let paragraphs = text.replace("\r\n", "\n").split("\n\n")
+      .map(str::trim)
+      .filter(|p| !p.is_empty())
+      .map(|p| OutlineNode { text: p.to_string(), ..Default::default() })
+      .collect::<Vec<_>>();
+    ("paragraphs", paragraphs)
+  };
+
+  Ok(ClipboardOutline { format: format.to_string(), import: outline::to_notes(&nodes, options)? })
+}
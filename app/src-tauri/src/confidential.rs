@@ -0,0 +1,292 @@
+use argon2::Argon2;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{groups, model, settings};
+
+// Confidential notes: individual notes whose content (text, spans, checklist
+// and table) is encrypted at rest while the rest of the board stays readable.
+// The key is derived from a passphrase set per document (Argon2id) and can be
+// remembered in the OS keychain; `noteEncryption` in board.json holds only the
+// salt and a verifier to check passphrases against.
+//
+// A locked note has its content in `sealedText` (XChaCha20-Poly1305, bound to
+// the note id) and an empty `text`; unlocking decrypts it and clears
+// `sealedText`. Every write seals unlocked confidential notes again, so
+// board.json never holds their plaintext. Locked notes are left out of
+// plaintext exports, and confidential notes never go into the search sidecar.
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const VERIFIER_ID: &str = "noteEncryption";
+const VERIFIER_TEXT: &str = "fim";
+
+type Key = [u8; KEY_LEN];
+
+// What gets sealed
+#[derive(Serialize, Deserialize, Default)]
+struct SealedContent {
+  text: String,
+  spans: Option<Vec<model::TextSpan>>,
+  checklist: Option<Vec<model::ChecklistItem>>,
+  table: Option<model::NoteTable>,
+}
+
+// Keys unlocked this session, by key id
+fn unlocked() -> &'static Mutex<HashMap<String, Key>> {
+  static KEYS: OnceLock<Mutex<HashMap<String, Key>>> = OnceLock::new();
+  KEYS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn cached_key(key_id: &str) -> Option<Key> {
+  unlocked().lock().ok().and_then(|keys| keys.get(key_id).copied())
+}
+
+fn cache_key(key_id: &str, key: Key) {
+  if let Ok(mut keys) = unlocked().lock() {
+    keys.insert(key_id.to_string(), key);
+  }
+}
+
+fn forget_key(key_id: &str) {
+  if let Ok(mut keys) = unlocked().lock() {
+    keys.remove(key_id);
+  }
+}
+
+fn keychain_entry(key_id: &str) -> Result<keyring::Entry, String> {
+  keyring::Entry::new(settings::APP_IDENTIFIER, &format!("notes-{}", key_id))
+    .map_err(|e| format!("Failed to access the keychain: {}", e))
+}
+
+fn remember_key(key_id: &str, key: &Key) -> Result<(), String> {
+  keychain_entry(key_id)?
+    .set_password(&base64::engine::general_purpose::STANDARD.encode(key))
+    .map_err(|e| format!("Failed to store the note key in the keychain: {}", e))
+}
+
+fn recall_key(key_id: &str) -> Option<Key> {
+  let encoded = keychain_entry(key_id).ok()?.get_password().ok()?;
+  base64::engine::general_purpose::STANDARD.decode(encoded).ok()?.try_into().ok()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+  let mut key = [0u8; KEY_LEN];
+  Argon2::default()
+    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+    .map_err(|e| format!("Failed to derive the note key: {}", e))?;
+  Ok(key)
+}
+
+fn encrypt(key: &Key, note_id: &str, plaintext: &[u8]) -> Result<String, String> {
+  let cipher = XChaCha20Poly1305::new(key.into());
+  let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+  let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext, aad: note_id.as_bytes() })
+    .map_err(|_| "Failed to encrypt note".to_string())?;
+  let mut sealed = nonce.to_vec();
+  sealed.extend(ciphertext);
+  Ok(base64::engine::general_purpose::STANDARD.encode(sealed))
+}
+
+fn decrypt(key: &Key, note_id: &str, sealed: &str) -> Result<Vec<u8>, String> {
+  let data = base64::engine::general_purpose::STANDARD.decode(sealed)
+    .map_err(|e| format!("Invalid encrypted note '{}': {}", note_id, e))?;
+  if data.len() < NONCE_LEN {
+    return Err(format!("Invalid encrypted note '{}'", note_id));
+  }
+  let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+  XChaCha20Poly1305::new(key.into())
+    .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: note_id.as_bytes() })
+    .map_err(|_| format!("Failed to decrypt note '{}': wrong key or damaged data", note_id))
+}
+
+pub fn is_confidential(note: &model::Note) -> bool {
+  note.confidential == Some(true)
+}
+
+pub fn is_locked(note: &model::Note) -> bool {
+  note.sealed_text.is_some()
+}
+
+// Every note of the document, including trashed ones
+fn all_notes(doc: &mut model::BoardDocument) -> impl Iterator<Item = &mut model::Note> {
+  let trashed = doc.trash.iter_mut().flat_map(|t| t.notes.iter_mut().map(|t| &mut t.note));
+  doc.notes.iter_mut().chain(trashed)
+}
+
+fn seal_note(key: &Key, note: &mut model::Note) -> Result<(), String> {
+  let content = SealedContent {
+    text: std::mem::take(&mut note.text),
+    spans: note.spans.take(),
+    checklist: note.checklist.take(),
+    table: note.table.take(),
+  };
+  let json = serde_json::to_vec(&content).map_err(|e| format!("Failed to serialize note '{}': {}", note.id, e))?;
+  note.sealed_text = Some(encrypt(key, &note.id, &json)?);
+  Ok(())
+}
+
+fn open_note(key: &Key, note: &mut model::Note) -> Result<(), String> {
+  let Some(sealed) = note.sealed_text.as_deref() else {
+    return Ok(());
+  };
+  let content: SealedContent = serde_json::from_slice(&decrypt(key, &note.id, sealed)?)
+    .map_err(|e| format!("Invalid encrypted note '{}': {}", note.id, e))?;
+  note.text = content.text;
+  note.spans = content.spans;
+  note.checklist = content.checklist;
+  note.table = content.table;
+  note.sealed_text = None;
+  Ok(())
+}
+
+// The key for the document's confidential notes: from `passphrase` when
+// given (checked against the verifier), else unlocked earlier this session or
+// remembered in the keychain
+fn document_key(doc: &model::BoardDocument, passphrase: Option<&str>) -> Result<(String, Key), String> {
+  let encryption = doc.note_encryption.as_ref().ok_or("This board has no passphrase for confidential notes")?;
+  let key = match passphrase {
+    Some(passphrase) => {
+      let salt = base64::engine::general_purpose::STANDARD.decode(&encryption.salt)
+        .map_err(|e| format!("Invalid note encryption salt: {}", e))?;
+      let key = derive_key(passphrase, &salt)?;
+      decrypt(&key, VERIFIER_ID, &encryption.verifier).map_err(|_| "Wrong passphrase".to_string())?;
+      key
+    },
+    None => cached_key(&encryption.key_id)
+      .or_else(|| recall_key(&encryption.key_id))
+      .ok_or("Enter the board's passphrase to unlock confidential notes")?,
+  };
+  Ok((encryption.key_id.clone(), key))
+}
+
+// Set the passphrase that protects the board's confidential notes; the key
+// is unlocked for this session
+pub fn set_passphrase(doc: &model::BoardDocument, passphrase: &str, remember: bool) -> Result<model::BoardDocument, String> {
+  if doc.note_encryption.is_some() {
+    return Err("This board already has a passphrase for confidential notes".to_string());
+  }
+  if passphrase.is_empty() {
+    return Err("The passphrase must not be empty".to_string());
+  }
+  // The hint is stored unencrypted
+  let hint = doc.info.as_ref().and_then(|info| info.password_hint.as_deref()).unwrap_or("");
+  if hint.trim().eq_ignore_ascii_case(passphrase.trim()) {
+    return Err("The password hint must not be the passphrase itself".to_string());
+  }
+  let mut salt = [0u8; SALT_LEN];
+  OsRng.fill_bytes(&mut salt);
+  let key = derive_key(passphrase, &salt)?;
+  let key_id = crate::audit::hash_bytes(&salt)[..16].to_string();
+
+  let mut document = doc.clone();
+  document.note_encryption = Some(model::NoteEncryption {
+    key_id: key_id.clone(),
+    salt: base64::engine::general_purpose::STANDARD.encode(salt),
+    verifier: encrypt(&key, VERIFIER_ID, VERIFIER_TEXT.as_bytes())?,
+  });
+  cache_key(&key_id, key);
+  if remember {
+    remember_key(&key_id, &key)?;
+  }
+  Ok(document)
+}
+
+// Decrypt the board's locked notes, with `passphrase` or a key unlocked
+// before; `remember` keeps the key in the keychain
+pub fn unlock(doc: &model::BoardDocument, passphrase: Option<&str>, remember: bool) -> Result<model::BoardDocument, String> {
+  let (key_id, key) = document_key(doc, passphrase)?;
+  let mut document = doc.clone();
+  for note in all_notes(&mut document) {
+    open_note(&key, note)?;
+  }
+  cache_key(&key_id, key);
+  if remember {
+    remember_key(&key_id, &key)?;
+  }
+  Ok(document)
+}
+
+// Seal the board's confidential notes and forget the key for this session;
+// `forget` also removes it from the keychain
+pub fn lock(doc: &model::BoardDocument, forget: bool) -> Result<model::BoardDocument, String> {
+  let document = seal(doc)?;
+  if let Some(encryption) = &document.note_encryption {
+    forget_key(&encryption.key_id);
+    if forget {
+      match keychain_entry(&encryption.key_id)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {},
+        Err(e) => return Err(format!("Failed to remove the note key from the keychain: {}", e)),
+      }
+    }
+  }
+  Ok(document)
+}
+
+// Mark notes as confidential or not. Needs the board's key: a passphrase has
+// to be set, and locked notes are decrypted when they stop being confidential.
+pub fn set_confidential(doc: &model::BoardDocument, note_ids: &[model::ID], confidential: bool) -> Result<model::BoardDocument, String> {
+  let (_, key) = document_key(doc, None)?;
+  let ids: HashSet<&str> = note_ids.iter().map(String::as_str).collect();
+  let mut document = doc.clone();
+  for note in all_notes(&mut document).filter(|n| ids.contains(n.id.as_str())) {
+    if !confidential {
+      open_note(&key, note)?;
+    }
+    note.confidential = confidential.then_some(true);
+  }
+  Ok(document)
+}
+
+// The board as stored: unlocked confidential notes sealed again. Fails when
+// one has plaintext but its key isn't unlocked.
+pub fn seal(doc: &model::BoardDocument) -> Result<model::BoardDocument, String> {
+  let mut document = doc.clone();
+  let key_id = document.note_encryption.as_ref().map(|e| e.key_id.clone());
+  let mut key = None;
+  for note in all_notes(&mut document).filter(|n| is_confidential(n) && !is_locked(n)) {
+    if key.is_none() {
+      key = key_id.as_deref().and_then(cached_key);
+    }
+    let key = key.as_ref().ok_or("Confidential notes can't be saved while their key is locked; unlock them first")?;
+    seal_note(key, note)?;
+  }
+  Ok(document)
+}
+
+// Copy of `doc` without the notes `drop` selects, and what refers to them
+fn without(doc: &model::BoardDocument, drop: impl Fn(&model::Note) -> bool) -> model::BoardDocument {
+  let removed: HashSet<model::ID> = doc.notes.iter().filter(|n| drop(n)).map(|n| n.id.clone()).collect();
+  let mut document = doc.clone();
+  if let Some(trash) = document.trash.as_mut() {
+    trash.notes.retain(|t| !drop(&t.note));
+  }
+  if removed.is_empty() {
+    return document;
+  }
+  document.notes.retain(|n| !removed.contains(&n.id));
+  document.connections.retain(|c| !removed.contains(&c.src_note_id) && !removed.contains(&c.dst_note_id));
+  for stack in &mut document.stacks {
+    stack.note_ids.retain(|id| !removed.contains(id));
+  }
+  document.stacks.retain(|s| !s.note_ids.is_empty());
+  groups::prune_groups(&mut document);
+  document
+}
+
+// For plaintext exports: the board without locked notes
+pub fn without_locked(doc: &model::BoardDocument) -> model::BoardDocument {
+  without(doc, is_locked)
+}
+
+// For files kept next to the board, like the search sidecar: the board
+// without any confidential notes
+pub fn without_confidential(doc: &model::BoardDocument) -> model::BoardDocument {
+  without(doc, is_confidential)
+}
@@ -0,0 +1,62 @@
+// Minimal RFC 4180 reading: quoted fields may contain the delimiter, quotes
+// (doubled) and line breaks. Rows keep whatever number of fields they have.
+pub fn parse(text: &str, delimiter: char) -> Vec<Vec<String>> {
+  let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+  let mut rows = Vec::new();
+  let mut row = Vec::new();
+  let mut field = String::new();
+  let mut in_quotes = false;
+  let mut chars = text.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    if in_quotes {
+      match c {
+        '"' if chars.peek() == Some(&'"') => {
+          field.push('"');
+          chars.next();
+        },
+        '"' => in_quotes = false,
+        _ => field.push(c),
+      }
+      continue;
+    }
+    match c {
+      '"' if field.is_empty() => in_quotes = true,
+      '\r' if chars.peek() == Some(&'\n') => {},
+      '\n' | '\r' => {
+        row.push(std::mem::take(&mut field));
+        rows.push(std::mem::take(&mut row));
+      },
+      _ if c == delimiter => row.push(std::mem::take(&mut field)),
+      _ => field.push(c),
+    }
+  }
+  if !field.is_empty() || !row.is_empty() {
+    row.push(field);
+    rows.push(row);
+  }
+
+  // Blank lines aren't records
+  rows.retain(|row| !(row.len() == 1 && row[0].is_empty()));
+  rows
+}
+
+// Fields containing the delimiter, quotes or line breaks are quoted, with
+// quotes doubled; rows end with CRLF as RFC 4180 asks
+pub fn write(rows: &[Vec<String>], delimiter: char) -> String {
+  let mut output = String::new();
+  for row in rows {
+    let fields: Vec<String> = row.iter()
+      .map(|field| {
+        if field.contains([delimiter, '"', '\n', '\r']) {
+          format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+          field.clone()
+        }
+      })
+      .collect();
+    output += &fields.join(&delimiter.to_string());
+    output += "\r\n";
+  }
+  output
+}
@@ -0,0 +1,95 @@
+use crate::model;
+
+// Connection geometry. A connection runs from its source note's center
+// through its bend points to the destination's center; `curveType` decides
+// how those anchors are joined:
+//   "straight" – direct line, bend points ignored
+//   "polyline" – straight segments through the bend points (the default)
+//   "smooth"   – Catmull-Rom spline through the bend points
+//   "cubic"    – cubic Bézier segments; `controlPoints` holds two control
+//                points per segment, in segment order
+pub const CURVE_TYPES: [&str; 4] = ["straight", "polyline", "smooth", "cubic"];
+
+// Segments a flattened curve is split into between two anchors
+const CURVE_STEPS: usize = 16;
+
+fn cubic_point(p0: &model::Point, c1: &model::Point, c2: &model::Point, p1: &model::Point, t: f64) -> model::Point {
+  let u = 1.0 - t;
+  let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+  model::Point {
+    x: a * p0.x + b * c1.x + c * c2.x + d * p1.x,
+    y: a * p0.y + b * c1.y + c * c2.y + d * p1.y,
+  }
+}
+
+fn anchors(src: model::Point, dst: model::Point, connection: &model::Connection) -> Vec<model::Point> {
+  let mut points = vec![src];
+  points.extend(connection.bend_points.iter().flatten().cloned());
+  points.push(dst);
+  points
+}
+
+// Number of control points a "cubic" connection needs for its anchors
+pub fn expected_control_points(connection: &model::Connection) -> usize {
+  (connection.bend_points.as_ref().map_or(0, |b| b.len()) + 1) * 2
+}
+
+// Flatten the connection into a polyline renderers can stroke directly
+pub fn flatten(
+  src: model::Point,
+  dst: model::Point,
+  connection: &model::Connection,
+  style: Option<&model::ConnectionStyle>,
+) -> Vec<model::Point> {
+  let curve_type = style.and_then(|s| s.curve_type.as_deref()).unwrap_or("polyline");
+  if curve_type == "straight" {
+    return vec![src, dst];
+  }
+
+  let anchors = anchors(src, dst, connection);
+  match curve_type {
+    "smooth" => catmull_rom(&anchors),
+    "cubic" => {
+      let controls = connection.control_points.as_deref().unwrap_or(&[]);
+      let mut points = vec![anchors[0].clone()];
+      for (i, segment) in anchors.windows(2).enumerate() {
+        match (controls.get(i * 2), controls.get(i * 2 + 1)) {
+          (Some(c1), Some(c2)) => {
+            for step in 1..=CURVE_STEPS {
+              let t = step as f64 / CURVE_STEPS as f64;
+              points.push(cubic_point(&segment[0], c1, c2, &segment[1], t));
+            }
+          },
+          // Missing control points degrade to a straight segment
+          _ => points.push(segment[1].clone()),
+        }
+      }
+      points
+    },
+    _ => anchors,
+  }
+}
+
+// Uniform Catmull-Rom through all anchors, with the end anchors duplicated
+// so the curve starts and ends on the notes.
+fn catmull_rom(anchors: &[model::Point]) -> Vec<model::Point> {
+  if anchors.len() < 3 {
+    return anchors.to_vec();
+  }
+
+  let mut points = vec![anchors[0].clone()];
+  for i in 0..anchors.len() - 1 {
+    let p0 = &anchors[i.saturating_sub(1)];
+    let p1 = &anchors[i];
+    let p2 = &anchors[i + 1];
+    let p3 = &anchors[(i + 2).min(anchors.len() - 1)];
+    // Equivalent Bézier control points for the segment p1 → p2
+    let c1 = model::Point { x: p1.x + (p2.x - p0.x) / 6.0, y: p1.y + (p2.y - p0.y) / 6.0 };
+    let c2 = model::Point { x: p2.x - (p3.x - p1.x) / 6.0, y: p2.y - (p3.y - p1.y) / 6.0 };
+    for step in 1..=CURVE_STEPS {
+      let t = step as f64 / CURVE_STEPS as f64;
+      points.push(cubic_point(p1, &c1, &c2, p2, t));
+    }
+  }
+  points
+}
@@ -0,0 +1,50 @@
+use std::path::{Path, PathBuf};
+
+// Custom URL scheme registered in tauri.conf.json (plugins > deep-link)
+pub const SCHEME: &str = "fim";
+
+// Where a `fim://open?path=...&note=...` link points to
+#[derive(Debug, Clone)]
+pub struct DeepLinkTarget {
+  pub path: PathBuf,
+  pub note_id: Option<String>,
+}
+
+pub fn parse_deep_link(url: &tauri::Url) -> Result<DeepLinkTarget, String> {
+  if url.scheme() != SCHEME {
+    return Err(format!("Unsupported link scheme '{}'", url.scheme()));
+  }
+  if url.host_str() != Some("open") {
+    return Err(format!("Unsupported link action '{}'", url.host_str().unwrap_or("")));
+  }
+
+  let mut path = None;
+  let mut note_id = None;
+  for (key, value) in url.query_pairs() {
+    match key.as_ref() {
+      "path" => path = Some(PathBuf::from(value.as_ref())),
+      "note" if !value.is_empty() => note_id = Some(value.into_owned()),
+      _ => {},
+    }
+  }
+
+  let path = path.ok_or("Link is missing the 'path' parameter")?;
+  if !path.is_absolute() {
+    return Err(format!("Link path '{}' must be absolute", path.display()));
+  }
+
+  Ok(DeepLinkTarget { path, note_id })
+}
+
+pub fn build_deep_link(path: &Path, note_id: Option<&str>) -> Result<String, String> {
+  let mut url = tauri::Url::parse(&format!("{}://open", SCHEME))
+    .map_err(|e| format!("Failed to build link: {}", e))?;
+  {
+    let mut query = url.query_pairs_mut();
+    query.append_pair("path", &path.to_string_lossy());
+    if let Some(note_id) = note_id {
+      query.append_pair("note", note_id);
+    }
+  }
+  Ok(url.to_string())
+}
@@ -0,0 +1,250 @@
+use crate::{markdown, model, relations, shapes};
+
+// Graphviz DOT and Mermaid flowchart exports, for dropping a board into docs
+// and READMEs as a graph. Notes become nodes labelled with their text (faded
+// ones drawn grey and dashed), connections become edges with their label,
+// line style and arrows, and background shapes become clusters (DOT) or
+// subgraphs (Mermaid) around the notes inside them. A note inside nested
+// shapes goes into the innermost one. Nodes are named n1, n2, ... in export
+// order rather than by note ID, which Mermaid would read as syntax.
+
+struct Cluster<'a> {
+  shape: &'a model::BackgroundShape,
+  // Indexes into the exported notes
+  notes: Vec<usize>,
+  children: Vec<usize>,
+}
+
+struct Diagram<'a> {
+  notes: &'a [model::Note],
+  clusters: Vec<Cluster<'a>>,
+  // Notes outside every shape
+  loose_notes: Vec<usize>,
+  // Clusters not inside another one
+  top_clusters: Vec<usize>,
+}
+
+fn area(shape: &model::BackgroundShape) -> f64 {
+  shape.frame.w.abs() * shape.frame.h.abs()
+}
+
+// The smallest of `shapes` containing `point` that `candidate` accepts
+fn innermost(shapes: &[&model::BackgroundShape], point: &model::Point, candidate: impl Fn(usize) -> bool) -> Option<usize> {
+  shapes.iter().enumerate()
+    .filter(|(index, shape)| candidate(*index) && shapes::contains_point(shape, point))
+    .min_by(|(_, a), (_, b)| area(a).total_cmp(&area(b)))
+    .map(|(index, _)| index)
+}
+
+impl<'a> Diagram<'a> {
+  fn new(doc: &'a model::BoardDocument, notes: &'a [model::Note]) -> Self {
+    // Lines enclose nothing
+    let containers: Vec<&model::BackgroundShape> = doc.shapes.iter().filter(|s| shapes::kind(s) != "line").collect();
+    let mut clusters: Vec<Cluster> = containers.iter()
+      .map(|shape| Cluster { shape, notes: Vec::new(), children: Vec::new() })
+      .collect();
+
+    let mut loose_notes = Vec::new();
+    for (index, note) in notes.iter().enumerate() {
+      let center = model::Point { x: note.frame.x + note.frame.w / 2.0, y: note.frame.y + note.frame.h / 2.0 };
+      match innermost(&containers, &center, |_| true) {
+        Some(cluster) => clusters[cluster].notes.push(index),
+        None => loose_notes.push(index),
+      }
+    }
+
+    // A shape nests in the smallest larger shape around its center; of two
+    // the same size, the later one nests in the earlier
+    let mut top_clusters = Vec::new();
+    for (index, shape) in containers.iter().enumerate() {
+      let f = &shape.frame;
+      let center = model::Point { x: f.x + f.w / 2.0, y: f.y + f.h / 2.0 };
+      let larger = |other: usize| {
+        let (a, b) = (area(containers[other]), area(shape));
+        a > b || (a == b && other < index)
+      };
+      let parent = innermost(&containers, &center, larger);
+      match parent {
+        Some(parent) => clusters[parent].children.push(index),
+        None => top_clusters.push(index),
+      }
+    }
+
+    Diagram { notes, clusters, loose_notes, top_clusters }
+  }
+
+  // Clusters with no notes anywhere inside are left out
+  fn has_notes(&self, cluster: usize) -> bool {
+    let cluster = &self.clusters[cluster];
+    !cluster.notes.is_empty() || cluster.children.iter().any(|child| self.has_notes(*child))
+  }
+
+  fn node_name(&self, note_id: &str) -> Option<String> {
+    self.notes.iter().position(|n| n.id == note_id).map(|index| format!("n{}", index + 1))
+  }
+}
+
+fn note_label(note: &model::Note) -> String {
+  let (text, _) = markdown::note_rich_text(note);
+  let text = text.trim();
+  if text.is_empty() { "(empty)".to_string() } else { text.to_string() }
+}
+
+// "none", "src", "dst" or "both"; unset draws no arrows
+fn arrows(doc: &model::BoardDocument, connection: &model::Connection) -> String {
+  relations::effective_style(doc, connection)
+    .and_then(|s| s.arrows)
+    .unwrap_or_else(|| "none".to_string())
+}
+
+// New connections are dotted unless set to solid
+fn is_dotted(doc: &model::BoardDocument, connection: &model::Connection) -> bool {
+  relations::effective_style(doc, connection)
+    .and_then(|s| s.kind)
+    .map_or(true, |kind| kind != "solid")
+}
+
+fn dot_escape(text: &str) -> String {
+  text.replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace("\r\n", "\n")
+    .replace('\n', "\\n")
+}
+
+pub fn to_dot(doc: &model::BoardDocument, notes: &[model::Note]) -> String {
+  let diagram = Diagram::new(doc, notes);
+  let mut dot = "digraph board {\n".to_string();
+  dot += "  node [shape=box, style=rounded];\n";
+  dot += "  edge [arrowhead=normal];\n";
+
+  for index in &diagram.loose_notes {
+    dot += &dot_node(&diagram, *index, 1);
+  }
+  for cluster in &diagram.top_clusters {
+    dot += &dot_cluster(&diagram, *cluster, 1);
+  }
+
+  for connection in &doc.connections {
+    let (Some(src), Some(dst)) = (diagram.node_name(&connection.src_note_id), diagram.node_name(&connection.dst_note_id)) else {
+      continue;
+    };
+    let mut attributes = vec![match arrows(doc, connection).as_str() {
+      "dst" => "dir=forward",
+      "src" => "dir=back",
+      "both" => "dir=both",
+      _ => "dir=none",
+    }.to_string()];
+    if is_dotted(doc, connection) {
+      attributes.push("style=dotted".to_string());
+    }
+    if let Some(label) = connection.label.as_deref().filter(|l| !l.trim().is_empty()) {
+      attributes.push(format!("label=\"{}\"", dot_escape(label.trim())));
+    }
+    dot += &format!("  {} -> {} [{}];\n", src, dst, attributes.join(", "));
+  }
+  dot += "}\n";
+  dot
+}
+
+fn dot_node(diagram: &Diagram, index: usize, depth: usize) -> String {
+  let note = &diagram.notes[index];
+  let mut attributes = vec![format!("label=\"{}\"", dot_escape(&note_label(note)))];
+  if note.faded.unwrap_or(false) {
+    attributes.push("style=\"rounded,dashed\", color=gray60, fontcolor=gray50".to_string());
+  }
+  format!("{}n{} [{}];\n", "  ".repeat(depth), index + 1, attributes.join(", "))
+}
+
+fn dot_cluster(diagram: &Diagram, cluster: usize, depth: usize) -> String {
+  if !diagram.has_notes(cluster) {
+    return String::new();
+  }
+  let indent = "  ".repeat(depth);
+  let shape = diagram.clusters[cluster].shape;
+  let mut dot = format!("{}subgraph cluster_{} {{\n", indent, cluster + 1);
+  dot += &format!("{}  label=\"{}\";\n", indent, dot_escape(shape.label.as_deref().unwrap_or("").trim()));
+  for index in &diagram.clusters[cluster].notes {
+    dot += &dot_node(diagram, *index, depth + 1);
+  }
+  for child in &diagram.clusters[cluster].children {
+    dot += &dot_cluster(diagram, *child, depth + 1);
+  }
+  dot += &format!("{}}}\n", indent);
+  dot
+}
+
+// Quoted Mermaid text; quotes and angle brackets as entity codes so they
+// aren't read as syntax or HTML
+fn mermaid_escape(text: &str) -> String {
+  text.replace('"', "#quot;")
+    .replace('<', "#lt;")
+    .replace('>', "#gt;")
+    .replace('|', "#124;")
+    .replace("\r\n", "\n")
+    .replace('\n', "<br/>")
+}
+
+pub fn to_mermaid(doc: &model::BoardDocument, notes: &[model::Note]) -> String {
+  let diagram = Diagram::new(doc, notes);
+  let mut mermaid = "flowchart TB\n".to_string();
+
+  for index in &diagram.loose_notes {
+    mermaid += &mermaid_node(&diagram, *index, 1);
+  }
+  for cluster in &diagram.top_clusters {
+    mermaid += &mermaid_cluster(&diagram, *cluster, 1);
+  }
+
+  for connection in &doc.connections {
+    let (Some(src), Some(dst)) = (diagram.node_name(&connection.src_note_id), diagram.node_name(&connection.dst_note_id)) else {
+      continue;
+    };
+    let dotted = is_dotted(doc, connection);
+    // Mermaid has no arrow at the start only, so those edges are turned around
+    let (from, to, link) = match (arrows(doc, connection).as_str(), dotted) {
+      ("dst", false) => (&src, &dst, "-->"),
+      ("src", false) => (&dst, &src, "-->"),
+      ("both", false) => (&src, &dst, "<-->"),
+      (_, false) => (&src, &dst, "---"),
+      ("dst", true) => (&src, &dst, "-.->"),
+      ("src", true) => (&dst, &src, "-.->"),
+      ("both", true) => (&src, &dst, "<-.->"),
+      (_, true) => (&src, &dst, "-.-"),
+    };
+    match connection.label.as_deref().filter(|l| !l.trim().is_empty()) {
+      Some(label) => mermaid += &format!("  {} {}|\"{}\"| {}\n", from, link, mermaid_escape(label.trim()), to),
+      None => mermaid += &format!("  {} {} {}\n", from, link, to),
+    }
+  }
+
+  let faded: Vec<String> = notes.iter().enumerate()
+    .filter(|(_, note)| note.faded.unwrap_or(false))
+    .map(|(index, _)| format!("n{}", index + 1))
+    .collect();
+  if !faded.is_empty() {
+    mermaid += "  classDef faded opacity:0.5,stroke-dasharray:4 3\n";
+    mermaid += &format!("  class {} faded\n", faded.join(","));
+  }
+  mermaid
+}
+
+fn mermaid_node(diagram: &Diagram, index: usize, depth: usize) -> String {
+  format!("{}n{}[\"{}\"]\n", "  ".repeat(depth), index + 1, mermaid_escape(&note_label(&diagram.notes[index])))
+}
+
+fn mermaid_cluster(diagram: &Diagram, cluster: usize, depth: usize) -> String {
+  if !diagram.has_notes(cluster) {
+    return String::new();
+  }
+  let indent = "  ".repeat(depth);
+  let label = diagram.clusters[cluster].shape.label.as_deref().map(str::trim).filter(|l| !l.is_empty()).unwrap_or(" ");
+  let mut mermaid = format!("{}subgraph s{}[\"{}\"]\n", indent, cluster + 1, mermaid_escape(label));
+  for index in &diagram.clusters[cluster].notes {
+    mermaid += &mermaid_node(diagram, *index, depth + 1);
+  }
+  for child in &diagram.clusters[cluster].children {
+    mermaid += &mermaid_cluster(diagram, *child, depth + 1);
+  }
+  mermaid += &format!("{}end\n", indent);
+  mermaid
+}
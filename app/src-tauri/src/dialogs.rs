@@ -0,0 +1,48 @@
+use tauri_plugin_dialog::{FileDialogBuilder, FilePath};
+use tokio::sync::oneshot;
+
+// Native file dialogs that don't hold up the async runtime. The blocking
+// dialog calls park a runtime worker until the user answers; with a few
+// dialogs open, or a slow export waiting behind them, commands stall. These
+// show the dialog and await its answer instead.
+
+pub trait AsyncFileDialog {
+  async fn pick_file_async(self) -> Option<FilePath>;
+  async fn pick_files_async(self) -> Option<Vec<FilePath>>;
+  async fn pick_folder_async(self) -> Option<FilePath>;
+  async fn save_file_async(self) -> Option<FilePath>;
+}
+
+impl AsyncFileDialog for FileDialogBuilder<tauri::Wry> {
+  async fn pick_file_async(self) -> Option<FilePath> {
+    let (sender, receiver) = oneshot::channel();
+    self.pick_file(move |path| {
+      let _ = sender.send(path);
+    });
+    receiver.await.ok().flatten()
+  }
+
+  async fn pick_files_async(self) -> Option<Vec<FilePath>> {
+    let (sender, receiver) = oneshot::channel();
+    self.pick_files(move |paths| {
+      let _ = sender.send(paths);
+    });
+    receiver.await.ok().flatten()
+  }
+
+  async fn pick_folder_async(self) -> Option<FilePath> {
+    let (sender, receiver) = oneshot::channel();
+    self.pick_folder(move |path| {
+      let _ = sender.send(path);
+    });
+    receiver.await.ok().flatten()
+  }
+
+  async fn save_file_async(self) -> Option<FilePath> {
+    let (sender, receiver) = oneshot::channel();
+    self.save_file(move |path| {
+      let _ = sender.send(path);
+    });
+    receiver.await.ok().flatten()
+  }
+}
@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+use crate::{model, raster, text_stats};
+
+// Board totals at a glance: the counts from text_stats.rs (words, characters,
+// notes per shape and stack) with what the board holds besides text, so a
+// board used to plan a manuscript can be measured without exporting it.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DocumentStats {
+  pub notes: usize,
+  pub words: usize,
+  pub characters: usize,
+  pub reading_minutes: f64,
+  pub connections: usize,
+  pub faded_notes: usize,
+  // Notes and words per shape (shapes without notes left out) and per stack
+  pub shapes: Vec<text_stats::GroupTextStats>,
+  pub stacks: Vec<text_stats::GroupTextStats>,
+  // Around the notes and shapes; none for an empty board
+  pub bounds: Option<model::Rect>,
+  // Images stored in the board, their decoded size, and images it only
+  // refers to by path
+  pub images: usize,
+  pub media_bytes: u64,
+  pub linked_images: usize,
+}
+
+// Bytes of base64 data once decoded, without decoding it
+fn decoded_len(data: &str) -> u64 {
+  let data = data.trim_end();
+  let padding = data.bytes().rev().take_while(|b| *b == b'=').count();
+  (data.len() as u64 * 3 / 4).saturating_sub(padding as u64)
+}
+
+pub fn document_stats(doc: &model::BoardDocument) -> DocumentStats {
+  let text = text_stats::text_stats(doc);
+  let images = doc.images.iter().flatten();
+  DocumentStats {
+    notes: text.notes,
+    words: text.words,
+    characters: text.characters,
+    reading_minutes: text.reading_minutes,
+    connections: doc.connections.len(),
+    faded_notes: doc.notes.iter().filter(|n| n.faded.unwrap_or(false)).count(),
+    shapes: text.shapes,
+    stacks: text.stacks,
+    bounds: raster::content_bounds(doc),
+    images: images.clone().filter(|image| image.data_base64.is_some()).count(),
+    media_bytes: images.clone().filter_map(|image| image.data_base64.as_deref()).map(decoded_len).sum(),
+    linked_images: images.filter(|image| image.data_base64.is_none() && image.path.is_some()).count(),
+  }
+}
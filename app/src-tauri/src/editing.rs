@@ -0,0 +1,223 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{groups, layers, links, model, trash};
+
+// Delete notes, shapes, strokes and connections by ID, cleaning up everything that
+// referenced the removed notes (connections, stack and group membership).
+// Notes and connections go to the document's trash rather than away.
+// Locked notes, shapes and strokes (including those on locked layers) are left
+// in place; their IDs are returned.
+pub fn delete_items(doc: &mut model::BoardDocument, ids: &[model::ID]) -> Vec<model::ID> {
+  let ids: HashSet<&str> = ids.iter().map(|id| id.as_str()).collect();
+  let locked = layers::locked_ids(doc);
+  let mut skipped = Vec::new();
+
+  let mut removed_notes = HashSet::new();
+  let mut trashed_notes = Vec::new();
+  doc.notes.retain(|note| {
+    if !ids.contains(note.id.as_str()) {
+      return true;
+    }
+    if locked.contains(&note.id) {
+      skipped.push(note.id.clone());
+      return true;
+    }
+    removed_notes.insert(note.id.clone());
+    trashed_notes.push(note.clone());
+    false
+  });
+  doc.shapes.retain(|shape| {
+    if !ids.contains(shape.id.as_str()) {
+      return true;
+    }
+    if locked.contains(&shape.id) {
+      skipped.push(shape.id.clone());
+      return true;
+    }
+    false
+  });
+
+  if let Some(strokes) = doc.strokes.as_mut() {
+    strokes.retain(|stroke| {
+      if !ids.contains(stroke.id.as_str()) {
+        return true;
+      }
+      if locked.contains(&stroke.id) {
+        skipped.push(stroke.id.clone());
+        return true;
+      }
+      false
+    });
+  }
+
+  let mut trashed_connections = Vec::new();
+  doc.connections.retain(|c| {
+    let keep = !ids.contains(c.id.as_str())
+      && !removed_notes.contains(&c.src_note_id)
+      && !removed_notes.contains(&c.dst_note_id);
+    if !keep {
+      trashed_connections.push(c.clone());
+    }
+    keep
+  });
+  let connections: HashSet<&str> = doc.connections.iter().map(|c| c.id.as_str()).collect();
+  for note in &mut doc.notes {
+    if let Some(refs) = note.connections.as_mut() {
+      refs.retain(|id| connections.contains(id.as_str()));
+    }
+  }
+
+  for stack in &mut doc.stacks {
+    stack.note_ids.retain(|id| !removed_notes.contains(id));
+    if let Some(levels) = stack.indent_levels.as_mut() {
+      levels.retain(|id, _| !removed_notes.contains(id));
+    }
+  }
+  doc.stacks.retain(|s| !s.note_ids.is_empty());
+  let stacks: HashSet<&str> = doc.stacks.iter().map(|s| s.id.as_str()).collect();
+  for note in &mut doc.notes {
+    if note.stack_id.as_deref().is_some_and(|id| !stacks.contains(id)) {
+      note.stack_id = None;
+    }
+  }
+
+  groups::prune_groups(doc);
+  trash::move_to_trash(doc, trashed_notes, trashed_connections);
+  skipped
+}
+
+// What `duplicate_notes` adds to the board; the frontend appends these and
+// selects the new notes. `id_map` maps every copied note ID to its copy's.
+#[derive(Serialize, Debug, Clone)]
+pub struct Duplication {
+  pub notes: Vec<model::Note>,
+  pub connections: Vec<model::Connection>,
+  pub stacks: Vec<model::Stack>,
+  pub images: Vec<model::EmbeddedImage>,
+  pub id_map: HashMap<model::ID, model::ID>,
+}
+
+// The given notes plus, with `include_descendants`, everything reachable
+// through outgoing connections (the parent → child direction of outlines)
+fn collect_subtree(doc: &model::BoardDocument, note_ids: &[model::ID], include_descendants: bool) -> HashSet<model::ID> {
+  let mut selected: HashSet<model::ID> = note_ids.iter()
+    .filter(|id| doc.notes.iter().any(|n| &n.id == *id))
+    .cloned()
+    .collect();
+  if !include_descendants {
+    return selected;
+  }
+  let mut pending: Vec<model::ID> = selected.iter().cloned().collect();
+  while let Some(id) = pending.pop() {
+    for c in doc.connections.iter().filter(|c| c.src_note_id == id) {
+      if selected.insert(c.dst_note_id.clone()) {
+        pending.push(c.dst_note_id.clone());
+      }
+    }
+  }
+  selected
+}
+
+// Deep-copy notes with fresh IDs, moved by `offset`. Connections between
+// copied notes are copied too, stacks with two or more copied members become
+// new stacks, and the copies get their own image entries. `note://` links
+// between copied notes point at the copies.
+pub fn duplicate_notes(
+  doc: &model::BoardDocument,
+  note_ids: &[model::ID],
+  include_descendants: bool,
+  offset: &model::Point,
+) -> Result<Duplication, String> {
+  let selected = collect_subtree(doc, note_ids, include_descendants);
+  if selected.is_empty() {
+    return Err("No notes found to duplicate".to_string());
+  }
+
+  // Document order, so the copies layer the same way the originals do
+  let originals: Vec<&model::Note> = doc.notes.iter().filter(|n| selected.contains(&n.id)).collect();
+  let id_map: HashMap<model::ID, model::ID> = originals.iter()
+    .map(|n| (n.id.clone(), model::new_id("note")))
+    .collect();
+
+  let connections: Vec<model::Connection> = doc.connections.iter()
+    .filter(|c| id_map.contains_key(&c.src_note_id) && id_map.contains_key(&c.dst_note_id))
+    .map(|c| {
+      let mut copy = c.clone();
+      copy.id = model::new_id("conn");
+      copy.src_note_id = id_map[&c.src_note_id].clone();
+      copy.dst_note_id = id_map[&c.dst_note_id].clone();
+      let shift = |p: &mut model::Point| {
+        p.x += offset.x;
+        p.y += offset.y;
+      };
+      copy.bend_points.iter_mut().flatten().for_each(shift);
+      copy.control_points.iter_mut().flatten().for_each(shift);
+      copy
+    })
+    .collect();
+  let connection_map: HashMap<&str, &model::ID> = doc.connections.iter()
+    .filter(|c| id_map.contains_key(&c.src_note_id) && id_map.contains_key(&c.dst_note_id))
+    .map(|c| c.id.as_str())
+    .zip(connections.iter().map(|c| &c.id))
+    .collect();
+
+  let mut stacks = Vec::new();
+  let mut stack_map: HashMap<&str, model::ID> = HashMap::new();
+  for stack in &doc.stacks {
+    let members: Vec<model::ID> = stack.note_ids.iter().filter_map(|id| id_map.get(id).cloned()).collect();
+    // A single copied member is just a note
+    if members.len() < 2 {
+      continue;
+    }
+    let mut copy = stack.clone();
+    copy.id = model::new_id("stack");
+    copy.note_ids = members;
+    copy.indent_levels = stack.indent_levels.as_ref().map(|levels| levels.iter()
+      .filter_map(|(id, level)| Some((id_map.get(id)?.clone(), *level)))
+      .collect());
+    stack_map.insert(stack.id.as_str(), copy.id.clone());
+    stacks.push(copy);
+  }
+
+  let available_images: HashMap<&str, &model::EmbeddedImage> = doc.images.iter().flatten()
+    .map(|image| (image.id.as_str(), image))
+    .collect();
+  let mut images = Vec::new();
+  let remap_link = |link: &mut String| {
+    if let Some(copy) = links::note_link_target(link).and_then(|id| id_map.get(id)) {
+      *link = format!("{}{}", links::NOTE_LINK_SCHEME, copy);
+    }
+  };
+
+  let notes = originals.into_iter()
+    .map(|original| {
+      let mut note = original.clone();
+      note.id = id_map[&original.id].clone();
+      note.frame.x += offset.x;
+      note.frame.y += offset.y;
+      note.stack_id = original.stack_id.as_deref().and_then(|id| stack_map.get(id).cloned());
+      note.connections = original.connections.as_ref().map(|refs| refs.iter()
+        .filter_map(|id| connection_map.get(id.as_str()).map(|copy| (*copy).clone()))
+        .collect());
+      // Each copy owns its image entries, so editing one leaves the other alone
+      note.images = original.images.as_ref().map(|refs| refs.iter()
+        .map(|id| match available_images.get(id.as_str()) {
+          Some(image) => {
+            let mut copy = (*image).clone();
+            copy.id = model::new_id("image");
+            let copy_id = copy.id.clone();
+            images.push(copy);
+            copy_id
+          }
+          None => id.clone(),
+        })
+        .collect());
+      note.links.iter_mut().flatten().for_each(&remap_link);
+      note.spans.iter_mut().flatten().filter_map(|span| span.link.as_mut()).for_each(&remap_link);
+      note
+    })
+    .collect();
+
+  Ok(Duplication { notes, connections, stacks, images, id_map })
+}
@@ -0,0 +1,111 @@
+use base64::Engine;
+
+// Email export: the board as an RFC 822 message (.eml) with the text outline
+// as the body and the rendered board attached, opened in the default mail
+// client. The message is marked unsent (`X-Unsent: 1`), so clients that honor
+// it (Outlook, Apple Mail) open it as a draft ready to address and send.
+
+// Base64 lines are wrapped at this length (RFC 2045)
+const LINE_LENGTH: usize = 76;
+
+pub struct Attachment {
+  pub name: String,
+  pub mime: String,
+  pub data: Vec<u8>,
+}
+
+pub struct Message<'a> {
+  pub subject: &'a str,
+  pub to: &'a [String],
+  pub text: &'a str,
+  // HTML alternative of `text`
+  pub html: Option<&'a str>,
+  pub attachments: &'a [Attachment],
+}
+
+fn encode_base64(data: &[u8]) -> String {
+  let encoded = base64::engine::general_purpose::STANDARD.encode(data);
+  let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / LINE_LENGTH * 2 + 2);
+  for line in encoded.as_bytes().chunks(LINE_LENGTH) {
+    wrapped.push_str(std::str::from_utf8(line).unwrap_or(""));
+    wrapped.push_str("\r\n");
+  }
+  wrapped
+}
+
+// A header value, as an RFC 2047 encoded word unless it is plain ASCII
+fn encode_header(value: &str) -> String {
+  let value: String = value.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+  if value.is_ascii() {
+    value
+  } else {
+    format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(value.as_bytes()))
+  }
+}
+
+// A file name parameter, quoted and stripped of characters that would end it
+fn quote_name(name: &str) -> String {
+  let name: String = name.chars().filter(|c| !matches!(c, '"' | '\\' | '\r' | '\n')).collect();
+  format!("\"{}\"", encode_header(&name))
+}
+
+fn boundary(kind: &str, message: &Message) -> String {
+  let seed = format!("{}{}{}", kind, message.subject, message.text);
+  format!("----=_fim_{}_{}", kind, &crate::audit::hash_bytes(seed.as_bytes())[..16])
+}
+
+fn text_part(mime: &str, content: &str) -> String {
+  format!(
+    "Content-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+    mime,
+    encode_base64(content.as_bytes())
+  )
+}
+
+pub fn build_eml(message: &Message) -> Vec<u8> {
+  let mut eml = String::new();
+  eml.push_str("MIME-Version: 1.0\r\n");
+  eml.push_str(&format!("Date: {}\r\n", chrono::Local::now().to_rfc2822()));
+  if !message.to.is_empty() {
+    let to: Vec<String> = message.to.iter().map(|a| encode_header(a.trim())).filter(|a| !a.is_empty()).collect();
+    eml.push_str(&format!("To: {}\r\n", to.join(", ")));
+  }
+  eml.push_str(&format!("Subject: {}\r\n", encode_header(message.subject)));
+  eml.push_str("X-Unsent: 1\r\n");
+
+  let mixed = boundary("mixed", message);
+  eml.push_str(&format!("Content-Type: multipart/mixed; boundary=\"{}\"\r\n\r\n", mixed));
+  eml.push_str("This is a multi-part message in MIME format.\r\n");
+
+  eml.push_str(&format!("--{}\r\n", mixed));
+  match message.html {
+    Some(html) => {
+      let alternative = boundary("alternative", message);
+      eml.push_str(&format!("Content-Type: multipart/alternative; boundary=\"{}\"\r\n\r\n", alternative));
+      eml.push_str(&format!("--{}\r\n{}", alternative, text_part("text/plain", message.text)));
+      eml.push_str(&format!("--{}\r\n{}", alternative, text_part("text/html", html)));
+      eml.push_str(&format!("--{}--\r\n", alternative));
+    },
+    None => eml.push_str(&text_part("text/plain", message.text)),
+  }
+
+  for attachment in message.attachments {
+    let name = quote_name(&attachment.name);
+    eml.push_str(&format!("--{}\r\n", mixed));
+    eml.push_str(&format!("Content-Type: {}; name={}\r\n", attachment.mime, name));
+    eml.push_str("Content-Transfer-Encoding: base64\r\n");
+    eml.push_str(&format!("Content-Disposition: attachment; filename={}\r\n\r\n", name));
+    eml.push_str(&encode_base64(&attachment.data));
+  }
+  eml.push_str(&format!("--{}--\r\n", mixed));
+  eml.into_bytes()
+}
+
+// An HTML page body for the outline
+pub fn html_body(title: &str, outline_html: &str) -> String {
+  format!(
+    "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{0}</title></head><body><h1>{0}</h1>{1}</body></html>",
+    crate::outline::html_escape(title),
+    outline_html
+  )
+}
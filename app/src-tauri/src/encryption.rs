@@ -0,0 +1,381 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{archive, recent_files};
+
+// Password-protected .fim files. The whole container (board.json, media,
+// fonts, preview and metadata) is encrypted as one piece, so nothing about
+// the board is readable without the password:
+//
+//   FIMENC1\n
+//   {"kdf":"argon2id",...,"salt":..,"verifier":..,"nonce":..}\n
+//   XChaCha20-Poly1305 ciphertext of the plain .fim zip
+//
+// The key is derived from the password with Argon2id; the parameters are kept
+// in the header so they can be raised later without breaking old files. The
+// verifier, a known text encrypted with the key, tells a wrong password apart
+// from a damaged file, and the header is authenticated along with the body.
+//
+// Keys unlocked this session are kept in memory by key id, and each protected
+// board's path points at its key, so saving, autosave and recovery files stay
+// encrypted without asking for the password again. Nothing goes to the
+// keychain.
+
+const MAGIC: &[u8] = b"FIMENC1\n";
+const KDF: &str = "argon2id";
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const VERIFIER_TEXT: &[u8] = b"fim";
+// Largest header line, and the costs a file may ask for, so a crafted header
+// can't make opening it take forever
+const MAX_HEADER_LEN: usize = 4096;
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+const MAX_PARALLELISM: u32 = 16;
+
+type Key = [u8; KEY_LEN];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Header {
+  kdf: String,
+  memory_kib: u32,
+  iterations: u32,
+  parallelism: u32,
+  salt: String,
+  key_id: String,
+  verifier: String,
+  nonce: String,
+}
+
+// A derived key with what's needed to write a header for it
+#[derive(Clone)]
+pub struct FileKey {
+  key_id: String,
+  salt: Vec<u8>,
+  params: (u32, u32, u32),
+  key: Key,
+}
+
+fn keys() -> &'static Mutex<HashMap<String, FileKey>> {
+  static KEYS: OnceLock<Mutex<HashMap<String, FileKey>>> = OnceLock::new();
+  KEYS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// Protected board → key id
+fn protected() -> &'static Mutex<HashMap<PathBuf, String>> {
+  static PROTECTED: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+  PROTECTED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+  PathBuf::from(recent_files::canonical_path(&path.to_string_lossy()))
+}
+
+fn display_name(path: &Path) -> String {
+  path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string())
+}
+
+fn derive_key(password: &str, salt: &[u8], (memory_kib, iterations, parallelism): (u32, u32, u32)) -> Result<Key, String> {
+  let params = Params::new(memory_kib, iterations, parallelism, Some(KEY_LEN))
+    .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+  let mut key = [0u8; KEY_LEN];
+  Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+    .hash_password_into(password.as_bytes(), salt, &mut key)
+    .map_err(|e| format!("Failed to derive the file key: {}", e))?;
+  Ok(key)
+}
+
+fn seal(key: &Key, plaintext: &[u8], aad: &[u8]) -> Result<(XNonce, Vec<u8>), String> {
+  let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+  let ciphertext = XChaCha20Poly1305::new(key.into())
+    .encrypt(&nonce, Payload { msg: plaintext, aad })
+    .map_err(|_| "Failed to encrypt the board".to_string())?;
+  Ok((nonce, ciphertext))
+}
+
+fn open(key: &Key, nonce: &[u8], ciphertext: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+  if nonce.len() != NONCE_LEN {
+    return None;
+  }
+  XChaCha20Poly1305::new(key.into())
+    .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+    .ok()
+}
+
+fn decode(value: &str, what: &str, path: &Path) -> Result<Vec<u8>, String> {
+  base64::engine::general_purpose::STANDARD.decode(value)
+    .map_err(|_| format!("Invalid encrypted board '{}': bad {}", display_name(path), what))
+}
+
+// A new key for `password`, with a fresh salt
+pub fn new_key(password: &str) -> Result<FileKey, String> {
+  if password.is_empty() {
+    return Err("The password must not be empty".to_string());
+  }
+  let mut salt = vec![0u8; SALT_LEN];
+  OsRng.fill_bytes(&mut salt);
+  let defaults = Params::default();
+  let params = (defaults.m_cost(), defaults.t_cost(), defaults.p_cost());
+  let key = derive_key(password, &salt, params)?;
+  let key_id = crate::audit::hash_bytes(&salt)[..16].to_string();
+  Ok(FileKey { key_id, salt, params, key })
+}
+
+// Whether the file at `path` is a password-protected board
+pub fn is_encrypted(path: &Path) -> bool {
+  let mut head = [0u8; MAGIC.len()];
+  std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut head)).is_ok() && head == MAGIC
+}
+
+// `plain_fim`, the bytes of an ordinary .fim container, encrypted with `key`
+pub fn encrypt(key: &FileKey, plain_fim: &[u8]) -> Result<Vec<u8>, String> {
+  let (verifier_nonce, verifier) = seal(&key.key, VERIFIER_TEXT, key.key_id.as_bytes())?;
+  let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+  let encode = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+  let header = Header {
+    kdf: KDF.to_string(),
+    memory_kib: key.params.0,
+    iterations: key.params.1,
+    parallelism: key.params.2,
+    salt: encode(&key.salt),
+    key_id: key.key_id.clone(),
+    verifier: encode(&[verifier_nonce.as_slice(), &verifier].concat()),
+    nonce: encode(&nonce),
+  };
+  let mut line = serde_json::to_vec(&header).map_err(|e| format!("Failed to serialize encryption header: {}", e))?;
+  line.push(b'\n');
+
+  let mut output = MAGIC.to_vec();
+  output.extend(&line);
+  // The header can't be swapped for another one
+  let body = XChaCha20Poly1305::new((&key.key).into())
+    .encrypt(&nonce, Payload { msg: plain_fim, aad: &line })
+    .map_err(|_| "Failed to encrypt the board".to_string())?;
+  output.extend(body);
+  Ok(output)
+}
+
+// The header and where the ciphertext starts
+fn read_header(path: &Path, data: &[u8]) -> Result<(Header, usize), String> {
+  let rest = data.strip_prefix(MAGIC).ok_or_else(|| format!("'{}' is not a password-protected board", display_name(path)))?;
+  let end = rest.iter().take(MAX_HEADER_LEN).position(|b| *b == b'\n')
+    .ok_or_else(|| format!("Invalid encrypted board '{}': damaged header", display_name(path)))?;
+  let header: Header = serde_json::from_slice(&rest[..end])
+    .map_err(|_| format!("Invalid encrypted board '{}': damaged header", display_name(path)))?;
+  if header.kdf != KDF {
+    return Err(format!("Unsupported encryption '{}' in '{}'. Please update the application.", header.kdf, display_name(path)));
+  }
+  if header.memory_kib > MAX_MEMORY_KIB || header.iterations > MAX_ITERATIONS || header.parallelism > MAX_PARALLELISM {
+    return Err(format!("Unsupported encryption settings in '{}'", display_name(path)));
+  }
+  Ok((header, MAGIC.len() + end + 1))
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, String> {
+  let size = std::fs::metadata(path).map(|m| m.len())
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+  if size > archive::MAX_TOTAL_UNCOMPRESSED {
+    return Err(format!("Invalid encrypted board '{}': file is too large", display_name(path)));
+  }
+  std::fs::read(path).map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))
+}
+
+// Check `password` against the board at `path` and keep its key for this
+// session, so the board can be read and saved again
+pub fn unlock(path: &Path, password: &str) -> Result<(), String> {
+  let data = read_file(path)?;
+  let (header, _) = read_header(path, &data)?;
+  let salt = decode(&header.salt, "salt", path)?;
+  let params = (header.memory_kib, header.iterations, header.parallelism);
+  let key = derive_key(password, &salt, params)?;
+  let verifier = decode(&header.verifier, "verifier", path)?;
+  if verifier.len() < NONCE_LEN {
+    return Err(format!("Invalid encrypted board '{}': bad verifier", display_name(path)));
+  }
+  let (nonce, ciphertext) = verifier.split_at(NONCE_LEN);
+  if open(&key, nonce, ciphertext, header.key_id.as_bytes()).as_deref() != Some(VERIFIER_TEXT) {
+    return Err(format!("Wrong password for '{}'", display_name(path)));
+  }
+  remember(path, FileKey { key_id: header.key_id, salt, params, key });
+  Ok(())
+}
+
+// Whether the board at `path` is protected by a key not unlocked this session
+pub fn needs_password(path: &Path) -> bool {
+  let mut head = Vec::new();
+  let read = std::fs::File::open(path)
+    .and_then(|file| file.take((MAGIC.len() + MAX_HEADER_LEN) as u64).read_to_end(&mut head));
+  if read.is_err() || !head.starts_with(MAGIC) {
+    return false;
+  }
+  match read_header(path, &head) {
+    Ok((header, _)) => !keys().lock().is_ok_and(|keys| keys.contains_key(&header.key_id)),
+    // Loading reports the damage
+    Err(_) => false,
+  }
+}
+
+// The plain .fim container inside the protected board at `path`, with a key
+// unlocked earlier
+pub fn decrypt_file(path: &Path) -> Result<Vec<u8>, String> {
+  let data = read_file(path)?;
+  let (header, start) = read_header(path, &data)?;
+  let key = keys().lock().ok().and_then(|keys| keys.get(&header.key_id).cloned())
+    .ok_or_else(|| format!("'{}' is password-protected; a password is required to open it", display_name(path)))?;
+  let nonce = decode(&header.nonce, "nonce", path)?;
+  let aad = &data[MAGIC.len()..start];
+  open(&key.key, &nonce, &data[start..], aad)
+    .ok_or_else(|| format!("Invalid encrypted board '{}': the archive failed its integrity check and is damaged", display_name(path)))
+}
+
+// The protected board at `path` opened as a zip archive in memory
+pub fn open_archive(path: &Path) -> Result<zip::ZipArchive<Cursor<Vec<u8>>>, String> {
+  let mut zip = zip::ZipArchive::new(Cursor::new(decrypt_file(path)?))
+    .map_err(|e| format!("Failed to read zip file '{}': {}", path.display(), e))?;
+  archive::validate_archive(&mut zip)?;
+  Ok(zip)
+}
+
+// Keep `key` for this session as the key of the board at `path`
+pub fn remember(path: &Path, key: FileKey) {
+  if let Ok(mut protected) = protected().lock() {
+    protected.insert(canonical(path), key.key_id.clone());
+  }
+  if let Ok(mut keys) = keys().lock() {
+    keys.insert(key.key_id.clone(), key);
+  }
+}
+
+// Saving the board at `path` no longer encrypts it
+pub fn forget(path: &Path) {
+  if let Ok(mut protected) = protected().lock() {
+    protected.remove(&canonical(path));
+  }
+}
+
+// The key the board at `path` is saved with, if it is protected
+pub fn key_for(path: &Path) -> Option<FileKey> {
+  let key_id = protected().lock().ok()?.get(&canonical(path))?.clone();
+  keys().lock().ok()?.get(&key_id).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A key with the cheapest Argon2 costs, so the tests stay fast
+  fn cheap_key(password: &str) -> FileKey {
+    let mut salt = vec![0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let params = (Params::MIN_M_COST, Params::MIN_T_COST, Params::MIN_P_COST);
+    let key = derive_key(password, &salt, params).unwrap();
+    let key_id = crate::audit::hash_bytes(&salt)[..16].to_string();
+    FileKey { key_id, salt, params, key }
+  }
+
+  fn write_board(name: &str, data: &[u8]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("scananas-encryption-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("board.fim");
+    std::fs::write(&path, data).unwrap();
+    path
+  }
+
+  // `data` with its header replaced by `change(header)`, body untouched
+  fn with_header(data: &[u8], change: impl FnOnce(&mut Header)) -> Vec<u8> {
+    let (mut header, start) = read_header(Path::new("board.fim"), data).unwrap();
+    change(&mut header);
+    let mut output = MAGIC.to_vec();
+    output.extend(serde_json::to_vec(&header).unwrap());
+    output.push(b'\n');
+    output.extend(&data[start..]);
+    output
+  }
+
+  #[test]
+  fn encrypted_boards_round_trip() {
+    let key = cheap_key("correct horse");
+    let encrypted = encrypt(&key, b"plain fim bytes").unwrap();
+    assert!(encrypted.starts_with(MAGIC));
+    assert!(!encrypted.windows(15).any(|window| window == b"plain fim bytes"));
+
+    let path = write_board("round-trip", &encrypted);
+    assert!(is_encrypted(&path));
+    unlock(&path, "correct horse").unwrap();
+    assert!(!needs_password(&path));
+    assert_eq!(decrypt_file(&path).unwrap(), b"plain fim bytes");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn wrong_passwords_are_rejected() {
+    let path = write_board("wrong-password", &encrypt(&cheap_key("correct horse"), b"plain fim bytes").unwrap());
+    assert!(unlock(&path, "battery staple").unwrap_err().starts_with("Wrong password"));
+    assert!(needs_password(&path));
+    assert!(decrypt_file(&path).unwrap_err().contains("a password is required"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn tampered_headers_and_bodies_are_detected() {
+    let key = cheap_key("correct horse");
+    let encrypted = encrypt(&key, b"plain fim bytes").unwrap();
+    let path = write_board("tampered", &encrypted);
+    unlock(&path, "correct horse").unwrap();
+
+    // The header is authenticated along with the body
+    let mut other_salt = vec![0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut other_salt);
+    let tampered = with_header(&encrypted, |header| {
+      header.salt = base64::engine::general_purpose::STANDARD.encode(&other_salt);
+    });
+    std::fs::write(&path, tampered).unwrap();
+    assert!(decrypt_file(&path).unwrap_err().contains("failed its integrity check"));
+
+    let mut tampered = encrypted.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    std::fs::write(&path, tampered).unwrap();
+    assert!(decrypt_file(&path).unwrap_err().contains("failed its integrity check"));
+
+    let mut tampered = encrypted.clone();
+    tampered.truncate(encrypted.len() - 4);
+    std::fs::write(&path, tampered).unwrap();
+    assert!(decrypt_file(&path).unwrap_err().contains("failed its integrity check"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn oversized_key_derivation_costs_are_refused() {
+    let encrypted = encrypt(&cheap_key("correct horse"), b"plain fim bytes").unwrap();
+    let oversized: [fn(&mut Header); 3] = [
+      |header| header.memory_kib = MAX_MEMORY_KIB + 1,
+      |header| header.iterations = MAX_ITERATIONS + 1,
+      |header| header.parallelism = MAX_PARALLELISM + 1,
+    ];
+    let path = write_board("oversized", &encrypted);
+    for change in oversized {
+      std::fs::write(&path, with_header(&encrypted, change)).unwrap();
+      assert!(unlock(&path, "correct horse").unwrap_err().starts_with("Unsupported encryption settings"));
+      assert!(decrypt_file(&path).is_err());
+    }
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn damaged_headers_are_reported() {
+    let path = write_board("damaged", b"FIMENC1\nnot json\n");
+    assert!(unlock(&path, "correct horse").unwrap_err().contains("damaged header"));
+    let path = write_board("damaged", &[MAGIC, &vec![b' '; MAX_HEADER_LEN + 1]].concat());
+    assert!(unlock(&path, "correct horse").unwrap_err().contains("damaged header"));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+  }
+}
@@ -0,0 +1,294 @@
+use serde::Serialize;
+
+use crate::{archive, tasks};
+
+// The error every command returns. It reaches the frontend as
+// `{ code, message, recoverable, hint }` (plus `window` for `already_open`
+// and `path` for `password_required`)
+// so the UI can tell a closed dialog from a permission problem from a damaged
+// file without parsing messages.
+//
+// Helpers across the backend return `Result<_, String>`; those messages are
+// classified into a code when they cross into a command (`From<String>`),
+// which keeps `?` working everywhere. Code that knows better builds the
+// variant directly.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+  // The user closed a dialog or cancelled a task; usually not worth showing
+  Cancelled { message: String },
+  // The path isn't writable or readable for us (scope or OS permissions)
+  PermissionDenied { message: String },
+  NotFound { message: String },
+  // The file is damaged or not what its extension says
+  CorruptFile { message: String },
+  // A format, version or mode we don't handle
+  Unsupported { message: String },
+  // Arguments from the frontend that don't make sense
+  InvalidInput { message: String },
+  Network { message: String },
+  // The board was opened read-only, so it can't be changed or saved over
+  ReadOnly { message: String },
+  // The file changed on disk since it was opened, so saving would lose those
+  // changes
+  Conflict { message: String },
+  // The board is open in another window, which the frontend should bring
+  // forward instead of opening a second copy
+  AlreadyOpen { message: String, window: String },
+  // The board is password-protected and its key isn't unlocked; the frontend
+  // asks for the password, unlocks `path` and tries again
+  PasswordRequired { message: String, path: Option<String> },
+  // The password doesn't open the board; distinct from a damaged file
+  WrongPassword { message: String },
+  // Reading or writing failed for another reason (disk full, ...)
+  Io { message: String },
+  Other { message: String },
+}
+
+#[derive(Serialize)]
+struct ErrorPayload<'a> {
+  code: &'a str,
+  message: &'a str,
+  // Whether trying again (possibly after the hint) can succeed
+  recoverable: bool,
+  hint: Option<&'a str>,
+  // Label of the window involved, for `already_open`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  window: Option<&'a str>,
+  // The protected board, for `password_required`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  path: Option<&'a str>,
+}
+
+impl AppError {
+  pub fn code(&self) -> &'static str {
+    match self {
+      AppError::Cancelled { .. } => "cancelled",
+      AppError::PermissionDenied { .. } => "permission_denied",
+      AppError::NotFound { .. } => "not_found",
+      AppError::CorruptFile { .. } => "corrupt_file",
+      AppError::Unsupported { .. } => "unsupported",
+      AppError::InvalidInput { .. } => "invalid_input",
+      AppError::Network { .. } => "network",
+      AppError::ReadOnly { .. } => "read_only",
+      AppError::Conflict { .. } => "conflict",
+      AppError::AlreadyOpen { .. } => "already_open",
+      AppError::PasswordRequired { .. } => "password_required",
+      AppError::WrongPassword { .. } => "wrong_password",
+      AppError::Io { .. } => "io",
+      AppError::Other { .. } => "error",
+    }
+  }
+
+  pub fn message(&self) -> &str {
+    match self {
+      AppError::Cancelled { message }
+      | AppError::PermissionDenied { message }
+      | AppError::NotFound { message }
+      | AppError::CorruptFile { message }
+      | AppError::Unsupported { message }
+      | AppError::InvalidInput { message }
+      | AppError::Network { message }
+      | AppError::ReadOnly { message }
+      | AppError::Conflict { message }
+      | AppError::AlreadyOpen { message, .. }
+      | AppError::PasswordRequired { message, .. }
+      | AppError::WrongPassword { message }
+      | AppError::Io { message }
+      | AppError::Other { message } => message,
+    }
+  }
+
+  pub fn window(&self) -> Option<&str> {
+    match self {
+      AppError::AlreadyOpen { window, .. } => Some(window),
+      _ => None,
+    }
+  }
+
+  pub fn path(&self) -> Option<&str> {
+    match self {
+      AppError::PasswordRequired { path, .. } => path.as_deref(),
+      _ => None,
+    }
+  }
+
+  pub fn recoverable(&self) -> bool {
+    !matches!(self, AppError::CorruptFile { .. } | AppError::Unsupported { .. })
+  }
+
+  pub fn hint(&self) -> Option<&'static str> {
+    match self {
+      AppError::Cancelled { .. } => None,
+      AppError::PermissionDenied { .. } => Some("Choose the location again through the file dialog, or pick a folder you can write to."),
+      AppError::NotFound { .. } => Some("The file may have been moved, renamed or deleted."),
+      AppError::CorruptFile { .. } => Some("Try opening it with repairs (open leniently) or restore it from an autosave or backup."),
+      AppError::Unsupported { .. } => Some("Check the file type, or update the application if the file comes from a newer version."),
+      AppError::InvalidInput { .. } => None,
+      AppError::Network { .. } => Some("Check your connection and try again."),
+      AppError::ReadOnly { .. } => Some("Save a copy under a new name, or reopen the board for editing."),
+      AppError::Conflict { .. } => Some("Save under a new name to keep both versions, or save anyway to replace the other changes."),
+      AppError::AlreadyOpen { .. } => Some("Switch to the window that has it open."),
+      AppError::PasswordRequired { .. } => Some("Enter the board's password to open it."),
+      AppError::WrongPassword { .. } => Some("Passwords are case-sensitive; check it and try again."),
+      AppError::Io { .. } => Some("Check that the disk isn't full and the file isn't in use, then try again."),
+      AppError::Other { .. } => None,
+    }
+  }
+}
+
+impl std::fmt::Display for AppError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(self.message())
+  }
+}
+
+impl Serialize for AppError {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    ErrorPayload {
+      code: self.code(),
+      message: self.message(),
+      recoverable: self.recoverable(),
+      hint: self.hint(),
+      window: self.window(),
+      path: self.path(),
+    }.serialize(serializer)
+  }
+}
+
+type Classify = fn(String) -> AppError;
+
+// Message fragments that identify a kind of error, checked in order. They
+// follow the wording the backend uses for these failures. Specific damaged-file
+// messages come before the generic I/O prefixes, which come before anything
+// that could also appear in a file name or an OS error text.
+const CLASSIFICATION: &[(&str, Classify)] = &[
+  ("cancelled by user", |message| AppError::Cancelled { message }),
+  ("wrong password", |message| AppError::WrongPassword { message }),
+  ("a password is required", |message| AppError::PasswordRequired { message, path: None }),
+  ("invalid encrypted board", |message| AppError::CorruptFile { message }),
+  (tasks::CANCELLED, |message| AppError::Cancelled { message }),
+  ("write access denied", |message| AppError::PermissionDenied { message }),
+  ("refusing to write through symlink", |message| AppError::PermissionDenied { message }),
+  ("permission denied", |message| AppError::PermissionDenied { message }),
+  ("os error 13)", |message| AppError::PermissionDenied { message }),
+  ("not found", |message| AppError::NotFound { message }),
+  ("no such file or directory", |message| AppError::NotFound { message }),
+  ("cannot find the file", |message| AppError::NotFound { message }),
+  ("os error 2)", |message| AppError::NotFound { message }),
+  ("unsupported", |message| AppError::Unsupported { message }),
+  ("invalid json", |message| AppError::CorruptFile { message }),
+  ("invalid .fim archive", |message| AppError::CorruptFile { message }),
+  ("failed to read zip", |message| AppError::CorruptFile { message }),
+  ("invalid or missing schema version", |message| AppError::CorruptFile { message }),
+  ("failed to read", |message| AppError::Io { message }),
+  ("failed to write", |message| AppError::Io { message }),
+  ("failed to create", |message| AppError::Io { message }),
+  ("failed to open", |message| AppError::Io { message }),
+  ("i/o error", |message| AppError::Io { message }),
+  ("already exists", |message| AppError::Conflict { message }),
+  ("failed to fetch", |message| AppError::Network { message }),
+  ("invalid url", |message| AppError::InvalidInput { message }),
+  ("must be one of", |message| AppError::InvalidInput { message }),
+];
+
+// Words too common to mean anything in the middle of a message (a path may
+// well contain "archive"), so they only count at its start
+const LEADING_CLASSIFICATION: &[(&str, Classify)] = &[
+  ("archive ", |message| AppError::CorruptFile { message }),
+  ("invalid ", |message| AppError::InvalidInput { message }),
+];
+
+// The message with its quoted parts blanked out. The backend quotes paths and
+// names ('/Users/me/not found/plan.fim'), which are the user's and may
+// contain any of the fragments above. A quote only opens after a non-letter
+// and only closes before one, so apostrophes ("can't", "John's") don't count.
+fn without_quoted(message: &str) -> String {
+  let chars: Vec<char> = message.chars().collect();
+  let mut unquoted = String::with_capacity(message.len());
+  let mut i = 0;
+  while i < chars.len() {
+    let opens = chars[i] == '\'' && (i == 0 || !chars[i - 1].is_alphanumeric());
+    let close = opens.then(|| (i + 1..chars.len())
+      .find(|&j| chars[j] == '\'' && chars.get(j + 1).map_or(true, |next| !next.is_alphanumeric())))
+      .flatten();
+    match close {
+      Some(close) => {
+        unquoted.push_str("''");
+        i = close + 1;
+      },
+      None => {
+        unquoted.push(chars[i]);
+        i += 1;
+      },
+    }
+  }
+  unquoted
+}
+
+impl From<String> for AppError {
+  fn from(message: String) -> AppError {
+    let lower = without_quoted(&message).to_lowercase();
+    let classified = CLASSIFICATION.iter().find(|(fragment, _)| lower.contains(&fragment.to_lowercase()))
+      .or_else(|| LEADING_CLASSIFICATION.iter().find(|(prefix, _)| lower.starts_with(prefix)));
+    match classified {
+      Some((_, make)) => make(message),
+      None => AppError::Other { message },
+    }
+  }
+}
+
+impl From<&str> for AppError {
+  fn from(message: &str) -> AppError {
+    AppError::from(message.to_string())
+  }
+}
+
+impl From<archive::ArchiveError> for AppError {
+  fn from(err: archive::ArchiveError) -> AppError {
+    match err {
+      archive::ArchiveError::Io { .. } => AppError::Io { message: err.to_string() },
+      _ => AppError::CorruptFile { message: err.to_string() },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn failing_to_create_the_archive_directory_is_io() {
+    let error = AppError::from("Failed to create archive directory '/data/archive': Read-only file system (os error 30)".to_string());
+    assert_eq!(error.code(), "io");
+  }
+
+  #[test]
+  fn unarchiving_over_an_existing_file_is_a_conflict() {
+    let error = AppError::from("Cannot unarchive: '/boards/Plans.fim' already exists".to_string());
+    assert_eq!(error.code(), "conflict");
+  }
+
+  #[test]
+  fn words_in_quoted_paths_are_ignored() {
+    let error = AppError::from("Failed to write '/Users/me/not found/unsupported.fim': disk full".to_string());
+    assert_eq!(error.code(), "io");
+    let error = AppError::from("Failed to read '/boards/invalid ideas.fim': Permission denied (os error 13)".to_string());
+    assert_eq!(error.code(), "permission_denied");
+    let error = AppError::from("Failed to open '/Users/me/John's archive/board.fim': unexpected end of file".to_string());
+    assert_eq!(error.code(), "io");
+  }
+
+  #[test]
+  fn words_outside_quotes_still_count() {
+    assert_eq!(AppError::from("Template 'unsupported' not found".to_string()).code(), "not_found");
+    assert_eq!(AppError::from("Unsupported share format 'not found'. Must be one of: fim, json".to_string()).code(), "unsupported");
+    assert_eq!(AppError::from("Invalid target language 'de de'".to_string()).code(), "invalid_input");
+  }
+
+  #[test]
+  fn damaged_archives_stay_corrupt() {
+    assert_eq!(AppError::from("Failed to read zip archive 'a.zip': invalid Zip archive".to_string()).code(), "corrupt_file");
+    assert_eq!(AppError::from("Archive entry 'board.json' has an unsafe path (absolute or containing '..')".to_string()).code(), "corrupt_file");
+  }
+}
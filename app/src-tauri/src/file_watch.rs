@@ -0,0 +1,113 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+// Noticing when the open board's file changes on disk behind our back, e.g.
+// a sync client bringing in edits made on another machine. The folder is
+// watched rather than the file, since sync clients and our own saves replace
+// the file by renaming a new one over it. Bursts of events are collapsed into
+// one callback once the folder has been quiet for a moment.
+//
+// Whether the file really changed is decided by its stamp: a different
+// modification time or size only counts when the content hash differs too,
+// so touching the file or rewriting the same bytes isn't a conflict.
+
+// Quiet time before a burst of events is reported
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const HASH_CHUNK_SIZE: usize = 256 * 1024;
+
+// A file as it was when we last read or wrote it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileStamp {
+  pub modified: Option<SystemTime>,
+  pub size: u64,
+  hash: String,
+}
+
+fn hash_file(path: &Path) -> Option<String> {
+  let mut file = std::fs::File::open(path).ok()?;
+  let mut hasher = Sha256::new();
+  let mut buffer = vec![0u8; HASH_CHUNK_SIZE];
+  loop {
+    match file.read(&mut buffer).ok()? {
+      0 => break,
+      read => hasher.update(&buffer[..read]),
+    }
+  }
+  Some(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+impl FileStamp {
+  // None when the file doesn't exist (yet)
+  pub fn read(path: &Path) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(FileStamp { modified: metadata.modified().ok(), size: metadata.len(), hash: hash_file(path)? })
+  }
+}
+
+// Whether the file at `path` differs from `known`. A file that is gone
+// hasn't changed in a way saving could lose; one that appeared since has.
+pub fn changed(known: Option<&FileStamp>, path: &Path) -> bool {
+  let Ok(metadata) = std::fs::metadata(path) else {
+    return false;
+  };
+  let Some(known) = known else {
+    return true;
+  };
+  if metadata.modified().ok() == known.modified && metadata.len() == known.size {
+    return false;
+  }
+  hash_file(path).is_some_and(|hash| hash != known.hash)
+}
+
+// Watches one file until dropped
+pub struct DocumentWatcher {
+  path: PathBuf,
+  _watcher: notify::RecommendedWatcher,
+}
+
+impl std::fmt::Debug for DocumentWatcher {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("DocumentWatcher").field("path", &self.path).finish()
+  }
+}
+
+impl DocumentWatcher {
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+}
+
+// Call `on_change` on a background thread whenever the file at `path` may
+// have been created, changed, replaced or removed; the callback compares
+// stamps to find out whether it really was
+pub fn watch(path: &Path, mut on_change: impl FnMut() + Send + 'static) -> Result<DocumentWatcher, String> {
+  let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+  let name = path.file_name().ok_or_else(|| format!("Cannot watch '{}': not a file", path.display()))?.to_os_string();
+
+  let (sender, receiver) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+    let Ok(event) = event else {
+      return;
+    };
+    let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) | EventKind::Any);
+    if relevant && event.paths.iter().any(|p| p.file_name() == Some(name.as_os_str())) {
+      let _ = sender.send(());
+    }
+  })
+    .map_err(|e| format!("Failed to watch '{}': {}", path.display(), e))?;
+  watcher.watch(dir, RecursiveMode::NonRecursive)
+    .map_err(|e| format!("Failed to watch '{}': {}", path.display(), e))?;
+
+  // Ends when the watcher is dropped, which drops the sender
+  std::thread::spawn(move || {
+    while receiver.recv().is_ok() {
+      while let Ok(()) = receiver.recv_timeout(DEBOUNCE) {}
+      on_change();
+    }
+  });
+  Ok(DocumentWatcher { path: path.to_path_buf(), _watcher: watcher })
+}
@@ -0,0 +1,220 @@
+use resvg::usvg::fontdb;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use crate::{archive, encryption, model};
+
+// Font embedding. Text styles name their font, so a board opened on a machine
+// without it falls back to something else. With embedding on, saving copies
+// the font files the board's styles use into `fonts/` in the .fim container,
+// as long as the font's license (the OS/2 `fsType` flags) allows embedding.
+// Opening the board registers them for rendering. Fonts already embedded are
+// kept when the board is saved on a machine that lacks them.
+
+pub const FONTS_DIR: &str = "fonts/";
+// Font files larger than this are left out (CJK families can be huge)
+const MAX_FONT_SIZE: u64 = 32 * 1024 * 1024;
+// Families every renderer resolves on its own
+const GENERIC_FAMILIES: &[&str] = &["serif", "sans-serif", "monospace", "cursive", "fantasy", "system-ui"];
+
+pub struct FontFile {
+  // File name inside `fonts/`
+  pub name: String,
+  pub data: Vec<u8>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SkippedFont {
+  pub family: String,
+  pub reason: String,
+}
+
+// Installed fonts, loaded once
+pub fn system_database() -> Arc<fontdb::Database> {
+  static FONTS: OnceLock<Arc<fontdb::Database>> = OnceLock::new();
+  FONTS.get_or_init(|| {
+    let mut fonts = fontdb::Database::new();
+    fonts.load_system_fonts();
+    // fontdb maps `sans-serif` to Arial, which most Linux installs lack; fall
+    // back to whatever is installed so text still renders
+    let query = fontdb::Query { families: &[fontdb::Family::SansSerif], ..Default::default() };
+    if fonts.query(&query).is_none() {
+      let families: Vec<String> = fonts.faces().filter_map(|face| face.families.first().map(|(name, _)| name.clone())).collect();
+      let fallback = families.iter().find(|name| name.contains("Sans") && !name.contains("Mono")).or(families.first()).cloned();
+      if let Some(family) = fallback {
+        fonts.set_sans_serif_family(family);
+      }
+    }
+    Arc::new(fonts)
+  }).clone()
+}
+
+// Font families the board's note styles use, generic families left out
+pub fn used_fonts(doc: &model::BoardDocument) -> Vec<String> {
+  let mut seen = HashSet::new();
+  doc.note_styles.iter()
+    .map(|style| style.text_style.font.trim())
+    .filter(|font| !font.is_empty() && !GENERIC_FAMILIES.contains(&font.to_lowercase().as_str()))
+    .filter(|font| seen.insert(font.to_lowercase()))
+    .map(str::to_string)
+    .collect()
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+  Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+  Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+// The OS/2 table's `fsType` for face `index` of a font file (or collection)
+fn fs_type(data: &[u8], index: u32) -> Option<u16> {
+  let start = if data.starts_with(b"ttcf") {
+    read_u32(data, 12 + 4 * index as usize)? as usize
+  } else {
+    0
+  };
+  let tables = read_u16(data, start + 4)? as usize;
+  (0..tables)
+    .map(|i| start + 12 + 16 * i)
+    .find(|&record| data.get(record..record + 4) == Some(b"OS/2".as_slice()))
+    .and_then(|record| read_u32(data, record + 8))
+    .and_then(|table| read_u16(data, table as usize + 8))
+}
+
+// Embedding is allowed unless the font is "restricted license" or only
+// allows embedding its bitmaps. Fonts without an OS/2 table are installable.
+pub fn embedding_allowed(data: &[u8], index: u32) -> bool {
+  match fs_type(data, index) {
+    Some(flags) => flags & 0x000f != 0x0002 && flags & 0x0200 == 0,
+    None => true,
+  }
+}
+
+// Family names a font file declares
+fn families(data: &[u8]) -> Vec<String> {
+  let mut database = fontdb::Database::new();
+  database.load_font_data(data.to_vec());
+  database.faces().flat_map(|face| face.families.iter().map(|(name, _)| name.clone())).collect()
+}
+
+fn face_path(face: &fontdb::FaceInfo) -> Option<&Path> {
+  match &face.source {
+    fontdb::Source::File(path) | fontdb::Source::SharedFile(path, _) => Some(path),
+    fontdb::Source::Binary(_) => None,
+  }
+}
+
+// Font files to embed for the board: installed files for each family used,
+// falling back to the ones `previous` (the board's earlier save) embedded
+pub fn collect(doc: &model::BoardDocument, previous: Vec<FontFile>) -> (Vec<FontFile>, Vec<SkippedFont>) {
+  let system = system_database();
+  let mut files: Vec<FontFile> = Vec::new();
+  let mut skipped = Vec::new();
+  let mut read_paths = HashSet::new();
+  let mut previous: Vec<Option<FontFile>> = previous.into_iter().map(Some).collect();
+
+  for family in used_fonts(doc) {
+    let matches = |name: &String| name.eq_ignore_ascii_case(&family);
+    let faces: Vec<&fontdb::FaceInfo> = system.faces().filter(|face| face.families.iter().any(|(name, _)| matches(name))).collect();
+    let mut found = false;
+    let mut restricted = false;
+    for face in faces {
+      let Some(path) = face_path(face) else {
+        continue;
+      };
+      if !read_paths.insert(path.to_path_buf()) {
+        found = true;
+        continue;
+      }
+      let too_large = std::fs::metadata(path).map_or(true, |m| m.len() > MAX_FONT_SIZE);
+      let Some(data) = (!too_large).then(|| std::fs::read(path).ok()).flatten() else {
+        continue;
+      };
+      if !embedding_allowed(&data, face.index) {
+        restricted = true;
+        continue;
+      }
+      let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| format!("{}.ttf", family));
+      let name = if files.iter().any(|f| f.name == file_name) { format!("{}-{}", files.len(), file_name) } else { file_name };
+      files.push(FontFile { name, data });
+      found = true;
+    }
+
+    if !found {
+      // Not installed here (or not embeddable): keep what the board carried
+      for slot in previous.iter_mut() {
+        if slot.as_ref().is_some_and(|file| families(&file.data).iter().any(matches)) {
+          files.extend(slot.take());
+          found = true;
+        }
+      }
+    }
+    if !found {
+      let reason = if restricted { "The font's license doesn't allow embedding" } else { "The font isn't installed" };
+      skipped.push(SkippedFont { family, reason: reason.to_string() });
+    }
+  }
+  (files, skipped)
+}
+
+// Fonts embedded in a .fim container; none for other files
+pub fn read_embedded(path: &Path) -> Vec<FontFile> {
+  // Password-protected boards only while their key is unlocked
+  if encryption::is_encrypted(path) {
+    return encryption::open_archive(path).map(|mut zip| read_embedded_from(&mut zip)).unwrap_or_default();
+  }
+  let Ok(file) = std::fs::File::open(path) else {
+    return Vec::new();
+  };
+  let Ok(mut zip) = zip::ZipArchive::new(file) else {
+    return Vec::new();
+  };
+  if archive::validate_archive(&mut zip).is_err() {
+    return Vec::new();
+  }
+  read_embedded_from(&mut zip)
+}
+
+fn read_embedded_from<R: Read + Seek>(zip: &mut zip::ZipArchive<R>) -> Vec<FontFile> {
+  let names: Vec<String> = zip.file_names()
+    .filter(|name| name.starts_with(FONTS_DIR) && name.len() > FONTS_DIR.len())
+    .map(str::to_string)
+    .collect();
+  names.into_iter()
+    .filter_map(|name| {
+      let entry = zip.by_name(&name).ok()?;
+      if entry.size() > MAX_FONT_SIZE {
+        return None;
+      }
+      let mut data = Vec::new();
+      entry.take(MAX_FONT_SIZE).read_to_end(&mut data).ok()?;
+      Some(FontFile { name: name[FONTS_DIR.len()..].to_string(), data })
+    })
+    .collect()
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EmbeddedFont {
+  pub family: String,
+  pub file: String,
+  // Base64 font data for the frontend's FontFace
+  pub data: String,
+}
+
+// One entry per family a font file declares
+pub fn describe(files: &[FontFile]) -> Vec<EmbeddedFont> {
+  use base64::Engine;
+  files.iter()
+    .flat_map(|file| {
+      let data = base64::engine::general_purpose::STANDARD.encode(&file.data);
+      let mut names = families(&file.data);
+      names.dedup();
+      names.into_iter().map(move |family| EmbeddedFont { family, file: file.name.clone(), data: data.clone() })
+    })
+    .collect()
+}
@@ -0,0 +1,372 @@
+use std::path::Path;
+
+use lopdf::Object;
+use serde::Serialize;
+
+use crate::{markings, media, model};
+
+// Apple Freeform boards can't be read directly, so migration goes through
+// what Freeform can export: PDFs and images. Each PDF page becomes a region
+// of the board with its embedded pictures as positioned image notes and its
+// text gathered into one note beside it; exported images become image notes
+// of their own. Vector drawing (shapes, pen strokes, sticky backgrounds)
+// isn't rasterized and is reported as a warning.
+
+// PDF points to board units (CSS pixels)
+const POINTS_TO_UNITS: f64 = 96.0 / 72.0;
+const ASSET_GAP: f64 = 80.0;
+const TEXT_NOTE_WIDTH: f64 = 320.0;
+const TEXT_LINE_HEIGHT: f64 = 20.0;
+// Form XObjects can nest; deeper trees are almost certainly malformed
+const MAX_FORM_DEPTH: usize = 8;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FreeformImport {
+  pub notes: Vec<model::Note>,
+  pub images: Vec<model::EmbeddedImage>,
+  pub warnings: Vec<String>,
+}
+
+// Affine transform [a b c d e f], as in PDF
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+// `m` applied first, then `n`
+fn multiply(m: &Matrix, n: &Matrix) -> Matrix {
+  [
+    m[0] * n[0] + m[1] * n[2],
+    m[0] * n[1] + m[1] * n[3],
+    m[2] * n[0] + m[3] * n[2],
+    m[2] * n[1] + m[3] * n[3],
+    m[4] * n[0] + m[5] * n[2] + n[4],
+    m[4] * n[1] + m[5] * n[3] + n[5],
+  ]
+}
+
+fn number(object: &Object) -> Option<f64> {
+  object.as_float().map(f64::from).or_else(|_| object.as_i64().map(|i| i as f64)).ok()
+}
+
+fn matrix_from(operands: &[Object]) -> Option<Matrix> {
+  let values: Vec<f64> = operands.iter().map(number).collect::<Option<_>>()?;
+  values.try_into().ok()
+}
+
+fn resolve<'a>(doc: &'a lopdf::Document, object: &'a Object) -> Option<&'a Object> {
+  doc.dereference(object).ok().map(|(_, object)| object)
+}
+
+fn note(text: String, frame: model::Rect) -> model::Note {
+  model::Note {
+    id: model::new_id("note"),
+    text,
+    content_format: None,
+    spans: None,
+    rich_attrs: None,
+    frame,
+    style_id: None,
+    faded: None,
+    stack_id: None,
+    links: None,
+    images: None,
+    connections: None,
+    z_index: None,
+    locked: None,
+    layer_id: None,
+    checklist: None,
+    labels: None,
+    tags: None,
+    table: None,
+    icon: None,
+    properties: None,
+    starred: None,
+    created_at: Some(chrono::Utc::now()),
+    updated_at: None,
+    confidential: None,
+    sealed_text: None,
+  }
+}
+
+fn image_note(image: &model::EmbeddedImage, frame: model::Rect) -> model::Note {
+  model::Note { images: Some(vec![image.id.clone()]), ..note(String::new(), frame) }
+}
+
+// Components per pixel of an image's color space, for the spaces we decode
+fn color_components(doc: &lopdf::Document, color_space: Option<&Object>) -> Option<usize> {
+  let color_space = resolve(doc, color_space?)?;
+  if let Ok(name) = color_space.as_name() {
+    return match name {
+      b"DeviceRGB" => Some(3),
+      b"DeviceGray" => Some(1),
+      _ => None,
+    };
+  }
+  // [/ICCBased stream]: the profile says how many components there are
+  let array = color_space.as_array().ok()?;
+  if array.first()?.as_name().ok()? != b"ICCBased" {
+    return None;
+  }
+  let profile = resolve(doc, array.get(1)?)?.as_stream().ok()?;
+  match profile.dict.get(b"N").ok().and_then(number)? as usize {
+    n @ (1 | 3) => Some(n),
+    _ => None,
+  }
+}
+
+// Raw samples of an uncompressed or Flate-compressed image. lopdf refuses to
+// decompress image streams on its own, so decode a copy that doesn't say it's
+// an image.
+fn image_samples(stream: &lopdf::Stream) -> Option<Vec<u8>> {
+  let mut plain = stream.clone();
+  plain.dict.remove(b"Subtype");
+  plain.get_plain_content().ok()
+}
+
+// Image XObject to PNG or JPEG bytes
+fn decode_image(doc: &lopdf::Document, stream: &lopdf::Stream) -> Result<Vec<u8>, String> {
+  let filters = stream.filters().unwrap_or_default();
+  if filters.iter().any(|f| f == "DCTDecode") {
+    // The stream is the JPEG file itself
+    return Ok(stream.content.clone());
+  }
+  if filters.iter().any(|f| f != "FlateDecode") {
+    return Err(format!("unsupported image encoding ({})", filters.join(", ")));
+  }
+
+  let dict = &stream.dict;
+  let width = dict.get(b"Width").ok().and_then(number).unwrap_or(0.0) as usize;
+  let height = dict.get(b"Height").ok().and_then(number).unwrap_or(0.0) as usize;
+  let bits = dict.get(b"BitsPerComponent").ok().and_then(number).unwrap_or(8.0) as usize;
+  let components = color_components(doc, dict.get(b"ColorSpace").ok())
+    .ok_or("unsupported image color space")?;
+  if width == 0 || height == 0 || bits != 8 {
+    return Err("unsupported image layout".to_string());
+  }
+  let samples = image_samples(stream).ok_or("image data couldn't be decompressed")?;
+  if samples.len() < width * height * components {
+    return Err("image data is truncated".to_string());
+  }
+
+  // Transparency comes as a separate grayscale soft mask of the same size
+  let alpha = dict.get(b"SMask").ok()
+    .and_then(|mask| resolve(doc, mask))
+    .and_then(|mask| mask.as_stream().ok())
+    .and_then(image_samples)
+    .filter(|mask| mask.len() >= width * height);
+
+  let mut rgba = Vec::with_capacity(width * height * 4);
+  for pixel in 0..width * height {
+    let sample = &samples[pixel * components..(pixel + 1) * components];
+    match sample {
+      [gray] => rgba.extend_from_slice(&[*gray, *gray, *gray]),
+      _ => rgba.extend_from_slice(&sample[..3]),
+    }
+    rgba.push(alpha.as_ref().map(|mask| mask[pixel]).unwrap_or(255));
+  }
+  media::encode_png_rgba(&rgba, width as u32, height as u32)
+}
+
+struct PageWalker<'a> {
+  doc: &'a lopdf::Document,
+  // Board position of the page's top-left corner, and its height in points
+  left: f64,
+  top: f64,
+  page_height: f64,
+  notes: Vec<model::Note>,
+  images: Vec<model::EmbeddedImage>,
+  warnings: Vec<String>,
+  page: u32,
+}
+
+impl PageWalker<'_> {
+  // Board frame of the unit square under `ctm`; PDF's y axis points up
+  fn frame_for(&self, ctm: &Matrix) -> model::Rect {
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+      .map(|(x, y)| (x * ctm[0] + y * ctm[2] + ctm[4], x * ctm[1] + y * ctm[3] + ctm[5]));
+    let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+    let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+    let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max);
+    model::Rect {
+      x: self.left + min_x * POINTS_TO_UNITS,
+      y: self.top + (self.page_height - max_y) * POINTS_TO_UNITS,
+      w: ((max_x - min_x) * POINTS_TO_UNITS).max(1.0),
+      h: ((max_y - min_y) * POINTS_TO_UNITS).max(1.0),
+    }
+  }
+
+  fn place_image(&mut self, stream: &lopdf::Stream, ctm: &Matrix) {
+    let result = decode_image(self.doc, stream).and_then(|data| media::image_from_bytes(&data));
+    match result {
+      Ok(image) => {
+        self.notes.push(image_note(&image, self.frame_for(ctm)));
+        if !self.images.iter().any(|i| i.id == image.id) {
+          self.images.push(image);
+        }
+      },
+      Err(e) => self.warnings.push(format!("Page {}: skipped an image: {}", self.page, e)),
+    }
+  }
+
+  fn walk(&mut self, content: &[u8], resources: Option<&lopdf::Dictionary>, base: Matrix, depth: usize) {
+    let operations = match lopdf::content::Content::decode(content) {
+      Ok(content) => content.operations,
+      Err(e) => {
+        self.warnings.push(format!("Page {}: couldn't read the page content: {}", self.page, e));
+        return;
+      },
+    };
+    let xobjects = resources
+      .and_then(|r| r.get(b"XObject").ok())
+      .and_then(|x| resolve(self.doc, x))
+      .and_then(|x| x.as_dict().ok());
+
+    let mut ctm = base;
+    let mut saved = Vec::new();
+    for operation in operations {
+      match operation.operator.as_str() {
+        "q" => saved.push(ctm),
+        "Q" => ctm = saved.pop().unwrap_or(base),
+        "cm" => if let Some(m) = matrix_from(&operation.operands) {
+          ctm = multiply(&m, &ctm);
+        },
+        "Do" => {
+          let Some(stream) = operation.operands.first()
+            .and_then(|name| name.as_name().ok())
+            .and_then(|name| xobjects?.get(name).ok())
+            .and_then(|x| resolve(self.doc, x))
+            .and_then(|x| x.as_stream().ok())
+          else {
+            continue;
+          };
+          match stream.dict.get(b"Subtype").and_then(Object::as_name) {
+            Ok(b"Image") => self.place_image(stream, &ctm),
+            Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+              let matrix = stream.dict.get(b"Matrix").ok()
+                .and_then(|m| m.as_array().ok())
+                .and_then(|m| matrix_from(m))
+                .unwrap_or(IDENTITY);
+              let form_resources = stream.dict.get(b"Resources").ok()
+                .and_then(|r| resolve(self.doc, r))
+                .and_then(|r| r.as_dict().ok())
+                .or(resources);
+              let content = stream.get_plain_content().unwrap_or_default();
+              self.walk(&content, form_resources, multiply(&matrix, &ctm), depth + 1);
+            },
+            _ => {},
+          }
+        },
+        _ => {},
+      }
+    }
+  }
+}
+
+// Lay the pages of an exported PDF out top to bottom from `origin`; returns
+// the bottom edge of the last page
+fn import_pdf(data: &[u8], name: &str, origin: &model::Point, result: &mut FreeformImport) -> Result<f64, String> {
+  let doc = lopdf::Document::load_mem(data)
+    .map_err(|e| format!("Failed to read PDF '{}': {}", name, e))?;
+  let pages = doc.get_pages();
+  if pages.is_empty() {
+    return Err(format!("PDF '{}' has no pages", name));
+  }
+
+  let mut top = origin.y;
+  for (number, page_id) in pages {
+    let (width, height) = markings::media_box(&doc, page_id)?;
+    let mut walker = PageWalker {
+      doc: &doc,
+      left: origin.x,
+      top,
+      page_height: height,
+      notes: Vec::new(),
+      images: Vec::new(),
+      warnings: Vec::new(),
+      page: number,
+    };
+    let (resources, inherited) = doc.get_page_resources(page_id)
+      .map_err(|e| format!("Invalid PDF page in '{}': {}", name, e))?;
+    let resources = resources.or_else(|| inherited.first().and_then(|id| doc.get_dictionary(*id).ok()));
+    match doc.get_page_content(page_id) {
+      Ok(content) => walker.walk(&content, resources, IDENTITY, 0),
+      Err(e) => walker.warnings.push(format!("Page {}: couldn't read the page content: {}", number, e)),
+    }
+    if walker.notes.is_empty() {
+      walker.warnings.push(format!(
+        "Page {} of '{}' has no embedded pictures; its drawing isn't imported (export the board as an image to keep it)",
+        number, name
+      ));
+    }
+
+    let page_width = width * POINTS_TO_UNITS;
+    let text = doc.extract_text(&[number]).unwrap_or_default();
+    let text = text.lines().map(str::trim).filter(|l| !l.is_empty()).collect::<Vec<_>>().join("\n");
+    if !text.is_empty() {
+      let lines = text.lines().count() as f64;
+      walker.notes.push(note(text, model::Rect {
+        x: origin.x + page_width + ASSET_GAP,
+        y: top,
+        w: TEXT_NOTE_WIDTH,
+        h: (lines + 1.0) * TEXT_LINE_HEIGHT,
+      }));
+    }
+
+    result.notes.append(&mut walker.notes);
+    for image in walker.images {
+      if !result.images.iter().any(|i| i.id == image.id) {
+        result.images.push(image);
+      }
+    }
+    result.warnings.append(&mut walker.warnings);
+    top += height * POINTS_TO_UNITS + ASSET_GAP;
+  }
+  Ok(top - ASSET_GAP)
+}
+
+// Import exported PDFs and images, one below the other from `origin`.
+// Files that fail are reported in the warnings and the rest still import.
+// `on_asset(index, total)` is called before each file is read; an error from
+// it stops the import
+pub fn import_assets(
+  paths: &[&Path],
+  origin: Option<model::Point>,
+  on_asset: &mut dyn FnMut(usize, usize) -> Result<(), String>,
+) -> Result<FreeformImport, String> {
+  let origin = origin.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  let mut result = FreeformImport { notes: Vec::new(), images: Vec::new(), warnings: Vec::new() };
+  let mut top = origin.y;
+
+  for (index, path) in paths.iter().enumerate() {
+    on_asset(index, paths.len())?;
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let is_pdf = path.extension().and_then(|e| e.to_str()).map(|e| e.eq_ignore_ascii_case("pdf")).unwrap_or(false);
+    let imported = if is_pdf {
+      std::fs::read(path)
+        .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))
+        .and_then(|data| import_pdf(&data, &name, &model::Point { x: origin.x, y: top }, &mut result))
+    } else {
+      media::import_image_file(path).map(|image| {
+        let frame = model::Rect { x: origin.x, y: top, w: image.width, h: image.height };
+        result.notes.push(image_note(&image, frame));
+        if !result.images.iter().any(|i| i.id == image.id) {
+          result.images.push(image.clone());
+        }
+        top + image.height
+      })
+    };
+    match imported {
+      Ok(bottom) => top = bottom + ASSET_GAP,
+      Err(e) => result.warnings.push(e),
+    }
+  }
+
+  if result.notes.is_empty() {
+    return Err(match result.warnings.first() {
+      Some(reason) => format!("Nothing could be imported: {}", reason),
+      None => "Nothing could be imported".to_string(),
+    });
+  }
+  Ok(result)
+}
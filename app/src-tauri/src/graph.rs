@@ -0,0 +1,474 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{csv, model, shapes};
+
+// Structure of the connection graph: degrees, centrality and connected
+// components, to find the notes a map actually revolves around. Connections
+// are treated as undirected for centrality and components (arrows in a map
+// are often decorative); in/out degrees keep the direction. Self-connections
+// and repeated connections between the same two notes count once. Cycle and
+// orphan checks audit the structure before outline exports.
+
+// Simple paths can be exponentially many on dense boards
+const MAX_PATH_ALTERNATIVES: usize = 10;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GraphReport {
+  pub notes: usize,
+  pub connections: usize,
+  // Share of possible note pairs that are connected
+  pub density: f64,
+  pub degree_distribution: Vec<DegreeCount>,
+  // Largest first
+  pub components: Vec<Vec<model::ID>>,
+  // Most central first (betweenness, then degree)
+  pub metrics: Vec<NoteMetrics>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DegreeCount {
+  pub degree: usize,
+  pub notes: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NoteMetrics {
+  pub id: model::ID,
+  // First line of the note's text
+  pub label: String,
+  pub in_degree: usize,
+  pub out_degree: usize,
+  pub degree: usize,
+  // Normalized to 0..1: share of shortest paths between other notes that
+  // pass through this one
+  pub betweenness: f64,
+  // Normalized to 0..1 and scaled by the share of notes reachable, so
+  // notes in small components don't score as if they were central
+  pub closeness: f64,
+  // Index into `components`
+  pub component: usize,
+}
+
+// Undirected adjacency by note index, without self-loops or duplicates
+pub struct Graph<'a> {
+  pub ids: Vec<&'a str>,
+  pub index: HashMap<&'a str, usize>,
+  pub neighbors: Vec<Vec<usize>>,
+}
+
+impl<'a> Graph<'a> {
+  pub fn new(doc: &'a model::BoardDocument) -> Self {
+    let ids: Vec<&str> = doc.notes.iter().map(|n| n.id.as_str()).collect();
+    let index: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut edges = HashSet::new();
+    for c in &doc.connections {
+      let (Some(&a), Some(&b)) = (index.get(c.src_note_id.as_str()), index.get(c.dst_note_id.as_str())) else {
+        continue;
+      };
+      if a != b {
+        edges.insert((a.min(b), a.max(b)));
+      }
+    }
+    let mut neighbors = vec![Vec::new(); ids.len()];
+    let mut edges: Vec<_> = edges.into_iter().collect();
+    // Sorted so traversal order (and ties) doesn't depend on hashing
+    edges.sort_unstable();
+    for (a, b) in edges {
+      neighbors[a].push(b);
+      neighbors[b].push(a);
+    }
+    Graph { ids, index, neighbors }
+  }
+
+  pub fn edge_count(&self) -> usize {
+    self.neighbors.iter().map(Vec::len).sum::<usize>() / 2
+  }
+
+  // Component index per note, components numbered largest first
+  pub fn components(&self) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let mut component = vec![usize::MAX; self.ids.len()];
+    let mut components = Vec::new();
+    for start in 0..self.ids.len() {
+      if component[start] != usize::MAX {
+        continue;
+      }
+      let mut members = vec![start];
+      component[start] = components.len();
+      let mut i = 0;
+      while i < members.len() {
+        for &next in &self.neighbors[members[i]] {
+          if component[next] == usize::MAX {
+            component[next] = components.len();
+            members.push(next);
+          }
+        }
+        i += 1;
+      }
+      members.sort_unstable();
+      components.push(members);
+    }
+
+    let mut order: Vec<usize> = (0..components.len()).collect();
+    order.sort_by_key(|&c| (std::cmp::Reverse(components[c].len()), components[c][0]));
+    let mut renumber = vec![0; components.len()];
+    for (new, &old) in order.iter().enumerate() {
+      renumber[old] = new;
+    }
+    let component = component.into_iter().map(|c| renumber[c]).collect();
+    let components = order.into_iter().map(|c| std::mem::take(&mut components[c])).collect();
+    (component, components)
+  }
+}
+
+fn label(note: &model::Note) -> String {
+  note.text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default().to_string()
+}
+
+// Brandes' algorithm, also collecting distance sums for closeness
+fn centrality(graph: &Graph) -> (Vec<f64>, Vec<f64>) {
+  let n = graph.ids.len();
+  let mut betweenness = vec![0.0; n];
+  let mut closeness = vec![0.0; n];
+  for source in 0..n {
+    let mut order = Vec::new();
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut paths = vec![0.0; n];
+    let mut distance: Vec<Option<usize>> = vec![None; n];
+    paths[source] = 1.0;
+    distance[source] = Some(0);
+    let mut queue = VecDeque::from([source]);
+    while let Some(v) = queue.pop_front() {
+      order.push(v);
+      let d = distance[v].unwrap_or_default();
+      for &w in &graph.neighbors[v] {
+        if distance[w].is_none() {
+          distance[w] = Some(d + 1);
+          queue.push_back(w);
+        }
+        if distance[w] == Some(d + 1) {
+          paths[w] += paths[v];
+          predecessors[w].push(v);
+        }
+      }
+    }
+
+    let reached = order.len() - 1;
+    let total: usize = distance.iter().flatten().sum();
+    if total > 0 && n > 1 {
+      closeness[source] = (reached as f64 / total as f64) * (reached as f64 / (n - 1) as f64);
+    }
+
+    let mut dependency = vec![0.0; n];
+    for &w in order.iter().rev() {
+      for &v in &predecessors[w] {
+        dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+      }
+      if w != source {
+        betweenness[w] += dependency[w];
+      }
+    }
+  }
+
+  // Every pair was counted from both ends
+  if n > 2 {
+    let pairs = ((n - 1) * (n - 2)) as f64;
+    for b in &mut betweenness {
+      *b /= pairs;
+    }
+  }
+  (betweenness, closeness)
+}
+
+pub fn analyze(doc: &model::BoardDocument) -> GraphReport {
+  let graph = Graph::new(doc);
+  let n = graph.ids.len();
+  let (component, components) = graph.components();
+  let (betweenness, closeness) = centrality(&graph);
+
+  let mut in_degree = vec![0; n];
+  let mut out_degree = vec![0; n];
+  for c in doc.connections.iter().filter(|c| c.src_note_id != c.dst_note_id) {
+    if let (Some(&src), Some(&dst)) = (graph.index.get(c.src_note_id.as_str()), graph.index.get(c.dst_note_id.as_str())) {
+      out_degree[src] += 1;
+      in_degree[dst] += 1;
+    }
+  }
+
+  let mut metrics: Vec<NoteMetrics> = doc.notes.iter().enumerate()
+    .map(|(i, note)| NoteMetrics {
+      id: note.id.clone(),
+      label: label(note),
+      in_degree: in_degree[i],
+      out_degree: out_degree[i],
+      degree: graph.neighbors[i].len(),
+      betweenness: betweenness[i],
+      closeness: closeness[i],
+      component: component[i],
+    })
+    .collect();
+  // Stable, so equally central notes keep document order
+  metrics.sort_by(|a, b| b.betweenness.total_cmp(&a.betweenness).then(b.degree.cmp(&a.degree)));
+
+  let mut distribution: HashMap<usize, usize> = HashMap::new();
+  for neighbors in &graph.neighbors {
+    *distribution.entry(neighbors.len()).or_default() += 1;
+  }
+  let mut degree_distribution: Vec<DegreeCount> = distribution.into_iter()
+    .map(|(degree, notes)| DegreeCount { degree, notes })
+    .collect();
+  degree_distribution.sort_by_key(|d| d.degree);
+
+  let edges = graph.edge_count();
+  GraphReport {
+    notes: n,
+    connections: edges,
+    density: if n > 1 { 2.0 * edges as f64 / (n * (n - 1)) as f64 } else { 0.0 },
+    degree_distribution,
+    components: components.iter()
+      .map(|members| members.iter().map(|&i| graph.ids[i].to_string()).collect())
+      .collect(),
+    metrics,
+  }
+}
+
+// The whole structure of a board at once, for an analysis panel that would
+// otherwise make three calls over the same large board
+#[derive(Serialize, Debug, Clone)]
+pub struct DocumentAnalysis {
+  #[serde(flatten)]
+  pub report: GraphReport,
+  // See find_orphans
+  pub orphans: Vec<model::ID>,
+  // See find_cycles
+  pub cycles: Vec<Cycle>,
+}
+
+pub fn analyze_document(doc: &model::BoardDocument) -> DocumentAnalysis {
+  DocumentAnalysis {
+    report: analyze(doc),
+    orphans: find_orphans(doc),
+    cycles: find_cycles(doc),
+  }
+}
+
+// One row per note, in the report's order
+pub fn to_csv(report: &GraphReport) -> String {
+  let mut rows = vec![
+    ["id", "label", "degree", "in_degree", "out_degree", "betweenness", "closeness", "component"]
+      .iter().map(|h| h.to_string()).collect::<Vec<_>>(),
+  ];
+  rows.extend(report.metrics.iter().map(|m| vec![
+    m.id.clone(),
+    m.label.clone(),
+    m.degree.to_string(),
+    m.in_degree.to_string(),
+    m.out_degree.to_string(),
+    format!("{:.6}", m.betweenness),
+    format!("{:.6}", m.closeness),
+    m.component.to_string(),
+  ]));
+  csv::write(&rows, ',')
+}
+
+// A directed cycle: `note_ids[i]` connects to `note_ids[i + 1]` through
+// `connection_ids[i]`, and the last note back to the first
+#[derive(Serialize, Debug, Clone)]
+pub struct Cycle {
+  pub note_ids: Vec<model::ID>,
+  pub connection_ids: Vec<model::ID>,
+}
+
+// Cycles that hierarchical exports have to cut. Enumerating every cycle can
+// blow up on dense maps, so this reports one per back edge of a depth-first
+// search: removing (or reversing) the last connection of each cycle leaves
+// the board acyclic. Self-connections are cycles of one note.
+pub fn find_cycles(doc: &model::BoardDocument) -> Vec<Cycle> {
+  let index: HashMap<&str, usize> = doc.notes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+  let mut outgoing: Vec<Vec<(usize, &model::Connection)>> = vec![Vec::new(); doc.notes.len()];
+  for c in &doc.connections {
+    if let (Some(&src), Some(&dst)) = (index.get(c.src_note_id.as_str()), index.get(c.dst_note_id.as_str())) {
+      outgoing[src].push((dst, c));
+    }
+  }
+
+  // 0: unvisited, 1: on the current path, 2: done
+  let mut state = vec![0u8; doc.notes.len()];
+  let mut cycles = Vec::new();
+  for root in 0..doc.notes.len() {
+    if state[root] != 0 {
+      continue;
+    }
+    // Current path: note and the connection that led to it; plus the next
+    // outgoing connection to look at for each note on the path
+    let mut path: Vec<(usize, Option<&model::Connection>)> = vec![(root, None)];
+    let mut next_edge = vec![0];
+    state[root] = 1;
+    while let Some(&(note, _)) = path.last() {
+      let edge = next_edge.last_mut().expect("one entry per path note");
+      let Some(&(dst, connection)) = outgoing[note].get(*edge) else {
+        state[note] = 2;
+        path.pop();
+        next_edge.pop();
+        continue;
+      };
+      *edge += 1;
+      match state[dst] {
+        0 => {
+          state[dst] = 1;
+          path.push((dst, Some(connection)));
+          next_edge.push(0);
+        },
+        1 => {
+          let start = path.iter().position(|(n, _)| *n == dst).expect("note on the current path");
+          let mut connection_ids: Vec<model::ID> = path[start + 1..].iter()
+            .filter_map(|(_, c)| c.map(|c| c.id.clone()))
+            .collect();
+          connection_ids.push(connection.id.clone());
+          cycles.push(Cycle {
+            note_ids: path[start..].iter().map(|(n, _)| doc.notes[*n].id.clone()).collect(),
+            connection_ids,
+          });
+        },
+        _ => {},
+      }
+    }
+  }
+  cycles
+}
+
+// Notes that stand alone: no connections, not in a stack and not on a shape
+pub fn find_orphans(doc: &model::BoardDocument) -> Vec<model::ID> {
+  let connected: HashSet<&str> = doc.connections.iter()
+    .flat_map(|c| [c.src_note_id.as_str(), c.dst_note_id.as_str()])
+    .collect();
+  let stacked: HashSet<&str> = doc.stacks.iter().flat_map(|s| s.note_ids.iter().map(String::as_str)).collect();
+  doc.notes.iter()
+    .filter(|n| !connected.contains(n.id.as_str()) && !stacked.contains(n.id.as_str()) && n.stack_id.is_none())
+    .filter(|n| !doc.shapes.iter().any(|shape| shapes::contains_note(shape, n)))
+    .map(|n| n.id.clone())
+    .collect()
+}
+
+// A chain of notes from one note to another; `connection_ids[i]` joins
+// `note_ids[i]` and `note_ids[i + 1]`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NotePath {
+  pub note_ids: Vec<model::ID>,
+  pub connection_ids: Vec<model::ID>,
+}
+
+// Adjacency with the connection behind each step; the first connection
+// between two notes stands for any repeats
+struct Steps<'a> {
+  doc: &'a model::BoardDocument,
+  next: Vec<Vec<(usize, usize)>>,
+}
+
+impl Steps<'_> {
+  // Fewest steps from `from` to `to`, avoiding the banned notes and
+  // connections (by index)
+  fn shortest(&self, from: usize, to: usize, banned_notes: &HashSet<usize>, banned_connections: &HashSet<usize>) -> Option<(Vec<usize>, Vec<usize>)> {
+    let mut came_from: HashMap<usize, (usize, usize)> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    let mut seen = HashSet::from([from]);
+    while let Some(note) = queue.pop_front() {
+      if note == to {
+        let (mut notes, mut connections) = (vec![to], Vec::new());
+        let mut current = to;
+        while let Some(&(previous, connection)) = came_from.get(&current) {
+          notes.push(previous);
+          connections.push(connection);
+          current = previous;
+        }
+        notes.reverse();
+        connections.reverse();
+        return Some((notes, connections));
+      }
+      for &(next, connection) in &self.next[note] {
+        if banned_notes.contains(&next) || banned_connections.contains(&connection) || !seen.insert(next) {
+          continue;
+        }
+        came_from.insert(next, (note, connection));
+        queue.push_back(next);
+      }
+    }
+    None
+  }
+
+  fn path(&self, (notes, connections): &(Vec<usize>, Vec<usize>)) -> NotePath {
+    NotePath {
+      note_ids: notes.iter().map(|&i| self.doc.notes[i].id.clone()).collect(),
+      connection_ids: connections.iter().map(|&i| self.doc.connections[i].id.clone()).collect(),
+    }
+  }
+}
+
+// The shortest path between two notes, then up to `alternatives` further
+// paths in order of length (Yen's algorithm; paths never visit a note
+// twice). Undirected unless `directed`, in which case steps follow arrows
+// from source to destination. Empty when the notes aren't connected.
+pub fn find_paths(
+  doc: &model::BoardDocument,
+  from_note_id: &str,
+  to_note_id: &str,
+  directed: bool,
+  alternatives: usize,
+) -> Result<Vec<NotePath>, String> {
+  let position = |id: &str| doc.notes.iter().position(|n| n.id == id)
+    .ok_or_else(|| format!("Note not found: {}", id));
+  let (from, to) = (position(from_note_id)?, position(to_note_id)?);
+
+  let index: HashMap<&str, usize> = doc.notes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+  let mut next = vec![Vec::new(); doc.notes.len()];
+  let mut linked = HashSet::new();
+  for (i, c) in doc.connections.iter().enumerate() {
+    let (Some(&src), Some(&dst)) = (index.get(c.src_note_id.as_str()), index.get(c.dst_note_id.as_str())) else {
+      continue;
+    };
+    if src == dst {
+      continue;
+    }
+    if linked.insert((src, dst)) {
+      next[src].push((dst, i));
+    }
+    if !directed && linked.insert((dst, src)) {
+      next[dst].push((src, i));
+    }
+  }
+  let steps = Steps { doc, next };
+
+  let Some(shortest) = steps.shortest(from, to, &HashSet::new(), &HashSet::new()) else {
+    return Ok(Vec::new());
+  };
+  let alternatives = alternatives.min(MAX_PATH_ALTERNATIVES);
+  let mut found = vec![shortest];
+  let mut candidates: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+  while found.len() <= alternatives {
+    let (notes, connections) = found.last().expect("at least the shortest path").clone();
+    for spur in 0..notes.len().saturating_sub(1) {
+      let root = &notes[..=spur];
+      // Leave the root the way no found path has
+      let banned_connections: HashSet<usize> = found.iter()
+        .filter(|(n, _)| n.len() > spur + 1 && &n[..=spur] == root)
+        .map(|(_, c)| c[spur])
+        .collect();
+      let banned_notes: HashSet<usize> = root[..spur].iter().copied().collect();
+      if let Some((spur_notes, spur_connections)) = steps.shortest(notes[spur], to, &banned_notes, &banned_connections) {
+        let candidate = (
+          root[..spur].iter().copied().chain(spur_notes).collect(),
+          connections[..spur].iter().copied().chain(spur_connections).collect(),
+        );
+        if !found.contains(&candidate) && !candidates.contains(&candidate) {
+          candidates.push(candidate);
+        }
+      }
+    }
+    // Shortest candidate next; the first found among equals
+    let Some(best) = candidates.iter().enumerate().min_by_key(|(_, (_, c))| c.len()).map(|(i, _)| i) else {
+      break;
+    };
+    found.push(candidates.remove(best));
+  }
+  Ok(found.iter().map(|path| steps.path(path)).collect())
+}
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+
+use crate::{layers, model};
+
+// Groups are unordered spatial collections of notes and shapes that move as a
+// unit. Unlike stacks they impose no layout; an entity belongs to at most one
+// group so moving a group never has to resolve overlapping memberships.
+
+pub fn create_group(
+  doc: &mut model::BoardDocument,
+  note_ids: &[model::ID],
+  shape_ids: &[model::ID],
+  label: Option<String>,
+) -> Result<model::ID, String> {
+  let mut seen = HashSet::new();
+  let note_ids: Vec<model::ID> = note_ids.iter().filter(|id| seen.insert(id.as_str())).cloned().collect();
+  let shape_ids: Vec<model::ID> = shape_ids.iter().filter(|id| seen.insert(id.as_str())).cloned().collect();
+  if note_ids.len() + shape_ids.len() < 2 {
+    return Err("A group needs at least two members".to_string());
+  }
+
+  for id in &note_ids {
+    if !doc.notes.iter().any(|n| &n.id == id) {
+      return Err(format!("Note '{}' not found", id));
+    }
+  }
+  for id in &shape_ids {
+    if !doc.shapes.iter().any(|s| &s.id == id) {
+      return Err(format!("Shape '{}' not found", id));
+    }
+  }
+
+  let groups = doc.groups.get_or_insert_with(Vec::new);
+  for id in note_ids.iter().chain(&shape_ids) {
+    if let Some(existing) = groups.iter().find(|g| g.note_ids.contains(id) || g.shape_ids.contains(id)) {
+      return Err(format!("'{}' already belongs to group '{}'", id, existing.id));
+    }
+  }
+
+  let id = model::new_id("group");
+  groups.push(model::Group {
+    id: id.clone(),
+    note_ids,
+    shape_ids,
+    label: label.filter(|l| !l.trim().is_empty()),
+    collapsed: None,
+  });
+  Ok(id)
+}
+
+// Remove the group itself; its members stay where they are.
+pub fn dissolve_group(doc: &mut model::BoardDocument, group_id: &str) -> Result<model::Group, String> {
+  let groups = doc.groups.as_mut().ok_or_else(|| format!("Group '{}' not found", group_id))?;
+  let index = groups.iter().position(|g| g.id == group_id)
+    .ok_or_else(|| format!("Group '{}' not found", group_id))?;
+  let group = groups.remove(index);
+  if groups.is_empty() {
+    doc.groups = None;
+  }
+  Ok(group)
+}
+
+// Offset every unlocked member by (dx, dy), along with the bend and control
+// points of connections running between two moved notes so their routing keeps its
+// shape. Returns the IDs of locked members that stayed put.
+pub fn move_group(doc: &mut model::BoardDocument, group_id: &str, dx: f64, dy: f64) -> Result<Vec<model::ID>, String> {
+  let group = doc.groups.iter().flatten().find(|g| g.id == group_id)
+    .ok_or_else(|| format!("Group '{}' not found", group_id))?;
+  let notes: HashSet<&str> = group.note_ids.iter().map(|id| id.as_str()).collect();
+  let shapes: HashSet<&str> = group.shape_ids.iter().map(|id| id.as_str()).collect();
+
+  let locked = layers::locked_ids(doc);
+  let mut skipped = Vec::new();
+  let mut moved_notes = HashSet::new();
+  for note in doc.notes.iter_mut().filter(|n| notes.contains(n.id.as_str())) {
+    if locked.contains(&note.id) {
+      skipped.push(note.id.clone());
+      continue;
+    }
+    note.frame.x += dx;
+    note.frame.y += dy;
+    moved_notes.insert(note.id.clone());
+  }
+  for shape in doc.shapes.iter_mut().filter(|s| shapes.contains(s.id.as_str())) {
+    if locked.contains(&shape.id) {
+      skipped.push(shape.id.clone());
+      continue;
+    }
+    shape.frame.x += dx;
+    shape.frame.y += dy;
+  }
+  for connection in &mut doc.connections {
+    if !moved_notes.contains(&connection.src_note_id) || !moved_notes.contains(&connection.dst_note_id) {
+      continue;
+    }
+    let control_points = connection.control_points.iter_mut().flatten();
+    for point in connection.bend_points.iter_mut().flatten().chain(control_points) {
+      point.x += dx;
+      point.y += dy;
+    }
+  }
+  Ok(skipped)
+}
+
+// Drop members that no longer exist, and groups left with fewer than two.
+pub fn prune_groups(doc: &mut model::BoardDocument) {
+  let Some(groups) = doc.groups.as_mut() else {
+    return;
+  };
+  let notes: HashSet<&str> = doc.notes.iter().map(|n| n.id.as_str()).collect();
+  let shapes: HashSet<&str> = doc.shapes.iter().map(|s| s.id.as_str()).collect();
+  for group in groups.iter_mut() {
+    group.note_ids.retain(|id| notes.contains(id.as_str()));
+    group.shape_ids.retain(|id| shapes.contains(id.as_str()));
+  }
+  groups.retain(|g| g.note_ids.len() + g.shape_ids.len() >= 2);
+  if groups.is_empty() {
+    doc.groups = None;
+  }
+}
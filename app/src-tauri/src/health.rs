@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::{backup, locale, recovery, settings, sync_write};
+
+// A check of the places the app writes to, run on first launch or after a
+// save or autosave failed, so the UI can point at the cause (a read-only
+// config folder, a full disk, a recovery location that vanished) instead of
+// a bare error. Checks only report; nothing is changed or cleaned up.
+
+// Free space below which saves and autosaves are at risk
+const LOW_DISK_SPACE: u64 = 500 * 1024 * 1024;
+const CRITICAL_DISK_SPACE: u64 = 50 * 1024 * 1024;
+// Temporary save files older than this are left over from a crash
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+const PROBE_FILE: &str = ".fim-health-check";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+  Ok,
+  Warning,
+  Error,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthCheck {
+  // "config", "data", "recovery", "backup", "documents", "disk_space",
+  // "temp_files" or "settings"
+  pub name: String,
+  pub status: Status,
+  pub message: String,
+  pub path: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct HealthReport {
+  // The worst status of any check
+  pub status: Status,
+  pub checks: Vec<HealthCheck>,
+  pub checked_at: DateTime<Utc>,
+}
+
+fn check(name: &str, status: Status, message: String, path: Option<&Path>) -> HealthCheck {
+  HealthCheck {
+    name: name.to_string(),
+    status,
+    message,
+    path: path.map(|p| p.to_string_lossy().to_string()),
+  }
+}
+
+// Whether files can be created in `dir`, creating it if needed
+fn probe_writable(dir: &Path) -> Result<(), String> {
+  std::fs::create_dir_all(dir)
+    .map_err(|e| format!("Cannot create '{}': {}", dir.display(), e))?;
+  let probe = dir.join(format!("{}-{}", PROBE_FILE, std::process::id()));
+  std::fs::write(&probe, b"ok")
+    .map_err(|e| format!("Cannot write to '{}': {}", dir.display(), e))?;
+  let _ = std::fs::remove_file(&probe);
+  Ok(())
+}
+
+fn check_writable(name: &str, label: &str, dir: Option<PathBuf>) -> HealthCheck {
+  let Some(dir) = dir else {
+    return check(name, Status::Error, format!("Could not determine the {}", label), None);
+  };
+  match probe_writable(&dir) {
+    Ok(()) => check(name, Status::Ok, format!("The {} is writable", label), Some(&dir)),
+    Err(e) => check(name, Status::Error, e, Some(&dir)),
+  }
+}
+
+// Autosave falls back to later locations, so only an unusable list is an error
+fn check_recovery(settings: &settings::AppSettings) -> HealthCheck {
+  let locations = recovery::locations(&settings.recovery_locations);
+  let mut failures = Vec::new();
+  for (index, dir) in locations.iter().enumerate() {
+    match probe_writable(dir) {
+      Ok(()) if index == 0 => return check("recovery", Status::Ok, "The recovery location is writable".to_string(), Some(dir)),
+      Ok(()) => {
+        let message = format!("Autosave falls back to this location because earlier ones are unusable ({})", failures.join("; "));
+        return check("recovery", Status::Warning, message, Some(dir));
+      },
+      Err(e) => failures.push(e),
+    }
+  }
+  check("recovery", Status::Error, format!("No usable recovery location ({})", failures.join("; ")), None)
+}
+
+fn check_backup(settings: &settings::AppSettings) -> HealthCheck {
+  if !settings.backup.enabled {
+    return check("backup", Status::Ok, "Scheduled backups are off".to_string(), None);
+  }
+  check_writable("backup", "backup folder", backup::backup_dir(&settings.backup))
+}
+
+// The nearest existing ancestor, since free space is per volume
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+  path.ancestors().find(|p| p.exists())
+}
+
+fn check_disk_space(dirs: &[PathBuf]) -> HealthCheck {
+  let mut lowest: Option<(u64, &Path)> = None;
+  for dir in dirs {
+    let Some(existing) = existing_ancestor(dir) else {
+      continue;
+    };
+    if let Ok(available) = fs2::available_space(existing) {
+      if lowest.map_or(true, |(space, _)| available < space) {
+        lowest = Some((available, dir));
+      }
+    }
+  }
+  let Some((available, dir)) = lowest else {
+    return check("disk_space", Status::Warning, "Could not determine the free disk space".to_string(), None);
+  };
+  let megabytes = available / (1024 * 1024);
+  let status = match available {
+    a if a < CRITICAL_DISK_SPACE => Status::Error,
+    a if a < LOW_DISK_SPACE => Status::Warning,
+    _ => Status::Ok,
+  };
+  check("disk_space", status, format!("{} MB free", megabytes), Some(dir))
+}
+
+fn check_temp_files() -> HealthCheck {
+  let stale = sync_write::stale_temp_files(STALE_TEMP_AGE);
+  match stale.first() {
+    None => check("temp_files", Status::Ok, "No leftover temporary save files".to_string(), None),
+    Some(first) => check(
+      "temp_files",
+      Status::Warning,
+      format!("{} temporary save file(s) left over from saves that didn't finish; they may hold unsaved work", stale.len()),
+      first.parent(),
+    ),
+  }
+}
+
+// Problems that `load_settings` hides by falling back to defaults
+fn check_settings(settings: &settings::AppSettings) -> HealthCheck {
+  let path = settings::settings_path();
+  if let Some(path) = path.as_deref().filter(|p| p.exists()) {
+    let parsed = std::fs::read_to_string(path)
+      .map_err(|e| format!("Cannot read the settings file: {}", e))
+      .and_then(|content| serde_json::from_str::<settings::AppSettings>(&content)
+        .map_err(|e| format!("The settings file is invalid and defaults are in use: {}", e)));
+    if let Err(e) = parsed {
+      return check("settings", Status::Error, e, Some(path));
+    }
+  }
+
+  let mut problems = Vec::new();
+  if !matches!(settings.write_mode.as_str(), "auto" | "syncSafe" | "direct") {
+    problems.push(format!("unknown write mode '{}'", settings.write_mode));
+  }
+  if let Err(e) = locale::ExportLocale::new(&settings.export_locale, &settings.export_timezone) {
+    problems.push(e);
+  }
+  if settings.backup.enabled && (settings.backup.interval_minutes == 0 || settings.backup.keep == 0) {
+    problems.push("backups are on but their interval or count is zero".to_string());
+  }
+  if problems.is_empty() {
+    check("settings", Status::Ok, "Settings are valid".to_string(), path.as_deref())
+  } else {
+    check("settings", Status::Warning, problems.join("; "), path.as_deref())
+  }
+}
+
+pub fn run(settings: &settings::AppSettings) -> HealthReport {
+  let documents = dirs::document_dir();
+  let mut checks = vec![
+    check_writable("config", "configuration folder", settings::config_dir()),
+    check_writable("data", "app data folder", settings::data_dir()),
+    check_recovery(settings),
+    check_backup(settings),
+    check_writable("documents", "documents folder", documents.clone()),
+  ];
+
+  let mut space_dirs: Vec<PathBuf> = documents.into_iter().collect();
+  space_dirs.extend(recovery::locations(&settings.recovery_locations).into_iter().take(1));
+  checks.push(check_disk_space(&space_dirs));
+  checks.push(check_temp_files());
+  checks.push(check_settings(settings));
+
+  HealthReport {
+    status: checks.iter().map(|c| c.status).max().unwrap_or(Status::Ok),
+    checks,
+    checked_at: Utc::now(),
+  }
+}
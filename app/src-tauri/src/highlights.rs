@@ -0,0 +1,125 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{csv, model, outline};
+
+// Reading highlights exported as CSV (Readwise's export, and Kindle notebook
+// exports converted by tools such as Bookcision). Each highlight becomes a
+// note carrying its book, author and location as properties; each book's
+// highlights form one stack, with books side by side.
+
+pub(crate) const NOTE_WIDTH: f64 = 280.0;
+const COLUMN_GAP: f64 = 60.0;
+const STACK_SPACING: f64 = 16.0;
+// Rough wrapping estimate for sizing notes to their text
+const CHARS_PER_LINE: usize = 36;
+const LINE_HEIGHT: f64 = 20.0;
+const MIN_NOTE_HEIGHT: f64 = 60.0;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct HighlightsImport {
+  pub notes: Vec<model::Note>,
+  pub stacks: Vec<model::Stack>,
+}
+
+// Header names used by the exporters we know, lowercased
+const HIGHLIGHT_HEADERS: &[&str] = &["highlight", "text", "quote", "annotation"];
+const BOOK_HEADERS: &[&str] = &["book title", "title", "book"];
+const AUTHOR_HEADERS: &[&str] = &["book author", "author", "authors"];
+const LOCATION_HEADERS: &[&str] = &["location", "page", "position"];
+// Other columns kept as properties when present: header → property name
+const EXTRA_COLUMNS: &[(&str, &str)] = &[
+  ("note", "note"),
+  ("color", "color"),
+  ("tags", "tags"),
+  ("highlighted at", "highlightedAt"),
+  ("date", "highlightedAt"),
+  ("url", "url"),
+];
+
+fn column(headers: &[String], names: &[&str]) -> Option<usize> {
+  names.iter().find_map(|name| headers.iter().position(|h| h == name))
+}
+
+pub(crate) fn note_height(text: &str) -> f64 {
+  let lines: usize = text.lines().map(|line| line.chars().count().div_ceil(CHARS_PER_LINE).max(1)).sum();
+  (lines as f64 * LINE_HEIGHT + LINE_HEIGHT).max(MIN_NOTE_HEIGHT)
+}
+
+pub fn import(text: &str, origin: Option<model::Point>) -> Result<HighlightsImport, String> {
+  let rows = csv::parse(text, ',');
+  // Some exports put a title block above the table; the header row is the
+  // first one naming a highlight column
+  let header_index = rows.iter()
+    .position(|row| row.iter().any(|cell| HIGHLIGHT_HEADERS.contains(&cell.trim().to_lowercase().as_str())))
+    .ok_or("No highlight column found; expected a header such as \"Highlight\"")?;
+  let headers: Vec<String> = rows[header_index].iter().map(|h| h.trim().to_lowercase()).collect();
+  let highlight = column(&headers, HIGHLIGHT_HEADERS).ok_or("No highlight column found")?;
+  let book = column(&headers, BOOK_HEADERS);
+  let author = column(&headers, AUTHOR_HEADERS);
+  let location = column(&headers, LOCATION_HEADERS);
+  let extras: Vec<(usize, &str)> = EXTRA_COLUMNS.iter()
+    .filter_map(|(header, property)| Some((headers.iter().position(|h| h == header)?, *property)))
+    .collect();
+
+  let cell = |row: &[String], index: Option<usize>| -> Option<String> {
+    let value = row.get(index?)?.trim();
+    (!value.is_empty()).then(|| value.to_string())
+  };
+
+  // Books in order of first appearance
+  let mut books: Vec<(String, Vec<model::Note>)> = Vec::new();
+  for row in &rows[header_index + 1..] {
+    let Some(text) = cell(row, Some(highlight)) else {
+      continue;
+    };
+    let title = cell(row, book).unwrap_or_default();
+
+    let mut properties = HashMap::new();
+    let mut set = |name: &str, value: Option<String>| {
+      if let Some(value) = value {
+        properties.insert(name.to_string(), serde_json::Value::String(value));
+      }
+    };
+    set("book", (!title.is_empty()).then(|| title.clone()));
+    set("author", cell(row, author));
+    set("location", cell(row, location));
+    for (index, property) in &extras {
+      set(property, cell(row, Some(*index)));
+    }
+
+    let mut note = outline::imported_note(&text, model::Rect { x: 0.0, y: 0.0, w: NOTE_WIDTH, h: note_height(&text) });
+    note.properties = (!properties.is_empty()).then_some(properties);
+    match books.iter_mut().find(|(t, _)| *t == title) {
+      Some((_, notes)) => notes.push(note),
+      None => books.push((title, vec![note])),
+    }
+  }
+  if books.is_empty() {
+    return Err("The CSV file has no highlights".to_string());
+  }
+
+  let origin = origin.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  let mut result = HighlightsImport::default();
+  for (column_index, (_, mut notes)) in books.into_iter().enumerate() {
+    let stack_id = model::new_id("stack");
+    let x = origin.x + column_index as f64 * (NOTE_WIDTH + COLUMN_GAP);
+    let mut y = origin.y;
+    for note in &mut notes {
+      note.frame.x = x;
+      note.frame.y = y;
+      note.stack_id = Some(stack_id.clone());
+      y += note.frame.h + STACK_SPACING;
+    }
+    result.stacks.push(model::Stack {
+      id: stack_id,
+      note_ids: notes.iter().map(|n| n.id.clone()).collect(),
+      orientation: Some("vertical".to_string()),
+      spacing: Some(STACK_SPACING),
+      indent_levels: None,
+      aligned_width: None,
+    });
+    result.notes.extend(notes);
+  }
+  Ok(result)
+}
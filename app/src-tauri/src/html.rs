@@ -0,0 +1,64 @@
+// Small helpers for reading the loose HTML found in exports and web pages
+// without a full parser
+
+// Character references (`&amp;`, `&#233;`, `&#xE9;`) as text
+pub fn decode_entities(text: &str) -> String {
+  let mut decoded = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(start) = rest.find('&') {
+    decoded.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let entity = rest.find(';').filter(|end| *end <= 10).map(|end| &rest[1..end]);
+    let character = entity.and_then(|entity| match entity {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      "nbsp" => Some('\u{a0}'),
+      _ => {
+        let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+          Some(hex) => u32::from_str_radix(hex, 16).ok(),
+          None => entity.strip_prefix('#').and_then(|d| d.parse().ok()),
+        };
+        code.and_then(char::from_u32)
+      },
+    });
+    match (entity, character) {
+      (Some(entity), Some(character)) => {
+        decoded.push(character);
+        rest = &rest[entity.len() + 2..];
+      },
+      _ => {
+        decoded.push('&');
+        rest = &rest[1..];
+      },
+    }
+  }
+  decoded.push_str(rest);
+  decoded
+}
+
+// Value of the `name` attribute (lowercase) in a tag's source, quoted or not
+pub fn attribute(source: &str, name: &str) -> Option<String> {
+  let lower = source.to_ascii_lowercase();
+  let mut search = 0;
+  while let Some(found) = lower[search..].find(name) {
+    let start = search + found;
+    search = start + name.len();
+    // Must be a whole attribute name followed by `=`
+    if start > 0 && !lower.as_bytes()[start - 1].is_ascii_whitespace() {
+      continue;
+    }
+    let Some(value) = source[search..].trim_start().strip_prefix('=') else {
+      continue;
+    };
+    let value = value.trim_start();
+    let raw = match value.chars().next() {
+      Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or(""),
+      _ => value.split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or(""),
+    };
+    return Some(decode_entities(raw));
+  }
+  None
+}
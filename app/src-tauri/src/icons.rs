@@ -0,0 +1,40 @@
+use crate::model;
+
+// Note icons are either an emoji/symbol stored as-is or one of these names,
+// which exports without an icon set of their own render as a Unicode symbol.
+const NAMED_ICONS: [(&str, &str); 12] = [
+  ("star", "★"),
+  ("check", "✓"),
+  ("cross", "✗"),
+  ("warning", "⚠"),
+  ("question", "?"),
+  ("flag", "⚑"),
+  ("heart", "♥"),
+  ("idea", "💡"),
+  ("pin", "📌"),
+  ("fire", "🔥"),
+  ("bookmark", "🔖"),
+  ("clock", "🕒"),
+];
+
+pub fn is_named_icon(icon: &str) -> bool {
+  NAMED_ICONS.iter().any(|(name, _)| *name == icon)
+}
+
+pub fn display(icon: &str) -> &str {
+  NAMED_ICONS.iter()
+    .find(|(name, _)| *name == icon)
+    .map_or(icon, |(_, symbol)| symbol)
+}
+
+// "<icon> " for notes with an icon, empty otherwise
+pub fn prefix(icon: Option<&str>) -> String {
+  match icon.map(str::trim).filter(|i| !i.is_empty()) {
+    Some(icon) => format!("{} ", display(icon)),
+    None => String::new(),
+  }
+}
+
+pub fn note_prefix(note: &model::Note) -> String {
+  prefix(note.icon.as_deref())
+}
@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::{archive, audit, media, model};
+
+// Saving a big board again without redoing the work for what didn't change.
+// For each .fim container written or opened this session we remember the
+// hash of its board.json and which images it holds. Writing the same file
+// again copies the unchanged entries over as they are stored (a zip raw copy)
+// instead of decoding, compressing or rendering them again:
+//
+// - images whose entry and fingerprint match; image IDs come from their
+//   content, the fingerprint guards against boards made elsewhere that reuse
+//   an ID for different data
+// - board.json when it comes out the same, and with it meta.json and
+//   preview.png, which are made from the board
+// - the snapshots in `versions/`, unless the caller brings its own
+//
+// The file must still be as we left it (same size and modification time).
+// Password-protected containers are always written in full.
+
+// Bytes at either end of an image's data that go into its fingerprint;
+// hashing all of it would cost about as much as writing it
+const FINGERPRINT_SAMPLE: usize = 4096;
+const PARTIAL_SUFFIX: &str = ".partial";
+
+// What a container holds, for reusing it next time
+#[derive(Debug, Clone, Default)]
+pub struct Contents {
+  pub board_hash: String,
+  // Image entry → fingerprint of its data
+  pub media: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct Known {
+  modified: Option<SystemTime>,
+  size: u64,
+  contents: Contents,
+}
+
+fn known() -> &'static Mutex<HashMap<PathBuf, Known>> {
+  static KNOWN: OnceLock<Mutex<HashMap<PathBuf, Known>>> = OnceLock::new();
+  KNOWN.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn file_state(path: &Path) -> Option<(Option<SystemTime>, u64)> {
+  let metadata = std::fs::metadata(path).ok()?;
+  Some((metadata.modified().ok(), metadata.len()))
+}
+
+pub fn fingerprint(image: &model::EmbeddedImage) -> Option<String> {
+  let data = image.data_base64.as_deref()?.as_bytes();
+  let head = &data[..data.len().min(FINGERPRINT_SAMPLE)];
+  let tail = &data[data.len().saturating_sub(FINGERPRINT_SAMPLE)..];
+  Some(format!("{}:{}:{}", data.len(), audit::hash_bytes(head), audit::hash_bytes(tail)))
+}
+
+// The images of `doc` stored in a container, given the entries it has
+pub fn media_contents(doc: &model::BoardDocument, stored: &HashSet<String>) -> HashMap<String, String> {
+  doc.images.iter().flatten()
+    .filter_map(|image| {
+      let entry = media::media_entry(image);
+      if !stored.contains(&entry) {
+        return None;
+      }
+      Some((entry, fingerprint(image)?))
+    })
+    .collect()
+}
+
+// Remember what the container at `path` holds, as it is on disk now
+pub fn remember(path: &Path, contents: Contents) {
+  let Some((modified, size)) = file_state(path) else {
+    return;
+  };
+  if let Ok(mut known) = known().lock() {
+    known.insert(path.to_path_buf(), Known { modified, size, contents });
+  }
+}
+
+pub fn forget(path: &Path) {
+  if let Ok(mut known) = known().lock() {
+    known.remove(path);
+  }
+}
+
+// Where a container is written before replacing the one it is read from
+pub fn partial_path(path: &Path) -> PathBuf {
+  let mut name = path.as_os_str().to_os_string();
+  name.push(PARTIAL_SUFFIX);
+  PathBuf::from(name)
+}
+
+// A container written before, open for copying from
+pub struct Previous {
+  pub contents: Contents,
+  archive: zip::ZipArchive<File>,
+  // Entry name → index in the archive
+  entries: HashMap<String, usize>,
+}
+
+// The container at `path` if we know what it holds and it is still as we
+// left it
+pub fn previous(path: &Path) -> Option<Previous> {
+  let known = known().lock().ok()?.get(path).cloned()?;
+  if file_state(path)? != (known.modified, known.size) {
+    return None;
+  }
+  let mut archive = zip::ZipArchive::new(File::open(path).ok()?).ok()?;
+  archive::validate_archive(&mut archive).ok()?;
+  let entries = (0..archive.len())
+    .filter_map(|index| Some((archive.by_index_raw(index).ok()?.name().to_string(), index)))
+    .collect();
+  Some(Previous { contents: known.contents, archive, entries })
+}
+
+impl Previous {
+  pub fn board_unchanged(&self, board_hash: &str) -> bool {
+    self.contents.board_hash == board_hash
+  }
+
+  // Entries of the images of `doc` that can be copied instead of written
+  pub fn reusable_media(&self, doc: &model::BoardDocument) -> HashSet<String> {
+    doc.images.iter().flatten()
+      .filter_map(|image| {
+        let entry = media::media_entry(image);
+        let same = self.contents.media.get(&entry).is_some_and(|known| Some(known) == fingerprint(image).as_ref());
+        (same && self.entries.contains_key(&entry)).then_some(entry)
+      })
+      .collect()
+  }
+
+  pub fn has(&self, name: &str) -> bool {
+    self.entries.contains_key(name)
+  }
+
+  // Copy entry `name` as it is stored
+  pub fn copy<W: Write + Seek>(&mut self, zip: &mut zip::ZipWriter<W>, name: &str) -> Result<(), String> {
+    let Some(&index) = self.entries.get(name) else {
+      return Err(format!("{} is missing from the previous file", name));
+    };
+    let entry = self.archive.by_index_raw(index)
+      .map_err(|e| format!("Failed to read {} from the previous file: {}", name, e))?;
+    zip.raw_copy_file(entry)
+      .map_err(|e| format!("Failed to copy {} in zip: {}", name, e))
+  }
+
+  // Copy every entry under `dir`
+  pub fn copy_dir<W: Write + Seek>(&mut self, zip: &mut zip::ZipWriter<W>, dir: &str) -> Result<(), String> {
+    let mut names: Vec<String> = self.entries.keys()
+      .filter(|name| name.starts_with(dir))
+      .cloned()
+      .collect();
+    names.sort();
+    for name in names {
+      self.copy(zip, &name)?;
+    }
+    Ok(())
+  }
+}
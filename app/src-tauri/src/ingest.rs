@@ -0,0 +1,203 @@
+use base64::Engine;
+use resvg::{tiny_skia, usvg};
+use serde::Serialize;
+
+use crate::{media, model};
+
+// Images as they are added to a board (pasted, dropped, imported), made no
+// bigger than they need to be. Screenshots at retina resolution were stored
+// at full size and made .fim files grow to hundreds of megabytes. Here an
+// image is decoded (PNG, JPEG, WebP and static GIF, through resvg), shrunk
+// so neither side is longer than the maximum dimension, and encoded again as
+// lossless WebP or PNG. An image that needn't shrink keeps its original
+// bytes when encoding wouldn't make it smaller, and GIFs (which may be
+// animated) and BMPs are kept as they are unless they must shrink. The result
+// is matched against the board's images by content, so the same picture
+// pasted twice is stored once.
+
+// Decoding larger images would take gigabytes of memory
+const MAX_PIXELS: u64 = 100_000_000;
+pub const FORMATS: &[&str] = &["webp", "png"];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct IngestedImage {
+  // The image to refer to, new or already on the board
+  pub id: model::ID,
+  pub width: f64,
+  pub height: f64,
+  // The image to add to the board; none when the board already has it
+  pub image: Option<model::EmbeddedImage>,
+  // Bytes stored (none for an image already there), and how many the image
+  // had when it came in
+  pub bytes: u64,
+  pub original_bytes: u64,
+}
+
+// Size of an image whose longer side is at most `max_dimension`, keeping
+// its aspect ratio; None when it already fits
+fn shrunk_size(width: u32, height: u32, max_dimension: Option<u32>) -> Option<(u32, u32)> {
+  let max = max_dimension.filter(|max| *max > 0)?;
+  if width.max(height) <= max {
+    return None;
+  }
+  let scale = max as f64 / width.max(height) as f64;
+  Some((((width as f64 * scale).round() as u32).max(1), ((height as f64 * scale).round() as u32).max(1)))
+}
+
+// Premultiplied RGBA pixels of the image at its own size
+fn decode(data: &[u8], mime: &str, width: u32, height: u32) -> Result<Vec<u8>, String> {
+  let svg = format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><image width=\"{w}\" height=\"{h}\" preserveAspectRatio=\"none\" href=\"data:{};base64,{}\"/></svg>",
+    mime, base64::engine::general_purpose::STANDARD.encode(data), w = width, h = height
+  );
+  let tree = usvg::Tree::from_str(&svg, &usvg::Options::default())
+    .map_err(|e| format!("Failed to decode image: {}", e))?;
+  let mut pixmap = tiny_skia::Pixmap::new(width, height)
+    .ok_or_else(|| format!("Cannot decode a {}×{} image", width, height))?;
+  resvg::render(&tree, tiny_skia::Transform::identity(), &mut pixmap.as_mut());
+  Ok(pixmap.take())
+}
+
+// For each pixel of a row `to` long, the pixels of a row `from` long it
+// covers and how much of it each makes up
+fn coverage(from: u32, to: u32) -> Vec<Vec<(usize, f32)>> {
+  let ratio = from as f64 / to as f64;
+  (0..to)
+    .map(|i| {
+      let (start, end) = (i as f64 * ratio, (i + 1) as f64 * ratio);
+      (start.floor() as usize..(end.ceil() as usize).min(from as usize))
+        .map(|source| (source, ((end.min(source as f64 + 1.0) - start.max(source as f64)) / ratio) as f32))
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect()
+    })
+    .collect()
+}
+
+// Shrink premultiplied RGBA pixels by averaging the area each new pixel
+// covers, first along rows, then along columns
+fn shrink(pixels: &[u8], width: u32, height: u32, to_width: u32, to_height: u32) -> Vec<u8> {
+  let (columns, rows) = (coverage(width, to_width), coverage(height, to_height));
+  let mut narrowed = vec![0f32; to_width as usize * height as usize * 4];
+  for y in 0..height as usize {
+    for (x, taps) in columns.iter().enumerate() {
+      let target = (y * to_width as usize + x) * 4;
+      for &(source, weight) in taps {
+        let source = (y * width as usize + source) * 4;
+        for channel in 0..4 {
+          narrowed[target + channel] += pixels[source + channel] as f32 * weight;
+        }
+      }
+    }
+  }
+  let mut shrunk = vec![0u8; to_width as usize * to_height as usize * 4];
+  for (y, taps) in rows.iter().enumerate() {
+    for x in 0..to_width as usize {
+      let target = (y * to_width as usize + x) * 4;
+      for channel in 0..4 {
+        let value: f32 = taps.iter().map(|&(source, weight)| narrowed[(source * to_width as usize + x) * 4 + channel] * weight).sum();
+        shrunk[target + channel] = value.round().clamp(0.0, 255.0) as u8;
+      }
+    }
+  }
+  shrunk
+}
+
+fn demultiply(pixels: &[u8]) -> Vec<u8> {
+  pixels.chunks_exact(4)
+    .flat_map(|p| {
+      let color = tiny_skia::PremultipliedColorU8::from_rgba(p[0], p[1], p[2], p[3])
+        .map(|color| color.demultiply())
+        .unwrap_or(tiny_skia::ColorU8::from_rgba(0, 0, 0, 0));
+      [color.red(), color.green(), color.blue(), color.alpha()]
+    })
+    .collect()
+}
+
+fn encode(rgba: &[u8], width: u32, height: u32, format: &str) -> Result<Vec<u8>, String> {
+  match format {
+    "png" => media::encode_png_rgba(rgba, width, height),
+    _ => {
+      let mut data = Vec::new();
+      image_webp::WebPEncoder::new(&mut data)
+        .encode(rgba, width, height, image_webp::ColorType::Rgba8)
+        .map_err(|e| format!("Failed to encode WebP: {}", e))?;
+      Ok(data)
+    },
+  }
+}
+
+// The stored bytes of an image coming in: shrunk and encoded as `format`
+// (webp or png) when that is needed or makes it smaller, else as they came
+fn prepare(data: &[u8], max_dimension: Option<u32>, format: &str) -> Result<Vec<u8>, String> {
+  let mime = media::sniff_image_mime(data).ok_or("Unsupported or unrecognized image format")?;
+  let size = imagesize::blob_size(data)
+    .map_err(|e| format!("Failed to read image dimensions: {}", e))?;
+  let (width, height) = (size.width as u32, size.height as u32);
+  let target = shrunk_size(width, height, max_dimension);
+  if target.is_none() && matches!(mime, "image/gif" | "image/bmp") {
+    return Ok(data.to_vec());
+  }
+  if mime == "image/bmp" {
+    return Err("BMP images can't be shrunk; convert them to PNG first".to_string());
+  }
+  if width as u64 * height as u64 > MAX_PIXELS {
+    return Err(format!("Image is {}×{} pixels (limit is {} megapixels)", width, height, MAX_PIXELS / 1_000_000));
+  }
+
+  let pixels = decode(data, mime, width, height)?;
+  let encoded = match target {
+    Some((to_width, to_height)) => encode(&demultiply(&shrink(&pixels, width, height, to_width, to_height)), to_width, to_height, format)?,
+    None => encode(&demultiply(&pixels), width, height, format)?,
+  };
+  match target.is_none() && encoded.len() >= data.len() {
+    true => Ok(data.to_vec()),
+    false => Ok(encoded),
+  }
+}
+
+// The board's image with the same content as `image`, if it has one. IDs
+// come from the content, so matching them is enough for images made here;
+// boards from elsewhere may use other IDs, so the data is compared too.
+fn find_existing<'a>(doc: &'a model::BoardDocument, image: &model::EmbeddedImage) -> Option<&'a model::EmbeddedImage> {
+  let data = image.data_base64.as_deref()?;
+  doc.images.iter().flatten()
+    .find(|existing| existing.id == image.id || existing.data_base64.as_deref() == Some(data))
+}
+
+fn already_there(existing: &model::EmbeddedImage, original_bytes: usize) -> IngestedImage {
+  IngestedImage {
+    id: existing.id.clone(),
+    width: existing.width,
+    height: existing.height,
+    image: None,
+    bytes: 0,
+    original_bytes: original_bytes as u64,
+  }
+}
+
+// Take in an image for `doc`; see the top of this file
+pub fn ingest(doc: &model::BoardDocument, data: &[u8], max_dimension: Option<u32>, format: &str) -> Result<IngestedImage, String> {
+  if !FORMATS.contains(&format) {
+    return Err(format!("Unsupported image format '{}'. Must be one of: {}", format, FORMATS.join(", ")));
+  }
+  if data.len() as u64 > media::MAX_IMAGE_BYTES {
+    return Err(format!("Image is {} bytes (limit is {})", data.len(), media::MAX_IMAGE_BYTES));
+  }
+  // Pasted again: the board may have it as it came in, or as prepared before
+  if let Some(existing) = find_existing(doc, &media::image_from_bytes(data)?) {
+    return Ok(already_there(existing, data.len()));
+  }
+  let stored = prepare(data, max_dimension, format)?;
+  let image = media::image_from_bytes(&stored)?;
+  if let Some(existing) = find_existing(doc, &image) {
+    return Ok(already_there(existing, data.len()));
+  }
+  Ok(IngestedImage {
+    id: image.id.clone(),
+    width: image.width,
+    height: image.height,
+    image: Some(image),
+    bytes: stored.len() as u64,
+    original_bytes: data.len() as u64,
+  })
+}
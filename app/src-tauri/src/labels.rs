@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::model;
+
+// Labels are a document-level taxonomy (priorities, statuses, colors) kept
+// apart from note styles, so "P1" means the same thing whether the board is
+// being searched, exported or laid out. Notes reference labels by ID.
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LabelFilter {
+  pub labels: Vec<model::ID>,
+  // "any" (default): note carries at least one of the labels; "all": every one
+  pub mode: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LabelCount {
+  pub id: model::ID,
+  // None for labels used on notes but missing from the palette
+  pub name: Option<String>,
+  pub color: Option<String>,
+  pub count: usize,
+}
+
+pub fn palette(doc: &model::BoardDocument) -> &[model::LabelDef] {
+  doc.document_style.as_ref()
+    .and_then(|style| style.labels.as_deref())
+    .unwrap_or(&[])
+}
+
+fn note_labels(note: &model::Note) -> impl Iterator<Item = &str> {
+  note.labels.iter().flatten().map(|id| id.as_str())
+}
+
+pub fn filter_note_ids(doc: &model::BoardDocument, filter: &LabelFilter) -> Result<Vec<model::ID>, String> {
+  let known: HashSet<&str> = palette(doc).iter().map(|l| l.id.as_str()).collect();
+  if let Some(unknown) = filter.labels.iter().find(|id| !known.contains(id.as_str())) {
+    return Err(format!("Label '{}' is not defined in this document", unknown));
+  }
+
+  let wanted: HashSet<&str> = filter.labels.iter().map(|id| id.as_str()).collect();
+  let require_all = match filter.mode.as_deref() {
+    None | Some("any") => false,
+    Some("all") => true,
+    Some(other) => return Err(format!("Unsupported label filter mode '{}'. Must be one of: any, all", other)),
+  };
+
+  Ok(doc.notes.iter()
+    .filter(|note| {
+      let labels: HashSet<&str> = note_labels(note).collect();
+      if require_all {
+        wanted.is_subset(&labels)
+      } else {
+        !wanted.is_disjoint(&labels)
+      }
+    })
+    .map(|note| note.id.clone())
+    .collect())
+}
+
+// Usage count for every palette entry (in palette order, including unused
+// ones), followed by any dangling label IDs found on notes.
+pub fn count_labels(doc: &model::BoardDocument) -> Vec<LabelCount> {
+  let count = |id: &str| doc.notes.iter().filter(|n| note_labels(n).any(|l| l == id)).count();
+
+  let mut counts: Vec<LabelCount> = palette(doc).iter()
+    .map(|label| LabelCount {
+      id: label.id.clone(),
+      name: Some(label.name.clone()),
+      color: label.color.clone(),
+      count: count(&label.id),
+    })
+    .collect();
+
+  let mut seen: HashSet<&str> = palette(doc).iter().map(|l| l.id.as_str()).collect();
+  for id in doc.notes.iter().flat_map(note_labels) {
+    if seen.insert(id) {
+      counts.push(LabelCount { id: id.to_string(), name: None, color: None, count: count(id) });
+    }
+  }
+  counts
+}
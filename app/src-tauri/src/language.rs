@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::{markdown, model, settings};
+
+// Language detection for notes (whatlang, offline) and translation through a
+// provider the user configures: a local command or a LibreTranslate-style
+// HTTP endpoint. Translations are placed next to their source note rather
+// than replacing it.
+
+// Space between a note and its translation
+const TRANSLATION_GAP: f64 = 20.0;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NoteLanguage {
+  pub id: model::ID,
+  // ISO 639-3 code ("eng", "deu", ...); None when the text is empty or too
+  // ambiguous to tell
+  pub language: Option<String>,
+  // English name of the language
+  pub name: Option<String>,
+  pub confidence: f64,
+  pub reliable: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Translation {
+  // New notes, each to the right of its source note
+  pub notes: Vec<model::Note>,
+  // Notes that were left out because they have no text
+  pub skipped: Vec<model::ID>,
+}
+
+pub(crate) fn note_text(note: &model::Note) -> String {
+  let mut text = markdown::note_rich_text(note).0;
+  for item in note.checklist.iter().flatten() {
+    text += "\n";
+    text += &item.text;
+  }
+  text
+}
+
+pub fn detect_languages(doc: &model::BoardDocument) -> Vec<NoteLanguage> {
+  doc.notes.iter()
+    .map(|note| match whatlang::detect(&note_text(note)) {
+      Some(info) => NoteLanguage {
+        id: note.id.clone(),
+        language: Some(info.lang().code().to_string()),
+        name: Some(info.lang().eng_name().to_string()),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+      },
+      None => NoteLanguage { id: note.id.clone(), language: None, name: None, confidence: 0.0, reliable: false },
+    })
+    .collect()
+}
+
+pub trait TranslationProvider: Send {
+  // One translation per text, in order
+  fn translate(&self, texts: &[String], target: &str) -> Result<Vec<String>, String>;
+}
+
+// Translation programs the "command" provider can run, by engine name: the
+// program looked up on PATH and its arguments, `{target}` standing for the
+// target language. Settings only name an engine, so a script in the webview
+// can't make the backend run anything else.
+const COMMAND_ENGINES: &[(&str, &str, &[&str])] = &[
+  ("translate-shell", "trans", &["-brief", "-no-ansi", ":{target}"]),
+];
+
+// Runs the engine's program once per text
+struct CommandProvider {
+  program: &'static str,
+  args: &'static [&'static str],
+}
+
+impl TranslationProvider for CommandProvider {
+  fn translate(&self, texts: &[String], target: &str) -> Result<Vec<String>, String> {
+    if !is_language_tag(target) {
+      return Err(format!("Invalid target language '{}'", target));
+    }
+    let args: Vec<String> = self.args.iter().map(|arg| arg.replace("{target}", target)).collect();
+    texts.iter()
+      .map(|text| {
+        let mut child = Command::new(self.program)
+          .args(&args)
+          .stdin(Stdio::piped())
+          .stdout(Stdio::piped())
+          .stderr(Stdio::piped())
+          .spawn()
+          .map_err(|e| format!("Failed to start translation command '{}': {}", self.program, e))?;
+        // Dropped at the end of the block, closing stdin so the program sees EOF
+        if let Some(mut stdin) = child.stdin.take() {
+          stdin.write_all(text.as_bytes())
+            .map_err(|e| format!("Failed to write to translation command '{}': {}", self.program, e))?;
+        }
+        let output = child.wait_with_output()
+          .map_err(|e| format!("Translation command '{}' failed: {}", self.program, e))?;
+        if !output.status.success() {
+          return Err(format!("Translation command '{}' failed ({}): {}",
+            self.program, output.status, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+      })
+      .collect()
+  }
+}
+
+// Language codes such as "de", "pt-BR" or "zh_Hant"; anything else could be
+// read as an option by the engine
+fn is_language_tag(target: &str) -> bool {
+  !target.is_empty() && target.len() <= 16
+    && target.starts_with(|c: char| c.is_ascii_alphabetic())
+    && target.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+struct HttpProvider {
+  endpoint: String,
+  api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HttpRequest<'a> {
+  q: &'a [String],
+  source: &'a str,
+  target: &'a str,
+  format: &'a str,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  api_key: Option<&'a str>,
+}
+
+// LibreTranslate answers an array of texts with an array of translations
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TranslatedText {
+  One(String),
+  Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct HttpResponse {
+  #[serde(rename = "translatedText")]
+  translated_text: TranslatedText,
+}
+
+impl TranslationProvider for HttpProvider {
+  fn translate(&self, texts: &[String], target: &str) -> Result<Vec<String>, String> {
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    let request = HttpRequest { q: texts, source: "auto", target, format: "text", api_key: self.api_key.as_deref() };
+    let response: HttpResponse = agent.post(&self.endpoint).send_json(&request)
+      .map_err(|e| format!("Failed to fetch translation from '{}': {}", self.endpoint, e))?
+      .into_json()
+      .map_err(|e| format!("Invalid translation response from '{}': {}", self.endpoint, e))?;
+    let translations = match response.translated_text {
+      TranslatedText::One(text) => vec![text],
+      TranslatedText::Many(texts) => texts,
+    };
+    if translations.len() != texts.len() {
+      return Err(format!("Invalid translation response from '{}': expected {} translations, got {}",
+        self.endpoint, texts.len(), translations.len()));
+    }
+    Ok(translations)
+  }
+}
+
+pub fn provider(settings: &settings::TranslationSettings) -> Result<Box<dyn TranslationProvider>, String> {
+  match settings.provider.as_str() {
+    "command" => {
+      let engine = settings.engine.as_deref().map(str::trim).filter(|e| !e.is_empty())
+        .ok_or("Invalid translation settings: no engine configured")?;
+      let &(_, program, args) = COMMAND_ENGINES.iter().find(|(name, _, _)| *name == engine)
+        .ok_or_else(|| format!("Unsupported translation engine '{}'. Must be one of: {}",
+          engine, COMMAND_ENGINES.iter().map(|(name, _, _)| *name).collect::<Vec<_>>().join(", ")))?;
+      Ok(Box::new(CommandProvider { program, args }))
+    },
+    "http" => {
+      let endpoint = settings.endpoint.clone().filter(|e| !e.trim().is_empty())
+        .ok_or("Invalid translation settings: no endpoint configured")?;
+      Ok(Box::new(HttpProvider { endpoint, api_key: settings.api_key.clone() }))
+    },
+    "" | "none" => Err("Translation is not configured; choose a translation provider in settings".to_string()),
+    other => Err(format!("Unsupported translation provider '{}'. Must be one of: command, http", other)),
+  }
+}
+
+// Translated copies of the given notes, each placed to the right of its
+// source with the same size and style. Checklists, tables and inline
+// formatting aren't carried over; the copy records its source and language
+// in its properties.
+pub fn translate_notes(
+  doc: &model::BoardDocument,
+  note_ids: &[model::ID],
+  target: &str,
+  provider: &dyn TranslationProvider,
+) -> Result<Translation, String> {
+  let target = target.trim();
+  if target.is_empty() {
+    return Err("Invalid target language: empty".to_string());
+  }
+
+  let mut sources = Vec::new();
+  let mut skipped = Vec::new();
+  for note in doc.notes.iter().filter(|n| note_ids.contains(&n.id)) {
+    if note.text.trim().is_empty() {
+      skipped.push(note.id.clone());
+    } else {
+      sources.push(note);
+    }
+  }
+  if sources.is_empty() {
+    return Ok(Translation { notes: Vec::new(), skipped });
+  }
+
+  let texts: Vec<String> = sources.iter().map(|n| n.text.clone()).collect();
+  let translations = provider.translate(&texts, target)?;
+
+  let notes = sources.into_iter().zip(translations)
+    .map(|(source, text)| {
+      let mut properties = std::collections::HashMap::new();
+      properties.insert("translationOf".to_string(), serde_json::Value::String(source.id.clone()));
+      properties.insert("language".to_string(), serde_json::Value::String(target.to_string()));
+      model::Note {
+        id: model::new_id("note"),
+        text,
+        content_format: source.content_format.clone(),
+        spans: None,
+        rich_attrs: None,
+        frame: model::Rect {
+          x: source.frame.x + source.frame.w + TRANSLATION_GAP,
+          ..source.frame.clone()
+        },
+        style_id: source.style_id.clone(),
+        faded: None,
+        stack_id: None,
+        links: source.links.clone(),
+        images: None,
+        connections: None,
+        z_index: source.z_index,
+        locked: None,
+        layer_id: source.layer_id.clone(),
+        checklist: None,
+        labels: source.labels.clone(),
+        tags: source.tags.clone(),
+        table: None,
+        icon: source.icon.clone(),
+        properties: Some(properties),
+        starred: None,
+        created_at: Some(chrono::Utc::now()),
+        updated_at: None,
+        confidential: None,
+        sealed_text: None,
+      }
+    })
+    .collect();
+
+  Ok(Translation { notes, skipped })
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+
+use crate::{groups, model};
+
+// Notes and background shapes are stacked independently (shapes always render
+// behind notes), each ordered by `zIndex`. Entities without one — everything
+// saved before layering existed — keep their position in the document, which
+// is the order they were drawn in.
+pub(crate) trait Layered {
+  fn item_id(&self) -> &str;
+  fn z_index(&self) -> Option<i64>;
+  fn set_z_index(&mut self, z_index: i64);
+}
+
+impl Layered for model::Note {
+  fn item_id(&self) -> &str { &self.id }
+  fn z_index(&self) -> Option<i64> { self.z_index }
+  fn set_z_index(&mut self, z_index: i64) { self.z_index = Some(z_index); }
+}
+
+impl Layered for model::BackgroundShape {
+  fn item_id(&self) -> &str { &self.id }
+  fn z_index(&self) -> Option<i64> { self.z_index }
+  fn set_z_index(&mut self, z_index: i64) { self.z_index = Some(z_index); }
+}
+
+// Paint order key of the item at `position` in the document
+fn stacking_key<T: Layered>(position: usize, item: &T) -> (i64, usize) {
+  (item.z_index().unwrap_or(position as i64), position)
+}
+
+// Items bottom to top, each with its position in the document; what the
+// canvas draws and exports should follow
+pub(crate) fn paint_order<T: Layered>(items: &[T]) -> Vec<(usize, &T)> {
+  let mut ordered: Vec<(usize, &T)> = items.iter().enumerate().collect();
+  ordered.sort_by_key(|(position, item)| stacking_key(*position, *item));
+  ordered
+}
+
+// Move the selected items to the top or bottom of their layer, keeping their
+// relative order, then renumber the whole layer 0..n so indices stay dense and
+// the document order matches the paint order. Returns how many items moved.
+fn restack<T: Layered>(items: &mut Vec<T>, ids: &HashSet<&str>, to_front: bool) -> usize {
+  let mut indexed: Vec<(usize, T)> = items.drain(..).enumerate().collect();
+  indexed.sort_by_key(|(position, item)| stacking_key(*position, item));
+
+  let (selected, rest): (Vec<T>, Vec<T>) = indexed.into_iter()
+    .map(|(_, item)| item)
+    .partition(|item| ids.contains(item.item_id()));
+  let moved = selected.len();
+
+  *items = if to_front {
+    rest.into_iter().chain(selected).collect()
+  } else {
+    selected.into_iter().chain(rest).collect()
+  };
+  for (z_index, item) in items.iter_mut().enumerate() {
+    item.set_z_index(z_index as i64);
+  }
+  moved
+}
+
+fn reorder(doc: &mut model::BoardDocument, ids: &[model::ID], to_front: bool) -> Result<(), String> {
+  let ids: HashSet<&str> = ids.iter().map(|id| id.as_str()).collect();
+  let moved = restack(&mut doc.notes, &ids, to_front) + restack(&mut doc.shapes, &ids, to_front);
+  if moved == 0 {
+    return Err("None of the given IDs match a note or shape".to_string());
+  }
+  Ok(())
+}
+
+pub fn bring_to_front(doc: &mut model::BoardDocument, ids: &[model::ID]) -> Result<(), String> {
+  reorder(doc, ids, true)
+}
+
+pub fn send_to_back(doc: &mut model::BoardDocument, ids: &[model::ID]) -> Result<(), String> {
+  reorder(doc, ids, false)
+}
+
+// Named layers (`doc.layers`) are a separate axis from stacking order: notes,
+// shapes and strokes can sit on a layer via `layerId`, and a layer can be
+// hidden from the board and exports or locked against edits. Entities without
+// a `layerId` are on the base layer, which is always visible and unlocked.
+
+fn find_layer<'a>(doc: &'a model::BoardDocument, layer_id: Option<&str>) -> Option<&'a model::Layer> {
+  let layer_id = layer_id?;
+  doc.layers.iter().flatten().find(|layer| layer.id == layer_id)
+}
+
+// IDs of layers that are currently visible
+pub fn visible_layers(doc: &model::BoardDocument) -> Vec<model::ID> {
+  doc.layers.iter().flatten().filter(|layer| layer.visible).map(|layer| layer.id.clone()).collect()
+}
+
+// Notes and shapes that can't be edited, either because they are locked
+// themselves or because their layer is
+pub fn locked_ids(doc: &model::BoardDocument) -> HashSet<model::ID> {
+  let layer_locked = |layer_id: &Option<model::ID>| find_layer(doc, layer_id.as_deref()).is_some_and(|layer| layer.locked);
+  let notes = doc.notes.iter()
+    .filter(|note| note.is_locked() || layer_locked(&note.layer_id))
+    .map(|note| note.id.clone());
+  let shapes = doc.shapes.iter()
+    .filter(|shape| shape.is_locked() || layer_locked(&shape.layer_id))
+    .map(|shape| shape.id.clone());
+  let strokes = doc.strokes.iter().flatten()
+    .filter(|stroke| layer_locked(&stroke.layer_id))
+    .map(|stroke| stroke.id.clone());
+  notes.chain(shapes).chain(strokes).collect()
+}
+
+// Copy of the document with only the base layer and the given layers, for
+// exports. References to layers the document doesn't define are kept, so a
+// typo can't silently drop content. Connections to removed notes go too.
+pub fn filter_layers(doc: &model::BoardDocument, include: &[model::ID]) -> model::BoardDocument {
+  let included = |layer_id: &Option<model::ID>| match layer_id {
+    Some(id) => include.contains(id) || find_layer(doc, Some(id)).is_none(),
+    None => true,
+  };
+
+  let mut subset = doc.clone();
+  subset.notes.retain(|note| included(&note.layer_id));
+  subset.shapes.retain(|shape| included(&shape.layer_id));
+  if let Some(strokes) = subset.strokes.as_mut() {
+    strokes.retain(|stroke| included(&stroke.layer_id));
+  }
+
+  let notes: HashSet<&str> = subset.notes.iter().map(|n| n.id.as_str()).collect();
+  subset.connections.retain(|c| notes.contains(c.src_note_id.as_str()) && notes.contains(c.dst_note_id.as_str()));
+  for stack in &mut subset.stacks {
+    stack.note_ids.retain(|id| notes.contains(id.as_str()));
+  }
+  subset.stacks.retain(|s| !s.note_ids.is_empty());
+  groups::prune_groups(&mut subset);
+  subset
+}
+
+// Move notes, shapes and strokes onto a layer (or back to the base layer with
+// `None`). Locked items stay where they are; their IDs are returned.
+pub fn move_to_layer(doc: &mut model::BoardDocument, ids: &[model::ID], layer_id: Option<&str>) -> Result<Vec<model::ID>, String> {
+  if let Some(id) = layer_id.filter(|_| find_layer(doc, layer_id).is_none()) {
+    return Err(format!("Layer '{}' not found", id));
+  }
+  let ids: HashSet<&str> = ids.iter().map(|id| id.as_str()).collect();
+  let locked = locked_ids(doc);
+  let target = layer_id.map(str::to_string);
+
+  let mut skipped = Vec::new();
+  let mut assign = |id: &str, current: &mut Option<model::ID>| {
+    if !ids.contains(id) {
+      return;
+    }
+    if locked.contains(id) {
+      skipped.push(id.to_string());
+    } else {
+      *current = target.clone();
+    }
+  };
+  for note in &mut doc.notes {
+    assign(&note.id, &mut note.layer_id);
+  }
+  for shape in &mut doc.shapes {
+    assign(&shape.id, &mut shape.layer_id);
+  }
+  for stroke in doc.strokes.iter_mut().flatten() {
+    assign(&stroke.id, &mut stroke.layer_id);
+  }
+  Ok(skipped)
+}
@@ -0,0 +1,404 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{layers, model, shapes};
+
+// Automatic layout for piles of notes, e.g. after importing an outline or a
+// quick brainstorm:
+//   "force" – connected notes pull together, all notes push apart
+//   "tree"  – layers along connections, each parent centered on its children
+//   "grid"  – rows in reading order, packed to a roughly 3:2 block
+// Stacks move as one block so their own layout survives, and locked notes stay
+// put. With a shape, only the notes inside it are arranged, starting at its
+// top-left corner. Nothing on the board changes; the frontend applies the
+// returned frames as one undoable step.
+
+pub const ALGORITHMS: [&str; 3] = ["force", "tree", "grid"];
+const DEFAULT_SPACING: f64 = 40.0;
+const MAX_SPACING: f64 = 1000.0;
+const DEFAULT_ITERATIONS: usize = 300;
+const MAX_ITERATIONS: usize = 2000;
+// Width to height of grid layouts without a shape to fill
+const GRID_ASPECT: f64 = 1.5;
+// Between a shape's edge and the notes laid out inside it
+const SHAPE_PADDING: f64 = 20.0;
+// Notes further apart than this many ideal edge lengths don't repel
+const REPULSION_RANGE: f64 = 4.0;
+const OVERLAP_PASSES: usize = 100;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct LayoutOptions {
+  // "force" (default), "tree" or "grid"
+  pub algorithm: Option<String>,
+  // Only lay out the notes inside this shape
+  pub shape_id: Option<model::ID>,
+  // Gap between notes; defaults to 40px
+  pub spacing: Option<f64>,
+  // Tree layouts: "right" (default) or "down"
+  pub direction: Option<String>,
+  // Force layouts: simulation steps, defaults to 300
+  pub iterations: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LayoutResult {
+  // New frames of the notes laid out
+  pub notes: Vec<shapes::FrameUpdate>,
+  // Area they cover now
+  pub bounds: Option<model::Rect>,
+  // The shape grown to hold the layout, when it no longer fits
+  pub shape: Option<shapes::FrameUpdate>,
+}
+
+// A note, or a whole stack, moved as one
+struct Unit {
+  note_ids: Vec<model::ID>,
+  frame: model::Rect,
+}
+
+fn union(a: &model::Rect, b: &model::Rect) -> model::Rect {
+  let (x, y) = (a.x.min(b.x), a.y.min(b.y));
+  model::Rect { x, y, w: (a.x + a.w).max(b.x + b.w) - x, h: (a.y + a.h).max(b.y + b.h) - y }
+}
+
+fn center(frame: &model::Rect) -> model::Point {
+  model::Point { x: frame.x + frame.w / 2.0, y: frame.y + frame.h / 2.0 }
+}
+
+// Movable notes in scope, grouped into units in reading order
+fn units(doc: &model::BoardDocument, shape: Option<&model::BackgroundShape>) -> Vec<Unit> {
+  let locked = layers::locked_ids(doc);
+  let notes: HashMap<&str, &model::Note> = doc.notes.iter().map(|n| (n.id.as_str(), n)).collect();
+
+  let mut units = Vec::new();
+  let mut seen: HashSet<&str> = HashSet::new();
+  for stack in &doc.stacks {
+    let members: Vec<&model::Note> = stack.note_ids.iter().filter_map(|id| notes.get(id.as_str()).copied()).collect();
+    seen.extend(members.iter().map(|n| n.id.as_str()));
+    // A stack with a locked note can't move without breaking its layout
+    if members.is_empty() || members.iter().any(|n| locked.contains(&n.id)) {
+      continue;
+    }
+    let frame = members.iter().skip(1).fold(members[0].frame.clone(), |frame, n| union(&frame, &n.frame));
+    units.push(Unit { note_ids: members.iter().map(|n| n.id.clone()).collect(), frame });
+  }
+  for note in &doc.notes {
+    if !seen.contains(note.id.as_str()) && note.stack_id.is_none() && !locked.contains(&note.id) {
+      units.push(Unit { note_ids: vec![note.id.clone()], frame: note.frame.clone() });
+    }
+  }
+
+  units.retain(|unit| {
+    let valid = [unit.frame.x, unit.frame.y, unit.frame.w, unit.frame.h].iter().all(|v| v.is_finite());
+    valid && shape.map_or(true, |shape| shapes::contains_point(shape, &center(&unit.frame)))
+  });
+  units.sort_by(|a, b| a.frame.y.total_cmp(&b.frame.y).then(a.frame.x.total_cmp(&b.frame.x)));
+  units
+}
+
+// Connections between distinct units, each pair once, as (source, target)
+fn edges(doc: &model::BoardDocument, units: &[Unit]) -> Vec<(usize, usize)> {
+  let unit_of: HashMap<&str, usize> = units.iter().enumerate()
+    .flat_map(|(index, unit)| unit.note_ids.iter().map(move |id| (id.as_str(), index)))
+    .collect();
+  let mut seen = HashSet::new();
+  doc.connections.iter()
+    .filter_map(|c| Some((*unit_of.get(c.src_note_id.as_str())?, *unit_of.get(c.dst_note_id.as_str())?)))
+    .filter(|(a, b)| a != b && seen.insert((*a, *b)))
+    .collect()
+}
+
+// Top-left corners, packed into rows no wider than `width`
+fn grid(units: &[Unit], spacing: f64, width: Option<f64>) -> Vec<model::Point> {
+  let widest = units.iter().map(|u| u.frame.w).fold(0.0, f64::max);
+  let area: f64 = units.iter().map(|u| (u.frame.w + spacing) * (u.frame.h + spacing)).sum();
+  let width = width.unwrap_or((area * GRID_ASPECT).sqrt()).max(widest);
+
+  let (mut x, mut y, mut row_height) = (0.0, 0.0, 0.0_f64);
+  units.iter().map(|unit| {
+    if x > 0.0 && x + unit.frame.w > width {
+      (x, y, row_height) = (0.0, y + row_height + spacing, 0.0);
+    }
+    let position = model::Point { x, y };
+    x += unit.frame.w + spacing;
+    row_height = row_height.max(unit.frame.h);
+    position
+  }).collect()
+}
+
+// Top-left corners of a layered tree. Units without incoming connections are
+// roots; in cycles the first unit in reading order is. Each unit hangs under
+// the first parent that reaches it.
+fn tree(units: &[Unit], edges: &[(usize, usize)], spacing: f64, down: bool) -> Vec<model::Point> {
+  let n = units.len();
+  let mut children: Vec<Vec<usize>> = vec![Vec::new(); n];
+  let mut has_parent = vec![false; n];
+  for &(source, target) in edges {
+    children[source].push(target);
+    has_parent[target] = true;
+  }
+  for list in &mut children {
+    list.sort_unstable();
+  }
+
+  // Breadth-first from the roots, so parents come before their children
+  let mut order = Vec::with_capacity(n);
+  let mut depth = vec![0; n];
+  let mut tree_children: Vec<Vec<usize>> = vec![Vec::new(); n];
+  let mut roots = Vec::new();
+  let mut visited = vec![false; n];
+  let starts: Vec<usize> = (0..n).filter(|&i| !has_parent[i]).chain(0..n).collect();
+  for start in starts {
+    if visited[start] {
+      continue;
+    }
+    visited[start] = true;
+    roots.push(start);
+    let mut queue = VecDeque::from([start]);
+    while let Some(unit) = queue.pop_front() {
+      order.push(unit);
+      for &child in &children[unit] {
+        if !visited[child] {
+          visited[child] = true;
+          depth[child] = depth[unit] + 1;
+          tree_children[unit].push(child);
+          queue.push_back(child);
+        }
+      }
+    }
+  }
+
+  // Extent along the depth axis, and across it
+  let along = |u: usize| if down { units[u].frame.h } else { units[u].frame.w };
+  let across = |u: usize| if down { units[u].frame.w } else { units[u].frame.h };
+
+  // Each level is as deep as its deepest unit, with room for connections
+  let levels = depth.iter().max().map_or(0, |d| d + 1);
+  let mut level_size = vec![0.0_f64; levels];
+  for u in 0..n {
+    level_size[depth[u]] = level_size[depth[u]].max(along(u));
+  }
+  let mut level_offset = vec![0.0; levels];
+  for level in 1..levels {
+    level_offset[level] = level_offset[level - 1] + level_size[level - 1] + spacing * 2.0;
+  }
+
+  // Room each subtree needs across, children before parents
+  let mut extent = vec![0.0; n];
+  for &u in order.iter().rev() {
+    let kids = &tree_children[u];
+    let kids_extent: f64 = kids.iter().map(|&c| extent[c]).sum::<f64>() + spacing * kids.len().saturating_sub(1) as f64;
+    extent[u] = across(u).max(kids_extent);
+  }
+
+  // Place subtrees side by side, parents centered on their children
+  let mut start = vec![0.0; n];
+  let mut cursor = 0.0;
+  for &root in &roots {
+    start[root] = cursor;
+    cursor += extent[root] + spacing;
+  }
+  let mut positions = vec![model::Point { x: 0.0, y: 0.0 }; n];
+  for &u in &order {
+    let kids = &tree_children[u];
+    let kids_extent: f64 = kids.iter().map(|&c| extent[c]).sum::<f64>() + spacing * kids.len().saturating_sub(1) as f64;
+    let mut child_start = start[u] + (extent[u] - kids_extent).max(0.0) / 2.0;
+    for &child in kids {
+      start[child] = child_start;
+      child_start += extent[child] + spacing;
+    }
+    let cross = start[u] + (extent[u] - across(u)) / 2.0;
+    positions[u] = if down {
+      model::Point { x: cross, y: level_offset[depth[u]] }
+    } else {
+      model::Point { x: level_offset[depth[u]], y: cross }
+    };
+  }
+  positions
+}
+
+// Top-left corners from a force simulation started at the current positions:
+// connections act as springs, every pair of nearby units repels, and the
+// movement allowed per step cools down to zero. Overlaps left at the end are
+// pushed apart.
+fn force(units: &[Unit], edges: &[(usize, usize)], spacing: f64, iterations: usize) -> Vec<model::Point> {
+  let n = units.len();
+  // Ideal distance between connected centers
+  let k = units.iter().map(|u| u.frame.w.max(u.frame.h)).sum::<f64>() / n as f64 + spacing;
+  let range = k * REPULSION_RANGE;
+
+  // Units on the same spot get spread on a spiral, so they can repel
+  let mut positions: Vec<model::Point> = units.iter().map(|u| center(&u.frame)).collect();
+  let mut taken = HashSet::new();
+  for (index, p) in positions.iter_mut().enumerate() {
+    if !taken.insert((p.x.round() as i64, p.y.round() as i64)) {
+      let angle = index as f64 * 2.399_963;
+      let radius = k * 0.1 * (index as f64).sqrt();
+      p.x += radius * angle.cos();
+      p.y += radius * angle.sin();
+    }
+  }
+
+  let initial_temperature = k * (n as f64).sqrt();
+  for step in 0..iterations {
+    let temperature = initial_temperature * (1.0 - step as f64 / iterations as f64);
+    let mut shift = vec![(0.0, 0.0); n];
+
+    // Repulsion between units in neighboring cells
+    let cell = |p: &model::Point| ((p.x / range).floor() as i64, (p.y / range).floor() as i64);
+    let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (index, p) in positions.iter().enumerate() {
+      cells.entry(cell(p)).or_default().push(index);
+    }
+    for (a, p) in positions.iter().enumerate() {
+      let (cx, cy) = cell(p);
+      for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+        for &b in cells.get(&(cx + dx, cy + dy)).into_iter().flatten() {
+          if b == a {
+            continue;
+          }
+          let (x, y) = (p.x - positions[b].x, p.y - positions[b].y);
+          let distance = (x * x + y * y).sqrt().max(0.01);
+          if distance < range {
+            let push = k * k / distance;
+            shift[a].0 += x / distance * push;
+            shift[a].1 += y / distance * push;
+          }
+        }
+      }
+    }
+
+    // Attraction along connections
+    for &(a, b) in edges {
+      let (x, y) = (positions[a].x - positions[b].x, positions[a].y - positions[b].y);
+      let distance = (x * x + y * y).sqrt().max(0.01);
+      let pull = distance * distance / k;
+      shift[a].0 -= x / distance * pull;
+      shift[a].1 -= y / distance * pull;
+      shift[b].0 += x / distance * pull;
+      shift[b].1 += y / distance * pull;
+    }
+
+    for (p, (x, y)) in positions.iter_mut().zip(shift) {
+      let length = (x * x + y * y).sqrt();
+      if length > 0.0 {
+        let moved = length.min(temperature);
+        p.x += x / length * moved;
+        p.y += y / length * moved;
+      }
+    }
+  }
+
+  let mut frames: Vec<model::Rect> = units.iter().zip(&positions)
+    .map(|(u, p)| model::Rect { x: p.x - u.frame.w / 2.0, y: p.y - u.frame.h / 2.0, ..u.frame.clone() })
+    .collect();
+  separate(&mut frames, spacing);
+  frames.into_iter().map(|f| model::Point { x: f.x, y: f.y }).collect()
+}
+
+// Push overlapping frames apart, each by half the overlap along the axis
+// where it is smallest, until at least `gap` lies between all of them or the
+// passes run out
+fn separate(frames: &mut [model::Rect], gap: f64) {
+  for _ in 0..OVERLAP_PASSES {
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by(|&a, &b| frames[a].x.total_cmp(&frames[b].x));
+    let mut moved = false;
+    for (i, &a) in order.iter().enumerate() {
+      for &b in &order[i + 1..] {
+        let (fa, fb) = (&frames[a], &frames[b]);
+        if fb.x >= fa.x + fa.w + gap {
+          break;
+        }
+        let overlap_x = (fa.x + fa.w + gap).min(fb.x + fb.w + gap) - fa.x.max(fb.x);
+        let overlap_y = (fa.y + fa.h + gap).min(fb.y + fb.h + gap) - fa.y.max(fb.y);
+        if overlap_x <= 0.0 || overlap_y <= 0.0 {
+          continue;
+        }
+        moved = true;
+        if overlap_x < overlap_y {
+          let sign = if center(fa).x <= center(fb).x { 1.0 } else { -1.0 };
+          frames[a].x -= sign * overlap_x / 2.0;
+          frames[b].x += sign * overlap_x / 2.0;
+        } else {
+          let sign = if center(fa).y <= center(fb).y { 1.0 } else { -1.0 };
+          frames[a].y -= sign * overlap_y / 2.0;
+          frames[b].y += sign * overlap_y / 2.0;
+        }
+      }
+    }
+    if !moved {
+      break;
+    }
+  }
+}
+
+pub fn layout(doc: &model::BoardDocument, options: &LayoutOptions) -> Result<LayoutResult, String> {
+  let algorithm = options.algorithm.as_deref().unwrap_or("force");
+  if !ALGORITHMS.contains(&algorithm) {
+    return Err(format!("Invalid layout algorithm '{}'. Must be one of: {}", algorithm, ALGORITHMS.join(", ")));
+  }
+  let down = match options.direction.as_deref().unwrap_or("right") {
+    "right" => false,
+    "down" => true,
+    other => return Err(format!("Invalid layout direction '{}'. Must be one of: right, down", other)),
+  };
+  let spacing = options.spacing.unwrap_or(DEFAULT_SPACING);
+  if !spacing.is_finite() || !(0.0..=MAX_SPACING).contains(&spacing) {
+    return Err(format!("Layout spacing must be between 0 and {}", MAX_SPACING));
+  }
+  let shape = match options.shape_id.as_deref() {
+    Some(id) => {
+      let shape = doc.shapes.iter().find(|s| s.id == id).ok_or_else(|| format!("Shape '{}' not found", id))?;
+      if shapes::kind(shape) == "line" {
+        return Err("Lines can't hold notes to lay out".to_string());
+      }
+      Some(shape)
+    },
+    None => None,
+  };
+
+  let units = units(doc, shape);
+  if units.is_empty() {
+    return Ok(LayoutResult { notes: Vec::new(), bounds: None, shape: None });
+  }
+  let edges = edges(doc, &units);
+  let positions = match algorithm {
+    "grid" => grid(&units, spacing, shape.map(|s| (s.frame.w - SHAPE_PADDING * 2.0).max(0.0))),
+    "tree" => tree(&units, &edges, spacing, down),
+    _ => force(&units, &edges, spacing, options.iterations.unwrap_or(DEFAULT_ITERATIONS).clamp(1, MAX_ITERATIONS)),
+  };
+
+  // Start where the notes started, or inside the shape's corner
+  let current = units.iter().skip(1).fold(units[0].frame.clone(), |bounds, u| union(&bounds, &u.frame));
+  let origin = match shape {
+    Some(shape) => model::Point { x: shape.frame.x + SHAPE_PADDING, y: shape.frame.y + SHAPE_PADDING },
+    None => model::Point { x: current.x, y: current.y },
+  };
+  let min_x = positions.iter().map(|p| p.x).fold(f64::INFINITY, f64::min);
+  let min_y = positions.iter().map(|p| p.y).fold(f64::INFINITY, f64::min);
+  let (dx, dy) = (origin.x - min_x, origin.y - min_y);
+
+  let frames: HashMap<&str, &model::Rect> = doc.notes.iter().map(|n| (n.id.as_str(), &n.frame)).collect();
+  let mut notes = Vec::new();
+  let mut bounds: Option<model::Rect> = None;
+  for (unit, position) in units.iter().zip(&positions) {
+    let (ux, uy) = (position.x + dx - unit.frame.x, position.y + dy - unit.frame.y);
+    for id in &unit.note_ids {
+      let frame = frames[id.as_str()];
+      let frame = model::Rect { x: frame.x + ux, y: frame.y + uy, ..frame.clone() };
+      bounds = Some(bounds.map_or(frame.clone(), |b| union(&b, &frame)));
+      notes.push(shapes::FrameUpdate { id: id.clone(), frame });
+    }
+  }
+
+  let shape = shape.zip(bounds.as_ref()).and_then(|(shape, bounds)| {
+    let w = shape.frame.w.max(bounds.x + bounds.w + SHAPE_PADDING - shape.frame.x);
+    let h = shape.frame.h.max(bounds.y + bounds.h + SHAPE_PADDING - shape.frame.y);
+    (w > shape.frame.w || h > shape.frame.h).then(|| shapes::FrameUpdate {
+      id: shape.id.clone(),
+      frame: model::Rect { w, h, ..shape.frame.clone() },
+    })
+  });
+  Ok(LayoutResult { notes, bounds, shape })
+}
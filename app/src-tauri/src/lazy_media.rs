@@ -0,0 +1,123 @@
+use base64::Engine;
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::{archive, media, model};
+
+// Opening an image-heavy board without reading every image into memory. When
+// the images stored in a .fim container add up to more than the `lazyMediaMb`
+// setting, they are left in their `media/` files: the board's images keep
+// their `path` and get no `dataBase64`, and the frontend fetches each one with
+// `read_media` when it is shown. Which container holds each deferred image is
+// kept here, so drawing the board, keeping a snapshot or saving it, here or
+// elsewhere, reads just the images it needs from that file. Password-protected
+// containers are decrypted in memory as a whole and always load fully.
+
+#[derive(Debug, Clone)]
+struct Source {
+  path: PathBuf,
+  entry: String,
+}
+
+// Image ID → where its data is
+fn sources() -> &'static Mutex<HashMap<model::ID, Source>> {
+  static SOURCES: OnceLock<Mutex<HashMap<model::ID, Source>>> = OnceLock::new();
+  SOURCES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn source(id: &str) -> Option<Source> {
+  sources().lock().ok()?.get(id).cloned()
+}
+
+// Whether the image's data was left in its container
+pub fn is_deferred(image: &model::EmbeddedImage) -> bool {
+  image.data_base64.is_none() && image.path.as_deref().is_some_and(|p| p.starts_with(media::MEDIA_DIR))
+}
+
+// Bytes the board's stored images take up in the container, going by what
+// its central directory says
+pub fn stored_size<R: Read + Seek>(doc: &model::BoardDocument, zip: &mut zip::ZipArchive<R>) -> u64 {
+  doc.images.iter().flatten()
+    .filter(|image| is_deferred(image))
+    .filter_map(|image| zip.by_name(image.path.as_deref()?).ok().map(|entry| entry.size()))
+    .sum()
+}
+
+// Remember that the deferred images of `doc` are in the container at `path`
+pub fn index(doc: &model::BoardDocument, path: &Path) {
+  let Ok(mut sources) = sources().lock() else {
+    return;
+  };
+  for image in doc.images.iter().flatten().filter(|image| is_deferred(image)) {
+    let entry = image.path.clone().unwrap_or_default();
+    sources.insert(image.id.clone(), Source { path: path.to_path_buf(), entry });
+  }
+}
+
+// After `doc` was saved to `path`, its deferred images are read from there
+pub fn moved(doc: &model::BoardDocument, path: &Path) {
+  let Ok(mut sources) = sources().lock() else {
+    return;
+  };
+  for image in doc.images.iter().flatten().filter(|image| is_deferred(image)) {
+    if let Some(source) = sources.get_mut(&image.id) {
+      source.path = path.to_path_buf();
+    }
+  }
+}
+
+// Whether saving `doc` reads images from the file at `path`
+pub fn reads_from(doc: &model::BoardDocument, path: &Path) -> bool {
+  doc.images.iter().flatten()
+    .filter(|image| is_deferred(image))
+    .any(|image| source(&image.id).is_some_and(|source| source.path == path))
+}
+
+fn open(path: &Path) -> Result<zip::ZipArchive<std::fs::File>, String> {
+  let file = std::fs::File::open(path)
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+  let mut zip = zip::ZipArchive::new(file)
+    .map_err(|e| format!("Failed to read zip file '{}': {}", path.display(), e))?;
+  archive::validate_archive(&mut zip)?;
+  Ok(zip)
+}
+
+// The data of a deferred image, by its ID
+pub fn read(id: &str) -> Result<Vec<u8>, String> {
+  let source = source(id).ok_or_else(|| format!("Image '{}' not found among the images left to load", id))?;
+  let mut zip = open(&source.path)?;
+  archive::read_entry_limited(&mut zip, &source.entry, media::MAX_IMAGE_BYTES)
+    .map_err(|e| format!("Failed to read image '{}' from '{}': {}", id, source.path.display(), e))
+}
+
+// Put the data of the board's deferred images back, for what needs the
+// whole board (snapshots, JSON files)
+pub fn load(doc: &mut model::BoardDocument) -> Result<(), String> {
+  for image in doc.images.iter_mut().flatten() {
+    if is_deferred(image) && source(&image.id).is_some() {
+      image.data_base64 = Some(base64::engine::general_purpose::STANDARD.encode(read(&image.id)?));
+      image.path = None;
+    }
+  }
+  Ok(())
+}
+
+// Copy a deferred image's entry, as stored, from its container into `zip`.
+// Ok(false) when it isn't known where the image is, e.g. an image that
+// couldn't be read when its board was opened.
+pub fn copy<W: Write + Seek>(zip: &mut zip::ZipWriter<W>, image: &model::EmbeddedImage) -> Result<bool, String> {
+  let Some(source) = source(&image.id) else {
+    return Ok(false);
+  };
+  let mut from = open(&source.path)?;
+  let found = (0..from.len())
+    .find(|&index| from.by_index_raw(index).is_ok_and(|entry| entry.name() == source.entry))
+    .ok_or_else(|| format!("Image '{}' not found in '{}'", image.id, source.path.display()))?;
+  let entry = from.by_index_raw(found)
+    .map_err(|e| format!("Failed to read image '{}' from '{}': {}", image.id, source.path.display(), e))?;
+  zip.raw_copy_file_rename(entry, media::media_entry(image))
+    .map_err(|e| format!("Failed to copy image '{}' in zip: {}", image.id, e))?;
+  Ok(true)
+}
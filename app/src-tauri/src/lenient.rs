@@ -0,0 +1,200 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{migrations, model};
+
+// Lenient parsing for hand-edited board JSON. Instead of failing on the first
+// serde error, each section and each entry is read on its own: missing
+// sections get their defaults, fields with the wrong type are dropped (optional
+// ones) and entries that still can't be read are skipped. Every repair is
+// reported with the JSON path it happened at.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Diagnostic {
+  // e.g. `notes[3].zIndex`
+  pub path: String,
+  // What the field should hold, when serde says so (e.g. "f64", "a sequence")
+  pub expected: Option<String>,
+  pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LenientDocument {
+  pub document: model::BoardDocument,
+  pub diagnostics: Vec<Diagnostic>,
+}
+
+fn diagnostic(path: String, error: &str, message: String) -> Diagnostic {
+  let expected = error.split_once("expected ").map(|(_, expected)| expected.to_string());
+  Diagnostic { path, expected, message }
+}
+
+// serde names a missing required field as "missing field `name`"
+fn missing_field(error: &str) -> Option<&str> {
+  error.strip_prefix("missing field `")?.strip_suffix('`')
+}
+
+// Read one entry, dropping the fields that keep it from deserializing. serde
+// reports the first bad field it meets, so removing a field that changes the
+// error (without making it a missing required field) means that field was
+// the culprit.
+fn read_entry<T: DeserializeOwned>(value: Value, path: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<T> {
+  let mut error = match serde_json::from_value::<T>(value.clone()) {
+    Ok(entry) => return Some(entry),
+    Err(e) => e.to_string(),
+  };
+  let Value::Object(mut object) = value else {
+    diagnostics.push(diagnostic(path.to_string(), &error, format!("Skipped the entry: {}", error)));
+    return None;
+  };
+
+  loop {
+    let culprit = object.keys().cloned().find_map(|key| {
+      let mut trial = object.clone();
+      trial.remove(&key);
+      match serde_json::from_value::<T>(Value::Object(trial)) {
+        Ok(_) => Some((key, None)),
+        Err(e) => {
+          let e = e.to_string();
+          (e != error && missing_field(&e) != Some(key.as_str())).then_some((key, Some(e)))
+        },
+      }
+    });
+    let Some((key, next_error)) = culprit else {
+      // A required field is missing, or is the bad one: without it the only
+      // complaint left is that it's missing
+      let field = missing_field(&error).map(str::to_string).or_else(|| object.keys().find(|key| {
+        let mut trial = object.clone();
+        trial.remove(*key);
+        serde_json::from_value::<T>(Value::Object(trial))
+          .is_err_and(|e| missing_field(&e.to_string()) == Some(key.as_str()))
+      }).cloned());
+      let field_path = match field {
+        Some(field) => format!("{}.{}", path, field),
+        None => path.to_string(),
+      };
+      diagnostics.push(diagnostic(field_path, &error, format!("Skipped the entry: {}", error)));
+      return None;
+    };
+
+    object.remove(&key);
+    diagnostics.push(diagnostic(format!("{}.{}", path, key), &error, format!("Ignored the field: {}", error)));
+    match next_error {
+      Some(e) => error = e,
+      None => return serde_json::from_value(Value::Object(object)).ok(),
+    }
+  }
+}
+
+// A list section; entries that can't be read are skipped. `None` when the
+// section is absent or isn't a list.
+fn read_list<T: DeserializeOwned>(root: &mut Map<String, Value>, key: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<Vec<T>> {
+  match root.remove(key)? {
+    Value::Array(values) => Some(values.into_iter().enumerate()
+      .filter_map(|(index, value)| read_entry(value, &format!("{}[{}]", key, index), diagnostics))
+      .collect()),
+    Value::Null => None,
+    other => {
+      diagnostics.push(Diagnostic {
+        path: key.to_string(),
+        expected: Some("a sequence".to_string()),
+        message: format!("Ignored the section: found {}", value_kind(&other)),
+      });
+      None
+    },
+  }
+}
+
+// A list every document has; defaults to empty
+fn read_required_list<T: DeserializeOwned>(root: &mut Map<String, Value>, key: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<T> {
+  if !root.contains_key(key) {
+    diagnostics.push(Diagnostic {
+      path: key.to_string(),
+      expected: Some("a sequence".to_string()),
+      message: "Missing section, using an empty list".to_string(),
+    });
+  }
+  read_list(root, key, diagnostics).unwrap_or_default()
+}
+
+fn read_value<T: DeserializeOwned>(root: &mut Map<String, Value>, key: &str, diagnostics: &mut Vec<Diagnostic>) -> Option<T> {
+  match root.remove(key)? {
+    Value::Null => None,
+    value => read_entry(value, key, diagnostics),
+  }
+}
+
+fn value_kind(value: &Value) -> &'static str {
+  match value {
+    Value::Null => "null",
+    Value::Bool(_) => "a boolean",
+    Value::Number(_) => "a number",
+    Value::String(_) => "a string",
+    Value::Array(_) => "a sequence",
+    Value::Object(_) => "a map",
+  }
+}
+
+// Parse board JSON, repairing what can be repaired. Only text that isn't JSON
+// at all, or isn't an object, is an error.
+pub fn parse_document(json: &[u8]) -> Result<LenientDocument, String> {
+  let mut value: Value = serde_json::from_slice(json)
+    .map_err(|e| format!("Invalid JSON format: {}", e))?;
+  if !value.is_object() {
+    return Err(format!("Expected a board object, found {}", value_kind(&value)));
+  }
+
+  // Upgrades are reported like repairs; a board that can't be upgraded is
+  // read as it is
+  let mut diagnostics = Vec::new();
+  match migrations::migrate(&mut value) {
+    Ok(report) => {
+      diagnostics.extend(report.applied.into_iter().map(|message| Diagnostic { path: "schemaVersion".to_string(), expected: None, message }));
+      if report.newer {
+        diagnostics.push(Diagnostic {
+          path: "schemaVersion".to_string(),
+          expected: None,
+          message: format!("Written by a newer version of the app (schema version {}); fields it added are dropped", report.from_version),
+        });
+      }
+    },
+    Err(e) => diagnostics.push(Diagnostic { path: "schemaVersion".to_string(), expected: None, message: e }),
+  }
+  let Value::Object(mut root) = value else {
+    return Err(format!("Expected a board object, found {}", value_kind(&value)));
+  };
+  let schema_version = match read_value::<u32>(&mut root, "schemaVersion", &mut diagnostics) {
+    Some(version) if version > 0 => version,
+    _ => {
+      diagnostics.push(Diagnostic {
+        path: "schemaVersion".to_string(),
+        expected: Some("u32".to_string()),
+        message: "Missing or invalid schema version, assuming 1".to_string(),
+      });
+      1
+    },
+  };
+
+  let document = model::BoardDocument {
+    schema_version,
+    notes: read_required_list(&mut root, "notes", &mut diagnostics),
+    connections: read_required_list(&mut root, "connections", &mut diagnostics),
+    shapes: read_required_list(&mut root, "shapes", &mut diagnostics),
+    stacks: read_required_list(&mut root, "stacks", &mut diagnostics),
+    note_styles: read_required_list(&mut root, "noteStyles", &mut diagnostics),
+    document_style: read_value(&mut root, "documentStyle", &mut diagnostics),
+    images: read_list(&mut root, "images", &mut diagnostics),
+    groups: read_list(&mut root, "groups", &mut diagnostics),
+    relation_types: read_list(&mut root, "relationTypes", &mut diagnostics),
+    strokes: read_list(&mut root, "strokes", &mut diagnostics),
+    layers: read_list(&mut root, "layers", &mut diagnostics),
+    templates: read_list(&mut root, "templates", &mut diagnostics),
+    properties: read_value(&mut root, "properties", &mut diagnostics),
+    trash: read_value(&mut root, "trash", &mut diagnostics),
+    note_encryption: read_value(&mut root, "noteEncryption", &mut diagnostics),
+    info: read_value(&mut root, "info", &mut diagnostics),
+  };
+
+  Ok(LenientDocument { document, diagnostics })
+}
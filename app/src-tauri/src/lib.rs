@@ -1,13 +1,134 @@
+mod activity;
+mod archive;
+mod audit;
+mod autosave;
+mod backup;
+mod batch;
+mod bibtex;
+mod board_templates;
+mod bookmarks;
+mod canvas;
+mod capture;
+mod checklist;
+mod cli;
+mod clipboard;
+mod confidential;
+mod csv;
+mod curves;
+mod deep_link;
+mod diagram;
+mod dialogs;
+mod document_stats;
+mod editing;
+mod email;
+mod encryption;
+mod error;
+mod file_watch;
+mod fonts;
+mod freeform;
+mod graph;
+mod groups;
+mod health;
+mod html;
+mod highlights;
+mod icons;
+mod incremental;
+mod ingest;
+mod labels;
+mod language;
+mod lazy_media;
+mod lenient;
+mod markdown;
+mod markings;
+mod layers;
+mod layout;
+mod library;
+mod link_check;
+mod links;
+mod locale;
+mod logging;
+mod media;
+mod merge;
+mod metadata;
+mod migrations;
 mod model;
+mod open_documents;
+mod opml;
+mod outline;
+mod palette;
+mod pdf;
+mod poster;
+mod presentation;
+mod preview;
+mod print;
+mod progress;
+mod properties;
+mod publish;
+mod qr;
+mod raster;
+mod recent_documents;
+mod recent_files;
+mod recovery;
+mod relations;
+mod render;
+mod rich_text;
+mod scapple;
+mod search;
+mod search_index;
+mod security;
+mod session;
+mod settings;
+mod shapes;
+mod share;
+mod share_server;
+mod similar;
+mod snap;
+mod snapshots;
+mod split;
+mod spreadsheet;
+mod stacks;
+mod static_html;
+mod strokes;
+mod styles;
+mod summarize;
+mod sync_write;
+mod tables;
+mod tags;
+mod tasks;
+mod templates;
+mod text_stats;
+mod textures;
+mod theme;
+mod title;
+mod trash;
+mod usage;
+mod validation;
+mod vault;
+mod walkthrough;
+mod web;
 
 use std::sync::Mutex;
-use std::collections::VecDeque;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use std::path::{Path, PathBuf};
+use error::AppError;
+use recovery::AutosaveInfo;
+use dialogs::AsyncFileDialog;
 
 #[derive(serde::Deserialize)]
 struct SaveArgs {
   doc: model::BoardDocument,
+  output_path: Option<String>, // write here instead of asking; see save_path
+  // Protect the .fim file with this password from now on; see encryption.rs
+  password: Option<String>,
+  // Save a protected board unencrypted again
+  remove_password: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+struct SaveCurrentArgs {
+  doc: model::BoardDocument,
+  // Save even though the file changed on disk
+  force: Option<bool>,
 }
 
 #[derive(serde::Deserialize)]
@@ -16,678 +137,5414 @@ struct AutosaveArgs {
   file_path: String,
 }
 
+#[derive(serde::Deserialize)]
+struct ExportCanvasArgs {
+  doc: model::BoardDocument,
+  layers: Option<Vec<model::ID>>, // layers to include; defaults to the visible ones
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Deserialize)]
+struct ExportHtmlArgs {
+  doc: model::BoardDocument,
+  layers: Option<Vec<model::ID>>, // layers to include; defaults to the visible ones
+  note_ids: Option<Vec<model::ID>>, // only export these notes; see export_selection
+  title: Option<String>, // page title; defaults to the board's suggested title
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
 #[derive(serde::Deserialize)]
 struct ExportTextArgs {
   doc: model::BoardDocument,
-  format: String, // "txt", "rtf", "opml"
+  format: String, // "txt", "md", "rtf", "opml", "dot", "mermaid", "csv", "tsv"
   ordering: Option<String>, // "spatial", "connections", "hierarchical"
+  scrub_metadata: Option<bool>, // strip timestamps, style names and IDs
+  label_filter: Option<labels::LabelFilter>, // only export notes with these labels
+  include_properties: Option<bool>, // emit note properties as OPML attributes
+  include_stats: Option<bool>, // append word counts and frequent terms (txt, md and rtf)
+  layers: Option<Vec<model::ID>>, // layers to include; defaults to the visible ones
+  note_ids: Option<Vec<model::ID>>, // only export these notes; see export_selection
+  locale: Option<String>, // language of headers and dates, e.g. "de"; defaults to the setting
+  timezone: Option<String>, // "local", "UTC" or "+02:00"; defaults to the setting
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Deserialize)]
+struct ShareServerArgs {
+  doc: model::BoardDocument,
+  port: Option<u16>, // defaults to share_server::DEFAULT_PORT; 0 for any free port
+}
+
+#[derive(serde::Deserialize)]
+struct BatchExportArgs {
+  input_dir: Option<String>, // folder of .fim boards; asks for one when omitted
+  format: String, // "txt", "md", "opml", "pdf", "png"
+  output_dir: Option<String>, // asks for a folder when omitted
+  ordering: Option<String>, // note order in text formats; see ExportTextArgs
+  locale: Option<String>,
+  timezone: Option<String>,
+}
+
+// What `preview_export` returns: `content` for text formats, `png` for
+// visual ones
+#[derive(serde::Serialize, Debug, Clone)]
+struct ExportPreview {
+  format: String,
+  content: Option<String>,
+  // Only the start of the content is returned
+  truncated: bool,
+  png: Option<Vec<u8>>,
+}
+
+impl ExportPreview {
+  fn new(format: String) -> ExportPreview {
+    ExportPreview { format, content: None, truncated: false, png: None }
+  }
+}
+
+// Longest text returned by `preview_export`, in characters
+const EXPORT_PREVIEW_MAX_CHARS: usize = 100_000;
+
+#[derive(serde::Deserialize)]
+struct PngExportArgs {
+  doc: model::BoardDocument,
+  note_ids: Option<Vec<model::ID>>, // only export these notes; see export_selection
+  output_path: Option<String>, // write here instead of asking; see save_path
+  #[serde(flatten)]
+  options: raster::RasterOptions, // scale or dpi, plus region or padding
+}
+
+#[derive(serde::Deserialize)]
+struct RenderArgs {
+  doc: model::BoardDocument,
+  note_ids: Option<Vec<model::ID>>, // only draw these notes; see export_selection
+  #[serde(flatten)]
+  options: raster::RasterOptions, // scale or dpi, plus region or padding
+  scrub_metadata: Option<bool>, // SVG: strip EXIF and text chunks from embedded images
+}
+
+// A PDF for the backend to generate, instead of one rendered by the frontend
+#[derive(serde::Deserialize)]
+struct PdfRenderArgs {
+  doc: model::BoardDocument,
+  note_ids: Option<Vec<model::ID>>, // only export these notes; see export_selection
+  #[serde(flatten)]
+  options: pdf::PdfOptions, // fit or tile, resolution, poster scale and overlap
+  markings: Option<markings::ExportMarkings>,
+  scrub_metadata: Option<bool>, // leave out document information and XMP metadata
+  protection: Option<pdf::PdfProtection>, // passwords and print/copy restrictions
+}
+
+// A board to print; pages are laid out as for PDF exports
+#[derive(serde::Deserialize)]
+struct PrintArgs {
+  doc: model::BoardDocument,
+  note_ids: Option<Vec<model::ID>>, // only print these notes; see export_selection
+  page_size: String,
+  orientation: String,
+  #[serde(flatten)]
+  options: pdf::PdfOptions, // fit on one page or tile across pages
+  printer: Option<String>, // default printer when None
+  copies: Option<u32>,
+  // Open the PDF in the default viewer to print from its dialog
+  show_dialog: Option<bool>,
+}
+
+// Where to save a PNG export and what region/scale the frontend should render
+#[derive(serde::Serialize, Debug, Clone)]
+struct PngExportTarget {
+  path: String,
+  plan: raster::RasterPlan,
+  // The part of the board to render, when the export is limited to a selection
+  selection: Option<model::BoardDocument>,
+}
+
+#[derive(serde::Deserialize)]
+struct PosterArgs {
+  doc: model::BoardDocument,
+  #[serde(flatten)]
+  options: poster::PosterOptions,
+}
+
+#[derive(serde::Deserialize)]
+struct ExportThemeArgs {
+  doc: model::BoardDocument,
+  export_theme: String, // "light", "dark", "custom"
+  background: Option<String>, // required for "custom"
+}
+
+#[derive(serde::Deserialize)]
+struct PresentationPlanArgs {
+  doc: model::BoardDocument,
+  path: Option<Vec<model::ID>>, // note/shape IDs in slide order; defaults to the stacks
+}
+
+#[derive(serde::Deserialize)]
+struct PresentationExportArgs {
+  doc: model::BoardDocument,
+  path: Option<Vec<model::ID>>,
+  format: String, // "pptx", "html"
+  title: Option<String>,
+  images: Vec<Vec<u8>>, // one rendered PNG per planned slide, in order
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Deserialize)]
+struct WalkthroughArgs {
+  doc: model::BoardDocument,
+  #[serde(flatten)]
+  options: walkthrough::WalkthroughOptions,
+}
+
+#[derive(serde::Deserialize)]
+struct WalkthroughExportArgs {
+  doc: model::BoardDocument,
+  #[serde(flatten)]
+  options: walkthrough::WalkthroughOptions,
+  format: String, // "gif", "webm"
+  frames: Option<Vec<Vec<u8>>>, // gif: one rendered PNG per planned frame
+  video: Option<Vec<u8>>, // webm: the clip recorded by the webview
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Deserialize)]
+struct TextOutlineArgs {
+  text: String,
+  #[serde(flatten)]
+  options: outline::OutlineImportOptions, // mode ("connections", "stack") and origin
+}
+
+#[derive(serde::Deserialize)]
+struct SplitTextArgs {
+  text: String,
+  #[serde(flatten)]
+  options: split::SplitOptions, // strategy, max_length, layout ("column", "stack") and origin
+}
+
+#[derive(serde::Deserialize)]
+struct IngestImageArgs {
+  doc: model::BoardDocument,
+  data: Vec<u8>,
+  max_dimension: Option<u32>, // longest side to keep; none keeps the size
+  format: Option<String>, // "webp" (default) or "png"
+}
+
+#[derive(serde::Deserialize)]
+struct ClipboardOutlineArgs {
+  text: String,
+  #[serde(flatten)]
+  options: outline::OutlineImportOptions, // mode ("connections", "stack") and origin
+}
+
+#[derive(serde::Deserialize)]
+struct ImageFolderArgs {
+  dir: Option<String>, // asks for a folder when missing
+  origin: Option<model::Point>, // top-left of the grid
+}
+
+#[derive(serde::Deserialize)]
+struct BookmarksImportArgs {
+  #[serde(flatten)]
+  options: bookmarks::BookmarksImportOptions, // mode ("connections", "stack", "shapes") and origin
+}
+
+#[derive(serde::Deserialize)]
+struct ExportThemeFileArgs {
+  doc: model::BoardDocument,
+  name: Option<String>, // theme name stored in the file
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Deserialize)]
+struct ApplyThemeFileArgs {
+  doc: model::BoardDocument,
+  path: Option<String>, // asks for a .fimtheme file when missing
+}
+
+#[derive(serde::Deserialize)]
+struct SaveLibraryStyleArgs {
+  doc: model::BoardDocument,
+  style_id: model::ID,
+  name: Option<String>, // defaults to the style's name
+}
+
+#[derive(serde::Deserialize)]
+struct InsertLibraryStylesArgs {
+  doc: model::BoardDocument,
+  ids: Vec<model::ID>,
+}
+
+#[derive(serde::Deserialize)]
+struct ReplaceColorArgs {
+  doc: model::BoardDocument,
+  from: String,
+  to: String,
+}
+
+#[derive(serde::Deserialize)]
+struct SpreadsheetImportArgs {
+  path: Option<String>, // asks for a file when missing
+  origin: Option<model::Point>, // top-left of the grid
+}
+
+#[derive(serde::Deserialize)]
+struct HighlightsImportArgs {
+  origin: Option<model::Point>, // top-left of the first book's stack
+}
+
+#[derive(serde::Deserialize)]
+struct BibtexImportArgs {
+  path: Option<String>, // asks for a file when missing
+  origin: Option<model::Point>, // top-left of the grid
+}
+
+#[derive(serde::Deserialize)]
+struct ImportTextureArgs {
+  doc: model::BoardDocument,
+  path: Option<String>, // asks for an image when missing
+  scale: Option<f64>,
+  opacity: Option<f64>,
+}
+
+#[derive(serde::Deserialize)]
+struct ImportDocumentArgs {
+  path: Option<String>, // asks for an .opml or .md file when missing
+  mode: Option<String>, // Markdown layout: "connections" (default) or "stack"
+}
+
+#[derive(serde::Deserialize)]
+struct AddImageArgs {
+  doc: model::BoardDocument,
+  path: Option<String>, // asks for an image when missing
+}
+
+// What `add_image_to_document` returns: the board with the image among its
+// images, for a note to refer to by `image_id`
+#[derive(serde::Serialize, Debug, Clone)]
+struct AddedImage {
+  document: model::BoardDocument,
+  image_id: model::ID,
+}
+
+#[derive(serde::Deserialize)]
+struct ImageDataArgs {
+  image_id: String,
+  // Board file to read from; defaults to the open board's file
+  file_path: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct ImageData {
+  id: String,
+  mime: String,
+  data_base64: String,
+}
+
+#[derive(serde::Deserialize)]
+struct UrlImportArgs {
+  url: String,
+  origin: Option<model::Point>, // top-left of the card
+}
+
+#[derive(serde::Deserialize)]
+struct VaultExportArgs {
+  doc: model::BoardDocument,
+  dir: Option<String>, // asks for a folder when missing
+  mode: String, // "notes" or "subtrees"
+}
+
+#[derive(serde::Deserialize)]
+struct VaultSyncArgs {
+  doc: model::BoardDocument,
+  dir: Option<String>, // asks for a folder when missing
+}
+
+#[derive(serde::Deserialize)]
+struct FreeformImportArgs {
+  origin: Option<model::Point>, // top-left of the imported assets
+}
+
+#[derive(serde::Deserialize)]
+struct CopyNotesArgs {
+  doc: model::BoardDocument,
+  note_ids: Vec<String>,
+  format: String, // "plain", "rtf", "markdown"
+}
+
+#[derive(serde::Deserialize)]
+struct LabelFilterArgs {
+  doc: model::BoardDocument,
+  filter: labels::LabelFilter,
+}
+
+#[derive(serde::Deserialize)]
+struct TagFilterArgs {
+  doc: model::BoardDocument,
+  filter: tags::TagFilter,
+}
+
+#[derive(serde::Deserialize)]
+struct SearchNotesArgs {
+  doc: model::BoardDocument,
+  query: String,
+  #[serde(flatten)]
+  options: search::SearchOptions, // case_sensitive, whole_word, regex, limit
+  // Also search the boards in the recent files list
+  include_recent: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+struct DiffDocumentsArgs {
+  path_a: String,
+  path_b: String,
+}
+
+#[derive(serde::Deserialize)]
+struct MergeDocumentsArgs {
+  // Ours: the merge starts from this board
+  path_a: String,
+  // Theirs: its changes are brought in
+  path_b: String,
+  // The copy both started from, for a three-way merge
+  base_path: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct PropertyQueryArgs {
+  doc: model::BoardDocument,
+  query: properties::PropertyQuery,
+}
+
+#[derive(serde::Deserialize)]
+struct InstantiateTemplateArgs {
+  doc: model::BoardDocument,
+  template_id: model::ID,
+  values: std::collections::HashMap<String, String>,
+  position: Option<model::Point>, // insertion point; defaults to the origin
+}
+
+#[derive(serde::Deserialize)]
+struct CreateSnapshotArgs {
+  doc: model::BoardDocument,
+  label: Option<String>,
+  path: Option<String>, // the board's .fim file; defaults to the open board's
+}
+
+#[derive(serde::Deserialize)]
+struct SaveAsTemplateArgs {
+  doc: model::BoardDocument,
+  name: String,
+  keep_content: Option<bool>, // keep note text and the like; defaults to false
+  overwrite: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+struct MoveToLayerArgs {
+  doc: model::BoardDocument,
+  ids: Vec<model::ID>,
+  layer_id: Option<model::ID>, // None moves items back to the base layer
+}
+
+#[derive(serde::Deserialize)]
+struct ReorderArgs {
+  doc: model::BoardDocument,
+  ids: Vec<model::ID>, // note and/or shape IDs
+}
+
+#[derive(serde::Deserialize)]
+struct CreateGroupArgs {
+  doc: model::BoardDocument,
+  note_ids: Vec<model::ID>,
+  shape_ids: Vec<model::ID>,
+  label: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct GroupArgs {
+  doc: model::BoardDocument,
+  group_id: model::ID,
+}
+
+#[derive(serde::Deserialize)]
+struct MoveGroupArgs {
+  doc: model::BoardDocument,
+  group_id: model::ID,
+  dx: f64,
+  dy: f64,
+}
+
+#[derive(serde::Deserialize)]
+struct DeleteItemsArgs {
+  doc: model::BoardDocument,
+  ids: Vec<model::ID>, // note, shape and/or connection IDs
+}
+
+#[derive(serde::Deserialize)]
+struct RestoreFromTrashArgs {
+  doc: model::BoardDocument,
+  ids: Vec<model::ID>, // trashed note and/or connection IDs
+}
+
+#[derive(serde::Deserialize)]
+struct EmptyTrashArgs {
+  doc: model::BoardDocument,
+  older_than: Option<chrono::DateTime<chrono::Utc>>, // only items deleted before this; all when missing
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct EmptyTrashResult {
+  document: model::BoardDocument,
+  removed: usize,
+}
+
+#[derive(serde::Deserialize)]
+struct AnalyzeGraphArgs {
+  doc: model::BoardDocument,
+  export_csv: Option<bool>, // also ask where to save per-note metrics as CSV
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Deserialize)]
+struct FindPathArgs {
+  doc: model::BoardDocument,
+  from_note_id: model::ID,
+  to_note_id: model::ID,
+  directed: Option<bool>, // only follow connections from source to destination
+  alternatives: Option<usize>, // further paths after the shortest; defaults to 2
+}
+
+#[derive(serde::Deserialize)]
+struct SuggestSimilarArgs {
+  doc: model::BoardDocument,
+  text: String,
+  limit: Option<usize>, // per kind (notes, boards); defaults to 5
+  min_score: Option<f64>, // 0..1; defaults to 0.4
+  exclude_note_id: Option<model::ID>, // the note being edited
+  include_library: Option<bool>, // also match titles/tags of boards from the last library scan
+}
+
+#[derive(serde::Deserialize)]
+struct SuggestStacksArgs {
+  doc: model::BoardDocument,
+  #[serde(flatten)]
+  options: stacks::StackSuggestionOptions, // max_gap, align_tolerance, min_notes
+}
+
+#[derive(serde::Deserialize)]
+struct LayoutDocumentArgs {
+  doc: model::BoardDocument,
+  #[serde(flatten)]
+  options: layout::LayoutOptions, // algorithm, shape_id, spacing, direction, iterations
+}
+
+#[derive(serde::Deserialize)]
+struct MoveShapeArgs {
+  doc: model::BoardDocument,
+  shape_id: model::ID,
+  dx: f64,
+  dy: f64,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct GraphAnalysis {
+  report: graph::GraphReport,
+  csv_path: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct SplitByActivityArgs {
+  doc: model::BoardDocument,
+  cutoff: chrono::DateTime<chrono::Utc>,
+  save_archive: Option<bool>, // also ask where to save the stale branches
+  output_path: Option<String>, // write here instead of asking; see save_path
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct ActivitySplitResult {
+  #[serde(flatten)]
+  split: activity::ActivitySplit,
+  archive_path: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct RecentlyEditedArgs {
+  doc: model::BoardDocument,
+  since: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(serde::Deserialize)]
+struct TranslateNotesArgs {
+  doc: model::BoardDocument,
+  note_ids: Vec<model::ID>,
+  target_lang: String, // as the configured provider expects it, e.g. "de"
+}
+
+#[derive(serde::Deserialize)]
+struct SummarizeNotesArgs {
+  doc: model::BoardDocument,
+  note_ids: Vec<model::ID>,
+}
+
+#[derive(serde::Deserialize)]
+struct NotesPassphraseArgs {
+  doc: model::BoardDocument,
+  passphrase: String,
+  remember: Option<bool>, // keep the key in the keychain
+}
+
+#[derive(serde::Deserialize)]
+struct UnlockDocumentArgs {
+  file_path: String,
+  password: String,
+}
+
+#[derive(serde::Deserialize)]
+struct UnlockNotesArgs {
+  doc: model::BoardDocument,
+  passphrase: Option<String>, // tries the session and the keychain when missing
+  remember: Option<bool>,
+}
+
+#[derive(serde::Deserialize)]
+struct LockNotesArgs {
+  doc: model::BoardDocument,
+  forget: Option<bool>, // also remove the key from the keychain
+}
+
+#[derive(serde::Deserialize)]
+struct SetConfidentialArgs {
+  doc: model::BoardDocument,
+  note_ids: Vec<model::ID>,
+  confidential: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct PublishBoardArgs {
+  doc: model::BoardDocument,
+  target: String, // "confluence" or "notion"
+  space: Option<String>, // Confluence space key
+  page: Option<String>, // parent page id
+  path: Option<String>, // the board's file, to update its page on re-publish
+  image: Option<String>, // base64 PNG render; a preview is rendered when missing
+}
+
+#[derive(serde::Deserialize)]
+struct DuplicateNotesArgs {
+  doc: model::BoardDocument,
+  note_ids: Vec<model::ID>,
+  include_descendants: Option<bool>, // also copy notes reachable through outgoing connections
+  offset: Option<model::Point>, // defaults to 20, 20
+}
+
+// Result of an edit that leaves locked notes and shapes untouched
+#[derive(serde::Serialize, Debug, Clone)]
+struct EditResult {
+  document: model::BoardDocument,
+  skipped_locked: Vec<model::ID>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct GroupCreated {
+  document: model::BoardDocument,
+  group_id: model::ID,
+}
+
+#[derive(serde::Deserialize)]
+struct ShareExportArgs {
+  doc: model::BoardDocument,
+  format: String, // "fim", "json", "txt", "md", "rtf", "opml", "dot", "mermaid", "csv", "tsv", "png", "pdf"
+  data: Option<Vec<u8>>, // PNG/PDF bytes rendered by the frontend
+  layers: Option<Vec<model::ID>>, // layers in text exports; defaults to the visible ones
+  locale: Option<String>, // for text exports; defaults to the setting
+  timezone: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct EmailExportArgs {
+  doc: model::BoardDocument,
+  to: Option<Vec<String>>,
+  subject: Option<String>, // defaults to the board's suggested title
+  png: Option<Vec<u8>>, // renders by the frontend to attach; a preview
+  pdf: Option<Vec<u8>>, // thumbnail is attached when neither is given
+  layers: Option<Vec<model::ID>>, // layers in the outline; defaults to the visible ones
+  locale: Option<String>, // of the text body; defaults to the setting
+  timezone: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct EmailExportResult {
+  path: String,
+  // false when no mail client could be started; the file is still there
+  opened: bool,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct ShareResult {
+  path: String,
+  // false when no share sheet is available and the file was revealed instead
+  presented: bool,
+}
+
+// Options shared by the text-based generators
+#[derive(Debug, Clone)]
+struct TextExportOptions {
+  ordering: String,
+  scrub_metadata: bool,
+  include_properties: bool,
+  include_stats: bool,
+  locale: locale::ExportLocale,
+}
+
+fn export_locale_settings(app: &tauri::AppHandle) -> (String, String) {
+  app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|app_state| (
+      app_state.settings.export_locale.clone(),
+      app_state.settings.export_timezone.clone(),
+    )))
+    .unwrap_or_default()
+}
+
+// The export locale asked for, else the one in settings
+fn export_locale(app: &tauri::AppHandle, locale: Option<&str>, timezone: Option<&str>) -> Result<locale::ExportLocale, String> {
+  let (default_locale, default_timezone) = export_locale_settings(app);
+  locale::ExportLocale::new(locale.unwrap_or(&default_locale), timezone.unwrap_or(&default_timezone))
+}
+
+fn localized_markings(app: &tauri::AppHandle, markings: markings::ExportMarkings) -> Result<markings::ExportMarkings, String> {
+  let (locale, timezone) = export_locale_settings(app);
+  markings.localized(&locale, &timezone)
+}
+
+
+#[derive(Debug, Default)]
+struct AppState {
+  recent_files: recent_files::RecentFiles,
+  // Last open board and last save location, kept across launches
+  session: session::Session,
+  path_scope: security::PathScope,
+  settings: settings::AppSettings,
+  // Documents for each window to open once its frontend has mounted, and the
+  // windows whose frontend has
+  pending_open_documents: std::collections::HashMap<String, Vec<OpenDocumentEvent>>,
+  ready_windows: std::collections::HashSet<String>,
+  tasks: tasks::TaskRegistry,
+  usage: usage::UsageTracker,
+  backups: backup::BackupTracker,
+  autosave: autosave::AutosaveQueue,
+  // Each window's board; see open_documents.rs
+  open_documents: open_documents::OpenDocuments,
+  // The board being shared over the LAN, if any; see share_server.rs
+  share_server: Option<share_server::ShareServer>,
+}
+
+// Payload of the `open-document` event emitted when the OS asks us to open a file
+#[derive(serde::Serialize, Debug, Clone)]
+struct OpenDocumentEvent {
+  path: String,
+  document: Option<model::BoardDocument>,
+  error: Option<String>,
+  // Note to scroll to and highlight once the document is shown (deep links)
+  focus_note_id: Option<String>,
+  // Set when the board was upgraded or comes from a newer version of the
+  // app, which the frontend should then open read-only
+  migration: Option<migrations::MigrationReport>,
+}
+
+// Payload of the `document-changed-externally` event, emitted when the open
+// board's file changes on disk by other means than saving it here
+#[derive(serde::Serialize, Debug, Clone)]
+struct DocumentChangedEvent {
+  path: String,
+  // False when the file was deleted or moved away
+  exists: bool,
+}
+
+// Payload of the `document-migrated` event, emitted when an opened board was
+// upgraded from an older schema version or comes from a newer one
+#[derive(serde::Serialize, Debug, Clone)]
+struct DocumentMigratedEvent {
+  path: String,
+  report: migrations::MigrationReport,
+}
+
+// Payload of the `autosave-failed` event, for autosaves written after the
+// command returned
+#[derive(serde::Serialize, Debug, Clone)]
+struct AutosaveFailedEvent {
+  path: String,
+  error: String,
+}
+
+// Payload of the `focus-note` event for deep links into the open document
+#[derive(serde::Serialize, Debug, Clone)]
+struct FocusNoteEvent {
+  note_id: String,
+}
+
+// Payload of the `quick-capture-note` event: a captured note for the board
+// the window has open, to be added like any other edit
+#[derive(serde::Serialize, Debug, Clone)]
+struct QuickCaptureEvent {
+  note: model::Note,
+}
+
+// An image dropped onto the window, placed at the drop position
+#[derive(serde::Serialize, Debug, Clone)]
+struct DroppedImage {
+  image: model::EmbeddedImage,
+  position: model::Point,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+struct DropError {
+  path: String,
+  error: String,
+}
+
+// Payload of the `files-dropped` event; one event is emitted per drop
+#[derive(serde::Serialize, Debug, Clone)]
+struct FilesDroppedEvent {
+  position: model::Point,
+  documents: Vec<OpenDocumentEvent>,
+  images: Vec<DroppedImage>,
+  errors: Vec<DropError>,
+}
+
+
+// Helper functions for path authorization
+fn authorize_path(app: &tauri::AppHandle, path: &Path) {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.path_scope.authorize(path);
+    }
+  }
+}
+
+// Register a long-running operation under its progress operation ID so it
+// can be listed and cancelled; pair with end_task
+fn begin_task(app: &tauri::AppHandle, kind: &str, progress: &progress::Progress) {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.tasks.start(progress.operation_id(), kind, progress.cancel_flag());
+    }
+  }
+}
+
+fn end_task<T>(app: &tauri::AppHandle, progress: &progress::Progress, result: &Result<T, String>) {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.tasks.finish(progress.operation_id(), result.as_ref().err().map(String::as_str));
+    }
+  }
+}
+
+fn check_write_path(app: &tauri::AppHandle, path: &Path) -> Result<PathBuf, String> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      return app_state.path_scope.check_write(path);
+    }
+  }
+  security::PathScope::default().check_write(path)
+}
+
+fn check_write_folder(app: &tauri::AppHandle, dir: &Path) -> Result<PathBuf, String> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      return app_state.path_scope.check_write_folder(dir);
+    }
+  }
+  security::PathScope::default().check_write_folder(dir)
+}
+
+// Where to write an export or save: `output_path` when given, checked
+// against the path scope, so scripts and tests can run without a dialog;
+// otherwise the file the user picks in `dialog`
+async fn save_path(
+  app: &tauri::AppHandle,
+  output_path: Option<&str>,
+  dialog: tauri_plugin_dialog::FileDialogBuilder<tauri::Wry>,
+  operation: &str,
+) -> Result<PathBuf, AppError> {
+  if let Some(output_path) = output_path {
+    return Ok(check_write_path(app, Path::new(output_path))?);
+  }
+  let path = match dialog.save_file_async().await {
+    Some(p) => match p.as_path() {
+      Some(path) => path.to_path_buf(),
+      None => return Err("Invalid save path selected".into()),
+    },
+    None => return Err(format!("{} operation cancelled by user", operation).into()),
+  };
+  authorize_path(app, &path);
+  Ok(path)
+}
+
+// Helper functions for the audit log
+fn record_audit<T>(
+  app: &tauri::AppHandle,
+  operation: &str,
+  path: &Path,
+  document_hash: impl FnOnce() -> Option<String>,
+  result: &Result<T, String>,
+) {
+  let mut enabled = false;
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      enabled = app_state.settings.audit_log;
+    }
+  }
+
+  if enabled {
+    let entry = audit::AuditEntry::new(operation, path, document_hash(), result);
+    if let Err(e) = audit::append_entry(&entry) {
+      log::warn!("{}", e);
+    }
+  }
+}
+
+// Feed the local usage statistics, when the user opted in
+fn record_usage(app: &tauri::AppHandle, update: impl FnOnce(&mut usage::UsageTracker)) {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      if app_state.settings.usage_stats {
+        update(&mut app_state.usage);
+      }
+    }
+  }
+}
+
+fn add_recent_file(app: &tauri::AppHandle, path_str: &str) {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.recent_files.touch(path_str);
+      if let Err(e) = app_state.recent_files.save() {
+        log::warn!("{}", e);
+      }
+    }
+  }
+  recent_documents::note_recent_document(app, Path::new(path_str));
+}
+
+// Helper functions for documents opened by the OS (file associations, argv)
+fn supported_document_path(path: &Path) -> bool {
+  let extension = path.extension()
+    .and_then(|ext| ext.to_str())
+    .map(|ext| ext.to_ascii_lowercase());
+  matches!(extension.as_deref(), Some("fim") | Some("json") | Some("canvas") | Some("scap")) && path.is_file()
+}
+
+// Relative paths are taken relative to `cwd`, the folder the app was launched from
+fn document_paths_from_args<I: IntoIterator<Item = std::ffi::OsString>>(args: I, cwd: &Path) -> Vec<PathBuf> {
+  args.into_iter()
+    .filter_map(|arg| {
+      let arg_str = arg.to_string_lossy();
+      // Skip flags such as macOS' legacy `-psn_*` process serial number
+      if arg_str.starts_with('-') {
+        return None;
+      }
+      // Linux desktop entries may pass file:// URLs (%U)
+      if arg_str.starts_with("file://") {
+        return tauri::Url::parse(&arg_str).ok().and_then(|url| url.to_file_path().ok());
+      }
+      Some(cwd.join(arg))
+    })
+    .filter(|path| supported_document_path(path))
+    .collect()
+}
+
+// Bring a window forward; false when there is no window with that label
+fn focus_window(app: &tauri::AppHandle, label: &str) -> bool {
+  let Some(window) = app.get_webview_window(label) else {
+    return false;
+  };
+  let _ = window.unminimize();
+  let _ = window.show();
+  let _ = window.set_focus();
+  true
+}
+
+fn focus_main_window(app: &tauri::AppHandle) {
+  if focus_window(app, "main") {
+    return;
+  }
+
+  // The last window was closed (macOS keeps the app alive); create a new one.
+  // Its frontend has to ask for pending documents again once it mounts.
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.ready_windows.remove("main");
+    }
+  }
+  if let Err(e) = tauri::WebviewWindowBuilder::new(app, "main", tauri::WebviewUrl::default())
+    .title("FIM")
+    .inner_size(1200.0, 800.0)
+    .build()
+  {
+    log::error!("Failed to create window for opened document: {}", e);
+  }
+}
+
+// Load a document handed to us outside of our own dialogs (Finder, Explorer,
+// drag and drop, deep links) and record it like a regular open.
+fn load_external_document(app: &tauri::AppHandle, path: &Path) -> OpenDocumentEvent {
+  let result = load_document_migrated(path, lazy_media_limit(app), &progress::Progress::none());
+  record_audit(app, "open", path, || result.as_ref().ok().and_then(|(doc, _)| audit::hash_document(doc)), &result);
+
+  let path_str = path.to_string_lossy().to_string();
+  match result {
+    Ok((doc, report)) => {
+      add_recent_file(app, &path_str);
+      record_usage(app, |usage| usage.opened(path, &doc));
+      let migration = (!report.is_empty()).then_some(report);
+      OpenDocumentEvent { path: path_str, document: Some(doc), error: None, focus_note_id: None, migration }
+    },
+    Err(e) => OpenDocumentEvent { path: path_str, document: None, error: Some(e), focus_note_id: None, migration: None },
+  }
+}
+
+fn open_document_from_os(app: &tauri::AppHandle, path: &Path) {
+  // The user picked this file in Finder/Explorer, which is as good as a dialog
+  authorize_path(app, path);
+  // Show the window that has it instead of loading a second copy
+  if let Some(owner) = document_window(app, path) {
+    focus_window(app, &owner);
+    return;
+  }
+  deliver_open_document(app, load_external_document(app, path));
+}
+
+// Launching the app again while it runs (double-clicking a board on Windows
+// or Linux) hands the new process's arguments to this one and exits it. Its
+// fim:// links arrive through the deep-link plugin's `on_open_url` instead.
+#[cfg(desktop)]
+fn handle_second_instance(app: &tauri::AppHandle, args: Vec<String>, cwd: String) {
+  let paths = document_paths_from_args(args.into_iter().skip(1).map(std::ffi::OsString::from), Path::new(&cwd));
+  if paths.is_empty() {
+    focus_main_window(app);
+  }
+  for path in paths {
+    open_document_from_os(app, &path);
+  }
+}
+
+fn deliver_open_document(app: &tauri::AppHandle, event: OpenDocumentEvent) {
+  focus_main_window(app);
+
+  // Queue the document until the frontend has mounted and can listen for events
+  let mut frontend_ready = false;
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      frontend_ready = app_state.ready_windows.contains("main");
+      if !frontend_ready {
+        app_state.pending_open_documents.entry("main".to_string()).or_default().push(event.clone());
+      }
+    }
+  }
+  if frontend_ready {
+    if let Err(e) = app.emit_to("main", "open-document", event) {
+      log::error!("Failed to emit open-document event: {}", e);
+    }
+  }
+}
+
+// Spacing between images dropped together so they don't stack on top of each other
+const DROP_CASCADE_OFFSET: f64 = 24.0;
+
+fn handle_files_dropped(window: &tauri::Window, paths: &[PathBuf], position: tauri::PhysicalPosition<f64>) {
+  let app = window.app_handle();
+  // The frontend works in CSS pixels
+  let scale_factor = window.scale_factor().unwrap_or(1.0);
+  let position = model::Point { x: position.x / scale_factor, y: position.y / scale_factor };
+
+  let mut event = FilesDroppedEvent {
+    position: position.clone(),
+    documents: Vec::new(),
+    images: Vec::new(),
+    errors: Vec::new(),
+  };
+
+  for path in paths {
+    let extension = path.extension()
+      .and_then(|ext| ext.to_str())
+      .map(|ext| ext.to_ascii_lowercase())
+      .unwrap_or_default();
+    let path_str = path.to_string_lossy().to_string();
+
+    match extension.as_str() {
+      "fim" | "json" | "canvas" | "scap" => {
+        authorize_path(app, path);
+        event.documents.push(load_external_document(app, path));
+      },
+      _ if media::is_image_path(path) => match media::import_image_file(path) {
+        Ok(image) => {
+          let offset = event.images.len() as f64 * DROP_CASCADE_OFFSET;
+          event.images.push(DroppedImage {
+            image,
+            position: model::Point { x: position.x + offset, y: position.y + offset },
+          });
+        },
+        Err(e) => event.errors.push(DropError { path: path_str, error: e }),
+      },
+      "md" | "markdown" | "opml" => event.errors.push(DropError {
+        path: path_str,
+        error: format!("Importing .{} files is not supported yet", extension),
+      }),
+      _ => event.errors.push(DropError {
+        path: path_str,
+        error: "Unsupported file type".to_string(),
+      }),
+    }
+  }
+
+  if let Err(e) = window.emit("files-dropped", event) {
+    log::error!("Failed to emit files-dropped event: {}", e);
+  }
+}
+
+fn handle_window_event(window: &tauri::Window, event: &tauri::WindowEvent) {
+  match event {
+    tauri::WindowEvent::DragDrop(tauri::DragDropEvent::Drop { paths, position }) => {
+      handle_files_dropped(window, paths, *position);
+    },
+    tauri::WindowEvent::Destroyed => {
+      if let Some(state) = window.app_handle().try_state::<Mutex<AppState>>() {
+        if let Ok(mut app_state) = state.lock() {
+          app_state.open_documents.close_window(window.label());
+          app_state.pending_open_documents.remove(window.label());
+          app_state.ready_windows.remove(window.label());
+          // Sharing ends with the window whose board is shared
+          if app_state.share_server.as_ref().is_some_and(|server| server.window == window.label()) {
+            if let Some(server) = app_state.share_server.take() {
+              server.stop();
+            }
+          }
+        }
+      }
+    },
+    _ => {},
+  }
+}
+
+// Whether the frontend of `window` has mounted and listens for the events
+// sent to it; it subscribes before calling take_pending_open_documents
+fn frontend_ready(app: &tauri::AppHandle, window: &str) -> bool {
+  app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|app_state| app_state.ready_windows.contains(window)))
+    .unwrap_or(false)
+}
+
+// Handle `fim://open?path=...&note=...`. Links may come from any web page,
+// so the target is opened read-only as far as the path scope is concerned.
+fn open_deep_link(app: &tauri::AppHandle, url: &tauri::Url) {
+  let target = match deep_link::parse_deep_link(url) {
+    Ok(target) => target,
+    Err(e) => {
+      log::warn!("Ignoring deep link '{}': {}", url, e);
+      return;
+    },
+  };
+
+  if !supported_document_path(&target.path) {
+    log::warn!("Ignoring deep link to unsupported or missing file '{}'", target.path.display());
+    return;
+  }
+
+  // Don't reload an open document (and lose unsaved edits) just to reveal a note
+  if let Some(owner) = document_window(app, &target.path).filter(|owner| frontend_ready(app, owner)) {
+    focus_window(app, &owner);
+    if let Some(note_id) = target.note_id {
+      if let Err(e) = app.emit_to(owner.as_str(), "focus-note", FocusNoteEvent { note_id }) {
+        log::error!("Failed to emit focus-note event: {}", e);
+      }
+    }
+    return;
+  }
+
+  let mut event = load_external_document(app, &target.path);
+  event.focus_note_id = target.note_id;
+  deliver_open_document(app, event);
+}
+
+// Helper functions for quick capture
+fn show_quick_capture_window(app: &tauri::AppHandle) {
+  if let Some(window) = app.get_webview_window(capture::QUICK_CAPTURE_WINDOW) {
+    let _ = window.show();
+    let _ = window.set_focus();
+    return;
+  }
+
+  if let Err(e) = tauri::WebviewWindowBuilder::new(
+    app,
+    capture::QUICK_CAPTURE_WINDOW,
+    tauri::WebviewUrl::App("quick-capture.html".into()),
+  )
+    .title("Quick Capture")
+    .inner_size(420.0, 160.0)
+    .resizable(false)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .center()
+    .focused(true)
+    .build()
+  {
+    log::error!("Failed to open quick-capture window: {}", e);
+  }
+}
+
+#[cfg(desktop)]
+fn register_quick_capture_shortcut(app: &tauri::AppHandle, previous: &str, shortcut: &str) -> Result<(), String> {
+  use tauri_plugin_global_shortcut::GlobalShortcutExt;
+
+  if !previous.is_empty() {
+    let _ = app.global_shortcut().unregister(previous);
+  }
+  if shortcut.is_empty() {
+    return Ok(());
+  }
+  app.global_shortcut().register(shortcut)
+    .map_err(|e| format!("Failed to register quick-capture shortcut '{}': {}", shortcut, e))
+}
+
+// The inbox board, checked against the path scope like any write: the
+// setting comes from the webview
+fn inbox_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+  let mut configured = None;
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      configured = app_state.settings.inbox_path.clone();
+    }
+  }
+
+  let path = configured.map(PathBuf::from)
+    .or_else(capture::default_inbox_path)
+    .ok_or_else(|| "Could not determine the inbox board location".to_string())?;
+  check_write_path(app, &path)
+}
+
+fn handle_run_event(app: &tauri::AppHandle, event: tauri::RunEvent) {
+  match event {
+    // Autosaves still waiting for a quiet period are written before quitting
+    tauri::RunEvent::Exit => flush_autosaves(app),
+    // Finder double-click / `open board.fim` on macOS
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    tauri::RunEvent::Opened { urls } => {
+      for url in urls {
+        if let Ok(path) = url.to_file_path() {
+          if supported_document_path(&path) {
+            open_document_from_os(app, &path);
+          }
+        }
+      }
+    },
+    _ => {},
+  }
+}
+
+// Configured recovery locations; see recovery.rs
+fn recovery_locations(app: &tauri::AppHandle) -> Vec<String> {
+  app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|s| s.settings.recovery_locations.clone()))
+    .unwrap_or_else(|| settings::load_settings().recovery_locations)
+}
+
+// Files that belong to a board and move with it when it is archived
+fn board_artifacts(path: &Path, recovery_locations: &[String]) -> Vec<PathBuf> {
+  let mut artifacts = vec![search_index::sidecar_path(path)];
+  if let Some(recovery_path) = recovery::find_recovery(recovery_locations, path) {
+    artifacts.extend(recovery::files(&recovery_path));
+  }
+  artifacts
+}
+
+fn save_as_recovery(doc: &model::BoardDocument, original_path: &std::path::Path, recovery_locations: &[String]) -> Result<AutosaveInfo, String> {
+  let recovery_path = recovery::recovery_path(recovery_locations, original_path)?;
+
+  // Create autosave info
+  let autosave_info = AutosaveInfo {
+    original_path: original_path.to_string_lossy().to_string(),
+    recovery_path: recovery_path.to_string_lossy().to_string(),
+    timestamp: chrono::Utc::now(),
+  };
+
+  // Create metadata file with autosave info
+  let metadata_path = recovery::metadata_path(&recovery_path);
+  let metadata_json = serde_json::to_string_pretty(&autosave_info)
+    .map_err(|e| format!("Failed to serialize recovery metadata: {}", e))?;
+
+  std::fs::write(&metadata_path, metadata_json)
+    .map_err(|e| format!("Failed to write recovery metadata: {}", e))?;
+
+  // Save the actual document to recovery file
+  // A protected board's recovery file is encrypted with the same key
+  let written = save_as_fim(
+    doc, &recovery_path, &[], Some(&snapshots::History::default()), &recovery_path,
+    encryption::key_for(original_path).as_ref(), &progress::Progress::none(),
+  )?;
+  match written {
+    Some(contents) => incremental::remember(&recovery_path, contents),
+    None => incremental::forget(&recovery_path),
+  }
+
+  // Without the index entry it is still found by name, only not listed
+  if let Err(e) = recovery::record(&autosave_info) {
+    log::warn!("{}", e);
+  }
+  Ok(autosave_info)
+}
+
+// Delete the recovery file of a board and its metadata
+fn remove_recovery(recovery_locations: &[String], original_path: &Path, secure: bool) {
+  if let Some(recovery_path) = recovery::find_recovery(recovery_locations, original_path) {
+    for file in recovery::files(&recovery_path) {
+      if file.exists() {
+        let _ = security::remove_file(&file, secure);
+      }
+    }
+    recovery::forget(original_path);
+  }
+}
+
+// Write an autosave snapshot to the board's recovery file
+fn write_autosave(app: &tauri::AppHandle, doc: &model::BoardDocument, path: &Path) -> Result<AutosaveInfo, String> {
+  let result = save_as_recovery(doc, path, &recovery_locations(app));
+  record_audit(app, "autosave", path, || audit::hash_document(doc), &result);
+  let autosave_info = result?;
+  record_usage(app, |usage| usage.saved(path, doc, false));
+
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      if let Some(document) = app_state.open_documents.showing_mut(path) {
+        document.last_autosave_time = Some(std::time::SystemTime::now());
+        document.is_dirty = false;
+      }
+    }
+  }
+  Ok(autosave_info)
+}
+
+// Send an event about the board at `path` to the window that has it open, or
+// to every window when none has
+fn emit_to_document_window<S: serde::Serialize + Clone>(app: &tauri::AppHandle, path: &Path, event: &str, payload: S) -> tauri::Result<()> {
+  match document_window(app, path) {
+    Some(owner) => app.emit_to(owner.as_str(), event, payload),
+    None => app.emit(event, payload),
+  }
+}
+
+// Write the snapshots queued for a board until none is left; see autosave.rs
+fn run_autosave_writer(app: tauri::AppHandle, path: PathBuf) {
+  let state = match app.try_state::<Mutex<AppState>>() {
+    Some(state) => state,
+    None => return,
+  };
+  loop {
+    let next = match state.lock() {
+      Ok(mut app_state) => app_state.autosave.next(&path),
+      Err(_) => return,
+    };
+    match next {
+      autosave::Next::Done => return,
+      autosave::Next::Wait(delay) => std::thread::sleep(delay),
+      autosave::Next::Write(doc) => {
+        // The command only queued the snapshot, so the window learns here
+        // whether and when it was written
+        let sent = match write_autosave(&app, &doc, &path) {
+          Ok(info) => emit_to_document_window(&app, &path, "autosave-completed", info),
+          Err(e) => {
+            log::warn!("Autosave of '{}' failed: {}", path.display(), e);
+            let event = AutosaveFailedEvent { path: path.to_string_lossy().to_string(), error: e };
+            emit_to_document_window(&app, &path, "autosave-failed", event)
+          },
+        };
+        if let Err(e) = sent {
+          log::warn!("Failed to emit autosave event: {}", e);
+        }
+        // Saved while we were writing: the recovery file is already stale
+        let stale = match state.lock() {
+          Ok(mut app_state) => app_state.autosave.finished(&path)
+            .then(|| (app_state.settings.recovery_locations.clone(), app_state.settings.secure_cleanup)),
+          Err(_) => return,
+        };
+        if let Some((recovery_locations, secure)) = stale {
+          remove_recovery(&recovery_locations, &path, secure);
+        }
+      },
+    }
+  }
+}
+
+fn flush_autosaves(app: &tauri::AppHandle) {
+  let pending = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|mut app_state| app_state.autosave.take_all()))
+    .unwrap_or_default();
+  for (path, doc) in pending {
+    if let Err(e) = write_autosave(app, &doc, &path) {
+      log::warn!("Autosave of '{}' failed: {}", path.display(), e);
+    }
+  }
+}
+
+// Helper functions for .fim zip container. With `key` the container is
+// encrypted as a whole; see encryption.rs. `replaces` is the container this
+// one takes the place of, which may be `path` itself: its unchanged entries
+// are copied rather than written again (see incremental.rs) and its snapshots
+// are carried over unless `history` is given. Returns what an unencrypted
+// container holds, to be remembered once it is in place.
+fn save_as_fim(
+  doc: &model::BoardDocument,
+  path: &std::path::Path,
+  fonts: &[fonts::FontFile],
+  history: Option<&snapshots::History>,
+  replaces: &Path,
+  key: Option<&encryption::FileKey>,
+  progress: &progress::Progress,
+) -> Result<Option<incremental::Contents>, String> {
+  let mut previous = incremental::previous(replaces).filter(|_| key.is_none());
+  let carried;
+  let history = match history {
+    Some(history) => Some(history),
+    None if previous.is_some() => None,
+    None => {
+      carried = snapshots::History::read_file(replaces);
+      Some(&carried)
+    },
+  };
+
+  let Some(key) = key else {
+    // Written next to the container it copies from, then moved over it
+    let in_place = (previous.is_some() && replaces == path) || lazy_media::reads_from(doc, path);
+    let target = if in_place { incremental::partial_path(path) } else { path.to_path_buf() };
+    let file = std::fs::File::create(&target)
+      .map_err(|e| format!("Failed to create file '{}': {}", target.display(), e))?;
+    let result = write_fim(doc, file, path, fonts, history, previous.as_mut(), progress);
+    drop(previous);
+    if target != path {
+      match &result {
+        Ok(_) => std::fs::rename(&target, path)
+          .map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))?,
+        Err(_) => {
+          let _ = std::fs::remove_file(&target);
+        },
+      }
+    }
+    return result.map(Some);
+  };
+  let mut plain = std::io::Cursor::new(Vec::new());
+  write_fim(doc, &mut plain, path, fonts, history, None, progress)?;
+  let encrypted = encryption::encrypt(key, plain.get_ref())?;
+  std::fs::write(path, encrypted)
+    .map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))?;
+  Ok(None)
+}
+
+fn write_fim<W: std::io::Write + std::io::Seek>(
+  doc: &model::BoardDocument,
+  writer: W,
+  path: &Path,
+  fonts: &[fonts::FontFile],
+  history: Option<&snapshots::History>,
+  mut previous: Option<&mut incremental::Previous>,
+  progress: &progress::Progress,
+) -> Result<incremental::Contents, String> {
+  use std::io::Write;
+  use zip::{ZipWriter, write::FileOptions};
+
+  let mut zip = ZipWriter::new(writer);
+  let options = FileOptions::default()
+    .compression_method(zip::CompressionMethod::Deflated)
+    .unix_permissions(0o755);
+
+  // Add board.json; images are stored under media/ and confidential notes
+  // are encrypted. Images the replaced container has aren't decoded again.
+  progress.phase("serializing", 0.0);
+  let doc = &confidential::seal(doc)?;
+  let kept = previous.as_ref().map(|p| p.reusable_media(doc)).unwrap_or_default();
+  let (stored, media_files) = media::externalize(doc, &kept);
+  let json = serde_json::to_string_pretty(&stored)
+    .map_err(|e| format!("Failed to serialize document: {}", e))?;
+  let board_hash = audit::hash_bytes(json.as_bytes());
+
+  // An unchanged board comes with its metadata and preview
+  let made_from_board = ["board.json", library::METADATA_ENTRY, preview::PREVIEW_ENTRY, preview::THUMBNAIL_ENTRY];
+  let unchanged = previous.as_ref()
+    .is_some_and(|p| p.board_unchanged(&board_hash) && made_from_board.iter().all(|entry| p.has(entry)));
+  if let Some(previous) = previous.as_mut().filter(|_| unchanged) {
+    for entry in made_from_board {
+      previous.copy(&mut zip, entry)?;
+    }
+  } else {
+    zip.start_file("board.json", options)
+      .map_err(|e| format!("Failed to create board.json in zip: {}", e))?;
+    progress.write_all(&mut zip, json.as_bytes(), "writing", 10.0, 70.0)
+      .map_err(|e| format!("Failed to write board.json: {}", e))?;
+
+    // Title, tags and counts for the library scanner, so it needn't parse board.json
+    let metadata = serde_json::to_vec(&library::BoardMetadata::from_document(doc))
+      .map_err(|e| format!("Failed to serialize board metadata: {}", e))?;
+    zip.start_file(library::METADATA_ENTRY, options)
+      .map_err(|e| format!("Failed to create {} in zip: {}", library::METADATA_ENTRY, e))?;
+    zip.write_all(&metadata)
+      .map_err(|e| format!("Failed to write {}: {}", library::METADATA_ENTRY, e))?;
+  }
+
+  // Image formats are compressed already
+  zip.add_directory(media::MEDIA_DIR, options)
+    .map_err(|e| format!("Failed to create media directory: {}", e))?;
+  let stored_options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+  if let Some(previous) = previous.as_mut() {
+    for entry in &kept {
+      previous.copy(&mut zip, entry)?;
+    }
+  }
+  for (entry, data) in &media_files {
+    zip.start_file(entry, stored_options)
+      .map_err(|e| format!("Failed to create {} in zip: {}", entry, e))?;
+    zip.write_all(data)
+      .map_err(|e| format!("Failed to write {}: {}", entry, e))?;
+  }
+  // Images still in the container the board was opened from; see lazy_media.rs
+  let mut deferred = std::collections::HashSet::new();
+  for image in doc.images.iter().flatten().filter(|image| lazy_media::is_deferred(image)) {
+    let entry = image.path.clone().unwrap_or_default();
+    if !deferred.insert(entry.clone()) {
+      continue;
+    }
+    match previous.as_mut().filter(|p| p.has(&entry)) {
+      Some(previous) => previous.copy(&mut zip, &entry)?,
+      None => if !lazy_media::copy(&mut zip, image)? {
+        log::warn!("Image '{}' has no data to save in '{}'", image.id, path.display());
+      },
+    }
+  }
+
+  for font in fonts {
+    let entry = format!("{}{}", fonts::FONTS_DIR, font.name);
+    zip.start_file(&entry, options)
+      .map_err(|e| format!("Failed to create {} in zip: {}", entry, e))?;
+    zip.write_all(&font.data)
+      .map_err(|e| format!("Failed to write {}: {}", entry, e))?;
+  }
+
+  // Add preview.png for OS preview handlers and thumbnail.png for the
+  // recent-files screen; a failed render must not block saving
+  progress.phase("preview", 70.0);
+  if !unchanged {
+    let visible = layers::filter_layers(doc, &layers::visible_layers(doc));
+    let renders = [
+      (preview::PREVIEW_ENTRY, preview::render_preview(&visible)),
+      (preview::THUMBNAIL_ENTRY, preview::render_thumbnail(&visible)),
+    ];
+    for (entry, rendered) in renders {
+      match rendered {
+        Ok(png) => {
+          zip.start_file(entry, stored_options)
+            .map_err(|e| format!("Failed to create {} in zip: {}", entry, e))?;
+          zip.write_all(&png)
+            .map_err(|e| format!("Failed to write {}: {}", entry, e))?;
+        },
+        Err(e) => log::warn!("Skipping {} for '{}': {}", entry, path.display(), e),
+      }
+    }
+  }
+
+  match (history, previous.as_mut()) {
+    (Some(history), _) => history.write(&mut zip)?,
+    (None, Some(previous)) => previous.copy_dir(&mut zip, snapshots::VERSIONS_DIR)?,
+    (None, None) => {},
+  }
+
+  progress.phase("finishing", 90.0);
+  zip.finish()
+    .map_err(|e| format!("Failed to finalize zip file: {}", e))?;
+
+  let written: std::collections::HashSet<String> = kept.into_iter().chain(media_files.into_iter().map(|(entry, _)| entry)).collect();
+  Ok(incremental::Contents { board_hash, media: incremental::media_contents(doc, &written) })
+}
+
+fn open_fim_archive(path: &Path) -> Result<zip::ZipArchive<std::fs::File>, String> {
+  let file = std::fs::File::open(path)
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+
+  let mut archive = zip::ZipArchive::new(file)
+    .map_err(|e| format!("Failed to read zip file '{}': {}", path.display(), e))?;
+
+  // Reject zip-slip paths and zip bombs before decompressing anything
+  archive::validate_archive(&mut archive)?;
+  Ok(archive)
+}
+
+// Read board.json out of a .fim archive
+fn read_fim_board_json<R: std::io::Read + std::io::Seek>(
+  archive: &mut zip::ZipArchive<R>,
+  progress: &progress::Progress,
+) -> Result<Vec<u8>, String> {
+  progress.phase("reading", 0.0);
+  let mut on_read = |read, total| progress.step("reading", 0.0, 60.0, read, total);
+  Ok(archive::read_entry_with_progress(archive, "board.json", archive::MAX_BOARD_JSON_SIZE, &mut on_read)?)
+}
+
+// Images stored in the container are left there when they add up to more
+// than `lazy_above` bytes; see lazy_media.rs
+fn load_from_fim<R: std::io::Read + std::io::Seek>(
+  mut archive: zip::ZipArchive<R>,
+  path: &Path,
+  lazy_above: Option<u64>,
+  progress: &progress::Progress,
+) -> Result<(model::BoardDocument, migrations::MigrationReport), String> {
+  // board.json is parsed as it is decompressed, and hashed on the way for
+  // incremental.rs
+  progress.phase("reading", 0.0);
+  let mut on_read = |read, total| progress.step("reading", 0.0, 60.0, read, total);
+  let entry = archive::open_entry(&mut archive, "board.json", archive::MAX_BOARD_JSON_SIZE, &mut on_read)?;
+  let mut reader = audit::HashingReader::new(entry);
+  let value: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(&mut reader))
+    .map_err(|e| match e.is_io() {
+      true => format!("Failed to read board.json: {}", e),
+      false => format!("Invalid JSON format: {}", e),
+    })?;
+  let board_hash = reader.finish();
+
+  let (mut doc, report) = board_from_value(value, progress)?;
+  let stored: std::collections::HashSet<String> = doc.images.iter().flatten()
+    .filter(|image| image.path.as_deref() == Some(media::media_entry(image).as_str()))
+    .map(media::media_entry)
+    .collect();
+  match lazy_above.is_some_and(|limit| lazy_media::stored_size(&doc, &mut archive) > limit) {
+    true => lazy_media::index(&doc, path),
+    false => media::internalize(&mut doc, &mut archive, path),
+  }
+
+  // Saving it again can reuse what is unchanged; see incremental.rs
+  let media = incremental::media_contents(&doc, &stored);
+  incremental::remember(path, incremental::Contents { board_hash, media });
+
+  Ok((doc, report))
+}
+
+// Board JSON to a document, upgraded from older schema versions first; see
+// migrations.rs
+fn parse_board(json: &[u8], progress: &progress::Progress) -> Result<(model::BoardDocument, migrations::MigrationReport), String> {
+  let value: serde_json::Value = serde_json::from_slice(json)
+    .map_err(|e| format!("Invalid JSON format: {}", e))?;
+  board_from_value(value, progress)
+}
+
+fn board_from_value(mut value: serde_json::Value, progress: &progress::Progress) -> Result<(model::BoardDocument, migrations::MigrationReport), String> {
+  progress.phase("migrating", 80.0);
+  let report = migrations::migrate(&mut value)?;
+  let mut doc: model::BoardDocument = serde_json::from_value(value)
+    .map_err(|e| match report.newer {
+      // Its changes go beyond new fields, which would just be ignored
+      true => format!("Unsupported schema version {}. Please update the application.", report.from_version),
+      false => format!("Invalid JSON format: {}", e),
+    })?;
+  rich_text::migrate_document(&mut doc);
+  Ok((doc, report))
+}
+
+// Whether a board file is a .fim container ("fim"), a password-protected one
+// ("encrypted") or plain JSON ("json"), from its first bytes rather than its
+// extension, so a board renamed to the other extension still opens. JSON in a
+// .canvas file is JSON Canvas ("canvas"), and XML with a <ScappleDocument>
+// root a Scapple board ("scapple").
+fn sniff_format(path: &Path) -> Result<&'static str, String> {
+  use std::io::Read;
+
+  let mut head = [0u8; 512];
+  let file = std::fs::File::open(path)
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+  let read = file.take(head.len() as u64).read(&mut head)
+    .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+  let head = &head[..read];
+
+  // Local file header, or the end record of an empty archive
+  if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+    return Ok("fim");
+  }
+  if encryption::is_encrypted(path) {
+    return Ok("encrypted");
+  }
+  let text = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+  if text.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+    let is_canvas = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("canvas"));
+    return Ok(if is_canvas { "canvas" } else { "json" });
+  }
+  let is_scap = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("scap"));
+  if text.first() == Some(&b'<') && (is_scap || head.windows(17).any(|w| w == b"<ScappleDocument ")) {
+    return Ok("scapple");
+  }
+  Err(format!(
+    "Unsupported file format: '{}' is neither a .fim board nor a JSON board",
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string()),
+  ))
+}
+
+// Load a document from disk, choosing the loader by the file's content
+fn load_document(path: &Path, progress: &progress::Progress) -> Result<model::BoardDocument, String> {
+  load_document_migrated(path, None, progress).map(|(doc, _)| doc)
+}
+
+// Like load_document, also saying which schema migrations were applied, for
+// the commands that open a board for the user. The images of a .fim board
+// are left in it past `lazy_above` bytes; see lazy_media.rs
+fn load_document_migrated(
+  path: &Path,
+  lazy_above: Option<u64>,
+  progress: &progress::Progress,
+) -> Result<(model::BoardDocument, migrations::MigrationReport), String> {
+  let (doc, report) = match sniff_format(path)? {
+    "fim" => load_from_fim(open_fim_archive(path)?, path, lazy_above, progress)?,
+    // Decrypted in memory as a whole, so there is nothing to gain
+    "encrypted" => {
+      progress.phase("decrypting", 0.0);
+      load_from_fim(encryption::open_archive(path)?, path, None, progress)?
+    },
+    // Made by other apps, so there is nothing to migrate
+    "canvas" => {
+      progress.phase("reading", 0.0);
+      let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+      progress.phase("parsing", 60.0);
+      let report = migrations::MigrationReport {
+        from_version: migrations::CURRENT_VERSION,
+        to_version: migrations::CURRENT_VERSION,
+        ..Default::default()
+      };
+      (canvas::import(&json)?, report)
+    },
+    "scapple" => {
+      progress.phase("reading", 0.0);
+      let xml = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+      progress.phase("parsing", 60.0);
+      let report = migrations::MigrationReport {
+        from_version: migrations::CURRENT_VERSION,
+        to_version: migrations::CURRENT_VERSION,
+        ..Default::default()
+      };
+      (scapple::import(&xml)?, report)
+    },
+    _ => {
+      progress.phase("reading", 0.0);
+      let data = std::fs::read(path)
+        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+
+      progress.phase("parsing", 60.0);
+      parse_board(&data, progress)?
+    },
+  };
+
+  tracing::info!(
+    notes = doc.notes.len(),
+    connections = doc.connections.len(),
+    bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    from_version = report.from_version,
+    to_version = report.to_version,
+    "Loaded '{}'", path.display(),
+  );
+
+  Ok((doc, report))
+}
+
+// Tell the window that opened a board that it was upgraded or comes from a
+// newer version of the app
+fn report_migration(app: &tauri::AppHandle, path: &Path, report: &migrations::MigrationReport, window: &str) {
+  if report.is_empty() {
+    return;
+  }
+  let event = DocumentMigratedEvent { path: path.to_string_lossy().to_string(), report: report.clone() };
+  if let Err(e) = app.emit_to(window, "document-migrated", event) {
+    log::warn!("Failed to emit document-migrated event: {}", e);
+  }
+}
+
+// Like load_document, but repairs what it can instead of failing on the
+// first bad field; see lenient.rs
+fn load_document_lenient(path: &Path) -> Result<lenient::LenientDocument, String> {
+  let format = sniff_format(path)?;
+  if format == "canvas" {
+    let json = std::fs::read_to_string(path)
+      .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+    return Ok(lenient::LenientDocument { document: canvas::import(&json)?, diagnostics: Vec::new() });
+  }
+  if format == "scapple" {
+    let xml = std::fs::read_to_string(path)
+      .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+    return Ok(lenient::LenientDocument { document: scapple::import(&xml)?, diagnostics: Vec::new() });
+  }
+  let none = progress::Progress::none();
+  let mut parsed = match format {
+    "fim" => {
+      let mut archive = open_fim_archive(path)?;
+      let mut parsed = lenient::parse_document(&read_fim_board_json(&mut archive, &none)?)?;
+      media::internalize(&mut parsed.document, &mut archive, path);
+      parsed
+    },
+    "encrypted" => {
+      let mut archive = encryption::open_archive(path)?;
+      let mut parsed = lenient::parse_document(&read_fim_board_json(&mut archive, &none)?)?;
+      media::internalize(&mut parsed.document, &mut archive, path);
+      parsed
+    },
+    _ => {
+      let data = std::fs::read(path)
+        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+      lenient::parse_document(&data)?
+    },
+  };
+  rich_text::migrate_document(&mut parsed.document);
+
+  Ok(parsed)
+}
+
+// Write a document to disk, choosing the format by file extension; `fonts`
+// are embedded in .fim containers
+// The file is written to a temporary file first and renamed into place; see
+// sync_write.rs
+fn write_document(
+  doc: &model::BoardDocument,
+  path: &Path,
+  fonts: &[fonts::FontFile],
+  options: sync_write::WriteOptions,
+  progress: &progress::Progress,
+) -> Result<(), String> {
+  let extension = path.extension()
+    .and_then(|ext| ext.to_str())
+    .unwrap_or("");
+
+  let result = match extension {
+    "fim" => {
+      let key = encryption::key_for(path);
+      // Unchanged parts and snapshots come from the file being replaced
+      let mut written = None;
+      let result = sync_write::write_with(path, options, |target| {
+        written = save_as_fim(doc, target, fonts, None, path, key.as_ref(), progress)?;
+        Ok(())
+      });
+      match written.filter(|_| result.is_ok()) {
+        Some(contents) => incremental::remember(path, contents),
+        None => incremental::forget(path),
+      }
+      if result.is_ok() {
+        lazy_media::moved(doc, path);
+      }
+      result
+    },
+    "json" => {
+      progress.phase("serializing", 0.0);
+      // JSON boards keep their images inline
+      let mut sealed = confidential::seal(doc)?;
+      lazy_media::load(&mut sealed)?;
+      let json = serde_json::to_string_pretty(&sealed)
+        .map_err(|e| format!("Failed to serialize document: {}", e))?;
+
+      sync_write::write_with(path, options, |target| {
+        progress.write_file(target, json.as_bytes(), 10.0, 95.0)
+          .map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))
+      })
+    },
+    // An opened .canvas file is saved back as JSON Canvas; see canvas.rs
+    "canvas" => {
+      progress.phase("serializing", 0.0);
+      let json = canvas::export(&confidential::seal(doc)?)?;
+      sync_write::write_with(path, options, |target| {
+        progress.write_file(target, json.as_bytes(), 10.0, 95.0)
+          .map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))
+      })
+    },
+    // Imported only; see scapple.rs
+    "scap" => Err(format!("'{}' is a Scapple board and can't be saved back; save it as a .fim board instead",
+      path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default())),
+    _ => Err(format!("Unsupported file format: '{}'. Supported formats: .fim, .json, .canvas", extension)),
+  };
+  if result.is_ok() {
+    tracing::info!(
+      notes = doc.notes.len(),
+      connections = doc.connections.len(),
+      bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+      "Wrote '{}'", path.display(),
+    );
+  }
+  result
+}
+
+// How saving to `path` goes, per the `writeMode` and `keepVersions` settings
+// Bytes of images past which an opened .fim board leaves them in the file;
+// see lazy_media.rs
+fn lazy_media_limit(app: &tauri::AppHandle) -> Option<u64> {
+  let megabytes = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|s| s.settings.lazy_media_mb))
+    .unwrap_or_else(|| settings::load_settings().lazy_media_mb);
+  (megabytes > 0).then(|| megabytes.saturating_mul(1024 * 1024))
+}
+
+fn write_options(app: &tauri::AppHandle, path: &Path) -> sync_write::WriteOptions {
+  let (mode, keep_versions) = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|s| (s.settings.write_mode.clone(), s.settings.keep_versions)))
+    .unwrap_or_default();
+  sync_write::WriteOptions::new(&mode, keep_versions, path)
+}
+
+// Remember which file the open board came from, for embedded fonts and saving,
+// and which window shows it
+fn set_opened_path(app: &tauri::AppHandle, path: &Path, doc: &model::BoardDocument, read_only: bool, window: &str) {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.session.opened(path);
+      let document = app_state.open_documents.open(path, window);
+      document.note_history = Some(metadata::NoteHistory::of(doc));
+      document.read_only = read_only;
+      document.is_dirty = false;
+      document.last_autosave_time = None;
+      track_document_file(app, document, path, window);
+      if !read_only {
+        app_state.backups.track(path);
+      }
+    }
+  }
+}
+
+// The window that has the board at `path` open, if it still exists
+fn document_window(app: &tauri::AppHandle, path: &Path) -> Option<String> {
+  let state = app.try_state::<Mutex<AppState>>()?;
+  let app_state = state.lock().ok()?;
+  app_state.open_documents.owner(path)
+    .filter(|owner| app.get_webview_window(owner).is_some())
+    .map(str::to_string)
+}
+
+// File of the board open in `window`, if it has been saved
+fn current_document_path(app: &tauri::AppHandle, window: &str) -> Option<String> {
+  let state = app.try_state::<Mutex<AppState>>()?;
+  let app_state = state.lock().ok()?;
+  app_state.open_documents.window(window)?.path.clone()
+}
+
+// Refuse to open or save over a board that another window has open, so the
+// two don't diverge; the frontend brings that window forward instead
+fn ensure_not_open_elsewhere(app: &tauri::AppHandle, path: &Path, window: &str) -> Result<(), AppError> {
+  let Some(state) = app.try_state::<Mutex<AppState>>() else {
+    return Ok(());
+  };
+  let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  let Some(owner) = app_state.open_documents.owner_other_than(path, window) else {
+    return Ok(());
+  };
+  // A window closed without us hearing about it doesn't count
+  if app.get_webview_window(owner).is_none() {
+    return Ok(());
+  }
+  let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+  Err(AppError::AlreadyOpen {
+    message: format!("'{}' is already open in another window", name),
+    window: owner.to_string(),
+  })
+}
+
+// Refuse changes to a board `window` opened read-only. Exports, search and
+// the like don't check this; saving under a new name makes the copy editable.
+fn ensure_writable(app: &tauri::AppHandle, window: &str) -> Result<(), AppError> {
+  let Some(state) = app.try_state::<Mutex<AppState>>() else {
+    return Ok(());
+  };
+  let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  let Some(document) = app_state.open_documents.window(window).filter(|document| document.read_only) else {
+    return Ok(());
+  };
+  let name = document.path.as_deref()
+    .and_then(|p| Path::new(p).file_name())
+    .map(|n| n.to_string_lossy().to_string())
+    .unwrap_or_else(|| "This board".to_string());
+  Err(AppError::ReadOnly { message: format!("'{}' is open read-only", name) })
+}
+
+// Refuse to load a password-protected board whose key isn't unlocked yet, so
+// the frontend can ask for the password and call `unlock_document`
+fn ensure_unlocked(path: &Path) -> Result<(), AppError> {
+  if !encryption::needs_password(path) {
+    return Ok(());
+  }
+  let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+  Err(AppError::PasswordRequired {
+    message: format!("'{}' is password-protected; a password is required to open it", name),
+    path: Some(path.to_string_lossy().to_string()),
+  })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn open_document(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<model::BoardDocument, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let file_path = app.dialog()
+    .file()
+    .add_filter("FIM Files", &["fim"])
+    .add_filter("JSON", &["json"])
+    .add_filter("JSON Canvas", &["canvas"])
+    .add_filter("Scapple", &["scap"])
+    .add_filter("All Supported", &["fim", "json", "canvas", "scap"])
+    .set_title("Open Board Document")
+    .pick_file_async().await;
+
+  let path = match file_path {
+    Some(p) => match p.as_path() {
+      Some(path) => path.to_path_buf(),
+      None => return Err("Invalid file path selected".into()),
+    },
+    None => return Err("Operation cancelled by user".into()),
+  };
+  authorize_path(&app, &path);
+  ensure_not_open_elsewhere(&app, &path, window.label())?;
+  ensure_unlocked(&path)?;
+
+  let progress = progress::Progress::new(on_progress);
+  let result = load_document_migrated(&path, lazy_media_limit(&app), &progress);
+  record_audit(&app, "open", &path, || result.as_ref().ok().and_then(|(doc, _)| audit::hash_document(doc)), &result);
+  let (doc, report) = result?;
+
+  // Add to recent files
+  add_recent_file(&app, &path.to_string_lossy());
+  record_usage(&app, |usage| usage.opened(&path, &doc));
+  // Saving a board from a newer version would drop what we don't know about
+  set_opened_path(&app, &path, &doc, report.newer, window.label());
+  report_migration(&app, &path, &report, window.label());
+  progress.done();
+  
+  Ok(doc)
+}
+
+// Notes for tab/space-indented text or bullet lists, laid out as a connected
+// tree or a single stack
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_text_outline(args: TextOutlineArgs) -> Result<outline::OutlineImport, AppError> {
+  Ok(outline::to_notes(&outline::parse_indented_text(&args.text), &args.options)?)
+}
+
+// One image note per picture in a folder, laid out in a grid
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_image_folder(
+  app: tauri::AppHandle,
+  args: ImageFolderArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<media::ImageFolderImport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let dir = match args.dir {
+    Some(dir) => PathBuf::from(dir),
+    None => {
+      let folder = app.dialog()
+        .file()
+        .set_title("Import Folder of Images")
+        .pick_folder_async().await
+        .ok_or("Operation cancelled by user")?;
+      let dir = folder.as_path().ok_or("Invalid folder selected")?.to_path_buf();
+      authorize_path(&app, &dir);
+      dir
+    },
+  };
+
+  let progress = progress::Progress::new(on_progress);
+  begin_task(&app, "import-image-folder", &progress);
+  let mut on_image = |index: usize, total: usize| {
+    progress.step("reading", 0.0, 100.0, index as u64, total as u64);
+    progress.check_cancelled()
+  };
+  let result = media::import_image_folder(&dir, args.origin, &mut on_image);
+  end_task(&app, &progress, &result);
+  record_audit(&app, "import-image-folder", &dir, || None, &result);
+  progress.done();
+  Ok(result?)
+}
+
+// Notes for a browser bookmarks export, keeping its folders; see bookmarks.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_bookmarks(app: tauri::AppHandle, args: BookmarksImportArgs) -> Result<bookmarks::BookmarksImport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let file_path = app.dialog()
+    .file()
+    .add_filter("Bookmarks", &["html", "htm"])
+    .set_title("Import Browser Bookmarks")
+    .pick_file_async().await;
+
+  let path = match file_path {
+    Some(p) => match p.as_path() {
+      Some(path) => path.to_path_buf(),
+      None => return Err("Invalid file path selected".into()),
+    },
+    None => return Err("Operation cancelled by user".into()),
+  };
+  authorize_path(&app, &path);
+
+  let result = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read bookmarks file '{}': {}", path.display(), e))
+    .and_then(|html| bookmarks::import(&html, &args.options));
+  record_audit(&app, "import-bookmarks", &path, || None, &result);
+  Ok(result?)
+}
+
+// One note per row of a CSV or TSV table, in a grid; see spreadsheet.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_notes_from_csv(app: tauri::AppHandle, args: SpreadsheetImportArgs) -> Result<spreadsheet::SpreadsheetImport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let path = match args.path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      let file_path = app.dialog()
+        .file()
+        .add_filter("Spreadsheet Tables", &["csv", "tsv", "tab"])
+        .set_title("Import Notes from CSV")
+        .pick_file_async().await
+        .ok_or("Operation cancelled by user")?;
+      let path = file_path.as_path().ok_or("Invalid file path selected")?.to_path_buf();
+      authorize_path(&app, &path);
+      path
+    },
+  };
+
+  let delimiter = spreadsheet::delimiter(path.extension().and_then(|e| e.to_str()).unwrap_or(""));
+  let result = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read table '{}': {}", path.display(), e))
+    .and_then(|text| spreadsheet::import(&text, delimiter, args.origin));
+  record_audit(&app, "import-csv", &path, || None, &result);
+  Ok(result?)
+}
+
+// One note per highlight in a Readwise/Kindle CSV, stacked by book; see highlights.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_highlights_csv(app: tauri::AppHandle, args: HighlightsImportArgs) -> Result<highlights::HighlightsImport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let file_path = app.dialog()
+    .file()
+    .add_filter("CSV Files", &["csv"])
+    .set_title("Import Highlights")
+    .pick_file_async().await;
+
+  let path = match file_path {
+    Some(p) => match p.as_path() {
+      Some(path) => path.to_path_buf(),
+      None => return Err("Invalid file path selected".into()),
+    },
+    None => return Err("Operation cancelled by user".into()),
+  };
+  authorize_path(&app, &path);
+
+  let result = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read highlights file '{}': {}", path.display(), e))
+    .and_then(|text| highlights::import(&text, args.origin));
+  record_audit(&app, "import-highlights", &path, || None, &result);
+  Ok(result?)
+}
+
+// Save the board's note styles and document style as a .fimtheme file
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_style_theme(app: tauri::AppHandle, args: ExportThemeFileArgs) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let file_name = format!("{}.{}", args.name.as_deref().unwrap_or("theme"), styles::THEME_EXTENSION);
+  let dialog = app.dialog()
+    .file()
+    .add_filter("Freeform Themes", &[styles::THEME_EXTENSION])
+    .set_file_name(&file_name)
+    .set_title("Export Theme");
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let theme = styles::export_theme(&args.doc, args.name);
+  let json = serde_json::to_string_pretty(&theme)
+    .map_err(|e| format!("Failed to serialize theme: {}", e))?;
+  let result = std::fs::write(&path, &json)
+    .map_err(|e| format!("Failed to write theme file '{}': {}", path.display(), e));
+  record_audit(&app, "export-theme", &path, || Some(audit::hash_bytes(json.as_bytes())), &result);
+  result?;
+  Ok(path.to_string_lossy().to_string())
+}
+
+// Restyle the board from a .fimtheme file; see styles.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn apply_style_theme(app: tauri::AppHandle, args: ApplyThemeFileArgs) -> Result<styles::ThemeApplication, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let path = match args.path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      let file_path = app.dialog()
+        .file()
+        .add_filter("Freeform Themes", &[styles::THEME_EXTENSION])
+        .set_title("Apply Theme")
+        .pick_file_async().await
+        .ok_or("Operation cancelled by user")?;
+      let path = file_path.as_path().ok_or("Invalid file path selected")?.to_path_buf();
+      authorize_path(&app, &path);
+      path
+    },
+  };
+
+  let json = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read theme file '{}': {}", path.display(), e))?;
+  let theme = styles::parse_theme(&json)?;
+  Ok(styles::apply_theme(&args.doc, &theme))
+}
+
+// Shared style library in the app data folder; see styles.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_style_to_library(args: SaveLibraryStyleArgs) -> Result<styles::LibraryStyle, AppError> {
+  let mut library = styles::StyleLibrary::load();
+  let entry = library.add(&args.doc, &args.style_id, args.name)?;
+  library.save()?;
+  Ok(entry)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_library_styles() -> Result<Vec<styles::LibraryStyle>, AppError> {
+  Ok(styles::StyleLibrary::load().styles)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn insert_library_styles(args: InsertLibraryStylesArgs) -> Result<styles::StyleInsertion, AppError> {
+  Ok(styles::StyleLibrary::load().insert(&args.doc, &args.ids)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn remove_library_style(id: model::ID) -> Result<(), AppError> {
+  let mut library = styles::StyleLibrary::load();
+  library.remove(&id)?;
+  Ok(library.save()?)
+}
+
+// Colors the board uses, most used first; see palette.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn extract_palette(doc: model::BoardDocument) -> Result<Vec<palette::PaletteColor>, AppError> {
+  Ok(palette::extract_palette(&doc))
+}
+
+// Recolor every use of one color across the board
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn replace_color(app: tauri::AppHandle, window: tauri::Window, args: ReplaceColorArgs) -> Result<palette::ColorReplacement, AppError> {
+  ensure_writable(&app, window.label())?;
+  Ok(palette::replace_color(&args.doc, &args.from, &args.to)?)
+}
+
+// Fonts embedded in a board (default: the open one), for the frontend to
+// register before rendering; see fonts.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_embedded_fonts(app: tauri::AppHandle, window: tauri::Window, path: Option<String>) -> Result<Vec<fonts::EmbeddedFont>, AppError> {
+  let path = match path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      match current_document_path(&app, window.label()) {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(Vec::new()),
+      }
+    },
+  };
+  Ok(fonts::describe(&fonts::read_embedded(&path)))
+}
+
+// Make an image the board's background texture; see textures.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_texture(app: tauri::AppHandle, window: tauri::Window, args: ImportTextureArgs) -> Result<model::BoardDocument, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+  ensure_writable(&app, window.label())?;
+
+  let path = match args.path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      let file_path = app.dialog()
+        .file()
+        .add_filter("Images", media::IMAGE_EXTENSIONS)
+        .set_title("Choose Background Texture")
+        .pick_file_async().await
+        .ok_or("Operation cancelled by user")?;
+      let path = file_path.as_path().ok_or("Invalid file path selected")?.to_path_buf();
+      authorize_path(&app, &path);
+      path
+    },
+  };
+
+  let result = textures::import_texture(&args.doc, &path, args.scale, args.opacity);
+  record_audit(&app, "import-texture", &path, || None, &result);
+  Ok(result?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn remove_texture(app: tauri::AppHandle, window: tauri::Window, doc: model::BoardDocument) -> Result<model::BoardDocument, AppError> {
+  ensure_writable(&app, window.label())?;
+  Ok(textures::remove_texture(&doc))
+}
+
+// Add a picture to the board's images; it is stored as a file under
+// `media/` when the board is saved as .fim. See media.rs.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn add_image_to_document(app: tauri::AppHandle, window: tauri::Window, args: AddImageArgs) -> Result<AddedImage, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+  ensure_writable(&app, window.label())?;
+
+  let path = match args.path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      let file_path = app.dialog()
+        .file()
+        .add_filter("Images", media::IMAGE_EXTENSIONS)
+        .set_title("Add Image")
+        .pick_file_async().await
+        .ok_or("Operation cancelled by user")?;
+      let path = file_path.as_path().ok_or("Invalid file path selected")?.to_path_buf();
+      authorize_path(&app, &path);
+      path
+    },
+  };
+
+  let result = media::add_image(&args.doc, &path);
+  record_audit(&app, "add-image", &path, || None, &result);
+  let (document, image_id) = result?;
+  Ok(AddedImage { document, image_id })
+}
+
+// An image of a board on disk, read from its `media/` file without loading
+// the whole board
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_image_data(app: tauri::AppHandle, window: tauri::Window, args: ImageDataArgs) -> Result<ImageData, AppError> {
+  use base64::Engine;
+
+  let file_path = match args.file_path {
+    Some(file_path) => file_path,
+    None => current_document_path(&app, window.label()).ok_or_else(|| AppError::InvalidInput {
+      message: "No board is open; pass the board's file".to_string(),
+    })?,
+  };
+  let path = PathBuf::from(file_path);
+  let image_id = args.image_id.clone();
+
+  // Large boards; keep it off the async workers
+  let data = tauri::async_runtime::spawn_blocking(move || -> Result<Vec<u8>, String> {
+    match sniff_format(&path)? {
+      "fim" => media::read_media(&path, &image_id),
+      // JSON boards keep their images inline
+      _ => {
+        let doc = load_document(&path, &progress::Progress::none())?;
+        let image = doc.images.iter().flatten()
+          .find(|i| i.id == image_id)
+          .ok_or_else(|| format!("Image '{}' not found in '{}'", image_id, path.display()))?;
+        let encoded = image.data_base64.as_deref()
+          .ok_or_else(|| format!("Image '{}' in '{}' has no data", image_id, path.display()))?;
+        base64::engine::general_purpose::STANDARD.decode(encoded)
+          .map_err(|e| format!("Invalid image data for '{}': {}", image_id, e))
+      },
+    }
+  })
+    .await
+    .map_err(|e| format!("Reading image failed: {}", e))??;
+
+  let mime = media::sniff_image_mime(&data).ok_or("Unsupported or unrecognized image format")?;
+  Ok(ImageData {
+    id: args.image_id,
+    mime: mime.to_string(),
+    data_base64: base64::engine::general_purpose::STANDARD.encode(&data),
+  })
+}
+
+// An image being added to the board, shrunk, encoded again and matched
+// against the board's images; see ingest.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn ingest_image(args: IngestImageArgs) -> Result<ingest::IngestedImage, AppError> {
+  let format = args.format.unwrap_or_else(|| "webp".to_string());
+  let ingested = tauri::async_runtime::spawn_blocking(move || ingest::ingest(&args.doc, &args.data, args.max_dimension, &format))
+    .await
+    .map_err(|e| format!("Adding image failed: {}", e))??;
+  Ok(ingested)
+}
+
+// An image the open board left in its file when opened; see lazy_media.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn read_media(image_id: String) -> Result<ImageData, AppError> {
+  use base64::Engine;
+
+  let id = image_id.clone();
+  let data = tauri::async_runtime::spawn_blocking(move || lazy_media::read(&id))
+    .await
+    .map_err(|e| format!("Reading image failed: {}", e))??;
+  let mime = media::sniff_image_mime(&data).ok_or("Unsupported or unrecognized image format")?;
+  Ok(ImageData {
+    id: image_id,
+    mime: mime.to_string(),
+    data_base64: base64::engine::general_purpose::STANDARD.encode(&data),
+  })
+}
+
+// One citation note per BibTeX entry; see bibtex.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_bibtex(app: tauri::AppHandle, args: BibtexImportArgs) -> Result<bibtex::BibtexImport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let path = match args.path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      let file_path = app.dialog()
+        .file()
+        .add_filter("BibTeX Files", &["bib", "bibtex"])
+        .set_title("Import BibTeX")
+        .pick_file_async().await
+        .ok_or("Operation cancelled by user")?;
+      let path = file_path.as_path().ok_or("Invalid file path selected")?.to_path_buf();
+      authorize_path(&app, &path);
+      path
+    },
+  };
+
+  let result = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read BibTeX file '{}': {}", path.display(), e))
+    .and_then(|text| bibtex::import(&text, args.origin));
+  record_audit(&app, "import-bibtex", &path, || None, &result);
+  Ok(result?)
+}
+
+// A link card for a URL, with the page's title, description and preview
+// image; see web.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_url(args: UrlImportArgs) -> Result<web::UrlImport, AppError> {
+  // ureq blocks; keep it off the async workers
+  let import = tauri::async_runtime::spawn_blocking(move || web::import_url(&args.url, args.origin))
+    .await
+    .map_err(|e| format!("Link preview failed: {}", e))??;
+  Ok(import)
+}
+
+// Check the board's links: web links over the network, links to other notes
+// against the board; see link_check.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn validate_links(doc: model::BoardDocument) -> Result<link_check::LinkValidation, AppError> {
+  let validation = tauri::async_runtime::spawn_blocking(move || link_check::validate(&doc))
+    .await
+    .map_err(|e| format!("Link check failed: {}", e))?;
+  Ok(validation)
+}
+
+// Title and icon of a linked page, for showing the link nicely
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn fetch_link_metadata(url: String) -> Result<web::LinkMetadata, AppError> {
+  let metadata = tauri::async_runtime::spawn_blocking(move || web::fetch_link_metadata(&url))
+    .await
+    .map_err(|e| format!("Fetching link details failed: {}", e))??;
+  Ok(metadata)
+}
+
+// Folder to write into, for Markdown vaults and batch exports: the given
+// one, or one the user picks (which then becomes writable)
+async fn writable_folder(app: &tauri::AppHandle, dir: Option<String>, title: &str) -> Result<PathBuf, String> {
+  use tauri_plugin_dialog::DialogExt;
+
+  match dir {
+    Some(dir) => Ok(PathBuf::from(dir)),
+    None => {
+      let folder = app.dialog()
+        .file()
+        .set_title(title)
+        .pick_folder_async().await
+        .ok_or("Operation cancelled by user")?;
+      let dir = folder.as_path().ok_or("Invalid folder selected")?.to_path_buf();
+      if let Some(state) = app.try_state::<Mutex<AppState>>() {
+        if let Ok(mut app_state) = state.lock() {
+          app_state.path_scope.authorize_folder(&dir);
+        }
+      }
+      Ok(dir)
+    },
+  }
+}
+
+// Write the board as a folder of Markdown files; see vault.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_markdown_vault(
+  app: tauri::AppHandle,
+  args: VaultExportArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<vault::VaultExport, AppError> {
+  let dir = writable_folder(&app, args.dir, "Export Markdown Vault").await?;
+  let progress = progress::Progress::new(on_progress);
+  begin_task(&app, "export-markdown-vault", &progress);
+  progress.phase("writing", 0.0);
+  // Every file goes through the write check, which makes it the place to stop
+  let check_write = |path: &Path| {
+    progress.check_cancelled()?;
+    check_write_path(&app, path)
+  };
+  let result = vault::export(&confidential::without_locked(&args.doc), &dir, &args.mode, &check_write);
+  end_task(&app, &progress, &result);
+  progress.done();
+  record_audit(&app, "export-markdown-vault", &dir, || None, &result);
+  Ok(result?)
+}
+
+// Export every board in a folder, one file each; see batch.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn batch_export(
+  app: tauri::AppHandle,
+  args: BatchExportArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<batch::BatchExport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let input_dir = match args.input_dir {
+    Some(dir) => PathBuf::from(dir),
+    None => {
+      let folder = app.dialog()
+        .file()
+        .set_title("Choose Folder of Boards to Export")
+        .pick_folder_async().await
+        .ok_or("Operation cancelled by user")?;
+      folder.as_path().ok_or("Invalid folder selected")?.to_path_buf()
+    },
+  };
+  let output_dir = writable_folder(&app, args.output_dir, "Export Boards To").await?;
+  let options = TextExportOptions {
+    ordering: args.ordering.unwrap_or_else(|| "spatial".to_string()),
+    scrub_metadata: false,
+    include_properties: false,
+    include_stats: false,
+    locale: export_locale(&app, args.locale.as_deref(), args.timezone.as_deref())?,
+  };
+
+  let progress = progress::Progress::new(on_progress);
+  begin_task(&app, "batch-export", &progress);
+  progress.phase("exporting", 0.0);
+  let mut on_board = |index: usize, total: usize| {
+    progress.step("exporting", 0.0, 100.0, index as u64, total as u64);
+    progress.check_cancelled()
+  };
+  let result = batch::export(&input_dir, &output_dir, &args.format, &options, &|path| check_write_path(&app, path), &mut on_board);
+  end_task(&app, &progress, &result);
+  record_audit(&app, "batch-export", &output_dir, || None, &result);
+  progress.done();
+  Ok(result?)
+}
+
+// Serve the window's board read-only over the LAN, updated live as the
+// frontend reports changes; see share_server.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn start_share_server(app: tauri::AppHandle, window: tauri::Window, args: ShareServerArgs) -> Result<share_server::ShareServerInfo, AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("App state unavailable")?;
+  let mut app_state = state.lock().map_err(|_| "App state lock poisoned")?;
+  if let Some(server) = &app_state.share_server {
+    return Err(format!("A board is already shared at {}; stop sharing it first", server.info().url).into());
+  }
+  let server = share_server::start(&args.doc, args.port.unwrap_or(share_server::DEFAULT_PORT), window.label())?;
+  let info = server.info();
+  app_state.share_server = Some(server);
+  Ok(info)
+}
+
+// The board of the window changed; viewers get it if it is the shared one.
+// None when that board isn't shared.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn update_share_server(app: tauri::AppHandle, window: tauri::Window, doc: model::BoardDocument) -> Result<Option<share_server::ShareServerInfo>, AppError> {
+  let server = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().and_then(|app_state| app_state.share_server.clone()))
+    .filter(|server| server.window == window.label());
+  let Some(server) = server else {
+    return Ok(None);
+  };
+  let info = tauri::async_runtime::spawn_blocking(move || server.update(&doc).map(|_| server.info()))
+    .await
+    .map_err(|e| format!("Updating the shared board failed: {}", e))??;
+  Ok(Some(info))
+}
+
+// Stop sharing; false when nothing was shared
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn stop_share_server(app: tauri::AppHandle) -> Result<bool, AppError> {
+  let server = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().and_then(|mut app_state| app_state.share_server.take()));
+  if let Some(server) = &server {
+    server.stop();
+  }
+  Ok(server.is_some())
+}
+
+// Read edits made to an exported Markdown vault back into the board
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn sync_markdown_vault(app: tauri::AppHandle, args: VaultSyncArgs) -> Result<vault::VaultSync, AppError> {
+  let dir = writable_folder(&app, args.dir, "Sync Markdown Vault").await?;
+  let result = vault::sync(&args.doc, &dir, &|path| check_write_path(&app, path));
+  record_audit(&app, "sync-markdown-vault", &dir, || None, &result);
+  Ok(result?)
+}
+
+// Image notes from PDFs and images exported by Apple Freeform; see freeform.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_freeform_assets(
+  app: tauri::AppHandle,
+  args: FreeformImportArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<freeform::FreeformImport, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let file_paths = app.dialog()
+    .file()
+    .add_filter("Freeform Exports", &["pdf", "png", "jpg", "jpeg", "gif", "webp", "bmp"])
+    .set_title("Import Freeform Export")
+    .pick_files_async().await
+    .ok_or("Operation cancelled by user")?;
+
+  let paths = file_paths.iter()
+    .map(|p| p.as_path().map(Path::to_path_buf).ok_or("Invalid file path selected"))
+    .collect::<Result<Vec<_>, _>>()?;
+  for path in &paths {
+    authorize_path(&app, path);
+  }
+
+  let path_refs: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+  let progress = progress::Progress::new(on_progress);
+  begin_task(&app, "import-freeform", &progress);
+  let mut on_asset = |index: usize, total: usize| {
+    progress.step("reading", 0.0, 100.0, index as u64, total as u64);
+    progress.check_cancelled()
+  };
+  let result = freeform::import_assets(&path_refs, args.origin, &mut on_asset);
+  end_task(&app, &progress, &result);
+  for path in &paths {
+    record_audit(&app, "import-freeform", path, || None, &result);
+  }
+  progress.done();
+  Ok(result?)
+}
+
+// Build a board from an OPML outline; see opml.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_opml(app: tauri::AppHandle) -> Result<model::BoardDocument, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let file_path = app.dialog()
+    .file()
+    .add_filter("OPML Files", &["opml", "xml"])
+    .set_title("Import OPML Outline")
+    .pick_file_async().await;
+
+  let path = match file_path {
+    Some(p) => match p.as_path() {
+      Some(path) => path.to_path_buf(),
+      None => return Err("Invalid file path selected".into()),
+    },
+    None => return Err("Operation cancelled by user".into()),
+  };
+  authorize_path(&app, &path);
+
+  let result = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read OPML file '{}': {}", path.display(), e))
+    .and_then(|xml| opml::import(&xml));
+  record_audit(&app, "import-opml", &path, || result.as_ref().ok().and_then(audit::hash_document), &result);
+  Ok(result?)
+}
+
+// Build a board from an OPML or Markdown outline: outline items become notes,
+// parent → child relationships connections, laid out left to right. The
+// format is told from the content, since outliners export both as .txt too.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn import_document(app: tauri::AppHandle, args: ImportDocumentArgs) -> Result<model::BoardDocument, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let path = match args.path {
+    Some(path) => PathBuf::from(path),
+    None => {
+      let file_path = app.dialog()
+        .file()
+        .add_filter("Outlines", &["opml", "xml", "md", "markdown", "txt"])
+        .add_filter("OPML Files", &["opml", "xml"])
+        .add_filter("Markdown Files", &["md", "markdown", "txt"])
+        .set_title("Import Outline")
+        .pick_file_async().await
+        .ok_or("Operation cancelled by user")?;
+      let path = file_path.as_path().ok_or("Invalid file path selected")?.to_path_buf();
+      authorize_path(&app, &path);
+      path
+    },
+  };
+
+  let options = outline::OutlineImportOptions { mode: args.mode, origin: None };
+  let result = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Failed to read outline file '{}': {}", path.display(), e))
+    .and_then(|text| {
+      let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+      if text.trim_start().starts_with('<') {
+        opml::import(text)
+      } else {
+        outline::import_markdown(text, &options)
+      }
+    });
+  record_audit(&app, "import-document", &path, || result.as_ref().ok().and_then(audit::hash_document), &result);
+  Ok(result?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn open_specific_document(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  file_path: String,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<model::BoardDocument, AppError> {
+  let path = Path::new(&file_path);
+  ensure_not_open_elsewhere(&app, path, window.label())?;
+  ensure_unlocked(path)?;
+
+  let progress = progress::Progress::new(on_progress);
+  let result = load_document_migrated(path, lazy_media_limit(&app), &progress);
+  record_audit(&app, "open", path, || result.as_ref().ok().and_then(|(doc, _)| audit::hash_document(doc)), &result);
+  let (doc, report) = result?;
+
+  // Add to recent files
+  add_recent_file(&app, &path.to_string_lossy());
+  record_usage(&app, |usage| usage.opened(path, &doc));
+  set_opened_path(&app, path, &doc, report.newer, window.label());
+  report_migration(&app, path, &report, window.label());
+  progress.done();
+
+  Ok(doc)
+}
+
+// Open a board for reference: edits, saving over it and autosave are refused
+// until another board is opened or it is saved under a new name. Also the
+// fallback when a board can't be opened for editing.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn open_readonly(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  file_path: String,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<model::BoardDocument, AppError> {
+  let path = Path::new(&file_path);
+  ensure_not_open_elsewhere(&app, path, window.label())?;
+  ensure_unlocked(path)?;
+
+  let progress = progress::Progress::new(on_progress);
+  let result = load_document_migrated(path, lazy_media_limit(&app), &progress);
+  record_audit(&app, "open-readonly", path, || result.as_ref().ok().and_then(|(doc, _)| audit::hash_document(doc)), &result);
+  let (doc, report) = result?;
+
+  add_recent_file(&app, &path.to_string_lossy());
+  record_usage(&app, |usage| usage.opened(path, &doc));
+  set_opened_path(&app, path, &doc, true, window.label());
+  report_migration(&app, path, &report, window.label());
+  progress.done();
+
+  Ok(doc)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn is_read_only(app: tauri::AppHandle, window: tauri::Window) -> Result<bool, AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  Ok(app_state.open_documents.window(window.label()).is_some_and(|document| document.read_only))
+}
+
+// Open a hand-edited board that doesn't load normally: returns the
+// best-effort document along with what had to be repaired
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn open_document_lenient(app: tauri::AppHandle, file_path: String) -> Result<lenient::LenientDocument, AppError> {
+  let path = Path::new(&file_path);
+  ensure_unlocked(path)?;
+
+  let result = load_document_lenient(path);
+  record_audit(&app, "open-lenient", path, || result.as_ref().ok().and_then(|r| audit::hash_document(&r.document)), &result);
+  let parsed = result?;
+
+  add_recent_file(&app, &path.to_string_lossy());
+
+  Ok(parsed)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_document(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  args: SaveArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  // Validate document before saving
+  if args.doc.schema_version == 0 {
+    return Err("Cannot save document with invalid schema version".into());
+  }
+
+  let mut dialog = app.dialog()
+    .file()
+    .add_filter("FIM Files", &["fim"])
+    .add_filter("JSON", &["json"])
+    .add_filter("All Supported", &["fim", "json"])
+    .set_file_name(format!("{}.fim", title::file_stem(&args.doc)))
+    .set_title("Save Board Document");
+  // Start where the last save went
+  let directory = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().and_then(|s| s.session.save_directory()));
+  if let Some(directory) = directory {
+    dialog = dialog.set_directory(directory);
+  }
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Save").await?;
+  let current_path = current_document_path(&app, window.label());
+  set_protection(&path, current_path, args.password, args.remove_password.unwrap_or(false)).await?;
+
+  save_to_path(&app, args.doc, &path, window.label(), progress::Progress::new(on_progress))
+}
+
+// Decide whether saving to `path` encrypts it: with a new password, without
+// one any more, or like the open board (from `current_path`) when saving it
+// under a new name
+async fn set_protection(path: &Path, current_path: Option<String>, password: Option<String>, remove: bool) -> Result<(), AppError> {
+  if remove {
+    encryption::forget(path);
+    return Ok(());
+  }
+  let inherited = current_path.and_then(|current| encryption::key_for(Path::new(&current)));
+  if password.is_none() && inherited.is_none() && encryption::key_for(path).is_none() {
+    return Ok(());
+  }
+  if path.extension().map_or(true, |ext| ext != "fim") {
+    return Err(AppError::Unsupported {
+      message: "Password protection needs a .fim file; save as .fim or remove the password".to_string(),
+    });
+  }
+  let key = match password {
+    // Deriving the key takes a moment on purpose
+    Some(password) => Some(tauri::async_runtime::spawn_blocking(move || encryption::new_key(&password))
+      .await
+      .map_err(|e| format!("Deriving the file key failed: {}", e))??),
+    None => inherited.filter(|_| encryption::key_for(path).is_none()),
+  };
+  if let Some(key) = key {
+    encryption::remember(path, key);
+  }
+  Ok(())
+}
+
+// Save the open board to the file it came from, without a dialog. Refuses
+// when the file changed on disk since it was opened or last saved, unless
+// `force` is set; the frontend then offers Save As or saving anyway.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_current_document(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  args: SaveCurrentArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  save_over_current(&app, window.label(), args.doc, args.force.unwrap_or(false), progress::Progress::new(on_progress))
+}
+
+// Like save_current_document, but never overwrites changes made elsewhere:
+// fails with `conflict` when the file's content changed since it was opened
+// or last saved
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_document_with_conflict_check(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  doc: model::BoardDocument,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  save_over_current(&app, window.label(), doc, false, progress::Progress::new(on_progress))
+}
+
+fn save_over_current(
+  app: &tauri::AppHandle,
+  window: &str,
+  doc: model::BoardDocument,
+  force: bool,
+  progress: progress::Progress,
+) -> Result<String, AppError> {
+  if doc.schema_version == 0 {
+    return Err("Cannot save document with invalid schema version".into());
+  }
+  ensure_writable(app, window)?;
+
+  let (current_path, known) = {
+    let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+    let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+    let document = app_state.open_documents.window(window);
+    (document.and_then(|d| d.path.clone()), document.and_then(|d| d.stamp.clone()))
+  };
+  let current_path = current_path.ok_or_else(|| AppError::InvalidInput {
+    message: "The board hasn't been saved yet; use Save As to choose a file".to_string(),
+  })?;
+  let path = check_write_path(app, Path::new(&current_path))?;
+
+  // A file that is gone is simply written again
+  if !force && file_watch::changed(known.as_ref(), &path) {
+    return Err(AppError::Conflict {
+      message: format!("'{}' changed on disk since it was opened", path.display()),
+    });
+  }
+
+  save_to_path(app, doc, &path, window, progress)
+}
+
+// Remember the file of the board open in `window` as it is on disk now, and
+// watch it for changes made elsewhere; see file_watch.rs
+fn track_document_file(app: &tauri::AppHandle, document: &mut open_documents::WindowDocument, path: &Path, window: &str) {
+  document.stamp = file_watch::FileStamp::read(path);
+  if document.watcher.as_ref().is_some_and(|watcher| watcher.path() == path) {
+    return;
+  }
+  let (handle, watched, label) = (app.clone(), path.to_path_buf(), window.to_string());
+  let mut reported = None;
+  let watcher = file_watch::watch(path, move || {
+    let known = handle.try_state::<Mutex<AppState>>()
+      .and_then(|state| state.lock().ok().and_then(|s| s.open_documents.window(&label).map(|d| (d.path.clone(), d.stamp.clone()))));
+    let Some((Some(current), known)) = known else {
+      return;
+    };
+    if Path::new(&current) != watched {
+      return;
+    }
+    let exists = watched.exists();
+    // Report each state of the file once; a deleted file only if it was there
+    let changed = if exists { file_watch::changed(known.as_ref(), &watched) } else { known.is_some() };
+    let state = Some(file_watch::FileStamp::read(&watched));
+    if !changed || state == reported {
+      return;
+    }
+    reported = state;
+    let event = DocumentChangedEvent { path: current, exists };
+    if let Err(e) = handle.emit_to(label.as_str(), "document-changed-externally", event) {
+      log::warn!("Failed to emit document-changed-externally event: {}", e);
+    }
+  });
+  document.watcher = match watcher {
+    Ok(watcher) => Some(watcher),
+    Err(e) => {
+      log::warn!("{}", e);
+      None
+    },
+  };
+}
+
+// Write the board to `path` and make it the open board's file in `window`:
+// embedded fonts, recent files, backups, the search sidecar and recovery
+// cleanup. Shared by Save and Save As.
+fn save_to_path(
+  app: &tauri::AppHandle,
+  mut doc: model::BoardDocument,
+  path: &Path,
+  window: &str,
+  progress: progress::Progress,
+) -> Result<String, AppError> {
+  let path = path.to_path_buf();
+  ensure_not_open_elsewhere(app, &path, window)?;
+  let (simplify_strokes, embed_fonts, current_path, read_only, history, author) = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|app_state| {
+      let document = app_state.open_documents.window(window);
+      (
+        app_state.settings.simplify_strokes,
+        app_state.settings.embed_fonts,
+        document.and_then(|d| d.path.clone()),
+        document.is_some_and(|d| d.read_only),
+        document.and_then(|d| d.note_history.clone()),
+        app_state.settings.author_name.clone(),
+      )
+    }))
+    .unwrap_or_default();
+  // A read-only board can be saved as a copy, not over itself
+  if read_only && current_path.as_deref().is_some_and(|current| Path::new(current) == path) {
+    ensure_writable(app, window)?;
+  }
+  if simplify_strokes {
+    strokes::simplify_document(&mut doc, strokes::SIMPLIFY_TOLERANCE);
+  }
+  metadata::stamp(&mut doc, history.as_ref(), metadata::default_author(&author), chrono::Utc::now());
+
+  // Fonts the board carried so far come from the file being replaced, or the
+  // one it was opened from when saving under a new name
+  let fonts = if embed_fonts && path.extension().is_some_and(|ext| ext == "fim") {
+    let previous = if path.exists() { path.clone() } else { current_path.map(PathBuf::from).unwrap_or_default() };
+    let (fonts, skipped) = fonts::collect(&doc, fonts::read_embedded(&previous));
+    for font in skipped {
+      log::warn!("Not embedding font '{}': {}", font.family, font.reason);
+    }
+    fonts
+  } else {
+    Vec::new()
+  };
+
+  let result = write_document(&doc, &path, &fonts, write_options(app, &path), &progress);
+  record_audit(app, "save", &path, || audit::hash_document(&doc), &result);
+  result?;
+  record_usage(app, |usage| usage.saved(&path, &doc, true));
+  // Update state with current document path
+  let path_str = path.to_string_lossy().to_string();
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.session.saved(&path);
+      let document = app_state.open_documents.open(&path, window);
+      document.note_history = Some(metadata::NoteHistory::of(&doc));
+      document.read_only = false;
+      document.is_dirty = false;
+      document.last_autosave_time = Some(std::time::SystemTime::now());
+      track_document_file(app, document, &path, window);
+      app_state.backups.track(&path);
+
+      // Also add to recent files
+      app_state.recent_files.touch(&path_str);
+      if let Err(e) = app_state.recent_files.save() {
+        log::warn!("{}", e);
+      }
+
+      // Keep the plain-text twin for OS search in sync with the board
+      // A protected board's text stays encrypted
+      if app_state.settings.search_sidecar && path.extension().is_some_and(|ext| ext == "fim") && encryption::key_for(&path).is_none() {
+        if let Err(e) = search_index::write_sidecar(&doc, &path) {
+          log::warn!("{}", e);
+        }
+      }
+
+      // The saved file supersedes any pending autosave and the recovery file
+      app_state.autosave.discard(&path);
+      remove_recovery(&app_state.settings.recovery_locations, &path, app_state.settings.secure_cleanup);
+    }
+  }
+  progress.done();
+  
+  Ok(path_str)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn take_pending_open_documents(app: tauri::AppHandle, window: tauri::Window) -> Result<Vec<OpenDocumentEvent>, AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      // From now on documents are delivered via the `open-document` event
+      app_state.ready_windows.insert(window.label().to_string());
+      return Ok(app_state.pending_open_documents.remove(window.label()).unwrap_or_default());
+    }
+  }
+  Ok(Vec::new())
+}
+
+// Open the board at `path` in a window of its own, so two boards can be
+// worked on side by side. The new window gets the board from
+// take_pending_open_documents once its frontend has mounted; a board already
+// open in a window is brought forward there instead. Returns the window's label.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn new_window_with_document(app: tauri::AppHandle, path: String) -> Result<String, AppError> {
+  let path = PathBuf::from(path);
+  if let Some(owner) = document_window(&app, &path) {
+    focus_window(&app, &owner);
+    return Ok(owner);
+  }
+  if !supported_document_path(&path) {
+    return Err(AppError::Unsupported { message: format!("'{}' is not a board that can be opened", path.display()) });
+  }
+  ensure_unlocked(&path)?;
+
+  let event = load_external_document(&app, &path);
+  let doc = match (&event.document, &event.error) {
+    (Some(doc), _) => doc,
+    (None, error) => return Err(error.clone().unwrap_or_else(|| "Failed to open board".to_string()).into()),
+  };
+  // Claimed before the window exists, so the board can't be opened twice meanwhile
+  let label = model::new_id("board");
+  let read_only = event.migration.as_ref().is_some_and(|report| report.newer);
+  set_opened_path(&app, &path, doc, read_only, &label);
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.pending_open_documents.insert(label.clone(), vec![event]);
+    }
+  }
+
+  let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "FIM".to_string());
+  let built = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::default())
+    .title(title)
+    .inner_size(1200.0, 800.0)
+    .build();
+  if let Err(e) = built {
+    if let Some(state) = app.try_state::<Mutex<AppState>>() {
+      if let Ok(mut app_state) = state.lock() {
+        app_state.open_documents.close_window(&label);
+        app_state.pending_open_documents.remove(&label);
+      }
+    }
+    return Err(format!("Failed to open a window for '{}': {}", path.display(), e).into());
+  }
+  Ok(label)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_recent_files(app: tauri::AppHandle) -> Result<Vec<recent_files::RecentFileEntry>, AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      return Ok(app_state.recent_files.entries());
+    }
+  }
+  Ok(Vec::new())
+}
+
+// Pinned recent files stay at the top of the list and survive clearing
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn set_recent_file_pinned(app: tauri::AppHandle, path: String, pinned: bool) -> Result<(), AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.recent_files.set_pinned(&path, pinned)?;
+      return Ok(app_state.recent_files.save()?);
+    }
+  }
+  Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn pin_recent_file(app: tauri::AppHandle, path: String) -> Result<(), AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  app_state.recent_files.set_pinned(&path, true)?;
+  Ok(app_state.recent_files.save()?)
+}
+
+// Drop one entry from the recent files list, pinned or not
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn remove_recent_file(app: tauri::AppHandle, path: String) -> Result<(), AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  app_state.recent_files.remove(&path);
+  Ok(app_state.recent_files.save()?)
+}
+
+// The board open when the app last quit and where the last save went, for
+// offering to reopen it on the start screen
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_last_session(app: tauri::AppHandle) -> Result<session::LastSession, AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  Ok(app_state.session.describe())
+}
+
+// Local usage statistics; `since` (YYYY-MM-DD) limits the days returned
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_usage_stats(app: tauri::AppHandle, since: Option<String>) -> Result<usage::UsageReport, AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  let enabled = app_state.settings.usage_stats;
+  Ok(app_state.usage.report(enabled, since.as_deref()))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn clear_usage_stats(app: tauri::AppHandle) -> Result<(), AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  Ok(app_state.usage.clear()?)
+}
+
+// Long-running operations, running ones first; see tasks.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_tasks(app: tauri::AppHandle) -> Result<Vec<tasks::TaskInfo>, AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      return Ok(app_state.tasks.list());
+    }
+  }
+  Ok(Vec::new())
+}
+
+// Ask a running task to stop; it fails with "Task cancelled" at its next
+// safe point
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn cancel_task(app: tauri::AppHandle, id: String) -> Result<(), AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  Ok(app_state.tasks.cancel(&id)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn clear_recent_files(app: tauri::AppHandle) -> Result<(), AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.recent_files.clear();
+      if let Err(e) = app_state.recent_files.save() {
+        log::warn!("{}", e);
+      }
+      app_state.session.clear();
+    }
+  }
+  recent_documents::clear_recent_documents(&app);
+  Ok(())
+}
+
+// Move a board and its sidecar and recovery files into the archive folder,
+// recorded in the library index; see library.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn archive_board(app: tauri::AppHandle, path: String) -> Result<library::ArchivedBoard, AppError> {
+  let path = check_write_path(&app, Path::new(&path))?;
+  let title = load_document(&path, &progress::Progress::none()).ok()
+    .and_then(|doc| title::suggest_titles(&doc).into_iter().next())
+    .map(|suggestion| suggestion.title)
+    .unwrap_or_else(|| path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default());
+  let secure = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|app_state| app_state.settings.secure_cleanup))
+    .unwrap_or(false);
+
+  let artifacts = board_artifacts(&path, &recovery_locations(&app));
+  let result = library::archive_board(&path, &artifacts, &title, secure);
+  record_audit(&app, "archive", &path, || None, &result);
+  let archived = result?;
+
+  let mut index = library::Library::load();
+  index.boards.push(archived.clone());
+  index.save()?;
+
+  // Finished projects shouldn't clutter the recent files
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.recent_files.remove(&archived.original_path);
+      if let Err(e) = app_state.recent_files.save() {
+        log::warn!("{}", e);
+      }
+    }
+  }
+  Ok(archived)
+}
+
+// Restore an archived board to where it was, or into `destination`; returns
+// the board's path
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn unarchive_board(app: tauri::AppHandle, id: String, destination: Option<String>) -> Result<String, AppError> {
+  let mut index = library::Library::load();
+  let position = index.boards.iter().position(|b| b.id == id)
+    .ok_or_else(|| format!("Archived board '{}' not found", id))?;
+  let board = index.boards[position].clone();
+
+  let mut targets = library::restore_targets(&board, destination.as_deref().map(Path::new))?;
+  // The index only holds places boards came from; other folders need permission
+  if destination.is_some() {
+    for (_, target) in targets.iter_mut() {
+      *target = check_write_path(&app, target)?;
+    }
+  }
+
+  let result = library::unarchive_board(&board, &targets);
+  let audit_path = targets.first().map(|(_, target)| target.clone()).unwrap_or_default();
+  record_audit(&app, "unarchive", &audit_path, || None, &result);
+  let restored = result?;
+
+  index.boards.remove(position);
+  index.save()?;
+  authorize_path(&app, &restored);
+  add_recent_file(&app, &restored.to_string_lossy());
+  Ok(restored.to_string_lossy().to_string())
+}
+
+// A board's title, counts and info block (owner, password hint) without
+// opening it, e.g. before asking for the passphrase of its confidential notes
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn read_document_info(file_path: String) -> Result<library::BoardMetadata, AppError> {
+  let (metadata, _) = library::read_board_metadata(Path::new(&file_path))?;
+  Ok(metadata)
+}
+
+// Boards under `root_dirs` (default: Documents) for the gallery; see library.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn scan_library(root_dirs: Option<Vec<String>>) -> Result<Vec<library::LibraryBoard>, AppError> {
+  let roots: Vec<PathBuf> = match root_dirs {
+    Some(dirs) if !dirs.is_empty() => dirs.into_iter().map(PathBuf::from).collect(),
+    _ => dirs::document_dir().into_iter().collect(),
+  };
+  // Walks folders and opens every board; keep it off the async workers
+  let boards = tauri::async_runtime::spawn_blocking(move || library::scan(&roots))
+    .await
+    .map_err(|e| format!("Library scan failed: {}", e))?;
+  Ok(boards)
+}
+
+// Existing notes (and library boards) resembling text being typed; see similar.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn suggest_similar(args: SuggestSimilarArgs) -> Result<similar::SimilarSuggestions, AppError> {
+  let boards = if args.include_library.unwrap_or(false) { library::cached_boards() } else { Vec::new() };
+  Ok(similar::suggest_similar(
+    &args.doc,
+    &args.text,
+    args.limit,
+    args.min_score,
+    args.exclude_note_id.as_deref(),
+    &boards,
+  ))
+}
+
+// Archived boards, newest first; `query` matches titles and original paths
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_archived_boards(query: Option<String>) -> Result<Vec<library::ArchivedBoard>, AppError> {
+  let query = query.map(|q| q.trim().to_lowercase()).filter(|q| !q.is_empty());
+  let mut boards: Vec<library::ArchivedBoard> = library::Library::load().boards.into_iter()
+    .filter(|board| query.as_ref().map_or(true, |q| {
+      board.title.to_lowercase().contains(q) || board.original_path.to_lowercase().contains(q)
+    }))
+    .collect();
+  boards.sort_by_key(|board| std::cmp::Reverse(board.archived_at));
+  Ok(boards)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_settings(app: tauri::AppHandle) -> Result<settings::AppSettings, AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      return Ok(app_state.settings.clone());
+    }
+  }
+  Ok(settings::load_settings())
+}
+
+// Change the settings given in `changes`, keeping the rest
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn update_settings(app: tauri::AppHandle, changes: serde_json::Value) -> Result<settings::AppSettings, AppError> {
+  let current = match app.try_state::<Mutex<AppState>>() {
+    Some(state) => state.lock().map_err(|e| format!("Failed to access application state: {}", e))?.settings.clone(),
+    None => settings::load_settings(),
+  };
+  let mut settings = settings::merge_settings(&current, changes)?;
+  // Autosave writes into these folders without a path scope check, so they
+  // only change through add_recovery_location / remove_recovery_location
+  settings.recovery_locations = current.recovery_locations.clone();
+
+  #[cfg(desktop)]
+  {
+    let previous = &current.quick_capture_shortcut;
+    if previous != &settings.quick_capture_shortcut {
+      if let Err(e) = register_quick_capture_shortcut(&app, previous, &settings.quick_capture_shortcut) {
+        // Keep the old shortcut working when the new one is invalid or taken
+        let _ = register_quick_capture_shortcut(&app, "", previous);
+        return Err(e.into());
+      }
+    }
+  }
+
+  settings::save_settings(&settings)?;
+
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      app_state.settings = settings.clone();
+    }
+  }
+
+  Ok(settings)
+}
+
+// Whether the folders the app writes to are usable; see health.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn run_health_check(app: tauri::AppHandle) -> Result<health::HealthReport, AppError> {
+  let settings = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|s| s.settings.clone()))
+    .unwrap_or_else(settings::load_settings);
+  // Probes the disk, possibly network drives; keep it off the async workers
+  let report = tauri::async_runtime::spawn_blocking(move || health::run(&settings))
+    .await
+    .map_err(|e| format!("Health check failed: {}", e))?;
+  Ok(report)
+}
+
+// The end of the diagnostics log, for attaching to bug reports
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_recent_logs(limit: Option<usize>) -> Result<logging::RecentLogs, AppError> {
+  Ok(logging::recent(limit)?)
+}
+
+// Most IDs one `generate_ids` call hands out
+const MAX_GENERATED_IDS: usize = 10_000;
+
+// IDs for entities the frontend creates, from the same generator as the
+// backend's own so they sort by creation time and never collide; see
+// `model::new_id`. `prefix` names the kind, e.g. "note" or "conn".
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn generate_ids(count: usize, prefix: Option<String>) -> Result<Vec<String>, AppError> {
+  if count > MAX_GENERATED_IDS {
+    return Err(AppError::InvalidInput { message: format!("Cannot generate more than {} IDs at once", MAX_GENERATED_IDS) });
+  }
+  let prefix = prefix.unwrap_or_else(|| "id".to_string());
+  if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+    return Err(AppError::InvalidInput { message: format!("Invalid ID prefix '{}': use letters, digits and hyphens", prefix) });
+  }
+  Ok((0..count).map(|_| model::new_id(&prefix)).collect())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_audit_log(limit: Option<usize>) -> Result<Vec<audit::AuditEntry>, AppError> {
+  Ok(audit::read_entries(limit)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn autosave_document(app: tauri::AppHandle, window: tauri::Window, args: AutosaveArgs) -> Result<AutosaveInfo, AppError> {
+  // Validate document before autosaving
+  if args.doc.schema_version == 0 {
+    return Err("Cannot autosave document with invalid schema version".into());
+  }
+  ensure_writable(&app, window.label())?;
+
+  let path = check_write_path(&app, Path::new(&args.file_path))?;
+  let recovery_path = recovery::recovery_path(&recovery_locations(&app), &path)?;
+
+  // Only the latest snapshot is kept; the writer writes it once editing
+  // pauses and reports the outcome as `autosave-completed` or
+  // `autosave-failed`. The timestamp returned is when it was queued.
+  let start_writer = {
+    let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+    let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+    app_state.autosave.submit(&path, args.doc)
+  };
+  if start_writer {
+    let handle = app.clone();
+    let writer_path = path.clone();
+    std::thread::spawn(move || run_autosave_writer(handle, writer_path));
+  }
+
+  Ok(AutosaveInfo {
+    original_path: path.to_string_lossy().to_string(),
+    recovery_path: recovery_path.to_string_lossy().to_string(),
+    timestamp: chrono::Utc::now(),
+  })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn set_document_dirty(app: tauri::AppHandle, window: tauri::Window, is_dirty: bool) -> Result<(), AppError> {
+  if is_dirty {
+    ensure_writable(&app, window.label())?;
+  }
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      let document = app_state.open_documents.window_mut(window.label());
+      document.is_dirty = is_dirty;
+      let path = document.path.clone();
+      // Edits mark the board dirty, so this doubles as activity
+      if is_dirty && app_state.settings.usage_stats {
+        if let Some(path) = path {
+          app_state.usage.edited(&path);
+        }
+      }
+    }
+  }
+  Ok(())
+}
+
+// Copy the boards that changed since their last backup to the backup folder.
+// Scheduled runs only happen when backups are on and the interval has passed;
+// `force` runs regardless.
+fn run_backups(app: &tauri::AppHandle, force: bool) -> Result<Vec<backup::BackupRecord>, String> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let (settings, changed) = {
+    let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+    let settings = app_state.settings.backup.clone();
+    let interval = std::time::Duration::from_secs(settings.interval_minutes.max(1) as u64 * 60);
+    if !force && (!settings.enabled || !app_state.backups.is_due(interval)) {
+      return Ok(Vec::new());
+    }
+    (settings, app_state.backups.changed())
+  };
+  let dir = backup::backup_dir(&settings).ok_or("Could not determine the backup folder")?;
+
+  let mut records = Vec::new();
+  let mut backed_up = Vec::new();
+  let mut errors = Vec::new();
+  for (source, modified) in changed {
+    let result = backup::backup_file(&source, &dir, settings.keep);
+    record_audit(app, "backup", &source, || None, &result);
+    match result {
+      Ok(target) => {
+        records.push(backup::BackupRecord {
+          source: source.to_string_lossy().to_string(),
+          backup: target.to_string_lossy().to_string(),
+        });
+        backed_up.push((source, modified));
+      },
+      Err(e) => errors.push(e),
+    }
+  }
+
+  if let Ok(mut app_state) = state.lock() {
+    app_state.backups.finished(&backed_up);
+  }
+  if !errors.is_empty() {
+    return Err(errors.join("; "));
+  }
+  Ok(records)
+}
+
+// Back up the changed boards now, whatever the schedule
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn run_backup_now(app: tauri::AppHandle) -> Result<Vec<backup::BackupRecord>, AppError> {
+  let handle = app.clone();
+  let records = tauri::async_runtime::spawn_blocking(move || run_backups(&handle, true))
+    .await
+    .map_err(|e| format!("Backup failed: {}", e))?;
+  Ok(records?)
+}
+
+// Backups in the backup folder, newest first; only those of `path` when given
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_backups(app: tauri::AppHandle, path: Option<String>) -> Result<Vec<backup::BackupEntry>, AppError> {
+  let settings = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|s| s.settings.backup.clone()))
+    .unwrap_or_else(|| settings::load_settings().backup);
+  let dir = backup::backup_dir(&settings).ok_or("Could not determine the backup folder")?;
+  Ok(backup::list_backups(&dir, path.as_deref().map(Path::new)))
+}
+
+// Snapshots; see snapshots.rs
+fn ensure_snapshot_container(path: &Path) -> Result<(), AppError> {
+  ensure_unlocked(path)?;
+  if !matches!(sniff_format(path)?, "fim" | "encrypted") {
+    return Err(AppError::Unsupported {
+      message: format!("Snapshots are kept in .fim boards; save '{}' as .fim first", path.display()),
+    });
+  }
+  Ok(())
+}
+
+// Keep `doc` as it is now inside its .fim file, leaving the saved board as it is
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn create_snapshot(app: tauri::AppHandle, window: tauri::Window, args: CreateSnapshotArgs) -> Result<snapshots::Snapshot, AppError> {
+  let (current_path, known, keep) = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|s| {
+      let document = s.open_documents.window(window.label());
+      (document.and_then(|d| d.path.clone()), document.and_then(|d| d.stamp.clone()), s.settings.keep_snapshots)
+    }))
+    .unwrap_or_else(|| (None, None, settings::load_settings().keep_snapshots));
+  let path = args.path.or(current_path.clone()).map(PathBuf::from).ok_or_else(|| AppError::InvalidInput {
+    message: "The board hasn't been saved yet; save it before taking a snapshot".to_string(),
+  })?;
+  let is_current = current_path.as_deref().is_some_and(|current| Path::new(current) == path);
+  if is_current {
+    ensure_writable(&app, window.label())?;
+  }
+  let path = check_write_path(&app, &path)?;
+  ensure_snapshot_container(&path)?;
+
+  // Whether the file is still as we last read or wrote it
+  let unchanged = is_current && !file_watch::changed(known.as_ref(), &path);
+  let options = write_options(&app, &path);
+  let written = path.clone();
+  let label = args.label.unwrap_or_default();
+  let snapshot = tauri::async_runtime::spawn_blocking(move || snapshots::add_to_file(&written, &args.doc, &label, keep, options))
+    .await
+    .map_err(|e| format!("Taking snapshot failed: {}", e))??;
+
+  // Rewriting the file to add the snapshot isn't a change made elsewhere
+  if unchanged {
+    if let Some(state) = app.try_state::<Mutex<AppState>>() {
+      if let Ok(mut app_state) = state.lock() {
+        app_state.open_documents.window_mut(window.label()).stamp = file_watch::FileStamp::read(&path);
+      }
+    }
+  }
+  Ok(snapshot)
+}
+
+// Snapshots kept in the .fim board at `path`, newest first
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_snapshots(path: String) -> Result<Vec<snapshots::Snapshot>, AppError> {
+  let path = PathBuf::from(path);
+  ensure_snapshot_container(&path)?;
+  let found = tauri::async_runtime::spawn_blocking(move || snapshots::list(&path))
+    .await
+    .map_err(|e| format!("Listing snapshots failed: {}", e))??;
+  Ok(found)
+}
+
+// The board as it was when the snapshot was taken, upgraded like an opened
+// board. Nothing is written; the frontend shows it and saves it as usual.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn restore_snapshot(path: String, snapshot_id: String) -> Result<model::BoardDocument, AppError> {
+  let path = PathBuf::from(path);
+  ensure_snapshot_container(&path)?;
+  let (doc, _) = tauri::async_runtime::spawn_blocking(move || {
+    let board = snapshots::read_board(&path, &snapshot_id)?;
+    parse_board(&board, &progress::Progress::none())
+  })
+    .await
+    .map_err(|e| format!("Restoring snapshot failed: {}", e))??;
+  Ok(doc)
+}
+
+// Append captured text to the inbox board, creating the board if needed
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn submit_quick_capture(app: tauri::AppHandle, text: String) -> Result<String, AppError> {
+  let path = inbox_path(&app)?;
+  let captured = capture_to_board(&app, &path, &text).await?;
+
+  if let Some(window) = app.get_webview_window(capture::QUICK_CAPTURE_WINDOW) {
+    let _ = window.close();
+  }
+  Ok(captured.path)
+}
+
+// Append captured text as a note to the board at `target_path` (the inbox
+// board by default) without opening it, creating the board if needed
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn quick_capture(app: tauri::AppHandle, text: String, target_path: Option<String>) -> Result<capture::CapturedNote, AppError> {
+  let path = match target_path {
+    Some(path) => check_write_path(&app, Path::new(&path))?,
+    None => inbox_path(&app)?,
+  };
+  capture_to_board(&app, &path, &text).await
+}
+
+// A board open in a ready window gets the note from that window, so its
+// unsaved edits aren't overwritten; otherwise the file is loaded, the note
+// added and the board saved again like any save
+async fn capture_to_board(app: &tauri::AppHandle, path: &Path, text: &str) -> Result<capture::CapturedNote, AppError> {
+  let text = text.trim();
+  if text.is_empty() {
+    return Err(AppError::InvalidInput { message: "Nothing to capture".to_string() });
+  }
+  if !matches!(path.extension().and_then(|ext| ext.to_str()), Some("fim") | Some("json")) {
+    return Err(AppError::InvalidInput { message: format!("Can't capture into '{}': only .fim and .json boards", path.display()) });
+  }
+
+  let (load_path, text_owned) = (path.to_path_buf(), text.to_string());
+  let (doc, note_id) = tauri::async_runtime::spawn_blocking(move || -> Result<_, String> {
+    let mut doc = if load_path.exists() {
+      load_document(&load_path, &progress::Progress::none())?
+    } else {
+      model::BoardDocument::empty()
+    };
+    let note_id = capture::append_note(&mut doc, &text_owned);
+    Ok((doc, note_id))
+  })
+    .await
+    .map_err(|e| format!("Quick capture failed: {}", e))??;
+
+  // A window that isn't listening yet would drop the event, so the note goes
+  // into the file instead and the window sees the board change on disk
+  if let Some(owner) = document_window(app, path).filter(|owner| frontend_ready(app, owner)) {
+    let note = doc.notes.iter().find(|note| note.id == note_id).cloned().ok_or("Captured note went missing")?;
+    match app.emit_to(owner.as_str(), "quick-capture-note", QuickCaptureEvent { note }) {
+      Ok(()) => return Ok(capture::CapturedNote { path: path.to_string_lossy().to_string(), note_id, delivered: true }),
+      Err(e) => log::warn!("Failed to hand the note to the window showing '{}', saving it instead: {}", path.display(), e),
+    }
+  }
+
+  let result = write_document(&doc, path, &[], write_options(app, path), &progress::Progress::none());
+  record_audit(app, "quick-capture", path, || audit::hash_document(&doc), &result);
+  result?;
+  Ok(capture::CapturedNote { path: path.to_string_lossy().to_string(), note_id, delivered: false })
+}
+
+// Bring forward the window named by an `already_open` error
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn focus_document_window(app: tauri::AppHandle, label: String) -> Result<(), AppError> {
+  if !focus_window(&app, &label) {
+    return Err(AppError::NotFound { message: format!("Window '{}' not found", label) });
+  }
+  Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn close_quick_capture(app: tauri::AppHandle) -> Result<(), AppError> {
+  if let Some(window) = app.get_webview_window(capture::QUICK_CAPTURE_WINDOW) {
+    window.close().map_err(|e| format!("Failed to close quick-capture window: {}", e))?;
+  }
+  Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn set_current_document_path(app: tauri::AppHandle, window: tauri::Window, file_path: String) -> Result<(), AppError> {
+  ensure_not_open_elsewhere(&app, Path::new(&file_path), window.label())?;
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      let document = app_state.open_documents.window_mut(window.label());
+      // Loaded by the frontend, so we don't know what was in it. The same
+      // path again is the frontend taking up a board the backend opened for
+      // this window (new_window_with_document), which may be read-only.
+      if document.path.as_deref() != Some(file_path.as_str()) {
+        document.note_history = None;
+        document.read_only = false;
+      }
+      document.set_path(Path::new(&file_path));
+      document.last_autosave_time = None; // Reset autosave time for new document
+      track_document_file(&app, document, Path::new(&file_path), window.label());
+    }
+  }
+  Ok(())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_autosave_status(app: tauri::AppHandle, window: tauri::Window) -> Result<Option<AutosaveInfo>, AppError> {
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(app_state) = state.lock() {
+      if let Some(current_path) = app_state.open_documents.window(window.label()).and_then(|d| d.path.as_ref()) {
+        return Ok(recovery::autosave_info(&app_state.settings.recovery_locations, Path::new(current_path)));
+      }
+    }
+  }
+  Ok(None)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn check_recovery_files(app: tauri::AppHandle) -> Result<Vec<AutosaveInfo>, AppError> {
+  Ok(recovery::list(&recovery_locations(&app))?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_recovery_locations(app: tauri::AppHandle) -> Result<Vec<String>, AppError> {
+  Ok(recovery_locations(&app))
+}
+
+// Update the recovery locations in the settings and save them
+fn update_recovery_locations(app: &tauri::AppHandle, update: impl FnOnce(&mut Vec<String>)) -> Result<Vec<String>, String> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  let mut settings = app_state.settings.clone();
+  update(&mut settings.recovery_locations);
+  settings::save_settings(&settings)?;
+  app_state.settings = settings;
+  Ok(app_state.settings.recovery_locations.clone())
+}
+
+// Add a folder to search for recovery files; autosave uses it once the
+// locations before it are unusable. Without `path`, asks for one; a given
+// path must be writable under the path scope.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn add_recovery_location(app: tauri::AppHandle, path: Option<String>) -> Result<Vec<String>, AppError> {
+  let dir = match path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+    Some(path) => check_write_folder(&app, Path::new(path))?,
+    None => writable_folder(&app, None, "Choose Recovery Location").await?,
+  };
+  let dir = dir.to_string_lossy().to_string();
+  Ok(update_recovery_locations(&app, |locations| {
+    if !locations.contains(&dir) {
+      locations.push(dir);
+    }
+  })?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn remove_recovery_location(app: tauri::AppHandle, path: String) -> Result<Vec<String>, AppError> {
+  Ok(update_recovery_locations(&app, |locations| locations.retain(|l| l != &path))?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn recover_from_autosave(
+  app: tauri::AppHandle,
+  window: tauri::Window,
+  recovery_path: String,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<model::BoardDocument, AppError> {
+  let path = Path::new(&recovery_path);
+
+  if !path.exists() {
+    return Err("Recovery file not found".into());
+  }
+
+  // Load from the recovery file (which is in .fim format, encrypted when the
+  // board is password-protected)
+  ensure_unlocked(path)?;
+  let progress = progress::Progress::new(on_progress);
+  let (doc, _) = match encryption::is_encrypted(path) {
+    true => load_from_fim(encryption::open_archive(path)?, path, None, &progress)?,
+    false => load_from_fim(open_fim_archive(path)?, path, None, &progress)?,
+  };
+
+  // The metadata records the original path; recovery files from earlier
+  // versions lack it, so fall back to guessing from the file name
+  let mut original = recovery::read_metadata(path).map(|info| info.original_path);
+  if original.is_none() {
+    if let Some(original_path) = path.parent().and_then(|p| p.file_name()) {
+      if let Some(original_str) = original_path.to_str() {
+        // Remove .recovery extension to get original filename
+        let original_filename = original_str.replace(".fim.recovery", ".fim");
+        if let Some(parent) = path.parent() {
+          original = Some(format!("{}/{}", parent.display(), original_filename));
+        }
+      }
+    }
+  }
+  if let Some(original) = &original {
+    ensure_not_open_elsewhere(&app, Path::new(original), window.label())?;
+  }
+
+  // Update state to indicate we're working with a recovered document
+  if let Some(state) = app.try_state::<Mutex<AppState>>() {
+    if let Ok(mut app_state) = state.lock() {
+      let document = app_state.open_documents.window_mut(window.label());
+      if let Some(original) = &original {
+        document.set_path(Path::new(original));
+        // Saving the recovered board keeps it protected
+        if let Some(key) = encryption::key_for(path) {
+          encryption::remember(Path::new(original), key);
+        }
+      }
+      document.read_only = false;
+      document.is_dirty = true; // Mark as dirty since it's recovered
+      document.last_autosave_time = None;
+    }
+  }
+  progress.done();
+
+  Ok(doc)
+}
+
+// How the recovery file differs from its board, for choosing between
+// recovering and discarding it
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn preview_recovery(app: tauri::AppHandle, recovery_path: String) -> Result<recovery::RecoveryPreview, AppError> {
+  let path = PathBuf::from(recovery_path);
+  let info = recovery::info(&recovery_locations(&app), &path)?;
+  let original = PathBuf::from(&info.original_path);
+  ensure_unlocked(&path)?;
+  if original.is_file() {
+    ensure_unlocked(&original)?;
+  }
+  let preview = tauri::async_runtime::spawn_blocking(move || {
+    let recovered = load_document(&path, &progress::Progress::none())?;
+    let board = original.is_file().then(|| load_document(&original, &progress::Progress::none())).transpose()?;
+    recovery::preview(&info, board.as_ref(), &recovered)
+  }).await.map_err(|e| format!("Comparing recovery file failed: {}", e))??;
+  Ok(preview)
+}
+
+// Delete a recovery file that isn't wanted, together with its metadata
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn discard_recovery(app: tauri::AppHandle, recovery_path: String) -> Result<(), AppError> {
+  let path = PathBuf::from(recovery_path);
+  let info = recovery::info(&recovery_locations(&app), &path)?;
+  let secure = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|app_state| app_state.settings.secure_cleanup))
+    .unwrap_or(false);
+  let result = recovery::discard(&info, secure);
+  record_audit(&app, "discard-recovery", Path::new(&info.original_path), || None, &result);
+  Ok(result?)
+}
+
+// PNG export command - handles file dialog and path selection
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_document_as_png(app: tauri::AppHandle, args: PngExportArgs) -> Result<PngExportTarget, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  // Validate before asking for a path, so impossible exports fail fast
+  let selection = args.note_ids.as_deref().map(|ids| export_selection(&args.doc, ids)).transpose()?;
+  let plan = raster::plan(selection.as_ref().unwrap_or(&args.doc), &args.options)?;
+
+  let dialog = app.dialog()
+    .file()
+    .add_filter("PNG Files", &["png"])
+    .set_file_name(&format!("idea_map_{}x.png", plan.scale))
+    .set_title(format!("Export as PNG ({}×{} px)", plan.width, plan.height));
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  Ok(PngExportTarget { path: path.to_string_lossy().to_string(), plan, selection })
+}
+
+// The board as SVG, drawn by the backend; see render.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn render_document_to_svg(args: RenderArgs) -> Result<String, AppError> {
+  let (doc, plan) = render_input(&args)?;
+  let doc = match args.scrub_metadata.unwrap_or(false) {
+    true => media::scrub_images(&doc),
+    false => doc,
+  };
+  Ok(render::to_svg(&doc, &plan.region))
+}
+
+// The board as PNG, drawn by the backend, so boards larger than the webview's
+// canvas limit still export
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn render_document_to_png(args: RenderArgs) -> Result<Vec<u8>, AppError> {
+  let (doc, plan) = render_input(&args)?;
+  let png = tauri::async_runtime::spawn_blocking(move || render::to_png(&doc, &plan))
+    .await
+    .map_err(|e| format!("Rendering failed: {}", e))??;
+  Ok(png)
+}
+
+// The visible part of the board a backend rendering draws, limited to the
+// selection if there is one, and where
+fn render_input(args: &RenderArgs) -> Result<(model::BoardDocument, raster::RasterPlan), String> {
+  let doc = layers::filter_layers(&args.doc, &layers::visible_layers(&args.doc));
+  let doc = match &args.note_ids {
+    Some(ids) => export_selection(&doc, ids)?,
+    None => doc,
+  };
+  let plan = raster::plan(&doc, &args.options)?;
+  Ok((doc, plan))
+}
+
+// PDF export command - handles file dialog and path selection. With `render`
+// the backend also writes the PDF (see pdf.rs); without it the frontend
+// renders one and saves it with save_pdf_to_file.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_document_as_pdf(
+  app: tauri::AppHandle,
+  page_size: String,
+  orientation: String,
+  output_path: Option<String>,
+  render: Option<PdfRenderArgs>,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  // Validate page size
+  let valid_sizes = vec!["a3", "a4", "a5", "letter", "legal"];
+  if !valid_sizes.contains(&page_size.as_str()) {
+    return Err("Invalid page size. Must be one of: a3, a4, a5, letter, legal".to_string().into());
+  }
+
+  // Validate orientation
+  let valid_orientations = vec!["auto", "portrait", "landscape"];
+  if !valid_orientations.contains(&orientation.as_str()) {
+    return Err("Invalid orientation. Must be one of: auto, portrait, landscape".to_string().into());
+  }
+
+  let dialog = app.dialog()
+    .file()
+    .add_filter("PDF Files", &["pdf"])
+    .set_file_name(&format!("idea_map_{}_{}.pdf", page_size, orientation))
+    .set_title(format!("Export as PDF ({} {})", page_size.to_uppercase(), orientation));
+  let path = save_path(&app, output_path.as_deref(), dialog, "Export").await?;
+
+  if let Some(PdfRenderArgs { doc, note_ids, options, markings, scrub_metadata, protection }) = render {
+    let doc = match &note_ids {
+      Some(ids) => export_selection(&doc, ids)?,
+      None => doc,
+    };
+    let markings = markings.map(|m| localized_markings(&app, m)).transpose()?;
+    let progress = progress::Progress::new(on_progress);
+    let (content, progress) = tauri::async_runtime::spawn_blocking(move || -> Result<_, String> {
+      let content = pdf::generate(&doc, &page_size, &orientation, &options, &progress)?;
+      let content = match &markings {
+        Some(markings) => markings::apply_to_pdf(&content, markings)?,
+        None => content,
+      };
+      let content = match scrub_metadata.unwrap_or(false) {
+        true => pdf::scrub_metadata(&content)?,
+        false => content,
+      };
+      let content = match &protection {
+        Some(protection) => pdf::protect(&content, protection)?,
+        None => content,
+      };
+      Ok((content, progress))
+    }).await.map_err(|e| format!("PDF export failed: {}", e))??;
+
+    let result = progress.write_file(&path, &content, 95.0, 100.0)
+      .map_err(|e| format!("Failed to write PDF file '{}': {}", path.display(), e));
+    record_audit(&app, "export-pdf", &path, || Some(audit::hash_bytes(&content)), &result);
+    result?;
+    progress.done();
+  }
+
+  Ok(path.to_string_lossy().to_string())
+}
+
+// Print the board through the system instead of the webview; see print.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn print_document(
+  app: tauri::AppHandle,
+  args: PrintArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<print::PrintJob, AppError> {
+  print::validate(args.printer.as_deref(), args.copies)?;
+  let doc = match &args.note_ids {
+    Some(ids) => export_selection(&args.doc, ids)?,
+    None => args.doc,
+  };
+  // Locked notes have nothing to print
+  let doc = confidential::without_locked(&doc);
+  let (page_size, orientation, options) = (args.page_size.clone(), args.orientation, args.options);
+  let progress = progress::Progress::new(on_progress);
+  let (content, progress) = tauri::async_runtime::spawn_blocking(move || -> Result<_, String> {
+    Ok((pdf::generate(&doc, &page_size, &orientation, &options, &progress)?, progress))
+  }).await.map_err(|e| format!("Printing failed: {}", e))??;
+  let pages = lopdf::Document::load_mem(&content).map(|pdf| pdf.get_pages().len()).unwrap_or(0);
+
+  let secure_cleanup = app.try_state::<Mutex<AppState>>()
+    .and_then(|state| state.lock().ok().map(|app_state| app_state.settings.secure_cleanup))
+    .unwrap_or(false);
+  let path = share::prepare_share_path("idea_map_print.pdf", secure_cleanup)?;
+  progress.write_file(&path, &content, 90.0, 95.0)
+    .map_err(|e| format!("Failed to write print file '{}': {}", path.display(), e))?;
+
+  let opened = args.show_dialog.unwrap_or(false);
+  let result = match opened {
+    true => share::open_with_default_app(&path).map(|_| None),
+    false => print::send(&path, &args.page_size, args.printer.as_deref(), args.copies),
+  };
+  record_audit(&app, "print", &path, || Some(audit::hash_bytes(&content)), &result);
+  let job_id = result?;
+  progress.done();
+  Ok(print::PrintJob { path: path.to_string_lossy().to_string(), pages, printer: args.printer, job_id, opened })
+}
+
+// Bookmark tree for PDF exports, following the hierarchical ordering: root
+// notes at the top level, connected children nested below them
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_pdf_bookmarks(doc: model::BoardDocument) -> Result<Vec<outline::Bookmark>, AppError> {
+  let ordered = order_notes_hierarchically(&doc);
+  Ok(outline::bookmarks(&doc, &ordered))
+}
+
+// Restyle a document for export on a different background (e.g. a dark board
+// printed on white paper); the PNG/PDF exporters render the returned copy
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn apply_export_theme(args: ExportThemeArgs) -> Result<model::BoardDocument, AppError> {
+  Ok(theme::apply_export_theme(&args.doc, &args.export_theme, args.background.as_deref())?)
+}
+
+// Page grid for a poster-mode PDF export
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn plan_poster_export(args: PosterArgs) -> Result<poster::PosterPlan, AppError> {
+  Ok(poster::plan(&args.doc, &args.options)?)
+}
+
+// Slides for a presentation export; the frontend renders each slide's region
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn plan_presentation(args: PresentationPlanArgs) -> Result<Vec<presentation::Slide>, AppError> {
+  let doc = layers::filter_layers(&args.doc, &layers::visible_layers(&args.doc));
+  Ok(presentation::plan(&doc, args.path.as_deref())?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_presentation(
+  app: tauri::AppHandle,
+  args: PresentationExportArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let progress = progress::Progress::new(on_progress);
+  progress.phase("building", 0.0);
+  let doc = layers::filter_layers(&args.doc, &layers::visible_layers(&args.doc));
+  let slides = presentation::plan(&doc, args.path.as_deref())?;
+  let title = args.title.clone().unwrap_or_else(|| "Idea Map".to_string());
+
+  let content = match args.format.as_str() {
+    "pptx" => presentation::to_pptx(&slides, &args.images)?,
+    "html" => presentation::to_reveal_html(&title, &slides, &args.images)?.into_bytes(),
+    other => return Err(format!("Unsupported presentation format '{}'. Must be one of: pptx, html", other).into()),
+  };
+  let (filter_name, extension) = match args.format.as_str() {
+    "pptx" => ("PowerPoint Presentations", "pptx"),
+    _ => ("HTML Slide Decks", "html"),
+  };
+
+  let dialog = app.dialog()
+    .file()
+    .add_filter(filter_name, &[extension])
+    .set_file_name(format!("{}.{}", title, extension))
+    .set_title(format!("Export Presentation ({} slides)", slides.len()));
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let result = progress.write_file(&path, &content, 80.0, 100.0)
+    .map_err(|e| format!("Failed to write presentation '{}': {}", path.display(), e));
+  record_audit(&app, &format!("export-{}", extension), &path, || Some(audit::hash_bytes(&content)), &result);
+  result?;
+  progress.done();
+
+  Ok(path.to_string_lossy().to_string())
+}
+
+// Camera path for an animated walkthrough; the frontend renders each frame
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn plan_walkthrough(args: WalkthroughArgs) -> Result<walkthrough::WalkthroughPlan, AppError> {
+  let doc = layers::filter_layers(&args.doc, &layers::visible_layers(&args.doc));
+  Ok(walkthrough::plan(&doc, &args.options)?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_walkthrough(
+  app: tauri::AppHandle,
+  args: WalkthroughExportArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
+
+  let progress = progress::Progress::new(on_progress);
+  let doc = layers::filter_layers(&args.doc, &layers::visible_layers(&args.doc));
+  let plan = walkthrough::plan(&doc, &args.options)?;
+
+  let content = match args.format.as_str() {
+    "gif" => {
+      // Encoding is the slow part, and the part that can be cancelled
+      begin_task(&app, "export-walkthrough", &progress);
+      progress.phase("encoding", 0.0);
+      let frame_count = plan.frames.len() as u64;
+      let mut on_frame = |encoded: usize| {
+        progress.step("encoding", 0.0, 80.0, encoded as u64, frame_count);
+        progress.check_cancelled()
+      };
+      let encoded = walkthrough::encode_gif(&plan, args.frames.as_deref().unwrap_or_default(), &mut on_frame);
+      end_task(&app, &progress, &encoded);
+      encoded?
+    },
+    "webm" => match args.video {
+      Some(video) if walkthrough::is_webm(&video) => video,
+      Some(_) => return Err("The recorded walkthrough is not a WebM video".to_string().into()),
+      None => return Err("A WebM walkthrough needs the recorded video".to_string().into()),
+    },
+    other => return Err(format!("Unsupported walkthrough format '{}'. Must be one of: gif, webm", other).into()),
+  };
+  let (filter_name, extension) = match args.format.as_str() {
+    "gif" => ("GIF Animations", "gif"),
+    _ => ("WebM Videos", "webm"),
+  };
+
+  let dialog = app.dialog()
+    .file()
+    .add_filter(filter_name, &[extension])
+    .set_file_name(format!("walkthrough.{}", extension))
+    .set_title(format!("Export Walkthrough ({} notes)", args.options.note_ids.len()));
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let result = progress.write_file(&path, &content, 80.0, 100.0)
+    .map_err(|e| format!("Failed to write walkthrough '{}': {}", path.display(), e));
+  record_audit(&app, &format!("export-{}", extension), &path, || Some(audit::hash_bytes(&content)), &result);
+  result?;
+  progress.done();
+
+  Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_pdf_to_file(
+  app: tauri::AppHandle,
+  file_path: String,
+  pdf_data: Vec<u8>,
+  markings: Option<markings::ExportMarkings>,
+  scrub_metadata: Option<bool>,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<(), AppError> {
+  let path = check_write_path(&app, Path::new(&file_path))?;
+  let markings = markings.map(|m| localized_markings(&app, m)).transpose()?;
+  let progress = progress::Progress::new(on_progress);
+  let pdf_data = match &markings {
+    Some(markings) => {
+      progress.phase("marking", 0.0);
+      markings::apply_to_pdf(&pdf_data, markings)?
+    },
+    None => pdf_data,
+  };
+  // The webview's PDF writer records its name and the time in every file
+  let pdf_data = match scrub_metadata.unwrap_or(false) {
+    true => pdf::scrub_metadata(&pdf_data)?,
+    false => pdf_data,
+  };
+
+  let result = progress.write_file(&path, &pdf_data, 50.0, 100.0)
+    .map_err(|e| format!("Failed to write PDF file '{}': {}", file_path, e));
+  record_audit(&app, "export-pdf", &path, || Some(audit::hash_bytes(&pdf_data)), &result);
+  if result.is_ok() {
+    progress.done();
+  }
+
+  Ok(result?)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_png_to_file(
+  app: tauri::AppHandle,
+  file_path: String,
+  png_data: Vec<u8>,
+  markings: Option<markings::ExportMarkings>,
+) -> Result<(), AppError> {
+  use std::fs;
+
+  let path = check_write_path(&app, Path::new(&file_path))?;
+  let markings = markings.map(|m| localized_markings(&app, m)).transpose()?;
+  let png_data = match &markings {
+    Some(markings) => markings::apply_to_png(&png_data, markings)?,
+    None => png_data,
+  };
+
+  let result = fs::write(&path, &png_data)
+    .map_err(|e| format!("Failed to write PNG file '{}': {}", file_path, e));
+  record_audit(&app, "export-png", &path, || Some(audit::hash_bytes(&png_data)), &result);
+
+  Ok(result?)
+}
+
+// Build a fim:// link to the current document, optionally pointing at a note
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_note_link(app: tauri::AppHandle, window: tauri::Window, note_id: Option<String>) -> Result<String, AppError> {
+  let path = current_document_path(&app, window.label()).ok_or("Save the document before creating a link to it")?;
+  Ok(deep_link::build_deep_link(Path::new(&path), note_id.as_deref())?)
+}
+
+// QR code for a link (usually from get_note_link) as PNG bytes; see qr.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn generate_qr(data: String, module_size: Option<u32>) -> Result<Vec<u8>, AppError> {
+  Ok(qr::to_png(&data, module_size)?)
+}
+
+// Returns the board thumbnail stored in a .fim file as base64-encoded PNG
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_document_preview(file_path: String) -> Result<String, AppError> {
+  use base64::Engine;
+
+  let png = preview::extract_preview(Path::new(&file_path))?;
+  Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+// The rendered thumbnail stored in a .fim file (the schematic preview for
+// boards saved before there were thumbnails) as base64-encoded PNG
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_document_thumbnail(path: String) -> Result<String, AppError> {
+  use base64::Engine;
+
+  let png = tauri::async_runtime::spawn_blocking(move || preview::extract_thumbnail(Path::new(&path)))
+    .await
+    .map_err(|e| format!("Reading thumbnail failed: {}", e))??;
+  Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+// Layering commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn bring_to_front(app: tauri::AppHandle, window: tauri::Window, args: ReorderArgs) -> Result<model::BoardDocument, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  layers::bring_to_front(&mut doc, &args.ids)?;
+  Ok(doc)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn send_to_back(app: tauri::AppHandle, window: tauri::Window, args: ReorderArgs) -> Result<model::BoardDocument, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  layers::send_to_back(&mut doc, &args.ids)?;
+  Ok(doc)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn move_to_layer(app: tauri::AppHandle, window: tauri::Window, args: MoveToLayerArgs) -> Result<EditResult, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut document = args.doc;
+  let skipped_locked = layers::move_to_layer(&mut document, &args.ids, args.layer_id.as_deref())?;
+  Ok(EditResult { document, skipped_locked })
+}
+
+// Group commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn create_group(app: tauri::AppHandle, window: tauri::Window, args: CreateGroupArgs) -> Result<GroupCreated, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  let group_id = groups::create_group(&mut doc, &args.note_ids, &args.shape_ids, args.label)?;
+  Ok(GroupCreated { document: doc, group_id })
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn dissolve_group(app: tauri::AppHandle, window: tauri::Window, args: GroupArgs) -> Result<model::BoardDocument, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  groups::dissolve_group(&mut doc, &args.group_id)?;
+  Ok(doc)
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn move_group(app: tauri::AppHandle, window: tauri::Window, args: MoveGroupArgs) -> Result<EditResult, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  let skipped_locked = groups::move_group(&mut doc, &args.group_id, args.dx, args.dy)?;
+  Ok(EditResult { document: doc, skipped_locked })
 }
 
-
-#[derive(Debug, Default)]
-struct AppState {
-  recent_files: VecDeque<String>,
-  last_save_path: Option<String>,
-  current_document_path: Option<String>,
-  last_autosave_time: Option<std::time::SystemTime>,
-  is_dirty: bool,
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn delete_items(app: tauri::AppHandle, window: tauri::Window, args: DeleteItemsArgs) -> Result<EditResult, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  let skipped_locked = editing::delete_items(&mut doc, &args.ids);
+  Ok(EditResult { document: doc, skipped_locked })
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-struct AutosaveInfo {
-  original_path: String,
-  recovery_path: String,
-  timestamp: chrono::DateTime<chrono::Utc>,
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn duplicate_notes(app: tauri::AppHandle, window: tauri::Window, args: DuplicateNotesArgs) -> Result<editing::Duplication, AppError> {
+  ensure_writable(&app, window.label())?;
+  let offset = args.offset.unwrap_or(model::Point { x: 20.0, y: 20.0 });
+  Ok(editing::duplicate_notes(&args.doc, &args.note_ids, args.include_descendants.unwrap_or(false), &offset)?)
 }
 
-const MAX_RECENT_FILES: usize = 10;
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn text_stats(doc: model::BoardDocument) -> Result<text_stats::TextStats, AppError> {
+  Ok(text_stats::text_stats(&doc))
+}
 
-// Helper functions for recovery file management
-fn get_recovery_path(original_path: &std::path::Path) -> PathBuf {
-  let mut recovery_path = original_path.to_owned();
-  recovery_path.set_extension("fim.recovery");
-  recovery_path
+// Board totals: words, connections, faded notes, extent and media; see document_stats.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_document_stats(doc: model::BoardDocument) -> Result<document_stats::DocumentStats, AppError> {
+  Ok(document_stats::document_stats(&doc))
 }
 
-fn save_as_recovery(doc: &model::BoardDocument, original_path: &std::path::Path) -> Result<AutosaveInfo, String> {
-  let recovery_path = get_recovery_path(original_path);
+// Candidate board titles, best first; see title.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn suggest_title(doc: model::BoardDocument) -> Result<Vec<title::TitleSuggestion>, AppError> {
+  Ok(title::suggest_titles(&doc))
+}
 
-  // Create autosave info
-  let autosave_info = AutosaveInfo {
-    original_path: original_path.to_string_lossy().to_string(),
-    recovery_path: recovery_path.to_string_lossy().to_string(),
-    timestamp: chrono::Utc::now(),
+// Title, authorship, when the board and its notes were made and last changed,
+// including what the last save stamped that the frontend's copy lacks
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_document_info(app: tauri::AppHandle, window: tauri::Window, mut doc: model::BoardDocument) -> Result<metadata::DocumentSummary, AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let path = {
+    let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+    let document = app_state.open_documents.window(window.label());
+    if let Some(history) = document.and_then(|d| d.note_history.as_ref()) {
+      metadata::restore(&mut doc, history);
+    }
+    document.and_then(|d| d.path.clone())
   };
+  Ok(metadata::summary(&doc, path))
+}
 
-  // Create metadata file with autosave info
-  let metadata_path = recovery_path.with_extension("fim.recovery.meta");
-  let metadata_json = serde_json::to_string_pretty(&autosave_info)
-    .map_err(|e| format!("Failed to serialize recovery metadata: {}", e))?;
-
-  std::fs::write(&metadata_path, metadata_json)
-    .map_err(|e| format!("Failed to write recovery metadata: {}", e))?;
-
-  // Save the actual document to recovery file
-  save_as_fim(doc, &recovery_path)?;
+// IDs of starred notes, in document order
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_starred(doc: model::BoardDocument) -> Result<Vec<model::ID>, AppError> {
+  Ok(doc.notes.iter().filter(|n| n.is_starred()).map(|n| n.id.clone()).collect())
+}
 
-  Ok(autosave_info)
+// Language commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn detect_languages(doc: model::BoardDocument) -> Result<Vec<language::NoteLanguage>, AppError> {
+  Ok(language::detect_languages(&doc))
 }
 
-fn check_for_recovery_files() -> Result<Vec<AutosaveInfo>, String> {
-  use std::fs;
-  let mut recovery_files = Vec::new();
-
-  // Common directories where recovery files might be found
-  let search_paths = vec![
-    std::env::temp_dir(),
-    std::env::current_dir().unwrap_or_else(|_| std::env::temp_dir()),
-    // Add user's home directory if available
-    dirs::home_dir().unwrap_or_else(|| std::env::temp_dir()),
-    // Add user's documents directory if available
-    dirs::document_dir().unwrap_or_else(|| std::env::temp_dir()),
-  ];
-
-  for search_path in search_paths {
-    if let Ok(entries) = fs::read_dir(&search_path) {
-      for entry in entries.flatten() {
-        let path = entry.path();
-        if let Some(file_name) = path.file_name() {
-          if let Some(name_str) = file_name.to_str() {
-            if name_str.ends_with(".fim.recovery") {
-              // Try to extract metadata from the recovery file
-              if let Ok(metadata) = fs::metadata(&path) {
-                if let Ok(modified) = metadata.modified() {
-                  // Determine original filename by removing .recovery extension
-                  let original_filename = name_str.replace(".recovery", "");
-                  
-                  recovery_files.push(AutosaveInfo {
-                    recovery_path: path.to_string_lossy().to_string(),
-                    original_path: format!("{}/{}", search_path.display(), original_filename),
-                    timestamp: chrono::DateTime::from_timestamp(
-                      modified.duration_since(std::time::UNIX_EPOCH)
-                        .unwrap_or(std::time::Duration::from_secs(0))
-                        .as_secs() as i64, 
-                      0
-                    ).unwrap_or(chrono::Utc::now()),
-                  });
-                }
-              }
-            }
-          }
-        }
-      }
-    }
-  }
+// Translated copies of notes through the provider configured in settings
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn translate_notes(app: tauri::AppHandle, args: TranslateNotesArgs) -> Result<language::Translation, AppError> {
+  let translation_settings = match app.try_state::<Mutex<AppState>>() {
+    Some(state) => state.lock()
+      .map_err(|e| format!("Failed to access application state: {}", e))?
+      .settings.translation.clone(),
+    None => settings::load_settings().translation,
+  };
+  let provider = language::provider(&translation_settings)?;
+  // Commands and HTTP requests block; keep them off the async workers
+  let translation = tauri::async_runtime::spawn_blocking(move || {
+    language::translate_notes(&args.doc, &args.note_ids, &args.target_lang, provider.as_ref())
+  })
+    .await
+    .map_err(|e| format!("Translation failed: {}", e))??;
+  Ok(translation)
+}
 
-  // Sort by timestamp (newest first)
-  recovery_files.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-  
-  Ok(recovery_files)
+// A draft summary of the selected notes through the provider configured in
+// settings; see summarize.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn summarize_notes(app: tauri::AppHandle, args: SummarizeNotesArgs) -> Result<summarize::SummaryDraft, AppError> {
+  let summarization_settings = match app.try_state::<Mutex<AppState>>() {
+    Some(state) => state.lock()
+      .map_err(|e| format!("Failed to access application state: {}", e))?
+      .settings.summarization.clone(),
+    None => settings::load_settings().summarization,
+  };
+  let provider = summarize::provider(&summarization_settings)?;
+  // Commands and HTTP requests block; keep them off the async workers
+  let draft = tauri::async_runtime::spawn_blocking(move || {
+    summarize::summarize_notes(&args.doc, &args.note_ids, summarization_settings.instructions.as_deref(), provider.as_ref())
+  })
+    .await
+    .map_err(|e| format!("Summarization failed: {}", e))??;
+  Ok(draft)
 }
 
-// Helper functions for .fim zip container
-fn save_as_fim(doc: &model::BoardDocument, path: &std::path::Path) -> Result<(), String> {
-  use std::io::Write;
-  use zip::{ZipWriter, write::FileOptions};
+// Confidential notes; see confidential.rs. Argon2 takes a moment, so key
+// derivation runs off the async workers.
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn set_notes_passphrase(app: tauri::AppHandle, window: tauri::Window, args: NotesPassphraseArgs) -> Result<model::BoardDocument, AppError> {
+  ensure_writable(&app, window.label())?;
+  let doc = tauri::async_runtime::spawn_blocking(move || {
+    confidential::set_passphrase(&args.doc, &args.passphrase, args.remember.unwrap_or(false))
+  })
+    .await
+    .map_err(|e| format!("Setting the passphrase failed: {}", e))??;
+  Ok(doc)
+}
 
-  let file = std::fs::File::create(path)
-    .map_err(|e| format!("Failed to create file '{}': {}", path.display(), e))?;
+// Check the password of a protected board and keep its key for this session;
+// opening, saving and autosave then work as for any board
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn unlock_document(args: UnlockDocumentArgs) -> Result<(), AppError> {
+  tauri::async_runtime::spawn_blocking(move || encryption::unlock(Path::new(&args.file_path), &args.password))
+    .await
+    .map_err(|e| format!("Unlocking failed: {}", e))??;
+  Ok(())
+}
 
-  let mut zip = ZipWriter::new(file);
-  let options = FileOptions::default()
-    .compression_method(zip::CompressionMethod::Deflated)
-    .unix_permissions(0o755);
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn unlock_confidential_notes(args: UnlockNotesArgs) -> Result<model::BoardDocument, AppError> {
+  let doc = tauri::async_runtime::spawn_blocking(move || {
+    confidential::unlock(&args.doc, args.passphrase.as_deref(), args.remember.unwrap_or(false))
+  })
+    .await
+    .map_err(|e| format!("Unlocking failed: {}", e))??;
+  Ok(doc)
+}
 
-  // Add board.json
-  let json = serde_json::to_string_pretty(doc)
-    .map_err(|e| format!("Failed to serialize document: {}", e))?;
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn lock_confidential_notes(args: LockNotesArgs) -> Result<model::BoardDocument, AppError> {
+  Ok(confidential::lock(&args.doc, args.forget.unwrap_or(false))?)
+}
 
-  zip.start_file("board.json", options)
-    .map_err(|e| format!("Failed to create board.json in zip: {}", e))?;
-  zip.write_all(json.as_bytes())
-    .map_err(|e| format!("Failed to write board.json: {}", e))?;
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn set_notes_confidential(app: tauri::AppHandle, window: tauri::Window, args: SetConfidentialArgs) -> Result<model::BoardDocument, AppError> {
+  ensure_writable(&app, window.label())?;
+  Ok(confidential::set_confidential(&args.doc, &args.note_ids, args.confidential)?)
+}
 
-  // Create media directory (empty for now, but will be used for future media files)
-  zip.add_directory("media/", options)
-    .map_err(|e| format!("Failed to create media directory: {}", e))?;
+// Store the API token for a publishing target in the keychain; an empty
+// token removes it
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn set_publish_token(target: String, token: String) -> Result<(), AppError> {
+  let target = publish::Target::parse(&target)?;
+  Ok(publish::set_token(target, &token)?)
+}
 
-  zip.finish()
-    .map_err(|e| format!("Failed to finalize zip file: {}", e))?;
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn has_publish_token(target: String) -> Result<bool, AppError> {
+  Ok(publish::has_token(publish::Target::parse(&target)?))
+}
 
-  Ok(())
+// Publish the board to Confluence or Notion; see publish.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn publish_board(app: tauri::AppHandle, args: PublishBoardArgs) -> Result<publish::PublishResult, AppError> {
+  use base64::Engine;
+
+  let target = publish::Target::parse(&args.target)?;
+  let config = match app.try_state::<Mutex<AppState>>() {
+    Some(state) => state.lock()
+      .map_err(|e| format!("Failed to access application state: {}", e))?
+      .settings.publish.clone(),
+    None => settings::load_settings().publish,
+  };
+  let image = match &args.image {
+    Some(encoded) => base64::engine::general_purpose::STANDARD.decode(encoded)
+      .map_err(|e| format!("Invalid board image: {}", e))?,
+    None => preview::render_preview(&args.doc)?,
+  };
+  let title = title::suggest_titles(&args.doc).into_iter().next()
+    .map(|suggestion| suggestion.title)
+    .unwrap_or_else(|| "Untitled board".to_string());
+  let board = args.path.clone();
+  let existing = board.as_deref()
+    .and_then(|board| publish::PublishedPages::load().find(target, board).map(|page| page.page_id.clone()));
+
+  // The keychain and HTTP requests block; keep them off the async workers
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    let doc = confidential::without_locked(&args.doc);
+    let outline = outline::build_outline(&doc, &doc.notes);
+    let publication = publish::Publication {
+      title: &title,
+      outline: &outline,
+      image: &image,
+      space: args.space.as_deref(),
+      parent: args.page.as_deref(),
+    };
+    publish::publish(target, &config, &publication, existing.as_deref())
+  })
+    .await
+    .map_err(|e| format!("Publishing failed: {}", e))?;
+
+  if let Some(board) = &board {
+    record_audit(&app, "publish", Path::new(board), || None, &result);
+    if let Ok(published) = &result {
+      let mut pages = publish::PublishedPages::load();
+      pages.record(target, board, published);
+      if let Err(e) = pages.save() {
+        log::warn!("{}", e);
+      }
+    }
+  }
+  Ok(result?)
 }
 
-fn load_from_fim(path: &std::path::Path) -> Result<model::BoardDocument, String> {
-  use std::io::Read;
-  use zip::ZipArchive;
+// Trash commands; delete_items moves notes and connections to the trash
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn restore_from_trash(app: tauri::AppHandle, window: tauri::Window, args: RestoreFromTrashArgs) -> Result<trash::RestoreResult, AppError> {
+  ensure_writable(&app, window.label())?;
+  Ok(trash::restore(args.doc, &args.ids))
+}
 
-  let file = std::fs::File::open(path)
-    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn empty_trash(app: tauri::AppHandle, window: tauri::Window, args: EmptyTrashArgs) -> Result<EmptyTrashResult, AppError> {
+  ensure_writable(&app, window.label())?;
+  let mut doc = args.doc;
+  let removed = trash::empty(&mut doc, args.older_than);
+  Ok(EmptyTrashResult { document: doc, removed })
+}
 
-  let mut archive = ZipArchive::new(file)
-    .map_err(|e| format!("Failed to read zip file '{}': {}", path.display(), e))?;
+// Degrees, centrality and components of the connection graph; see graph.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn analyze_graph(app: tauri::AppHandle, args: AnalyzeGraphArgs) -> Result<GraphAnalysis, AppError> {
+  use tauri_plugin_dialog::DialogExt;
 
-  // Read board.json from the zip
-  let board_json_file = archive.by_name("board.json")
-    .map_err(|e| format!("Failed to find board.json in zip: {}", e))?;
+  let doc = args.doc;
+  let report = tauri::async_runtime::spawn_blocking(move || graph::analyze(&doc))
+    .await
+    .map_err(|e| format!("Graph analysis failed: {}", e))?;
+  if !args.export_csv.unwrap_or(false) {
+    return Ok(GraphAnalysis { report, csv_path: None });
+  }
 
-  let mut json_content = String::new();
-  board_json_file.take(100_000_000).read_to_string(&mut json_content) // Limit to 100MB
-    .map_err(|e| format!("Failed to read board.json content: {}", e))?;
+  let dialog = app.dialog()
+    .file()
+    .add_filter("CSV Files", &["csv"])
+    .set_file_name("graph-metrics.csv")
+    .set_title("Export Graph Metrics");
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let content = graph::to_csv(&report);
+  let result = std::fs::write(&path, &content)
+    .map_err(|e| format!("Failed to write export file '{}': {}", path.display(), e));
+  record_audit(&app, "export-csv", &path, || Some(audit::hash_bytes(content.as_bytes())), &result);
+  result?;
+  Ok(GraphAnalysis { report, csv_path: Some(path.to_string_lossy().to_string()) })
+}
 
-  let doc: model::BoardDocument = serde_json::from_str(&json_content)
-    .map_err(|e| format!("Invalid JSON format in board.json: {}", e))?;
+// Components, degrees, centrality, orphans and cycles in one go; see graph.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn analyze_document(doc: model::BoardDocument) -> Result<graph::DocumentAnalysis, AppError> {
+  let analysis = tauri::async_runtime::spawn_blocking(move || graph::analyze_document(&doc))
+    .await
+    .map_err(|e| format!("Graph analysis failed: {}", e))?;
+  Ok(analysis)
+}
 
-  Ok(doc)
+// Notes created or edited since a moment, newest first; see activity.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn recently_edited(args: RecentlyEditedArgs) -> Result<Vec<activity::RecentNote>, AppError> {
+  Ok(activity::recently_edited(&args.doc, args.since))
 }
 
+// Separate the branches untouched since `cutoff` from the rest of the board
 #[tauri::command]
-async fn open_document(app: tauri::AppHandle) -> Result<model::BoardDocument, String> {
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn split_by_activity(app: tauri::AppHandle, args: SplitByActivityArgs) -> Result<ActivitySplitResult, AppError> {
   use tauri_plugin_dialog::DialogExt;
-  use std::fs;
 
-  let file_path = app.dialog()
+  let split = activity::split_by_activity(&args.doc, args.cutoff);
+  if !args.save_archive.unwrap_or(false) || split.archived_note_ids.is_empty() {
+    return Ok(ActivitySplitResult { split, archive_path: None });
+  }
+
+  let dialog = app.dialog()
     .file()
     .add_filter("FIM Files", &["fim"])
-    .add_filter("JSON", &["json"])
-    .add_filter("All Supported", &["fim", "json"])
-    .set_title("Open Board Document")
-    .blocking_pick_file();
+    .set_file_name("archive.fim")
+    .set_title("Save Archived Branches");
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let result = write_document(&split.archived, &path, &[], write_options(&app, &path), &progress::Progress::none());
+  record_audit(&app, "archive-branches", &path, || audit::hash_document(&split.archived), &result);
+  result?;
+  Ok(ActivitySplitResult { split, archive_path: Some(path.to_string_lossy().to_string()) })
+}
 
-  let path = match file_path {
-    Some(p) => match p.as_path() {
-      Some(path) => path.to_path_buf(),
-      None => return Err("Invalid file path selected".into()),
-    },
-    None => return Err("Operation cancelled by user".into()),
-  };
+// Connection cycles, which hierarchical exports break arbitrarily
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn find_cycles(doc: model::BoardDocument) -> Result<Vec<graph::Cycle>, AppError> {
+  Ok(graph::find_cycles(&doc))
+}
 
-  // Check file extension to determine format
-  let extension = path.extension()
-    .and_then(|ext| ext.to_str())
-    .unwrap_or("");
+// Notes without connections, stack or shape
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn find_orphans(doc: model::BoardDocument) -> Result<Vec<model::ID>, AppError> {
+  Ok(graph::find_orphans(&doc))
+}
 
-  let doc = match extension {
-    "fim" => load_from_fim(&path)?,
-    "json" => {
-      let data = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+// Shortest path between two notes first, then alternatives
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn find_path(args: FindPathArgs) -> Result<Vec<graph::NotePath>, AppError> {
+  Ok(graph::find_paths(
+    &args.doc,
+    &args.from_note_id,
+    &args.to_note_id,
+    args.directed.unwrap_or(false),
+    args.alternatives.unwrap_or(2),
+  )?)
+}
 
-      let parsed_doc: model::BoardDocument = serde_json::from_str(&data)
-        .map_err(|e| format!("Invalid JSON format: {}", e))?;
-      parsed_doc
-    },
-    _ => return Err(format!("Unsupported file format: '{}'. Supported formats: .fim, .json", extension)),
-  };
-  
-  // Schema validation
-  if doc.schema_version == 0 { 
-    return Err("Invalid or missing schema version".into()); 
-  }
-  
-  if doc.schema_version > 1 {
-    return Err(format!("Unsupported schema version {}. Please update the application.", doc.schema_version));
-  }
-  
-  // Add to recent files
-  let path_str = path.to_string_lossy().to_string();
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      // Remove if already exists to avoid duplicates
-      app_state.recent_files.retain(|p| p != &path_str);
-      // Add to front
-      app_state.recent_files.push_front(path_str);
-      // Limit size
-      if app_state.recent_files.len() > MAX_RECENT_FILES {
-        app_state.recent_files.pop_back();
-      }
-    }
-  }
-  
-  Ok(doc)
+// Proposed stacks for aligned, closely spaced notes; see stacks.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn suggest_stacks(args: SuggestStacksArgs) -> Result<Vec<stacks::StackSuggestion>, AppError> {
+  Ok(stacks::suggest_stacks(&args.doc, &args.options))
 }
 
+// New note frames from an automatic layout; see layout.rs
 #[tauri::command]
-async fn open_specific_document(app: tauri::AppHandle, file_path: String) -> Result<model::BoardDocument, String> {
-  use std::path::Path;
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn layout_document(app: tauri::AppHandle, window: tauri::Window, args: LayoutDocumentArgs) -> Result<layout::LayoutResult, AppError> {
+  ensure_writable(&app, window.label())?;
+  let result = tauri::async_runtime::spawn_blocking(move || layout::layout(&args.doc, &args.options))
+    .await
+    .map_err(|e| format!("Layout failed: {}", e))??;
+  Ok(result)
+}
 
-  let path = Path::new(&file_path);
+// Which notes sit inside each shape
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn compute_shape_membership(doc: model::BoardDocument) -> Result<Vec<shapes::ShapeMembership>, AppError> {
+  Ok(shapes::membership(&doc))
+}
 
-  // Check file extension to determine format
-  let extension = path.extension()
-    .and_then(|ext| ext.to_str())
-    .unwrap_or("");
+// Frames after moving a shape, including the notes a magnetic shape carries
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn move_shape(app: tauri::AppHandle, window: tauri::Window, args: MoveShapeArgs) -> Result<shapes::ShapeMove, AppError> {
+  ensure_writable(&app, window.label())?;
+  Ok(shapes::move_shape(&args.doc, &args.shape_id, args.dx, args.dy)?)
+}
 
-  let doc = match extension {
-    "fim" => load_from_fim(&path)?,
-    "json" => {
-      use std::fs;
-      let data = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+// Index the board's notes for snapping; call when a drag starts
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn prepare_snapping(app: tauri::AppHandle, window: tauri::Window, doc: model::BoardDocument) -> Result<(), AppError> {
+  let index = snap::SnapIndex::build(&doc);
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let mut app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  app_state.open_documents.window_mut(window.label()).snap_index = Some(index);
+  Ok(())
+}
 
-      let parsed_doc: model::BoardDocument = serde_json::from_str(&data)
-        .map_err(|e| format!("Invalid JSON format: {}", e))?;
-      parsed_doc
-    },
-    _ => return Err(format!("Unsupported file format: '{}'. Supported formats: .fim, .json", extension)),
-  };
+// Snap positions and guide lines for a dragged frame; see snap.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn snap_frame(app: tauri::AppHandle, window: tauri::Window, args: snap::SnapRequest) -> Result<snap::SnapResult, AppError> {
+  let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+  let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+  let index = app_state.open_documents.window(window.label())
+    .and_then(|document| document.snap_index.as_ref())
+    .ok_or("Snapping isn't prepared; call prepare_snapping first")?;
+  Ok(index.snap(&args))
+}
 
-  // Schema validation
-  if doc.schema_version == 0 {
-    return Err("Invalid or missing schema version".into());
-  }
+// Label commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn filter_notes_by_label(args: LabelFilterArgs) -> Result<Vec<model::ID>, AppError> {
+  Ok(labels::filter_note_ids(&args.doc, &args.filter)?)
+}
 
-  if doc.schema_version > 1 {
-    return Err(format!("Unsupported schema version {}. Please update the application.", doc.schema_version));
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn count_labels(doc: model::BoardDocument) -> Result<Vec<labels::LabelCount>, AppError> {
+  Ok(labels::count_labels(&doc))
+}
+
+// Tag commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_all_tags(doc: model::BoardDocument) -> Result<Vec<tags::TagCount>, AppError> {
+  Ok(tags::all_tags(&doc))
+}
+
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn filter_notes_by_tag(args: TagFilterArgs) -> Result<Vec<model::ID>, AppError> {
+  Ok(tags::filter_note_ids(&args.doc, &args.filter)?)
+}
+
+// Full-text search of the board's notes, optionally across recent files too;
+// see search.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn search_notes(app: tauri::AppHandle, window: tauri::Window, args: SearchNotesArgs) -> Result<search::SearchResults, AppError> {
+  let matcher = search::Matcher::new(&args.query, &args.options)?;
+  let mut recent = Vec::new();
+  if args.include_recent.unwrap_or(false) {
+    let state = app.try_state::<Mutex<AppState>>().ok_or("Application state is unavailable")?;
+    let app_state = state.lock().map_err(|e| format!("Failed to access application state: {}", e))?;
+    // The open board is searched as it is now, not as last saved
+    let current = app_state.open_documents.window(window.label())
+      .and_then(|document| document.path.as_deref())
+      .map(recent_files::canonical_path);
+    recent = app_state.recent_files.entries().into_iter()
+      .filter(|entry| current.as_deref() != Some(recent_files::canonical_path(&entry.path).as_str()))
+      .collect();
   }
 
-  // Add to recent files
-  let path_str = path.to_string_lossy().to_string();
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      // Remove if already exists to avoid duplicates
-      app_state.recent_files.retain(|p| p != &path_str);
-      // Add to front
-      app_state.recent_files.push_front(path_str);
-      // Limit size
-      if app_state.recent_files.len() > MAX_RECENT_FILES {
-        app_state.recent_files.pop_back();
+  // Loading other boards takes a while; keep it off the async workers
+  let results = tauri::async_runtime::spawn_blocking(move || {
+    let mut results = search::SearchResults { matches: search::search(&args.doc, &matcher), ..Default::default() };
+    for entry in recent {
+      match load_document(Path::new(&entry.path), &progress::Progress::none()) {
+        Ok(doc) => {
+          let matches = search::search(&doc, &matcher);
+          if !matches.is_empty() {
+            results.documents.push(search::DocumentMatches { path: entry.path, display_name: entry.display_name, matches });
+          }
+        },
+        Err(e) => results.skipped.push(format!("{}: {}", entry.path, e)),
       }
     }
-  }
-
-  Ok(doc)
+    results
+  }).await.map_err(|e| format!("Search failed: {}", e))?;
+  Ok(results)
 }
 
+// Diff and merge commands
 #[tauri::command]
-async fn save_document(app: tauri::AppHandle, args: SaveArgs) -> Result<String, String> {
-  use tauri_plugin_dialog::DialogExt;
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn diff_documents(args: DiffDocumentsArgs) -> Result<merge::DocumentDiff, AppError> {
+  let (path_a, path_b) = (PathBuf::from(args.path_a), PathBuf::from(args.path_b));
+  ensure_unlocked(&path_a)?;
+  ensure_unlocked(&path_b)?;
+  let diff = tauri::async_runtime::spawn_blocking(move || {
+    let a = load_document(&path_a, &progress::Progress::none())?;
+    let b = load_document(&path_b, &progress::Progress::none())?;
+    merge::diff(&a, &b)
+  }).await.map_err(|e| format!("Comparing boards failed: {}", e))??;
+  Ok(diff)
+}
 
-  // Validate document before saving
-  if args.doc.schema_version == 0 {
-    return Err("Cannot save document with invalid schema version".into());
+// The merged board is returned, not saved; the frontend shows the conflicts
+// and saves it where the user wants
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn merge_documents(args: MergeDocumentsArgs) -> Result<merge::MergeResult, AppError> {
+  let (path_a, path_b) = (PathBuf::from(args.path_a), PathBuf::from(args.path_b));
+  let base_path = args.base_path.map(PathBuf::from);
+  for path in [Some(&path_a), Some(&path_b), base_path.as_ref()].into_iter().flatten() {
+    ensure_unlocked(path)?;
   }
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    let ours = load_document(&path_a, &progress::Progress::none())?;
+    let theirs = load_document(&path_b, &progress::Progress::none())?;
+    let base = base_path.map(|path| load_document(&path, &progress::Progress::none())).transpose()?;
+    merge::merge(&ours, &theirs, base.as_ref())
+  }).await.map_err(|e| format!("Merging boards failed: {}", e))??;
+  Ok(result)
+}
 
-  let file_path = app.dialog()
-    .file()
-    .add_filter("FIM Files", &["fim"])
-    .add_filter("JSON", &["json"])
-    .add_filter("All Supported", &["fim", "json"])
-    .set_file_name("untitled.fim")
-    .set_title("Save Board Document")
-    .blocking_save_file();
+// Property commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn find_notes_by_property(args: PropertyQueryArgs) -> Result<Vec<model::ID>, AppError> {
+  Ok(properties::filter_note_ids(&args.doc, &args.query))
+}
 
-  let path = match file_path {
-    Some(p) => match p.as_path() {
-      Some(path) => path.to_path_buf(),
-      None => return Err("Invalid save path selected".into()),
-    },
-    None => return Err("Save operation cancelled by user".into()),
-  };
+// Template commands
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn instantiate_template(app: tauri::AppHandle, window: tauri::Window, args: InstantiateTemplateArgs) -> Result<Vec<model::Note>, AppError> {
+  ensure_writable(&app, window.label())?;
+  let position = args.position.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  Ok(templates::instantiate(&args.doc, &args.template_id, &args.values, &position)?)
+}
 
-  // Check file extension to determine format
-  let extension = path.extension()
-    .and_then(|ext| ext.to_str())
-    .unwrap_or("");
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_template_placeholders(doc: model::BoardDocument, template_id: model::ID) -> Result<Vec<String>, AppError> {
+  let template = doc.templates.iter().flatten().find(|t| t.id == template_id)
+    .ok_or_else(|| format!("Template '{}' not found", template_id))?;
+  Ok(templates::placeholders(template))
+}
 
-  match extension {
-    "fim" => save_as_fim(&args.doc, &path)?,
-    "json" => {
-      let json = serde_json::to_string_pretty(&args.doc)
-        .map_err(|e| format!("Failed to serialize document: {}", e))?;
+// Board templates; see board_templates.rs
+fn board_templates_dir() -> Result<PathBuf, String> {
+  board_templates::templates_dir().ok_or_else(|| "Could not determine the app data directory".to_string())
+}
 
-      use std::fs;
-      fs::write(&path, &json)
-        .map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))?;
-    },
-    _ => return Err(format!("Unsupported file format: '{}'. Supported formats: .fim, .json", extension)),
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn save_as_template(args: SaveAsTemplateArgs) -> Result<board_templates::BoardTemplate, AppError> {
+  let dir = board_templates_dir()?;
+  let path = board_templates::template_path(&dir, &args.name)
+    .map_err(|message| AppError::InvalidInput { message })?;
+  if path.exists() && !args.overwrite.unwrap_or(false) {
+    return Err(AppError::Conflict { message: format!("A template named '{}' already exists", args.name.trim()) });
   }
-  
-  // Update state with current document path
-  let path_str = path.to_string_lossy().to_string();
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      app_state.last_save_path = Some(path_str.clone());
-      app_state.current_document_path = Some(path_str.clone());
-      app_state.is_dirty = false;
-      app_state.last_autosave_time = Some(std::time::SystemTime::now());
 
-      // Also add to recent files
-      app_state.recent_files.retain(|p| p != &path_str);
-      app_state.recent_files.push_front(path_str.clone());
-      if app_state.recent_files.len() > MAX_RECENT_FILES {
-        app_state.recent_files.pop_back();
-      }
+  let template = board_templates::prepare(&args.doc, args.keep_content.unwrap_or(false));
+  let saved = tauri::async_runtime::spawn_blocking(move || {
+    std::fs::create_dir_all(&dir)
+      .map_err(|e| format!("Failed to create templates folder '{}': {}", dir.display(), e))?;
+    write_document(&template, &path, &[], sync_write::WriteOptions::default(), &progress::Progress::none())?;
+    board_templates::describe_template(&path)
+  })
+    .await
+    .map_err(|e| format!("Saving template failed: {}", e))??;
+  Ok(saved)
+}
 
-      // Clean up recovery file if it exists
-      let recovery_path = get_recovery_path(&path);
-      if recovery_path.exists() {
-        let _ = std::fs::remove_file(&recovery_path);
-      }
-      let metadata_path = recovery_path.with_extension("fim.recovery.meta");
-      if metadata_path.exists() {
-        let _ = std::fs::remove_file(&metadata_path);
-      }
-    }
-  }
-  
-  Ok(path_str)
+// Saved board templates, by name
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn list_templates() -> Result<Vec<board_templates::BoardTemplate>, AppError> {
+  Ok(board_templates::list_templates(&board_templates_dir()?))
 }
 
+// A new, unsaved board set up like the template
 #[tauri::command]
-async fn get_recent_files(app: tauri::AppHandle) -> Result<Vec<String>, String> {
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(app_state) = state.lock() {
-      return Ok(app_state.recent_files.iter().cloned().collect());
-    }
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn create_from_template(name: String) -> Result<model::BoardDocument, AppError> {
+  let path = board_templates::template_path(&board_templates_dir()?, &name)
+    .map_err(|message| AppError::InvalidInput { message })?;
+  if !path.exists() {
+    return Err(AppError::NotFound { message: format!("Template '{}' not found", name.trim()) });
   }
-  Ok(Vec::new())
+  let template = tauri::async_runtime::spawn_blocking(move || load_document(&path, &progress::Progress::none()))
+    .await
+    .map_err(|e| format!("Reading template failed: {}", e))??;
+  Ok(board_templates::instantiate(template))
 }
 
 #[tauri::command]
-async fn clear_recent_files(app: tauri::AppHandle) -> Result<(), String> {
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      app_state.recent_files.clear();
-    }
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn delete_template(name: String) -> Result<(), AppError> {
+  let path = board_templates::template_path(&board_templates_dir()?, &name)
+    .map_err(|message| AppError::InvalidInput { message })?;
+  if !path.exists() {
+    return Err(AppError::NotFound { message: format!("Template '{}' not found", name.trim()) });
   }
+  std::fs::remove_file(&path)
+    .map_err(|e| format!("Failed to delete template '{}': {}", path.display(), e))?;
   Ok(())
 }
 
+// Target note ID → IDs of notes linking to it through `note://` links
 #[tauri::command]
-async fn autosave_document(app: tauri::AppHandle, args: AutosaveArgs) -> Result<AutosaveInfo, String> {
-  use std::path::Path;
-
-  // Validate document before autosaving
-  if args.doc.schema_version == 0 {
-    return Err("Cannot autosave document with invalid schema version".into());
-  }
-
-  let path = Path::new(&args.file_path);
-
-  // Perform autosave
-  let autosave_info = save_as_recovery(&args.doc, path)?;
-
-  // Update state
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      app_state.last_autosave_time = Some(std::time::SystemTime::now());
-      app_state.is_dirty = false;
-    }
-  }
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_backlink_index(doc: model::BoardDocument) -> Result<std::collections::BTreeMap<model::ID, Vec<model::ID>>, AppError> {
+  Ok(links::backlink_index(&doc))
+}
 
-  Ok(autosave_info)
+// Markdown → HTML (for the note renderer) and plain text + spans
+#[derive(serde::Serialize, Debug, Clone)]
+struct RenderedMarkdown {
+  html: String,
+  text: String,
+  spans: Vec<model::TextSpan>,
 }
 
 #[tauri::command]
-async fn set_document_dirty(app: tauri::AppHandle, is_dirty: bool) -> Result<(), String> {
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      app_state.is_dirty = is_dirty;
-    }
-  }
-  Ok(())
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn render_markdown(markdown: String) -> Result<RenderedMarkdown, AppError> {
+  let (text, spans) = markdown::to_spans(&markdown);
+  Ok(RenderedMarkdown { html: markdown::to_html(&markdown), text, spans })
+}
+
+// Structural checks on a document (dangling references, bad registries, ...)
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn validate_document(doc: model::BoardDocument) -> Result<Vec<validation::ValidationIssue>, AppError> {
+  Ok(validation::validate_document(&doc))
 }
 
+// Open/done checklist counts for the board and per shape/stack
 #[tauri::command]
-async fn set_current_document_path(app: tauri::AppHandle, file_path: String) -> Result<(), String> {
-  if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      app_state.current_document_path = Some(file_path);
-      app_state.last_autosave_time = None; // Reset autosave time for new document
-    }
-  }
-  Ok(())
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn get_task_summary(doc: model::BoardDocument) -> Result<checklist::TaskSummary, AppError> {
+  Ok(checklist::task_summary(&doc))
 }
 
+// Export into a temporary file and hand it to the OS share sheet
 #[tauri::command]
-async fn get_autosave_status(app: tauri::AppHandle) -> Result<Option<AutosaveInfo>, String> {
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn share_export(app: tauri::AppHandle, args: ShareExportArgs) -> Result<ShareResult, AppError> {
+  let extension = args.format.as_str();
+  let file_name = format!("idea_map.{}", if extension == "mermaid" { "mmd" } else { extension });
+
+  let mut secure_cleanup = false;
   if let Some(state) = app.try_state::<Mutex<AppState>>() {
     if let Ok(app_state) = state.lock() {
-      if let Some(current_path) = &app_state.current_document_path {
-        let recovery_path = get_recovery_path(Path::new(current_path));
-        let metadata_path = recovery_path.with_extension("fim.recovery.meta");
-
-        if metadata_path.exists() {
-          let metadata_content = std::fs::read_to_string(&metadata_path)
-            .map_err(|e| format!("Failed to read recovery metadata: {}", e))?;
-
-          let autosave_info: AutosaveInfo = serde_json::from_str(&metadata_content)
-            .map_err(|e| format!("Failed to parse recovery metadata: {}", e))?;
-
-          return Ok(Some(autosave_info));
-        }
-      }
+      secure_cleanup = app_state.settings.secure_cleanup;
     }
   }
-  Ok(None)
-}
+  let path = share::prepare_share_path(&file_name, secure_cleanup)?;
+
+  let options = TextExportOptions {
+    ordering: "spatial".to_string(),
+    scrub_metadata: false,
+    include_properties: false,
+    include_stats: false,
+    locale: export_locale(&app, args.locale.as_deref(), args.timezone.as_deref())?,
+  };
+  let result = match extension {
+    // Deleted notes stay with the board, not with copies handed out
+    "fim" | "json" => write_document(&trash::without_trash(&args.doc), &path, &[], sync_write::WriteOptions::default(), &progress::Progress::none()),
+    "txt" | "md" | "rtf" | "opml" | "dot" | "mermaid" | "csv" | "tsv" => {
+      let included_layers = args.layers.clone().unwrap_or_else(|| layers::visible_layers(&args.doc));
+      let doc = layers::filter_layers(&confidential::without_locked(&args.doc), &included_layers);
+      let content = generate_text_export(&doc, extension, &options)?;
+      std::fs::write(&path, content)
+        .map_err(|e| format!("Failed to write share file '{}': {}", path.display(), e))
+    },
+    "png" | "pdf" => {
+      let data = args.data.as_ref()
+        .ok_or_else(|| format!("Rendered {} data is required to share this format", extension.to_uppercase()))?;
+      std::fs::write(&path, data)
+        .map_err(|e| format!("Failed to write share file '{}': {}", path.display(), e))
+    },
+    _ => return Err(format!("Unsupported share format '{}'. Must be one of: fim, json, txt, md, rtf, opml, dot, mermaid, csv, tsv, png, pdf", extension).into()),
+  };
+  record_audit(&app, &format!("share-{}", extension), &path, || audit::hash_document(&args.doc), &result);
+  result?;
 
-#[tauri::command]
-async fn check_recovery_files() -> Result<Vec<AutosaveInfo>, String> {
-  check_for_recovery_files()
+  let presented = share::present_share_sheet(&app, &path)?;
+  Ok(ShareResult { path: path.to_string_lossy().to_string(), presented })
 }
 
+// Write the board as an .eml draft with the outline as the body and the
+// renders attached, and open it in the default mail client; see email.rs
 #[tauri::command]
-async fn recover_from_autosave(app: tauri::AppHandle, recovery_path: String) -> Result<model::BoardDocument, String> {
-  let path = Path::new(&recovery_path);
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_as_email(app: tauri::AppHandle, args: EmailExportArgs) -> Result<EmailExportResult, AppError> {
+  let title = title::suggest_titles(&args.doc).into_iter().next()
+    .map(|suggestion| suggestion.title)
+    .unwrap_or_else(|| "Idea map".to_string());
+  let subject = args.subject.as_deref().map(str::trim).filter(|s| !s.is_empty()).unwrap_or(&title).to_string();
+
+  let included_layers = args.layers.clone().unwrap_or_else(|| layers::visible_layers(&args.doc));
+  let doc = layers::filter_layers(&confidential::without_locked(&args.doc), &included_layers);
+  let options = TextExportOptions {
+    ordering: "spatial".to_string(),
+    scrub_metadata: false,
+    include_properties: false,
+    include_stats: false,
+    locale: export_locale(&app, args.locale.as_deref(), args.timezone.as_deref())?,
+  };
+  let text = generate_txt_content(&doc, &options)?;
+  let ordered = order_notes_spatially(&doc);
+  let html = email::html_body(&subject, &outline::to_html(&outline::build_outline(&doc, &ordered)));
 
-  if !path.exists() {
-    return Err("Recovery file not found".into());
+  let mut attachments = Vec::new();
+  if let Some(png) = args.png {
+    attachments.push(email::Attachment { name: "idea_map.png".to_string(), mime: "image/png".to_string(), data: png });
+  }
+  if let Some(pdf) = args.pdf {
+    attachments.push(email::Attachment { name: "idea_map.pdf".to_string(), mime: "application/pdf".to_string(), data: pdf });
+  }
+  if attachments.is_empty() {
+    attachments.push(email::Attachment { name: "idea_map.png".to_string(), mime: "image/png".to_string(), data: preview::render_preview(&args.doc)? });
   }
 
-  // Load from the recovery file (which is in .fim format)
-  let doc = load_from_fim(path)?;
-
-  // Update state to indicate we're working with a recovered document
+  let mut secure_cleanup = false;
   if let Some(state) = app.try_state::<Mutex<AppState>>() {
-    if let Ok(mut app_state) = state.lock() {
-      // Try to determine the original path from recovery file
-      if let Some(original_path) = path.parent().and_then(|p| p.file_name()) {
-        if let Some(original_str) = original_path.to_str() {
-          // Remove .recovery extension to get original filename
-          let original_filename = original_str.replace(".fim.recovery", ".fim");
-          if let Some(parent) = path.parent() {
-            app_state.current_document_path = Some(format!("{}/{}", parent.display(), original_filename));
-          }
-        }
-      }
-      app_state.is_dirty = true; // Mark as dirty since it's recovered
-      app_state.last_autosave_time = None;
+    if let Ok(app_state) = state.lock() {
+      secure_cleanup = app_state.settings.secure_cleanup;
     }
   }
-
-  Ok(doc)
+  let path = share::prepare_share_path("idea_map.eml", secure_cleanup)?;
+  let message = email::Message {
+    subject: &subject,
+    to: args.to.as_deref().unwrap_or(&[]),
+    text: &text,
+    html: Some(&html),
+    attachments: &attachments,
+  };
+  let result = std::fs::write(&path, email::build_eml(&message))
+    .map_err(|e| format!("Failed to write email file '{}': {}", path.display(), e));
+  record_audit(&app, "email", &path, || audit::hash_document(&args.doc), &result);
+  result?;
+
+  let opened = match share::open_with_default_app(&path) {
+    Ok(()) => true,
+    Err(e) => {
+      log::warn!("{}", e);
+      false
+    },
+  };
+  Ok(EmailExportResult { path: path.to_string_lossy().to_string(), opened })
 }
 
-// PNG export command - handles file dialog and path selection
+// Clipboard commands
 #[tauri::command]
-async fn export_document_as_png(app: tauri::AppHandle, scale: f64) -> Result<String, String> {
-  use tauri_plugin_dialog::DialogExt;
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn copy_notes_to_clipboard(app: tauri::AppHandle, args: CopyNotesArgs) -> Result<(), AppError> {
+  use tauri_plugin_clipboard_manager::ClipboardExt;
 
-  // Validate scale is one of the supported values
-  if scale != 1.0 && scale != 2.0 && scale != 3.0 {
-    return Err("Scale must be 1.0, 2.0, or 3.0".to_string());
+  let selection = select_notes(&confidential::without_locked(&args.doc), &args.note_ids);
+  if selection.notes.is_empty() {
+    return Err(AppError::InvalidInput { message: "No notes selected".to_string() });
   }
+  let ordered = order_notes_spatially(&selection);
+  let text = clipboard::notes_to_clipboard_text(&selection, &ordered, &args.format)?;
 
-  let file_path = app.dialog()
-    .file()
-    .add_filter("PNG Files", &["png"])
-    .set_file_name(&format!("idea_map_{}x.png", scale))
-    .set_title(&format!("Export as PNG ({}x DPI)", scale))
-    .blocking_save_file();
-
-  let path = match file_path {
-    Some(p) => match p.as_path() {
-      Some(path) => path.to_path_buf(),
-      None => return Err("Invalid save path selected".into()),
-    },
-    None => return Err("Export operation cancelled by user".into()),
+  let result = match text.html {
+    Some(html) => app.clipboard().write_html(html, Some(text.plain)),
+    None => app.clipboard().write_text(text.plain),
   };
-
-  Ok(path.to_string_lossy().to_string())
+  result.map_err(|e| AppError::Io { message: format!("Failed to write to clipboard: {}", e) })
 }
 
-// PDF export command - handles file dialog and path selection
 #[tauri::command]
-async fn export_document_as_pdf(app: tauri::AppHandle, page_size: String, orientation: String) -> Result<String, String> {
-  use tauri_plugin_dialog::DialogExt;
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn copy_png_to_clipboard(app: tauri::AppHandle, png_data: Vec<u8>) -> Result<(), AppError> {
+  use tauri_plugin_clipboard_manager::ClipboardExt;
+
+  let (rgba, width, height) = media::decode_png_rgba(&png_data)?;
+  let image = tauri::image::Image::new_owned(rgba, width, height);
+  app.clipboard().write_image(&image)
+    .map_err(|e| AppError::Io { message: format!("Failed to copy image to clipboard: {}", e) })
+}
 
-  // Validate page size
-  let valid_sizes = vec!["a3", "a4", "a5", "letter", "legal"];
-  if !valid_sizes.contains(&page_size.as_str()) {
-    return Err("Invalid page size. Must be one of: a3, a4, a5, letter, legal".to_string());
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn read_clipboard(app: tauri::AppHandle) -> Result<clipboard::ClipboardContent, AppError> {
+  use tauri_plugin_clipboard_manager::ClipboardExt;
+
+  // Images take precedence: copying a picture often puts its URL or name in the text flavor too
+  if let Ok(image) = app.clipboard().read_image() {
+    if image.width() > 0 && image.height() > 0 {
+      let png_data = media::encode_png_rgba(image.rgba(), image.width(), image.height())?;
+      return Ok(clipboard::ClipboardContent::Image { image: media::image_from_bytes(&png_data)? });
+    }
   }
 
-  // Validate orientation
-  let valid_orientations = vec!["auto", "portrait", "landscape"];
-  if !valid_orientations.contains(&orientation.as_str()) {
-    return Err("Invalid orientation. Must be one of: auto, portrait, landscape".to_string());
+  match app.clipboard().read_text() {
+    Ok(text) => Ok(clipboard::classify_text(&text)),
+    // An empty clipboard or one holding an unsupported flavor reads as an error
+    Err(_) => Ok(clipboard::ClipboardContent::Empty),
   }
+}
 
-  let file_path = app.dialog()
-    .file()
-    .add_filter("PDF Files", &["pdf"])
-    .set_file_name(&format!("idea_map_{}_{}.pdf", page_size, orientation))
-    .set_title(&format!("Export as PDF ({} {})", page_size.to_uppercase(), orientation))
-    .blocking_save_file();
-
-  let path = match file_path {
-    Some(p) => match p.as_path() {
-      Some(path) => path.to_path_buf(),
-      None => return Err("Invalid save path selected".into()),
-    },
-    None => return Err("Export operation cancelled by user".into()),
-  };
-
-  Ok(path.to_string_lossy().to_string())
+// Notes for pasted text with list, indentation or spreadsheet structure; see
+// clipboard::parse_outline
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn parse_clipboard_outline(args: ClipboardOutlineArgs) -> Result<clipboard::ClipboardOutline, AppError> {
+  Ok(clipboard::parse_outline(&args.text, &args.options)?)
 }
 
+// Notes for a long paste, split by paragraphs, sentences or length; see split.rs
 #[tauri::command]
-async fn save_pdf_to_file(file_path: String, pdf_data: Vec<u8>) -> Result<(), String> {
-  use std::fs;
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn split_text_to_notes(args: SplitTextArgs) -> Result<outline::OutlineImport, AppError> {
+  Ok(split::to_notes(&args.text, &args.options)?)
+}
 
-  fs::write(&file_path, pdf_data)
-    .map_err(|e| format!("Failed to write PDF file '{}': {}", file_path, e))?;
+// Write the board as a JSON Canvas file (.canvas) for Obsidian and other
+// canvas apps; see canvas.rs
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_document_as_canvas(app: tauri::AppHandle, args: ExportCanvasArgs) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
 
-  Ok(())
+  let included_layers = args.layers.clone().unwrap_or_else(|| layers::visible_layers(&args.doc));
+  let doc = layers::filter_layers(&confidential::without_locked(&args.doc), &included_layers);
+  let dialog = app.dialog()
+    .file()
+    .add_filter("JSON Canvas", &["canvas"])
+    .set_file_name(format!("{}.canvas", title::file_stem(&args.doc)))
+    .set_title("Export as JSON Canvas");
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let result = canvas::export(&doc).and_then(|json| {
+    std::fs::write(&path, json)
+      .map_err(|e| format!("Failed to write export file '{}': {}", path.display(), e))
+  });
+  record_audit(&app, "export-canvas", &path, || audit::hash_document(&doc), &result);
+  result?;
+  Ok(path.to_string_lossy().to_string())
 }
 
+// Write the board as one self-contained, read-only web page that can be
+// panned and zoomed; see static_html.rs
 #[tauri::command]
-async fn save_png_to_file(file_path: String, png_data: Vec<u8>) -> Result<(), String> {
-  use std::fs;
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_document_as_html(
+  app: tauri::AppHandle,
+  args: ExportHtmlArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
+  use tauri_plugin_dialog::DialogExt;
 
-  fs::write(&file_path, png_data)
-    .map_err(|e| format!("Failed to write PNG file '{}': {}", file_path, e))?;
+  let included_layers = args.layers.clone().unwrap_or_else(|| layers::visible_layers(&args.doc));
+  let doc = layers::filter_layers(&confidential::without_locked(&args.doc), &included_layers);
+  let doc = match &args.note_ids {
+    Some(ids) => export_selection(&doc, ids)?,
+    None => doc,
+  };
+  let dialog = app.dialog()
+    .file()
+    .add_filter("HTML Files", &["html", "htm"])
+    .set_file_name(format!("{}.html", title::file_stem(&args.doc)))
+    .set_title("Export as Web Page");
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
+
+  let progress = progress::Progress::new(on_progress);
+  progress.phase("generating", 0.0);
+  let content = tauri::async_runtime::spawn_blocking(move || static_html::to_html(&doc, args.title.as_deref()))
+    .await
+    .map_err(|e| format!("HTML export failed: {}", e))?;
+
+  let result = progress.write_file(&path, content.as_bytes(), 60.0, 100.0)
+    .map_err(|e| format!("Failed to write export file '{}': {}", path.display(), e));
+  record_audit(&app, "export-html", &path, || Some(audit::hash_bytes(content.as_bytes())), &result);
+  result?;
+  progress.done();
 
-  Ok(())
+  Ok(path.to_string_lossy().to_string())
 }
 
 // Text export commands
 #[tauri::command]
-async fn export_document_as_text(app: tauri::AppHandle, args: ExportTextArgs) -> Result<String, String> {
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn export_document_as_text(
+  app: tauri::AppHandle,
+  args: ExportTextArgs,
+  on_progress: Option<tauri::ipc::Channel<progress::ProgressEvent>>,
+) -> Result<String, AppError> {
   use tauri_plugin_dialog::DialogExt;
 
-  let ordering = args.ordering.unwrap_or_else(|| "spatial".to_string());
+  let (doc, options) = text_export_input(&app, &args)?;
 
   // Determine file extension and dialog filter
   let (extension, filter_name, default_name) = match args.format.as_str() {
+    "md" => ("md", "Markdown Files", "untitled.md"),
     "rtf" => ("rtf", "RTF Files", "untitled.rtf"),
     "opml" => ("opml", "OPML Files", "untitled.opml"),
+    "dot" => ("dot", "Graphviz Files", "untitled.dot"),
+    "mermaid" => ("mmd", "Mermaid Files", "untitled.mmd"),
+    "csv" => ("csv", "CSV Files", "untitled.csv"),
+    "tsv" => ("tsv", "TSV Files", "untitled.tsv"),
     _ => ("txt", "Text Files", "untitled.txt"),
   };
 
-  let file_path = app.dialog()
+  let dialog = app.dialog()
     .file()
     .add_filter(filter_name, &[extension])
-    .add_filter("All Text Formats", &["txt", "rtf", "opml"])
+    .add_filter("All Text Formats", &["txt", "md", "rtf", "opml", "dot", "mmd", "csv", "tsv"])
     .set_file_name(default_name)
-    .set_title(&format!("Export as {}", extension.to_uppercase()))
-    .blocking_save_file();
-
-  let path = match file_path {
-    Some(p) => match p.as_path() {
-      Some(path) => path.to_path_buf(),
-      None => return Err("Invalid save path selected".into()),
-    },
-    None => return Err("Export operation cancelled by user".into()),
-  };
+    .set_title(format!("Export as {}", extension.to_uppercase()));
+  let path = save_path(&app, args.output_path.as_deref(), dialog, "Export").await?;
 
   // Generate text content based on format
-  let content = match args.format.as_str() {
-    "rtf" => generate_rtf_content(&args.doc, &ordering)?,
-    "opml" => generate_opml_content(&args.doc, &ordering)?,
-    _ => generate_txt_content(&args.doc, &ordering)?,
-  };
+  let progress = progress::Progress::new(on_progress);
+  progress.phase("generating", 0.0);
+  let content = generate_text_export(&doc, &args.format, &options)?;
 
   // Write content to file
-  std::fs::write(&path, content)
-    .map_err(|e| format!("Failed to write export file '{}': {}", path.display(), e))?;
+  let result = progress.write_file(&path, content.as_bytes(), 60.0, 100.0)
+    .map_err(|e| format!("Failed to write export file '{}': {}", path.display(), e));
+  record_audit(&app, &format!("export-{}", extension), &path, || audit::hash_document(&doc), &result);
+  result?;
+  progress.done();
 
   Ok(path.to_string_lossy().to_string())
 }
 
-fn generate_txt_content(doc: &model::BoardDocument, ordering: &str) -> Result<String, String> {
-  let ordered_notes = order_notes_by_heuristic(doc, ordering);
+// The part of the board a text export covers, and its options
+fn text_export_input(app: &tauri::AppHandle, args: &ExportTextArgs) -> Result<(model::BoardDocument, TextExportOptions), String> {
+  let options = TextExportOptions {
+    ordering: args.ordering.clone().unwrap_or_else(|| "spatial".to_string()),
+    scrub_metadata: args.scrub_metadata.unwrap_or(false),
+    include_properties: args.include_properties.unwrap_or(false),
+    include_stats: args.include_stats.unwrap_or(false),
+    locale: export_locale(app, args.locale.as_deref(), args.timezone.as_deref())?,
+  };
+
+  let included_layers = args.layers.clone().unwrap_or_else(|| layers::visible_layers(&args.doc));
+  let doc = layers::filter_layers(&confidential::without_locked(&args.doc), &included_layers);
+  let doc = match &args.note_ids {
+    Some(ids) => export_selection(&doc, ids)?,
+    None => doc,
+  };
+  let doc = match &args.label_filter {
+    Some(filter) => select_notes(&doc, &labels::filter_note_ids(&doc, filter)?),
+    None => doc,
+  };
+  Ok((doc, options))
+}
+
+// Text export content by format; anything unknown is plain text
+fn generate_text_export(doc: &model::BoardDocument, format: &str, options: &TextExportOptions) -> Result<String, String> {
+  match format {
+    "md" => generate_markdown_content(doc, options),
+    "rtf" => generate_rtf_content(doc, options),
+    "opml" => generate_opml_content(doc, options),
+    "dot" => Ok(diagram::to_dot(doc, &order_notes_by_heuristic(doc, &options.ordering))),
+    "mermaid" => Ok(diagram::to_mermaid(doc, &order_notes_by_heuristic(doc, &options.ordering))),
+    "csv" | "tsv" => Ok(spreadsheet::export(doc, &order_notes_by_heuristic(doc, &options.ordering), spreadsheet::delimiter(format))),
+    _ => generate_txt_content(doc, options),
+  }
+}
+
+// Export content for a preview pane, without asking for a file: the text of
+// text formats, cut off at `EXPORT_PREVIEW_MAX_CHARS`, or a small rendering
+// of the board for image and PDF exports
+#[tauri::command]
+#[tracing::instrument(skip_all, err(level = "warn"))]
+async fn preview_export(app: tauri::AppHandle, args: ExportTextArgs) -> Result<ExportPreview, AppError> {
+  let (doc, options) = text_export_input(&app, &args)?;
+  let format = args.format;
+  // Rendering and generating large boards takes a while; keep it off the async workers
+  let preview = tauri::async_runtime::spawn_blocking(move || -> Result<ExportPreview, String> {
+    match format.as_str() {
+      "png" | "pdf" => Ok(ExportPreview {
+        png: Some(preview::render_preview(&doc)?),
+        ..ExportPreview::new(format)
+      }),
+      "txt" | "md" | "rtf" | "opml" | "dot" | "mermaid" | "csv" | "tsv" => {
+        let content = generate_text_export(&doc, &format, &options)?;
+        let truncated = content.chars().count() > EXPORT_PREVIEW_MAX_CHARS;
+        let content = if truncated { content.chars().take(EXPORT_PREVIEW_MAX_CHARS).collect() } else { content };
+        Ok(ExportPreview { content: Some(content), truncated, ..ExportPreview::new(format) })
+      },
+      other => Err(format!("Unsupported export preview format: '{}'. Supported formats: txt, md, rtf, opml, dot, mermaid, csv, tsv, png, pdf", other)),
+    }
+  }).await.map_err(|e| format!("Export preview failed: {}", e))??;
+  Ok(preview)
+}
+
+// Author, creation and last change of the board for export footers
+fn document_meta_fields(doc: &model::BoardDocument, options: &TextExportOptions) -> Vec<String> {
+  let (locale, strings) = (&options.locale, options.locale.strings);
+  let Some(info) = doc.info.as_ref().filter(|_| !options.scrub_metadata) else {
+    return Vec::new();
+  };
+  let mut fields = Vec::new();
+  if let Some(author) = info.author.as_deref().filter(|a| !a.trim().is_empty()) {
+    fields.push(locale.field(strings.author, author));
+  }
+  if let Some(created) = info.created_at {
+    fields.push(locale.field(strings.created, &locale.datetime(created)));
+  }
+  if let Some(updated) = info.updated_at {
+    fields.push(locale.field(strings.modified, &locale.datetime(updated)));
+  }
+  fields
+}
+
+fn generate_txt_content(doc: &model::BoardDocument, options: &TextExportOptions) -> Result<String, String> {
+  let ordered_notes = order_notes_by_heuristic(doc, &options.ordering);
+  let locale = &options.locale;
+  let strings = locale.strings;
 
-  let mut output = "Freeform Idea Map Export\n".to_string();
+  let mut output = format!("{}\n", strings.title);
   output += &"=".repeat(30);
   output += "\n\n";
 
+  // Starred notes up front, pointing at their number in the list below
+  let starred: Vec<(usize, &model::Note)> = ordered_notes.iter().enumerate().filter(|(_, n)| n.is_starred()).collect();
+  if !starred.is_empty() {
+    output += &format!("{}\n\n", locale.heading(strings.highlights));
+    for (index, note) in starred {
+      output += &format!("* [{}] {}{}\n", index + 1, icons::note_prefix(note), markdown::note_rich_text(note).0);
+    }
+    output += "\n";
+  }
+
   // Add notes
-  output += "NOTES:\n\n";
+  output += &format!("{}\n\n", locale.heading(strings.notes));
   for (index, note) in ordered_notes.iter().enumerate() {
-    output += &format!("{}. {}\n", index + 1, note.text);
+    let (text, _) = markdown::note_rich_text(note);
+    output += &format!("{}. {}{}\n", index + 1, icons::note_prefix(note), text);
     if note.faded.unwrap_or(false) {
-      output += "   (faded)\n";
+      output += &format!("   ({})\n", strings.faded);
+    }
+    let note_tags = tags::note_tags(note);
+    if !note_tags.is_empty() {
+      output += &format!("   {}\n", locale.field(strings.tags, &note_tags.join(", ")));
+    }
+    for item in checklist::ordered_items(note) {
+      output += &format!("   {} {}\n", checklist::checkbox(item.done), item.text);
+    }
+    if let Some(table) = &note.table {
+      for line in tables::to_aligned_text(table).lines() {
+        output += &format!("   {}\n", line);
+      }
+    }
+    // Links to other notes point at their number, like the highlights
+    for link in note.links.iter().flatten() {
+      match links::note_link_target(link) {
+        Some(target) => {
+          let Some(position) = ordered_notes.iter().position(|n| n.id == target) else { continue };
+          let title = ordered_notes[position].text.lines().next().unwrap_or("");
+          output += &format!("   → [{}] {}\n", position + 1, title);
+        },
+        None => output += &format!("   {}\n", link),
+      }
     }
     output += "\n";
   }
 
-  // Add connections with context
+  // Add connections with context, grouped by relation type when typed
   if !doc.connections.is_empty() {
-    output += "\nCONNECTIONS:\n\n";
-    for (index, conn) in doc.connections.iter().enumerate() {
-      if let (Some(src_note), Some(dst_note)) = (
-        ordered_notes.iter().find(|n| n.id == conn.src_note_id),
-        ordered_notes.iter().find(|n| n.id == conn.dst_note_id)
-      ) {
+    output += &format!("\n{}\n\n", locale.heading(strings.connections));
+    let typed = relations::has_typed_connections(doc);
+    let mut index = 0;
+    for group in relations::group_connections(doc) {
+      if typed {
+        let name = group.relation.map_or(strings.untyped, |r| r.name.as_str());
+        output += &format!("{}\n", locale.colon(name));
+      }
+      for conn in group.connections {
+        index += 1;
+        let (Some(src_note), Some(dst_note)) = (
+          ordered_notes.iter().find(|n| n.id == conn.src_note_id),
+          ordered_notes.iter().find(|n| n.id == conn.dst_note_id)
+        ) else {
+          continue;
+        };
         let src_index = ordered_notes.iter().position(|n| n.id == conn.src_note_id)
           .map(|i| i + 1)
           .unwrap_or(0);
         let dst_index = ordered_notes.iter().position(|n| n.id == conn.dst_note_id)
           .map(|i| i + 1)
           .unwrap_or(0);
-        
+
         // Skip if we couldn't find the note indices
         if src_index == 0 || dst_index == 0 {
           continue;
         }
-        
-        output += &format!("{}. [{}] → [{}]: \"{}\" → \"{}\"\n",
-          index + 1, src_index, dst_index, src_note.text, dst_note.text);
+
+        let arrow = if relations::is_directed(doc, conn) { "→" } else { "—" };
+        output += &format!("{}. {} {} {} {}\n",
+          index, locale.colon(&format!("[{}] {} [{}]", src_index, arrow, dst_index)),
+          locale.quote(&src_note.text), arrow, locale.quote(&dst_note.text));
         if let Some(label) = &conn.label {
-          output += &format!("   Label: {}\n", label);
+          output += &format!("   {}\n", locale.field(strings.label, label));
         }
-        if let Some(style) = &conn.style {
-          if let Some(kind) = &style.kind {
-            output += &format!("   Style: {}\n", kind);
+        if let Some(style) = relations::effective_style(doc, conn) {
+          if let Some(kind) = style.kind.as_ref().filter(|_| !options.scrub_metadata) {
+            output += &format!("   {}\n", locale.field(strings.style, kind));
           }
           if let Some(arrows) = &style.arrows {
             if arrows != "none" {
-              output += &format!("   Arrows: {}\n", arrows);
+              output += &format!("   {}\n", locale.field(strings.arrows, arrows));
             }
           }
         }
       }
+      if typed {
+        output += "\n";
+      }
     }
   }
 
   // Add stacks information
   if !doc.stacks.is_empty() {
-    output += "\nSTACKS:\n\n";
+    output += &format!("\n{}\n\n", locale.heading(strings.stacks));
     for (index, stack) in doc.stacks.iter().enumerate() {
-      output += &format!("{}. Stack ({} notes):\n", index + 1, stack.note_ids.len());
+      let stack_name = format!("{} ({})", strings.stack, locale.notes(stack.note_ids.len()));
+      output += &format!("{}. {}\n", index + 1, locale.colon(&stack_name));
       for note_id in &stack.note_ids {
         if let Some(note) = ordered_notes.iter().find(|n| n.id == *note_id) {
           if let Some(note_index) = ordered_notes.iter().position(|n| n.id == *note_id) {
@@ -699,78 +5556,285 @@ fn generate_txt_content(doc: &model::BoardDocument, ordering: &str) -> Result<St
     }
   }
 
-  output += &format!("\nGenerated: {}\n", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
-  output += &format!("Ordering: {}\n", ordering);
-  output += &format!("{} notes, {} connections\n", doc.notes.len(), doc.connections.len());
+  output += "\n";
+  if !options.scrub_metadata {
+    output += &format!("{}\n", locale.field(strings.generated, &locale.datetime(chrono::Utc::now())));
+  }
+  for field in document_meta_fields(doc, options) {
+    output += &format!("{}\n", field);
+  }
+  output += &format!("{}\n", locale.field(strings.ordering, &options.ordering));
+  output += &format!("{}, {}\n", locale.notes(doc.notes.len()), locale.connections(doc.connections.len()));
+  if options.include_stats {
+    output += &format!("\n{}\n\n", locale.heading(strings.statistics));
+    output += &text_stats::summary(&text_stats::text_stats(doc));
+  }
+
+  Ok(output)
+}
+
+// Markdown for pasting into note-taking apps: the notes as a nested bullet
+// list (connected notes under their source, siblings in export order), then
+// the connections as links to the notes they join and the stacks as sub-lists
+fn generate_markdown_content(doc: &model::BoardDocument, options: &TextExportOptions) -> Result<String, String> {
+  let ordered_notes = order_notes_by_heuristic(doc, &options.ordering);
+  let locale = &options.locale;
+  let strings = locale.strings;
+  let find_note = |id: &str| ordered_notes.iter().find(|n| n.id == id);
+
+  // Notes that links below point at get an anchor in the outline
+  let starred: Vec<&model::Note> = ordered_notes.iter().filter(|n| n.is_starred()).collect();
+  let anchored: std::collections::HashSet<&str> = starred.iter().map(|n| n.id.as_str())
+    .chain(doc.connections.iter().flat_map(|c| [c.src_note_id.as_str(), c.dst_note_id.as_str()]))
+    .chain(doc.stacks.iter().flat_map(|s| s.note_ids.iter().map(|id| id.as_str())))
+    .chain(ordered_notes.iter().flat_map(|n| n.links.iter().flatten().filter_map(|link| links::note_link_target(link))))
+    .collect();
+
+  let mut output = format!("# {}\n\n", strings.title);
+
+  if !starred.is_empty() {
+    output += &format!("## {}\n\n", strings.highlights);
+    for note in starred {
+      output += &format!("- {}\n", markdown_note_link(note));
+    }
+    output += "\n";
+  }
+
+  output += &format!("## {}\n\n", strings.notes);
+  output += &outline::to_markdown_with_anchors(&outline::build_outline(doc, &ordered_notes), &anchored);
+
+  // Grouped by relation type when typed, like the plain text export
+  if !doc.connections.is_empty() {
+    output += &format!("\n## {}\n\n", strings.connections);
+    let typed = relations::has_typed_connections(doc);
+    for group in relations::group_connections(doc) {
+      if typed {
+        let name = group.relation.map_or(strings.untyped, |r| r.name.as_str());
+        output += &format!("### {}\n\n", markdown_escape(name));
+      }
+      for conn in group.connections {
+        let (Some(src_note), Some(dst_note)) = (find_note(&conn.src_note_id), find_note(&conn.dst_note_id)) else {
+          continue;
+        };
+        let arrow = if relations::is_directed(doc, conn) { "→" } else { "—" };
+        output += &format!("- {} {} {}", markdown_note_link(src_note), arrow, markdown_note_link(dst_note));
+        if let Some(label) = &conn.label {
+          output += &format!(" (*{}*)", markdown_escape(label));
+        }
+        output += "\n";
+      }
+      if typed {
+        output += "\n";
+      }
+    }
+  }
+
+  if !doc.stacks.is_empty() {
+    output += &format!("\n## {}\n\n", strings.stacks);
+    for stack in &doc.stacks {
+      output += &format!("- {} ({})\n", strings.stack, locale.notes(stack.note_ids.len()));
+      for note in stack.note_ids.iter().filter_map(|id| find_note(id)) {
+        output += &format!("  - {}\n", markdown_note_link(note));
+      }
+    }
+  }
+
+  output += "\n---\n\n";
+  if !options.scrub_metadata {
+    output += &format!("{}\n\n", locale.field(strings.generated, &locale.datetime(chrono::Utc::now())));
+  }
+  for field in document_meta_fields(doc, options) {
+    output += &format!("{}\n\n", field);
+  }
+  output += &format!("{}\n\n", locale.field(strings.ordering, &options.ordering));
+  output += &format!("{}, {}\n", locale.notes(doc.notes.len()), locale.connections(doc.connections.len()));
+  if options.include_stats {
+    output += &format!("\n## {}\n\n", strings.statistics);
+    for line in text_stats::summary(&text_stats::text_stats(doc)).lines() {
+      output += &format!("- {}\n", line);
+    }
+  }
 
   Ok(output)
 }
 
-fn generate_rtf_content(doc: &model::BoardDocument, ordering: &str) -> Result<String, String> {
-  let ordered_notes = order_notes_by_heuristic(doc, ordering);
+// `[first line](#anchor)`, pointing at the note's item in the Markdown outline
+fn markdown_note_link(note: &model::Note) -> String {
+  let (text, _) = markdown::note_rich_text(note);
+  let title = match text.lines().next().map(str::trim).unwrap_or("") {
+    "" => note.id.as_str(),
+    line => line,
+  };
+  format!("[{}{}](#{})", icons::note_prefix(note), markdown_escape(title), links::anchor_id(&note.id))
+}
+
+// Plain text for inline Markdown, with the characters that would start
+// emphasis, code, links or HTML escaped
+fn markdown_escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+fn generate_rtf_content(doc: &model::BoardDocument, options: &TextExportOptions) -> Result<String, String> {
+  let ordered_notes = order_notes_by_heuristic(doc, &options.ordering);
+
+  // Span and note style text colors are appended to the color table after
+  // the two fixed entries
+  let mut table_colors = rich_text::span_colors(doc);
+  for color in doc.note_styles.iter().filter_map(|s| s.text_style.color.as_ref()) {
+    if !table_colors.contains(color) {
+      table_colors.push(color.clone());
+    }
+  }
+  let color_index = |color: &str| table_colors.iter()
+    .position(|c| c == color)
+    .filter(|_| preview::parse_hex_color(color).is_some())
+    .map(|i| i + 3);
+
+  // Note style fonts follow the default font
+  let mut fonts: Vec<&str> = Vec::new();
+  for font in doc.note_styles.iter().map(|s| s.text_style.font.trim()) {
+    if !font.is_empty() && !fonts.contains(&font) {
+      fonts.push(font);
+    }
+  }
+  let font_index = |font: &str| fonts.iter().position(|f| *f == font.trim()).map(|i| i + 1);
 
-  let mut rtf = "{\\rtf1\\ansi\\deff0 {\\fonttbl {\\f0 Times New Roman;}}".to_string();
-  rtf += "{\\colortbl ;\\red0\\green0\\blue0;\\red100\\green100\\blue100;}";
-  rtf += "\\fs24\\pard\\qc\\b Freeform Idea Map Export\\b0\\par\\par\\pard\\ql";
+  let mut rtf = "{\\rtf1\\ansi\\deff0 {\\fonttbl {\\f0 Times New Roman;}".to_string();
+  for (index, font) in fonts.iter().enumerate() {
+    rtf += &format!("{{\\f{} {};}}", index + 1, rtf_escape(font));
+  }
+  rtf += "}{\\colortbl ;\\red0\\green0\\blue0;\\red100\\green100\\blue100;";
+  for color in &table_colors {
+    let [r, g, b, _] = preview::parse_hex_color(color).unwrap_or([0, 0, 0, 255]);
+    rtf += &format!("\\red{}\\green{}\\blue{};", r, g, b);
+  }
+  rtf += "}";
+  let locale = &options.locale;
+  let strings = locale.strings;
+  rtf += &format!("\\fs24\\pard\\qc\\b {}\\b0\\par\\par\\pard\\ql", rtf_escape(strings.title));
 
   // Notes section
-  rtf += "\\b Notes\\b0\\par\\par";
+  rtf += &format!("\\b {}\\b0\\par\\par", rtf_escape(strings.notes));
   for (index, note) in ordered_notes.iter().enumerate() {
-    rtf += &format!("{}. {}\\par", index + 1, rtf_escape(&note.text));
+    let (text, spans) = markdown::note_rich_text(note);
+    let mut text = rich_text::to_rtf(&text, &spans, rtf_escape, color_index);
+    if let Some(style) = doc.note_style(note) {
+      let codes = rich_text::rtf_style_codes(
+        &style.text_style,
+        font_index(&style.text_style.font),
+        style.text_style.color.as_deref().and_then(color_index),
+      );
+      if !codes.is_empty() {
+        text = format!("{{{} {}}}", codes, text);
+      }
+    }
+    rtf += &format!("{}. {}{}\\par", index + 1, rtf_escape(&icons::note_prefix(note)), text);
     if note.faded.unwrap_or(false) {
-      rtf += "\\cf1 (faded)\\cf0\\par";
+      rtf += &format!("\\cf1 ({})\\cf0\\par", rtf_escape(strings.faded));
+    }
+    let note_tags = tags::note_tags(note);
+    if !note_tags.is_empty() {
+      rtf += &format!("   \\cf2 {}\\cf0\\par", rtf_escape(&locale.field(strings.tags, &note_tags.join(", "))));
+    }
+    for item in checklist::ordered_items(note) {
+      rtf += &format!("   {} {}\\par", checklist::checkbox(item.done), rtf_escape(&item.text));
+    }
+    if let Some(table) = &note.table {
+      rtf += &tables::to_rtf(table, rtf_escape);
     }
     rtf += "\\par";
   }
 
-  // Connections section
+  // Connections section, grouped by relation type when typed
   if !doc.connections.is_empty() {
-    rtf += "\\b Connections\\b0\\par\\par";
-    for (index, conn) in doc.connections.iter().enumerate() {
-      if let (Some(src_note), Some(dst_note)) = (
-        ordered_notes.iter().find(|n| n.id == conn.src_note_id),
-        ordered_notes.iter().find(|n| n.id == conn.dst_note_id)
-      ) {
+    rtf += &format!("\\b {}\\b0\\par\\par", rtf_escape(strings.connections));
+    let typed = relations::has_typed_connections(doc);
+    let mut index = 0;
+    for group in relations::group_connections(doc) {
+      if typed {
+        let name = group.relation.map_or(strings.untyped, |r| r.name.as_str());
+        rtf += &format!("\\i {}\\i0\\par", rtf_escape(name));
+      }
+      for conn in group.connections {
+        index += 1;
+        let (Some(src_note), Some(dst_note)) = (
+          ordered_notes.iter().find(|n| n.id == conn.src_note_id),
+          ordered_notes.iter().find(|n| n.id == conn.dst_note_id)
+        ) else {
+          continue;
+        };
         let src_index = ordered_notes.iter().position(|n| n.id == conn.src_note_id)
           .map(|i| i + 1)
           .unwrap_or(0);
         let dst_index = ordered_notes.iter().position(|n| n.id == conn.dst_note_id)
           .map(|i| i + 1)
           .unwrap_or(0);
-        
+
         // Skip if we couldn't find the note indices
         if src_index == 0 || dst_index == 0 {
           continue;
         }
-        
-        rtf += &format!("{}. [{}] → [{}]: {} → {}\\par",
-          index + 1, src_index, dst_index,
-          rtf_escape(&src_note.text), rtf_escape(&dst_note.text));
+
+        let arrow = if relations::is_directed(doc, conn) { "→" } else { "—" };
+        rtf += &format!("{}. {} {} {} {}\\par",
+          index, rtf_escape(&locale.colon(&format!("[{}] {} [{}]", src_index, arrow, dst_index))),
+          rtf_escape(&src_note.text), rtf_escape(arrow), rtf_escape(&dst_note.text));
         if let Some(label) = &conn.label {
-          rtf += &format!("   Label: {}\\par", rtf_escape(label));
+          rtf += &format!("   {}\\par", rtf_escape(&locale.field(strings.label, label)));
         }
       }
+      if typed {
+        rtf += "\\par";
+      }
     }
   }
 
   // Metadata
   rtf += "\\par\\par";
-  rtf += &format!("Generated: {}\\par", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
-  rtf += &format!("Ordering: {}\\par", ordering);
-  rtf += &format!("{} notes, {} connections\\par", doc.notes.len(), doc.connections.len());
+  if !options.scrub_metadata {
+    rtf += &format!("{}\\par", rtf_escape(&locale.field(strings.generated, &locale.datetime(chrono::Utc::now()))));
+  }
+  for field in document_meta_fields(doc, options) {
+    rtf += &format!("{}\\par", rtf_escape(&field));
+  }
+  rtf += &format!("{}\\par", rtf_escape(&locale.field(strings.ordering, &options.ordering)));
+  rtf += &format!("{}, {}\\par", rtf_escape(&locale.notes(doc.notes.len())), rtf_escape(&locale.connections(doc.connections.len())));
+  if options.include_stats {
+    rtf += &format!("\\par\\b {}\\b0\\par\\par", rtf_escape(strings.statistics));
+    for line in text_stats::summary(&text_stats::text_stats(doc)).lines() {
+      rtf += &format!("{}\\par", rtf_escape(line));
+    }
+  }
   rtf += "}";
 
   Ok(rtf)
 }
 
-fn generate_opml_content(doc: &model::BoardDocument, ordering: &str) -> Result<String, String> {
-  let ordered_notes = order_notes_by_heuristic(doc, ordering);
+fn generate_opml_content(doc: &model::BoardDocument, options: &TextExportOptions) -> Result<String, String> {
+  let ordered_notes = order_notes_by_heuristic(doc, &options.ordering);
 
   let mut opml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
   opml += "<opml version=\"2.0\">\n";
   opml += "  <head>\n";
-  opml += "    <title>Freeform Idea Map Export</title>\n";
-  opml += &format!("    <dateCreated>{}</dateCreated>\n", chrono::Utc::now().to_rfc3339());
+  opml += &format!("    <title>{}</title>\n", opml_escape(options.locale.strings.title));
+  if !options.scrub_metadata {
+    let info = doc.info.clone().unwrap_or_default();
+    // OPML dates are RFC 822
+    opml += &format!("    <dateCreated>{}</dateCreated>\n", info.created_at.unwrap_or_else(chrono::Utc::now).to_rfc2822());
+    if let Some(updated) = info.updated_at {
+      opml += &format!("    <dateModified>{}</dateModified>\n", updated.to_rfc2822());
+    }
+    if let Some(author) = info.author.as_deref().filter(|a| !a.trim().is_empty()) {
+      opml += &format!("    <ownerName>{}</ownerName>\n", opml_escape(author));
+    }
+  }
   opml += "    <expansionState>1,2,3</expansionState>\n";
   opml += "  </head>\n";
   opml += "  <body>\n";
@@ -784,23 +5848,15 @@ fn generate_opml_content(doc: &model::BoardDocument, ordering: &str) -> Result<S
 
   // Add root notes and their connections
   for note in root_notes {
-    opml += &format!("    <outline text=\"{}\"{}\n",
-      opml_escape(&note.text),
-      if note.faded.unwrap_or(false) { " _faded=\"true\"" } else { "" });
-
     if !processed.contains(&note.id) {
-      processed.insert(note.id.clone());
-      opml += &add_note_to_opml_recursive(note, doc, &ordered_notes, &mut processed, 6)?;
-      opml += "    </outline>\n";
+      opml += &write_opml_outline(note, None, doc, &ordered_notes, &mut processed, 2, options);
     }
   }
 
   // Add any remaining notes (orphans)
   for note in &ordered_notes {
     if !processed.contains(&note.id) {
-      opml += &format!("    <outline text=\"{}\"{}/>\n",
-        opml_escape(&note.text),
-        if note.faded.unwrap_or(false) { " _faded=\"true\"" } else { "" });
+      opml += &write_opml_outline(note, None, doc, &ordered_notes, &mut processed, 2, options);
     }
   }
 
@@ -811,6 +5867,33 @@ fn generate_opml_content(doc: &model::BoardDocument, ordering: &str) -> Result<S
 }
 
 // Helper functions for text ordering and formatting
+fn select_notes(doc: &model::BoardDocument, note_ids: &[String]) -> model::BoardDocument {
+  let selected: std::collections::HashSet<&str> = note_ids.iter().map(|id| id.as_str()).collect();
+  let mut subset = doc.clone();
+  subset.notes.retain(|n| selected.contains(n.id.as_str()));
+  subset.connections.retain(|c| selected.contains(c.src_note_id.as_str()) && selected.contains(c.dst_note_id.as_str()));
+  for stack in &mut subset.stacks {
+    stack.note_ids.retain(|id| selected.contains(id.as_str()));
+  }
+  subset.stacks.retain(|s| !s.note_ids.is_empty());
+  groups::prune_groups(&mut subset);
+  subset
+}
+
+// The part of the board an export of the selected notes covers: those notes,
+// the connections between them and the shapes they are in. Ink is left out.
+fn export_selection(doc: &model::BoardDocument, note_ids: &[model::ID]) -> Result<model::BoardDocument, String> {
+  let mut subset = select_notes(doc, note_ids);
+  if subset.notes.is_empty() {
+    return Err("Selected notes not found on the board".to_string());
+  }
+  let notes = &subset.notes;
+  subset.shapes.retain(|shape| notes.iter().any(|note| shapes::contains_note(shape, note)));
+  subset.strokes = None;
+  groups::prune_groups(&mut subset);
+  Ok(subset)
+}
+
 fn order_notes_by_heuristic(doc: &model::BoardDocument, ordering: &str) -> Vec<model::Note> {
   match ordering {
     "connections" => order_notes_by_connections(doc),
@@ -925,6 +6008,15 @@ fn order_notes_hierarchically(doc: &model::BoardDocument) -> Vec<model::Note> {
     note_styles: doc.note_styles.clone(),
     document_style: doc.document_style.clone(),
     images: doc.images.clone(),
+    groups: doc.groups.clone(),
+    relation_types: doc.relation_types.clone(),
+    strokes: doc.strokes.clone(),
+    layers: doc.layers.clone(),
+    templates: doc.templates.clone(),
+    properties: doc.properties.clone(),
+    trash: None,
+    note_encryption: doc.note_encryption.clone(),
+    info: doc.info.clone(),
   });
 
   ordered.extend(remaining_ordered);
@@ -932,11 +6024,25 @@ fn order_notes_hierarchically(doc: &model::BoardDocument) -> Vec<model::Note> {
 }
 
 fn rtf_escape(text: &str) -> String {
-  text.replace('\\', "\\\\")
-    .replace('{', "\\{")
-    .replace('}', "\\}")
-    .replace('\n', "\\par ")
-    .replace('\t', "\\tab ")
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '\\' => escaped += "\\\\",
+      '{' => escaped += "\\{",
+      '}' => escaped += "\\}",
+      '\n' => escaped += "\\par ",
+      '\t' => escaped += "\\tab ",
+      c if c.is_ascii() => escaped.push(c),
+      // Anything else as UTF-16 units (signed, as RTF wants them), with `?`
+      // for readers that don't know \u
+      c => {
+        for unit in c.encode_utf16(&mut [0; 2]) {
+          escaped += &format!("\\u{}?", *unit as i16);
+        }
+      },
+    }
+  }
+  escaped
 }
 
 fn opml_escape(text: &str) -> String {
@@ -945,64 +6051,161 @@ fn opml_escape(text: &str) -> String {
     .replace('>', "&gt;")
     .replace('"', "&quot;")
     .replace('\'', "&#39;")
+    // Attribute values are whitespace-normalized on read otherwise
+    .replace('\n', "&#10;")
 }
 
-fn add_note_to_opml_recursive(
+// Write a note as an <outline> element: checklist items first (with the
+// `_status` attribute outliners use for checkboxes), then the notes it
+// connects to, nested recursively. `_`-prefixed attributes carry what the
+// outline structure can't (IDs, stacks, styles, links to notes placed
+// elsewhere) so `opml::import` can rebuild the board.
+fn write_opml_outline(
   note: &model::Note,
+  via: Option<&model::Connection>,
   doc: &model::BoardDocument,
   ordered_notes: &[model::Note],
   processed: &mut std::collections::HashSet<String>,
-  indent: usize
-) -> Result<String, String> {
-  if processed.contains(&note.id) {
-    return Ok(String::new());
+  depth: usize,
+  options: &TextExportOptions
+) -> String {
+  processed.insert(note.id.clone());
+  let indent = "  ".repeat(depth);
+  let mut attributes = format!("text=\"{}\" _id=\"{}\"",
+    opml_escape(&format!("{}{}", icons::note_prefix(note), markdown::note_rich_text(note).0)),
+    opml_escape(&note.id));
+  if let Some(icon) = note.icon.as_deref().filter(|i| !i.trim().is_empty()) {
+    attributes += &format!(" _icon=\"{}\"", opml_escape(icon));
+  }
+  if note.is_markdown() {
+    attributes += &format!(" _markdown=\"{}\"", opml_escape(&note.text));
+  }
+  if note.faded.unwrap_or(false) {
+    attributes += " _faded=\"true\"";
+  }
+  if note.is_starred() {
+    attributes += " _starred=\"true\"";
+  }
+  let note_tags = tags::note_tags(note);
+  if !note_tags.is_empty() {
+    attributes += &format!(" _tags=\"{}\"", opml_escape(&note_tags.join(",")));
+  }
+  if !options.scrub_metadata {
+    if let Some(created) = note.created_at {
+      attributes += &format!(" created=\"{}\"", created.to_rfc2822());
+    }
+    if let Some(updated) = note.updated_at {
+      attributes += &format!(" _modified=\"{}\"", updated.to_rfc2822());
+    }
+  }
+  // `url` is what outliners open; `_note` keeps every link, one per line
+  let note_links: Vec<&str> = note.links.iter().flatten().map(String::as_str).collect();
+  if let Some(url) = note_links.iter().find(|l| links::note_link_target(l).is_none()) {
+    attributes += &format!(" url=\"{}\"", opml_escape(url));
+  }
+  if !note_links.is_empty() {
+    attributes += &format!(" _note=\"{}\"", opml_escape(&note_links.join("\n")));
+  }
+  if let Some((stack, index)) = doc.stacks.iter()
+    .find_map(|s| s.note_ids.iter().position(|id| id == &note.id).map(|i| (s, i))) {
+    attributes += &format!(" _stack=\"{}\" _stackIndex=\"{}\"", opml_escape(&stack.id), index);
+  }
+  if let Some(style) = doc.note_style(note) {
+    attributes += &format!(" _style=\"{}\"", opml_escape(&style.id));
+    if let Some(fill) = &style.fill {
+      attributes += &format!(" _fill=\"{}\"", opml_escape(fill));
+    }
+    if let Some(color) = &style.text_style.color {
+      attributes += &format!(" _color=\"{}\"", opml_escape(color));
+    }
+  }
+  let f = &note.frame;
+  attributes += &format!(" _frame=\"{},{},{},{}\"", f.x, f.y, f.w, f.h);
+  if let Some(conn) = via {
+    attributes += &opml_connection_attributes(conn);
+  }
+  if options.include_properties {
+    for (key, value) in properties::sorted(&note.properties) {
+      attributes += &format!(" {}=\"{}\"", properties::attribute_name(key), opml_escape(&properties::value_text(value)));
+    }
   }
 
-  let mut opml = String::new();
-
-  // Find children (notes this note connects to)
-  let children: Vec<_> = doc.connections.iter()
-    .filter(|c| c.src_note_id == note.id)
-    .filter_map(|c| ordered_notes.iter().find(|n| n.id == c.dst_note_id))
-    .collect();
+  let mut inner = String::new();
+  for item in checklist::ordered_items(note) {
+    inner += &format!("{}  <outline text=\"{}\" _status=\"{}\"/>\n",
+      indent,
+      opml_escape(&item.text),
+      if item.done { "checked" } else { "unchecked" });
+  }
 
-  if children.is_empty() {
-    opml += "/>\n";
-  } else {
-    opml += ">\n";
-    for child in children {
-      if !processed.contains(&child.id) {
-        processed.insert(child.id.clone());
-        opml += &format!("{}<outline text=\"{}\"{}{}\n",
-          "  ".repeat(indent / 2 + 1),
-          opml_escape(&child.text),
-          if child.faded.unwrap_or(false) { " _faded=\"true\"" } else { "" },
-          if has_children(&child.id, doc) { "" } else { "/" });
-
-        if has_children(&child.id, doc) {
-          opml += &add_note_to_opml_recursive(child, doc, ordered_notes, processed, indent + 2)?;
-          opml += &format!("{}</outline>\n", "  ".repeat(indent / 2 + 1));
-        } else {
-          opml.push('\n');
-        }
+  // Find children (notes this note connects to); typed connections are
+  // gathered under an outline named after their relation type. Targets
+  // already written elsewhere become `_ref` outlines pointing at them.
+  for group in relations::group_connections(doc) {
+    let child_depth = if group.relation.is_some() { depth + 2 } else { depth + 1 };
+    let mut children = String::new();
+    for conn in group.connections.iter().filter(|c| c.src_note_id == note.id) {
+      let Some(child) = ordered_notes.iter().find(|n| n.id == conn.dst_note_id) else {
+        continue;
+      };
+      if processed.contains(&child.id) {
+        children += &format!("{}<outline text=\"→ {}\" _ref=\"{}\"{}/>\n",
+          "  ".repeat(child_depth),
+          opml_escape(markdown::note_rich_text(child).0.lines().next().unwrap_or("")),
+          opml_escape(&child.id),
+          opml_connection_attributes(conn));
+      } else {
+        children += &write_opml_outline(child, Some(conn), doc, ordered_notes, processed, child_depth, options);
       }
     }
-    opml += &format!("{}</outline>\n", "  ".repeat(indent / 2));
+    match group.relation {
+      None => inner += &children,
+      Some(relation) if !children.is_empty() => {
+        inner += &format!("{}  <outline text=\"{}\" _relation=\"{}\">\n{}{}  </outline>\n",
+          indent, opml_escape(&relation.name), opml_escape(&relation.id), children, indent);
+      },
+      Some(_) => {},
+    }
   }
 
-  Ok(opml)
+  if inner.is_empty() {
+    format!("{}<outline {}/>\n", indent, attributes)
+  } else {
+    format!("{}<outline {}>\n{}{}</outline>\n", indent, attributes, inner, indent)
+  }
 }
 
-fn has_children(note_id: &str, doc: &model::BoardDocument) -> bool {
-  doc.connections.iter().any(|c| c.src_note_id == note_id)
+// The connection an outline was reached through: its ID and label
+fn opml_connection_attributes(conn: &model::Connection) -> String {
+  let mut attributes = format!(" _connection=\"{}\"", opml_escape(&conn.id));
+  if let Some(label) = conn.label.as_deref().filter(|l| !l.trim().is_empty()) {
+    attributes += &format!(" _label=\"{}\"", opml_escape(label));
+  }
+  attributes
 }
 
+// Command-line mode (`fim export ...`); None when the app should start
+pub fn run_cli(args: &[String]) -> Option<i32> {
+  cli::run(args)
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  let builder = tauri::Builder::default()
+  if let Err(e) = logging::init() {
+    log::warn!("{}", e);
+  }
+
+  let builder = tauri::Builder::default();
+
+  // Registered first, so a second instance exits before setting anything else up
+  #[cfg(desktop)]
+  let builder = builder.plugin(tauri_plugin_single_instance::init(handle_second_instance));
+
+  let builder = builder
     .plugin(tauri_plugin_dialog::init())
-    .plugin(tauri_plugin_fs::init());
+    .plugin(tauri_plugin_fs::init())
+    .plugin(tauri_plugin_clipboard_manager::init())
+    .plugin(tauri_plugin_deep_link::init());
 
   #[cfg(debug_assertions)]
   let builder = builder.plugin(tauri_plugin_devtools::init());
@@ -1013,39 +6216,254 @@ pub fn run() {
   builder
     .setup(|app| {
       // Initialize state
-      app.manage(Mutex::new(AppState::default()));
-      
-      if cfg!(debug_assertions) {
+      let settings = settings::load_settings();
+      #[cfg(desktop)]
+      let quick_capture_shortcut = settings.quick_capture_shortcut.clone();
+      app.manage(Mutex::new(AppState {
+        settings,
+        recent_files: recent_files::RecentFiles::load(),
+        session: session::Session::load(),
+        ..AppState::default()
+      }));
+
+      // Global quick-capture shortcut
+      #[cfg(desktop)]
+      {
+        use tauri_plugin_global_shortcut::ShortcutState;
+
         app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
+          tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, _shortcut, event| {
+              if event.state == ShortcutState::Pressed {
+                show_quick_capture_window(app);
+              }
+            })
             .build(),
         )?;
+        if let Err(e) = register_quick_capture_shortcut(app.handle(), "", &quick_capture_shortcut) {
+          log::warn!("{}", e);
+        }
+      }
+
+      // Scheduled backups; run_backups checks the settings on every wake-up
+      let handle = app.handle().clone();
+      std::thread::spawn(move || loop {
+        std::thread::sleep(backup::CHECK_INTERVAL);
+        if let Err(e) = run_backups(&handle, false) {
+          log::warn!("Scheduled backup failed: {}", e);
+        }
+      });
+
+      // Documents passed on the command line (file associations on Windows/Linux)
+      let cwd = std::env::current_dir().unwrap_or_default();
+      for path in document_paths_from_args(std::env::args_os().skip(1), &cwd) {
+        open_document_from_os(app.handle(), &path);
+      }
+
+      // fim:// links, both the one we were launched with and later ones
+      {
+        use tauri_plugin_deep_link::DeepLinkExt;
+
+        // Installed bundles register the scheme; this covers dev builds and AppImages
+        #[cfg(any(windows, target_os = "linux"))]
+        if let Err(e) = app.deep_link().register_all() {
+          log::warn!("Failed to register fim:// links: {}", e);
+        }
+
+        if let Ok(Some(urls)) = app.deep_link().get_current() {
+          for url in urls.iter().filter(|url| url.scheme() == deep_link::SCHEME) {
+            open_deep_link(app.handle(), url);
+          }
+        }
+
+        // On macOS this also fires for file:// URLs, which handle_run_event opens
+        let handle = app.handle().clone();
+        app.deep_link().on_open_url(move |event| {
+          for url in event.urls().iter().filter(|url| url.scheme() == deep_link::SCHEME) {
+            open_deep_link(&handle, url);
+          }
+        });
       }
       Ok(())
     })
+    .on_window_event(handle_window_event)
     .invoke_handler(tauri::generate_handler![
       open_document,
       open_specific_document,
+      take_pending_open_documents,
+      new_window_with_document,
       save_document,
       get_recent_files,
       clear_recent_files,
+      get_settings,
+      update_settings,
+      get_audit_log,
       autosave_document,
       set_document_dirty,
       set_current_document_path,
       get_autosave_status,
       check_recovery_files,
       recover_from_autosave,
+      preview_recovery,
+      discard_recovery,
       export_document_as_text,
       export_document_as_png,
       save_png_to_file,
       export_document_as_pdf,
-      save_pdf_to_file
+      print_document,
+      save_pdf_to_file,
+      copy_notes_to_clipboard,
+      copy_png_to_clipboard,
+      read_clipboard,
+      get_document_preview,
+      get_document_thumbnail,
+      get_note_link,
+      submit_quick_capture,
+      quick_capture,
+      close_quick_capture,
+      share_export,
+      bring_to_front,
+      send_to_back,
+      create_group,
+      dissolve_group,
+      move_group,
+      delete_items,
+      get_task_summary,
+      filter_notes_by_label,
+      count_labels,
+      get_all_tags,
+      filter_notes_by_tag,
+      validate_document,
+      render_markdown,
+      get_backlink_index,
+      instantiate_template,
+      get_template_placeholders,
+      save_as_template,
+      list_templates,
+      create_from_template,
+      delete_template,
+      find_notes_by_property,
+      move_to_layer,
+      get_pdf_bookmarks,
+      plan_poster_export,
+      apply_export_theme,
+      plan_presentation,
+      export_presentation,
+      plan_walkthrough,
+      export_walkthrough,
+      import_opml,
+      import_text_outline,
+      import_freeform_assets,
+      open_document_lenient,
+      parse_clipboard_outline,
+      import_image_folder,
+      import_bookmarks,
+      import_highlights_csv,
+      import_notes_from_csv,
+      import_bibtex,
+      import_url,
+      validate_links,
+      fetch_link_metadata,
+      export_markdown_vault,
+      batch_export,
+      start_share_server,
+      update_share_server,
+      stop_share_server,
+      sync_markdown_vault,
+      set_recent_file_pinned,
+      list_tasks,
+      cancel_task,
+      get_usage_stats,
+      clear_usage_stats,
+      duplicate_notes,
+      text_stats,
+      get_document_stats,
+      detect_languages,
+      translate_notes,
+      suggest_title,
+      get_document_info,
+      archive_board,
+      unarchive_board,
+      list_archived_boards,
+      scan_library,
+      list_starred,
+      restore_from_trash,
+      empty_trash,
+      analyze_graph,
+      analyze_document,
+      find_cycles,
+      find_orphans,
+      find_path,
+      suggest_similar,
+      suggest_stacks,
+      layout_document,
+      diff_documents,
+      merge_documents,
+      compute_shape_membership,
+      move_shape,
+      prepare_snapping,
+      snap_frame,
+      export_style_theme,
+      apply_style_theme,
+      save_style_to_library,
+      list_library_styles,
+      insert_library_styles,
+      remove_library_style,
+      get_embedded_fonts,
+      extract_palette,
+      replace_color,
+      import_texture,
+      remove_texture,
+      recently_edited,
+      split_by_activity,
+      run_backup_now,
+      list_backups,
+      create_snapshot,
+      list_snapshots,
+      restore_snapshot,
+      get_recovery_locations,
+      add_recovery_location,
+      remove_recovery_location,
+      set_publish_token,
+      has_publish_token,
+      publish_board,
+      export_as_email,
+      generate_qr,
+      set_notes_passphrase,
+      unlock_document,
+      unlock_confidential_notes,
+      lock_confidential_notes,
+      set_notes_confidential,
+      open_readonly,
+      is_read_only,
+      read_document_info,
+      split_text_to_notes,
+      summarize_notes,
+      save_current_document,
+      save_document_with_conflict_check,
+      preview_export,
+      get_recent_logs,
+      run_health_check,
+      focus_document_window,
+      generate_ids,
+      add_image_to_document,
+      get_image_data,
+      read_media,
+      ingest_image,
+      import_document,
+      pin_recent_file,
+      remove_recent_file,
+      get_last_session,
+      export_document_as_canvas,
+      export_document_as_html,
+      render_document_to_svg,
+      render_document_to_png,
+      search_notes
     ])
-    .run(tauri::generate_context!())
-    .map_err(|e| {
-      eprintln!("Failed to start Tauri application: {}", e);
+    .build(tauri::generate_context!())
+    .unwrap_or_else(|e| {
+      tracing::error!("Failed to start Tauri application: {}", e);
       std::process::exit(1);
     })
-    .unwrap();
+    .run(handle_run_event);
 }
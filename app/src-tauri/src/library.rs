@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{archive, encryption, model, preview, properties, security, settings, title};
+
+// The user's boards outside any single document.
+//
+// Archived boards: archiving packs a board and the files that travel with it
+// (search sidecar, recovery file and its metadata) into one zip under the app
+// data folder and deletes the originals; the library index remembers where
+// each board came from so it can be listed, found and unarchived later.
+//
+// Scanning: `scan` finds .fim files under a set of folders for the "all my
+// boards" gallery. It reads the small metadata entry saved boards carry
+// (falling back to board.json for boards saved before it existed) and caches
+// the result per file until the file changes.
+
+const LIBRARY_FILE: &str = "library.json";
+const ARCHIVE_DIR: &str = "archive";
+const SCAN_CACHE_FILE: &str = "library-cache.json";
+// Entry in .fim containers with the board's title, tags, counts and info
+// block, stored unencrypted
+pub const METADATA_ENTRY: &str = "meta.json";
+const MAX_METADATA_SIZE: u64 = 1024 * 1024;
+// Deep enough for any sensible folder layout, shallow enough to stop on loops
+const MAX_SCAN_DEPTH: usize = 12;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArchivedBoard {
+  pub id: model::ID,
+  pub title: String,
+  #[serde(rename = "originalPath")]
+  pub original_path: String,
+  #[serde(rename = "archivePath")]
+  pub archive_path: String,
+  // Original locations of everything in the archive, the board first
+  pub files: Vec<String>,
+  #[serde(rename = "archivedAt")]
+  pub archived_at: chrono::DateTime<chrono::Utc>,
+  // Size of the archive in bytes
+  pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Library {
+  pub boards: Vec<ArchivedBoard>,
+}
+
+fn library_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(LIBRARY_FILE))
+}
+
+impl Library {
+  pub fn load() -> Library {
+    let Some(path) = library_path() else {
+      return Library::default();
+    };
+    match std::fs::read_to_string(&path) {
+      Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Ignoring invalid library index '{}': {}", path.display(), e);
+        Library::default()
+      }),
+      Err(_) => Library::default(),
+    }
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    let path = library_path().ok_or("Could not determine the app data directory")?;
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(self)
+      .map_err(|e| format!("Failed to serialize library index: {}", e))?;
+    std::fs::write(&path, json)
+      .map_err(|e| format!("Failed to write library index '{}': {}", path.display(), e))
+  }
+}
+
+fn entry_name(path: &Path) -> Result<String, String> {
+  path.file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .ok_or_else(|| format!("Invalid file path '{}'", path.display()))
+}
+
+// Pack `board` and the existing files among `artifacts` into a new archive,
+// then delete them. The board is deleted first: if that fails nothing is
+// lost and the archive is discarded.
+pub fn archive_board(board: &Path, artifacts: &[PathBuf], title: &str, secure: bool) -> Result<ArchivedBoard, String> {
+  if !board.is_file() {
+    return Err(format!("Board '{}' not found", board.display()));
+  }
+  let files: Vec<&Path> = std::iter::once(board)
+    .chain(artifacts.iter().map(PathBuf::as_path).filter(|p| p.is_file()))
+    .collect();
+
+  let dir = settings::data_dir().ok_or("Could not determine the app data directory")?.join(ARCHIVE_DIR);
+  std::fs::create_dir_all(&dir)
+    .map_err(|e| format!("Failed to create archive directory '{}': {}", dir.display(), e))?;
+  let stem = board.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+  let archive_path = dir.join(format!("{}-{}.zip", stem, chrono::Local::now().format("%Y%m%d-%H%M%S")));
+
+  if let Err(e) = write_archive(&archive_path, &files) {
+    let _ = std::fs::remove_file(&archive_path);
+    return Err(e);
+  }
+  if let Err(e) = security::remove_file(board, secure) {
+    let _ = std::fs::remove_file(&archive_path);
+    return Err(e);
+  }
+  for artifact in &files[1..] {
+    if let Err(e) = security::remove_file(artifact, secure) {
+      log::warn!("{}", e);
+    }
+  }
+
+  let size = std::fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0);
+  Ok(ArchivedBoard {
+    id: model::new_id("archive"),
+    title: title.to_string(),
+    original_path: board.to_string_lossy().to_string(),
+    archive_path: archive_path.to_string_lossy().to_string(),
+    files: files.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+    archived_at: chrono::Utc::now(),
+    size,
+  })
+}
+
+fn write_archive(archive_path: &Path, files: &[&Path]) -> Result<(), String> {
+  use zip::{ZipWriter, write::FileOptions};
+
+  let file = std::fs::File::create(archive_path)
+    .map_err(|e| format!("Failed to create archive '{}': {}", archive_path.display(), e))?;
+  let mut zip = ZipWriter::new(file);
+  let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+  for path in files {
+    let data = std::fs::read(path)
+      .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    zip.start_file(entry_name(path)?, options)
+      .map_err(|e| format!("Failed to add '{}' to the archive: {}", path.display(), e))?;
+    zip.write_all(&data)
+      .map_err(|e| format!("Failed to write '{}' to the archive: {}", path.display(), e))?;
+  }
+  zip.finish()
+    .map_err(|e| format!("Failed to finalize archive '{}': {}", archive_path.display(), e))?;
+  Ok(())
+}
+
+// Where each archived file goes back to: its original place, or `destination`
+// when given
+pub fn restore_targets(board: &ArchivedBoard, destination: Option<&Path>) -> Result<Vec<(String, PathBuf)>, String> {
+  board.files.iter()
+    .map(|original| {
+      let original = Path::new(original);
+      let name = entry_name(original)?;
+      let target = match destination {
+        Some(dir) => dir.join(&name),
+        None => original.to_path_buf(),
+      };
+      Ok((name, target))
+    })
+    .collect()
+}
+
+// Extract an archived board to `targets` (from `restore_targets`, already
+// checked for write access) and delete the archive. Existing files are never
+// overwritten. Returns the board's restored path.
+pub fn unarchive_board(board: &ArchivedBoard, targets: &[(String, PathBuf)]) -> Result<PathBuf, String> {
+  if let Some((_, existing)) = targets.iter().find(|(_, target)| target.exists()) {
+    return Err(format!("Cannot unarchive: '{}' already exists", existing.display()));
+  }
+
+  let file = std::fs::File::open(&board.archive_path)
+    .map_err(|e| format!("Failed to open archive '{}': {}", board.archive_path, e))?;
+  let mut zip = zip::ZipArchive::new(file)
+    .map_err(|e| format!("Failed to read zip archive '{}': {}", board.archive_path, e))?;
+  archive::validate_archive(&mut zip).map_err(|e| e.to_string())?;
+
+  for (name, target) in targets {
+    let data = archive::read_entry_limited(&mut zip, name, archive::MAX_TOTAL_UNCOMPRESSED)
+      .map_err(|e| e.to_string())?;
+    if let Some(parent) = target.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create folder '{}': {}", parent.display(), e))?;
+    }
+    std::fs::write(target, data)
+      .map_err(|e| format!("Failed to write '{}': {}", target.display(), e))?;
+  }
+
+  if let Err(e) = std::fs::remove_file(&board.archive_path) {
+    log::warn!("Failed to delete archive '{}': {}", board.archive_path, e);
+  }
+  targets.first()
+    .map(|(_, target)| target.clone())
+    .ok_or_else(|| format!("Archive '{}' holds no board", board.archive_path))
+}
+
+// What a .fim container's metadata entry holds
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BoardMetadata {
+  pub title: Option<String>,
+  #[serde(default)]
+  pub tags: Vec<String>,
+  #[serde(rename = "noteCount")]
+  pub note_count: usize,
+  pub info: Option<model::DocumentInfo>,
+}
+
+// The board's `tags` property, as a list or comma-separated text
+fn document_tags(doc: &model::BoardDocument) -> Vec<String> {
+  let Some(value) = doc.properties.as_ref().and_then(|p| p.get("tags")) else {
+    return Vec::new();
+  };
+  let tags: Vec<String> = match value {
+    serde_json::Value::Array(items) => items.iter().map(properties::value_text).collect(),
+    other => properties::value_text(other).split(',').map(str::to_string).collect(),
+  };
+  tags.into_iter().map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect()
+}
+
+impl BoardMetadata {
+  pub fn from_document(doc: &model::BoardDocument) -> BoardMetadata {
+    BoardMetadata {
+      title: title::suggest_titles(doc).into_iter().next().map(|suggestion| suggestion.title),
+      tags: document_tags(doc),
+      note_count: doc.notes.len(),
+      info: doc.info.clone(),
+    }
+  }
+}
+
+// A board in the gallery
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryBoard {
+  pub path: String,
+  pub title: String,
+  pub tags: Vec<String>,
+  pub note_count: usize,
+  pub info: Option<model::DocumentInfo>,
+  pub modified: Option<chrono::DateTime<chrono::Utc>>,
+  pub size: u64,
+  pub thumbnail_available: bool,
+  // Set when the file couldn't be read; the other fields are then best effort
+  pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CachedBoard {
+  // File modification time (seconds since the epoch) and size the entry was read at
+  modified: u64,
+  size: u64,
+  board: LibraryBoard,
+}
+
+fn scan_cache_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(SCAN_CACHE_FILE))
+}
+
+fn load_scan_cache() -> BTreeMap<String, CachedBoard> {
+  scan_cache_path()
+    .and_then(|path| std::fs::read_to_string(path).ok())
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default()
+}
+
+fn save_scan_cache(cache: &BTreeMap<String, CachedBoard>) -> Result<(), String> {
+  let path = scan_cache_path().ok_or("Could not determine the app data directory")?;
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+  }
+  let json = serde_json::to_string(cache)
+    .map_err(|e| format!("Failed to serialize library cache: {}", e))?;
+  std::fs::write(&path, json)
+    .map_err(|e| format!("Failed to write library cache '{}': {}", path.display(), e))
+}
+
+// .fim files under `dir`, skipping hidden folders and symlinks
+fn find_boards(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return;
+  };
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let Ok(file_type) = entry.file_type() else {
+      continue;
+    };
+    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+    if file_type.is_dir() && !hidden && depth < MAX_SCAN_DEPTH {
+      find_boards(&path, depth + 1, found);
+    } else if file_type.is_file() && path.extension().is_some_and(|ext| ext == "fim") {
+      found.push(path);
+    }
+  }
+}
+
+// Metadata and whether there's a preview, reading as little as possible
+pub fn read_board_metadata(path: &Path) -> Result<(BoardMetadata, bool), String> {
+  // Its metadata is encrypted along with the rest
+  if encryption::is_encrypted(path) {
+    return Err(format!("'{}' is password-protected", path.file_name().unwrap_or_default().to_string_lossy()));
+  }
+  let file = std::fs::File::open(path)
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+  let mut zip = zip::ZipArchive::new(file)
+    .map_err(|e| format!("Failed to read zip file '{}': {}", path.display(), e))?;
+  archive::validate_archive(&mut zip)?;
+  let thumbnail = zip.by_name(preview::THUMBNAIL_ENTRY).is_ok() || zip.by_name(preview::PREVIEW_ENTRY).is_ok();
+
+  let metadata = match archive::read_entry_limited(&mut zip, METADATA_ENTRY, MAX_METADATA_SIZE) {
+    Ok(data) => serde_json::from_slice(&data)
+      .map_err(|e| format!("Invalid JSON format in {}: {}", METADATA_ENTRY, e))?,
+    Err(archive::ArchiveError::MissingEntry { .. }) => {
+      let data = archive::read_entry_limited(&mut zip, "board.json", archive::MAX_BOARD_JSON_SIZE)?;
+      let doc: model::BoardDocument = serde_json::from_slice(&data)
+        .map_err(|e| format!("Invalid JSON format in board.json: {}", e))?;
+      BoardMetadata::from_document(&doc)
+    },
+    Err(e) => return Err(e.to_string()),
+  };
+  Ok((metadata, thumbnail))
+}
+
+fn library_board(path: &Path, modified: Option<chrono::DateTime<chrono::Utc>>, size: u64) -> LibraryBoard {
+  let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+  let (metadata, thumbnail, error) = match read_board_metadata(path) {
+    Ok((metadata, thumbnail)) => (Some(metadata), thumbnail, None),
+    Err(e) => (None, false, Some(e)),
+  };
+  LibraryBoard {
+    path: path.to_string_lossy().to_string(),
+    title: metadata.as_ref().and_then(|m| m.title.clone()).unwrap_or(stem),
+    tags: metadata.as_ref().map(|m| m.tags.clone()).unwrap_or_default(),
+    note_count: metadata.as_ref().map_or(0, |m| m.note_count),
+    info: metadata.as_ref().and_then(|m| m.info.clone()),
+    modified,
+    size,
+    thumbnail_available: thumbnail,
+    error,
+  }
+}
+
+// Every board under `roots`, most recently modified first
+pub fn scan(roots: &[PathBuf]) -> Vec<LibraryBoard> {
+  let mut paths = Vec::new();
+  for root in roots {
+    find_boards(root, 0, &mut paths);
+  }
+  paths.sort();
+  paths.dedup();
+
+  let previous = load_scan_cache();
+  let mut boards = Vec::with_capacity(paths.len());
+  let mut fresh = BTreeMap::new();
+  for path in paths {
+    let Ok(file_metadata) = std::fs::metadata(&path) else {
+      continue;
+    };
+    let modified_time = file_metadata.modified().ok();
+    let modified_secs = modified_time
+      .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+      .map_or(0, |d| d.as_secs());
+    let key = path.to_string_lossy().to_string();
+    let board = match previous.get(&key) {
+      Some(cached) if cached.modified == modified_secs && cached.size == file_metadata.len() => cached.board.clone(),
+      _ => library_board(&path, modified_time.map(chrono::DateTime::from), file_metadata.len()),
+    };
+    fresh.insert(key, CachedBoard { modified: modified_secs, size: file_metadata.len(), board: board.clone() });
+    boards.push(board);
+  }
+
+  // Boards that were under the scanned folders but are gone now drop out
+  let mut cache: BTreeMap<String, CachedBoard> = previous.into_iter()
+    .filter(|(path, _)| !roots.iter().any(|root| Path::new(path).starts_with(root)))
+    .collect();
+  cache.extend(fresh);
+  if let Err(e) = save_scan_cache(&cache) {
+    log::warn!("{}", e);
+  }
+  boards.sort_by_key(|board| std::cmp::Reverse(board.modified));
+  boards
+}
+
+// Boards from the last scans, without touching the disk beyond the cache
+pub fn cached_boards() -> Vec<LibraryBoard> {
+  let mut boards: Vec<LibraryBoard> = load_scan_cache().into_values().map(|cached| cached.board).collect();
+  boards.sort_by_key(|board| std::cmp::Reverse(board.modified));
+  boards
+}
@@ -0,0 +1,159 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::{links, model};
+
+// Checking a board's web links, so links that stopped working can be found
+// before someone clicks them. Each http(s) URL on the notes is asked for once
+// with a HEAD request (a GET when the server doesn't take HEAD), a few at a
+// time and with short timeouts. Only a 404 or 410 or a host that doesn't
+// exist counts as dead: servers that refuse automated requests are reported
+// as blocked, and other failures as errors, since the link may work in a
+// browser. Internal `note://` links are checked against the board instead.
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(4);
+const TIMEOUT: Duration = Duration::from_secs(8);
+const MAX_REDIRECTS: u32 = 5;
+const WORKERS: usize = 8;
+// Boards with more links are checked in part
+const MAX_LINKS: usize = 300;
+const USER_AGENT: &str = concat!("FIM/", env!("CARGO_PKG_VERSION"), " (link check)");
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LinkStatus {
+  pub url: String,
+  // Notes with the link, in document order
+  pub note_ids: Vec<model::ID>,
+  // ok, dead, blocked, timeout or error
+  pub status: String,
+  pub http_status: Option<u16>,
+  // Where the link ends up, when it redirects elsewhere
+  pub redirected_to: Option<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LinkValidation {
+  pub links: Vec<LinkStatus>,
+  // Links of `links` that are dead; none when offline
+  pub dead: usize,
+  // (source note ID, missing target ID) for links to notes that are gone
+  pub broken_note_links: Vec<(model::ID, model::ID)>,
+  // Links not checked: past the limit, or not http(s) (mailto: and such)
+  pub skipped: usize,
+  // No link could be reached at all, which usually means no network rather
+  // than every link being dead
+  pub offline: bool,
+}
+
+// Web links of the board's notes with the notes they're on, in document order
+fn web_links(doc: &model::BoardDocument) -> (Vec<(String, Vec<model::ID>)>, usize) {
+  let mut found: Vec<(String, Vec<model::ID>)> = Vec::new();
+  let mut index: HashMap<String, usize> = HashMap::new();
+  let mut other = 0;
+  for note in &doc.notes {
+    for link in links::note_links(note).filter(|link| links::note_link_target(link).is_none()) {
+      let url = link.trim();
+      let web = tauri::Url::parse(url).map(|url| matches!(url.scheme(), "http" | "https")).unwrap_or(false);
+      if !web {
+        other += 1;
+        continue;
+      }
+      let position = *index.entry(url.to_string()).or_insert_with(|| {
+        found.push((url.to_string(), Vec::new()));
+        found.len() - 1
+      });
+      let note_ids = &mut found[position].1;
+      if !note_ids.contains(&note.id) {
+        note_ids.push(note.id.clone());
+      }
+    }
+  }
+  (found, other)
+}
+
+fn check(agent: &ureq::Agent, url: &str, note_ids: Vec<model::ID>) -> LinkStatus {
+  let response = match agent.head(url).call() {
+    // Some servers don't answer HEAD, or refuse it where they'd serve a page
+    Err(ureq::Error::Status(403 | 405 | 501, _)) => agent.get(url).call(),
+    response => response,
+  };
+  let (status, http_status, redirected_to, error) = match response {
+    Ok(response) => {
+      // Compared parsed, so https://example.com isn't "redirected" to https://example.com/
+      let final_url = response.get_url().to_string();
+      let moved = tauri::Url::parse(url).map(|url| url.as_str() != final_url).unwrap_or(false);
+      ("ok", Some(response.status()), moved.then_some(final_url), None)
+    },
+    Err(ureq::Error::Status(code, _)) => {
+      let status = match code {
+        404 | 410 => "dead",
+        401 | 403 | 407 | 429 => "blocked",
+        _ => "error",
+      };
+      (status, Some(code), None, Some(format!("HTTP {}", code)))
+    },
+    Err(ureq::Error::Transport(transport)) => {
+      let message = transport.to_string();
+      let status = match transport.kind() {
+        ureq::ErrorKind::Dns => "dead",
+        _ if message.contains("timed out") => "timeout",
+        _ => "error",
+      };
+      (status, None, None, Some(message))
+    },
+  };
+  LinkStatus {
+    url: url.to_string(),
+    note_ids,
+    status: status.to_string(),
+    http_status,
+    redirected_to,
+    error,
+  }
+}
+
+pub fn validate(doc: &model::BoardDocument) -> LinkValidation {
+  let (mut found, other) = web_links(doc);
+  let skipped = other + found.len().saturating_sub(MAX_LINKS);
+  found.truncate(MAX_LINKS);
+
+  let agent = ureq::AgentBuilder::new()
+    .timeout_connect(CONNECT_TIMEOUT)
+    .timeout(TIMEOUT)
+    .redirects(MAX_REDIRECTS)
+    .user_agent(USER_AGENT)
+    .build();
+  // Workers take the next unchecked link until none are left
+  let next = AtomicUsize::new(0);
+  let mut checked: Vec<(usize, LinkStatus)> = std::thread::scope(|scope| {
+    let workers: Vec<_> = (0..WORKERS.min(found.len()))
+      .map(|_| scope.spawn(|| {
+        let mut done = Vec::new();
+        loop {
+          let i = next.fetch_add(1, Ordering::Relaxed);
+          let Some((url, note_ids)) = found.get(i) else { break };
+          done.push((i, check(&agent, url, note_ids.clone())));
+        }
+        done
+      }))
+      .collect();
+    workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+  });
+  checked.sort_by_key(|(i, _)| *i);
+  let checked: Vec<LinkStatus> = checked.into_iter().map(|(_, status)| status).collect();
+
+  let offline = !checked.is_empty() && checked.iter().all(|link| link.http_status.is_none());
+  LinkValidation {
+    dead: match offline {
+      true => 0,
+      false => checked.iter().filter(|link| link.status == "dead").count(),
+    },
+    offline,
+    links: checked,
+    broken_note_links: links::broken_note_links(doc),
+    skipped,
+  }
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use crate::model;
+
+// Internal links between notes are stored alongside URLs in `Note.links` (and
+// in text span links) as `note://<id>`. `fim://note/<id>`, the form the app's
+// link scheme gives them when copied elsewhere, is read the same way.
+// Exporters turn internal links into cross-references to the target note.
+pub const NOTE_LINK_SCHEME: &str = "note://";
+const FIM_NOTE_LINK_PREFIX: &str = "fim://note/";
+
+pub fn note_link_target(link: &str) -> Option<&str> {
+  link.strip_prefix(NOTE_LINK_SCHEME)
+    .or_else(|| link.strip_prefix(FIM_NOTE_LINK_PREFIX))
+    .map(|id| id.trim_end_matches('/'))
+    .filter(|id| !id.is_empty())
+}
+
+// Element ID used for a note in HTML exports
+pub fn anchor_id(note_id: &str) -> String {
+  format!("note-{}", note_id)
+}
+
+// Where a link points in an exported HTML document
+pub fn href(link: &str) -> String {
+  match note_link_target(link) {
+    Some(id) => format!("#{}", anchor_id(id)),
+    None => link.to_string(),
+  }
+}
+
+// Every link on a note: `links` entries plus links in its formatted text
+pub fn note_links(note: &model::Note) -> impl Iterator<Item = &str> {
+  note.links.iter().flatten().map(|l| l.as_str())
+    .chain(note.spans.iter().flatten().filter_map(|s| s.link.as_deref()))
+}
+
+// Target note ID → notes linking to it, in document order without duplicates
+pub fn backlink_index(doc: &model::BoardDocument) -> BTreeMap<model::ID, Vec<model::ID>> {
+  let mut index: BTreeMap<model::ID, Vec<model::ID>> = BTreeMap::new();
+  for note in &doc.notes {
+    for target in note_links(note).filter_map(note_link_target) {
+      let sources = index.entry(target.to_string()).or_default();
+      if !sources.contains(&note.id) {
+        sources.push(note.id.clone());
+      }
+    }
+  }
+  index
+}
+
+// (source note ID, missing target ID) for internal links to notes that don't exist
+pub fn broken_note_links(doc: &model::BoardDocument) -> Vec<(model::ID, model::ID)> {
+  let mut broken = Vec::new();
+  for note in &doc.notes {
+    for target in note_links(note).filter_map(note_link_target) {
+      let exists = doc.notes.iter().any(|n| n.id == target);
+      let entry = (note.id.clone(), target.to_string());
+      if !exists && !broken.contains(&entry) {
+        broken.push(entry);
+      }
+    }
+  }
+  broken
+}
@@ -0,0 +1,369 @@
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+
+// Language and time zone of exported text: section headers, date formats and
+// punctuation follow the locale given with the export, else the `exportLocale`
+// and `exportTimezone` settings, else the system's. Languages we have no
+// strings for fall back to English. Machine-readable dates (OPML's
+// `dateCreated`, email headers) stay in their standard formats.
+
+#[derive(Debug)]
+pub struct Strings {
+  pub title: &'static str,
+  pub highlights: &'static str,
+  pub notes: &'static str,
+  pub connections: &'static str,
+  pub stacks: &'static str,
+  pub statistics: &'static str,
+  pub faded: &'static str,
+  pub label: &'static str,
+  pub tags: &'static str,
+  pub style: &'static str,
+  pub arrows: &'static str,
+  pub untyped: &'static str,
+  pub stack: &'static str,
+  pub generated: &'static str,
+  pub author: &'static str,
+  pub created: &'static str,
+  pub modified: &'static str,
+  pub ordering: &'static str,
+  pub untitled_board: &'static str,
+  // Singular and plural
+  note: (&'static str, &'static str),
+  connection: (&'static str, &'static str),
+  quotes: (&'static str, &'static str),
+  // French sets a narrow no-break space before the colon
+  colon: &'static str,
+  date: &'static str,
+  time: &'static str,
+}
+
+const ENGLISH: Strings = Strings {
+  title: "Freeform Idea Map Export",
+  highlights: "Highlights",
+  notes: "Notes",
+  connections: "Connections",
+  stacks: "Stacks",
+  statistics: "Statistics",
+  faded: "faded",
+  label: "Label",
+  tags: "Tags",
+  style: "Style",
+  arrows: "Arrows",
+  untyped: "Untyped",
+  stack: "Stack",
+  generated: "Generated",
+  author: "Author",
+  created: "Created",
+  modified: "Modified",
+  ordering: "Ordering",
+  untitled_board: "Untitled board",
+  note: ("note", "notes"),
+  connection: ("connection", "connections"),
+  quotes: ("\"", "\""),
+  colon: ":",
+  date: "%Y-%m-%d",
+  time: "%H:%M",
+};
+
+const GERMAN: Strings = Strings {
+  title: "Freeform-Ideenkarte – Export",
+  highlights: "Hervorhebungen",
+  notes: "Notizen",
+  connections: "Verbindungen",
+  stacks: "Stapel",
+  statistics: "Statistik",
+  faded: "verblasst",
+  label: "Beschriftung",
+  tags: "Schlagwörter",
+  style: "Stil",
+  arrows: "Pfeile",
+  untyped: "Ohne Typ",
+  stack: "Stapel",
+  generated: "Erstellt",
+  author: "Autor",
+  created: "Angelegt",
+  modified: "Geändert",
+  ordering: "Reihenfolge",
+  untitled_board: "Unbenanntes Board",
+  note: ("Notiz", "Notizen"),
+  connection: ("Verbindung", "Verbindungen"),
+  quotes: ("„", "“"),
+  colon: ":",
+  date: "%d.%m.%Y",
+  time: "%H:%M",
+};
+
+const FRENCH: Strings = Strings {
+  title: "Export de carte d’idées Freeform",
+  highlights: "Points forts",
+  notes: "Notes",
+  connections: "Connexions",
+  stacks: "Piles",
+  statistics: "Statistiques",
+  faded: "estompée",
+  label: "Libellé",
+  tags: "Mots-clés",
+  style: "Style",
+  arrows: "Flèches",
+  untyped: "Sans type",
+  stack: "Pile",
+  generated: "Généré le",
+  author: "Auteur",
+  created: "Créé",
+  modified: "Modifié",
+  ordering: "Ordre",
+  untitled_board: "Tableau sans titre",
+  note: ("note", "notes"),
+  connection: ("connexion", "connexions"),
+  quotes: ("«\u{a0}", "\u{a0}»"),
+  colon: "\u{202f}:",
+  date: "%d/%m/%Y",
+  time: "%H:%M",
+};
+
+const SPANISH: Strings = Strings {
+  title: "Exportación de mapa de ideas Freeform",
+  highlights: "Destacados",
+  notes: "Notas",
+  connections: "Conexiones",
+  stacks: "Pilas",
+  statistics: "Estadísticas",
+  faded: "atenuada",
+  label: "Etiqueta",
+  tags: "Etiquetas",
+  style: "Estilo",
+  arrows: "Flechas",
+  untyped: "Sin tipo",
+  stack: "Pila",
+  generated: "Generado",
+  author: "Autor",
+  created: "Creado",
+  modified: "Modificado",
+  ordering: "Orden",
+  untitled_board: "Tablero sin título",
+  note: ("nota", "notas"),
+  connection: ("conexión", "conexiones"),
+  quotes: ("«", "»"),
+  colon: ":",
+  date: "%d/%m/%Y",
+  time: "%H:%M",
+};
+
+const ITALIAN: Strings = Strings {
+  title: "Esportazione mappa di idee Freeform",
+  highlights: "In evidenza",
+  notes: "Note",
+  connections: "Collegamenti",
+  stacks: "Pile",
+  statistics: "Statistiche",
+  faded: "attenuata",
+  label: "Etichetta",
+  tags: "Tag",
+  style: "Stile",
+  arrows: "Frecce",
+  untyped: "Senza tipo",
+  stack: "Pila",
+  generated: "Generato",
+  author: "Autore",
+  created: "Creato",
+  modified: "Modificato",
+  ordering: "Ordine",
+  untitled_board: "Lavagna senza titolo",
+  note: ("nota", "note"),
+  connection: ("collegamento", "collegamenti"),
+  quotes: ("«", "»"),
+  colon: ":",
+  date: "%d/%m/%Y",
+  time: "%H:%M",
+};
+
+const PORTUGUESE: Strings = Strings {
+  title: "Exportação de mapa de ideias Freeform",
+  highlights: "Destaques",
+  notes: "Notas",
+  connections: "Conexões",
+  stacks: "Pilhas",
+  statistics: "Estatísticas",
+  faded: "esmaecida",
+  label: "Rótulo",
+  tags: "Tags",
+  style: "Estilo",
+  arrows: "Setas",
+  untyped: "Sem tipo",
+  stack: "Pilha",
+  generated: "Gerado em",
+  author: "Autor",
+  created: "Criado",
+  modified: "Modificado",
+  ordering: "Ordenação",
+  untitled_board: "Quadro sem título",
+  note: ("nota", "notas"),
+  connection: ("conexão", "conexões"),
+  quotes: ("“", "”"),
+  colon: ":",
+  date: "%d/%m/%Y",
+  time: "%H:%M",
+};
+
+const DUTCH: Strings = Strings {
+  title: "Freeform-ideeënkaart export",
+  highlights: "Uitgelicht",
+  notes: "Notities",
+  connections: "Verbindingen",
+  stacks: "Stapels",
+  statistics: "Statistieken",
+  faded: "vervaagd",
+  label: "Label",
+  tags: "Tags",
+  style: "Stijl",
+  arrows: "Pijlen",
+  untyped: "Zonder type",
+  stack: "Stapel",
+  generated: "Gegenereerd",
+  author: "Auteur",
+  created: "Gemaakt",
+  modified: "Gewijzigd",
+  ordering: "Volgorde",
+  untitled_board: "Naamloos bord",
+  note: ("notitie", "notities"),
+  connection: ("verbinding", "verbindingen"),
+  quotes: ("“", "”"),
+  colon: ":",
+  date: "%d-%m-%Y",
+  time: "%H:%M",
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Zone {
+  Local,
+  Fixed(FixedOffset),
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportLocale {
+  pub strings: &'static Strings,
+  date: &'static str,
+  time: &'static str,
+  zone: Zone,
+}
+
+// "+02:00", "-0530", "+2", optionally after "UTC" or "GMT"
+fn parse_offset(timezone: &str) -> Option<FixedOffset> {
+  let offset = timezone.strip_prefix("UTC").or_else(|| timezone.strip_prefix("GMT")).unwrap_or(timezone).trim();
+  let sign = match offset.chars().next()? {
+    '+' => 1,
+    '-' => -1,
+    _ => return None,
+  };
+  let digits: String = offset[1..].chars().filter(|c| *c != ':').collect();
+  if digits.is_empty() || digits.len() > 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  let (hours, minutes) = match digits.len() {
+    1 | 2 => (digits.as_str(), "0"),
+    _ => digits.split_at(digits.len() - 2),
+  };
+  let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+  if hours > 23 || minutes > 59 {
+    return None;
+  }
+  FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+fn parse_zone(timezone: &str) -> Result<Zone, String> {
+  match timezone.trim() {
+    "" | "local" => Ok(Zone::Local),
+    "UTC" | "utc" | "Z" | "GMT" => Ok(Zone::Fixed(FixedOffset::east_opt(0).ok_or("Invalid UTC offset")?)),
+    other => parse_offset(other).map(Zone::Fixed)
+      .ok_or_else(|| format!("Invalid timezone '{}'. Must be one of: local, UTC, or an offset such as +02:00", other)),
+  }
+}
+
+// "UTC" or "UTC+02:00"
+fn zone_label(offset: FixedOffset) -> String {
+  match offset.local_minus_utc() {
+    0 => "UTC".to_string(),
+    seconds => format!("UTC{}{:02}:{:02}", if seconds < 0 { '-' } else { '+' }, seconds.abs() / 3600, seconds.abs() % 3600 / 60),
+  }
+}
+
+impl ExportLocale {
+  // `locale` is a tag such as "de", "pt-BR" or "fr_CA.UTF-8", empty for the
+  // system's; `timezone` is "local" (or empty), "UTC" or an offset
+  pub fn new(locale: &str, timezone: &str) -> Result<ExportLocale, String> {
+    let tag = match locale.trim() {
+      "" => sys_locale::get_locale().unwrap_or_default(),
+      tag => tag.to_string(),
+    };
+    let mut parts = tag.split(['-', '_', '.', '@']);
+    let language = parts.next().unwrap_or("").to_lowercase();
+    let region = parts.next().unwrap_or("").to_uppercase();
+    let strings = match language.as_str() {
+      "de" => &GERMAN,
+      "fr" => &FRENCH,
+      "es" => &SPANISH,
+      "it" => &ITALIAN,
+      "pt" => &PORTUGUESE,
+      "nl" => &DUTCH,
+      _ => &ENGLISH,
+    };
+    // English dates depend on the region; ISO dates when there is none
+    let (date, time) = match (language.as_str(), region.as_str()) {
+      ("en", "US") => ("%m/%d/%Y", "%-I:%M %p"),
+      ("en", "") => (ENGLISH.date, ENGLISH.time),
+      ("en", "CA") => ("%Y-%m-%d", "%H:%M"),
+      ("en", _) => ("%d/%m/%Y", "%H:%M"),
+      _ => (strings.date, strings.time),
+    };
+    Ok(ExportLocale { strings, date, time, zone: parse_zone(timezone)? })
+  }
+
+  fn in_zone(&self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+    let offset = match self.zone {
+      Zone::Local => at.with_timezone(&chrono::Local).offset().fix(),
+      Zone::Fixed(offset) => offset,
+    };
+    at.with_timezone(&offset)
+  }
+
+  // Date and time with the zone, e.g. "16.10.2026 14:05 UTC+02:00"
+  pub fn datetime(&self, at: DateTime<Utc>) -> String {
+    let local = self.in_zone(at);
+    format!("{} {} {}", local.format(self.date), local.format(self.time), zone_label(*local.offset()))
+  }
+
+  pub fn date(&self, at: DateTime<Utc>) -> String {
+    self.in_zone(at).format(self.date).to_string()
+  }
+
+  // `text` followed by the locale's colon
+  pub fn colon(&self, text: &str) -> String {
+    format!("{}{}", text, self.strings.colon)
+  }
+
+  // "Label: value"
+  pub fn field(&self, name: &str, value: &str) -> String {
+    format!("{} {}", self.colon(name), value)
+  }
+
+  // A section header in plain text, e.g. "NOTES:"
+  pub fn heading(&self, name: &str) -> String {
+    self.colon(&name.to_uppercase())
+  }
+
+  pub fn quote(&self, text: &str) -> String {
+    format!("{}{}{}", self.strings.quotes.0, text, self.strings.quotes.1)
+  }
+
+  pub fn notes(&self, count: usize) -> String {
+    plural(count, self.strings.note)
+  }
+
+  pub fn connections(&self, count: usize) -> String {
+    plural(count, self.strings.connection)
+  }
+}
+
+fn plural(count: usize, (one, many): (&str, &str)) -> String {
+  format!("{} {}", count, if count == 1 { one } else { many })
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+
+use crate::settings;
+
+// Diagnostics for bug reports. Every command runs in a span named after it,
+// and the span's close event records how long it took; loading and saving
+// add the board's size. Messages from the `log` macros end up here too. Logs
+// go to daily files in `logs/` in app data, of which the newest `KEEP_FILES`
+// are kept, and in debug builds to stderr as well. Note text never goes into
+// the log, only counts, sizes and paths.
+
+const LOGS_DIR: &str = "logs";
+const FILE_PREFIX: &str = "fim";
+const FILE_SUFFIX: &str = "log";
+const KEEP_FILES: usize = 7;
+const DEFAULT_RECENT_LINES: usize = 500;
+const MAX_RECENT_LINES: usize = 5000;
+
+// Flushes buffered lines when dropped, so it lives as long as the process
+static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecentLogs {
+  // Folder holding the log files, for attaching them whole
+  pub directory: String,
+  // Oldest first
+  pub lines: Vec<String>,
+}
+
+pub fn logs_dir() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(LOGS_DIR))
+}
+
+// Install the subscriber; logging to the file is skipped if its folder can't
+// be created, and nothing is logged if another subscriber is installed
+pub fn init() -> Result<(), String> {
+  let level = if cfg!(debug_assertions) { LevelFilter::DEBUG } else { LevelFilter::INFO };
+  let stderr = cfg!(debug_assertions).then(|| {
+    tracing_subscriber::fmt::layer()
+      .with_writer(std::io::stderr)
+      .with_span_events(FmtSpan::CLOSE)
+  });
+
+  let appender = logs_dir()
+    .ok_or_else(|| "Could not determine the logs folder".to_string())
+    .and_then(|dir| file_appender(&dir));
+  let (file, error) = match appender {
+    Ok(appender) => {
+      let (writer, guard) = tracing_appender::non_blocking(appender);
+      let _ = GUARD.set(guard);
+      let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE);
+      (Some(layer), None)
+    },
+    Err(e) => (None, Some(e)),
+  };
+
+  tracing_subscriber::registry()
+    .with(level)
+    .with(stderr)
+    .with(file)
+    .try_init()
+    .map_err(|e| format!("Failed to set up logging: {}", e))?;
+  match error {
+    Some(e) => Err(e),
+    None => Ok(()),
+  }
+}
+
+fn file_appender(dir: &Path) -> Result<RollingFileAppender, String> {
+  std::fs::create_dir_all(dir)
+    .map_err(|e| format!("Failed to create logs folder '{}': {}", dir.display(), e))?;
+  RollingFileAppender::builder()
+    .rotation(Rotation::DAILY)
+    .filename_prefix(FILE_PREFIX)
+    .filename_suffix(FILE_SUFFIX)
+    .max_log_files(KEEP_FILES)
+    .build(dir)
+    .map_err(|e| format!("Failed to open log file in '{}': {}", dir.display(), e))
+}
+
+// Log files, oldest first; their names end in the date, so that is name order
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+  let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+    .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+    .unwrap_or_default();
+  files.retain(|path| {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    path.is_file() && name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX)
+  });
+  files.sort();
+  files
+}
+
+// The last `limit` lines across the log files
+pub fn recent(limit: Option<usize>) -> Result<RecentLogs, String> {
+  let dir = logs_dir().ok_or("Could not determine the logs folder")?;
+  let limit = limit.unwrap_or(DEFAULT_RECENT_LINES).clamp(1, MAX_RECENT_LINES);
+  let mut lines: Vec<String> = Vec::new();
+  for file in log_files(&dir).iter().rev() {
+    let content = std::fs::read(file)
+      .map_err(|e| format!("Failed to read log file '{}': {}", file.display(), e))?;
+    let content = String::from_utf8_lossy(&content);
+    let mut older: Vec<String> = content.lines().rev().take(limit - lines.len()).map(str::to_string).collect();
+    older.reverse();
+    older.append(&mut lines);
+    lines = older;
+    if lines.len() >= limit {
+      break;
+    }
+  }
+  Ok(RecentLogs { directory: dir.to_string_lossy().to_string(), lines })
+}
@@ -2,5 +2,9 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+  let args: Vec<String> = std::env::args_os().skip(1).map(|arg| arg.to_string_lossy().to_string()).collect();
+  if let Some(code) = app_lib::run_cli(&args) {
+    std::process::exit(code);
+  }
   app_lib::run();
 }
@@ -0,0 +1,161 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+use crate::model;
+
+// Markdown content mode for notes (`contentFormat: "markdown"`). The backend
+// owns the conversion so exports and the webview's HTML renderer agree on
+// what a note looks like.
+
+fn parser_options() -> Options {
+  Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS
+}
+
+// Link targets that could run script inside the webview are dropped
+fn is_safe_url(url: &str) -> bool {
+  let scheme = url.split(':').next().unwrap_or("").trim().to_ascii_lowercase();
+  !matches!(scheme.as_str(), "javascript" | "vbscript" | "data")
+}
+
+// Raw HTML is shown as text rather than injected into the page
+fn sanitize(event: Event<'_>) -> Event<'_> {
+  match event {
+    Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+    Event::Start(Tag::Link { link_type, dest_url, title, id }) if !is_safe_url(&dest_url) => {
+      Event::Start(Tag::Link { link_type, dest_url: "".into(), title, id })
+    },
+    other => other,
+  }
+}
+
+pub fn to_html(markdown: &str) -> String {
+  let parser = Parser::new_ext(markdown, parser_options()).map(sanitize);
+  let mut html = String::new();
+  pulldown_cmark::html::push_html(&mut html, parser);
+  html
+}
+
+fn utf16_len(text: &str) -> usize {
+  text.chars().map(char::len_utf16).sum()
+}
+
+// Flatten Markdown into plain text plus formatting spans, for exporters that
+// have their own notion of rich text (RTF, plain text). Headings become bold,
+// list items get bullets and block elements are separated by line breaks.
+pub fn to_spans(markdown: &str) -> (String, Vec<model::TextSpan>) {
+  let mut text = String::new();
+  let mut spans = Vec::new();
+  // Open inline formatting: (UTF-16 start offset, span template)
+  let mut open: Vec<(usize, model::TextSpan)> = Vec::new();
+  let mut list_numbers: Vec<Option<u64>> = Vec::new();
+
+  let span = |bold, italic, strike, link: Option<String>| model::TextSpan {
+    start: 0,
+    end: 0,
+    bold,
+    italic,
+    underline: None,
+    strike,
+    color: None,
+    link,
+  };
+  let start_block = |text: &mut String| {
+    if !text.is_empty() && !text.ends_with('\n') {
+      text.push('\n');
+    }
+  };
+
+  for event in Parser::new_ext(markdown, parser_options()).map(sanitize) {
+    match event {
+      Event::Start(tag) => match tag {
+        Tag::Paragraph | Tag::CodeBlock(_) | Tag::BlockQuote(_) | Tag::HtmlBlock => start_block(&mut text),
+        Tag::Heading { .. } => {
+          start_block(&mut text);
+          open.push((utf16_len(&text), span(Some(true), None, None, None)));
+        },
+        Tag::List(first) => {
+          start_block(&mut text);
+          list_numbers.push(first);
+        },
+        Tag::Item => {
+          start_block(&mut text);
+          text += &"  ".repeat(list_numbers.len().saturating_sub(1));
+          match list_numbers.last_mut() {
+            Some(Some(number)) => {
+              text += &format!("{}. ", number);
+              *number += 1;
+            },
+            _ => text += "• ",
+          }
+        },
+        Tag::Strong => open.push((utf16_len(&text), span(Some(true), None, None, None))),
+        Tag::Emphasis => open.push((utf16_len(&text), span(None, Some(true), None, None))),
+        Tag::Strikethrough => open.push((utf16_len(&text), span(None, None, Some(true), None))),
+        Tag::Link { dest_url, .. } => {
+          let link = (!dest_url.is_empty()).then(|| dest_url.to_string());
+          open.push((utf16_len(&text), span(None, None, None, link)));
+        },
+        Tag::TableRow | Tag::TableHead => start_block(&mut text),
+        Tag::TableCell if !text.is_empty() && !text.ends_with('\n') => text.push('\t'),
+        _ => {},
+      },
+      Event::End(tag) => match tag {
+        TagEnd::Heading(_) | TagEnd::Strong | TagEnd::Emphasis | TagEnd::Strikethrough | TagEnd::Link => {
+          if let Some((start, mut span)) = open.pop() {
+            span.start = start;
+            span.end = utf16_len(&text);
+            // Links with an unsafe target keep their text but lose the span
+            let formatted = span.link.is_some() || span.bold.is_some() || span.italic.is_some() || span.strike.is_some();
+            if span.end > span.start && formatted {
+              spans.push(span);
+            }
+          }
+          if matches!(tag, TagEnd::Heading(_)) {
+            text.push('\n');
+          }
+        },
+        TagEnd::Paragraph => text.push('\n'),
+        TagEnd::List(_) => { list_numbers.pop(); },
+        _ => {},
+      },
+      Event::Text(t) | Event::Code(t) | Event::InlineMath(t) | Event::DisplayMath(t) => text += &t,
+      Event::TaskListMarker(done) => text += if done { "[x] " } else { "[ ] " },
+      Event::SoftBreak => text.push(' '),
+      Event::HardBreak => text.push('\n'),
+      Event::Rule => {
+        start_block(&mut text);
+        text += "———\n";
+      },
+      _ => {},
+    }
+  }
+
+  let trimmed = text.trim_end().to_string();
+  let length = utf16_len(&trimmed);
+  for span in &mut spans {
+    span.end = span.end.min(length);
+  }
+  spans.retain(|s| s.start < s.end);
+  (trimmed, spans)
+}
+
+// Heuristic used by importers: does pasted/captured text use Markdown syntax
+// beyond what plain prose would contain?
+pub fn looks_like_markdown(text: &str) -> bool {
+  let block_syntax = text.lines().any(|line| {
+    let line = line.trim_start();
+    line.starts_with("# ") || line.starts_with("## ") || line.starts_with("### ")
+      || line.starts_with("> ") || line.starts_with("```")
+      || (line.starts_with('|') && line.ends_with('|') && line.len() > 1)
+  });
+  let inline_syntax = ["**", "__", "~~", "]("].iter().any(|marker| text.contains(marker));
+  block_syntax || inline_syntax
+}
+
+// Plain text and spans for a note, converting Markdown notes on the fly
+pub fn note_rich_text(note: &model::Note) -> (String, Vec<model::TextSpan>) {
+  if note.is_markdown() {
+    to_spans(&note.text)
+  } else {
+    (note.text.clone(), note.spans.clone().unwrap_or_default())
+  }
+}
@@ -0,0 +1,400 @@
+
+use base64::Engine;
+use lopdf::{dictionary, Dictionary, Object, Stream};
+use resvg::{tiny_skia, usvg};
+
+use crate::{fonts, locale, media, qr};
+
+// Watermarks, header/footer lines and QR codes stamped onto PNG and PDF
+// exports after they are rendered, so boards marked confidential carry the
+// marking no matter which renderer produced the pixels or pages.
+
+const DEFAULT_WATERMARK_OPACITY: f64 = 0.15;
+// PDF header/footer font size and distance from the page edge, in points
+const PDF_LINE_SIZE: f64 = 9.0;
+const PDF_LINE_INSET: f64 = 18.0;
+// Side of the QR code on PDF pages, in points (about an inch)
+const PDF_QR_SIZE: f64 = 72.0;
+
+#[derive(serde::Deserialize, Debug, Clone, Default)]
+pub struct ExportMarkings {
+  // Substituted for `{title}`; usually the document name
+  pub title: Option<String>,
+  // Header/footer templates; `{title}`, `{date}`, `{page}` and `{pages}` are replaced
+  pub header: Option<String>,
+  pub footer: Option<String>,
+  // Drawn diagonally across the middle of every page
+  pub watermark_text: Option<String>,
+  // Base64 PNG drawn centered behind the watermark text
+  pub watermark_image: Option<String>,
+  // 0..1, for both watermark text and image
+  pub watermark_opacity: Option<f64>,
+  // Encoded as a QR code in the bottom-right corner, usually a fim:// link
+  // back to the board or a note
+  pub qr_code: Option<String>,
+  // Language and time zone of `{date}` and the untitled fallback; filled in
+  // from settings when not given. See locale.rs
+  pub locale: Option<String>,
+  pub timezone: Option<String>,
+}
+
+impl ExportMarkings {
+  pub fn is_empty(&self) -> bool {
+    let blank = |value: &Option<String>| value.as_deref().map_or(true, |v| v.trim().is_empty());
+    blank(&self.header) && blank(&self.footer) && blank(&self.watermark_text) && blank(&self.watermark_image)
+      && blank(&self.qr_code)
+  }
+
+  // With `locale` and `timezone` defaulting to the given ones
+  pub fn localized(mut self, locale: &str, timezone: &str) -> Result<ExportMarkings, String> {
+    let locale = self.locale.get_or_insert_with(|| locale.to_string());
+    let timezone = self.timezone.get_or_insert_with(|| timezone.to_string());
+    locale::ExportLocale::new(locale, timezone)?;
+    Ok(self)
+  }
+
+  fn opacity(&self) -> f64 {
+    self.watermark_opacity.unwrap_or(DEFAULT_WATERMARK_OPACITY).clamp(0.0, 1.0)
+  }
+
+  fn line(&self, template: &Option<String>, page: usize, pages: usize) -> Option<String> {
+    let template = template.as_deref().filter(|t| !t.trim().is_empty())?;
+    // Checked by `localized`
+    let locale = locale::ExportLocale::new(self.locale.as_deref().unwrap_or(""), self.timezone.as_deref().unwrap_or("")).ok()?;
+    Some(template
+      .replace("{title}", self.title.as_deref().unwrap_or(locale.strings.untitled_board))
+      .replace("{date}", &locale.date(chrono::Utc::now()))
+      .replace("{page}", &page.to_string())
+      .replace("{pages}", &pages.to_string()))
+  }
+
+  fn watermark_png(&self) -> Result<Option<Vec<u8>>, String> {
+    let Some(encoded) = self.watermark_image.as_deref().filter(|v| !v.trim().is_empty()) else {
+      return Ok(None);
+    };
+    let data = base64::engine::general_purpose::STANDARD.decode(encoded.trim())
+      .map_err(|e| format!("Invalid watermark image: {}", e))?;
+    if media::sniff_image_mime(&data) != Some("image/png") {
+      return Err("Watermark image must be a PNG".to_string());
+    }
+    Ok(Some(data))
+  }
+
+  fn qr_modules(&self) -> Result<Option<qr::QrModules>, String> {
+    match self.qr_code.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+      Some(data) => qr::encode(data).map(Some),
+      None => Ok(None),
+    }
+  }
+}
+
+// ---- PNG ----
+
+fn xml_escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// The markings for one image as an SVG overlay of the same size
+// The QR code as SVG squares on a white background, `size` wide with its
+// top-left corner at `x`, `y`
+fn qr_svg(modules: &qr::QrModules, x: f64, y: f64, size: f64) -> String {
+  let module = size / modules.size() as f64;
+  let mut path = String::new();
+  for my in 0..modules.width {
+    for mx in 0..modules.width {
+      if modules.is_dark(mx, my) {
+        let (px, py) = (x + (mx + qr::QUIET_ZONE) as f64 * module, y + (my + qr::QUIET_ZONE) as f64 * module);
+        path += &format!("M{:.2} {:.2}h{:.2}v{:.2}h{:.2}z", px, py, module, module, -module);
+      }
+    }
+  }
+  format!(
+    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#ffffff\"/><path d=\"{}\" fill=\"#000000\" shape-rendering=\"crispEdges\"/>",
+    x, y, size, size, path
+  )
+}
+
+fn overlay_svg(markings: &ExportMarkings, width: u32, height: u32, watermark: Option<&[u8]>, qr_code: Option<&qr::QrModules>) -> String {
+  let (w, h) = (width as f64, height as f64);
+  let line_size = (h * 0.02).clamp(10.0, 48.0);
+  let opacity = markings.opacity();
+  let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">", width, height);
+
+  if let Some(png) = watermark {
+    let (box_w, box_h) = (w * 0.5, h * 0.5);
+    svg += &format!(
+      "<image x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" opacity=\"{}\" preserveAspectRatio=\"xMidYMid meet\" href=\"data:image/png;base64,{}\"/>",
+      (w - box_w) / 2.0, (h - box_h) / 2.0, box_w, box_h, opacity,
+      base64::engine::general_purpose::STANDARD.encode(png)
+    );
+  }
+  if let Some(text) = markings.watermark_text.as_deref().filter(|t| !t.trim().is_empty()) {
+    // Sized so the text spans roughly 60% of the diagonal
+    let diagonal = w.hypot(h);
+    let size = (diagonal * 0.6 / (text.chars().count().max(1) as f64 * 0.55)).min(h * 0.3);
+    let angle = -(h.atan2(w).to_degrees());
+    svg += &format!(
+      "<text x=\"{cx}\" y=\"{cy}\" font-family=\"sans-serif\" font-size=\"{}\" font-weight=\"bold\" fill=\"#808080\" fill-opacity=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" transform=\"rotate({} {cx} {cy})\">{}</text>",
+      size, opacity, angle, xml_escape(text), cx = w / 2.0, cy = h / 2.0
+    );
+  }
+  let lines = [(&markings.header, line_size * 1.5), (&markings.footer, h - line_size)];
+  for (template, y) in lines {
+    if let Some(text) = markings.line(template, 1, 1) {
+      svg += &format!(
+        "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" fill=\"#606060\" text-anchor=\"middle\">{}</text>",
+        w / 2.0, y, line_size, xml_escape(&text)
+      );
+    }
+  }
+  if let Some(modules) = qr_code {
+    // Whole pixels per module keep the code sharp enough to scan
+    let module = ((w.min(h) * 0.15) / modules.size() as f64).floor().max(2.0);
+    let size = module * modules.size() as f64;
+    svg += &qr_svg(modules, w - size - line_size, h - size - line_size * 2.0, size);
+  }
+  svg + "</svg>"
+}
+
+pub fn apply_to_png(png: &[u8], markings: &ExportMarkings) -> Result<Vec<u8>, String> {
+  if markings.is_empty() {
+    return Ok(png.to_vec());
+  }
+  let (rgba, width, height) = media::decode_png_rgba(png)?;
+  let mut pixmap = tiny_skia::Pixmap::new(width, height)
+    .ok_or_else(|| format!("Cannot mark a {}×{} image", width, height))?;
+  for (pixel, source) in pixmap.pixels_mut().iter_mut().zip(rgba.chunks_exact(4)) {
+    *pixel = tiny_skia::ColorU8::from_rgba(source[0], source[1], source[2], source[3]).premultiply();
+  }
+
+  let watermark = markings.watermark_png()?;
+  let qr_code = markings.qr_modules()?;
+  let svg = overlay_svg(markings, width, height, watermark.as_deref(), qr_code.as_ref());
+  let options = usvg::Options { fontdb: fonts::system_database(), ..Default::default() };
+  let tree = usvg::Tree::from_str(&svg, &options)
+    .map_err(|e| format!("Failed to lay out export markings: {}", e))?;
+  resvg::render(&tree, tiny_skia::Transform::identity(), &mut pixmap.as_mut());
+
+  let marked: Vec<u8> = pixmap.pixels().iter()
+    .flat_map(|pixel| {
+      let color = pixel.demultiply();
+      [color.red(), color.green(), color.blue(), color.alpha()]
+    })
+    .collect();
+  media::encode_png_rgba(&marked, width, height)
+}
+
+// ---- PDF ----
+
+// Helvetica advance widths (1/1000 em) for ASCII 32..=126
+const HELVETICA_WIDTHS: [u16; 95] = [
+  278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+  556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+  1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+  667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+  333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+  556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+fn text_width(text: &str, size: f64) -> f64 {
+  let units: f64 = text.chars()
+    .map(|c| match c as u32 {
+      code @ 32..=126 => HELVETICA_WIDTHS[(code - 32) as usize] as f64,
+      _ => 556.0,
+    })
+    .sum();
+  units * size / 1000.0
+}
+
+// PDF string literal in WinAnsi encoding; characters outside Latin-1 become '?'
+fn pdf_string(text: &str) -> Vec<u8> {
+  let mut bytes = vec![b'('];
+  for c in text.chars() {
+    let byte = match c as u32 {
+      code @ 32..=126 | code @ 160..=255 => code as u8,
+      _ => b'?',
+    };
+    if matches!(byte, b'(' | b')' | b'\\') {
+      bytes.push(b'\\');
+    }
+    bytes.push(byte);
+  }
+  bytes.push(b')');
+  bytes
+}
+
+pub(crate) fn media_box(doc: &lopdf::Document, page_id: lopdf::ObjectId) -> Result<(f64, f64), String> {
+  let mut node = doc.get_dictionary(page_id).map_err(|e| format!("Invalid PDF page: {}", e))?;
+  loop {
+    if let Ok(values) = node.get(b"MediaBox").and_then(Object::as_array) {
+      let numbers: Vec<f64> = values.iter()
+        .filter_map(|v| v.as_float().map(f64::from).or_else(|_| v.as_i64().map(|i| i as f64)).ok())
+        .collect();
+      if let [x0, y0, x1, y1] = numbers[..] {
+        return Ok((x1 - x0, y1 - y0));
+      }
+    }
+    let parent = node.get(b"Parent").and_then(Object::as_reference)
+      .map_err(|_| "PDF page has no media box".to_string())?;
+    node = doc.get_dictionary(parent).map_err(|e| format!("Invalid PDF page tree: {}", e))?;
+  }
+}
+
+// Content stream drawing the markings on a page of `width`×`height` points
+fn page_content(
+  markings: &ExportMarkings,
+  width: f64,
+  height: f64,
+  page: usize,
+  pages: usize,
+  image: Option<(u32, u32)>,
+  qr_code: Option<&qr::QrModules>,
+) -> Vec<u8> {
+  let mut content = Vec::new();
+
+  if let Some((image_w, image_h)) = image {
+    let fit = (width * 0.5 / image_w as f64).min(height * 0.5 / image_h as f64);
+    let (draw_w, draw_h) = (image_w as f64 * fit, image_h as f64 * fit);
+    content.extend(format!(
+      "q /FimGS gs {:.2} 0 0 {:.2} {:.2} {:.2} cm /FimImage Do Q\n",
+      draw_w, draw_h, (width - draw_w) / 2.0, (height - draw_h) / 2.0
+    ).into_bytes());
+  }
+
+  if let Some(text) = markings.watermark_text.as_deref().filter(|t| !t.trim().is_empty()) {
+    let angle = height.atan2(width);
+    let (sin, cos) = angle.sin_cos();
+    let size = (width.hypot(height) * 0.6 / (text_width(text, 1.0)).max(1.0)).min(height * 0.3);
+    let (text_w, cap) = (text_width(text, size), size * 0.7);
+    // Start of the baseline, so the rotated text is centered on the page
+    let x = width / 2.0 - cos * text_w / 2.0 + sin * cap / 2.0;
+    let y = height / 2.0 - sin * text_w / 2.0 - cos * cap / 2.0;
+    content.extend(format!(
+      "q /FimGS gs 0.5 g BT /FimFont {:.2} Tf {:.4} {:.4} {:.4} {:.4} {:.2} {:.2} Tm ",
+      size, cos, sin, -sin, cos, x, y
+    ).into_bytes());
+    content.extend(pdf_string(text));
+    content.extend(b" Tj ET Q\n");
+  }
+
+  let lines = [(&markings.header, height - PDF_LINE_INSET), (&markings.footer, PDF_LINE_INSET - PDF_LINE_SIZE / 2.0)];
+  for (template, y) in lines {
+    if let Some(text) = markings.line(template, page, pages) {
+      let x = (width - text_width(&text, PDF_LINE_SIZE)) / 2.0;
+      content.extend(format!("q 0.38 g BT /FimFont {} Tf {:.2} {:.2} Td ", PDF_LINE_SIZE, x, y).into_bytes());
+      content.extend(pdf_string(&text));
+      content.extend(b" Tj ET Q\n");
+    }
+  }
+
+  // Above the footer, clear of the page edge
+  if let Some(modules) = qr_code {
+    let size = PDF_QR_SIZE.min(width.min(height) * 0.25);
+    let module = size / modules.size() as f64;
+    let (x, y) = (width - size - PDF_LINE_INSET, PDF_LINE_INSET + PDF_LINE_SIZE);
+    content.extend(format!("q 1 g {:.2} {:.2} {:.2} {:.2} re f 0 g\n", x, y, size, size).into_bytes());
+    for my in 0..modules.width {
+      for mx in 0..modules.width {
+        if modules.is_dark(mx, my) {
+          // PDF y grows upwards; QR rows go down
+          let px = x + (mx + qr::QUIET_ZONE) as f64 * module;
+          let py = y + size - (my + qr::QUIET_ZONE + 1) as f64 * module;
+          content.extend(format!("{:.3} {:.3} {:.3} {:.3} re\n", px, py, module, module).into_bytes());
+        }
+      }
+    }
+    content.extend(b"f Q\n");
+  }
+  content
+}
+
+// The watermark PNG as an image XObject with its alpha channel as soft mask
+fn add_watermark_image(doc: &mut lopdf::Document, png: &[u8]) -> Result<(lopdf::ObjectId, (u32, u32)), String> {
+  let (rgba, width, height) = media::decode_png_rgba(png)?;
+  let rgb: Vec<u8> = rgba.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+  let alpha: Vec<u8> = rgba.chunks_exact(4).map(|p| p[3]).collect();
+
+  let image_dict = |color_space: &str| dictionary! {
+    "Type" => "XObject",
+    "Subtype" => "Image",
+    "Width" => width as i64,
+    "Height" => height as i64,
+    "ColorSpace" => color_space,
+    "BitsPerComponent" => 8,
+  };
+  let mut mask = Stream::new(image_dict("DeviceGray"), alpha);
+  let _ = mask.compress();
+  let mask_id = doc.add_object(mask);
+  let mut dict = image_dict("DeviceRGB");
+  dict.set("SMask", mask_id);
+  let mut image = Stream::new(dict, rgb);
+  let _ = image.compress();
+  Ok((doc.add_object(image), (width, height)))
+}
+
+pub fn apply_to_pdf(pdf: &[u8], markings: &ExportMarkings) -> Result<Vec<u8>, String> {
+  if markings.is_empty() {
+    return Ok(pdf.to_vec());
+  }
+  let mut doc = lopdf::Document::load_mem(pdf).map_err(|e| format!("Failed to read PDF for marking: {}", e))?;
+
+  let font_id = doc.add_object(dictionary! {
+    "Type" => "Font",
+    "Subtype" => "Type1",
+    "BaseFont" => "Helvetica",
+    "Encoding" => "WinAnsiEncoding",
+  });
+  let opacity = markings.opacity() as f32;
+  let state_id = doc.add_object(dictionary! { "Type" => "ExtGState", "ca" => opacity, "CA" => opacity });
+  let image = match markings.watermark_png()? {
+    Some(png) => Some(add_watermark_image(&mut doc, &png)?),
+    None => None,
+  };
+
+  let qr_code = markings.qr_modules()?;
+
+  let pages = doc.get_pages();
+  let total = pages.len();
+  for (number, page_id) in pages {
+    let (width, height) = media_box(&doc, page_id)?;
+    let content = page_content(markings, width, height, number as usize, total, image.map(|(_, size)| size), qr_code.as_ref());
+
+    // Each page gets a form XObject with its own resources, so the page's
+    // resource dictionary (often shared between pages) only gains one name
+    let mut resources = dictionary! {
+      "Font" => dictionary! { "FimFont" => font_id },
+      "ExtGState" => dictionary! { "FimGS" => state_id },
+    };
+    if let Some((image_id, _)) = image {
+      resources.set("XObject", dictionary! { "FimImage" => image_id });
+    }
+    let form = Stream::new(dictionary! {
+      "Type" => "XObject",
+      "Subtype" => "Form",
+      "BBox" => vec![0.into(), 0.into(), (width as f32).into(), (height as f32).into()],
+      "Resources" => resources,
+    }, content);
+    let form_id = doc.add_object(form);
+    let form_name = format!("FimMarkings{}", number);
+    doc.add_xobject(page_id, form_name.as_bytes(), form_id)
+      .map_err(|e| format!("Failed to add markings to page {}: {}", number, e))?;
+
+    // Wrap the existing content in q/Q so its graphics state can't leak into ours
+    let open = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let close = doc.add_object(Stream::new(Dictionary::new(), format!("\nQ\nq /{} Do Q\n", form_name).into_bytes()));
+    let page = doc.get_object_mut(page_id).and_then(Object::as_dict_mut)
+      .map_err(|e| format!("Invalid PDF page {}: {}", number, e))?;
+    let mut contents = vec![Object::Reference(open)];
+    match page.get(b"Contents") {
+      Ok(Object::Reference(id)) => contents.push(Object::Reference(*id)),
+      Ok(Object::Array(existing)) => contents.extend(existing.iter().cloned()),
+      _ => {},
+    }
+    contents.push(Object::Reference(close));
+    page.set("Contents", contents);
+  }
+
+  let mut output = Vec::new();
+  doc.save_to(&mut output).map_err(|e| format!("Failed to write marked PDF: {}", e))?;
+  Ok(output)
+}
@@ -0,0 +1,348 @@
+use base64::Engine;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::archive;
+use crate::audit;
+use crate::lazy_media;
+use crate::model;
+use crate::outline;
+
+// Images bigger than this are rejected rather than inlined into the document
+pub const MAX_IMAGE_BYTES: u64 = 50 * 1024 * 1024; // 50 MiB
+
+// In a .fim container image data is stored as files under `media/` rather
+// than as base64 in board.json, which grew with every photo and made each
+// save serialize and compress it all again. The loader puts the data back,
+// so the rest of the app sees ordinary embedded images.
+pub const MEDIA_DIR: &str = "media/";
+
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+pub fn is_image_path(path: &Path) -> bool {
+  path.extension()
+    .and_then(|ext| ext.to_str())
+    .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+    .unwrap_or(false)
+}
+
+// Identify the image format from its magic bytes; file extensions can't be trusted.
+pub fn sniff_image_mime(data: &[u8]) -> Option<&'static str> {
+  if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+    Some("image/png")
+  } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+    Some("image/jpeg")
+  } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+    Some("image/gif")
+  } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+    Some("image/webp")
+  } else if data.starts_with(b"BM") {
+    Some("image/bmp")
+  } else {
+    None
+  }
+}
+
+pub fn extension(mime: &str) -> &'static str {
+  match mime {
+    "image/jpeg" => "jpg",
+    "image/gif" => "gif",
+    "image/webp" => "webp",
+    "image/bmp" => "bmp",
+    _ => "png",
+  }
+}
+
+// Where an image's data is stored in a .fim container
+pub fn media_entry(image: &model::EmbeddedImage) -> String {
+  format!("{}{}.{}", MEDIA_DIR, image.id, extension(&image.mime))
+}
+
+// Turn raw image bytes into an embedded image. The ID is derived from the
+// content so importing the same picture twice yields the same image entry.
+pub fn image_from_bytes(data: &[u8]) -> Result<model::EmbeddedImage, String> {
+  let mime = sniff_image_mime(data).ok_or("Unsupported or unrecognized image format")?;
+  let size = imagesize::blob_size(data)
+    .map_err(|e| format!("Failed to read image dimensions: {}", e))?;
+
+  Ok(model::EmbeddedImage {
+    id: format!("img_{}", &audit::hash_bytes(data)[..16]),
+    mime: mime.to_string(),
+    width: size.width as f64,
+    height: size.height as f64,
+    data_base64: Some(base64::engine::general_purpose::STANDARD.encode(data)),
+    path: None,
+  })
+}
+
+pub fn import_image_file(path: &Path) -> Result<model::EmbeddedImage, String> {
+  let metadata = std::fs::metadata(path)
+    .map_err(|e| format!("Failed to read image '{}': {}", path.display(), e))?;
+  if metadata.len() > MAX_IMAGE_BYTES {
+    return Err(format!(
+      "Image '{}' is {} bytes (limit is {})",
+      path.display(), metadata.len(), MAX_IMAGE_BYTES
+    ));
+  }
+
+  let data = std::fs::read(path)
+    .map_err(|e| format!("Failed to read image '{}': {}", path.display(), e))?;
+  image_from_bytes(&data).map_err(|e| format!("{} ('{}')", e, path.display()))
+}
+
+// Add the image at `path` to the board's images, unless it already has it;
+// returns the board and the image's ID
+pub fn add_image(doc: &model::BoardDocument, path: &Path) -> Result<(model::BoardDocument, model::ID), String> {
+  let image = import_image_file(path)?;
+  let mut document = doc.clone();
+  let id = image.id.clone();
+  let images = document.images.get_or_insert_with(Vec::new);
+  if !images.iter().any(|i| i.id == image.id) {
+    images.push(image);
+  }
+  Ok((document, id))
+}
+
+// The board as written to board.json, with image data moved out to `media/`
+// files: returns the board and the files to store. Images whose data can't
+// be decoded stay inline. Entries in `kept` are already in the container
+// being written and are only referenced (see incremental.rs).
+pub fn externalize(doc: &model::BoardDocument, kept: &HashSet<String>) -> (model::BoardDocument, Vec<(String, Vec<u8>)>) {
+  let mut stored = doc.clone();
+  let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+  for image in stored.images.iter_mut().flatten() {
+    if image.data_base64.is_some() && kept.contains(&media_entry(image)) {
+      image.data_base64 = None;
+      image.path = Some(media_entry(image));
+      continue;
+    }
+    let data = image.data_base64.as_deref()
+      .and_then(|encoded| base64::engine::general_purpose::STANDARD.decode(encoded).ok());
+    let Some(data) = data else {
+      continue;
+    };
+    let entry = media_entry(image);
+    image.data_base64 = None;
+    image.path = Some(entry.clone());
+    if !files.iter().any(|(name, _)| *name == entry) {
+      files.push((entry, data));
+    }
+  }
+  (stored, files)
+}
+
+// Load the data of images stored under `media/` back into the board, from
+// its open container (which may have been decrypted into memory); `path`
+// only names it in warnings
+pub fn internalize<R: Read + Seek>(doc: &mut model::BoardDocument, zip: &mut zip::ZipArchive<R>, path: &Path) {
+  let stored = doc.images.iter_mut().flatten()
+    .filter(|i| i.data_base64.is_none() && i.path.as_deref().is_some_and(|p| p.starts_with(MEDIA_DIR)));
+  for image in stored {
+    let entry = image.path.clone().unwrap_or_default();
+    match archive::read_entry_limited(zip, &entry, MAX_IMAGE_BYTES) {
+      Ok(data) => {
+        image.data_base64 = Some(base64::engine::general_purpose::STANDARD.encode(data));
+        image.path = None;
+      },
+      // A missing image shouldn't keep the board from opening; the entry
+      // keeps its path, so saving doesn't lose track of it
+      Err(e) => log::warn!("Failed to read image '{}' from '{}': {}", entry, path.display(), String::from(e)),
+    }
+  }
+}
+
+// The bytes of image `id` stored in the .fim container at `path`, without
+// loading the board
+pub fn read_media(path: &Path, id: &str) -> Result<Vec<u8>, String> {
+  let file = std::fs::File::open(path)
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+  let mut zip = zip::ZipArchive::new(file)
+    .map_err(|e| format!("Failed to read zip file '{}': {}", path.display(), e))?;
+  archive::validate_archive(&mut zip)?;
+  let prefix = format!("{}{}.", MEDIA_DIR, id);
+  let entry = zip.file_names()
+    .find(|name| name.starts_with(&prefix))
+    .map(str::to_string)
+    .ok_or_else(|| format!("Image '{}' not found in '{}'", id, path.display()))?;
+  Ok(archive::read_entry_limited(&mut zip, &entry, MAX_IMAGE_BYTES)?)
+}
+
+// Image notes for a folder import, with the files that couldn't be read
+#[derive(Serialize, Debug, Clone)]
+pub struct ImageFolderImport {
+  pub notes: Vec<model::Note>,
+  pub images: Vec<model::EmbeddedImage>,
+  pub warnings: Vec<String>,
+}
+
+// Longest edge of an imported image note; the grid cell is this plus a gap
+const FOLDER_NOTE_SIZE: f64 = 240.0;
+const FOLDER_GAP: f64 = 40.0;
+
+// One image note per image file in `dir` (not its subfolders), titled with the
+// file name and laid out in a roughly square grid from `origin` in name order
+// `on_image(index, total)` is called before each image is read; an error from
+// it stops the import
+pub fn import_image_folder(
+  dir: &Path,
+  origin: Option<model::Point>,
+  on_image: &mut dyn FnMut(usize, usize) -> Result<(), String>,
+) -> Result<ImageFolderImport, String> {
+  let entries = std::fs::read_dir(dir)
+    .map_err(|e| format!("Failed to read folder '{}': {}", dir.display(), e))?;
+  let mut paths: Vec<_> = entries
+    .filter_map(|entry| entry.ok().map(|e| e.path()))
+    .filter(|path| path.is_file() && is_image_path(path))
+    .collect();
+  if paths.is_empty() {
+    return Err(format!("Folder '{}' has no images", dir.display()));
+  }
+  paths.sort_by_key(|path| path.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+
+  let origin = origin.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  let columns = (paths.len() as f64).sqrt().ceil() as usize;
+  let mut result = ImageFolderImport { notes: Vec::new(), images: Vec::new(), warnings: Vec::new() };
+  for (position, path) in paths.iter().enumerate() {
+    on_image(position, paths.len())?;
+    let image = match import_image_file(path) {
+      Ok(image) => image,
+      Err(e) => {
+        result.warnings.push(e);
+        continue;
+      },
+    };
+
+    // Fit the image in its cell, centered, keeping its aspect ratio
+    let index = result.notes.len();
+    let scale = (FOLDER_NOTE_SIZE / image.width.max(image.height).max(1.0)).min(1.0);
+    let (w, h) = ((image.width * scale).max(1.0), (image.height * scale).max(1.0));
+    let cell = FOLDER_NOTE_SIZE + FOLDER_GAP;
+    let frame = model::Rect {
+      x: origin.x + (index % columns) as f64 * cell + (FOLDER_NOTE_SIZE - w) / 2.0,
+      y: origin.y + (index / columns) as f64 * cell + (FOLDER_NOTE_SIZE - h) / 2.0,
+      w,
+      h,
+    };
+    let title = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let mut note = outline::imported_note(&title, frame);
+    note.images = Some(vec![image.id.clone()]);
+    result.notes.push(note);
+    // Identical files share one embedded image
+    if !result.images.iter().any(|i| i.id == image.id) {
+      result.images.push(image);
+    }
+  }
+
+  if result.notes.is_empty() {
+    return Err(format!("None of the images in '{}' could be imported: {}", dir.display(), result.warnings.join("; ")));
+  }
+  Ok(result)
+}
+
+// Decode a PNG into 8-bit RGBA pixels (the layout clipboards expect).
+pub fn decode_png_rgba(data: &[u8]) -> Result<(Vec<u8>, u32, u32), String> {
+  let mut decoder = png::Decoder::new(data);
+  decoder.set_transformations(png::Transformations::normalize_to_color8());
+  let mut reader = decoder.read_info()
+    .map_err(|e| format!("Failed to read PNG header: {}", e))?;
+  let mut buf = vec![0; reader.output_buffer_size()];
+  let info = reader.next_frame(&mut buf)
+    .map_err(|e| format!("Failed to decode PNG: {}", e))?;
+  buf.truncate(info.buffer_size());
+
+  let rgba = match info.color_type {
+    png::ColorType::Rgba => buf,
+    png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+    png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+    png::ColorType::Grayscale => buf.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+    png::ColorType::Indexed => return Err("Unexpected indexed PNG after palette expansion".to_string()),
+  };
+
+  Ok((rgba, info.width, info.height))
+}
+
+pub fn encode_png_rgba(rgba: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+  let mut data = Vec::new();
+  {
+    let mut encoder = png::Encoder::new(&mut data, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()
+      .map_err(|e| format!("Failed to write PNG header: {}", e))?;
+    writer.write_image_data(rgba)
+      .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+  }
+  Ok(data)
+}
+
+// JPEG segments and PNG chunks that carry metadata rather than pixels: EXIF
+// (camera, time taken, GPS position), XMP, IPTC, comments and text chunks.
+// Segments the image needs to look right, such as the ICC color profile, stay.
+const JPEG_METADATA_MARKERS: &[u8] = &[0xE1, 0xED, 0xFE];
+const PNG_METADATA_CHUNKS: &[&[u8; 4]] = &[b"tEXt", b"zTXt", b"iTXt", b"tIME", b"eXIf"];
+
+fn jpeg_without_metadata(data: &[u8]) -> Option<Vec<u8>> {
+  let mut output = data[..2].to_vec();
+  let mut i = 2;
+  while i + 4 <= data.len() {
+    if data[i] != 0xFF {
+      return None;
+    }
+    let marker = data[i + 1];
+    // Everything from the scan on is image data
+    if marker == 0xDA {
+      output.extend_from_slice(&data[i..]);
+      return Some(output);
+    }
+    let end = i + 2 + u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+    let segment = data.get(i..end)?;
+    if !JPEG_METADATA_MARKERS.contains(&marker) {
+      output.extend_from_slice(segment);
+    }
+    i = end;
+  }
+  None
+}
+
+fn png_without_metadata(data: &[u8]) -> Option<Vec<u8>> {
+  let mut output = data[..8].to_vec();
+  let mut i = 8;
+  while i < data.len() {
+    let length = u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?) as usize;
+    let chunk = data.get(i..i + 12 + length)?;
+    if !PNG_METADATA_CHUNKS.iter().any(|kind| &chunk[4..8] == *kind) {
+      output.extend_from_slice(chunk);
+    }
+    i += 12 + length;
+  }
+  Some(output)
+}
+
+// The image without its metadata; formats other than JPEG and PNG, and files
+// that don't parse, come back as they are
+pub fn without_metadata(data: &[u8]) -> Vec<u8> {
+  let stripped = match sniff_image_mime(data) {
+    Some("image/jpeg") => jpeg_without_metadata(data),
+    Some("image/png") => png_without_metadata(data),
+    _ => None,
+  };
+  stripped.unwrap_or_else(|| data.to_vec())
+}
+
+// `doc` with the metadata taken out of its images, for exports that embed
+// the image files as they are (SVG)
+pub fn scrub_images(doc: &model::BoardDocument) -> model::BoardDocument {
+  let mut doc = doc.clone();
+  for image in doc.images.iter_mut().flatten() {
+    let data = match lazy_media::is_deferred(image) {
+      true => lazy_media::read(&image.id).ok(),
+      false => image.data_base64.as_deref().and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok()),
+    };
+    if let Some(data) = data {
+      image.data_base64 = Some(base64::engine::general_purpose::STANDARD.encode(without_metadata(&data)));
+    }
+  }
+  doc
+}
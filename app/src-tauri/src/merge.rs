@@ -0,0 +1,316 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::{groups, model};
+
+// Comparing and reconciling two copies of a board, e.g. one sent around by
+// email and edited by several people. Entities are matched by ID and compared
+// field by field on their board.json form, so any field the model gains is
+// covered without touching this file.
+//
+// A merge takes "ours" as the starting point and brings in "theirs". With the
+// common ancestor both started from, changes are three-way: a field changed
+// on one side only is taken from that side, an entity removed on one side and
+// untouched on the other is removed, and only fields changed differently on
+// both sides conflict. Without it there's no telling an addition from a
+// removal, so entities from either side are kept and every difference in a
+// shared entity is a conflict. Conflicts keep our version, or whichever side
+// still has the entity, and are reported with both versions for the user to
+// settle.
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Modified {
+  pub id: model::ID,
+  // board.json field names that differ
+  pub fields: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct EntityChanges {
+  // In b but not in a
+  pub added: Vec<model::ID>,
+  // In a but not in b
+  pub removed: Vec<model::ID>,
+  pub modified: Vec<Modified>,
+}
+
+impl EntityChanges {
+  fn is_empty(&self) -> bool {
+    self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+  }
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct DocumentDiff {
+  pub notes: EntityChanges,
+  pub connections: EntityChanges,
+  pub stacks: EntityChanges,
+  pub shapes: EntityChanges,
+  pub identical: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct EntityRef {
+  // "note", "connection", "stack", "shape" or "stroke"
+  pub kind: String,
+  pub id: model::ID,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MergeConflict {
+  pub kind: String,
+  pub id: model::ID,
+  // "both_changed", or "changed_and_removed" when one side removed what the
+  // other changed
+  pub reason: String,
+  pub fields: Vec<String>,
+  // Both versions as in board.json; None on the side that removed it
+  pub ours: Option<Value>,
+  pub theirs: Option<Value>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MergeReport {
+  // Changes taken from theirs
+  pub added: Vec<EntityRef>,
+  pub updated: Vec<EntityRef>,
+  pub removed: Vec<EntityRef>,
+  pub conflicts: Vec<MergeConflict>,
+  // Connections dropped because a note they joined was removed
+  pub dropped_connections: Vec<model::ID>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct MergeResult {
+  pub document: model::BoardDocument,
+  pub report: MergeReport,
+}
+
+fn to_values<T: Serialize>(items: &[T], id: fn(&T) -> &str) -> Result<Vec<(String, Value)>, String> {
+  items.iter()
+    .map(|item| serde_json::to_value(item).map(|value| (id(item).to_string(), value)))
+    .collect::<Result<_, _>>()
+    .map_err(|e| format!("Failed to compare boards: {}", e))
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> &'a Value {
+  value.get(key).unwrap_or(&Value::Null)
+}
+
+// Field names present in any of `values`, in first-seen order
+fn keys(values: &[&Value]) -> Vec<String> {
+  let mut seen = HashSet::new();
+  values.iter()
+    .filter_map(|value| value.as_object())
+    .flat_map(|object| object.keys())
+    .filter(|key| seen.insert(key.as_str()))
+    .cloned()
+    .collect()
+}
+
+fn changed_fields(a: &Value, b: &Value) -> Vec<String> {
+  keys(&[a, b]).into_iter().filter(|key| field(a, key) != field(b, key)).collect()
+}
+
+fn diff_entities<T: Serialize>(a: &[T], b: &[T], id: fn(&T) -> &str) -> Result<EntityChanges, String> {
+  let (a, b) = (to_values(a, id)?, to_values(b, id)?);
+  let b_by_id: HashMap<&str, &Value> = b.iter().map(|(id, value)| (id.as_str(), value)).collect();
+  let a_ids: HashSet<&str> = a.iter().map(|(id, _)| id.as_str()).collect();
+
+  let mut changes = EntityChanges::default();
+  for (id, value) in &a {
+    match b_by_id.get(id.as_str()) {
+      None => changes.removed.push(id.clone()),
+      Some(other) if *other != value => changes.modified.push(Modified { id: id.clone(), fields: changed_fields(value, other) }),
+      Some(_) => {},
+    }
+  }
+  changes.added = b.iter().filter(|(id, _)| !a_ids.contains(id.as_str())).map(|(id, _)| id.clone()).collect();
+  Ok(changes)
+}
+
+// What changed going from `a` to `b`
+pub fn diff(a: &model::BoardDocument, b: &model::BoardDocument) -> Result<DocumentDiff, String> {
+  let mut diff = DocumentDiff {
+    notes: diff_entities(&a.notes, &b.notes, |n| &n.id)?,
+    connections: diff_entities(&a.connections, &b.connections, |c| &c.id)?,
+    stacks: diff_entities(&a.stacks, &b.stacks, |s| &s.id)?,
+    shapes: diff_entities(&a.shapes, &b.shapes, |s| &s.id)?,
+    identical: false,
+  };
+  diff.identical = diff.notes.is_empty() && diff.connections.is_empty() && diff.stacks.is_empty() && diff.shapes.is_empty();
+  Ok(diff)
+}
+
+// Field-wise three-way merge of one entity; the merged value and the fields
+// changed differently on both sides
+fn merge_fields(ours: &Value, theirs: &Value, base: &Value) -> (Value, Vec<String>, bool) {
+  let mut merged = serde_json::Map::new();
+  let mut conflicts = Vec::new();
+  let mut took_theirs = false;
+  for key in keys(&[ours, theirs, base]) {
+    let (o, t, b) = (field(ours, &key), field(theirs, &key), field(base, &key));
+    let value = if o == t || t == b {
+      o
+    } else if o == b {
+      took_theirs = true;
+      t
+    } else {
+      conflicts.push(key.clone());
+      o
+    };
+    if !value.is_null() {
+      merged.insert(key, value.clone());
+    }
+  }
+  (Value::Object(merged), conflicts, took_theirs)
+}
+
+fn merge_entities<T: Serialize + DeserializeOwned>(
+  kind: &str,
+  ours: &[T],
+  theirs: &[T],
+  base: Option<&[T]>,
+  id: fn(&T) -> &str,
+  report: &mut MergeReport,
+) -> Result<Vec<T>, String> {
+  let ours = to_values(ours, id)?;
+  let theirs = to_values(theirs, id)?;
+  let base = base.map(|base| to_values(base, id)).transpose()?;
+  let theirs_by_id: HashMap<&str, &Value> = theirs.iter().map(|(id, value)| (id.as_str(), value)).collect();
+  let ours_ids: HashSet<&str> = ours.iter().map(|(id, _)| id.as_str()).collect();
+  let base_by_id: Option<HashMap<&str, &Value>> = base.as_ref()
+    .map(|base| base.iter().map(|(id, value)| (id.as_str(), value)).collect());
+  let in_base = |id: &str| base_by_id.as_ref().and_then(|base| base.get(id).copied());
+  let entity = |id: &str| EntityRef { kind: kind.to_string(), id: id.to_string() };
+  let conflict = |id: &str, reason: &str, fields: Vec<String>, ours: Option<&Value>, theirs: Option<&Value>| MergeConflict {
+    kind: kind.to_string(),
+    id: id.to_string(),
+    reason: reason.to_string(),
+    fields,
+    ours: ours.cloned(),
+    theirs: theirs.cloned(),
+  };
+
+  let mut merged = Vec::with_capacity(ours.len());
+  for (id, value) in &ours {
+    match (theirs_by_id.get(id.as_str()), in_base(id)) {
+      (Some(other), _) if *other == value => merged.push(value.clone()),
+      (Some(other), Some(base)) => {
+        let (combined, fields, took_theirs) = merge_fields(value, other, base);
+        if !fields.is_empty() {
+          report.conflicts.push(conflict(id, "both_changed", fields, Some(value), Some(other)));
+        } else if took_theirs {
+          report.updated.push(entity(id));
+        }
+        merged.push(combined);
+      },
+      (Some(other), None) => {
+        report.conflicts.push(conflict(id, "both_changed", changed_fields(value, other), Some(value), Some(other)));
+        merged.push(value.clone());
+      },
+      // Removed on their side
+      (None, Some(base)) if base == value => report.removed.push(entity(id)),
+      (None, Some(base)) => {
+        report.conflicts.push(conflict(id, "changed_and_removed", changed_fields(base, value), Some(value), None));
+        merged.push(value.clone());
+      },
+      // Added on our side, or no ancestor to tell
+      (None, None) => merged.push(value.clone()),
+    }
+  }
+  for (id, value) in theirs.iter().filter(|(id, _)| !ours_ids.contains(id.as_str())) {
+    match in_base(id) {
+      // Removed on our side
+      Some(base) if base == value => {},
+      Some(base) => {
+        report.conflicts.push(conflict(id, "changed_and_removed", changed_fields(base, value), None, Some(value)));
+        merged.push(value.clone());
+      },
+      None => {
+        report.added.push(entity(id));
+        merged.push(value.clone());
+      },
+    }
+  }
+
+  merged.into_iter()
+    .map(serde_json::from_value)
+    .collect::<Result<_, _>>()
+    .map_err(|e| format!("Failed to merge {}s: {}", kind, e))
+}
+
+// Entries of theirs that ours lacks, for the styles, images and such that
+// merged notes may refer to
+fn union_by_id<T: Clone>(ours: &mut Vec<T>, theirs: &[T], id: fn(&T) -> &str) {
+  let known: HashSet<String> = ours.iter().map(|item| id(item).to_string()).collect();
+  ours.extend(theirs.iter().filter(|item| !known.contains(id(item))).cloned());
+}
+
+fn union_optional<T: Clone>(ours: &mut Option<Vec<T>>, theirs: &Option<Vec<T>>, id: fn(&T) -> &str) {
+  if let Some(theirs) = theirs.as_ref().filter(|theirs| !theirs.is_empty()) {
+    union_by_id(ours.get_or_insert_with(Vec::new), theirs, id);
+  }
+}
+
+// Connections, stacks and groups only referring to notes that made it
+fn repair_references(doc: &mut model::BoardDocument, report: &mut MergeReport) {
+  let notes: HashSet<String> = doc.notes.iter().map(|n| n.id.clone()).collect();
+  doc.connections.retain(|c| {
+    let keep = notes.contains(&c.src_note_id) && notes.contains(&c.dst_note_id);
+    if !keep {
+      report.dropped_connections.push(c.id.clone());
+    }
+    keep
+  });
+
+  // A note moved into different stacks on each side stays in the first
+  let mut stacked = HashSet::new();
+  for stack in doc.stacks.iter_mut() {
+    stack.note_ids.retain(|id| notes.contains(id) && stacked.insert(id.clone()));
+  }
+  doc.stacks.retain(|s| !s.note_ids.is_empty());
+  let stacks: HashMap<&str, &model::Stack> = doc.stacks.iter().map(|s| (s.id.as_str(), s)).collect();
+  for note in doc.notes.iter_mut() {
+    let member = note.stack_id.as_deref()
+      .and_then(|id| stacks.get(id))
+      .is_some_and(|stack| stack.note_ids.contains(&note.id));
+    if !member {
+      note.stack_id = None;
+    }
+  }
+  groups::prune_groups(doc);
+}
+
+// `theirs` merged into `ours`, three-way when `base` is their common ancestor
+pub fn merge(ours: &model::BoardDocument, theirs: &model::BoardDocument, base: Option<&model::BoardDocument>) -> Result<MergeResult, String> {
+  let mut report = MergeReport::default();
+  let mut doc = ours.clone();
+  doc.notes = merge_entities("note", &ours.notes, &theirs.notes, base.map(|b| b.notes.as_slice()), |n| &n.id, &mut report)?;
+  doc.connections = merge_entities("connection", &ours.connections, &theirs.connections, base.map(|b| b.connections.as_slice()), |c| &c.id, &mut report)?;
+  doc.stacks = merge_entities("stack", &ours.stacks, &theirs.stacks, base.map(|b| b.stacks.as_slice()), |s| &s.id, &mut report)?;
+  doc.shapes = merge_entities("shape", &ours.shapes, &theirs.shapes, base.map(|b| b.shapes.as_slice()), |s| &s.id, &mut report)?;
+  if ours.strokes.is_some() || theirs.strokes.is_some() {
+    let base_strokes = base.map(|b| b.strokes.as_deref().unwrap_or_default());
+    let strokes = merge_entities(
+      "stroke",
+      ours.strokes.as_deref().unwrap_or_default(),
+      theirs.strokes.as_deref().unwrap_or_default(),
+      base_strokes,
+      |s| &s.id,
+      &mut report,
+    )?;
+    doc.strokes = (!strokes.is_empty()).then_some(strokes);
+  }
+
+  union_by_id(&mut doc.note_styles, &theirs.note_styles, |s| &s.id);
+  union_optional(&mut doc.images, &theirs.images, |i| &i.id);
+  union_optional(&mut doc.relation_types, &theirs.relation_types, |r| &r.id);
+  union_optional(&mut doc.layers, &theirs.layers, |l| &l.id);
+  union_optional(&mut doc.groups, &theirs.groups, |g| &g.id);
+  union_optional(&mut doc.templates, &theirs.templates, |t| &t.id);
+  repair_references(&mut doc, &mut report);
+  Ok(MergeResult { document: doc, report })
+}
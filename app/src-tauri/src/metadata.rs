@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::{model, title};
+
+// When notes and boards were made and last changed, and by whom. The backend
+// stamps this when a board is saved, so it doesn't depend on every frontend
+// code path remembering to.
+//
+// Notes are compared with how they were when the board was last opened or
+// saved here: notes that weren't there get `createdAt`, notes whose content
+// changed get `updatedAt`. Notes from before timestamps existed keep no
+// creation time rather than being dated to the day they were first saved
+// again. The frontend's copy of the board doesn't see what saving stamped, so
+// timestamps it lacks are carried over from the last save.
+
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone)]
+struct KnownNote {
+  // The note without its timestamps, as board.json has it
+  content: Value,
+  created_at: Option<DateTime<Utc>>,
+  updated_at: Option<DateTime<Utc>>,
+}
+
+// The open board's notes and info as last opened or saved
+#[derive(Debug, Clone, Default)]
+pub struct NoteHistory {
+  notes: HashMap<model::ID, KnownNote>,
+  info: Option<model::DocumentInfo>,
+}
+
+fn content(note: &model::Note) -> Value {
+  let mut value = serde_json::to_value(note).unwrap_or(Value::Null);
+  if let Some(object) = value.as_object_mut() {
+    object.remove("createdAt");
+    object.remove("updatedAt");
+  }
+  value
+}
+
+impl NoteHistory {
+  pub fn of(doc: &model::BoardDocument) -> NoteHistory {
+    NoteHistory {
+      notes: doc.notes.iter()
+        .map(|note| (note.id.clone(), KnownNote { content: content(note), created_at: note.created_at, updated_at: note.updated_at }))
+        .collect(),
+      info: doc.info.clone(),
+    }
+  }
+}
+
+// Fill in what the last save stamped and `doc` lacks, without stamping anything new
+pub fn restore(doc: &mut model::BoardDocument, history: &NoteHistory) {
+  for note in doc.notes.iter_mut() {
+    if let Some(known) = history.notes.get(&note.id) {
+      note.created_at = note.created_at.or(known.created_at);
+      note.updated_at = note.updated_at.max(known.updated_at);
+    }
+  }
+  if let Some(known) = &history.info {
+    let info = doc.info.get_or_insert_with(Default::default);
+    info.author = info.author.take().or_else(|| known.author.clone());
+    info.created_at = info.created_at.or(known.created_at);
+    info.updated_at = info.updated_at.max(known.updated_at);
+    info.app_version = info.app_version.take().or_else(|| known.app_version.clone());
+  }
+}
+
+// The account name, for boards saved without an author set
+pub fn default_author(configured: &str) -> Option<String> {
+  let configured = configured.trim();
+  if !configured.is_empty() {
+    return Some(configured.to_string());
+  }
+  ["USER", "USERNAME"].iter()
+    .filter_map(|name| std::env::var(name).ok())
+    .map(|name| name.trim().to_string())
+    .find(|name| !name.is_empty())
+}
+
+// Stamp `doc` for saving at `now`. Without a history (a board whose file we
+// didn't open ourselves) only missing timestamps are carried over where known
+// and the board's own info is stamped.
+pub fn stamp(doc: &mut model::BoardDocument, history: Option<&NoteHistory>, author: Option<String>, now: DateTime<Utc>) {
+  if let Some(history) = history {
+    restore(doc, history);
+    for note in doc.notes.iter_mut() {
+      match history.notes.get(&note.id) {
+        Some(known) if known.content != content(note) => note.updated_at = Some(now),
+        Some(_) => {},
+        None => note.created_at = note.created_at.or(Some(now)),
+      }
+    }
+  }
+
+  let first_note = doc.notes.iter().filter_map(|note| note.created_at).min();
+  let info = doc.info.get_or_insert_with(Default::default);
+  info.created_at = info.created_at.or(first_note).or(Some(now));
+  info.updated_at = Some(now);
+  info.app_version = Some(APP_VERSION.to_string());
+  if info.author.as_deref().map_or(true, |a| a.trim().is_empty()) {
+    info.author = author;
+  }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DocumentSummary {
+  // The board's title, or the best suggestion for one
+  pub title: Option<String>,
+  pub author: Option<String>,
+  pub owner: Option<String>,
+  pub created_at: Option<DateTime<Utc>>,
+  pub updated_at: Option<DateTime<Utc>>,
+  // Version of the app that last saved the board
+  pub app_version: Option<String>,
+  pub note_count: usize,
+  pub connection_count: usize,
+  // Creation times of the oldest and newest notes that have one
+  pub first_note_at: Option<DateTime<Utc>>,
+  pub last_note_at: Option<DateTime<Utc>>,
+  // Notes without a creation time, from before timestamps were kept
+  pub undated_notes: usize,
+  pub path: Option<String>,
+}
+
+pub fn summary(doc: &model::BoardDocument, path: Option<String>) -> DocumentSummary {
+  let info = doc.info.clone().unwrap_or_default();
+  let created = || doc.notes.iter().filter_map(|note| note.created_at);
+  DocumentSummary {
+    title: info.title.clone().filter(|t| !t.trim().is_empty())
+      .or_else(|| title::suggest_titles(doc).into_iter().next().map(|s| s.title)),
+    author: info.author,
+    owner: info.owner,
+    created_at: info.created_at,
+    updated_at: info.updated_at,
+    app_version: info.app_version,
+    note_count: doc.notes.len(),
+    connection_count: doc.connections.len(),
+    first_note_at: created().min(),
+    last_note_at: created().max(),
+    undated_notes: doc.notes.iter().filter(|note| note.created_at.is_none()).count(),
+    path,
+  }
+}
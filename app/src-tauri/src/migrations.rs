@@ -0,0 +1,118 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{properties, tags};
+
+// Upgrades of board JSON between schema versions. Each migration turns a
+// board of version `from` into version `from + 1`, working on the raw JSON
+// before it is deserialized, so it can rename, move or reshape fields the
+// current model no longer has. Loading runs every migration from the file's
+// version up to `CURRENT_VERSION`. Boards from a newer version of the app are
+// left as they are and opened read-only, since saving them would drop what
+// this version doesn't know about.
+//
+// To change the model: bump `CURRENT_VERSION` and add the migration from the
+// previous version at the end of `MIGRATIONS`.
+
+pub const CURRENT_VERSION: u32 = 2;
+
+struct Migration {
+  from: u32,
+  // What changed, as shown to the user
+  description: &'static str,
+  apply: fn(&mut Map<String, Value>) -> Result<(), String>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    from: 0,
+    description: "Added the schema version to a board saved before boards were versioned",
+    apply: version_0_to_1,
+  },
+  Migration {
+    from: 1,
+    description: "Moved tags kept in note properties to the notes' own tags",
+    apply: version_1_to_2,
+  },
+];
+
+// Early boards had no `schemaVersion` but were otherwise version 1; anything
+// without notes isn't a board at all
+fn version_0_to_1(root: &mut Map<String, Value>) -> Result<(), String> {
+  if !root.get("notes").is_some_and(Value::is_array) {
+    return Err("Invalid or missing schema version".to_string());
+  }
+  Ok(())
+}
+
+// Notes gained `tags`. Tags kept by hand in a note's `tags` property, as a
+// list or comma-separated text, move there; other values stay properties.
+fn version_1_to_2(root: &mut Map<String, Value>) -> Result<(), String> {
+  let Some(notes) = root.get_mut("notes").and_then(Value::as_array_mut) else {
+    return Ok(());
+  };
+  for note in notes.iter_mut().filter_map(Value::as_object_mut) {
+    let Some(properties) = note.get_mut("properties").and_then(Value::as_object_mut) else {
+      continue;
+    };
+    let texts: Vec<String> = match properties.get("tags") {
+      Some(Value::Array(items)) => items.iter().map(properties::value_text).collect(),
+      Some(Value::String(text)) => text.split(',').map(str::to_string).collect(),
+      _ => continue,
+    };
+    properties.remove("tags");
+    if properties.is_empty() {
+      note.remove("properties");
+    }
+    let tags: Vec<Value> = texts.iter().filter_map(|tag| tags::normalize(tag)).map(Value::from).collect();
+    if !tags.is_empty() {
+      note.insert("tags".to_string(), Value::Array(tags));
+    }
+  }
+  Ok(())
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct MigrationReport {
+  pub from_version: u32,
+  pub to_version: u32,
+  // What each applied migration did, oldest first
+  pub applied: Vec<String>,
+  // Written by a newer version of the app
+  pub newer: bool,
+}
+
+impl MigrationReport {
+  // Nothing worth telling the user
+  pub fn is_empty(&self) -> bool {
+    self.applied.is_empty() && !self.newer
+  }
+}
+
+// Bring `value` up to `CURRENT_VERSION` in place
+pub fn migrate(value: &mut Value) -> Result<MigrationReport, String> {
+  let root = value.as_object_mut().ok_or("Invalid board: the document isn't a JSON object")?;
+  let version = match root.get("schemaVersion") {
+    None | Some(Value::Null) => 0,
+    Some(version) => version.as_u64()
+      .and_then(|v| u32::try_from(v).ok())
+      .ok_or("Invalid or missing schema version")?,
+  };
+
+  let mut report = MigrationReport { from_version: version, to_version: version, ..Default::default() };
+  if version > CURRENT_VERSION {
+    report.newer = true;
+    return Ok(report);
+  }
+  while report.to_version < CURRENT_VERSION {
+    let migration = MIGRATIONS.iter()
+      .find(|m| m.from == report.to_version)
+      .ok_or_else(|| format!("Unsupported schema version {}: no upgrade to version {}", report.to_version, report.to_version + 1))?;
+    (migration.apply)(root)
+      .map_err(|e| format!("Failed to upgrade board from schema version {}: {}", migration.from, e))?;
+    report.to_version = migration.from + 1;
+    root.insert("schemaVersion".to_string(), Value::from(report.to_version));
+    report.applied.push(migration.description.to_string());
+  }
+  Ok(report)
+}
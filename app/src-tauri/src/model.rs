@@ -39,6 +39,8 @@ pub struct TextStyle {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NoteStyle {
     pub id: ID,
+    // Display name; theme files match styles across boards by it
+    pub name: Option<String>,
     #[serde(rename = "textStyle")]
     pub text_style: TextStyle,
     pub fill: Option<String>,
@@ -63,6 +65,15 @@ pub struct DocumentStyle {
     #[serde(rename = "defaultShapeStyleId")]
     pub default_shape_style_id: Option<ID>,
     pub grid: Option<GridStyle>,
+    pub labels: Option<Vec<LabelDef>>,
+}
+
+// An entry of the document's label palette, referenced from `Note::labels`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LabelDef {
+    pub id: ID,
+    pub name: String,
+    pub color: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -70,6 +81,12 @@ pub struct BackgroundStyle {
     pub color: Option<String>,
     #[serde(rename = "textureId")]
     pub texture_id: Option<ID>,
+    // Board pixels per texture pixel; defaults to 1
+    #[serde(rename = "textureScale")]
+    pub texture_scale: Option<f64>,
+    // 0..1, drawn over the background color; defaults to 1
+    #[serde(rename = "textureOpacity")]
+    pub texture_opacity: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -96,6 +113,9 @@ pub struct ConnectionStyle {
     pub arrows: Option<String>,
     pub color: Option<String>,
     pub width: Option<f64>,
+    // "straight", "polyline" (default), "smooth" or "cubic"; see curves.rs
+    #[serde(rename = "curveType")]
+    pub curve_type: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -109,17 +129,73 @@ pub struct Connection {
     pub label: Option<String>,
     #[serde(rename = "bendPoints")]
     pub bend_points: Option<Vec<Point>>,
+    // Bézier control points for "cubic" curves, two per segment
+    #[serde(rename = "controlPoints")]
+    pub control_points: Option<Vec<Point>>,
+    #[serde(rename = "relationType")]
+    pub relation_type: Option<ID>,
+}
+
+// An entry of the document's relationship taxonomy, referenced from
+// `Connection::relation_type`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RelationType {
+    pub id: ID,
+    pub name: String,
+    // Style applied where the connection doesn't set its own
+    pub style: Option<ConnectionStyle>,
+    // Undirected relations ("related to") read the same both ways; defaults to true
+    pub directed: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BackgroundShape {
     pub id: ID,
+    // "rect" (default), "ellipse", "line" or "polygon"; see shapes.rs
+    pub kind: Option<String>,
     pub frame: Rect,
+    // Polygon/line points relative to the frame origin
+    pub points: Option<Vec<Point>>,
     pub radius: Option<f64>,
     pub magnetic: Option<bool>,
     #[serde(rename = "styleId")]
     pub style_id: Option<ID>,
     pub label: Option<String>,
+    #[serde(rename = "zIndex")]
+    pub z_index: Option<i64>,
+    pub locked: Option<bool>,
+    #[serde(rename = "layerId")]
+    pub layer_id: Option<ID>,
+}
+
+// A named layer; see layers.rs
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Layer {
+    pub id: ID,
+    pub name: String,
+    // Hidden layers are left out of the board and (by default) exports
+    pub visible: bool,
+    // Locked layers protect their entities like `locked` does
+    pub locked: bool,
+}
+
+// A freehand ink stroke; stored delta-encoded in board.json (see strokes.rs)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(into = "crate::strokes::EncodedStroke", try_from = "crate::strokes::EncodedStroke")]
+pub struct Stroke {
+    pub id: ID,
+    pub points: Vec<StrokePoint>,
+    pub color: Option<String>,
+    pub width: f64,
+    pub layer_id: Option<ID>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokePoint {
+    pub x: f64,
+    pub y: f64,
+    // 0..1; 1 for input without pressure (mouse, trackpad)
+    pub pressure: f64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -135,10 +211,54 @@ pub struct Stack {
     pub aligned_width: Option<f64>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChecklistItem {
+    pub text: String,
+    pub done: bool,
+    pub order: u32,
+}
+
+// Inline formatting over `[start, end)` of the note text, in UTF-16 code
+// units; unset attributes inherit from earlier spans / the note style
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TextSpan {
+    pub start: usize,
+    pub end: usize,
+    pub bold: Option<bool>,
+    pub italic: Option<bool>,
+    pub underline: Option<bool>,
+    pub strike: Option<bool>,
+    pub color: Option<String>,
+    pub link: Option<String>,
+}
+
+// Structured rows and columns of a table note
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NoteTable {
+    // Header cells; empty for tables without a header row
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Group {
+    pub id: ID,
+    #[serde(rename = "noteIds")]
+    pub note_ids: Vec<ID>,
+    #[serde(rename = "shapeIds")]
+    pub shape_ids: Vec<ID>,
+    pub label: Option<String>,
+    pub collapsed: Option<bool>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Note {
     pub id: ID,
     pub text: String,
+    // "plain" (default) or "markdown"
+    #[serde(rename = "contentFormat")]
+    pub content_format: Option<String>,
+    pub spans: Option<Vec<TextSpan>>,
     #[serde(rename = "richAttrs")]
     pub rich_attrs: Option<HashMap<String, serde_json::Value>>,
     pub frame: Rect,
@@ -150,6 +270,97 @@ pub struct Note {
     pub links: Option<Vec<String>>,
     pub images: Option<Vec<ID>>,
     pub connections: Option<Vec<ID>>,
+    #[serde(rename = "zIndex")]
+    pub z_index: Option<i64>,
+    pub locked: Option<bool>,
+    #[serde(rename = "layerId")]
+    pub layer_id: Option<ID>,
+    pub checklist: Option<Vec<ChecklistItem>>,
+    pub labels: Option<Vec<ID>>,
+    // Free-form keywords; see tags.rs
+    pub tags: Option<Vec<String>>,
+    pub table: Option<NoteTable>,
+    // Emoji or named symbol shown as a badge ("star", "warning", ...)
+    pub icon: Option<String>,
+    // Free-form metadata (frontmatter-style); see properties.rs
+    pub properties: Option<HashMap<String, serde_json::Value>>,
+    // Flagged as a key idea; starred notes get their own section in exports
+    pub starred: Option<bool>,
+    // Stamped by the frontend's command stack; see activity.rs
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Content encrypted at rest; see confidential.rs
+    pub confidential: Option<bool>,
+    // Encrypted content while the note is locked (`text` is empty then)
+    #[serde(rename = "sealedText")]
+    pub sealed_text: Option<String>,
+}
+
+// Key parameters for confidential notes; the key itself is never stored
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NoteEncryption {
+    #[serde(rename = "keyId")]
+    pub key_id: String,
+    // Argon2id salt, base64
+    pub salt: String,
+    // A known value encrypted with the key, to check passphrases against
+    pub verifier: String,
+}
+
+// About the board rather than its content: kept in the container's metadata
+// entry as well, so it can be read before anything is decrypted
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DocumentInfo {
+    pub title: Option<String>,
+    // Name, email or phone of whoever to ask about the board
+    pub owner: Option<String>,
+    // Reminder of the confidential notes' passphrase, never the passphrase itself
+    #[serde(rename = "passwordHint")]
+    pub password_hint: Option<String>,
+    // Who made the board; the rest is stamped on save, see metadata.rs
+    pub author: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+    // Version of the app that last saved the board
+    #[serde(rename = "appVersion")]
+    pub app_version: Option<String>,
+}
+
+// A reusable blueprint of notes; see templates.rs
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NoteTemplate {
+    pub id: ID,
+    pub name: String,
+    // Frames are relative to the insertion point; text may use {{placeholders}}
+    pub notes: Vec<Note>,
+}
+
+// Deleted notes and connections, kept with the document until the trash is
+// emptied; see trash.rs
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Trash {
+    #[serde(default)]
+    pub notes: Vec<TrashedNote>,
+    #[serde(default)]
+    pub connections: Vec<TrashedConnection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashedNote {
+    pub note: Note,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrashedConnection {
+    pub connection: Connection,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -165,4 +376,89 @@ pub struct BoardDocument {
     #[serde(rename = "documentStyle")]
     pub document_style: Option<DocumentStyle>,
     pub images: Option<Vec<EmbeddedImage>>,
+    pub groups: Option<Vec<Group>>,
+    #[serde(rename = "relationTypes")]
+    pub relation_types: Option<Vec<RelationType>>,
+    pub strokes: Option<Vec<Stroke>>,
+    pub layers: Option<Vec<Layer>>,
+    pub templates: Option<Vec<NoteTemplate>>,
+    pub properties: Option<HashMap<String, serde_json::Value>>,
+    pub trash: Option<Trash>,
+    #[serde(rename = "noteEncryption")]
+    pub note_encryption: Option<NoteEncryption>,
+    pub info: Option<DocumentInfo>,
+}
+
+// Generate an ID for an entity the backend creates: `<prefix>_<ULID>`. ULIDs
+// start with the creation time, so IDs of one kind sort in creation order,
+// and the generator is monotonic, so IDs made within the same millisecond
+// (a whole import) still differ and keep their order.
+pub fn new_id(prefix: &str) -> ID {
+    static GENERATOR: std::sync::Mutex<ulid::Generator> = std::sync::Mutex::new(ulid::Generator::new());
+
+    let ulid = GENERATOR.lock()
+        .ok()
+        .and_then(|mut generator| generator.generate().ok())
+        // The random part ran out within one millisecond, or the lock is poisoned
+        .unwrap_or_else(|| ulid::Ulid::from_datetime(std::time::SystemTime::now()));
+    format!("{}_{}", prefix, ulid)
+}
+
+// Locked entities are reference material: backend operations that move,
+// restyle or remove things skip them and report their IDs instead.
+impl Note {
+    pub fn is_locked(&self) -> bool {
+        self.locked.unwrap_or(false)
+    }
+
+    pub fn is_starred(&self) -> bool {
+        self.starred.unwrap_or(false)
+    }
+
+    // Boards from before `contentFormat` flag Markdown through richAttrs
+    pub fn is_markdown(&self) -> bool {
+        match self.content_format.as_deref() {
+            Some(format) => format == "markdown",
+            None => self.rich_attrs.as_ref()
+                .and_then(|attrs| attrs.get("markdownEnabled"))
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl BackgroundShape {
+    pub fn is_locked(&self) -> bool {
+        self.locked.unwrap_or(false)
+    }
+}
+
+impl BoardDocument {
+    pub fn empty() -> Self {
+        BoardDocument {
+            schema_version: crate::migrations::CURRENT_VERSION,
+            notes: Vec::new(),
+            connections: Vec::new(),
+            shapes: Vec::new(),
+            stacks: Vec::new(),
+            note_styles: Vec::new(),
+            document_style: None,
+            images: None,
+            groups: None,
+            relation_types: None,
+            strokes: None,
+            layers: None,
+            templates: None,
+            properties: None,
+            trash: None,
+            note_encryption: None,
+            info: None,
+        }
+    }
+    // The note's own style, else the document default
+    pub fn note_style(&self, note: &Note) -> Option<&NoteStyle> {
+        let style_id = note.style_id.as_ref()
+            .or_else(|| self.document_style.as_ref().and_then(|s| s.default_note_style_id.as_ref()))?;
+        self.note_styles.iter().find(|s| &s.id == style_id)
+    }
 }
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::{file_watch, metadata, recent_files, snap};
+
+// Which window has which board open, and what the backend keeps about that
+// board: its file, dirty and read-only state, autosave time, the watcher on
+// its file and so on. Each window works on its own board, so this is kept per
+// window label rather than once for the app, and commands find their board by
+// the window that called them.
+//
+// Opening a board that another window already shows would give two copies
+// that are edited and saved over each other, so the open commands ask here
+// first and send the user to the window that has it instead. Boards are
+// compared by canonical path, so a board reached through a symlink or a
+// relative path is still the same board.
+
+#[derive(Debug, Default)]
+pub struct WindowDocument {
+  // The board's file; None for a board not saved yet
+  pub path: Option<String>,
+  // Canonical form of `path`
+  key: Option<String>,
+  // That file as we last read or wrote it, to notice changes made elsewhere
+  // before saving over them, and the watcher reporting them as they happen
+  pub stamp: Option<file_watch::FileStamp>,
+  pub watcher: Option<file_watch::DocumentWatcher>,
+  pub last_autosave_time: Option<std::time::SystemTime>,
+  pub is_dirty: bool,
+  // The board was opened with `open_readonly`; see `ensure_writable`
+  pub read_only: bool,
+  // Notes of the board being dragged on, bucketed for snapping
+  pub snap_index: Option<snap::SnapIndex>,
+  // The board's notes as last opened or saved, to tell what saving should
+  // stamp; see metadata.rs
+  pub note_history: Option<metadata::NoteHistory>,
+}
+
+impl WindowDocument {
+  // The board now comes from `path`
+  pub fn set_path(&mut self, path: &Path) {
+    self.path = Some(path.to_string_lossy().to_string());
+    self.key = Some(OpenDocuments::key(path));
+  }
+
+  fn shows(&self, key: &str) -> bool {
+    self.key.as_deref() == Some(key)
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct OpenDocuments {
+  // Window label to its board
+  windows: HashMap<String, WindowDocument>,
+}
+
+impl OpenDocuments {
+  fn key(path: &Path) -> String {
+    recent_files::canonical_path(&path.to_string_lossy())
+  }
+
+  // The window other than `label` that has the board open
+  pub fn owner_other_than(&self, path: &Path, label: &str) -> Option<&str> {
+    let key = Self::key(path);
+    self.windows.iter()
+      .find(|(owner, document)| *owner != label && document.shows(&key))
+      .map(|(owner, _)| owner.as_str())
+  }
+
+  pub fn owner(&self, path: &Path) -> Option<&str> {
+    let key = Self::key(path);
+    self.windows.iter()
+      .find(|(_, document)| document.shows(&key))
+      .map(|(owner, _)| owner.as_str())
+  }
+
+  // The board of window `label`, if it has one
+  pub fn window(&self, label: &str) -> Option<&WindowDocument> {
+    self.windows.get(label)
+  }
+
+  pub fn window_mut(&mut self, label: &str) -> &mut WindowDocument {
+    self.windows.entry(label.to_string()).or_default()
+  }
+
+  // The window showing the board at `path`, if one does
+  pub fn showing_mut(&mut self, path: &Path) -> Option<&mut WindowDocument> {
+    let key = Self::key(path);
+    self.windows.values_mut().find(|document| document.shows(&key))
+  }
+
+  // The window now shows `path`, in place of whatever it showed before
+  pub fn open(&mut self, path: &Path, label: &str) -> &mut WindowDocument {
+    let document = self.window_mut(label);
+    document.set_path(path);
+    document
+  }
+
+  // The window was closed; its board and the watcher on its file go with it
+  pub fn close_window(&mut self, label: &str) {
+    self.windows.remove(label);
+  }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use crate::{icons, model, tags};
+
+// OPML import. Plain outlines from any outliner become a tree of notes wired
+// by connections; the `_`-prefixed attributes our exporter writes (see
+// `write_opml_outline`) restore IDs, frames, stacks, styles, links, tags,
+// relation types and cross-links, so export → import keeps the board's
+// structure.
+
+const NOTE_WIDTH: f64 = 200.0;
+const NOTE_HEIGHT: f64 = 80.0;
+const COLUMN_WIDTH: f64 = 260.0;
+const ROW_HEIGHT: f64 = 100.0;
+const MARGIN: f64 = 40.0;
+const STYLE_FONT: &str = "system-ui";
+const STYLE_SIZE: f64 = 14.0;
+
+struct Importer {
+  doc: model::BoardDocument,
+  // Exported note IDs to the IDs they were imported under
+  ids: HashMap<String, model::ID>,
+  // (stack ID, position, note ID)
+  stack_entries: Vec<(model::ID, usize, model::ID)>,
+  // (source note, exported target ID, connection template)
+  refs: Vec<(model::ID, String, model::Connection)>,
+  row: usize,
+}
+
+fn connection(node: &roxmltree::Node, src: &model::ID, dst: &model::ID, relation: Option<&model::ID>) -> model::Connection {
+  model::Connection {
+    id: node.attribute("_connection").map(str::to_string).unwrap_or_else(|| model::new_id("conn")),
+    src_note_id: src.clone(),
+    dst_note_id: dst.clone(),
+    style: None,
+    label: node.attribute("_label").map(str::to_string),
+    bend_points: None,
+    control_points: None,
+    relation_type: relation.cloned(),
+  }
+}
+
+fn parse_frame(value: &str) -> Option<model::Rect> {
+  let parts: Vec<f64> = value.split(',').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+  match parts[..] {
+    [x, y, w, h] if parts.iter().all(|v| v.is_finite()) && w > 0.0 && h > 0.0 => Some(model::Rect { x, y, w, h }),
+    _ => None,
+  }
+}
+
+impl Importer {
+  fn add_style(&mut self, node: &roxmltree::Node, style_id: &str) {
+    if self.doc.note_styles.iter().any(|s| s.id == style_id) {
+      return;
+    }
+    self.doc.note_styles.push(model::NoteStyle {
+      id: style_id.to_string(),
+      name: None,
+      text_style: model::TextStyle {
+        font: STYLE_FONT.to_string(),
+        size: STYLE_SIZE,
+        weight: None,
+        italic: None,
+        underline: None,
+        strike: None,
+        color: node.attribute("_color").map(str::to_string),
+        align: None,
+      },
+      fill: node.attribute("_fill").map(str::to_string),
+      border: None,
+      corner_radius: None,
+      shadow: None,
+    });
+  }
+
+  fn add_note(&mut self, node: &roxmltree::Node, depth: usize) -> model::ID {
+    let exported_id = node.attribute("_id");
+    let id = match exported_id {
+      Some(id) if !id.is_empty() && !self.doc.notes.iter().any(|n| n.id == id) => id.to_string(),
+      _ => model::new_id("note"),
+    };
+    if let Some(exported) = exported_id {
+      self.ids.entry(exported.to_string()).or_insert_with(|| id.clone());
+    }
+
+    let icon = node.attribute("_icon").map(str::to_string);
+    let text = node.attribute("text").unwrap_or("");
+    let (text, content_format) = match node.attribute("_markdown") {
+      Some(source) => (source.to_string(), Some("markdown".to_string())),
+      // The exported text starts with the icon's symbol
+      None => {
+        let prefix = icons::prefix(icon.as_deref());
+        (text.strip_prefix(prefix.as_str()).unwrap_or(text).to_string(), None)
+      },
+    };
+
+    let links: Vec<String> = match node.attribute("_note") {
+      Some(all) => all.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect(),
+      None => node.attribute("url").map(|u| vec![u.to_string()]).unwrap_or_default(),
+    };
+
+    let date = |name: &str| node.attribute(name)
+      .and_then(|d| chrono::DateTime::parse_from_rfc2822(d).or_else(|_| chrono::DateTime::parse_from_rfc3339(d)).ok())
+      .map(|d| d.with_timezone(&chrono::Utc));
+
+    let tags: Vec<String> = node.attribute("_tags").unwrap_or("").split(',').filter_map(tags::normalize).collect();
+
+    let style_id = node.attribute("_style").map(str::to_string);
+    if let Some(style_id) = &style_id {
+      self.add_style(node, style_id);
+    }
+
+    let stack_id = match (node.attribute("_stack"), node.attribute("_stackIndex")) {
+      (Some(stack), Some(index)) => {
+        self.stack_entries.push((stack.to_string(), index.parse().unwrap_or(usize::MAX), id.clone()));
+        Some(stack.to_string())
+      },
+      _ => None,
+    };
+
+    let properties: HashMap<String, serde_json::Value> = node.attributes()
+      .filter_map(|a| Some((a.name().strip_prefix("_prop_")?.to_string(), serde_json::Value::String(a.value().to_string()))))
+      .collect();
+
+    let frame = node.attribute("_frame").and_then(parse_frame).unwrap_or(model::Rect {
+      x: MARGIN + depth as f64 * COLUMN_WIDTH,
+      y: MARGIN + self.row as f64 * ROW_HEIGHT,
+      w: NOTE_WIDTH,
+      h: NOTE_HEIGHT,
+    });
+    self.row += 1;
+
+    self.doc.notes.push(model::Note {
+      id: id.clone(),
+      text,
+      content_format,
+      spans: None,
+      rich_attrs: None,
+      frame,
+      style_id,
+      faded: (node.attribute("_faded") == Some("true")).then_some(true),
+      stack_id,
+      links: (!links.is_empty()).then_some(links),
+      images: None,
+      connections: None,
+      z_index: None,
+      locked: None,
+      layer_id: None,
+      checklist: None,
+      labels: None,
+      tags: (!tags.is_empty()).then_some(tags),
+      table: None,
+      icon,
+      properties: (!properties.is_empty()).then_some(properties),
+      starred: (node.attribute("_starred") == Some("true")).then_some(true),
+      created_at: date("created").or_else(|| Some(chrono::Utc::now())),
+      updated_at: date("_modified"),
+      confidential: None,
+      sealed_text: None,
+    });
+    id
+  }
+
+  fn add_checklist_item(&mut self, note_id: &model::ID, node: &roxmltree::Node) {
+    let Some(note) = self.doc.notes.iter_mut().find(|n| &n.id == note_id) else {
+      return;
+    };
+    let items = note.checklist.get_or_insert_with(Vec::new);
+    items.push(model::ChecklistItem {
+      text: node.attribute("text").unwrap_or("").to_string(),
+      done: node.attribute("_status") == Some("checked"),
+      order: items.len() as u32,
+    });
+  }
+
+  fn visit(&mut self, node: roxmltree::Node, parent: Option<&model::ID>, relation: Option<&model::ID>, depth: usize) {
+    for child in node.children().filter(|c| c.has_tag_name("outline")) {
+      // Checklist items of the parent note
+      if let (Some(parent), Some(_)) = (parent, child.attribute("_status")) {
+        self.add_checklist_item(parent, &child);
+        continue;
+      }
+      // Link to a note written elsewhere in the outline
+      if let (Some(parent), Some(target)) = (parent, child.attribute("_ref")) {
+        let template = connection(&child, parent, parent, relation);
+        self.refs.push((parent.clone(), target.to_string(), template));
+        continue;
+      }
+      // Relation type grouping: children connect to the parent with this type
+      if let (Some(parent), Some(relation_id)) = (parent, child.attribute("_relation")) {
+        let relation_id = relation_id.to_string();
+        let relation_types = self.doc.relation_types.get_or_insert_with(Vec::new);
+        if !relation_types.iter().any(|r| r.id == relation_id) {
+          relation_types.push(model::RelationType {
+            id: relation_id.clone(),
+            name: child.attribute("text").unwrap_or(&relation_id).to_string(),
+            style: None,
+            directed: None,
+          });
+        }
+        self.visit(child, Some(parent), Some(&relation_id), depth);
+        continue;
+      }
+
+      let id = self.add_note(&child, depth);
+      if let Some(parent) = parent {
+        let conn = connection(&child, parent, &id, relation);
+        self.doc.connections.push(conn);
+      }
+      self.visit(child, Some(&id), None, depth + 1);
+    }
+  }
+
+  fn finish(mut self) -> model::BoardDocument {
+    for (src, target, mut conn) in std::mem::take(&mut self.refs) {
+      // References to notes that aren't in the file are dropped
+      if let Some(dst) = self.ids.get(&target) {
+        conn.src_note_id = src;
+        conn.dst_note_id = dst.clone();
+        self.doc.connections.push(conn);
+      }
+    }
+
+    // Stacks in order of first appearance, notes in their exported positions
+    let mut stacks: Vec<(model::ID, Vec<(usize, model::ID)>)> = Vec::new();
+    for (stack_id, index, note_id) in self.stack_entries {
+      match stacks.iter_mut().find(|(id, _)| id == &stack_id) {
+        Some((_, entries)) => entries.push((index, note_id)),
+        None => stacks.push((stack_id, vec![(index, note_id)])),
+      }
+    }
+    for (id, mut entries) in stacks {
+      entries.sort_by_key(|(index, _)| *index);
+      self.doc.stacks.push(model::Stack {
+        id,
+        note_ids: entries.into_iter().map(|(_, note_id)| note_id).collect(),
+        orientation: None,
+        spacing: None,
+        indent_levels: None,
+        aligned_width: None,
+      });
+    }
+    self.doc
+  }
+}
+
+pub fn import(xml: &str) -> Result<model::BoardDocument, String> {
+  let tree = roxmltree::Document::parse(xml)
+    .map_err(|e| format!("Failed to parse OPML: {}", e))?;
+  let root = tree.root_element();
+  if !root.has_tag_name("opml") {
+    return Err("Not an OPML file: the root element isn't <opml>".to_string());
+  }
+  let body = root.children().find(|c| c.has_tag_name("body"))
+    .ok_or("OPML file has no <body>")?;
+
+  let mut importer = Importer {
+    doc: model::BoardDocument::empty(),
+    ids: HashMap::new(),
+    stack_entries: Vec::new(),
+    refs: Vec::new(),
+    row: 0,
+  };
+  importer.visit(body, None, None, 0);
+  if importer.doc.notes.is_empty() {
+    return Err("The OPML file has no outline items".to_string());
+  }
+  Ok(importer.finish())
+}
@@ -0,0 +1,567 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::{checklist, icons, links, markdown, model, rich_text, tables, tags};
+
+// A tree of text items, used wherever the board is exchanged as an outline
+// (clipboard, Markdown, indented text).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct OutlineNode {
+  // Source note, used as the anchor for intra-document links in HTML
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pub id: Option<model::ID>,
+  pub text: String,
+  // Note icon, rendered in front of the text
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pub icon: Option<String>,
+  // Inline formatting of `text`, used by the HTML flavor
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pub spans: Option<Vec<model::TextSpan>>,
+  // `text` is Markdown source (written verbatim to Markdown, rendered for HTML)
+  #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+  pub markdown: bool,
+  // Set for checklist items: whether the task is done
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pub done: Option<bool>,
+  // Table payload of table notes, rendered after the item text
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pub table: Option<model::NoteTable>,
+  #[serde(skip_serializing_if = "Vec::is_empty", default)]
+  pub links: Vec<OutlineLink>,
+  // Note tags, written as hashtags after the item text in Markdown
+  #[serde(skip_serializing_if = "Vec::is_empty", default)]
+  pub tags: Vec<String>,
+  pub children: Vec<OutlineNode>,
+}
+
+// A resolved note link: the target note's first line for `note://` links
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OutlineLink {
+  pub text: String,
+  // None when the target isn't part of the outline
+  pub href: Option<String>,
+  // The target note of an internal link
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pub target: Option<model::ID>,
+}
+
+// Build an outline from notes, treating connections as parent → child edges.
+// Notes without incoming connections become roots; `ordered` decides sibling order.
+pub fn build_outline(doc: &model::BoardDocument, ordered: &[model::Note]) -> Vec<OutlineNode> {
+  let included: HashSet<&str> = ordered.iter().map(|n| n.id.as_str()).collect();
+  let mut visited = HashSet::new();
+  let mut roots = Vec::new();
+
+  let is_root = |note: &model::Note| !doc.connections.iter().any(|c| {
+    c.dst_note_id == note.id && c.src_note_id != note.id && included.contains(c.src_note_id.as_str())
+  });
+
+  for note in ordered.iter().filter(|n| is_root(n)) {
+    if let Some(node) = outline_node(note, doc, ordered, &mut visited) {
+      roots.push(node);
+    }
+  }
+
+  // Notes only reachable through a cycle have no root; add them at the top level
+  for note in ordered {
+    if let Some(node) = outline_node(note, doc, ordered, &mut visited) {
+      roots.push(node);
+    }
+  }
+
+  roots
+}
+
+fn outline_node<'a>(
+  note: &'a model::Note,
+  doc: &model::BoardDocument,
+  ordered: &'a [model::Note],
+  visited: &mut HashSet<&'a str>,
+) -> Option<OutlineNode> {
+  if !visited.insert(note.id.as_str()) {
+    return None;
+  }
+
+  // Checklist items come first, as leaf children ahead of connected notes
+  let tasks = checklist::ordered_items(note).into_iter()
+    .map(|item| OutlineNode { text: item.text.clone(), done: Some(item.done), ..Default::default() });
+  let connected: Vec<OutlineNode> = ordered.iter()
+    .filter(|child| doc.connections.iter().any(|c| c.src_note_id == note.id && c.dst_note_id == child.id))
+    .filter_map(|child| outline_node(child, doc, ordered, visited))
+    .collect();
+  let children = tasks.chain(connected).collect();
+
+  Some(OutlineNode {
+    id: Some(note.id.clone()),
+    text: note.text.clone(),
+    icon: note.icon.clone(),
+    spans: note.spans.clone(),
+    markdown: note.is_markdown(),
+    done: None,
+    table: note.table.clone(),
+    links: resolve_links(note, doc, ordered),
+    tags: tags::note_tags(note),
+    children,
+  })
+}
+
+// A PDF outline (bookmarks panel) entry: one per note, nested like the outline
+#[derive(Serialize, Debug, Clone)]
+pub struct Bookmark {
+  pub title: String,
+  pub note_id: model::ID,
+  // Board-space frame of the note, for writers that can target a position
+  pub frame: model::Rect,
+  pub children: Vec<Bookmark>,
+}
+
+// Bookmarks for a PDF export: the outline tree without checklist items, titled
+// by the first line of each note's plain text
+pub fn bookmarks(doc: &model::BoardDocument, ordered: &[model::Note]) -> Vec<Bookmark> {
+  fn convert(nodes: &[OutlineNode], doc: &model::BoardDocument) -> Vec<Bookmark> {
+    nodes.iter()
+      .filter_map(|node| {
+        let note = doc.notes.iter().find(|n| Some(&n.id) == node.id.as_ref())?;
+        let text = markdown::note_rich_text(note).0;
+        Some(Bookmark {
+          title: format!("{}{}", icons::note_prefix(note), text.lines().next().unwrap_or("").trim()),
+          note_id: note.id.clone(),
+          frame: note.frame.clone(),
+          children: convert(&node.children, doc),
+        })
+      })
+      .collect()
+  }
+  convert(&build_outline(doc, ordered), doc)
+}
+
+fn resolve_links(note: &model::Note, doc: &model::BoardDocument, ordered: &[model::Note]) -> Vec<OutlineLink> {
+  note.links.iter().flatten()
+    .filter_map(|link| match links::note_link_target(link) {
+      Some(target) => {
+        // Broken internal links are dropped; validate_document reports them
+        let target_note = doc.notes.iter().find(|n| n.id == target)?;
+        Some(OutlineLink {
+          text: target_note.text.lines().next().unwrap_or("").to_string(),
+          href: ordered.iter().any(|n| n.id == target).then(|| links::href(link)),
+          target: Some(target.to_string()),
+        })
+      },
+      None => Some(OutlineLink { text: link.clone(), href: Some(link.clone()), target: None }),
+    })
+    .collect()
+}
+
+pub fn to_markdown(nodes: &[OutlineNode]) -> String {
+  to_markdown_with_anchors(nodes, &HashSet::new())
+}
+
+// Like `to_markdown`, with an HTML anchor (`links::anchor_id`) at the start of
+// the items of the notes in `anchored`, so links elsewhere in the file can
+// point at them
+pub fn to_markdown_with_anchors(nodes: &[OutlineNode], anchored: &HashSet<&str>) -> String {
+  let mut output = String::new();
+  write_markdown(nodes, 0, anchored, &mut output);
+  output
+}
+
+fn write_markdown(nodes: &[OutlineNode], depth: usize, anchored: &HashSet<&str>, output: &mut String) {
+  let indent = "  ".repeat(depth);
+  for node in nodes {
+    let mut lines = node.text.lines();
+    let marker = match node.done {
+      Some(done) => format!("- {} ", checklist::checkbox(done)),
+      None => "- ".to_string(),
+    };
+    let anchor = node.id.as_deref()
+      .filter(|id| anchored.contains(id))
+      .map(|id| format!("<a id=\"{}\"></a>", links::anchor_id(id)))
+      .unwrap_or_default();
+    let hashtags: String = node.tags.iter().map(|tag| format!(" {}", tags::hashtag(tag))).collect();
+    output.push_str(&format!("{}{}{}{}{}{}\n", indent, marker, anchor, icons::prefix(node.icon.as_deref()), lines.next().unwrap_or(""), hashtags));
+    // Continuation lines stay inside the list item
+    for line in lines {
+      output.push_str(&format!("{}  {}\n", indent, line));
+    }
+    if let Some(table) = &node.table {
+      output.push('\n');
+      for line in tables::to_markdown(table).lines() {
+        output.push_str(&format!("{}  {}\n", indent, line));
+      }
+      output.push('\n');
+    }
+    // Internal links are cross-references, linked when their note has an anchor
+    for link in &node.links {
+      let line = match (&link.target, &link.href) {
+        (Some(target), Some(href)) if anchored.contains(target.as_str()) => format!("→ [{}]({})", link.text, href),
+        (Some(_), _) => format!("→ {}", link.text),
+        (None, _) => format!("<{}>", link.text),
+      };
+      output.push_str(&format!("{}  {}\n", indent, line));
+    }
+    write_markdown(&node.children, depth + 1, anchored, output);
+  }
+}
+
+pub fn to_html(nodes: &[OutlineNode]) -> String {
+  if nodes.is_empty() {
+    return String::new();
+  }
+
+  let mut html = "<ul>".to_string();
+  for node in nodes {
+    match &node.id {
+      Some(id) => html += &format!("<li id=\"{}\">", html_escape(&links::anchor_id(id))),
+      None => html += "<li>",
+    }
+    match node.done {
+      Some(true) => html += "&#9745; ",
+      Some(false) => html += "&#9744; ",
+      None => {},
+    }
+    html += &html_escape(&icons::prefix(node.icon.as_deref()));
+    if node.markdown {
+      html += &markdown::to_html(&node.text);
+    } else {
+      html += &rich_text::to_html(&node.text, node.spans.as_deref().unwrap_or(&[]), html_escape);
+    }
+    if let Some(table) = &node.table {
+      html += &tables::to_html(table, html_escape);
+    }
+    for link in &node.links {
+      html += "<br>";
+      match &link.href {
+        Some(href) => html += &format!("<a href=\"{}\">{}</a>", html_escape(href), html_escape(&link.text)),
+        None => html += &html_escape(&link.text),
+      }
+    }
+    html += &to_html(&node.children);
+    html += "</li>";
+  }
+  html += "</ul>";
+  html
+}
+
+pub fn html_escape(text: &str) -> String {
+  text.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
+}
+
+// Parse text where nesting is expressed by indentation (tabs or spaces) and
+// items may carry list markers such as `-`, `*`, `+` or `1.`.
+pub fn parse_indented_text(text: &str) -> Vec<OutlineNode> {
+  // (indent, node) pairs; the stack holds the chain of open ancestors
+  let mut stack: Vec<(usize, OutlineNode)> = Vec::new();
+  let mut roots = Vec::new();
+
+  for line in text.lines() {
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let indent = indentation_width(line);
+    let item = match checklist::parse_checklist_line(line) {
+      Some((done, text)) => OutlineNode { text: text.to_string(), done: Some(done), ..Default::default() },
+      None => OutlineNode { text: strip_list_marker(line.trim()).to_string(), ..Default::default() },
+    };
+
+    while stack.last().is_some_and(|(open_indent, _)| *open_indent >= indent) {
+      close_outline_item(&mut stack, &mut roots);
+    }
+    stack.push((indent, item));
+  }
+
+  while !stack.is_empty() {
+    close_outline_item(&mut stack, &mut roots);
+  }
+
+  roots
+}
+
+// Parse a Markdown outline: headings nest by level, and the lines under a
+// heading (nested by indentation, as in `parse_indented_text`) become its
+// children. Front matter, code fences and horizontal rules are skipped, and
+// items that use Markdown syntax are marked as Markdown.
+pub fn parse_markdown_outline(text: &str) -> Vec<OutlineNode> {
+  let mut headings: Vec<(usize, OutlineNode)> = Vec::new();
+  let mut roots = Vec::new();
+  let mut body = String::new();
+
+  for line in skip_front_matter(text).lines() {
+    let trimmed = line.trim();
+    if trimmed.starts_with("```") || trimmed.starts_with("~~~") || is_horizontal_rule(trimmed) {
+      continue;
+    }
+    let Some((level, title)) = heading(trimmed) else {
+      body.push_str(line);
+      body.push('\n');
+      continue;
+    };
+    attach_items(parse_indented_text(&body), &mut headings, &mut roots);
+    body.clear();
+    while headings.last().is_some_and(|(open_level, _)| *open_level >= level) {
+      close_outline_item(&mut headings, &mut roots);
+    }
+    headings.push((level, OutlineNode { text: title.to_string(), ..Default::default() }));
+  }
+  attach_items(parse_indented_text(&body), &mut headings, &mut roots);
+  while !headings.is_empty() {
+    close_outline_item(&mut headings, &mut roots);
+  }
+
+  fn mark_markdown(nodes: &mut [OutlineNode]) {
+    for node in nodes {
+      node.markdown = markdown::looks_like_markdown(&node.text);
+      mark_markdown(&mut node.children);
+    }
+  }
+  mark_markdown(&mut roots);
+  roots
+}
+
+fn attach_items(items: Vec<OutlineNode>, headings: &mut [(usize, OutlineNode)], roots: &mut Vec<OutlineNode>) {
+  match headings.last_mut() {
+    Some((_, heading)) => heading.children.extend(items),
+    None => roots.extend(items),
+  }
+}
+
+// `## Title` → (2, "Title"); closing hashes are dropped
+fn heading(line: &str) -> Option<(usize, &str)> {
+  let level = line.chars().take_while(|c| *c == '#').count();
+  if level == 0 || level > 6 {
+    return None;
+  }
+  let rest = &line[level..];
+  if !rest.is_empty() && !rest.starts_with(' ') {
+    return None;
+  }
+  Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+// `---`, `***`, `___` or spaced variants such as `- - -`
+fn is_horizontal_rule(line: &str) -> bool {
+  let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+  marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|c| *c == marks[0])
+}
+
+// The text after a leading `---` … `---` YAML block
+fn skip_front_matter(text: &str) -> &str {
+  let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+    return text;
+  };
+  let mut offset = 0;
+  for line in rest.split_inclusive('\n') {
+    offset += line.len();
+    if line.trim_end() == "---" {
+      return &rest[offset..];
+    }
+  }
+  text
+}
+
+fn close_outline_item(stack: &mut Vec<(usize, OutlineNode)>, roots: &mut Vec<OutlineNode>) {
+  if let Some((_, node)) = stack.pop() {
+    match stack.last_mut() {
+      Some((_, parent)) => parent.children.push(node),
+      None => roots.push(node),
+    }
+  }
+}
+
+fn indentation_width(line: &str) -> usize {
+  line.chars()
+    .take_while(|c| c.is_whitespace())
+    .map(|c| if c == '\t' { 4 } else { 1 })
+    .sum()
+}
+
+fn strip_list_marker(line: &str) -> &str {
+  for marker in ["- [ ] ", "- [x] ", "- [X] ", "- ", "* ", "+ ", "• "] {
+    if let Some(rest) = line.strip_prefix(marker) {
+      return rest.trim_start();
+    }
+  }
+
+  // Ordered list markers: `1.` or `1)`
+  let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+  if digits > 0 {
+    let rest = &line[digits..];
+    if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+      return rest.trim_start();
+    }
+  }
+
+  line
+}
+
+// Text looks like an outline when it has several items and either uses list
+// markers or expresses nesting through indentation.
+pub fn looks_like_outline(text: &str) -> bool {
+  let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+  if lines.len() < 2 {
+    return false;
+  }
+
+  let marked = lines.iter().filter(|l| strip_list_marker(l.trim()) != l.trim()).count();
+  let indented = lines.iter().any(|l| indentation_width(l) > 0);
+  marked * 2 >= lines.len() || indented
+}
+
+// How an imported outline lands on the board
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct OutlineImportOptions {
+  // "connections" (default): a left-to-right tree with parent → child
+  // connections; "stack": one indented stack
+  pub mode: Option<String>,
+  // Top-left of the imported notes; defaults to the board origin
+  pub origin: Option<model::Point>,
+}
+
+// Notes and structure ready to insert into a board
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct OutlineImport {
+  pub notes: Vec<model::Note>,
+  pub connections: Vec<model::Connection>,
+  pub stacks: Vec<model::Stack>,
+}
+
+pub(crate) const IMPORT_NOTE_WIDTH: f64 = 200.0;
+pub(crate) const IMPORT_NOTE_HEIGHT: f64 = 60.0;
+pub(crate) const IMPORT_COLUMN_WIDTH: f64 = 260.0;
+pub(crate) const IMPORT_ROW_GAP: f64 = 20.0;
+const IMPORT_STACK_INDENT: f64 = 24.0;
+
+pub(crate) fn imported_note(text: &str, frame: model::Rect) -> model::Note {
+  model::Note {
+    id: model::new_id("note"),
+    text: text.to_string(),
+    content_format: None,
+    spans: None,
+    rich_attrs: None,
+    frame,
+    style_id: None,
+    faded: None,
+    stack_id: None,
+    links: None,
+    images: None,
+    connections: None,
+    z_index: None,
+    locked: None,
+    layer_id: None,
+    checklist: None,
+    labels: None,
+    tags: None,
+    table: None,
+    icon: None,
+    properties: None,
+    starred: None,
+    created_at: Some(chrono::Utc::now()),
+    updated_at: None,
+    confidential: None,
+    sealed_text: None,
+  }
+}
+
+// Turn outline items into auto-laid-out notes. Checklist items under an item
+// become that note's checklist; top-level ones become notes of their own.
+pub fn to_notes(nodes: &[OutlineNode], options: &OutlineImportOptions) -> Result<OutlineImport, String> {
+  let as_stack = match options.mode.as_deref().unwrap_or("connections") {
+    "connections" => false,
+    "stack" => true,
+    other => return Err(format!("Unsupported outline import mode '{}'. Must be one of: connections, stack", other)),
+  };
+  let origin = options.origin.clone().unwrap_or(model::Point { x: 0.0, y: 0.0 });
+
+  // Depth-first, so rows follow the outline's reading order
+  fn place(
+    nodes: &[OutlineNode],
+    parent: Option<usize>,
+    depth: usize,
+    as_stack: bool,
+    origin: &model::Point,
+    result: &mut OutlineImport,
+    depths: &mut Vec<usize>,
+  ) {
+    for node in nodes {
+      if let (Some(parent), Some(done)) = (parent, node.done) {
+        let items = result.notes[parent].checklist.get_or_insert_with(Vec::new);
+        items.push(model::ChecklistItem { text: node.text.clone(), done, order: items.len() as u32 });
+        continue;
+      }
+
+      let row = result.notes.len() as f64;
+      let x = origin.x + depth as f64 * if as_stack { IMPORT_STACK_INDENT } else { IMPORT_COLUMN_WIDTH };
+      let y = origin.y + row * (IMPORT_NOTE_HEIGHT + IMPORT_ROW_GAP);
+      let text = match node.done {
+        Some(done) => format!("{} {}", checklist::checkbox(done), node.text),
+        None => node.text.clone(),
+      };
+      let mut note = imported_note(&text, model::Rect { x, y, w: IMPORT_NOTE_WIDTH, h: IMPORT_NOTE_HEIGHT });
+      if node.markdown {
+        note.content_format = Some("markdown".to_string());
+      }
+      if let (Some(parent), false) = (parent, as_stack) {
+        result.connections.push(model::Connection {
+          id: model::new_id("conn"),
+          src_note_id: result.notes[parent].id.clone(),
+          dst_note_id: note.id.clone(),
+          style: None,
+          label: None,
+          bend_points: None,
+          control_points: None,
+          relation_type: None,
+        });
+      }
+      result.notes.push(note);
+      depths.push(depth);
+      let index = result.notes.len() - 1;
+      place(&node.children, Some(index), depth + 1, as_stack, origin, result, depths);
+    }
+  }
+
+  let mut result = OutlineImport::default();
+  let mut depths = Vec::new();
+  place(nodes, None, 0, as_stack, &origin, &mut result, &mut depths);
+  if result.notes.is_empty() {
+    return Err("The text has no outline items".to_string());
+  }
+
+  if as_stack {
+    let stack_id = model::new_id("stack");
+    for note in &mut result.notes {
+      note.stack_id = Some(stack_id.clone());
+    }
+    let indent_levels = result.notes.iter().zip(&depths)
+      .filter(|(_, depth)| **depth > 0)
+      .map(|(note, depth)| (note.id.clone(), *depth as u32))
+      .collect::<std::collections::HashMap<_, _>>();
+    result.stacks.push(model::Stack {
+      id: stack_id,
+      note_ids: result.notes.iter().map(|n| n.id.clone()).collect(),
+      orientation: Some("vertical".to_string()),
+      spacing: Some(IMPORT_ROW_GAP),
+      indent_levels: (!indent_levels.is_empty()).then_some(indent_levels),
+      aligned_width: None,
+    });
+  }
+
+  Ok(result)
+}
+
+// A new board from a Markdown outline, laid out like an indented-text import
+pub fn import_markdown(text: &str, options: &OutlineImportOptions) -> Result<model::BoardDocument, String> {
+  let nodes = parse_markdown_outline(text);
+  if nodes.is_empty() {
+    return Err("The Markdown file has no outline items".to_string());
+  }
+  let import = to_notes(&nodes, options)?;
+  let mut doc = model::BoardDocument::empty();
+  doc.notes = import.notes;
+  doc.connections = import.connections;
+  doc.stacks = import.stacks;
+  Ok(doc)
+}
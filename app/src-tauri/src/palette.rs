@@ -0,0 +1,127 @@
+use serde::Serialize;
+
+use crate::{model, preview, theme};
+
+// The board's color palette: every color its styles, notes, connections,
+// strokes and labels set, with how often each is used. Colors compare by
+// value, so `#FFF`, `#ffffff` and `#ffffffff` are one entry. Values that
+// aren't hex colors (CSS names) are listed as written.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PaletteColor {
+  // Normalized `#rrggbb` (or `#rrggbbaa` when translucent)
+  pub color: String,
+  pub count: usize,
+  // Where it is used: "fill", "text", "border", "span", "connection",
+  // "relation", "stroke", "label" or "background"
+  pub usages: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ColorReplacement {
+  pub document: model::BoardDocument,
+  pub replaced: usize,
+}
+
+// Calls `visit` with every color slot the document has, and what it colors
+fn for_each_color(doc: &mut model::BoardDocument, mut visit: impl FnMut(&mut Option<String>, &'static str)) {
+  for style in &mut doc.note_styles {
+    visit(&mut style.fill, "fill");
+    visit(&mut style.text_style.color, "text");
+    if let Some(border) = style.border.as_mut() {
+      visit(&mut border.color, "border");
+    }
+  }
+  for note in &mut doc.notes {
+    for span in note.spans.iter_mut().flatten() {
+      visit(&mut span.color, "span");
+    }
+  }
+  for connection in &mut doc.connections {
+    if let Some(style) = connection.style.as_mut() {
+      visit(&mut style.color, "connection");
+    }
+  }
+  for relation in doc.relation_types.iter_mut().flatten() {
+    if let Some(style) = relation.style.as_mut() {
+      visit(&mut style.color, "relation");
+    }
+  }
+  for stroke in doc.strokes.iter_mut().flatten() {
+    visit(&mut stroke.color, "stroke");
+  }
+  if let Some(style) = doc.document_style.as_mut() {
+    for label in style.labels.iter_mut().flatten() {
+      visit(&mut label.color, "label");
+    }
+    if let Some(background) = style.background.as_mut() {
+      visit(&mut background.color, "background");
+    }
+  }
+}
+
+fn normalize(value: &str) -> Option<String> {
+  let value = value.trim();
+  if value.is_empty() {
+    return None;
+  }
+  Some(preview::parse_hex_color(value).map_or_else(|| value.to_lowercase(), theme::to_hex))
+}
+
+// Most used first
+pub fn extract_palette(doc: &model::BoardDocument) -> Vec<PaletteColor> {
+  let mut palette: Vec<PaletteColor> = Vec::new();
+  for_each_color(&mut doc.clone(), |slot, usage| {
+    let Some(color) = slot.as_deref().and_then(normalize) else {
+      return;
+    };
+    let entry = match palette.iter().position(|c| c.color == color) {
+      Some(i) => &mut palette[i],
+      None => {
+        palette.push(PaletteColor { color, count: 0, usages: Vec::new() });
+        palette.last_mut().unwrap()
+      },
+    };
+    entry.count += 1;
+    if !entry.usages.iter().any(|u| u == usage) {
+      entry.usages.push(usage.to_string());
+    }
+  });
+  palette.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.color.cmp(&b.color)));
+  palette
+}
+
+// Copy of `doc` with every use of `from` set to `to`. A `from` without alpha
+// matches the color at any opacity, and the opacity is kept unless `to` sets
+// its own.
+pub fn replace_color(doc: &model::BoardDocument, from: &str, to: &str) -> Result<ColorReplacement, String> {
+  let source = normalize(from).ok_or("The color to replace is empty")?;
+  let target = normalize(to).ok_or("The replacement color is empty")?;
+  let has_alpha = |value: &str| value.trim().len() == 9;
+  let source_rgba = preview::parse_hex_color(from);
+  let target_rgba = preview::parse_hex_color(to).filter(|_| !has_alpha(to));
+
+  let mut document = doc.clone();
+  let mut replaced = 0;
+  for_each_color(&mut document, |slot, _| {
+    let Some(value) = slot.as_deref() else {
+      return;
+    };
+    let replacement = match (source_rgba, preview::parse_hex_color(value)) {
+      (Some(source), Some(color)) => {
+        if source[..3] != color[..3] || (has_alpha(from) && source[3] != color[3]) {
+          return;
+        }
+        match target_rgba {
+          Some(target) => theme::to_hex([target[0], target[1], target[2], color[3]]),
+          None => target.clone(),
+        }
+      },
+      _ if normalize(value).as_deref() == Some(source.as_str()) => target.clone(),
+      _ => return,
+    };
+    *slot = Some(replacement);
+    replaced += 1;
+  });
+  Ok(ColorReplacement { document, replaced })
+}
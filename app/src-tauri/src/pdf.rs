@@ -0,0 +1,466 @@
+use aes::cipher::{generic_array::GenericArray, BlockEncrypt, KeyInit};
+use chacha20poly1305::aead::{rand_core::RngCore, OsRng};
+use lopdf::{dictionary, Object, StringFormat, Stream};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+
+use crate::{layers, model, poster, progress, raster, render};
+
+// PDF export written by the backend. Each page carries its part of the board
+// rendered by render.rs as an image, so the output matches PNG exports and
+// needs no fonts. "fit" puts the whole board on one page, scaled to the
+// printable area; "tile" prints it at true scale across a grid of pages as
+// planned by poster.rs, with crop marks around each page's printed area,
+// dashed guides where the next page repeats it and its grid label in the
+// margin, so the sheets can be trimmed and joined.
+
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+const DEFAULT_DPI: f64 = 150.0;
+const MIN_DPI: f64 = 72.0;
+const MAX_DPI: f64 = 600.0;
+// Largest rendered edge of a page image; fit mode lowers the resolution of
+// very large pages to stay within it
+const MAX_IMAGE_EDGE: f64 = 8192.0;
+const FIT_MARGIN_MM: f64 = 10.0;
+// Space kept around the content in fit mode, in board units
+const FIT_PADDING: f64 = 20.0;
+const CROP_MARK_MM: f64 = 5.0;
+// Between the printed area and the start of a crop mark
+const CROP_MARK_GAP_MM: f64 = 1.5;
+const LABEL_SIZE: f64 = 8.0;
+
+// Pads passwords to 32 bytes in the standard security handler
+const PASSWORD_PADDING: [u8; 32] = [
+  0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA, 0x01, 0x08,
+  0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE, 0x64, 0x53, 0x69, 0x7A,
+];
+// Permission bits of /P; the two lowest must be clear
+const ALL_PERMISSIONS: u32 = 0xFFFF_FFFC;
+const PERMISSION_PRINT: u32 = 1 << 2;
+const PERMISSION_COPY: u32 = 1 << 4;
+const PERMISSION_PRINT_HIGH_QUALITY: u32 = 1 << 11;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PdfOptions {
+  // "fit" (default) or "tile"
+  pub mode: Option<String>,
+  // Resolution of the page images
+  pub dpi: Option<f64>,
+  // Tile mode: 1.0 prints at true scale, and how far pages overlap in mm
+  pub scale: Option<f64>,
+  pub overlap_mm: Option<f64>,
+  // Tile mode draws crop marks unless this is false
+  pub crop_marks: Option<bool>,
+}
+
+// Password protection for a PDF; see `protect`
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PdfProtection {
+  // Asked for when the PDF is opened; none opens it without one
+  pub user_password: Option<String>,
+  // Lifts the restrictions below; without one nobody can
+  pub owner_password: Option<String>,
+  pub no_print: Option<bool>,
+  // No copying text or images out of the PDF (copying for accessibility
+  // tools is still allowed)
+  pub no_copy: Option<bool>,
+}
+
+// One page: its size, the board region it shows and where, in points from
+// the bottom-left corner
+struct PageLayout {
+  width: f64,
+  height: f64,
+  region: model::Rect,
+  placement: model::Rect,
+  crop_marks: bool,
+  // Width of the strip the page shares with its right and lower neighbors,
+  // in points
+  overlap_right: Option<f64>,
+  overlap_bottom: Option<f64>,
+  label: Option<String>,
+}
+
+fn fit_layout(doc: &model::BoardDocument, page_size: &str, orientation: &str) -> Result<Vec<PageLayout>, String> {
+  let (portrait_w, portrait_h) = poster::page_dimensions(page_size)?;
+  let bounds = raster::content_bounds(doc).ok_or("The board has nothing to export")?;
+  let region = model::Rect {
+    x: bounds.x - FIT_PADDING,
+    y: bounds.y - FIT_PADDING,
+    w: bounds.w.max(1.0) + FIT_PADDING * 2.0,
+    h: bounds.h.max(1.0) + FIT_PADDING * 2.0,
+  };
+  let landscape = match orientation {
+    "portrait" => false,
+    "landscape" => true,
+    "auto" => region.w > region.h,
+    other => return Err(format!("Invalid orientation '{}'. Must be one of: auto, portrait, landscape", other)),
+  };
+  let (page_w, page_h) = if landscape { (portrait_h, portrait_w) } else { (portrait_w, portrait_h) };
+  let (printable_w, printable_h) = (page_w - FIT_MARGIN_MM * 2.0, page_h - FIT_MARGIN_MM * 2.0);
+  let mm_per_unit = (printable_w / region.w).min(printable_h / region.h);
+  let (draw_w, draw_h) = (region.w * mm_per_unit, region.h * mm_per_unit);
+  Ok(vec![PageLayout {
+    width: page_w * POINTS_PER_MM,
+    height: page_h * POINTS_PER_MM,
+    region,
+    // Centered on the page
+    placement: model::Rect {
+      x: (page_w - draw_w) / 2.0 * POINTS_PER_MM,
+      y: (page_h - draw_h) / 2.0 * POINTS_PER_MM,
+      w: draw_w * POINTS_PER_MM,
+      h: draw_h * POINTS_PER_MM,
+    },
+    crop_marks: false,
+    overlap_right: None,
+    overlap_bottom: None,
+    label: None,
+  }])
+}
+
+fn tile_layout(doc: &model::BoardDocument, page_size: &str, orientation: &str, options: &PdfOptions) -> Result<Vec<PageLayout>, String> {
+  let plan = poster::plan(doc, &poster::PosterOptions {
+    page_size: page_size.to_string(),
+    orientation: orientation.to_string(),
+    scale: options.scale,
+    overlap_mm: options.overlap_mm,
+  })?;
+  let total = plan.tiles.len();
+  let overlap = (plan.overlap_mm > 0.0).then_some(plan.overlap_mm * POINTS_PER_MM);
+  Ok(plan.tiles.iter().enumerate().map(|(index, tile)| {
+    let (draw_w, draw_h) = (tile.region.w * plan.mm_per_unit, tile.region.h * plan.mm_per_unit);
+    PageLayout {
+      width: plan.page_width_mm * POINTS_PER_MM,
+      height: plan.page_height_mm * POINTS_PER_MM,
+      region: tile.region.clone(),
+      // Top-left of the printable area; PDF y grows upwards
+      placement: model::Rect {
+        x: plan.margin_mm * POINTS_PER_MM,
+        y: (plan.page_height_mm - plan.margin_mm - draw_h) * POINTS_PER_MM,
+        w: draw_w * POINTS_PER_MM,
+        h: draw_h * POINTS_PER_MM,
+      },
+      crop_marks: options.crop_marks.unwrap_or(true),
+      overlap_right: overlap.filter(|_| tile.column + 1 < plan.columns),
+      overlap_bottom: overlap.filter(|_| tile.row + 1 < plan.rows),
+      label: Some(format!("{} - page {} of {}, grid {} x {}", tile.label, index + 1, total, plan.columns, plan.rows)),
+    }
+  }).collect())
+}
+
+// Short lines in the margin continuing each edge of the placement
+fn crop_marks(area: &model::Rect) -> String {
+  let (length, gap) = (CROP_MARK_MM * POINTS_PER_MM, CROP_MARK_GAP_MM * POINTS_PER_MM);
+  let (x0, y0, x1, y1) = (area.x, area.y, area.x + area.w, area.y + area.h);
+  let mut content = "q 0.25 w 0 G\n".to_string();
+  for (x, y, dx, dy) in [(x0, y0, -1.0, -1.0), (x1, y0, 1.0, -1.0), (x0, y1, -1.0, 1.0), (x1, y1, 1.0, 1.0)] {
+    content += &format!("{:.2} {:.2} m {:.2} {:.2} l S\n", x + dx * gap, y, x + dx * (gap + length), y);
+    content += &format!("{:.2} {:.2} m {:.2} {:.2} l S\n", x, y + dy * gap, x, y + dy * (gap + length));
+  }
+  content + "Q\n"
+}
+
+// PDF string literal; the label is ASCII
+fn pdf_string(text: &str) -> String {
+  let escaped: String = text.chars()
+    .filter(|c| c.is_ascii() && !c.is_ascii_control())
+    .flat_map(|c| match c {
+      '(' | ')' | '\\' => vec!['\\', c],
+      _ => vec![c],
+    })
+    .collect();
+  format!("({})", escaped)
+}
+
+// The board as a PDF, `page_size` as in poster.rs. Only visible layers are
+// printed.
+pub fn generate(
+  doc: &model::BoardDocument,
+  page_size: &str,
+  orientation: &str,
+  options: &PdfOptions,
+  progress: &progress::Progress,
+) -> Result<Vec<u8>, String> {
+  let dpi = options.dpi.unwrap_or(DEFAULT_DPI);
+  if !dpi.is_finite() || !(MIN_DPI..=MAX_DPI).contains(&dpi) {
+    return Err(format!("PDF resolution must be between {} and {} DPI", MIN_DPI, MAX_DPI));
+  }
+  let doc = layers::filter_layers(doc, &layers::visible_layers(doc));
+  let pages = match options.mode.as_deref().unwrap_or("fit") {
+    "fit" => fit_layout(&doc, page_size, orientation)?,
+    "tile" => tile_layout(&doc, page_size, orientation, options)?,
+    other => return Err(format!("Invalid PDF mode '{}'. Must be one of: fit, tile", other)),
+  };
+
+  let mut pdf = lopdf::Document::with_version("1.5");
+  let pages_id = pdf.new_object_id();
+  let font_id = pdf.add_object(dictionary! {
+    "Type" => "Font",
+    "Subtype" => "Type1",
+    "BaseFont" => "Helvetica",
+    "Encoding" => "WinAnsiEncoding",
+  });
+
+  let mut page_ids = Vec::with_capacity(pages.len());
+  for (index, page) in pages.iter().enumerate() {
+    progress.check_cancelled()?;
+    progress.step("rendering", 0.0, 90.0, index as u64, pages.len() as u64);
+
+    // Pixels for the placement at the chosen resolution
+    let pixels_per_point = (dpi / 72.0).min(MAX_IMAGE_EDGE / page.placement.w.max(page.placement.h));
+    let width = (page.placement.w * pixels_per_point).ceil().max(1.0);
+    let height = (page.placement.h * pixels_per_point).ceil().max(1.0);
+    let plan = raster::RasterPlan {
+      scale: width / page.region.w,
+      region: page.region.clone(),
+      width: width as u32,
+      height: height as u32,
+    };
+    let rgb: Vec<u8> = render::to_rgba(&doc, &plan)?
+      .chunks_exact(4)
+      .flat_map(|p| [p[0], p[1], p[2]])
+      .collect();
+    let mut image = Stream::new(dictionary! {
+      "Type" => "XObject",
+      "Subtype" => "Image",
+      "Width" => plan.width as i64,
+      "Height" => plan.height as i64,
+      "ColorSpace" => "DeviceRGB",
+      "BitsPerComponent" => 8,
+    }, rgb);
+    let _ = image.compress();
+    let image_id = pdf.add_object(image);
+
+    let p = &page.placement;
+    let mut content = format!("q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Board Do Q\n", p.w, p.h, p.x, p.y);
+    if page.crop_marks {
+      content += &crop_marks(p);
+    }
+    if page.overlap_right.is_some() || page.overlap_bottom.is_some() {
+      content += "q 0.25 w 0.5 G [3 2] 0 d\n";
+      if let Some(overlap) = page.overlap_right {
+        content += &format!("{:.2} {:.2} m {:.2} {:.2} l S\n", p.x + p.w - overlap, p.y, p.x + p.w - overlap, p.y + p.h);
+      }
+      if let Some(overlap) = page.overlap_bottom {
+        content += &format!("{:.2} {:.2} m {:.2} {:.2} l S\n", p.x, p.y + overlap, p.x + p.w, p.y + overlap);
+      }
+      content += "Q\n";
+    }
+    if let Some(label) = &page.label {
+      content += &format!(
+        "q 0.4 g BT /Label {} Tf {:.2} {:.2} Td {} Tj ET Q\n",
+        LABEL_SIZE, p.x, (p.y - CROP_MARK_GAP_MM * POINTS_PER_MM - LABEL_SIZE).max(LABEL_SIZE / 2.0), pdf_string(label)
+      );
+    }
+    let content_id = pdf.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+    page_ids.push(pdf.add_object(dictionary! {
+      "Type" => "Page",
+      "Parent" => pages_id,
+      "MediaBox" => vec![0.into(), 0.into(), (page.width as f32).into(), (page.height as f32).into()],
+      "Contents" => content_id,
+      "Resources" => dictionary! {
+        "XObject" => dictionary! { "Board" => image_id },
+        "Font" => dictionary! { "Label" => font_id },
+      },
+    }));
+  }
+
+  let count = page_ids.len() as i64;
+  pdf.objects.insert(pages_id, Object::Dictionary(dictionary! {
+    "Type" => "Pages",
+    "Kids" => page_ids.into_iter().map(Object::Reference).collect::<Vec<Object>>(),
+    "Count" => count,
+  }));
+  let catalog_id = pdf.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+  pdf.trailer.set("Root", catalog_id);
+
+  progress.phase("writing", 90.0);
+  let mut output = Vec::new();
+  pdf.save_to(&mut output).map_err(|e| format!("Failed to write PDF: {}", e))?;
+  Ok(output)
+}
+
+// `content` without its document information (title, author, producer,
+// creation and modification dates), XMP metadata and file identifier, which
+// PDFs from the webview carry; for sharing a PDF anonymously
+pub fn scrub_metadata(content: &[u8]) -> Result<Vec<u8>, String> {
+  let mut pdf = lopdf::Document::load_mem(content).map_err(|e| format!("Failed to read PDF: {}", e))?;
+  if let Some(Ok(info)) = pdf.trailer.remove(b"Info").map(|info| info.as_reference()) {
+    pdf.objects.remove(&info);
+  }
+  pdf.trailer.remove(b"ID");
+  let metadata = pdf.catalog_mut().ok().and_then(|catalog| catalog.remove(b"Metadata"));
+  if let Some(Ok(metadata)) = metadata.map(|metadata| metadata.as_reference()) {
+    pdf.objects.remove(&metadata);
+  }
+
+  let mut output = Vec::new();
+  pdf.save_to(&mut output).map_err(|e| format!("Failed to write PDF: {}", e))?;
+  Ok(output)
+}
+
+fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+  let mut state: Vec<u8> = (0..=255).collect();
+  let mut j = 0u8;
+  for i in 0..256 {
+    j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+    state.swap(i, j as usize);
+  }
+  let (mut i, mut j) = (0u8, 0u8);
+  data.iter().map(|byte| {
+    i = i.wrapping_add(1);
+    j = j.wrapping_add(state[i as usize]);
+    state.swap(i as usize, j as usize);
+    byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+  }).collect()
+}
+
+// RC4 with the key and then with the key XORed with 1 to 19, as the O and U
+// entries are made
+fn rc4_rounds(key: &[u8], data: &[u8]) -> Vec<u8> {
+  (1..=19u8).fold(rc4(key, data), |data, round| {
+    let key: Vec<u8> = key.iter().map(|b| b ^ round).collect();
+    rc4(&key, &data)
+  })
+}
+
+// Viewers pass passwords in PDFDocEncoding, which matches Latin-1 for the
+// characters that can be typed
+fn padded_password(password: &str) -> Result<Vec<u8>, String> {
+  let mut padded = password.chars()
+    .map(|c| u8::try_from(u32::from(c)).map_err(|_| "PDF passwords can only use Latin letters, digits and symbols".to_string()))
+    .take(32)
+    .collect::<Result<Vec<u8>, String>>()?;
+  padded.extend_from_slice(&PASSWORD_PADDING[..32 - padded.len()]);
+  Ok(padded)
+}
+
+// AES-128-CBC with a random IV in front and PKCS#7 padding, keyed for the
+// object `id` (crypt filter method AESV2)
+fn encrypt_for_object(key: &[u8], id: lopdf::ObjectId, data: &[u8]) -> Vec<u8> {
+  let mut hash = Md5::new();
+  hash.update(key);
+  hash.update(&id.0.to_le_bytes()[..3]);
+  hash.update(&id.1.to_le_bytes()[..2]);
+  hash.update(b"sAlT");
+  let cipher = aes::Aes128::new(&hash.finalize());
+
+  let mut iv = [0u8; 16];
+  OsRng.fill_bytes(&mut iv);
+  let padding = 16 - data.len() % 16;
+  let mut plain = data.to_vec();
+  plain.resize(data.len() + padding, padding as u8);
+  let mut output = iv.to_vec();
+  let mut previous = GenericArray::from(iv);
+  for chunk in plain.chunks_exact(16) {
+    let mut block = GenericArray::clone_from_slice(chunk);
+    block.iter_mut().zip(previous.iter()).for_each(|(b, p)| *b ^= p);
+    cipher.encrypt_block(&mut block);
+    output.extend_from_slice(&block);
+    previous = block;
+  }
+  output
+}
+
+// Every string and stream in `object`, which is the object `id`
+fn encrypt_object(object: &mut Object, key: &[u8], id: lopdf::ObjectId) {
+  match object {
+    Object::String(data, _) => *object = Object::String(encrypt_for_object(key, id, data), StringFormat::Hexadecimal),
+    Object::Array(items) => items.iter_mut().for_each(|item| encrypt_object(item, key, id)),
+    Object::Dictionary(dict) => dict.iter_mut().for_each(|(_, value)| encrypt_object(value, key, id)),
+    Object::Stream(stream) => {
+      stream.dict.iter_mut().for_each(|(_, value)| encrypt_object(value, key, id));
+      let content = encrypt_for_object(key, id, &stream.content);
+      stream.set_content(content);
+    },
+    _ => {},
+  }
+}
+
+// `content` encrypted with the standard security handler at revision 4,
+// 128-bit AES, which any PDF 1.6 reader opens. Viewers ask for the user
+// password, if there is one, and honor the print and copy restrictions
+// unless opened with the owner password. It goes last, after markings and
+// scrubbing, which can't read an encrypted PDF.
+pub fn protect(content: &[u8], protection: &PdfProtection) -> Result<Vec<u8>, String> {
+  let mut permissions = ALL_PERMISSIONS;
+  if protection.no_print.unwrap_or(false) {
+    permissions &= !(PERMISSION_PRINT | PERMISSION_PRINT_HIGH_QUALITY);
+  }
+  if protection.no_copy.unwrap_or(false) {
+    permissions &= !PERMISSION_COPY;
+  }
+  let user_password = protection.user_password.as_deref().unwrap_or("");
+  let owner_password = protection.owner_password.as_deref().filter(|password| !password.is_empty());
+  if user_password.is_empty() && owner_password.is_none() && permissions == ALL_PERMISSIONS {
+    return Err("Set a password or a restriction to protect the PDF".to_string());
+  }
+  let owner_password = match owner_password {
+    Some(password) => password.to_string(),
+    None => {
+      let mut random = [0u8; 16];
+      OsRng.fill_bytes(&mut random);
+      random.iter().map(|b| format!("{:02x}", b)).collect()
+    },
+  };
+
+  let mut pdf = lopdf::Document::load_mem(content).map_err(|e| format!("Failed to read PDF: {}", e))?;
+  if pdf.is_encrypted() {
+    return Err("The PDF is already protected".to_string());
+  }
+  let mut file_id = [0u8; 16];
+  OsRng.fill_bytes(&mut file_id);
+  let user_padded = padded_password(user_password)?;
+
+  // The O entry: the padded user password encrypted with a key made from
+  // the owner password
+  let mut owner_key = Md5::digest(padded_password(&owner_password)?);
+  for _ in 0..50 {
+    owner_key = Md5::digest(owner_key);
+  }
+  let owner_entry = rc4_rounds(&owner_key, &user_padded);
+
+  // The file key, from the user password, O, the permissions and the file ID
+  let mut hash = Md5::new();
+  hash.update(&user_padded);
+  hash.update(&owner_entry);
+  hash.update(permissions.to_le_bytes());
+  hash.update(file_id);
+  let mut key = hash.finalize();
+  for _ in 0..50 {
+    key = Md5::digest(key);
+  }
+
+  // The U entry, which lets a viewer check the user password
+  let mut hash = Md5::new();
+  hash.update(PASSWORD_PADDING);
+  hash.update(file_id);
+  let mut user_entry = rc4_rounds(&key, &hash.finalize());
+  user_entry.extend_from_slice(&[0; 16]);
+
+  for (id, object) in pdf.objects.iter_mut() {
+    encrypt_object(object, &key, *id);
+  }
+  let encrypt_id = pdf.add_object(dictionary! {
+    "Filter" => "Standard",
+    "V" => 4,
+    "R" => 4,
+    "Length" => 128,
+    "CF" => dictionary! {
+      "StdCF" => dictionary! { "Type" => "CryptFilter", "CFM" => "AESV2", "AuthEvent" => "DocOpen", "Length" => 16 },
+    },
+    "StmF" => "StdCF",
+    "StrF" => "StdCF",
+    "O" => Object::String(owner_entry, StringFormat::Hexadecimal),
+    "U" => Object::String(user_entry, StringFormat::Hexadecimal),
+    "P" => permissions as i32 as i64,
+  });
+  pdf.trailer.set("Encrypt", encrypt_id);
+  let file_id = Object::String(file_id.to_vec(), StringFormat::Hexadecimal);
+  pdf.trailer.set("ID", vec![file_id.clone(), file_id]);
+  pdf.version = "1.6".to_string();
+
+  let mut output = Vec::new();
+  pdf.save_to(&mut output).map_err(|e| format!("Failed to write PDF: {}", e))?;
+  Ok(output)
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{layers, model, raster};
+
+// Poster mode for PDF exports: the board is printed at true scale (96 board
+// units per inch) across a grid of pages that overlap slightly, so they can be
+// trimmed along the crop marks and taped together. The plan is computed here;
+// the PDF writer draws each tile's region onto its page.
+
+// Millimetres per board unit at 1:1
+pub const MM_PER_UNIT: f64 = 25.4 / 96.0;
+// Unprinted border that most printers need
+const PAGE_MARGIN_MM: f64 = 10.0;
+const DEFAULT_OVERLAP_MM: f64 = 10.0;
+// Space kept around the content, in board units
+const CONTENT_PADDING: f64 = 20.0;
+const MAX_PAGES: usize = 400;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PosterOptions {
+  pub page_size: String, // "a3", "a4", "a5", "letter", "legal"
+  pub orientation: String, // "auto" picks whichever needs fewer pages
+  // 1.0 prints at true scale; 2.0 doubles everything
+  pub scale: Option<f64>,
+  // How far each page repeats its neighbor's edge, in mm
+  pub overlap_mm: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PosterTile {
+  pub row: usize,
+  pub column: usize,
+  // "A1" for the top-left page; rows are letters, columns numbers
+  pub label: String,
+  // Board-space area printed inside the page margins
+  pub region: model::Rect,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PosterPlan {
+  pub page_width_mm: f64,
+  pub page_height_mm: f64,
+  pub orientation: String,
+  pub margin_mm: f64,
+  pub overlap_mm: f64,
+  // Millimetres per board unit, including the scale
+  pub mm_per_unit: f64,
+  pub columns: usize,
+  pub rows: usize,
+  // Board-space area covered by the whole poster
+  pub content: model::Rect,
+  // Row by row, left to right
+  pub tiles: Vec<PosterTile>,
+}
+
+// Portrait page size in mm
+pub fn page_dimensions(page_size: &str) -> Result<(f64, f64), String> {
+  match page_size {
+    "a3" => Ok((297.0, 420.0)),
+    "a4" => Ok((210.0, 297.0)),
+    "a5" => Ok((148.0, 210.0)),
+    "letter" => Ok((215.9, 279.4)),
+    "legal" => Ok((215.9, 355.6)),
+    other => Err(format!("Invalid page size '{}'. Must be one of: a3, a4, a5, letter, legal", other)),
+  }
+}
+
+fn row_name(mut row: usize) -> String {
+  let mut name = String::new();
+  loop {
+    name.insert(0, (b'A' + (row % 26) as u8) as char);
+    if row < 26 {
+      return name;
+    }
+    row = row / 26 - 1;
+  }
+}
+
+// Pages needed to cover `length` mm with pages printing `printable` mm each,
+// consecutive pages sharing `overlap` mm
+fn pages_along(length: f64, printable: f64, overlap: f64) -> usize {
+  if length <= printable {
+    return 1;
+  }
+  ((length - overlap) / (printable - overlap)).ceil() as usize
+}
+
+fn layout(content: &model::Rect, page: (f64, f64), overlap_mm: f64, mm_per_unit: f64) -> (usize, usize) {
+  let (printable_w, printable_h) = (page.0 - PAGE_MARGIN_MM * 2.0, page.1 - PAGE_MARGIN_MM * 2.0);
+  (
+    pages_along(content.w * mm_per_unit, printable_w, overlap_mm),
+    pages_along(content.h * mm_per_unit, printable_h, overlap_mm),
+  )
+}
+
+pub fn plan(doc: &model::BoardDocument, options: &PosterOptions) -> Result<PosterPlan, String> {
+  let (portrait_w, portrait_h) = page_dimensions(&options.page_size)?;
+  let scale = options.scale.unwrap_or(1.0);
+  if !scale.is_finite() || scale <= 0.0 {
+    return Err("Poster scale must be a positive number".to_string());
+  }
+  let mm_per_unit = MM_PER_UNIT * scale;
+  let overlap_mm = options.overlap_mm.unwrap_or(DEFAULT_OVERLAP_MM);
+  let max_overlap = (portrait_w - PAGE_MARGIN_MM * 2.0) / 2.0;
+  if !overlap_mm.is_finite() || !(0.0..=max_overlap).contains(&overlap_mm) {
+    return Err(format!("Overlap must be between 0 and {} mm", max_overlap));
+  }
+
+  let visible = layers::filter_layers(doc, &layers::visible_layers(doc));
+  let bounds = raster::content_bounds(&visible).ok_or("The board has nothing to export")?;
+  let content = model::Rect {
+    x: bounds.x - CONTENT_PADDING,
+    y: bounds.y - CONTENT_PADDING,
+    w: bounds.w + CONTENT_PADDING * 2.0,
+    h: bounds.h + CONTENT_PADDING * 2.0,
+  };
+
+  let portrait = layout(&content, (portrait_w, portrait_h), overlap_mm, mm_per_unit);
+  let landscape = layout(&content, (portrait_h, portrait_w), overlap_mm, mm_per_unit);
+  let landscape_wins = landscape.0 * landscape.1 < portrait.0 * portrait.1;
+  let orientation = match options.orientation.as_str() {
+    "portrait" => "portrait",
+    "landscape" => "landscape",
+    "auto" if landscape_wins => "landscape",
+    "auto" => "portrait",
+    other => return Err(format!("Invalid orientation '{}'. Must be one of: auto, portrait, landscape", other)),
+  };
+  let ((columns, rows), (page_w, page_h)) = if orientation == "landscape" {
+    (landscape, (portrait_h, portrait_w))
+  } else {
+    (portrait, (portrait_w, portrait_h))
+  };
+  if columns * rows > MAX_PAGES {
+    return Err(format!(
+      "The poster would need {} pages ({}×{}); use a larger page size or a smaller scale",
+      columns * rows, columns, rows
+    ));
+  }
+
+  // Tile regions in board units: each page advances by its printable size
+  // minus the overlap
+  let printable_w = (page_w - PAGE_MARGIN_MM * 2.0) / mm_per_unit;
+  let printable_h = (page_h - PAGE_MARGIN_MM * 2.0) / mm_per_unit;
+  let overlap = overlap_mm / mm_per_unit;
+  let mut tiles = Vec::with_capacity(columns * rows);
+  for row in 0..rows {
+    for column in 0..columns {
+      tiles.push(PosterTile {
+        row,
+        column,
+        label: format!("{}{}", row_name(row), column + 1),
+        region: model::Rect {
+          x: content.x + column as f64 * (printable_w - overlap),
+          y: content.y + row as f64 * (printable_h - overlap),
+          w: printable_w,
+          h: printable_h,
+        },
+      });
+    }
+  }
+
+  Ok(PosterPlan {
+    page_width_mm: page_w,
+    page_height_mm: page_h,
+    orientation: orientation.to_string(),
+    margin_mm: PAGE_MARGIN_MM,
+    overlap_mm,
+    mm_per_unit,
+    columns,
+    rows,
+    content,
+    tiles,
+  })
+}
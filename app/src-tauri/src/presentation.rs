@@ -0,0 +1,331 @@
+use std::io::Write;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::{icons, markdown, media, model, outline, shapes};
+
+// Slide decks built from a board: one slide per stack, or one per item along
+// an explicit presentation path. The backend decides what each slide shows
+// (title, board region, speaker notes) and packages the rendered slide images
+// as a PPTX or a self-contained reveal.js page.
+
+// Space around each slide's content, in board units
+const SLIDE_PADDING: f64 = 40.0;
+const REVEAL_CDN: &str = "https://cdn.jsdelivr.net/npm/reveal.js@5";
+
+// 16:9 slide in EMUs (English Metric Units, 914400 per inch)
+const SLIDE_WIDTH_EMU: i64 = 12_192_000;
+const SLIDE_HEIGHT_EMU: i64 = 6_858_000;
+const SLIDE_MARGIN_EMU: i64 = 457_200;
+const TITLE_HEIGHT_EMU: i64 = 914_400;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Slide {
+  pub title: String,
+  // Board-space rectangle the slide image shows
+  pub region: model::Rect,
+  // Notes (and the shape, for path slides) the slide was built from
+  pub item_ids: Vec<model::ID>,
+  // Plain text of the slide's notes, used as speaker notes
+  pub speaker_notes: String,
+}
+
+fn first_line(note: &model::Note) -> String {
+  let text = markdown::note_rich_text(note).0;
+  format!("{}{}", icons::note_prefix(note), text.lines().next().unwrap_or("").trim())
+}
+
+fn speaker_notes(notes: &[&model::Note]) -> String {
+  notes.iter()
+    .map(|note| markdown::note_rich_text(note).0.trim().to_string())
+    .filter(|text| !text.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+fn padded(frames: &[&model::Rect]) -> Option<model::Rect> {
+  let (x0, y0, x1, y1) = frames.iter().fold(None, |acc: Option<(f64, f64, f64, f64)>, f| {
+    let (x0, y0, x1, y1) = acc.unwrap_or((f.x, f.y, f.x + f.w, f.y + f.h));
+    Some((x0.min(f.x), y0.min(f.y), x1.max(f.x + f.w), y1.max(f.y + f.h)))
+  })?;
+  Some(model::Rect {
+    x: x0 - SLIDE_PADDING,
+    y: y0 - SLIDE_PADDING,
+    w: (x1 - x0).max(1.0) + SLIDE_PADDING * 2.0,
+    h: (y1 - y0).max(1.0) + SLIDE_PADDING * 2.0,
+  })
+}
+
+fn stack_slide(doc: &model::BoardDocument, stack: &model::Stack) -> Option<Slide> {
+  let notes: Vec<&model::Note> = stack.note_ids.iter()
+    .filter_map(|id| doc.notes.iter().find(|n| &n.id == id))
+    .collect();
+  let region = padded(&notes.iter().map(|n| &n.frame).collect::<Vec<_>>())?;
+  Some(Slide {
+    title: first_line(notes[0]),
+    region,
+    item_ids: notes.iter().map(|n| n.id.clone()).collect(),
+    speaker_notes: speaker_notes(&notes),
+  })
+}
+
+// A path step is a note (the slide frames it) or a background shape (the
+// slide frames the shape and the notes inside it)
+fn path_slide(doc: &model::BoardDocument, id: &model::ID, index: usize) -> Result<Slide, String> {
+  if let Some(note) = doc.notes.iter().find(|n| &n.id == id) {
+    return Ok(Slide {
+      title: first_line(note),
+      region: padded(&[&note.frame]).unwrap_or_else(|| note.frame.clone()),
+      item_ids: vec![note.id.clone()],
+      speaker_notes: speaker_notes(&[note]),
+    });
+  }
+  let shape = doc.shapes.iter().find(|s| &s.id == id)
+    .ok_or_else(|| format!("Presentation path refers to unknown item '{}'", id))?;
+  let notes: Vec<&model::Note> = doc.notes.iter().filter(|n| shapes::contains_note(shape, n)).collect();
+  let title = shape.label.as_deref().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string)
+    .or_else(|| notes.first().map(|n| first_line(n)))
+    .unwrap_or_else(|| format!("Slide {}", index + 1));
+  Ok(Slide {
+    title,
+    region: padded(&[&shape.frame]).unwrap_or_else(|| shape.frame.clone()),
+    item_ids: std::iter::once(shape.id.clone()).chain(notes.iter().map(|n| n.id.clone())).collect(),
+    speaker_notes: speaker_notes(&notes),
+  })
+}
+
+// Slides in presentation order: the path if one is given, otherwise the
+// board's stacks in document order
+pub fn plan(doc: &model::BoardDocument, path: Option<&[model::ID]>) -> Result<Vec<Slide>, String> {
+  let slides = match path {
+    Some(path) => path.iter().enumerate()
+      .map(|(index, id)| path_slide(doc, id, index))
+      .collect::<Result<Vec<_>, _>>()?,
+    None => doc.stacks.iter().filter_map(|stack| stack_slide(doc, stack)).collect(),
+  };
+  if slides.is_empty() {
+    return Err("Nothing to present: the board has no stacks and no presentation path was given".to_string());
+  }
+  Ok(slides)
+}
+
+fn check_images(slides: &[Slide], images: &[Vec<u8>]) -> Result<(), String> {
+  if slides.len() != images.len() {
+    return Err(format!("Expected {} slide images, got {}", slides.len(), images.len()));
+  }
+  if let Some(index) = images.iter().position(|image| media::sniff_image_mime(image) != Some("image/png")) {
+    return Err(format!("Slide image {} is not a PNG", index + 1));
+  }
+  Ok(())
+}
+
+// ---- reveal.js ----
+
+pub fn to_reveal_html(title: &str, slides: &[Slide], images: &[Vec<u8>]) -> Result<String, String> {
+  check_images(slides, images)?;
+
+  let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+  html += &format!("<title>{}</title>\n", outline::html_escape(title));
+  html += &format!("<link rel=\"stylesheet\" href=\"{}/dist/reveal.css\">\n", REVEAL_CDN);
+  html += &format!("<link rel=\"stylesheet\" href=\"{}/dist/theme/white.css\">\n", REVEAL_CDN);
+  html += "<style>.reveal section img { max-width: 100%; max-height: 75vh; margin: 0; border: none; box-shadow: none; }</style>\n";
+  html += "</head>\n<body>\n<div class=\"reveal\">\n<div class=\"slides\">\n";
+
+  for (slide, image) in slides.iter().zip(images) {
+    html += "<section>\n";
+    if !slide.title.is_empty() {
+      html += &format!("<h3>{}</h3>\n", outline::html_escape(&slide.title));
+    }
+    html += &format!(
+      "<img src=\"data:image/png;base64,{}\" alt=\"{}\">\n",
+      base64::engine::general_purpose::STANDARD.encode(image),
+      outline::html_escape(&slide.title)
+    );
+    if !slide.speaker_notes.is_empty() {
+      html += &format!("<aside class=\"notes\">{}</aside>\n", outline::html_escape(&slide.speaker_notes));
+    }
+    html += "</section>\n";
+  }
+
+  html += "</div>\n</div>\n";
+  html += &format!("<script src=\"{}/dist/reveal.js\"></script>\n", REVEAL_CDN);
+  html += &format!("<script src=\"{}/plugin/notes/notes.js\"></script>\n", REVEAL_CDN);
+  html += "<script>Reveal.initialize({ hash: true, plugins: [RevealNotes] });</script>\n";
+  html += "</body>\n</html>\n";
+  Ok(html)
+}
+
+// ---- PPTX ----
+
+const NS_A: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const NS_P: &str = "http://schemas.openxmlformats.org/presentationml/2006/main";
+const REL: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+fn xml_escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn relationships(entries: &[(&str, &str)]) -> String {
+  let mut xml = format!("{}<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">", XML_HEADER);
+  for (index, (kind, target)) in entries.iter().enumerate() {
+    xml += &format!("<Relationship Id=\"rId{}\" Type=\"{}/{}\" Target=\"{}\"/>", index + 1, REL, kind, target);
+  }
+  xml += "</Relationships>";
+  xml
+}
+
+fn content_types(slide_count: usize) -> String {
+  let pml = "application/vnd.openxmlformats-officedocument.presentationml";
+  let mut xml = format!("{}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">", XML_HEADER);
+  xml += "<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>";
+  xml += "<Default Extension=\"xml\" ContentType=\"application/xml\"/>";
+  xml += "<Default Extension=\"png\" ContentType=\"image/png\"/>";
+  xml += &format!("<Override PartName=\"/ppt/presentation.xml\" ContentType=\"{}.presentation.main+xml\"/>", pml);
+  xml += &format!("<Override PartName=\"/ppt/slideMasters/slideMaster1.xml\" ContentType=\"{}.slideMaster+xml\"/>", pml);
+  xml += &format!("<Override PartName=\"/ppt/slideLayouts/slideLayout1.xml\" ContentType=\"{}.slideLayout+xml\"/>", pml);
+  xml += "<Override PartName=\"/ppt/theme/theme1.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.theme+xml\"/>";
+  for n in 1..=slide_count {
+    xml += &format!("<Override PartName=\"/ppt/slides/slide{}.xml\" ContentType=\"{}.slide+xml\"/>", n, pml);
+  }
+  xml += "</Types>";
+  xml
+}
+
+fn presentation_xml(slide_count: usize) -> String {
+  let mut xml = format!("{}<p:presentation xmlns:a=\"{}\" xmlns:r=\"{}\" xmlns:p=\"{}\">", XML_HEADER, NS_A, NS_R, NS_P);
+  xml += "<p:sldMasterIdLst><p:sldMasterId id=\"2147483648\" r:id=\"rId1\"/></p:sldMasterIdLst><p:sldIdLst>";
+  // rId1 is the master; slides follow
+  for n in 0..slide_count {
+    xml += &format!("<p:sldId id=\"{}\" r:id=\"rId{}\"/>", 256 + n, n + 2);
+  }
+  xml += &format!(
+    "</p:sldIdLst><p:sldSz cx=\"{}\" cy=\"{}\"/><p:notesSz cx=\"6858000\" cy=\"9144000\"/></p:presentation>",
+    SLIDE_WIDTH_EMU, SLIDE_HEIGHT_EMU
+  );
+  xml
+}
+
+const EMPTY_TREE: &str = "<p:cSld><p:spTree><p:nvGrpSpPr><p:cNvPr id=\"1\" name=\"\"/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/></p:spTree></p:cSld>";
+
+fn slide_master_xml() -> String {
+  format!(
+    "{}<p:sldMaster xmlns:a=\"{}\" xmlns:r=\"{}\" xmlns:p=\"{}\">{}\
+     <p:clrMap bg1=\"lt1\" tx1=\"dk1\" bg2=\"lt2\" tx2=\"dk2\" accent1=\"accent1\" accent2=\"accent2\" accent3=\"accent3\" \
+     accent4=\"accent4\" accent5=\"accent5\" accent6=\"accent6\" hlink=\"hlink\" folHlink=\"folHlink\"/>\
+     <p:sldLayoutIdLst><p:sldLayoutId id=\"2147483649\" r:id=\"rId1\"/></p:sldLayoutIdLst></p:sldMaster>",
+    XML_HEADER, NS_A, NS_R, NS_P, EMPTY_TREE
+  )
+}
+
+fn slide_layout_xml() -> String {
+  format!(
+    "{}<p:sldLayout xmlns:a=\"{}\" xmlns:r=\"{}\" xmlns:p=\"{}\" type=\"blank\">{}\
+     <p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sldLayout>",
+    XML_HEADER, NS_A, NS_R, NS_P, EMPTY_TREE
+  )
+}
+
+// The smallest theme PowerPoint and Keynote accept: a color, font and format scheme
+fn theme_xml() -> String {
+  let colors = [
+    ("dk1", "000000"), ("lt1", "FFFFFF"), ("dk2", "1F2937"), ("lt2", "F3F4F6"),
+    ("accent1", "4F46E5"), ("accent2", "0EA5E9"), ("accent3", "10B981"), ("accent4", "F59E0B"),
+    ("accent5", "EF4444"), ("accent6", "8B5CF6"), ("hlink", "2563EB"), ("folHlink", "7C3AED"),
+  ];
+  let mut xml = format!("{}<a:theme xmlns:a=\"{}\" name=\"Idea Map\"><a:themeElements><a:clrScheme name=\"Idea Map\">", XML_HEADER, NS_A);
+  for (name, rgb) in colors {
+    xml += &format!("<a:{0}><a:srgbClr val=\"{1}\"/></a:{0}>", name, rgb);
+  }
+  xml += "</a:clrScheme><a:fontScheme name=\"Idea Map\">";
+  xml += "<a:majorFont><a:latin typeface=\"Calibri\"/><a:ea typeface=\"\"/><a:cs typeface=\"\"/></a:majorFont>";
+  xml += "<a:minorFont><a:latin typeface=\"Calibri\"/><a:ea typeface=\"\"/><a:cs typeface=\"\"/></a:minorFont>";
+  xml += "</a:fontScheme><a:fmtScheme name=\"Idea Map\">";
+  let fill = "<a:solidFill><a:schemeClr val=\"phClr\"/></a:solidFill>";
+  xml += &format!("<a:fillStyleLst>{0}{0}{0}</a:fillStyleLst>", fill);
+  xml += &format!("<a:lnStyleLst>{0}{0}{0}</a:lnStyleLst>", format!("<a:ln w=\"9525\">{}</a:ln>", fill));
+  xml += "<a:effectStyleLst><a:effectStyle><a:effectLst/></a:effectStyle><a:effectStyle><a:effectLst/></a:effectStyle>\
+          <a:effectStyle><a:effectLst/></a:effectStyle></a:effectStyleLst>";
+  xml += &format!("<a:bgFillStyleLst>{0}{0}{0}</a:bgFillStyleLst>", fill);
+  xml += "</a:fmtScheme></a:themeElements></a:theme>";
+  xml
+}
+
+fn slide_xml(slide: &Slide, image_size: (u32, u32)) -> String {
+  // Fit the image below the title, centered, keeping its aspect ratio
+  let area_x = SLIDE_MARGIN_EMU;
+  let area_y = SLIDE_MARGIN_EMU + TITLE_HEIGHT_EMU;
+  let area_w = SLIDE_WIDTH_EMU - SLIDE_MARGIN_EMU * 2;
+  let area_h = SLIDE_HEIGHT_EMU - area_y - SLIDE_MARGIN_EMU;
+  let (image_w, image_h) = (image_size.0.max(1) as f64, image_size.1.max(1) as f64);
+  let fit = (area_w as f64 / image_w).min(area_h as f64 / image_h);
+  let (cx, cy) = ((image_w * fit) as i64, (image_h * fit) as i64);
+  let (x, y) = (area_x + (area_w - cx) / 2, area_y + (area_h - cy) / 2);
+
+  let mut xml = format!("{}<p:sld xmlns:a=\"{}\" xmlns:r=\"{}\" xmlns:p=\"{}\"><p:cSld><p:spTree>", XML_HEADER, NS_A, NS_R, NS_P);
+  xml += "<p:nvGrpSpPr><p:cNvPr id=\"1\" name=\"\"/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/>";
+  xml += &format!(
+    "<p:sp><p:nvSpPr><p:cNvPr id=\"2\" name=\"Title\"/><p:cNvSpPr txBox=\"1\"/><p:nvPr/></p:nvSpPr>\
+     <p:spPr><a:xfrm><a:off x=\"{}\" y=\"{}\"/><a:ext cx=\"{}\" cy=\"{}\"/></a:xfrm><a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></p:spPr>\
+     <p:txBody><a:bodyPr anchor=\"ctr\"/><a:lstStyle/><a:p><a:r><a:rPr lang=\"en-US\" sz=\"2800\" b=\"1\"/><a:t>{}</a:t></a:r></a:p></p:txBody></p:sp>",
+    SLIDE_MARGIN_EMU, SLIDE_MARGIN_EMU, area_w, TITLE_HEIGHT_EMU, xml_escape(&slide.title)
+  );
+  xml += &format!(
+    "<p:pic><p:nvPicPr><p:cNvPr id=\"3\" name=\"Board region\"/><p:cNvPicPr><a:picLocks noChangeAspect=\"1\"/></p:cNvPicPr><p:nvPr/></p:nvPicPr>\
+     <p:blipFill><a:blip r:embed=\"rId2\"/><a:stretch><a:fillRect/></a:stretch></p:blipFill>\
+     <p:spPr><a:xfrm><a:off x=\"{}\" y=\"{}\"/><a:ext cx=\"{}\" cy=\"{}\"/></a:xfrm><a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></p:spPr></p:pic>",
+    x, y, cx, cy
+  );
+  xml += "</p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sld>";
+  xml
+}
+
+pub fn to_pptx(slides: &[Slide], images: &[Vec<u8>]) -> Result<Vec<u8>, String> {
+  check_images(slides, images)?;
+
+  let mut parts: Vec<(String, Vec<u8>)> = vec![
+    ("[Content_Types].xml".to_string(), content_types(slides.len()).into_bytes()),
+    ("_rels/.rels".to_string(), relationships(&[("officeDocument", "ppt/presentation.xml")]).into_bytes()),
+    ("ppt/presentation.xml".to_string(), presentation_xml(slides.len()).into_bytes()),
+    ("ppt/slideMasters/slideMaster1.xml".to_string(), slide_master_xml().into_bytes()),
+    ("ppt/slideMasters/_rels/slideMaster1.xml.rels".to_string(),
+      relationships(&[("slideLayout", "../slideLayouts/slideLayout1.xml"), ("theme", "../theme/theme1.xml")]).into_bytes()),
+    ("ppt/slideLayouts/slideLayout1.xml".to_string(), slide_layout_xml().into_bytes()),
+    ("ppt/slideLayouts/_rels/slideLayout1.xml.rels".to_string(),
+      relationships(&[("slideMaster", "../slideMasters/slideMaster1.xml")]).into_bytes()),
+    ("ppt/theme/theme1.xml".to_string(), theme_xml().into_bytes()),
+  ];
+
+  let slide_targets: Vec<String> = (1..=slides.len()).map(|n| format!("slides/slide{}.xml", n)).collect();
+  let mut presentation_rels = vec![("slideMaster", "slideMasters/slideMaster1.xml")];
+  presentation_rels.extend(slide_targets.iter().map(|target| ("slide", target.as_str())));
+  presentation_rels.push(("theme", "theme/theme1.xml"));
+  parts.push(("ppt/_rels/presentation.xml.rels".to_string(), relationships(&presentation_rels).into_bytes()));
+
+  for (index, (slide, image)) in slides.iter().zip(images).enumerate() {
+    let n = index + 1;
+    let size = imagesize::blob_size(image)
+      .map_err(|e| format!("Failed to read slide image {}: {}", n, e))?;
+    let image_target = format!("../media/image{}.png", n);
+    parts.push((format!("ppt/slides/slide{}.xml", n), slide_xml(slide, (size.width as u32, size.height as u32)).into_bytes()));
+    parts.push((
+      format!("ppt/slides/_rels/slide{}.xml.rels", n),
+      relationships(&[("slideLayout", "../slideLayouts/slideLayout1.xml"), ("image", &image_target)]).into_bytes(),
+    ));
+    parts.push((format!("ppt/media/image{}.png", n), image.clone()));
+  }
+
+  let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+  let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+  for (name, data) in parts {
+    zip.start_file(name.as_str(), options)
+      .map_err(|e| format!("Failed to create {} in presentation: {}", name, e))?;
+    zip.write_all(&data)
+      .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+  }
+  let cursor = zip.finish()
+    .map_err(|e| format!("Failed to finalize presentation: {}", e))?;
+  Ok(cursor.into_inner())
+}
@@ -0,0 +1,288 @@
+use std::path::Path;
+
+use crate::archive;
+use crate::curves;
+use crate::lazy_media;
+use crate::media;
+use crate::model;
+use crate::raster;
+use crate::relations;
+use crate::render;
+use crate::shapes;
+use crate::textures;
+
+// Location of the rendered board thumbnail inside a .fim container. OS
+// preview generators (Quick Look, Explorer thumbnail handlers) and the
+// library view read this entry instead of parsing board.json.
+pub const PREVIEW_ENTRY: &str = "preview.png";
+// Longest edge of the preview in pixels
+pub const PREVIEW_MAX_SIZE: u32 = 512;
+const MAX_PREVIEW_BYTES: u64 = 16 * 1024 * 1024;
+const PREVIEW_MARGIN: f64 = 16.0;
+// The board as it looks, text and images included, drawn by the native
+// renderer for the recent-files screen and the library, where telling apart
+// boards with the same name takes more than note colors
+pub const THUMBNAIL_ENTRY: &str = "thumbnail.png";
+pub const THUMBNAIL_MAX_SIZE: u32 = 512;
+// Boards whose images add up to more than this (or that left them in their
+// file, see lazy_media.rs) are drawn without them, so that saving needn't
+// encode them all
+const THUMBNAIL_IMAGE_BUDGET: usize = 16 * 1024 * 1024;
+
+const DEFAULT_BACKGROUND: Rgba = [255, 255, 255, 255];
+const DEFAULT_NOTE_FILL: Rgba = [255, 248, 196, 255];
+const DEFAULT_NOTE_BORDER: Rgba = [200, 190, 140, 255];
+const SHAPE_FILL: Rgba = [232, 236, 242, 255];
+const SHAPE_STROKE: Rgba = [170, 180, 195, 255];
+const CONNECTION_COLOR: Rgba = [150, 150, 150, 255];
+const STROKE_COLOR: Rgba = [60, 60, 60, 255];
+
+type Rgba = [u8; 4];
+
+// Parse `#rgb`, `#rrggbb` or `#rrggbbaa` colors as used in note styles.
+pub fn parse_hex_color(value: &str) -> Option<Rgba> {
+  let hex = value.trim().strip_prefix('#')?;
+  let channel = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+  match hex.len() {
+    3 => Some([channel(0, 1)? * 17, channel(1, 1)? * 17, channel(2, 1)? * 17, 255]),
+    6 => Some([channel(0, 2)?, channel(2, 2)?, channel(4, 2)?, 255]),
+    8 => Some([channel(0, 2)?, channel(2, 2)?, channel(4, 2)?, channel(6, 2)?]),
+    _ => None,
+  }
+}
+
+// Minimal RGBA raster with alpha blending; enough for a thumbnail.
+struct Raster {
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+}
+
+impl Raster {
+  fn new(width: u32, height: u32, background: Rgba) -> Self {
+    let pixels = background.iter().copied().cycle().take((width * height * 4) as usize).collect();
+    Raster { width, height, pixels }
+  }
+
+  fn blend(&mut self, x: i64, y: i64, color: Rgba) {
+    if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+      return;
+    }
+    let i = ((y as u32 * self.width + x as u32) * 4) as usize;
+    let alpha = color[3] as u32;
+    for (dst, src) in self.pixels[i..i + 3].iter_mut().zip(color) {
+      *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+    }
+    self.pixels[i + 3] = 255;
+  }
+
+  fn fill_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: Rgba) {
+    for y in y0.round() as i64..y1.round() as i64 {
+      for x in x0.round() as i64..x1.round() as i64 {
+        self.blend(x, y, color);
+      }
+    }
+  }
+
+  fn stroke_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: Rgba) {
+    self.line(x0, y0, x1, y0, color);
+    self.line(x1, y0, x1, y1, color);
+    self.line(x1, y1, x0, y1, color);
+    self.line(x0, y1, x0, y0, color);
+  }
+
+  fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: Rgba) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as i64;
+    for step in 0..=steps {
+      let t = step as f64 / steps as f64;
+      self.blend((x0 + (x1 - x0) * t).round() as i64, (y0 + (y1 - y0) * t).round() as i64, color);
+    }
+  }
+}
+
+fn note_colors(doc: &model::BoardDocument, note: &model::Note) -> (Rgba, Rgba) {
+  let style = doc.note_style(note);
+
+  let fill = style.and_then(|s| s.fill.as_deref()).and_then(parse_hex_color).unwrap_or(DEFAULT_NOTE_FILL);
+  let border = style
+    .and_then(|s| s.border.as_ref())
+    .and_then(|b| b.color.as_deref())
+    .and_then(parse_hex_color)
+    .unwrap_or(DEFAULT_NOTE_BORDER);
+  (fill, border)
+}
+
+// Render a schematic thumbnail of the board: background shapes, connections
+// and note rectangles in their fill colors. Text is not drawn at this size.
+pub fn render_preview(doc: &model::BoardDocument) -> Result<Vec<u8>, String> {
+  let bounds = raster::content_bounds(doc).unwrap_or(model::Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 });
+  let (min_x, min_y, max_x, max_y) = (bounds.x, bounds.y, bounds.x + bounds.w, bounds.y + bounds.h);
+
+  let content_w = (max_x - min_x).max(1.0) + PREVIEW_MARGIN * 2.0;
+  let content_h = (max_y - min_y).max(1.0) + PREVIEW_MARGIN * 2.0;
+  let scale = (PREVIEW_MAX_SIZE as f64 / content_w.max(content_h)).min(1.0);
+  let width = ((content_w * scale).ceil() as u32).max(1);
+  let height = ((content_h * scale).ceil() as u32).max(1);
+
+  let background = doc.document_style.as_ref()
+    .and_then(|s| s.background.as_ref())
+    .and_then(|b| b.color.as_deref())
+    .and_then(parse_hex_color)
+    .unwrap_or(DEFAULT_BACKGROUND);
+  let mut raster = Raster::new(width, height, background);
+
+  let tx = |x: f64| (x - min_x + PREVIEW_MARGIN) * scale;
+  let ty = |y: f64| (y - min_y + PREVIEW_MARGIN) * scale;
+
+  // Background texture, sampled at each pixel center in board space
+  if let Some(texture) = textures::load(doc) {
+    for py in 0..height as i64 {
+      for px in 0..width as i64 {
+        let x = (px as f64 + 0.5) / scale + min_x - PREVIEW_MARGIN;
+        let y = (py as f64 + 0.5) / scale + min_y - PREVIEW_MARGIN;
+        raster.blend(px, py, texture.sample(x, y));
+      }
+    }
+  }
+
+  for shape in &doc.shapes {
+    let f = &shape.frame;
+    match shapes::kind(shape) {
+      "line" => {
+        for segment in shapes::absolute_points(shape).windows(2) {
+          raster.line(tx(segment[0].x), ty(segment[0].y), tx(segment[1].x), ty(segment[1].y), SHAPE_STROKE);
+        }
+      },
+      "ellipse" | "polygon" => {
+        // Sample each pixel center of the bounding box back in board space
+        let points = shapes::absolute_points(shape);
+        let xs = points.iter().map(|p| p.x).chain([f.x, f.x + f.w]);
+        let ys = points.iter().map(|p| p.y).chain([f.y, f.y + f.h]);
+        let (x0, x1) = xs.fold((f64::MAX, f64::MIN), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        let (y0, y1) = ys.fold((f64::MAX, f64::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+        for py in ty(y0).floor() as i64..ty(y1).ceil() as i64 {
+          for px in tx(x0).floor() as i64..tx(x1).ceil() as i64 {
+            let point = model::Point {
+              x: (px as f64 + 0.5) / scale + min_x - PREVIEW_MARGIN,
+              y: (py as f64 + 0.5) / scale + min_y - PREVIEW_MARGIN,
+            };
+            if shapes::contains_point(shape, &point) {
+              raster.blend(px, py, SHAPE_FILL);
+            }
+          }
+        }
+      },
+      _ => raster.fill_rect(tx(f.x), ty(f.y), tx(f.x + f.w), ty(f.y + f.h), SHAPE_FILL),
+    }
+  }
+
+  for conn in &doc.connections {
+    let src = doc.notes.iter().find(|n| n.id == conn.src_note_id);
+    let dst = doc.notes.iter().find(|n| n.id == conn.dst_note_id);
+    if let (Some(src), Some(dst)) = (src, dst) {
+      let style = relations::effective_style(doc, conn);
+      let color = style.as_ref()
+        .and_then(|s| s.color.as_deref())
+        .and_then(parse_hex_color)
+        .unwrap_or(CONNECTION_COLOR);
+      let center = |f: &model::Rect| model::Point { x: f.x + f.w / 2.0, y: f.y + f.h / 2.0 };
+      let path = curves::flatten(center(&src.frame), center(&dst.frame), conn, style.as_ref());
+      for segment in path.windows(2) {
+        raster.line(tx(segment[0].x), ty(segment[0].y), tx(segment[1].x), ty(segment[1].y), color);
+      }
+    }
+  }
+
+  for stroke in doc.strokes.iter().flatten() {
+    let color = stroke.color.as_deref().and_then(parse_hex_color).unwrap_or(STROKE_COLOR);
+    for segment in stroke.points.windows(2) {
+      raster.line(tx(segment[0].x), ty(segment[0].y), tx(segment[1].x), ty(segment[1].y), color);
+    }
+  }
+
+  for note in &doc.notes {
+    let (mut fill, mut border) = note_colors(doc, note);
+    if note.faded.unwrap_or(false) {
+      fill[3] /= 2;
+      border[3] /= 2;
+    }
+    let f = &note.frame;
+    raster.fill_rect(tx(f.x), ty(f.y), tx(f.x + f.w), ty(f.y + f.h), fill);
+    raster.stroke_rect(tx(f.x), ty(f.y), tx(f.x + f.w), ty(f.y + f.h), border);
+  }
+
+  media::encode_png_rgba(&raster.pixels, raster.width, raster.height)
+}
+
+// Render the board as thumbnail.png: the whole content, fitted into
+// THUMBNAIL_MAX_SIZE pixels
+pub fn render_thumbnail(doc: &model::BoardDocument) -> Result<Vec<u8>, String> {
+  let image_bytes: Option<usize> = doc.images.iter().flatten()
+    .map(|image| (!lazy_media::is_deferred(image)).then(|| image.data_base64.as_ref().map_or(0, |data| data.len())))
+    .sum();
+  let without_images;
+  let doc = if image_bytes.is_some_and(|bytes| bytes <= THUMBNAIL_IMAGE_BUDGET) {
+    doc
+  } else {
+    let mut copy = doc.clone();
+    for note in copy.notes.iter_mut() {
+      note.images = None;
+    }
+    without_images = copy;
+    &without_images
+  };
+
+  let bounds = raster::content_bounds(doc).unwrap_or(model::Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 });
+  let region = model::Rect {
+    x: bounds.x - PREVIEW_MARGIN,
+    y: bounds.y - PREVIEW_MARGIN,
+    w: bounds.w.max(1.0) + PREVIEW_MARGIN * 2.0,
+    h: bounds.h.max(1.0) + PREVIEW_MARGIN * 2.0,
+  };
+  let scale = (THUMBNAIL_MAX_SIZE as f64 / region.w.max(region.h)).min(1.0);
+  let plan = raster::RasterPlan {
+    scale,
+    width: ((region.w * scale).ceil() as u32).max(1),
+    height: ((region.h * scale).ceil() as u32).max(1),
+    region,
+  };
+  render::to_png(doc, &plan)
+}
+
+fn open_container(path: &Path) -> Result<zip::ZipArchive<std::fs::File>, String> {
+  let file = std::fs::File::open(path)
+    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+  let mut archive = zip::ZipArchive::new(file)
+    .map_err(|e| format!("Failed to read zip archive '{}': {}", path.display(), e))?;
+
+  archive::validate_archive(&mut archive)?;
+  Ok(archive)
+}
+
+// Extract the embedded preview from a .fim file without loading the board.
+pub fn extract_preview(path: &Path) -> Result<Vec<u8>, String> {
+  Ok(archive::read_entry_limited(&mut open_container(path)?, PREVIEW_ENTRY, MAX_PREVIEW_BYTES)?)
+}
+
+// The thumbnail of a .fim file, or the schematic preview of boards saved
+// before thumbnails were, without loading the board
+pub fn extract_thumbnail(path: &Path) -> Result<Vec<u8>, String> {
+  let mut archive = open_container(path)?;
+  match archive::read_entry_limited(&mut archive, THUMBNAIL_ENTRY, MAX_PREVIEW_BYTES) {
+    Err(archive::ArchiveError::MissingEntry { .. }) => Ok(archive::read_entry_limited(&mut archive, PREVIEW_ENTRY, MAX_PREVIEW_BYTES)?),
+    result => Ok(result?),
+  }
+}
+
+// Whether a .fim file carries a thumbnail or an embedded preview, without
+// reading it.
+pub fn has_preview(path: &Path) -> bool {
+  let Ok(file) = std::fs::File::open(path) else {
+    return false;
+  };
+  let Ok(mut archive) = zip::ZipArchive::new(file) else {
+    return false;
+  };
+  let found = archive.by_name(THUMBNAIL_ENTRY).is_ok() || archive.by_name(PREVIEW_ENTRY).is_ok();
+  found
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::path::Path;
+
+// Printing without the webview, whose window.print() clips the canvas to the
+// window. The board is written as a PDF by pdf.rs, one page fitted or tiled
+// across pages as in PDF exports, and the file is handed to the system: to
+// CUPS with `lp` on macOS and Linux, or to the print verb of the app
+// registered for PDFs on Windows. Asking for the print dialog instead opens
+// the PDF in the default viewer to print from there.
+
+const MAX_COPIES: u32 = 99;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PrintJob {
+  // The PDF that was printed, kept until the next share or print
+  pub path: String,
+  pub pages: usize,
+  // None for the default printer
+  pub printer: Option<String>,
+  // As reported by CUPS; the other ways of printing report none
+  pub job_id: Option<String>,
+  // Opened in the default viewer instead of sent to a printer
+  pub opened: bool,
+}
+
+// CUPS media name of a page size as accepted by poster.rs
+#[cfg(not(windows))]
+fn media(page_size: &str) -> &'static str {
+  match page_size {
+    "a3" => "A3",
+    "a5" => "A5",
+    "letter" => "Letter",
+    "legal" => "Legal",
+    _ => "A4",
+  }
+}
+
+// Checked here since the name is passed on the command line
+pub fn validate(printer: Option<&str>, copies: Option<u32>) -> Result<(), String> {
+  if let Some(printer) = printer {
+    if printer.trim().is_empty() || printer.starts_with('-') || printer.chars().any(|c| c.is_control()) {
+      return Err(format!("Invalid printer name '{}'", printer));
+    }
+  }
+  if let Some(copies) = copies {
+    if !(1..=MAX_COPIES).contains(&copies) {
+      return Err(format!("Copies must be between 1 and {}", MAX_COPIES));
+    }
+  }
+  Ok(())
+}
+
+// The request ID in lp's "request id is Office-42 (1 file(s))"
+#[cfg(not(windows))]
+fn job_id(output: &str) -> Option<String> {
+  let rest = output.split("request id is ").nth(1)?;
+  rest.split_whitespace().next().map(str::to_string)
+}
+
+#[cfg(not(windows))]
+pub fn send(path: &Path, page_size: &str, printer: Option<&str>, copies: Option<u32>) -> Result<Option<String>, String> {
+  let mut command = std::process::Command::new("lp");
+  if let Some(printer) = printer {
+    command.arg("-d").arg(printer);
+  }
+  command.arg("-n").arg(copies.unwrap_or(1).to_string())
+    .arg("-o").arg(format!("media={}", media(page_size)))
+    .arg("-t").arg("Board")
+    .arg("--")
+    .arg(path);
+  let output = command.output()
+    .map_err(|e| format!("Failed to run lp (is CUPS installed?): {}", e))?;
+  if !output.status.success() {
+    let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    return Err(format!("Printing failed: {}", if error.is_empty() { output.status.to_string() } else { error }));
+  }
+  Ok(job_id(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// The print verb of the PDF viewer prints one copy on the default printer,
+// PrintTo on a named one; the page size is the printer's
+#[cfg(windows)]
+pub fn send(path: &Path, _page_size: &str, printer: Option<&str>, copies: Option<u32>) -> Result<Option<String>, String> {
+  let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+  let script = match printer {
+    Some(printer) => format!("Start-Process -FilePath {} -Verb PrintTo -ArgumentList {} -WindowStyle Hidden", quote(&path.to_string_lossy()), quote(&format!("\"{}\"", printer))),
+    None => format!("Start-Process -FilePath {} -Verb Print -WindowStyle Hidden", quote(&path.to_string_lossy())),
+  };
+  for _ in 0..copies.unwrap_or(1) {
+    let output = std::process::Command::new("powershell")
+      .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+      .output()
+      .map_err(|e| format!("Failed to start printing: {}", e))?;
+    if !output.status.success() {
+      let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+      return Err(format!("Printing failed (is a PDF viewer installed?): {}", error));
+    }
+  }
+  Ok(None)
+}
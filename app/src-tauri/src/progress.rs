@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Arc;
+use tauri::ipc::Channel;
+
+use crate::{model, tasks};
+
+// Progress of long opens, saves and exports, streamed to the frontend over
+// the channel a command was given (its optional `on_progress` argument).
+// Without a channel every report is a no-op, so helpers can take a Progress
+// unconditionally. A Progress also carries the operation's cancellation flag,
+// which long loops poll through `check_cancelled`; see tasks.rs.
+
+// Data is written in chunks of this size so large boards report as they go
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProgressEvent {
+  // Same for every event of one command call
+  pub operation_id: String,
+  // What is happening: "reading", "parsing", "writing", "done", ...
+  pub phase: String,
+  // 0–100 for the whole operation
+  pub percent: f64,
+}
+
+#[derive(Default)]
+pub struct Progress {
+  channel: Option<Channel<ProgressEvent>>,
+  operation_id: String,
+  // Last whole percentage sent for the current phase, to avoid flooding the
+  // channel from tight loops
+  last_sent: AtomicI32,
+  // Set by `cancel_task`
+  cancelled: Arc<AtomicBool>,
+}
+
+impl Progress {
+  pub fn new(channel: Option<Channel<ProgressEvent>>) -> Progress {
+    Progress {
+      channel,
+      operation_id: model::new_id("op"),
+      last_sent: AtomicI32::new(-1),
+      cancelled: Arc::new(AtomicBool::new(false)),
+    }
+  }
+
+  // For callers that have nobody to report to (autosave, quick capture)
+  pub fn none() -> Progress {
+    Progress::default()
+  }
+
+  // Start a phase; always sent
+  pub fn phase(&self, phase: &str, percent: f64) {
+    self.last_sent.store(percent as i32, Ordering::Relaxed);
+    self.send(phase, percent);
+  }
+
+  // Progress within a phase that spans `start`–`end` percent, `done` of
+  // `total` units in. Only sent when the whole percentage changes.
+  pub fn step(&self, phase: &str, start: f64, end: f64, done: u64, total: u64) {
+    let fraction = if total == 0 { 1.0 } else { (done as f64 / total as f64).min(1.0) };
+    let percent = start + (end - start) * fraction;
+    if self.last_sent.swap(percent as i32, Ordering::Relaxed) != percent as i32 {
+      self.send(phase, percent);
+    }
+  }
+
+  pub fn operation_id(&self) -> &str {
+    &self.operation_id
+  }
+
+  pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+    self.cancelled.clone()
+  }
+
+  // Call at points where stopping leaves nothing half-written
+  pub fn check_cancelled(&self) -> Result<(), String> {
+    if self.cancelled.load(Ordering::Relaxed) {
+      return Err(tasks::CANCELLED.to_string());
+    }
+    Ok(())
+  }
+
+  pub fn done(&self) {
+    self.phase("done", 100.0);
+  }
+
+  fn send(&self, phase: &str, percent: f64) {
+    let Some(channel) = &self.channel else {
+      return;
+    };
+    let event = ProgressEvent { operation_id: self.operation_id.clone(), phase: phase.to_string(), percent };
+    // The window may be gone; the operation itself carries on
+    if let Err(e) = channel.send(event) {
+      log::debug!("Dropping progress event: {}", e);
+    }
+  }
+
+  // Write `data`, reporting it as `phase` over `start`–`end` percent
+  pub fn write_all(&self, writer: &mut dyn Write, data: &[u8], phase: &str, start: f64, end: f64) -> std::io::Result<()> {
+    self.phase(phase, start);
+    let mut written = 0;
+    for chunk in data.chunks(CHUNK_SIZE) {
+      writer.write_all(chunk)?;
+      written += chunk.len();
+      self.step(phase, start, end, written as u64, data.len() as u64);
+    }
+    Ok(())
+  }
+
+  // Like std::fs::write, with progress
+  pub fn write_file(&self, path: &std::path::Path, data: &[u8], start: f64, end: f64) -> std::io::Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    self.write_all(&mut file, data, "writing", start, end)?;
+    file.flush()
+  }
+}
@@ -0,0 +1,64 @@
+use serde_json::Value;
+
+use crate::model;
+
+// Free-form key/value metadata on documents and notes, in the spirit of
+// Markdown frontmatter. Values are arbitrary JSON; the helpers here flatten
+// them to text where a format only has room for strings.
+
+// Text form of a property value: strings as-is, arrays comma-separated,
+// everything else as compact JSON
+pub fn value_text(value: &Value) -> String {
+  match value {
+    Value::Null => String::new(),
+    Value::String(s) => s.clone(),
+    Value::Array(items) => items.iter().map(value_text).collect::<Vec<_>>().join(", "),
+    other => other.to_string(),
+  }
+}
+
+// Properties sorted by key, so exports are stable
+pub fn sorted(properties: &Option<std::collections::HashMap<String, Value>>) -> Vec<(&String, &Value)> {
+  let mut entries: Vec<_> = properties.iter().flatten().collect();
+  entries.sort_by(|a, b| a.0.cmp(b.0));
+  entries
+}
+
+// Attribute name for a property in formats with XML-style attributes (OPML):
+// prefixed to stay clear of the format's own attributes, with characters
+// that aren't valid in names replaced
+pub fn attribute_name(key: &str) -> String {
+  let name: String = key.chars()
+    .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+    .collect();
+  format!("_prop_{}", name)
+}
+
+// Property query: notes having `key`, optionally with a matching value. A
+// list-valued property matches when any element equals `value`; string
+// comparison ignores case.
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct PropertyQuery {
+  pub key: String,
+  pub value: Option<Value>,
+}
+
+fn value_matches(actual: &Value, wanted: &Value) -> bool {
+  match (actual, wanted) {
+    (Value::Array(items), _) if !wanted.is_array() => items.iter().any(|item| value_matches(item, wanted)),
+    (Value::String(a), Value::String(b)) => a.eq_ignore_ascii_case(b),
+    _ => actual == wanted,
+  }
+}
+
+pub fn filter_note_ids(doc: &model::BoardDocument, query: &PropertyQuery) -> Vec<model::ID> {
+  doc.notes.iter()
+    .filter(|note| {
+      match note.properties.as_ref().and_then(|p| p.get(&query.key)) {
+        Some(actual) => query.value.as_ref().map_or(true, |wanted| value_matches(actual, wanted)),
+        None => false,
+      }
+    })
+    .map(|note| note.id.clone())
+    .collect()
+}
@@ -0,0 +1,449 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::{outline, settings};
+
+// Publishing a board as a page in Confluence or Notion: the rendered board as
+// an image followed by its outline. API tokens live in the OS keychain, never
+// in settings. Each publish is recorded per board and target, so publishing
+// the same board again updates its page instead of creating another one.
+//
+// Confluence pages go into a space (`space` is its key), optionally under a
+// parent page; the body is storage-format XHTML with the image attached to
+// the page. Notion pages are created under a parent page that has been shared
+// with the integration; the outline becomes nested list blocks.
+
+const KEYCHAIN_SERVICE: &str = settings::APP_IDENTIFIER;
+const PUBLISHED_FILE: &str = "published-pages.json";
+const IMAGE_NAME: &str = "board.png";
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+const USER_AGENT: &str = concat!("FIM/", env!("CARGO_PKG_VERSION"));
+const NOTION_API: &str = "https://api.notion.com/v1";
+const NOTION_VERSION: &str = "2022-06-28";
+// Limits of the Notion API: blocks per request, nesting levels per request
+// and characters per text object
+const NOTION_MAX_BLOCKS: usize = 100;
+const NOTION_MAX_DEPTH: usize = 2;
+const NOTION_MAX_TEXT: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+  Confluence,
+  Notion,
+}
+
+impl Target {
+  pub fn parse(target: &str) -> Result<Target, String> {
+    match target {
+      "confluence" => Ok(Target::Confluence),
+      "notion" => Ok(Target::Notion),
+      _ => Err(format!("Unknown publishing target '{}'. Must be one of: confluence, notion", target)),
+    }
+  }
+
+  fn name(self) -> &'static str {
+    match self {
+      Target::Confluence => "confluence",
+      Target::Notion => "notion",
+    }
+  }
+}
+
+// What to publish and where
+pub struct Publication<'a> {
+  pub title: &'a str,
+  pub outline: &'a [outline::OutlineNode],
+  pub image: &'a [u8],
+  // Confluence space key
+  pub space: Option<&'a str>,
+  // Parent page: optional for Confluence, required for Notion
+  pub parent: Option<&'a str>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublishedPage {
+  pub target: String,
+  // Path of the published board
+  pub board: String,
+  #[serde(rename = "pageId")]
+  pub page_id: String,
+  pub url: String,
+  #[serde(rename = "publishedAt")]
+  pub published_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PublishedPages {
+  pub pages: Vec<PublishedPage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PublishResult {
+  pub page_id: String,
+  pub url: String,
+  // An earlier publish's page was updated rather than a new one created
+  pub updated: bool,
+}
+
+fn published_path() -> Option<std::path::PathBuf> {
+  settings::data_dir().map(|dir| dir.join(PUBLISHED_FILE))
+}
+
+impl PublishedPages {
+  pub fn load() -> PublishedPages {
+    published_path()
+      .and_then(|path| std::fs::read_to_string(path).ok())
+      .and_then(|content| serde_json::from_str(&content).ok())
+      .unwrap_or_default()
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    let path = published_path().ok_or("Could not determine the app data directory")?;
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(self)
+      .map_err(|e| format!("Failed to serialize published pages: {}", e))?;
+    std::fs::write(&path, json)
+      .map_err(|e| format!("Failed to write published pages '{}': {}", path.display(), e))
+  }
+
+  pub fn find(&self, target: Target, board: &str) -> Option<&PublishedPage> {
+    self.pages.iter().find(|p| p.target == target.name() && p.board == board)
+  }
+
+  pub fn record(&mut self, target: Target, board: &str, result: &PublishResult) {
+    self.pages.retain(|p| !(p.target == target.name() && p.board == board));
+    self.pages.push(PublishedPage {
+      target: target.name().to_string(),
+      board: board.to_string(),
+      page_id: result.page_id.clone(),
+      url: result.url.clone(),
+      published_at: chrono::Utc::now(),
+    });
+  }
+}
+
+fn keychain_entry(target: Target) -> Result<keyring::Entry, String> {
+  keyring::Entry::new(KEYCHAIN_SERVICE, target.name())
+    .map_err(|e| format!("Failed to access the keychain: {}", e))
+}
+
+// Store the API token for `target`, or remove it when `token` is empty
+pub fn set_token(target: Target, token: &str) -> Result<(), String> {
+  let entry = keychain_entry(target)?;
+  let token = token.trim();
+  if token.is_empty() {
+    return match entry.delete_credential() {
+      Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+      Err(e) => Err(format!("Failed to remove the {} token from the keychain: {}", target.name(), e)),
+    };
+  }
+  entry.set_password(token)
+    .map_err(|e| format!("Failed to store the {} token in the keychain: {}", target.name(), e))
+}
+
+pub fn has_token(target: Target) -> bool {
+  keychain_entry(target).is_ok_and(|entry| entry.get_password().is_ok())
+}
+
+fn token(target: Target) -> Result<String, String> {
+  match keychain_entry(target)?.get_password() {
+    Ok(token) => Ok(token),
+    Err(keyring::Error::NoEntry) => Err(format!("No {} API token is set; add one in the publishing settings", target.name())),
+    Err(e) => Err(format!("Failed to read the {} token from the keychain: {}", target.name(), e)),
+  }
+}
+
+fn agent() -> ureq::Agent {
+  ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).user_agent(USER_AGENT).build()
+}
+
+// The JSON body of a response, with the service's error message on failure
+fn read_json(response: Result<ureq::Response, ureq::Error>, action: &str) -> Result<Value, String> {
+  match response {
+    Ok(response) => {
+      let body = response.into_string().map_err(|e| format!("Failed to {}: {}", action, e))?;
+      if body.trim().is_empty() {
+        return Ok(Value::Null);
+      }
+      serde_json::from_str(&body).map_err(|e| format!("Failed to {}: invalid response: {}", action, e))
+    },
+    Err(ureq::Error::Status(status, response)) => {
+      let body = response.into_string().unwrap_or_default();
+      let message = serde_json::from_str::<Value>(&body).ok()
+        .and_then(|v| v.get("message").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or(body);
+      Err(format!("Failed to {} (HTTP {}): {}", action, status, message.trim()))
+    },
+    Err(e) => Err(format!("Failed to {}: {}", action, e)),
+  }
+}
+
+// An earlier publish's page, or None when it has been deleted since
+fn read_existing(response: Result<ureq::Response, ureq::Error>, action: &str) -> Result<Option<Value>, String> {
+  match response {
+    Err(ureq::Error::Status(404, _)) => Ok(None),
+    response => read_json(response, action).map(Some),
+  }
+}
+
+fn string_field(value: &Value, pointer: &str) -> Option<String> {
+  value.pointer(pointer).and_then(Value::as_str).map(str::to_string)
+}
+
+// A multipart/form-data body with a single file field
+fn multipart(field: &str, file_name: &str, mime: &str, data: &[u8]) -> (String, Vec<u8>) {
+  let boundary = format!("fim-{}", crate::audit::hash_bytes(data).get(..24).unwrap_or("boundary"));
+  let mut body = format!(
+    "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+    boundary, field, file_name, mime
+  ).into_bytes();
+  body.extend_from_slice(data);
+  body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+  (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+// Publish to `target`, updating `existing` (an earlier publish's page) when
+// given and still there
+pub fn publish(target: Target, config: &settings::PublishSettings, publication: &Publication, existing: Option<&str>) -> Result<PublishResult, String> {
+  let token = token(target)?;
+  match target {
+    Target::Confluence => publish_confluence(config, &token, publication, existing),
+    Target::Notion => publish_notion(&token, publication, existing),
+  }
+}
+
+// Confluence
+
+struct Confluence {
+  agent: ureq::Agent,
+  base: String,
+  authorization: String,
+}
+
+impl Confluence {
+  fn request(&self, method: &str, path: &str) -> ureq::Request {
+    self.agent.request(method, &format!("{}/rest/api{}", self.base, path))
+      .set("Authorization", &self.authorization)
+      .set("Accept", "application/json")
+  }
+}
+
+// Storage-format body: the attached image, then the outline. Storage format
+// is XHTML, so void elements have to be closed.
+fn confluence_body(publication: &Publication) -> String {
+  format!(
+    "<p><ac:image ac:width=\"100%\"><ri:attachment ri:filename=\"{}\" /></ac:image></p>{}",
+    IMAGE_NAME,
+    outline::to_html(publication.outline).replace("<br>", "<br />")
+  )
+}
+
+fn publish_confluence(config: &settings::PublishSettings, token: &str, publication: &Publication, existing: Option<&str>) -> Result<PublishResult, String> {
+  use base64::Engine;
+
+  let base = config.confluence_url.as_deref().map(str::trim).filter(|u| !u.is_empty())
+    .ok_or("Set the Confluence site URL in the publishing settings first")?
+    .trim_end_matches('/');
+  // Confluence Cloud's REST API lives under /wiki
+  let base = if base.ends_with("/wiki") || !base.contains(".atlassian.net") { base.to_string() } else { format!("{}/wiki", base) };
+  // Cloud sites take the account email with an API token; Server and Data
+  // Center take a personal access token alone
+  let authorization = match config.confluence_user.as_deref().map(str::trim).filter(|u| !u.is_empty()) {
+    Some(user) => format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, token))),
+    None => format!("Bearer {}", token),
+  };
+  let confluence = Confluence { agent: agent(), base, authorization };
+
+  let current = match existing {
+    Some(id) => read_existing(confluence.request("GET", &format!("/content/{}?expand=version", id)).call(), "read the published Confluence page")?,
+    None => None,
+  };
+
+  let body = json!({ "storage": { "value": confluence_body(publication), "representation": "storage" } });
+  let page = match &current {
+    Some(page) => {
+      let id = string_field(page, "/id").ok_or("Invalid response from Confluence: page without id")?;
+      let version = page.pointer("/version/number").and_then(Value::as_u64).unwrap_or(1);
+      read_json(confluence.request("PUT", &format!("/content/{}", id)).send_json(json!({
+        "id": id,
+        "type": "page",
+        "title": publication.title,
+        "version": { "number": version + 1 },
+        "body": body,
+      })), "update the Confluence page")?
+    },
+    None => {
+      let space = publication.space.map(str::trim).filter(|s| !s.is_empty())
+        .ok_or("Choose the Confluence space to publish to")?;
+      let mut page = json!({
+        "type": "page",
+        "title": publication.title,
+        "space": { "key": space },
+        "body": body,
+      });
+      if let Some(parent) = publication.parent.filter(|p| !p.trim().is_empty()) {
+        page["ancestors"] = json!([{ "id": parent.trim() }]);
+      }
+      read_json(confluence.request("POST", "/content").send_json(page), "create the Confluence page")?
+    },
+  };
+  let page_id = string_field(&page, "/id").ok_or("Invalid response from Confluence: page without id")?;
+
+  // PUT creates the attachment or adds a version to the one already there
+  let (content_type, data) = multipart("file", IMAGE_NAME, "image/png", publication.image);
+  read_json(
+    confluence.request("PUT", &format!("/content/{}/child/attachment", page_id))
+      .set("X-Atlassian-Token", "no-check")
+      .set("Content-Type", &content_type)
+      .send_bytes(&data),
+    "upload the board image to Confluence",
+  )?;
+
+  let url = match (string_field(&page, "/_links/base"), string_field(&page, "/_links/webui")) {
+    (Some(base), Some(webui)) => format!("{}{}", base, webui),
+    _ => format!("{}/pages/viewpage.action?pageId={}", confluence.base, page_id),
+  };
+  Ok(PublishResult { page_id, url, updated: current.is_some() })
+}
+
+// Notion
+
+struct Notion {
+  agent: ureq::Agent,
+  authorization: String,
+}
+
+impl Notion {
+  fn request(&self, method: &str, path: &str) -> ureq::Request {
+    self.agent.request(method, &format!("{}{}", NOTION_API, path))
+      .set("Authorization", &self.authorization)
+      .set("Notion-Version", NOTION_VERSION)
+  }
+}
+
+fn rich_text(text: &str) -> Value {
+  let chars: Vec<char> = text.chars().collect();
+  let parts: Vec<Value> = chars.chunks(NOTION_MAX_TEXT)
+    .map(|chunk| json!({ "type": "text", "text": { "content": chunk.iter().collect::<String>() } }))
+    .collect();
+  Value::Array(parts)
+}
+
+// List blocks for outline items; levels below the deepest Notion accepts in
+// one request are flattened into it
+fn notion_blocks(nodes: &[outline::OutlineNode], depth: usize) -> Vec<Value> {
+  let mut blocks = Vec::new();
+  for node in nodes {
+    let mut text = node.text.clone();
+    if let Some(icon) = node.icon.as_deref() {
+      text = format!("{} {}", icon, text);
+    }
+    for link in &node.links {
+      text = format!("{}\n{}", text, link.href.as_deref().unwrap_or(&link.text));
+    }
+    let (kind, mut content) = match node.done {
+      Some(done) => ("to_do", json!({ "rich_text": rich_text(&text), "checked": done })),
+      None => ("bulleted_list_item", json!({ "rich_text": rich_text(&text) })),
+    };
+    let nested = depth < NOTION_MAX_DEPTH;
+    if nested && !node.children.is_empty() {
+      content["children"] = Value::Array(notion_blocks(&node.children, depth + 1));
+    }
+    blocks.push(json!({ "object": "block", "type": kind, kind: content }));
+    if !nested {
+      blocks.extend(notion_blocks(&node.children, depth));
+    }
+  }
+  blocks
+}
+
+fn upload_notion_image(notion: &Notion, image: &[u8]) -> Result<String, String> {
+  let upload = read_json(
+    notion.request("POST", "/file_uploads").send_json(json!({ "filename": IMAGE_NAME, "content_type": "image/png" })),
+    "start the image upload to Notion",
+  )?;
+  let id = string_field(&upload, "/id").ok_or("Invalid response from Notion: upload without id")?;
+  let (content_type, data) = multipart("file", IMAGE_NAME, "image/png", image);
+  read_json(
+    notion.request("POST", &format!("/file_uploads/{}/send", id)).set("Content-Type", &content_type).send_bytes(&data),
+    "upload the board image to Notion",
+  )?;
+  Ok(id)
+}
+
+fn clear_notion_page(notion: &Notion, page_id: &str) -> Result<(), String> {
+  let mut cursor: Option<String> = None;
+  let mut children = Vec::new();
+  loop {
+    let mut path = format!("/blocks/{}/children?page_size={}", page_id, NOTION_MAX_BLOCKS);
+    if let Some(cursor) = &cursor {
+      path = format!("{}&start_cursor={}", path, cursor);
+    }
+    let page = read_json(notion.request("GET", &path).call(), "read the published Notion page")?;
+    children.extend(page.get("results").and_then(Value::as_array).into_iter().flatten()
+      .filter_map(|block| string_field(block, "/id")));
+    cursor = string_field(&page, "/next_cursor");
+    if cursor.is_none() {
+      break;
+    }
+  }
+  for id in children {
+    read_json(notion.request("DELETE", &format!("/blocks/{}", id)).call(), "clear the published Notion page")?;
+  }
+  Ok(())
+}
+
+fn publish_notion(token: &str, publication: &Publication, existing: Option<&str>) -> Result<PublishResult, String> {
+  let notion = Notion { agent: agent(), authorization: format!("Bearer {}", token) };
+  let title = json!({ "title": { "title": rich_text(publication.title) } });
+
+  let current = match existing {
+    Some(id) => read_existing(notion.request("GET", &format!("/pages/{}", id)).call(), "read the published Notion page")?,
+    None => None,
+  };
+  // A page moved to the trash counts as deleted
+  let current = current.filter(|page| {
+    !["archived", "in_trash"].iter().any(|key| page.get(*key).and_then(Value::as_bool) == Some(true))
+  });
+  let updated = current.is_some();
+
+  let image_id = upload_notion_image(&notion, publication.image)?;
+  let mut blocks = vec![json!({
+    "object": "block",
+    "type": "image",
+    "image": { "type": "file_upload", "file_upload": { "id": image_id } },
+  })];
+  blocks.extend(notion_blocks(publication.outline, 0));
+
+  let page = match current {
+    Some(page) => {
+      let id = string_field(&page, "/id").ok_or("Invalid response from Notion: page without id")?;
+      read_json(notion.request("PATCH", &format!("/pages/{}", id)).send_json(json!({ "properties": title })), "rename the Notion page")?;
+      clear_notion_page(&notion, &id)?;
+      page
+    },
+    None => {
+      let parent = publication.parent.map(str::trim).filter(|p| !p.is_empty())
+        .ok_or("Choose the Notion page to publish under")?;
+      read_json(notion.request("POST", "/pages").send_json(json!({
+        "parent": { "page_id": parent },
+        "properties": title,
+      })), "create the Notion page")?
+    },
+  };
+  let page_id = string_field(&page, "/id").ok_or("Invalid response from Notion: page without id")?;
+
+  for chunk in blocks.chunks(NOTION_MAX_BLOCKS) {
+    read_json(
+      notion.request("PATCH", &format!("/blocks/{}/children", page_id)).send_json(json!({ "children": chunk })),
+      "write the Notion page",
+    )?;
+  }
+
+  let url = string_field(&page, "/url").unwrap_or_else(|| format!("https://www.notion.so/{}", page_id.replace('-', "")));
+  Ok(PublishResult { page_id, url, updated })
+}
@@ -0,0 +1,64 @@
+use qrcode::{Color, EcLevel, QrCode};
+
+use crate::media;
+
+// QR codes for fim:// deep links and note URLs, so printed boards and posters
+// can link back to the live board. Codes use medium error correction, which
+// survives a crease or a smudge without growing much.
+
+// Light modules around the code that scanners need to find it
+pub const QUIET_ZONE: usize = 4;
+const DEFAULT_MODULE_SIZE: u32 = 8;
+const MAX_MODULE_SIZE: u32 = 64;
+
+// The code's modules, row by row; true for dark ones
+pub struct QrModules {
+  pub width: usize,
+  pub dark: Vec<bool>,
+}
+
+impl QrModules {
+  pub fn is_dark(&self, x: usize, y: usize) -> bool {
+    self.dark[y * self.width + x]
+  }
+
+  // Width including the quiet zone on both sides
+  pub fn size(&self) -> usize {
+    self.width + QUIET_ZONE * 2
+  }
+}
+
+pub fn encode(data: &str) -> Result<QrModules, String> {
+  if data.is_empty() {
+    return Err("Nothing to encode in the QR code".to_string());
+  }
+  let code = QrCode::with_error_correction_level(data.as_bytes(), EcLevel::M)
+    .map_err(|e| format!("Failed to create QR code: {}", e))?;
+  Ok(QrModules {
+    width: code.width(),
+    dark: code.to_colors().into_iter().map(|c| c == Color::Dark).collect(),
+  })
+}
+
+// The code as a black-on-white PNG with `module_size` pixels per module
+pub fn to_png(data: &str, module_size: Option<u32>) -> Result<Vec<u8>, String> {
+  let modules = encode(data)?;
+  let scale = module_size.unwrap_or(DEFAULT_MODULE_SIZE).clamp(1, MAX_MODULE_SIZE) as usize;
+  let side = modules.size() * scale;
+  let mut rgba = vec![255u8; side * side * 4];
+  for y in 0..modules.width {
+    for x in 0..modules.width {
+      if !modules.is_dark(x, y) {
+        continue;
+      }
+      for py in (y + QUIET_ZONE) * scale..(y + QUIET_ZONE + 1) * scale {
+        let row = py * side;
+        for px in (x + QUIET_ZONE) * scale..(x + QUIET_ZONE + 1) * scale {
+          let offset = (row + px) * 4;
+          rgba[offset..offset + 3].fill(0);
+        }
+      }
+    }
+  }
+  media::encode_png_rgba(&rgba, side as u32, side as u32)
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{layers, model};
+
+// Scale and region planning for raster (PNG) exports. The frontend renders
+// whatever region and scale the plan says, so every export path agrees on
+// what "fit content" means and oversized requests are rejected up front.
+
+// Board units are CSS pixels, i.e. 96 per inch at 1x
+pub const BASE_DPI: f64 = 96.0;
+const MIN_SCALE: f64 = 0.1;
+const MAX_SCALE: f64 = 16.0;
+// Largest canvas edge and area the webview (and most decoders) will handle
+const MAX_EDGE: u32 = 32_767;
+const MAX_PIXELS: u64 = 16_384 * 16_384;
+// Space around the content in "fit content" mode
+pub const DEFAULT_PADDING: f64 = 50.0;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RasterOptions {
+  // Multiplier over 1 board unit = 1 px; mutually exclusive with `dpi`
+  pub scale: Option<f64>,
+  pub dpi: Option<f64>,
+  // Crop to this board-space rectangle instead of fitting the content
+  pub region: Option<model::Rect>,
+  // Padding around the content when fitting; ignored with `region`
+  pub padding: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RasterPlan {
+  pub scale: f64,
+  // Board-space rectangle to render
+  pub region: model::Rect,
+  // Output size in pixels
+  pub width: u32,
+  pub height: u32,
+}
+
+// Bounding box of notes, shapes and strokes, if the board has any
+pub fn content_bounds(doc: &model::BoardDocument) -> Option<model::Rect> {
+  let frames = doc.notes.iter().map(|n| &n.frame).chain(doc.shapes.iter().map(|s| &s.frame));
+  let bounds = frames.fold(None, |acc: Option<(f64, f64, f64, f64)>, f| {
+    let (x0, y0, x1, y1) = acc.unwrap_or((f.x, f.y, f.x + f.w, f.y + f.h));
+    Some((x0.min(f.x), y0.min(f.y), x1.max(f.x + f.w), y1.max(f.y + f.h)))
+  });
+  let ink = doc.strokes.iter().flatten().flat_map(|s| &s.points);
+  let bounds = ink.fold(bounds, |acc, p| {
+    let (x0, y0, x1, y1) = acc.unwrap_or((p.x, p.y, p.x, p.y));
+    Some((x0.min(p.x), y0.min(p.y), x1.max(p.x), y1.max(p.y)))
+  });
+  bounds.map(|(x0, y0, x1, y1)| model::Rect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })
+}
+
+fn resolve_scale(options: &RasterOptions) -> Result<f64, String> {
+  let scale = match (options.scale, options.dpi) {
+    (Some(_), Some(_)) => return Err("Specify either a scale or a DPI, not both".to_string()),
+    (Some(scale), None) => scale,
+    (None, Some(dpi)) => dpi / BASE_DPI,
+    (None, None) => 1.0,
+  };
+  if !scale.is_finite() || !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+    return Err(format!(
+      "Scale must be between {} and {} ({} to {} DPI)",
+      MIN_SCALE, MAX_SCALE, MIN_SCALE * BASE_DPI, MAX_SCALE * BASE_DPI
+    ));
+  }
+  Ok(scale)
+}
+
+fn resolve_region(doc: &model::BoardDocument, options: &RasterOptions) -> Result<model::Rect, String> {
+  if let Some(region) = &options.region {
+    let values = [region.x, region.y, region.w, region.h];
+    if values.iter().any(|v| !v.is_finite()) || region.w <= 0.0 || region.h <= 0.0 {
+      return Err("Export region must have a finite position and a positive size".to_string());
+    }
+    return Ok(region.clone());
+  }
+
+  let padding = options.padding.unwrap_or(DEFAULT_PADDING);
+  if !padding.is_finite() || padding < 0.0 {
+    return Err("Padding must be zero or more".to_string());
+  }
+  // Only what's visible on the board ends up in the image
+  let visible = layers::filter_layers(doc, &layers::visible_layers(doc));
+  let bounds = content_bounds(&visible).ok_or("The board has nothing to export")?;
+  Ok(model::Rect {
+    x: bounds.x - padding,
+    y: bounds.y - padding,
+    w: bounds.w.max(1.0) + padding * 2.0,
+    h: bounds.h.max(1.0) + padding * 2.0,
+  })
+}
+
+pub fn plan(doc: &model::BoardDocument, options: &RasterOptions) -> Result<RasterPlan, String> {
+  let scale = resolve_scale(options)?;
+  let region = resolve_region(doc, options)?;
+
+  let width = (region.w * scale).ceil();
+  let height = (region.h * scale).ceil();
+  if width > MAX_EDGE as f64 || height > MAX_EDGE as f64 || (width * height) as u64 > MAX_PIXELS {
+    return Err(format!(
+      "A {}×{} px image is too large to export; lower the scale or export a smaller region",
+      width, height
+    ));
+  }
+  Ok(RasterPlan { scale, region, width: width as u32, height: height as u32 })
+}
@@ -0,0 +1,62 @@
+use std::path::Path;
+
+// Mirrors our recent-files list into the OS so boards show up in the macOS
+// Dock menu / File ▸ Open Recent and in the Windows taskbar jump list.
+// Activating one of those entries reopens the app with the file, which then
+// arrives through the regular file-association handling (argv or
+// `RunEvent::Opened`).
+
+#[cfg_attr(not(any(target_os = "macos", windows)), allow(unused_variables))]
+pub fn note_recent_document(app: &tauri::AppHandle, path: &Path) {
+  #[cfg(target_os = "macos")]
+  {
+    let path = path.to_path_buf();
+    // AppKit may only be touched from the main thread
+    let result = app.run_on_main_thread(move || {
+      use objc2_app_kit::NSDocumentController;
+      use objc2_foundation::NSURL;
+
+      let Some(mtm) = objc2::MainThreadMarker::new() else {
+        return;
+      };
+      if let Some(url) = NSURL::from_file_path(&path) {
+        unsafe { NSDocumentController::sharedDocumentController(mtm).noteNewRecentDocumentURL(&url) };
+      }
+    });
+    if let Err(e) = result {
+      log::warn!("Failed to update macOS recent documents: {}", e);
+    }
+  }
+
+  #[cfg(windows)]
+  {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::{SHAddToRecentDocs, SHARD_PATHW};
+
+    // Windows files the document under our registered .fim association, which
+    // is what feeds the "Recent" category of the jump list
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    unsafe { SHAddToRecentDocs(SHARD_PATHW as u32, wide.as_ptr().cast()) };
+  }
+}
+
+#[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+pub fn clear_recent_documents(app: &tauri::AppHandle) {
+  #[cfg(target_os = "macos")]
+  {
+    let result = app.run_on_main_thread(|| {
+      use objc2_app_kit::NSDocumentController;
+
+      let Some(mtm) = objc2::MainThreadMarker::new() else {
+        return;
+      };
+      unsafe { NSDocumentController::sharedDocumentController(mtm).clearRecentDocuments(None) };
+    });
+    if let Err(e) = result {
+      log::warn!("Failed to clear macOS recent documents: {}", e);
+    }
+  }
+
+  // SHAddToRecentDocs(NULL) would wipe the user's recent items for every
+  // application, so on Windows the jump list is left to age out on its own.
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+use crate::{audit, preview, settings};
+
+// The recent-files list behind the start screen, kept in app data so it
+// survives restarts. Pinned entries stay at the top and are never dropped
+// to make room for newer files.
+//
+// Each document is listed once: paths are stored canonical (`~` expanded,
+// absolute, symlinks resolved), compared ignoring case where the file system
+// usually does, and an entry whose path no longer matches (a drive mounted
+// elsewhere, say) is still recognized by its content hash and file name.
+
+const RECENT_FILES_FILE: &str = "recent-files.json";
+// Unpinned entries kept
+pub const MAX_RECENT_FILES: usize = 10;
+// Larger files aren't hashed when touched
+const MAX_HASHED_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredRecentFile {
+  pub path: String,
+  #[serde(rename = "lastOpened")]
+  pub last_opened: chrono::DateTime<chrono::Utc>,
+  #[serde(default)]
+  pub pinned: bool,
+  // Of the file when it was last opened or saved
+  #[serde(rename = "contentHash", default, skip_serializing_if = "Option::is_none")]
+  pub content_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecentFiles {
+  // Most recently opened first
+  pub entries: Vec<StoredRecentFile>,
+}
+
+// What the frontend gets for each entry
+#[derive(Serialize, Debug, Clone)]
+pub struct RecentFileEntry {
+  pub path: String,
+  pub display_name: String,
+  pub last_opened: chrono::DateTime<chrono::Utc>,
+  // None when the file is gone (moved, deleted, unmounted drive)
+  pub size: Option<u64>,
+  pub pinned: bool,
+  // The file has a thumbnail (or an older embedded preview) for
+  // `get_document_thumbnail`
+  pub thumbnail_available: bool,
+}
+
+fn store_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(RECENT_FILES_FILE))
+}
+
+// `.` and `..` resolved without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+  let mut normalized = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {},
+      Component::ParentDir => {
+        normalized.pop();
+      },
+      other => normalized.push(other),
+    }
+  }
+  normalized
+}
+
+// Windows' canonical paths are verbatim (`\\?\C:\...`); everyone else writes
+// them without the prefix
+fn strip_verbatim(path: String) -> String {
+  match path.strip_prefix(r"\\?\") {
+    Some(rest) => match rest.strip_prefix(r"UNC\") {
+      Some(share) => format!(r"\\{}", share),
+      None => rest.to_string(),
+    },
+    None => path,
+  }
+}
+
+// Where `path` really is: `~` expanded, made absolute and, when the file
+// exists, with symlinks resolved
+pub fn canonical_path(path: &str) -> String {
+  let home_relative = match path {
+    "~" => Some(""),
+    _ => path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")),
+  };
+  let expanded = match (home_relative, dirs::home_dir()) {
+    (Some(rest), Some(home)) => home.join(rest),
+    _ => PathBuf::from(path),
+  };
+  let absolute = if expanded.is_absolute() {
+    expanded
+  } else {
+    std::env::current_dir().map(|dir| dir.join(&expanded)).unwrap_or(expanded)
+  };
+  let resolved = std::fs::canonicalize(&absolute).unwrap_or_else(|_| normalize(&absolute));
+  strip_verbatim(resolved.to_string_lossy().to_string())
+}
+
+// macOS and Windows file systems ignore case by default
+fn same_name(a: &str, b: &str) -> bool {
+  if cfg!(any(target_os = "macos", windows)) {
+    a.to_lowercase() == b.to_lowercase()
+  } else {
+    a == b
+  }
+}
+
+fn content_hash(path: &str) -> Option<String> {
+  let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file() && m.len() <= MAX_HASHED_SIZE)?;
+  let data = std::fs::read(path).ok()?;
+  (data.len() as u64 == metadata.len()).then(|| audit::hash_bytes(&data))
+}
+
+fn file_name(path: &str) -> &str {
+  path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+impl StoredRecentFile {
+  // Whether this entry is the document at canonical `path` with `hash`
+  fn is(&self, path: &str, hash: Option<&str>) -> bool {
+    if same_name(&self.path, path) {
+      return true;
+    }
+    let same_content = matches!((self.content_hash.as_deref(), hash), (Some(a), Some(b)) if a == b);
+    same_content && same_name(file_name(&self.path), file_name(path))
+  }
+}
+
+impl RecentFiles {
+  // Load the list from disk; a missing or corrupt file is an empty list
+  pub fn load() -> RecentFiles {
+    let Some(path) = store_path() else {
+      return RecentFiles::default();
+    };
+    match std::fs::read_to_string(&path) {
+      Ok(content) => {
+        let mut recent: RecentFiles = serde_json::from_str(&content).unwrap_or_else(|e| {
+          log::warn!("Ignoring invalid recent files list '{}': {}", path.display(), e);
+          RecentFiles::default()
+        });
+        recent.merge_duplicates();
+        recent
+      },
+      Err(_) => RecentFiles::default(),
+    }
+  }
+
+  // Lists written before paths were canonical can name a document more than
+  // once; keep the most recent entry, pinned if any of them was
+  fn merge_duplicates(&mut self) {
+    let mut merged: Vec<StoredRecentFile> = Vec::new();
+    for mut entry in std::mem::take(&mut self.entries) {
+      entry.path = canonical_path(&entry.path);
+      match merged.iter_mut().find(|e| e.is(&entry.path, entry.content_hash.as_deref())) {
+        Some(existing) => {
+          existing.pinned |= entry.pinned;
+          if entry.last_opened > existing.last_opened {
+            existing.last_opened = entry.last_opened;
+          }
+        },
+        None => merged.push(entry),
+      }
+    }
+    self.entries = merged;
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    let path = store_path().ok_or("Could not determine the app data directory")?;
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(self)
+      .map_err(|e| format!("Failed to serialize recent files: {}", e))?;
+    std::fs::write(&path, json)
+      .map_err(|e| format!("Failed to write recent files list '{}': {}", path.display(), e))
+  }
+
+  // Move `path` to the front, replacing any other entry for the same
+  // document and keeping whether it was pinned
+  pub fn touch(&mut self, path: &str) {
+    let path = canonical_path(path);
+    let hash = content_hash(&path);
+    let pinned = self.entries.iter().any(|e| e.is(&path, hash.as_deref()) && e.pinned);
+    self.entries.retain(|e| !e.is(&path, hash.as_deref()));
+    self.entries.insert(0, StoredRecentFile { path, last_opened: chrono::Utc::now(), pinned, content_hash: hash });
+
+    let mut unpinned = 0;
+    self.entries.retain(|e| {
+      if !e.pinned {
+        unpinned += 1;
+      }
+      e.pinned || unpinned <= MAX_RECENT_FILES
+    });
+  }
+
+  pub fn set_pinned(&mut self, path: &str, pinned: bool) -> Result<(), String> {
+    let canonical = canonical_path(path);
+    let entry = self.entries.iter_mut().find(|e| e.is(&canonical, None))
+      .ok_or_else(|| format!("'{}' is not in the recent files list", path))?;
+    entry.pinned = pinned;
+    Ok(())
+  }
+
+  pub fn remove(&mut self, path: &str) {
+    let canonical = canonical_path(path);
+    self.entries.retain(|e| !e.is(&canonical, None));
+  }
+
+  // Forget everything except pinned entries
+  pub fn clear(&mut self) {
+    self.entries.retain(|e| e.pinned);
+  }
+
+  // Pinned entries first, each group most recent first
+  pub fn entries(&self) -> Vec<RecentFileEntry> {
+    let mut entries: Vec<RecentFileEntry> = self.entries.iter().map(describe).collect();
+    entries.sort_by(|a, b| b.pinned.cmp(&a.pinned).then(b.last_opened.cmp(&a.last_opened)));
+    entries
+  }
+}
+
+fn describe(stored: &StoredRecentFile) -> RecentFileEntry {
+  let path = Path::new(&stored.path);
+  let size = std::fs::metadata(path).ok().filter(|m| m.is_file()).map(|m| m.len());
+  let is_board = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("fim"));
+  RecentFileEntry {
+    path: stored.path.clone(),
+    display_name: path.file_stem()
+      .map(|stem| stem.to_string_lossy().to_string())
+      .unwrap_or_else(|| stored.path.clone()),
+    last_opened: stored.last_opened,
+    size,
+    pinned: stored.pinned,
+    thumbnail_available: size.is_some() && is_board && preview::has_preview(path),
+  }
+}
@@ -0,0 +1,397 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::{audit, merge, model, security, settings};
+
+// Where autosave keeps recovery files and how startup finds them. Autosave
+// writes into the first location of the `recoveryLocations` setting it can
+// create, by default the app's own recovery folder, so synced folders don't
+// fill up with recovery files. Recovery file names carry a hash of the
+// board's path, so boards with the same name in different folders don't
+// overwrite each other's recovery.
+//
+// Every recovery file written is recorded in an index in app data, which is
+// what `check_recovery_files` reads instead of searching folders. The first
+// time the index is used, the places earlier versions wrote recovery files to
+// (next to boards in Documents or the home folder, the working folder, temp)
+// are searched once and what is found is added to it.
+//
+// Before recovering, the recovery file can be compared with the board it
+// belongs to, and a recovery file that isn't wanted is discarded along with
+// its metadata: both are first renamed out of the way, so either both go or
+// neither does.
+
+pub const RECOVERY_EXTENSION: &str = "fim.recovery";
+const RECOVERY_DIR: &str = "recovery";
+const INDEX_FILE: &str = "recovery-index.json";
+// Changed notes shown in a recovery preview, and how much of their text
+const PREVIEW_SAMPLE: usize = 5;
+const PREVIEW_TEXT_CHARS: usize = 120;
+
+// Held while the index is read and rewritten; autosaves of different boards
+// run on their own threads
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AutosaveInfo {
+  pub original_path: String,
+  pub recovery_path: String,
+  pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ChangedNote {
+  pub id: model::ID,
+  // "added", "changed" or "removed"
+  pub change: String,
+  // Start of the note's text in the board and in the recovery file
+  pub before: Option<String>,
+  pub after: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RecoveryPreview {
+  pub original_path: String,
+  pub recovery_path: String,
+  pub timestamp: chrono::DateTime<chrono::Utc>,
+  // Whether the board is still there; when it isn't, every note is added
+  pub original_exists: bool,
+  pub notes_added: usize,
+  pub notes_changed: usize,
+  pub notes_removed: usize,
+  // Connections, stacks and shapes added, changed or removed
+  pub other_changes: usize,
+  pub identical: bool,
+  pub sample: Vec<ChangedNote>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct RecoveryIndex {
+  // Whether the folders earlier versions used have been searched
+  #[serde(rename = "legacyScanned", default)]
+  legacy_scanned: bool,
+  // Hash of the board's path to its recovery file
+  #[serde(default)]
+  entries: BTreeMap<String, AutosaveInfo>,
+}
+
+// Per-platform defaults: the app's own recovery folder, with the temp folder
+// for when it can't be created
+pub fn default_locations() -> Vec<String> {
+  let app_dir = if cfg!(target_os = "macos") {
+    // Where macOS apps keep autosaved documents
+    dirs::home_dir().map(|home| home.join("Library").join("Autosave Information").join(settings::APP_IDENTIFIER))
+  } else {
+    dirs::data_local_dir().map(|dir| dir.join(settings::APP_IDENTIFIER).join(RECOVERY_DIR))
+  };
+  [app_dir, Some(std::env::temp_dir())]
+    .into_iter()
+    .flatten()
+    .map(|dir| dir.to_string_lossy().to_string())
+    .collect()
+}
+
+// Configured locations, or the defaults when none are left
+pub fn locations(configured: &[String]) -> Vec<PathBuf> {
+  let configured: Vec<PathBuf> = configured.iter()
+    .map(|l| l.trim())
+    .filter(|l| !l.is_empty())
+    .map(PathBuf::from)
+    .collect();
+  if configured.is_empty() {
+    default_locations().into_iter().map(PathBuf::from).collect()
+  } else {
+    configured
+  }
+}
+
+fn file_name(original: &Path) -> String {
+  let stem = original.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+  let hash = audit::hash_bytes(original.to_string_lossy().as_bytes());
+  format!("{}.{}.{}", stem, &hash[..8], RECOVERY_EXTENSION)
+}
+
+// Recovery file for the board at `original`, in the first location that
+// exists or can be created
+pub fn recovery_path(configured: &[String], original: &Path) -> Result<PathBuf, String> {
+  let name = file_name(original);
+  let mut errors = Vec::new();
+  for dir in locations(configured) {
+    match std::fs::create_dir_all(&dir) {
+      Ok(()) => return Ok(dir.join(&name)),
+      Err(e) => errors.push(format!("'{}': {}", dir.display(), e)),
+    }
+  }
+  Err(format!("No usable recovery location ({})", errors.join("; ")))
+}
+
+// Where the recovery file for `original` is, if there is one
+pub fn find_recovery(configured: &[String], original: &Path) -> Option<PathBuf> {
+  if let Some(info) = indexed(original) {
+    return Some(PathBuf::from(info.recovery_path));
+  }
+  let name = file_name(original);
+  locations(configured).into_iter()
+    .map(|dir| dir.join(&name))
+    // Earlier versions put it next to the board
+    .chain([original.with_extension(RECOVERY_EXTENSION)])
+    .find(|path| path.exists())
+}
+
+// The board's `AutosaveInfo`, kept next to its recovery file
+pub fn metadata_path(recovery: &Path) -> PathBuf {
+  let mut path = recovery.as_os_str().to_owned();
+  path.push(".meta");
+  PathBuf::from(path)
+}
+
+// A recovery file with its metadata, including the name earlier versions gave it
+pub fn files(recovery: &Path) -> Vec<PathBuf> {
+  vec![recovery.to_path_buf(), metadata_path(recovery), recovery.with_extension("fim.recovery.meta")]
+}
+
+// The autosave info stored with a recovery file, under its current name or
+// the one earlier versions used
+pub fn read_metadata(recovery: &Path) -> Option<AutosaveInfo> {
+  files(recovery).into_iter().skip(1).find_map(|path| {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+  })
+}
+
+// Autosave info for a recovery file without metadata, which sits next to
+// the board it belongs to
+fn guessed_info(recovery: &Path) -> Option<AutosaveInfo> {
+  let modified = std::fs::metadata(recovery).and_then(|m| m.modified()).ok()?;
+  let name = recovery.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+  Some(AutosaveInfo {
+    recovery_path: recovery.to_string_lossy().to_string(),
+    original_path: recovery.with_file_name(name.replace(".recovery", "")).to_string_lossy().to_string(),
+    timestamp: chrono::DateTime::<chrono::Utc>::from(modified),
+  })
+}
+
+// Autosave info of the board at `original`, if it has a recovery file
+pub fn autosave_info(configured: &[String], original: &Path) -> Option<AutosaveInfo> {
+  indexed(original).or_else(|| find_recovery(configured, original).and_then(|path| read_metadata(&path)))
+}
+
+fn index_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(INDEX_FILE))
+}
+
+fn index_key(original: &Path) -> String {
+  audit::hash_bytes(original.to_string_lossy().as_bytes())
+}
+
+// A missing or corrupt index is an empty one, rebuilt by the next listing
+fn read_index() -> RecoveryIndex {
+  let Some(path) = index_path() else {
+    return RecoveryIndex::default();
+  };
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+      log::warn!("Ignoring invalid recovery index '{}': {}", path.display(), e);
+      RecoveryIndex::default()
+    }),
+    Err(_) => RecoveryIndex::default(),
+  }
+}
+
+// Written next to the index and renamed over it, so a crash can't leave half an index
+fn write_index(index: &RecoveryIndex) -> Result<(), String> {
+  let path = index_path().ok_or("Could not determine the app data directory")?;
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+  }
+  let json = serde_json::to_string_pretty(index)
+    .map_err(|e| format!("Failed to serialize recovery index: {}", e))?;
+  let temp = path.with_extension("json.saving");
+  std::fs::write(&temp, json)
+    .and_then(|_| std::fs::rename(&temp, &path))
+    .map_err(|e| format!("Failed to write recovery index '{}': {}", path.display(), e))
+}
+
+fn update_index(update: impl FnOnce(&mut RecoveryIndex)) -> Result<(), String> {
+  let _guard = INDEX_LOCK.lock().map_err(|e| format!("Failed to access recovery index: {}", e))?;
+  let mut index = read_index();
+  update(&mut index);
+  write_index(&index)
+}
+
+fn indexed(original: &Path) -> Option<AutosaveInfo> {
+  let _guard = INDEX_LOCK.lock().ok()?;
+  read_index().entries.remove(&index_key(original))
+    .filter(|info| Path::new(&info.recovery_path).is_file())
+}
+
+// Note a recovery file just written
+pub fn record(info: &AutosaveInfo) -> Result<(), String> {
+  let key = index_key(Path::new(&info.original_path));
+  update_index(|index| {
+    index.entries.insert(key, info.clone());
+  })
+}
+
+// The board's recovery file was deleted or moved away
+pub fn forget(original: &Path) {
+  let key = index_key(original);
+  if let Err(e) = update_index(|index| {
+    index.entries.remove(&key);
+  }) {
+    log::warn!("{}", e);
+  }
+}
+
+// Folders earlier versions wrote recovery files to
+fn legacy_locations(configured: &[String]) -> Vec<PathBuf> {
+  let mut folders = locations(configured);
+  folders.extend([dirs::document_dir(), dirs::home_dir(), std::env::current_dir().ok(), Some(std::env::temp_dir())].into_iter().flatten());
+  folders.sort();
+  folders.dedup();
+  folders
+}
+
+// Recovery files in the folders earlier versions used, with their autosave
+// info; files without metadata sit next to the board they belong to
+fn scan_legacy(configured: &[String]) -> Vec<AutosaveInfo> {
+  let suffix = format!(".{}", RECOVERY_EXTENSION);
+  legacy_locations(configured).into_iter()
+    .filter_map(|dir| std::fs::read_dir(dir).ok())
+    .flat_map(|entries| entries.flatten().map(|e| e.path()))
+    .filter(|path| path.is_file() && path.file_name().is_some_and(|n| n.to_string_lossy().ends_with(&suffix)))
+    .filter_map(|path| {
+      match read_metadata(&path) {
+        Some(info) => Some(AutosaveInfo { recovery_path: path.to_string_lossy().to_string(), ..info }),
+        None => guessed_info(&path),
+      }
+    })
+    .collect()
+}
+
+// Recovery files from the index, newest first. Entries whose file is gone
+// are dropped from it.
+pub fn list(configured: &[String]) -> Result<Vec<AutosaveInfo>, String> {
+  let _guard = INDEX_LOCK.lock().map_err(|e| format!("Failed to access recovery index: {}", e))?;
+  let mut index = read_index();
+  let mut changed = false;
+  if !index.legacy_scanned {
+    for info in scan_legacy(configured) {
+      index.entries.entry(index_key(Path::new(&info.original_path))).or_insert(info);
+    }
+    index.legacy_scanned = true;
+    changed = true;
+  }
+  let count = index.entries.len();
+  index.entries.retain(|_, info| Path::new(&info.recovery_path).is_file());
+  if changed || index.entries.len() != count {
+    if let Err(e) = write_index(&index) {
+      log::warn!("{}", e);
+    }
+  }
+
+  let mut found: Vec<AutosaveInfo> = index.entries.into_values().collect();
+  found.sort_by_key(|info| std::cmp::Reverse(info.timestamp));
+  Ok(found)
+}
+
+// Autosave info of the recovery file at `recovery`. Only recovery files are
+// accepted, so a path from the frontend can't be used to read or delete
+// anything else.
+pub fn info(configured: &[String], recovery: &Path) -> Result<AutosaveInfo, String> {
+  let suffix = format!(".{}", RECOVERY_EXTENSION);
+  if !recovery.file_name().is_some_and(|n| n.to_string_lossy().ends_with(&suffix)) {
+    return Err(format!("'{}' is not a recovery file", recovery.display()));
+  }
+  if !recovery.is_file() {
+    return Err("Recovery file not found".to_string());
+  }
+  let listed = list(configured)?.into_iter().find(|info| Path::new(&info.recovery_path) == recovery);
+  listed
+    .or_else(|| read_metadata(recovery).map(|info| AutosaveInfo { recovery_path: recovery.to_string_lossy().to_string(), ..info }))
+    .or_else(|| guessed_info(recovery))
+    .ok_or_else(|| format!("Failed to read recovery file '{}'", recovery.display()))
+}
+
+fn excerpt(note: Option<&model::Note>) -> Option<String> {
+  // Locked confidential notes have no text to show
+  let text = note?.text.trim();
+  if text.is_empty() {
+    return None;
+  }
+  let mut excerpt: String = text.chars().take(PREVIEW_TEXT_CHARS).collect();
+  if excerpt.len() < text.len() {
+    excerpt.push('…');
+  }
+  Some(excerpt)
+}
+
+// How the board in the recovery file differs from the board on disk, or
+// from an empty one when that is gone
+pub fn preview(info: &AutosaveInfo, original: Option<&model::BoardDocument>, recovered: &model::BoardDocument) -> Result<RecoveryPreview, String> {
+  let empty = model::BoardDocument::empty();
+  let before = original.unwrap_or(&empty);
+  let diff = merge::diff(before, recovered)?;
+  let note = |doc: &model::BoardDocument, id: &str| excerpt(doc.notes.iter().find(|n| n.id == id));
+
+  // Changed notes first, as those are the edits most likely to be lost
+  let sample = diff.notes.modified.iter().map(|m| ("changed", &m.id))
+    .chain(diff.notes.added.iter().map(|id| ("added", id)))
+    .chain(diff.notes.removed.iter().map(|id| ("removed", id)))
+    .take(PREVIEW_SAMPLE)
+    .map(|(change, id)| ChangedNote {
+      id: id.clone(),
+      change: change.to_string(),
+      before: note(before, id),
+      after: note(recovered, id),
+    })
+    .collect();
+  let other = [&diff.connections, &diff.stacks, &diff.shapes];
+  Ok(RecoveryPreview {
+    original_path: info.original_path.clone(),
+    recovery_path: info.recovery_path.clone(),
+    timestamp: info.timestamp,
+    original_exists: original.is_some(),
+    notes_added: diff.notes.added.len(),
+    notes_changed: diff.notes.modified.len(),
+    notes_removed: diff.notes.removed.len(),
+    other_changes: other.iter().map(|c| c.added.len() + c.modified.len() + c.removed.len()).sum(),
+    identical: diff.identical,
+    sample,
+  })
+}
+
+// Delete a recovery file and its metadata. Both are renamed out of the way
+// first, and put back if that fails, so a recovery file is never left
+// without its metadata or the other way round.
+pub fn discard(info: &AutosaveInfo, secure: bool) -> Result<(), String> {
+  let staged_path = |file: &Path| {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".discarding");
+    PathBuf::from(path)
+  };
+  let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+  for file in files(Path::new(&info.recovery_path)).into_iter().filter(|file| file.exists()) {
+    let to = staged_path(&file);
+    if let Err(e) = std::fs::rename(&file, &to) {
+      for (from, to) in staged.iter().rev() {
+        if let Err(e) = std::fs::rename(to, from) {
+          log::warn!("Failed to restore '{}': {}", from.display(), e);
+        }
+      }
+      return Err(format!("Failed to discard '{}': {}", file.display(), e));
+    }
+    staged.push((file, to));
+  }
+  forget(Path::new(&info.original_path));
+  // Out of the way already; what can't be deleted is only logged
+  for (_, to) in staged {
+    if let Err(e) = security::remove_file(&to, secure) {
+      log::warn!("{}", e);
+    }
+  }
+  Ok(())
+}
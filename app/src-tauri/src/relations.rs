@@ -0,0 +1,63 @@
+use crate::model;
+
+// Typed connections: `Connection::relation_type` points into the document's
+// registry of relation types ("supports", "contradicts", "depends on", ...),
+// which supplies a default style and whether the relation has a direction.
+
+pub fn relation_types(doc: &model::BoardDocument) -> &[model::RelationType] {
+  doc.relation_types.as_deref().unwrap_or(&[])
+}
+
+pub fn relation_type<'a>(doc: &'a model::BoardDocument, connection: &model::Connection) -> Option<&'a model::RelationType> {
+  let id = connection.relation_type.as_deref()?;
+  relation_types(doc).iter().find(|r| r.id == id)
+}
+
+// Untyped connections keep their historic source → destination reading
+pub fn is_directed(doc: &model::BoardDocument, connection: &model::Connection) -> bool {
+  relation_type(doc, connection).and_then(|r| r.directed).unwrap_or(true)
+}
+
+// The connection's own style, with unset fields taken from its relation type
+pub fn effective_style(doc: &model::BoardDocument, connection: &model::Connection) -> Option<model::ConnectionStyle> {
+  let default = relation_type(doc, connection).and_then(|r| r.style.as_ref());
+  match (&connection.style, default) {
+    (Some(own), Some(default)) => Some(model::ConnectionStyle {
+      kind: own.kind.clone().or_else(|| default.kind.clone()),
+      arrows: own.arrows.clone().or_else(|| default.arrows.clone()),
+      color: own.color.clone().or_else(|| default.color.clone()),
+      width: own.width.or(default.width),
+      curve_type: own.curve_type.clone().or_else(|| default.curve_type.clone()),
+    }),
+    (own, default) => own.clone().or_else(|| default.cloned()),
+  }
+}
+
+pub struct ConnectionGroup<'a> {
+  // None for untyped connections and ones whose type isn't registered
+  pub relation: Option<&'a model::RelationType>,
+  pub connections: Vec<&'a model::Connection>,
+}
+
+// Connections grouped by relation type, in registry order, with untyped
+// connections last. Empty groups are left out.
+pub fn group_connections(doc: &model::BoardDocument) -> Vec<ConnectionGroup<'_>> {
+  let mut groups: Vec<ConnectionGroup> = relation_types(doc).iter()
+    .map(|relation| ConnectionGroup {
+      relation: Some(relation),
+      connections: doc.connections.iter()
+        .filter(|c| c.relation_type.as_deref() == Some(relation.id.as_str()))
+        .collect(),
+    })
+    .collect();
+  groups.push(ConnectionGroup {
+    relation: None,
+    connections: doc.connections.iter().filter(|c| relation_type(doc, c).is_none()).collect(),
+  });
+  groups.retain(|g| !g.connections.is_empty());
+  groups
+}
+
+pub fn has_typed_connections(doc: &model::BoardDocument) -> bool {
+  doc.connections.iter().any(|c| relation_type(doc, c).is_some())
+}
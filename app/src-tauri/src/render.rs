@@ -0,0 +1,448 @@
+use base64::Engine;
+use resvg::{tiny_skia, usvg};
+
+use crate::{checklist, curves, fonts, icons, layers, lazy_media, markdown, media, model, preview, raster, relations, rich_text, shapes, tables};
+
+// Native rendering of a board as SVG, rasterized to PNG with resvg. Unlike the
+// webview's canvas this has no size limit beyond the raster plan's and runs
+// without a window, so large and headless exports go through here. The
+// drawing follows the board: background and texture, background shapes,
+// connections with arrows and labels, ink, then notes with their fill,
+// border, image and wrapped rich text, each kind in z-order. Callers pick
+// the layers to draw. Text is wrapped by an estimated glyph width, so line
+// breaks can differ slightly from the webview's.
+
+pub(crate) const DEFAULT_BACKGROUND: &str = "#ffffff";
+pub(crate) const DEFAULT_NOTE_FILL: &str = "#fff8c4";
+pub(crate) const DEFAULT_NOTE_BORDER: &str = "#c8be8c";
+pub(crate) const DEFAULT_TEXT_COLOR: &str = "#222222";
+pub(crate) const DEFAULT_FONT: &str = "sans-serif";
+pub(crate) const DEFAULT_FONT_SIZE: f64 = 14.0;
+const SHAPE_FILL: &str = "#e8ecf2";
+const SHAPE_STROKE: &str = "#aab4c3";
+const SHAPE_LABEL_COLOR: &str = "#5a6270";
+const CONNECTION_COLOR: &str = "#969696";
+const CONNECTION_WIDTH: f64 = 1.5;
+const STROKE_COLOR: &str = "#3c3c3c";
+pub(crate) const NOTE_PADDING: f64 = 8.0;
+pub(crate) const LINE_HEIGHT: f64 = 1.3;
+// Average advance of a glyph, in em, for wrapping
+const CHAR_WIDTH: f64 = 0.55;
+pub(crate) const FADED_OPACITY: f64 = 0.5;
+
+fn xml_escape(text: &str) -> String {
+  text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// A style color as an SVG color and opacity; `#rrggbbaa` is split in two
+pub(crate) fn color(value: Option<&str>, fallback: &str) -> (String, f64) {
+  match value.and_then(preview::parse_hex_color) {
+    Some([r, g, b, a]) => (format!("#{:02x}{:02x}{:02x}", r, g, b), a as f64 / 255.0),
+    None => (fallback.to_string(), 1.0),
+  }
+}
+
+// ` fill="…"` (or stroke), with the opacity when it isn't opaque
+fn paint(attribute: &str, value: Option<&str>, fallback: &str) -> String {
+  let (color, opacity) = color(value, fallback);
+  if opacity < 1.0 {
+    format!(" {}=\"{}\" {}-opacity=\"{:.3}\"", attribute, color, attribute, opacity)
+  } else {
+    format!(" {}=\"{}\"", attribute, color)
+  }
+}
+
+fn points_attribute(points: &[model::Point]) -> String {
+  points.iter().map(|p| format!("{:.2},{:.2}", p.x, p.y)).collect::<Vec<_>>().join(" ")
+}
+
+fn center(frame: &model::Rect) -> model::Point {
+  model::Point { x: frame.x + frame.w / 2.0, y: frame.y + frame.h / 2.0 }
+}
+
+// Where the line from the center of `frame` towards `toward` leaves the frame
+fn edge_point(frame: &model::Rect, toward: &model::Point) -> model::Point {
+  let c = center(frame);
+  let (dx, dy) = (toward.x - c.x, toward.y - c.y);
+  let tx = if dx != 0.0 { frame.w.abs() / 2.0 / dx.abs() } else { f64::INFINITY };
+  let ty = if dy != 0.0 { frame.h.abs() / 2.0 / dy.abs() } else { f64::INFINITY };
+  let t = tx.min(ty).min(1.0);
+  if !t.is_finite() {
+    return c;
+  }
+  model::Point { x: c.x + dx * t, y: c.y + dy * t }
+}
+
+pub(crate) fn image_data_uri(doc: &model::BoardDocument, id: &str) -> Option<String> {
+  let image = doc.images.iter().flatten().find(|i| i.id == id)?;
+  if lazy_media::is_deferred(image) {
+    let data = lazy_media::read(id).ok()?;
+    return Some(format!("data:{};base64,{}", image.mime, base64::engine::general_purpose::STANDARD.encode(data)));
+  }
+  let data = image.data_base64.as_deref().filter(|d| !d.is_empty())?;
+  Some(format!("data:{};base64,{}", image.mime, data))
+}
+
+// Opening tag of a standalone SVG document showing `region`
+fn svg_element(region: &model::Rect) -> String {
+  format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"{:.2} {:.2} {w:.2} {h:.2}\">\n",
+    region.x, region.y, w = region.w, h = region.h
+  )
+}
+
+// The board as a standalone SVG document showing `region` in board units
+pub fn to_svg(doc: &model::BoardDocument, region: &model::Rect) -> String {
+  let mut svg = svg_element(region) + &backdrop(doc, region);
+  for (index, note) in layers::paint_order(&doc.notes) {
+    svg += &note_svg(doc, note, index);
+  }
+  svg + "</svg>\n"
+}
+
+// Everything below the notes, as a standalone SVG document; for exports that
+// lay the notes out themselves (static_html.rs)
+pub(crate) fn backdrop_svg(doc: &model::BoardDocument, region: &model::Rect) -> String {
+  svg_element(region) + &backdrop(doc, region) + "</svg>\n"
+}
+
+// Background, shapes, connections and ink
+fn backdrop(doc: &model::BoardDocument, region: &model::Rect) -> String {
+  let mut svg = background(doc, region);
+  for (_, shape) in layers::paint_order(&doc.shapes) {
+    svg += &shape_svg(doc, shape);
+  }
+  for connection in &doc.connections {
+    svg += &connection_svg(doc, connection);
+  }
+  for stroke in doc.strokes.iter().flatten().filter(|s| s.points.len() > 1) {
+    let points: Vec<model::Point> = stroke.points.iter().map(|p| model::Point { x: p.x, y: p.y }).collect();
+    let pressure = stroke.points.iter().map(|p| p.pressure).sum::<f64>() / stroke.points.len() as f64;
+    svg += &format!(
+      "<polyline points=\"{}\" fill=\"none\"{} stroke-width=\"{:.2}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+      points_attribute(&points), paint("stroke", stroke.color.as_deref(), STROKE_COLOR), (stroke.width * pressure.clamp(0.2, 1.0)).max(0.5)
+    );
+  }
+  svg
+}
+
+fn background(doc: &model::BoardDocument, region: &model::Rect) -> String {
+  let background = doc.document_style.as_ref().and_then(|s| s.background.as_ref());
+  let rect = format!("x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"", region.x, region.y, region.w, region.h);
+  let mut svg = format!("<rect {}{}/>\n", rect, paint("fill", background.and_then(|b| b.color.as_deref()), DEFAULT_BACKGROUND));
+
+  // Tiled from the board origin, like the canvas does
+  let texture = background.and_then(|b| b.texture_id.as_deref()).and_then(|id| {
+    let image = doc.images.iter().flatten().find(|i| i.id == id)?;
+    Some((image, image_data_uri(doc, id)?))
+  });
+  if let (Some(background), Some((image, uri))) = (background, texture) {
+    let scale = background.texture_scale.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(1.0);
+    let (w, h) = (image.width * scale, image.height * scale);
+    if w >= 1.0 && h >= 1.0 {
+      svg += &format!(
+        "<defs><pattern id=\"texture\" patternUnits=\"userSpaceOnUse\" width=\"{w:.2}\" height=\"{h:.2}\"><image width=\"{w:.2}\" height=\"{h:.2}\" preserveAspectRatio=\"none\" href=\"{}\"/></pattern></defs>\n",
+        uri, w = w, h = h
+      );
+      svg += &format!(
+        "<rect {} fill=\"url(#texture)\" opacity=\"{:.3}\"/>\n",
+        rect, background.texture_opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+      );
+    }
+  }
+  svg
+}
+
+fn shape_svg(doc: &model::BoardDocument, shape: &model::BackgroundShape) -> String {
+  let style_id = shape.style_id.as_ref()
+    .or_else(|| doc.document_style.as_ref().and_then(|s| s.default_shape_style_id.as_ref()));
+  let style = style_id.and_then(|id| doc.note_styles.iter().find(|s| &s.id == id));
+  let border = style.and_then(|s| s.border.as_ref());
+  let stroke = paint("stroke", border.and_then(|b| b.color.as_deref()), SHAPE_STROKE);
+  let stroke_width = border.and_then(|b| b.width).unwrap_or(1.0);
+  let fill = paint("fill", style.and_then(|s| s.fill.as_deref()), SHAPE_FILL);
+  let f = &shape.frame;
+
+  let mut svg = match shapes::kind(shape) {
+    "line" => format!(
+      "<polyline points=\"{}\" fill=\"none\"{} stroke-width=\"{:.2}\" stroke-linecap=\"round\"/>\n",
+      points_attribute(&shapes::absolute_points(shape)), stroke, stroke_width.max(1.0)
+    ),
+    "ellipse" => format!(
+      "<ellipse cx=\"{:.2}\" cy=\"{:.2}\" rx=\"{:.2}\" ry=\"{:.2}\"{}{} stroke-width=\"{:.2}\"/>\n",
+      f.x + f.w / 2.0, f.y + f.h / 2.0, f.w.abs() / 2.0, f.h.abs() / 2.0, fill, stroke, stroke_width
+    ),
+    "polygon" => format!(
+      "<polygon points=\"{}\"{}{} stroke-width=\"{:.2}\"/>\n",
+      points_attribute(&shapes::absolute_points(shape)), fill, stroke, stroke_width
+    ),
+    _ => format!(
+      "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"{:.2}\"{}{} stroke-width=\"{:.2}\"/>\n",
+      f.x, f.y, f.w, f.h, shape.radius.unwrap_or(0.0).max(0.0), fill, stroke, stroke_width
+    ),
+  };
+  if let Some(label) = shape.label.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+    svg += &format!(
+      "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"{}\" font-size=\"13\" font-weight=\"bold\" fill=\"{}\">{}</text>\n",
+      f.x + NOTE_PADDING, f.y + NOTE_PADDING + 13.0, DEFAULT_FONT, SHAPE_LABEL_COLOR, xml_escape(label)
+    );
+  }
+  svg
+}
+
+// A filled triangle with its tip at `tip`, pointing away from `from`
+fn arrow_head(tip: &model::Point, from: &model::Point, size: f64) -> String {
+  let angle = (tip.y - from.y).atan2(tip.x - from.x);
+  let corner = |offset: f64| {
+    let a = angle + std::f64::consts::PI - offset;
+    format!("{:.2},{:.2}", tip.x + size * a.cos(), tip.y + size * a.sin())
+  };
+  format!("{:.2},{:.2} {} {}", tip.x, tip.y, corner(0.45), corner(-0.45))
+}
+
+// The point halfway along a polyline
+fn halfway(path: &[model::Point]) -> model::Point {
+  let length = |s: &[model::Point]| (s[1].x - s[0].x).hypot(s[1].y - s[0].y);
+  let mut remaining = path.windows(2).map(length).sum::<f64>() / 2.0;
+  for segment in path.windows(2) {
+    let l = length(segment);
+    if l > 0.0 && remaining <= l {
+      let t = remaining / l;
+      return model::Point { x: segment[0].x + (segment[1].x - segment[0].x) * t, y: segment[0].y + (segment[1].y - segment[0].y) * t };
+    }
+    remaining -= l;
+  }
+  path[0].clone()
+}
+
+fn connection_svg(doc: &model::BoardDocument, connection: &model::Connection) -> String {
+  let src = doc.notes.iter().find(|n| n.id == connection.src_note_id);
+  let dst = doc.notes.iter().find(|n| n.id == connection.dst_note_id);
+  let (Some(src), Some(dst)) = (src, dst) else {
+    return String::new();
+  };
+  let style = relations::effective_style(doc, connection);
+  let mut path = curves::flatten(center(&src.frame), center(&dst.frame), connection, style.as_ref());
+  if path.len() < 2 {
+    return String::new();
+  }
+  // From border to border rather than center to center
+  let last = path.len() - 1;
+  path[0] = edge_point(&src.frame, &path[1]);
+  path[last] = edge_point(&dst.frame, &path[last - 1]);
+
+  let width = style.as_ref().and_then(|s| s.width).filter(|w| w.is_finite() && *w > 0.0).unwrap_or(CONNECTION_WIDTH);
+  let stroke_color = style.as_ref().and_then(|s| s.color.as_deref());
+  // New connections are dotted unless set otherwise
+  let dash = match style.as_ref().and_then(|s| s.kind.as_deref()) {
+    Some("solid") => String::new(),
+    Some("dashed") => format!(" stroke-dasharray=\"{:.2} {:.2}\"", width * 5.0, width * 3.0),
+    _ => format!(" stroke-dasharray=\"0 {:.2}\"", width * 2.5),
+  };
+  let mut svg = format!(
+    "<polyline points=\"{}\" fill=\"none\"{} stroke-width=\"{:.2}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"{}/>\n",
+    points_attribute(&path), paint("stroke", stroke_color, CONNECTION_COLOR), width, dash
+  );
+
+  let arrows = style.as_ref().and_then(|s| s.arrows.as_deref()).unwrap_or("none");
+  let size = 6.0 + width * 2.0;
+  let fill = paint("fill", stroke_color, CONNECTION_COLOR);
+  if matches!(arrows, "dst" | "both") {
+    svg += &format!("<polygon points=\"{}\"{}/>\n", arrow_head(&path[last], &path[last - 1], size), fill);
+  }
+  if matches!(arrows, "src" | "both") {
+    svg += &format!("<polygon points=\"{}\"{}/>\n", arrow_head(&path[0], &path[1], size), fill);
+  }
+
+  if let Some(label) = connection.label.as_deref().map(str::trim).filter(|l| !l.is_empty()) {
+    let at = halfway(&path);
+    let size = 12.0;
+    let label_width = label.chars().count() as f64 * size * CHAR_WIDTH + 8.0;
+    svg += &format!(
+      "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"3\" fill=\"#ffffff\" fill-opacity=\"0.85\"/>\n",
+      at.x - label_width / 2.0, at.y - size * 0.8, label_width, size * 1.6
+    );
+    svg += &format!(
+      "<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"{}\" font-size=\"{}\" fill=\"#555555\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+      at.x, at.y, DEFAULT_FONT, size, xml_escape(label)
+    );
+  }
+  svg
+}
+
+// A piece of a wrapped line with uniform formatting
+struct Run {
+  text: String,
+  attributes: rich_text::Attributes,
+  monospace: bool,
+}
+
+// Break the runs into lines no wider than `max_width`, at spaces and newlines
+fn wrap(runs: Vec<Run>, max_width: f64, size: f64) -> Vec<Vec<Run>> {
+  let mut lines: Vec<Vec<Run>> = vec![Vec::new()];
+  let mut line_width = 0.0;
+  for run in runs {
+    for (index, paragraph) in run.text.split('\n').enumerate() {
+      if index > 0 {
+        lines.push(Vec::new());
+        line_width = 0.0;
+      }
+      for word in paragraph.split_inclusive(' ') {
+        let word_width = word.chars().count() as f64 * size * if run.attributes.bold { CHAR_WIDTH * 1.08 } else { CHAR_WIDTH };
+        if line_width + word_width > max_width && line_width > 0.0 && !word.trim().is_empty() {
+          lines.push(Vec::new());
+          line_width = 0.0;
+        }
+        line_width += word_width;
+        let line = lines.last_mut().expect("lines start with one line");
+        match line.last_mut() {
+          Some(last) if last.attributes == run.attributes && last.monospace == run.monospace => last.text += word,
+          _ => line.push(Run { text: word.to_string(), attributes: run.attributes.clone(), monospace: run.monospace }),
+        }
+      }
+    }
+  }
+  lines
+}
+
+// The note's text, checklist and table as runs, one paragraph after another
+fn note_runs(note: &model::Note) -> Vec<Run> {
+  let (text, spans) = markdown::note_rich_text(note);
+  let plain = |text: String| Run { text, attributes: rich_text::Attributes::default(), monospace: false };
+  let mut runs = vec![plain(icons::note_prefix(note))];
+  runs.extend(rich_text::segments(&text, &spans).into_iter()
+    .map(|(text, attributes)| Run { text: text.to_string(), attributes, monospace: false }));
+  for item in checklist::ordered_items(note) {
+    runs.push(plain(format!("\n{} {}", checklist::checkbox(item.done), item.text)));
+  }
+  if let Some(table) = &note.table {
+    runs.push(Run { text: format!("\n{}", tables::to_aligned_text(table).trim_end()), attributes: rich_text::Attributes::default(), monospace: true });
+  }
+  runs.retain(|run| !run.text.is_empty());
+  runs
+}
+
+fn tspan(run: &Run) -> String {
+  let a = &run.attributes;
+  let mut attributes = String::new();
+  if a.bold {
+    attributes += " font-weight=\"bold\"";
+  }
+  if a.italic {
+    attributes += " font-style=\"italic\"";
+  }
+  let decorations: Vec<&str> = [(a.underline || a.link.is_some(), "underline"), (a.strike, "line-through")]
+    .iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+  if !decorations.is_empty() {
+    attributes += &format!(" text-decoration=\"{}\"", decorations.join(" "));
+  }
+  if a.color.is_some() {
+    attributes += &paint("fill", a.color.as_deref(), DEFAULT_TEXT_COLOR);
+  }
+  if run.monospace {
+    attributes += " font-family=\"monospace\"";
+  }
+  format!("<tspan{}>{}</tspan>", attributes, xml_escape(&run.text))
+}
+
+fn note_svg(doc: &model::BoardDocument, note: &model::Note, index: usize) -> String {
+  let style = doc.note_style(note);
+  let text_style = style.map(|s| &s.text_style);
+  let border = style.and_then(|s| s.border.as_ref());
+  let f = &note.frame;
+  let radius = style.and_then(|s| s.corner_radius).unwrap_or(4.0).max(0.0);
+  let rect = format!("x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"{:.2}\"", f.x, f.y, f.w, f.h, radius);
+
+  let opacity = if note.faded.unwrap_or(false) { format!(" opacity=\"{}\"", FADED_OPACITY) } else { String::new() };
+  let mut svg = format!("<g{}>\n", opacity);
+  if style.and_then(|s| s.shadow).unwrap_or(false) {
+    svg += &format!(
+      "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" rx=\"{:.2}\" fill=\"#000000\" fill-opacity=\"0.15\"/>\n",
+      f.x + 2.0, f.y + 3.0, f.w, f.h, radius
+    );
+  }
+  let border_width = border.and_then(|b| b.width).unwrap_or(1.0);
+  let dash = match border.and_then(|b| b.style.as_deref()) {
+    Some("dashed") => format!(" stroke-dasharray=\"{:.2} {:.2}\"", border_width * 4.0, border_width * 3.0),
+    Some("dotted") => format!(" stroke-dasharray=\"{:.2} {:.2}\"", border_width, border_width * 2.0),
+    _ => String::new(),
+  };
+  let stroke = if border.and_then(|b| b.style.as_deref()) == Some("none") || border_width <= 0.0 {
+    String::new()
+  } else {
+    format!("{} stroke-width=\"{:.2}\"{}", paint("stroke", border.and_then(|b| b.color.as_deref()), DEFAULT_NOTE_BORDER), border_width, dash)
+  };
+  svg += &format!("<rect {}{}{}/>\n", rect, paint("fill", style.and_then(|s| s.fill.as_deref()), DEFAULT_NOTE_FILL), stroke);
+
+  // Everything inside is clipped to the note
+  svg += &format!("<clipPath id=\"note{}\"><rect {}/></clipPath>\n<g clip-path=\"url(#note{})\">\n", index, rect, index);
+  let size = text_style.map(|t| t.size).filter(|s| s.is_finite() && *s > 0.0).unwrap_or(DEFAULT_FONT_SIZE);
+  let inner_width = (f.w - NOTE_PADDING * 2.0).max(1.0);
+  let lines = wrap(note_runs(note), inner_width, size);
+  let lines: Vec<Vec<Run>> = match lines.iter().rposition(|line| line.iter().any(|run| !run.text.trim().is_empty())) {
+    Some(last) => lines.into_iter().take(last + 1).collect(),
+    None => Vec::new(),
+  };
+  let text_height = lines.len() as f64 * size * LINE_HEIGHT;
+
+  // An image takes the space above the text
+  let mut top = f.y + NOTE_PADDING;
+  let image = note.images.iter().flatten().find_map(|id| image_data_uri(doc, id));
+  let image_height = f.h - NOTE_PADDING * 2.0 - if lines.is_empty() { 0.0 } else { text_height + NOTE_PADDING };
+  if let Some(uri) = image.filter(|_| image_height >= 1.0) {
+    svg += &format!(
+      "<image x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" preserveAspectRatio=\"xMidYMid meet\" href=\"{}\"/>\n",
+      f.x + NOTE_PADDING, top, inner_width, image_height, uri
+    );
+    top += image_height + NOTE_PADDING;
+  }
+
+  let (anchor, x) = match text_style.and_then(|t| t.align.as_deref()) {
+    Some("center") => ("middle", f.x + f.w / 2.0),
+    Some("right") => ("end", f.x + f.w - NOTE_PADDING),
+    _ => ("start", f.x + NOTE_PADDING),
+  };
+  let font = text_style.map(|t| t.font.trim()).filter(|font| !font.is_empty() && *font != "system-ui").unwrap_or(DEFAULT_FONT);
+  let mut text_attributes = format!(
+    "font-family=\"{}, {}\" font-size=\"{:.2}\" text-anchor=\"{}\"{}",
+    xml_escape(font), DEFAULT_FONT, size, anchor, paint("fill", text_style.and_then(|t| t.color.as_deref()), DEFAULT_TEXT_COLOR)
+  );
+  if let Some(weight) = text_style.and_then(|t| t.weight) {
+    text_attributes += &format!(" font-weight=\"{}\"", weight);
+  }
+  if text_style.and_then(|t| t.italic).unwrap_or(false) {
+    text_attributes += " font-style=\"italic\"";
+  }
+  for (number, line) in lines.iter().enumerate() {
+    let y = top + size + number as f64 * size * LINE_HEIGHT;
+    if line.is_empty() {
+      continue;
+    }
+    let spans: String = line.iter().map(tspan).collect();
+    // Keeping leading and repeated spaces
+    svg += &format!("<text x=\"{:.2}\" y=\"{:.2}\" xml:space=\"preserve\" {}>{}</text>\n", x, y, text_attributes, spans);
+  }
+  svg + "</g>\n</g>\n"
+}
+
+// The board rasterized as the plan says, as RGBA rows
+pub fn to_rgba(doc: &model::BoardDocument, plan: &raster::RasterPlan) -> Result<Vec<u8>, String> {
+  let svg = to_svg(doc, &plan.region);
+  let options = usvg::Options { fontdb: fonts::system_database(), ..Default::default() };
+  let tree = usvg::Tree::from_str(&svg, &options).map_err(|e| format!("Failed to lay out the board: {}", e))?;
+  let mut pixmap = tiny_skia::Pixmap::new(plan.width, plan.height)
+    .ok_or_else(|| format!("Cannot render a {}×{} image", plan.width, plan.height))?;
+  let scale = plan.scale as f32;
+  resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+  Ok(pixmap.pixels().iter()
+    .flat_map(|pixel| {
+      let color = pixel.demultiply();
+      [color.red(), color.green(), color.blue(), color.alpha()]
+    })
+    .collect())
+}
+
+pub fn to_png(doc: &model::BoardDocument, plan: &raster::RasterPlan) -> Result<Vec<u8>, String> {
+  media::encode_png_rgba(&to_rgba(doc, plan)?, plan.width, plan.height)
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeSet;
+
+use crate::{links, model};
+
+// Typed inline formatting for note text. Spans cover `[start, end)` in UTF-16
+// code units — the offsets the webview's text APIs hand out — and may
+// overlap; where they do, attributes set by later spans win.
+
+// richAttrs keys that describe formatting of the whole note. Older boards
+// stored these (and an untyped `spans` array) in the opaque map.
+const WHOLE_NOTE_KEYS: [&str; 6] = ["bold", "italic", "underline", "strike", "color", "link"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attributes {
+  pub bold: bool,
+  pub italic: bool,
+  pub underline: bool,
+  pub strike: bool,
+  pub color: Option<String>,
+  pub link: Option<String>,
+}
+
+impl Attributes {
+  fn apply(&mut self, span: &model::TextSpan) {
+    if let Some(bold) = span.bold { self.bold = bold; }
+    if let Some(italic) = span.italic { self.italic = italic; }
+    if let Some(underline) = span.underline { self.underline = underline; }
+    if let Some(strike) = span.strike { self.strike = strike; }
+    if span.color.is_some() { self.color = span.color.clone(); }
+    if span.link.is_some() { self.link = span.link.clone(); }
+  }
+}
+
+// Byte index of a UTF-16 offset, clamped to the text and to char boundaries
+fn byte_index(text: &str, utf16_offset: usize) -> usize {
+  let mut units = 0;
+  for (index, c) in text.char_indices() {
+    if units >= utf16_offset {
+      return index;
+    }
+    units += c.len_utf16();
+  }
+  text.len()
+}
+
+pub(crate) fn utf16_len(text: &str) -> usize {
+  text.chars().map(char::len_utf16).sum()
+}
+
+// Split text into runs of uniform formatting
+pub fn segments<'a>(text: &'a str, spans: &[model::TextSpan]) -> Vec<(&'a str, Attributes)> {
+  let mut boundaries: BTreeSet<usize> = BTreeSet::from([0, text.len()]);
+  for span in spans {
+    boundaries.insert(byte_index(text, span.start));
+    boundaries.insert(byte_index(text, span.end));
+  }
+
+  let boundaries: Vec<usize> = boundaries.into_iter().collect();
+  boundaries.windows(2)
+    .filter(|w| w[0] < w[1])
+    .map(|w| {
+      let mut attributes = Attributes::default();
+      for span in spans {
+        if byte_index(text, span.start) <= w[0] && byte_index(text, span.end) >= w[1] {
+          attributes.apply(span);
+        }
+      }
+      (&text[w[0]..w[1]], attributes)
+    })
+    .collect()
+}
+
+// Move formatting out of the legacy richAttrs map into typed spans. Keys the
+// backend doesn't model (e.g. the frontend's `markdownEnabled`) stay behind.
+pub fn migrate_rich_attrs(note: &mut model::Note) {
+  let Some(attrs) = note.rich_attrs.as_mut() else {
+    return;
+  };
+  let mut migrated = Vec::new();
+
+  if let Some(value) = attrs.remove("spans") {
+    match serde_json::from_value::<Vec<model::TextSpan>>(value.clone()) {
+      Ok(spans) => migrated.extend(spans),
+      // Leave data we can't interpret where it was
+      Err(_) => { attrs.insert("spans".to_string(), value); },
+    }
+  }
+
+  if WHOLE_NOTE_KEYS.iter().any(|key| attrs.contains_key(*key)) {
+    let flag = |key: &str| attrs.get(key).and_then(|v| v.as_bool());
+    let string = |key: &str| attrs.get(key).and_then(|v| v.as_str()).map(str::to_string);
+    migrated.insert(0, model::TextSpan {
+      start: 0,
+      end: utf16_len(&note.text),
+      bold: flag("bold"),
+      italic: flag("italic"),
+      underline: flag("underline"),
+      strike: flag("strike"),
+      color: string("color"),
+      link: string("link"),
+    });
+    for key in WHOLE_NOTE_KEYS {
+      attrs.remove(key);
+    }
+  }
+
+  if attrs.is_empty() {
+    note.rich_attrs = None;
+  }
+  if !migrated.is_empty() {
+    note.spans.get_or_insert_with(Vec::new).splice(0..0, migrated);
+  }
+}
+
+pub fn migrate_document(doc: &mut model::BoardDocument) {
+  for note in &mut doc.notes {
+    migrate_rich_attrs(note);
+  }
+}
+
+// Distinct span colors in the document, for building an RTF color table
+pub fn span_colors(doc: &model::BoardDocument) -> Vec<String> {
+  let mut colors: Vec<String> = Vec::new();
+  for span in doc.notes.iter().flat_map(|n| n.spans.iter().flatten()) {
+    if let Some(color) = &span.color {
+      if !colors.contains(color) {
+        colors.push(color.clone());
+      }
+    }
+  }
+  colors
+}
+
+// RTF control words for a note style's text: font, size (board px → half
+// points), weight, slant, decorations and color
+pub fn rtf_style_codes(style: &model::TextStyle, font_index: Option<usize>, color_index: Option<usize>) -> String {
+  let mut codes = String::new();
+  if let Some(index) = font_index {
+    codes += &format!("\\f{}", index);
+  }
+  if style.size.is_finite() && style.size > 0.0 {
+    codes += &format!("\\fs{}", (style.size * 0.75 * 2.0).round() as u32);
+  }
+  if style.weight.is_some_and(|w| w >= 600) { codes += "\\b"; }
+  if style.italic.unwrap_or(false) { codes += "\\i"; }
+  if style.underline.unwrap_or(false) { codes += "\\ul"; }
+  if style.strike.unwrap_or(false) { codes += "\\strike"; }
+  if let Some(index) = color_index {
+    codes += &format!("\\cf{}", index);
+  }
+  codes
+}
+
+// RTF for note text. `color_index` maps a span color to its color table entry.
+pub fn to_rtf(
+  text: &str,
+  spans: &[model::TextSpan],
+  escape: fn(&str) -> String,
+  color_index: impl Fn(&str) -> Option<usize>,
+) -> String {
+  let mut rtf = String::new();
+  for (run, attributes) in segments(text, spans) {
+    let mut codes = String::new();
+    if attributes.bold { codes += "\\b"; }
+    if attributes.italic { codes += "\\i"; }
+    if attributes.underline { codes += "\\ul"; }
+    if attributes.strike { codes += "\\strike"; }
+    if let Some(index) = attributes.color.as_deref().and_then(&color_index) {
+      codes += &format!("\\cf{}", index);
+    }
+
+    let mut body = escape(run);
+    if !codes.is_empty() {
+      body = format!("{{{} {}}}", codes, body);
+    }
+    if let Some(link) = &attributes.link {
+      body = format!("{{\\field{{\\*\\fldinst HYPERLINK \"{}\"}}{{\\fldrslt {}}}}}", escape(link).replace('"', "\\'22"), body);
+    }
+    rtf += &body;
+  }
+  rtf
+}
+
+pub fn to_html(text: &str, spans: &[model::TextSpan], escape: fn(&str) -> String) -> String {
+  let mut html = String::new();
+  for (run, attributes) in segments(text, spans) {
+    let mut body = escape(run).replace('\n', "<br>");
+    if attributes.bold { body = format!("<strong>{}</strong>", body); }
+    if attributes.italic { body = format!("<em>{}</em>", body); }
+    if attributes.underline { body = format!("<u>{}</u>", body); }
+    if attributes.strike { body = format!("<s>{}</s>", body); }
+    if let Some(color) = &attributes.color {
+      body = format!("<span style=\"color: {}\">{}</span>", escape(color), body);
+    }
+    if let Some(link) = &attributes.link {
+      body = format!("<a href=\"{}\">{}</a>", escape(&links::href(link)), body);
+    }
+    html += &body;
+  }
+  html
+}
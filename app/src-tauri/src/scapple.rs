@@ -0,0 +1,287 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{model, outline};
+
+// Scapple (.scap) import. A Scapple board is XML: notes with a position, a
+// width, their text with formatting ranges and the IDs of the notes they are
+// connected to or point at, plus background shapes and the board's colors.
+// Notes, shapes and connections map onto ours; each distinct look (fill,
+// border, text color, size, alignment) becomes one note style. Scapple sizes
+// notes to their text and doesn't store the height, so it is estimated from
+// the text and width. Connections are undirected in Scapple unless one note
+// points at the other, which becomes an arrow. Scapple files are only read;
+// saving writes a .fim board.
+
+const DEFAULT_FONT: &str = "system-ui";
+const DEFAULT_FONT_SIZE: f64 = 12.0;
+const DEFAULT_WIDTH: f64 = 120.0;
+const MIN_WIDTH: f64 = 20.0;
+// Average glyph width and line height relative to the font size, and the
+// room around the text, for estimating note heights
+const CHAR_WIDTH: f64 = 0.55;
+const LINE_HEIGHT: f64 = 1.3;
+const NOTE_PADDING: f64 = 8.0;
+const ROUNDED_RADIUS: f64 = 6.0;
+const SHAPE_RADIUS: f64 = 25.0;
+// Widest ID range expanded from a connection list, so a damaged list can't
+// make the import crawl
+const MAX_ID_RANGE: u64 = 100_000;
+
+// A Scapple color, three or four floats from 0 to 1, as hex
+fn color(value: &str) -> Option<String> {
+  let parts: Vec<f64> = value.split_whitespace().map(|p| p.parse().ok()).collect::<Option<_>>()?;
+  if !(3..=4).contains(&parts.len()) || !parts.iter().all(|p| p.is_finite()) {
+    return None;
+  }
+  let byte = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+  Some(format!("#{:02x}{:02x}{:02x}", byte(parts[0]), byte(parts[1]), byte(parts[2])))
+}
+
+// Comma-separated numbers, as in "x,y" positions and "x,y,w,h" frames;
+// braces from older files are ignored
+fn numbers(value: &str) -> Option<Vec<f64>> {
+  let cleaned: String = value.chars().filter(|c| !matches!(c, '{' | '}')).collect();
+  let parts: Vec<f64> = cleaned.split(',').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+  parts.iter().all(|p| p.is_finite()).then_some(parts)
+}
+
+// "1, 3-5" → [1, 3, 4, 5]
+fn id_list(value: &str) -> Vec<u64> {
+  let mut ids = Vec::new();
+  for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+    match part.split_once('-') {
+      Some((start, end)) => {
+        if let (Ok(start), Ok(end)) = (start.trim().parse::<u64>(), end.trim().parse::<u64>()) {
+          if end >= start && end - start <= MAX_ID_RANGE {
+            ids.extend(start..=end);
+          }
+        }
+      },
+      None => ids.extend(part.parse::<u64>().ok()),
+    }
+  }
+  ids
+}
+
+fn child<'a, 'input>(node: &roxmltree::Node<'a, 'input>, name: &str) -> Option<roxmltree::Node<'a, 'input>> {
+  node.children().find(|c| c.has_tag_name(name))
+}
+
+fn child_text(node: &roxmltree::Node, name: &str) -> Option<String> {
+  child(node, name).and_then(|c| c.text()).map(str::to_string)
+}
+
+fn yes(node: &roxmltree::Node, name: &str) -> Option<bool> {
+  (node.attribute(name) == Some("Yes")).then_some(true)
+}
+
+// Rough height of `text` wrapped to `width`
+fn estimated_height(text: &str, width: f64, font_size: f64) -> f64 {
+  let per_line = ((width - NOTE_PADDING * 2.0).max(font_size) / (font_size * CHAR_WIDTH)).floor().max(1.0);
+  let lines: f64 = text.split('\n')
+    .map(|line| (line.chars().count() as f64 / per_line).ceil().max(1.0))
+    .sum();
+  lines * font_size * LINE_HEIGHT + NOTE_PADDING * 2.0
+}
+
+// Formatting ranges ("location,length" in UTF-16 code units, like our spans)
+fn spans(note: &roxmltree::Node, text_len: usize) -> Option<Vec<model::TextSpan>> {
+  let formatting = child(note, "Formatting")?;
+  let spans: Vec<model::TextSpan> = formatting.children()
+    .filter(|c| c.has_tag_name("FormatRange"))
+    .filter_map(|range| {
+      let parts = numbers(range.text()?)?;
+      let [location, length] = parts[..] else {
+        return None;
+      };
+      let start = (location.max(0.0) as usize).min(text_len);
+      let end = ((location + length).max(0.0) as usize).min(text_len);
+      (end > start).then(|| model::TextSpan {
+        start,
+        end,
+        bold: yes(&range, "Bold"),
+        italic: yes(&range, "Italic"),
+        underline: yes(&range, "Underline"),
+        strike: yes(&range, "Struckthrough"),
+        color: range.attribute("TextColor").and_then(color),
+        link: range.attribute("Link").map(str::to_string),
+      })
+    })
+    .filter(|span| span.bold.is_some() || span.italic.is_some() || span.underline.is_some()
+      || span.strike.is_some() || span.color.is_some() || span.link.is_some())
+    .collect();
+  (!spans.is_empty()).then_some(spans)
+}
+
+struct Importer {
+  doc: model::BoardDocument,
+  font: String,
+  text_color: Option<String>,
+  // Appearance → the style made for it
+  styles: HashMap<String, model::ID>,
+}
+
+impl Importer {
+  // The style for an <Appearance>, shared by everything that looks the same
+  fn style(&mut self, appearance: Option<roxmltree::Node>, font_size: f64, shape: bool) -> Option<model::ID> {
+    let appearance = appearance?;
+    let fill = child_text(&appearance, "Fill").as_deref().and_then(color);
+    let text_color = child_text(&appearance, "TextColor").as_deref().and_then(color).or_else(|| self.text_color.clone());
+    let align = child_text(&appearance, "Alignment").map(|a| a.to_lowercase())
+      .filter(|a| matches!(a.as_str(), "left" | "center" | "right" | "justified"));
+    let border = child(&appearance, "Border");
+    let border_weight = border.and_then(|b| b.attribute("Weight")).and_then(|w| w.parse::<f64>().ok())
+      .filter(|w| w.is_finite() && *w > 0.0);
+    let border_style = border.and_then(|b| b.attribute("Style")).unwrap_or("Square").to_string();
+    let border_color = child_text(&appearance, "BorderColor").as_deref().and_then(color)
+      .or_else(|| border_weight.and(text_color.clone()));
+
+    let key = format!("{:?}|{:?}|{:?}|{:?}|{:?}|{}|{}|{}", fill, text_color, align, border_weight, border_color, border_style, font_size, shape);
+    if let Some(id) = self.styles.get(&key) {
+      return Some(id.clone());
+    }
+    let id = format!("style_scapple_{}", self.styles.len() + 1);
+    let corner_radius = match border_style.as_str() {
+      "Rounded" if shape => Some(SHAPE_RADIUS),
+      "Rounded" | "Cloud" => Some(ROUNDED_RADIUS),
+      _ => None,
+    };
+    self.doc.note_styles.push(model::NoteStyle {
+      id: id.clone(),
+      name: None,
+      text_style: model::TextStyle {
+        font: self.font.clone(),
+        size: font_size,
+        weight: None,
+        italic: None,
+        underline: None,
+        strike: None,
+        color: text_color,
+        align,
+      },
+      fill,
+      border: border_weight.map(|width| model::BorderStyle { color: border_color, width: Some(width), style: Some("solid".to_string()) }),
+      corner_radius,
+      shadow: None,
+    });
+    self.styles.insert(key, id.clone());
+    Some(id)
+  }
+}
+
+pub fn import(xml: &str) -> Result<model::BoardDocument, String> {
+  let tree = roxmltree::Document::parse(xml)
+    .map_err(|e| format!("Invalid Scapple file: {}", e))?;
+  let root = tree.root_element();
+  if !root.has_tag_name("ScappleDocument") {
+    return Err("Not a Scapple file: the root element isn't <ScappleDocument>".to_string());
+  }
+
+  let settings = child(&root, "UISettings");
+  let mut importer = Importer {
+    doc: model::BoardDocument::empty(),
+    font: settings.and_then(|s| child_text(&s, "DefaultFont")).filter(|f| !f.trim().is_empty())
+      .unwrap_or_else(|| DEFAULT_FONT.to_string()),
+    text_color: settings.and_then(|s| child_text(&s, "DefaultTextColor")).as_deref().and_then(color),
+    styles: HashMap::new(),
+  };
+  if let Some(background) = settings.and_then(|s| child_text(&s, "BackgroundColor")).as_deref().and_then(color) {
+    importer.doc.document_style = Some(model::DocumentStyle {
+      background: Some(model::BackgroundStyle { color: Some(background), texture_id: None, texture_scale: None, texture_opacity: None }),
+      default_note_style_id: None,
+      default_shape_style_id: None,
+      grid: None,
+      labels: None,
+    });
+  }
+
+  // Scapple note ID → ours, and (connected, pointed at) per note
+  let mut ids: HashMap<u64, model::ID> = HashMap::new();
+  let mut links: Vec<(u64, Vec<u64>, Vec<u64>)> = Vec::new();
+  for note in child(&root, "Notes").iter().flat_map(|n| n.children()).filter(|c| c.has_tag_name("Note")) {
+    let Some(position) = note.attribute("Position").and_then(numbers).filter(|p| p.len() == 2) else {
+      continue;
+    };
+    let font_size = note.attribute("FontSize").and_then(|s| s.parse::<f64>().ok())
+      .filter(|s| s.is_finite() && *s > 0.0)
+      .unwrap_or(DEFAULT_FONT_SIZE);
+    let width = note.attribute("Width").and_then(|w| w.parse::<f64>().ok())
+      .filter(|w| w.is_finite())
+      .unwrap_or(DEFAULT_WIDTH)
+      .max(MIN_WIDTH);
+    let text = child_text(&note, "String").unwrap_or_default().replace("\r\n", "\n");
+    let frame = model::Rect { x: position[0], y: position[1], w: width, h: estimated_height(&text, width, font_size) };
+    let style_id = importer.style(child(&note, "Appearance"), font_size, false);
+    let spans = spans(&note, text.chars().map(char::len_utf16).sum());
+
+    let imported = model::Note { style_id, spans, ..outline::imported_note(&text, frame) };
+    if let Some(scapple_id) = note.attribute("ID").and_then(|id| id.parse::<u64>().ok()) {
+      if ids.insert(scapple_id, imported.id.clone()).is_none() {
+        let connected = child_text(&note, "ConnectedNoteIDs").map(|l| id_list(&l)).unwrap_or_default();
+        let points_to = child_text(&note, "PointsToNoteIDs").map(|l| id_list(&l)).unwrap_or_default();
+        links.push((scapple_id, connected, points_to));
+      }
+    }
+    importer.doc.notes.push(imported);
+  }
+
+  // Each pair once, whichever note lists it; (low, high) → (low points at
+  // high, high points at low)
+  let mut pairs: BTreeMap<(u64, u64), (bool, bool)> = BTreeMap::new();
+  for (id, connected, points_to) in &links {
+    for other in connected.iter().chain(points_to).filter(|other| *other != id && ids.contains_key(other)) {
+      let entry = pairs.entry(((*id).min(*other), (*id).max(*other))).or_default();
+      if points_to.contains(other) {
+        if id < other { entry.0 = true } else { entry.1 = true }
+      }
+    }
+  }
+  for ((low, high), (forward, backward)) in pairs {
+    let (src, dst, arrows) = match (forward, backward) {
+      (true, true) => (low, high, "both"),
+      (false, true) => (high, low, "dst"),
+      (true, false) => (low, high, "dst"),
+      (false, false) => (low, high, "none"),
+    };
+    importer.doc.connections.push(model::Connection {
+      id: model::new_id("conn"),
+      src_note_id: ids[&src].clone(),
+      dst_note_id: ids[&dst].clone(),
+      style: Some(model::ConnectionStyle {
+        kind: Some("solid".to_string()),
+        arrows: Some(arrows.to_string()),
+        color: None,
+        width: None,
+        curve_type: None,
+      }),
+      label: None,
+      bend_points: None,
+      control_points: None,
+      relation_type: None,
+    });
+  }
+
+  for shape in child(&root, "BackgroundShapes").iter().flat_map(|n| n.children()).filter(|c| c.has_tag_name("Shape")) {
+    let Some(frame) = shape.attribute("Frame").and_then(numbers).filter(|f| f.len() == 4 && f[2] > 0.0 && f[3] > 0.0) else {
+      continue;
+    };
+    let ellipse = shape.attribute("Type").is_some_and(|t| t.eq_ignore_ascii_case("Ellipse") || t.eq_ignore_ascii_case("Oval"));
+    let radius = shape.attribute("CornerRadius").and_then(|r| r.parse::<f64>().ok()).filter(|r| r.is_finite() && *r > 0.0);
+    let style_id = importer.style(child(&shape, "Appearance"), DEFAULT_FONT_SIZE, true);
+    importer.doc.shapes.push(model::BackgroundShape {
+      id: model::new_id("shape"),
+      kind: ellipse.then(|| "ellipse".to_string()),
+      frame: model::Rect { x: frame[0], y: frame[1], w: frame[2], h: frame[3] },
+      points: None,
+      radius: radius.filter(|_| !ellipse),
+      magnetic: yes(&shape, "Magnetic"),
+      style_id,
+      label: None,
+      z_index: None,
+      locked: None,
+      layer_id: None,
+    });
+  }
+
+  Ok(importer.doc)
+}
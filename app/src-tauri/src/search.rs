@@ -0,0 +1,160 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{markdown, model, shapes};
+
+// Full-text search over notes: their text (Markdown as shown, not as typed),
+// checklist items and table cells. Queries are plain text by default, or a
+// regular expression; either can be case-sensitive and limited to whole
+// words. Each matching note comes back with a snippet around its first match,
+// the ranges to highlight in it, and where the note sits on the board.
+
+// Characters of context kept before and after the first match
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_AFTER: usize = 80;
+const DEFAULT_LIMIT: usize = 500;
+// Compiled size cap for user-supplied patterns
+const MAX_PATTERN_SIZE: usize = 1024 * 1024;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SearchOptions {
+  pub case_sensitive: Option<bool>,
+  pub whole_word: Option<bool>,
+  // Read the query as a regular expression
+  pub regex: Option<bool>,
+  // Most matches returned per board
+  pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NoteMatch {
+  pub note_id: model::ID,
+  pub snippet: String,
+  // [start, end) of each match inside the snippet, in UTF-16 code units
+  // like text spans
+  pub highlights: Vec<(usize, usize)>,
+  // Matches in the whole note
+  pub match_count: usize,
+  pub frame: model::Rect,
+  pub stack_id: Option<model::ID>,
+  // Innermost background shape around the note
+  pub shape_id: Option<model::ID>,
+}
+
+// Matches in another board, for finding which board a note lives in
+#[derive(Serialize, Debug, Clone)]
+pub struct DocumentMatches {
+  pub path: String,
+  pub display_name: String,
+  pub matches: Vec<NoteMatch>,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SearchResults {
+  // In the board searched
+  pub matches: Vec<NoteMatch>,
+  // In recent files, when asked for; boards without matches are left out
+  pub documents: Vec<DocumentMatches>,
+  // Recent files that couldn't be searched, with the reason
+  pub skipped: Vec<String>,
+}
+
+pub struct Matcher {
+  pattern: Regex,
+  limit: usize,
+}
+
+impl Matcher {
+  pub fn new(query: &str, options: &SearchOptions) -> Result<Matcher, String> {
+    if query.trim().is_empty() {
+      return Err("Search query is empty".to_string());
+    }
+    let mut pattern = if options.regex.unwrap_or(false) { query.to_string() } else { regex::escape(query) };
+    if options.whole_word.unwrap_or(false) {
+      pattern = format!(r"\b(?:{})\b", pattern);
+    }
+    let pattern = RegexBuilder::new(&pattern)
+      .case_insensitive(!options.case_sensitive.unwrap_or(false))
+      .size_limit(MAX_PATTERN_SIZE)
+      .build()
+      .map_err(|e| format!("Invalid search pattern: {}", e))?;
+    let limit = options.limit.filter(|l| *l > 0).unwrap_or(DEFAULT_LIMIT);
+    Ok(Matcher { pattern, limit })
+  }
+}
+
+// Everything searchable in a note, one line per part
+fn note_text(note: &model::Note) -> String {
+  let mut parts = vec![markdown::note_rich_text(note).0];
+  parts.extend(note.checklist.iter().flatten().map(|item| item.text.clone()));
+  if let Some(table) = &note.table {
+    parts.extend(std::iter::once(&table.columns).chain(&table.rows).map(|row| row.join("\t")));
+  }
+  parts.retain(|part| !part.trim().is_empty());
+  parts.join("\n")
+}
+
+fn utf16_len(text: &str) -> usize {
+  text.chars().map(char::len_utf16).sum()
+}
+
+// Byte index `chars` characters before or after `index`, within the text
+fn step_chars(text: &str, index: usize, chars: usize, forward: bool) -> usize {
+  if forward {
+    text[index..].char_indices().nth(chars).map_or(text.len(), |(offset, _)| index + offset)
+  } else {
+    text[..index].char_indices().rev().nth(chars.saturating_sub(1)).map_or(0, |(offset, _)| offset)
+  }
+}
+
+fn snippet(text: &str, matches: &[(usize, usize)]) -> (String, Vec<(usize, usize)>) {
+  let (first_start, first_end) = matches[0];
+  let start = step_chars(text, first_start, SNIPPET_BEFORE, false);
+  let end = step_chars(text, first_end, SNIPPET_AFTER, true).max(first_end);
+  let prefix = if start > 0 { "…" } else { "" };
+  let suffix = if end < text.len() { "…" } else { "" };
+  // Line breaks would only waste the little room a snippet has
+  let flat = |s: &str| s.replace(['\n', '\t'], " ");
+
+  let offset = utf16_len(prefix);
+  let highlights = matches.iter()
+    .filter(|(s, e)| *s >= start && *e <= end)
+    .map(|(s, e)| (offset + utf16_len(&text[start..*s]), offset + utf16_len(&text[start..*e])))
+    .collect();
+  (format!("{}{}{}", prefix, flat(&text[start..end]), suffix), highlights)
+}
+
+fn innermost_shape<'a>(doc: &'a model::BoardDocument, note: &model::Note) -> Option<&'a model::BackgroundShape> {
+  doc.shapes.iter()
+    .filter(|shape| shapes::kind(shape) != "line" && shapes::contains_note(shape, note))
+    .min_by(|a, b| (a.frame.w * a.frame.h).abs().total_cmp(&(b.frame.w * b.frame.h).abs()))
+}
+
+// Matching notes in document order, at most the matcher's limit
+pub fn search(doc: &model::BoardDocument, matcher: &Matcher) -> Vec<NoteMatch> {
+  let mut results = Vec::new();
+  for note in &doc.notes {
+    if results.len() >= matcher.limit {
+      break;
+    }
+    let text = note_text(note);
+    let matches: Vec<(usize, usize)> = matcher.pattern.find_iter(&text)
+      .filter(|m| !m.is_empty())
+      .map(|m| (m.start(), m.end()))
+      .collect();
+    if matches.is_empty() {
+      continue;
+    }
+    let (snippet, highlights) = snippet(&text, &matches);
+    results.push(NoteMatch {
+      note_id: note.id.clone(),
+      snippet,
+      highlights,
+      match_count: matches.len(),
+      frame: note.frame.clone(),
+      stack_id: note.stack_id.clone(),
+      shape_id: innermost_shape(doc, note).map(|shape| shape.id.clone()),
+    });
+  }
+  results
+}
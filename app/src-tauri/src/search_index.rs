@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+use crate::{confidential, icons, markdown, model, properties};
+
+// .fim files are zip containers that Spotlight and Windows Search can't look
+// into, so boards can optionally get a plain-text twin next to them
+// (`Board.fim` → `Board.fim.txt`) holding everything worth searching for.
+pub fn sidecar_path(document_path: &Path) -> PathBuf {
+  let mut name = document_path.as_os_str().to_owned();
+  name.push(".txt");
+  PathBuf::from(name)
+}
+
+pub fn generate_search_text(doc: &model::BoardDocument, document_path: &Path) -> String {
+  let title = document_path.file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_else(|| "Untitled board".to_string());
+
+  let mut output = format!("{}\n", title);
+  output += "Search index for a Freeform Idea Map board. Open the .fim file to edit.\n\n";
+
+  for (key, value) in properties::sorted(&doc.properties) {
+    output += &format!("{}: {}\n", key, properties::value_text(value));
+  }
+  if doc.properties.as_ref().is_some_and(|p| !p.is_empty()) {
+    output += "\n";
+  }
+
+  for note in &doc.notes {
+    output += &icons::note_prefix(note);
+    output += markdown::note_rich_text(note).0.trim();
+    output += "\n";
+    for item in note.checklist.iter().flatten() {
+      output += item.text.trim();
+      output += "\n";
+    }
+    if let Some(table) = &note.table {
+      for row in std::iter::once(&table.columns).chain(&table.rows) {
+        let cells: Vec<&str> = row.iter().map(|c| c.trim()).filter(|c| !c.is_empty()).collect();
+        if !cells.is_empty() {
+          output += &cells.join("\t");
+          output += "\n";
+        }
+      }
+    }
+    for (key, value) in properties::sorted(&note.properties) {
+      output += &format!("{}: {}\n", key, properties::value_text(value));
+    }
+    output += "\n";
+  }
+
+  let labels = doc.connections.iter().filter_map(|c| c.label.as_deref())
+    .chain(doc.shapes.iter().filter_map(|s| s.label.as_deref()))
+    .map(str::trim)
+    .filter(|label| !label.is_empty());
+  for label in labels {
+    output += label;
+    output += "\n";
+  }
+
+  output
+}
+
+pub fn write_sidecar(doc: &model::BoardDocument, document_path: &Path) -> Result<PathBuf, String> {
+  let path = sidecar_path(document_path);
+  // The sidecar is plain text on disk; confidential notes stay out of it
+  std::fs::write(&path, generate_search_text(&confidential::without_confidential(doc), document_path))
+    .map_err(|e| format!("Failed to write search sidecar '{}': {}", path.display(), e))?;
+  Ok(path)
+}
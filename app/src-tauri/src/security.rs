@@ -0,0 +1,259 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+// Tracks which filesystem locations the webview is allowed to write to.
+// A path is writable if it was previously returned by one of the native
+// dialog commands (or lies inside a folder that was), or if it lives inside
+// one of the user-facing scopes that mirror `capabilities/default.json`
+// ($DOCUMENT, $DESKTOP, $DOWNLOAD).
+#[derive(Debug, Default)]
+pub struct PathScope {
+  authorized: HashSet<PathBuf>,
+  authorized_dirs: HashSet<PathBuf>,
+}
+
+impl PathScope {
+  // Record a path chosen by the user through a native dialog.
+  pub fn authorize(&mut self, path: &Path) {
+    if let Ok(normalized) = normalize_path(path) {
+      self.authorized.insert(normalized);
+    }
+  }
+
+  // Record a folder chosen by the user through a native dialog; files inside
+  // it become writable.
+  pub fn authorize_folder(&mut self, dir: &Path) {
+    if let Ok(canonical) = dir.canonicalize() {
+      if canonical.is_dir() {
+        self.authorized_dirs.insert(canonical);
+      }
+    }
+  }
+
+  // Validate a path received from the webview before writing to it.
+  // Returns the normalized path that should be used for the write.
+  pub fn check_write(&self, path: &Path) -> Result<PathBuf, String> {
+    let normalized = normalize_path(path)?;
+
+    if self.authorized.contains(&normalized) {
+      return Ok(normalized);
+    }
+
+    if self.authorized_dirs.iter().any(|dir| normalized.starts_with(dir)) {
+      return Ok(normalized);
+    }
+
+    if allowed_roots().iter().any(|root| normalized.starts_with(root)) {
+      return Ok(normalized);
+    }
+
+    Err(format!(
+      "Write access denied for '{}': path was not chosen via a dialog and is outside the allowed folders",
+      path.display()
+    ))
+  }
+
+  // Validate a folder received from the webview before writing files into
+  // it. Returns its canonical path.
+  pub fn check_write_folder(&self, dir: &Path) -> Result<PathBuf, String> {
+    if !dir.is_absolute() {
+      return Err(format!("Path '{}' must be absolute", dir.display()));
+    }
+    let canonical = dir.canonicalize()
+      .map_err(|e| format!("Cannot resolve directory '{}': {}", dir.display(), e))?;
+    if !canonical.is_dir() {
+      return Err(format!("Path '{}' is not a directory", dir.display()));
+    }
+
+    if self.authorized_dirs.iter().any(|authorized| canonical.starts_with(authorized))
+      || allowed_roots().iter().any(|root| canonical.starts_with(root)) {
+      return Ok(canonical);
+    }
+
+    Err(format!(
+      "Write access denied for '{}': folder was not chosen via a dialog and is outside the allowed folders",
+      dir.display()
+    ))
+  }
+}
+
+// Directories the webview may write into without a prior dialog selection.
+fn allowed_roots() -> Vec<PathBuf> {
+  [dirs::document_dir(), dirs::desktop_dir(), dirs::download_dir()]
+    .into_iter()
+    .flatten()
+    .filter_map(|root| root.canonicalize().ok())
+    .collect()
+}
+
+// Resolve a (possibly not yet existing) file path to an absolute, canonical
+// form so that `..` segments and symlinked parents can't escape a scope.
+fn normalize_path(path: &Path) -> Result<PathBuf, String> {
+  if !path.is_absolute() {
+    return Err(format!("Path '{}' must be absolute", path.display()));
+  }
+
+  if path.components().any(|c| matches!(c, Component::ParentDir)) {
+    return Err(format!("Path '{}' must not contain '..' segments", path.display()));
+  }
+
+  if let Ok(metadata) = std::fs::symlink_metadata(path) {
+    if metadata.file_type().is_symlink() {
+      return Err(format!("Refusing to write through symlink '{}'", path.display()));
+    }
+    if metadata.is_dir() {
+      return Err(format!("Path '{}' is a directory", path.display()));
+    }
+  }
+
+  let file_name = path.file_name()
+    .ok_or_else(|| format!("Path '{}' has no file name", path.display()))?;
+  let parent = path.parent()
+    .ok_or_else(|| format!("Path '{}' has no parent directory", path.display()))?;
+  let parent = parent.canonicalize()
+    .map_err(|e| format!("Cannot resolve directory '{}': {}", parent.display(), e))?;
+
+  Ok(parent.join(file_name))
+}
+
+// Overwrite a file's contents before unlinking it. This is best-effort: on
+// SSDs and copy-on-write filesystems the old blocks may survive, but it keeps
+// plaintext out of casual undelete tools and temp-folder scavengers.
+pub fn secure_delete(path: &Path) -> Result<(), String> {
+  use std::io::{Seek, SeekFrom, Write};
+
+  let overwrite = || -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let len = file.metadata()?.len();
+    let zeros = [0u8; 64 * 1024];
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut remaining = len;
+    while remaining > 0 {
+      let chunk = remaining.min(zeros.len() as u64) as usize;
+      file.write_all(&zeros[..chunk])?;
+      remaining -= chunk as u64;
+    }
+    file.sync_all()
+  };
+
+  if let Err(e) = overwrite() {
+    log::warn!("Could not overwrite '{}' before deletion: {}", path.display(), e);
+  }
+
+  std::fs::remove_file(path)
+    .map_err(|e| format!("Failed to delete '{}': {}", path.display(), e))
+}
+
+// Delete a file, overwriting it first when secure cleanup is enabled.
+pub fn remove_file(path: &Path, secure: bool) -> Result<(), String> {
+  if secure {
+    secure_delete(path)
+  } else {
+    std::fs::remove_file(path)
+      .map_err(|e| format!("Failed to delete '{}': {}", path.display(), e))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // A fresh folder under the system temp dir, outside the allowed roots
+  fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("scananas-security-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+  }
+
+  #[test]
+  fn files_inside_an_authorized_folder_are_writable() {
+    let root = scratch("inside");
+    let authorized = root.join("boards");
+    std::fs::create_dir_all(authorized.join("nested")).unwrap();
+    let mut scope = PathScope::default();
+    scope.authorize_folder(&authorized);
+
+    assert_eq!(scope.check_write(&authorized.join("plan.fim")).unwrap(), authorized.join("plan.fim"));
+    assert!(scope.check_write(&authorized.join("nested").join("plan.fim")).is_ok());
+    assert!(scope.check_write(&root.join("plan.fim")).is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn folders_sharing_a_prefix_are_not_authorized() {
+    let root = scratch("prefix");
+    let authorized = root.join("auth");
+    let sibling = root.join("auth-other");
+    std::fs::create_dir_all(&authorized).unwrap();
+    std::fs::create_dir_all(&sibling).unwrap();
+    let mut scope = PathScope::default();
+    scope.authorize_folder(&authorized);
+
+    assert!(scope.check_write(&sibling.join("plan.fim")).is_err());
+    assert!(scope.check_write_folder(&sibling).is_err());
+    assert!(scope.check_write_folder(&authorized).is_ok());
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn parent_segments_are_rejected() {
+    let root = scratch("parent");
+    let authorized = root.join("auth");
+    std::fs::create_dir_all(&authorized).unwrap();
+    let mut scope = PathScope::default();
+    scope.authorize_folder(&authorized);
+
+    let escaping = authorized.join("..").join("plan.fim");
+    assert!(normalize_path(&escaping).unwrap_err().contains("'..'"));
+    assert!(scope.check_write(&escaping).is_err());
+    // Even when the path would resolve back inside the folder
+    assert!(scope.check_write(&authorized.join("..").join("auth").join("plan.fim")).is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn relative_paths_are_rejected() {
+    let scope = PathScope::default();
+    for path in ["plan.fim", "boards/plan.fim", "./plan.fim"] {
+      assert!(normalize_path(Path::new(path)).unwrap_err().contains("must be absolute"));
+      assert!(scope.check_write(Path::new(path)).is_err());
+    }
+    assert!(scope.check_write_folder(Path::new("boards")).is_err());
+  }
+
+  #[test]
+  fn files_authorized_by_a_dialog_are_writable() {
+    let root = scratch("file");
+    let mut scope = PathScope::default();
+    scope.authorize(&root.join("plan.fim"));
+
+    assert!(scope.check_write(&root.join("plan.fim")).is_ok());
+    assert!(scope.check_write(&root.join("other.fim")).is_err());
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn symlinks_cannot_escape_an_authorized_folder() {
+    let root = scratch("symlink");
+    let authorized = root.join("auth");
+    let outside = root.join("outside");
+    std::fs::create_dir_all(&authorized).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    std::fs::write(outside.join("target.fim"), b"").unwrap();
+    std::os::unix::fs::symlink(&outside, authorized.join("linked")).unwrap();
+    std::os::unix::fs::symlink(outside.join("target.fim"), authorized.join("plan.fim")).unwrap();
+    let mut scope = PathScope::default();
+    scope.authorize_folder(&authorized);
+
+    // A symlinked parent resolves to where it really points
+    assert_eq!(normalize_path(&authorized.join("linked").join("plan.fim")).unwrap(), outside.join("plan.fim"));
+    assert!(scope.check_write(&authorized.join("linked").join("plan.fim")).is_err());
+    assert!(scope.check_write_folder(&authorized.join("linked")).is_err());
+    // Writing through a symlinked file is refused outright
+    assert!(scope.check_write(&authorized.join("plan.fim")).unwrap_err().contains("symlink"));
+    std::fs::remove_dir_all(&root).unwrap();
+  }
+}
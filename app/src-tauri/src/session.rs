@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::settings;
+
+// What the app remembers between launches besides the recent files: the board
+// that was open last, so the start screen can offer to reopen it, and where
+// the last save went, so Save As starts in that folder. Kept in app data and
+// written whenever either changes.
+
+const SESSION_FILE: &str = "session.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Session {
+  #[serde(rename = "lastDocument")]
+  pub last_document: Option<String>,
+  #[serde(rename = "lastSavePath")]
+  pub last_save_path: Option<String>,
+}
+
+// What the frontend gets; paths that no longer exist are left out
+#[derive(Serialize, Debug, Clone)]
+pub struct LastSession {
+  pub last_document: Option<String>,
+  pub last_save_path: Option<String>,
+}
+
+fn store_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(SESSION_FILE))
+}
+
+impl Session {
+  // Load the session from disk; a missing or corrupt file is an empty one
+  pub fn load() -> Session {
+    let Some(path) = store_path() else {
+      return Session::default();
+    };
+    match std::fs::read_to_string(&path) {
+      Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Ignoring invalid session file '{}': {}", path.display(), e);
+        Session::default()
+      }),
+      Err(_) => Session::default(),
+    }
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    let path = store_path().ok_or("Could not determine the app data directory")?;
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(self)
+      .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    std::fs::write(&path, json)
+      .map_err(|e| format!("Failed to write session file '{}': {}", path.display(), e))
+  }
+
+  // Record the board now open, writing the session if it changed
+  pub fn opened(&mut self, path: &Path) {
+    let path = path.to_string_lossy().to_string();
+    if self.last_document.as_deref() != Some(path.as_str()) {
+      self.last_document = Some(path);
+      self.flush();
+    }
+  }
+
+  pub fn saved(&mut self, path: &Path) {
+    let path = path.to_string_lossy().to_string();
+    if self.last_save_path.as_deref() != Some(path.as_str()) || self.last_document.as_deref() != Some(path.as_str()) {
+      self.last_save_path = Some(path.clone());
+      self.last_document = Some(path);
+      self.flush();
+    }
+  }
+
+  // Forget the last board, along with the recent files
+  pub fn clear(&mut self) {
+    *self = Session::default();
+    self.flush();
+  }
+
+  fn flush(&self) {
+    if let Err(e) = self.save() {
+      log::warn!("{}", e);
+    }
+  }
+
+  // Folder of the last save, for starting save dialogs there
+  pub fn save_directory(&self) -> Option<PathBuf> {
+    self.last_save_path.as_deref()
+      .and_then(|path| Path::new(path).parent())
+      .filter(|dir| dir.is_dir())
+      .map(Path::to_path_buf)
+  }
+
+  pub fn describe(&self) -> LastSession {
+    let existing = |path: &Option<String>| path.clone().filter(|p| Path::new(p).is_file());
+    LastSession {
+      last_document: existing(&self.last_document),
+      last_save_path: existing(&self.last_save_path),
+    }
+  }
+}
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+// Matches the bundle identifier in tauri.conf.json so the backend and the
+// Tauri path resolver agree on where app files live.
+pub const APP_IDENTIFIER: &str = "com.example.fim";
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct AppSettings {
+  // Overwrite recovery files and temporary exports before deleting them
+  #[serde(rename = "secureCleanup")]
+  pub secure_cleanup: bool,
+  // Record every open/save/export/autosave in the local audit log
+  #[serde(rename = "auditLog")]
+  pub audit_log: bool,
+  // Global shortcut that opens the quick-capture window; empty disables it
+  #[serde(rename = "quickCaptureShortcut")]
+  pub quick_capture_shortcut: String,
+  // Board that captured notes are appended to (defaults to Documents/Inbox.fim)
+  #[serde(rename = "inboxPath")]
+  pub inbox_path: Option<String>,
+  // Write a `.fim.txt` file with the board's text next to saved boards so
+  // Spotlight / Windows Search can find them by content
+  #[serde(rename = "searchSidecar")]
+  pub search_sidecar: bool,
+  // Drop ink stroke points that don't change the drawn line (within half a
+  // pixel) when saving
+  #[serde(rename = "simplifyStrokes")]
+  pub simplify_strokes: bool,
+  // Copy the font files the board's styles use into saved .fim files, where
+  // their license allows; see fonts.rs
+  #[serde(rename = "embedFonts")]
+  pub embed_fonts: bool,
+  // Keep local statistics of time spent, notes created and saves per board
+  #[serde(rename = "usageStats")]
+  pub usage_stats: bool,
+  // Where `translate_notes` sends text; see language.rs
+  pub translation: TranslationSettings,
+  // Where `summarize_notes` sends the selected notes; see summarize.rs
+  pub summarization: SummarizationSettings,
+  // Periodic copies of open boards to a backup folder; see backup.rs
+  pub backup: BackupSettings,
+  // "auto", "syncSafe" or "direct": how boards are written; see sync_write.rs
+  #[serde(rename = "writeMode")]
+  pub write_mode: String,
+  // Replaced versions kept next to a saved board as `name.fim.bak1` (newest)
+  // to `.bakN`; 0 keeps none
+  #[serde(rename = "keepVersions")]
+  pub keep_versions: usize,
+  // Snapshots kept inside a board, oldest dropped first; 0 for no limit. See
+  // snapshots.rs
+  #[serde(rename = "keepSnapshots")]
+  pub keep_snapshots: usize,
+  // Folders autosave writes recovery files to, first usable one wins, and
+  // that startup scans for them; see recovery.rs
+  #[serde(rename = "recoveryLocations")]
+  pub recovery_locations: Vec<String>,
+  // Where `publish_board` sends boards; tokens are kept in the keychain, see
+  // publish.rs
+  pub publish: PublishSettings,
+  // Language of exported text, e.g. "de" or "en-US"; empty for the system's.
+  // See locale.rs
+  #[serde(rename = "exportLocale")]
+  pub export_locale: String,
+  // "local", "UTC" or an offset such as "+02:00" for dates in exports
+  #[serde(rename = "exportTimezone")]
+  pub export_timezone: String,
+  // Author stamped on boards saved without one; empty for the account name
+  #[serde(rename = "authorName")]
+  pub author_name: String,
+  // Opened .fim boards whose images add up to more than this many MiB leave
+  // them in the file until shown; 0 always loads them. See lazy_media.rs
+  #[serde(rename = "lazyMediaMb")]
+  pub lazy_media_mb: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TranslationSettings {
+  // "none", "command" or "http"
+  pub provider: String,
+  // Local translation program for "command", by name: "translate-shell".
+  // The program and its arguments are fixed by the backend, since the
+  // webview can change settings; see language.rs
+  pub engine: Option<String>,
+  // LibreTranslate-compatible endpoint (POST JSON `{ q, source, target }`)
+  pub endpoint: Option<String>,
+  #[serde(rename = "apiKey")]
+  pub api_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SummarizationSettings {
+  // "none", "command", "llama" or "http"
+  pub provider: String,
+  // Local program for "command", by name: "llm" or "ollama" (which needs
+  // `model`). Fixed by the backend like translation engines; see summarize.rs
+  pub engine: Option<String>,
+  // llama.cpp server (e.g. http://127.0.0.1:8080), or an OpenAI-compatible
+  // chat completions URL for "http"
+  pub endpoint: Option<String>,
+  #[serde(rename = "apiKey")]
+  pub api_key: Option<String>,
+  // Model name for "http" and the "command" engines
+  pub model: Option<String>,
+  // What to ask for instead of the default few-sentence summary
+  pub instructions: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BackupSettings {
+  pub enabled: bool,
+  // Defaults to `backups` in the app data folder
+  pub directory: Option<String>,
+  #[serde(rename = "intervalMinutes")]
+  pub interval_minutes: u32,
+  // Backups kept per board; older ones are deleted
+  pub keep: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PublishSettings {
+  // Confluence site, e.g. https://example.atlassian.net
+  #[serde(rename = "confluenceUrl")]
+  pub confluence_url: Option<String>,
+  // Account email for Confluence Cloud; without it the token is sent as a
+  // personal access token (Server / Data Center)
+  #[serde(rename = "confluenceUser")]
+  pub confluence_user: Option<String>,
+}
+
+impl Default for BackupSettings {
+  fn default() -> Self {
+    BackupSettings {
+      enabled: false,
+      directory: None,
+      interval_minutes: 30,
+      keep: 20,
+    }
+  }
+}
+
+impl Default for AppSettings {
+  fn default() -> Self {
+    AppSettings {
+      secure_cleanup: false,
+      audit_log: false,
+      quick_capture_shortcut: crate::capture::DEFAULT_SHORTCUT.to_string(),
+      inbox_path: None,
+      search_sidecar: false,
+      simplify_strokes: false,
+      embed_fonts: false,
+      usage_stats: false,
+      translation: TranslationSettings::default(),
+      summarization: SummarizationSettings::default(),
+      backup: BackupSettings::default(),
+      write_mode: "auto".to_string(),
+      keep_versions: 0,
+      keep_snapshots: 20,
+      recovery_locations: crate::recovery::default_locations(),
+      publish: PublishSettings::default(),
+      export_locale: String::new(),
+      export_timezone: "local".to_string(),
+      author_name: String::new(),
+      lazy_media_mb: 64,
+    }
+  }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+  dirs::config_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+  dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+pub fn settings_path() -> Option<PathBuf> {
+  config_dir().map(|dir| dir.join(SETTINGS_FILE))
+}
+
+// Load settings from disk, falling back to defaults when the file is missing
+// or unreadable so a corrupt settings file never prevents startup.
+pub fn load_settings() -> AppSettings {
+  let Some(path) = settings_path() else {
+    return AppSettings::default();
+  };
+
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+      log::warn!("Ignoring invalid settings file '{}': {}", path.display(), e);
+      AppSettings::default()
+    }),
+    Err(_) => AppSettings::default(),
+  }
+}
+
+// `current` with the fields given in `changes` replaced; nested objects such
+// as `backup` are merged field by field too, so leaving a field out keeps it.
+pub fn merge_settings(current: &AppSettings, changes: serde_json::Value) -> Result<AppSettings, String> {
+  if !changes.is_object() {
+    return Err("Invalid settings: expected an object of fields to change".to_string());
+  }
+  let mut merged = serde_json::to_value(current)
+    .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+  merge_json(&mut merged, changes);
+  serde_json::from_value(merged).map_err(|e| format!("Invalid settings: {}", e))
+}
+
+fn merge_json(target: &mut serde_json::Value, changes: serde_json::Value) {
+  match (target, changes) {
+    (serde_json::Value::Object(target), serde_json::Value::Object(changes)) => {
+      for (key, value) in changes {
+        match target.get_mut(&key) {
+          Some(existing) => merge_json(existing, value),
+          None => {
+            target.insert(key, value);
+          },
+        }
+      }
+    },
+    (target, value) => *target = value,
+  }
+}
+
+pub fn save_settings(settings: &AppSettings) -> Result<(), String> {
+  let path = settings_path().ok_or("Could not determine the configuration directory")?;
+
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|e| format!("Failed to create config directory '{}': {}", parent.display(), e))?;
+  }
+
+  let json = serde_json::to_string_pretty(settings)
+    .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+  std::fs::write(&path, json)
+    .map_err(|e| format!("Failed to write settings file '{}': {}", path.display(), e))
+}
@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::{layers, model};
+
+// Background shape geometry. `kind` defaults to "rect" (a rounded rectangle
+// filling the frame) so boards from before shape kinds load unchanged:
+//   "ellipse" – ellipse inscribed in the frame
+//   "line"    – segment between two `points`, or the frame's diagonal
+//   "polygon" – closed polygon through `points`
+// Points are relative to the frame origin, so moving the frame moves the shape.
+// Magnetic shapes carry the notes inside them along when they move.
+pub const SHAPE_KINDS: [&str; 4] = ["rect", "ellipse", "line", "polygon"];
+
+pub fn kind(shape: &model::BackgroundShape) -> &str {
+  shape.kind.as_deref().unwrap_or("rect")
+}
+
+// Shape points in board coordinates
+pub fn absolute_points(shape: &model::BackgroundShape) -> Vec<model::Point> {
+  let f = &shape.frame;
+  match shape.points.as_deref() {
+    Some(points) if !points.is_empty() => points.iter()
+      .map(|p| model::Point { x: f.x + p.x, y: f.y + p.y })
+      .collect(),
+    _ if kind(shape) == "line" => vec![
+      model::Point { x: f.x, y: f.y },
+      model::Point { x: f.x + f.w, y: f.y + f.h },
+    ],
+    _ => Vec::new(),
+  }
+}
+
+fn polygon_contains(points: &[model::Point], p: &model::Point) -> bool {
+  // Even-odd ray casting
+  let mut inside = false;
+  let mut j = points.len().wrapping_sub(1);
+  for (i, a) in points.iter().enumerate() {
+    let b = &points[j];
+    if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+      inside = !inside;
+    }
+    j = i;
+  }
+  inside
+}
+
+// Whether a board point lies inside the shape. Lines enclose nothing.
+pub fn contains_point(shape: &model::BackgroundShape, p: &model::Point) -> bool {
+  let f = &shape.frame;
+  match kind(shape) {
+    "ellipse" => {
+      let (rx, ry) = (f.w / 2.0, f.h / 2.0);
+      if rx <= 0.0 || ry <= 0.0 {
+        return false;
+      }
+      let dx = (p.x - (f.x + rx)) / rx;
+      let dy = (p.y - (f.y + ry)) / ry;
+      dx * dx + dy * dy <= 1.0
+    },
+    "polygon" => {
+      let points = absolute_points(shape);
+      points.len() >= 3 && polygon_contains(&points, p)
+    },
+    "line" => false,
+    _ => p.x >= f.x && p.x <= f.x + f.w && p.y >= f.y && p.y <= f.y + f.h,
+  }
+}
+
+// A note belongs to a shape when its center lies inside it
+pub fn contains_note(shape: &model::BackgroundShape, note: &model::Note) -> bool {
+  let center = model::Point { x: note.frame.x + note.frame.w / 2.0, y: note.frame.y + note.frame.h / 2.0 };
+  contains_point(shape, &center)
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ShapeMembership {
+  pub shape_id: model::ID,
+  pub magnetic: bool,
+  pub note_ids: Vec<model::ID>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FrameUpdate {
+  pub id: model::ID,
+  pub frame: model::Rect,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ShapeMove {
+  pub shape: FrameUpdate,
+  // Notes carried along by a magnetic shape
+  pub notes: Vec<FrameUpdate>,
+}
+
+// The notes inside each shape, in document order. A note inside nested
+// shapes belongs to all of them.
+pub fn membership(doc: &model::BoardDocument) -> Vec<ShapeMembership> {
+  doc.shapes.iter()
+    .map(|shape| ShapeMembership {
+      shape_id: shape.id.clone(),
+      magnetic: shape.magnetic.unwrap_or(false),
+      note_ids: doc.notes.iter().filter(|n| contains_note(shape, n)).map(|n| n.id.clone()).collect(),
+    })
+    .collect()
+}
+
+// New frames for moving a shape by (dx, dy). A magnetic shape takes the
+// notes inside it at its old position along, with their whole stacks so
+// stack layout survives; locked notes stay put.
+pub fn move_shape(doc: &model::BoardDocument, shape_id: &str, dx: f64, dy: f64) -> Result<ShapeMove, String> {
+  let shape = doc.shapes.iter().find(|s| s.id == shape_id)
+    .ok_or_else(|| format!("Shape '{}' not found", shape_id))?;
+  let locked = layers::locked_ids(doc);
+  if locked.contains(&shape.id) {
+    return Err(format!("Shape '{}' is locked", shape_id));
+  }
+
+  let mut moved = HashSet::new();
+  if shape.magnetic.unwrap_or(false) {
+    for note in doc.notes.iter().filter(|n| contains_note(shape, n)) {
+      moved.insert(note.id.as_str());
+      let stack = note.stack_id.as_deref().and_then(|id| doc.stacks.iter().find(|s| s.id == id));
+      moved.extend(stack.iter().flat_map(|s| s.note_ids.iter().map(String::as_str)));
+    }
+  }
+
+  let notes = doc.notes.iter()
+    .filter(|n| moved.contains(n.id.as_str()) && !locked.contains(&n.id))
+    .map(|n| FrameUpdate {
+      id: n.id.clone(),
+      frame: model::Rect { x: n.frame.x + dx, y: n.frame.y + dy, ..n.frame.clone() },
+    })
+    .collect();
+  Ok(ShapeMove {
+    shape: FrameUpdate {
+      id: shape.id.clone(),
+      frame: model::Rect { x: shape.frame.x + dx, y: shape.frame.y + dy, ..shape.frame.clone() },
+    },
+    notes,
+  })
+}
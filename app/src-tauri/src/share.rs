@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+// Exports handed to the share sheet live here until the next share, since the
+// receiving app (Mail, AirDrop, ...) reads the file after our command returns.
+fn share_dir() -> PathBuf {
+  std::env::temp_dir().join("fim-share")
+}
+
+// Create a fresh share location for `file_name`, cleaning up earlier shares.
+pub fn prepare_share_path(file_name: &str, secure_cleanup: bool) -> Result<PathBuf, String> {
+  let dir = share_dir();
+  if let Ok(entries) = std::fs::read_dir(&dir) {
+    for entry in entries.flatten() {
+      if entry.path().is_file() {
+        if let Err(e) = crate::security::remove_file(&entry.path(), secure_cleanup) {
+          log::warn!("{}", e);
+        }
+      }
+    }
+  }
+
+  std::fs::create_dir_all(&dir)
+    .map_err(|e| format!("Failed to create share directory '{}': {}", dir.display(), e))?;
+  Ok(dir.join(file_name))
+}
+
+// Present the native share sheet for a file. Returns false when the platform
+// has no share sheet we can drive and the file was revealed instead.
+#[cfg(target_os = "macos")]
+pub fn present_share_sheet(app: &tauri::AppHandle, path: &Path) -> Result<bool, String> {
+  use tauri::Manager;
+
+  let app_handle = app.clone();
+  let path = path.to_path_buf();
+  app.run_on_main_thread(move || {
+    use objc2::AnyThread;
+    use objc2::runtime::AnyObject;
+    use objc2_app_kit::{NSSharingServicePicker, NSView};
+    use objc2_foundation::{NSArray, NSRectEdge, NSURL};
+
+    let Some(window) = app_handle.get_webview_window("main") else {
+      log::warn!("No window to anchor the share sheet to");
+      return;
+    };
+    let Ok(ns_view) = window.ns_view() else {
+      return;
+    };
+    let Some(url) = NSURL::from_file_path(&path) else {
+      log::warn!("Cannot share '{}': not a valid file URL", path.display());
+      return;
+    };
+
+    // SAFETY: `ns_view` is the window's content view and we are on the main thread
+    let view: &NSView = unsafe { &*(ns_view as *const NSView) };
+    let item: &AnyObject = &url;
+    let items = NSArray::from_slice(&[item]);
+    unsafe {
+      let picker = NSSharingServicePicker::initWithItems(NSSharingServicePicker::alloc(), &items);
+      picker.showRelativeToRect_ofView_preferredEdge(view.bounds(), view, NSRectEdge::MinY);
+    }
+  })
+  .map_err(|e| format!("Failed to show share sheet: {}", e))?;
+
+  Ok(true)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn present_share_sheet(_app: &tauri::AppHandle, path: &Path) -> Result<bool, String> {
+  reveal_in_file_manager(path)?;
+  Ok(false)
+}
+
+#[cfg(not(target_os = "macos"))]
+fn reveal_in_file_manager(path: &Path) -> Result<(), String> {
+  #[cfg(windows)]
+  let result = std::process::Command::new("explorer")
+    .arg(format!("/select,{}", path.display()))
+    .spawn();
+
+  #[cfg(not(windows))]
+  let result = std::process::Command::new("xdg-open")
+    .arg(path.parent().unwrap_or(path))
+    .spawn();
+
+  result
+    .map(|_| ())
+    .map_err(|e| format!("Failed to reveal '{}': {}", path.display(), e))
+}
+
+// Open a file with the app registered for its type, such as the default
+// mail client for .eml files
+pub fn open_with_default_app(path: &Path) -> Result<(), String> {
+  #[cfg(target_os = "macos")]
+  let result = std::process::Command::new("open").arg(path).spawn();
+
+  #[cfg(windows)]
+  let result = std::process::Command::new("explorer").arg(path).spawn();
+
+  #[cfg(not(any(target_os = "macos", windows)))]
+  let result = std::process::Command::new("xdg-open").arg(path).spawn();
+
+  result
+    .map(|_| ())
+    .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))
+}
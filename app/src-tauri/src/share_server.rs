@@ -0,0 +1,464 @@
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{confidential, layers, model, raster, render, static_html};
+
+// Following a board from other machines in the room, without sending files
+// around. While sharing, the board of the window that started it is served
+// over plain HTTP on every interface: the same page as the web page export
+// (pan and zoom, links), and the board as SVG. The page keeps a WebSocket
+// open and swaps in the board each time the frontend reports a change, so
+// viewers see edits as they happen. Viewing only: nothing viewers send is
+// taken in. Locked notes and hidden layers are left out as in exports, and
+// every address starts with a random token, so only people given the link
+// can look.
+
+pub const DEFAULT_PORT: u16 = 4820;
+const MAX_VIEWERS: usize = 64;
+// Connections handled at once, viewers included; more are closed right away
+// so a flood of idle connections can't pile up threads
+const MAX_CONNECTIONS: usize = MAX_VIEWERS + 16;
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+// Viewers only send close and ping frames
+const MAX_FRAME_BYTES: u64 = 4 * 1024;
+// For the whole request, so a connection without the token is gone by then
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// A viewer that takes longer to take an update is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+const RECONNECT_DELAY_MS: u32 = 2000;
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+// Added to the exported page: replaces the board with each update and
+// reconnects when the connection drops
+const LIVE_SCRIPT: &str = "\
+(function () {
+  var board = document.getElementById('board');
+  var status = document.getElementById('live');
+  function connect() {
+    var url = new URL('live', location.href);
+    url.protocol = url.protocol.replace('http', 'ws');
+    var socket = new WebSocket(url);
+    socket.onopen = function () { status.textContent = 'Live'; };
+    socket.onmessage = function (e) {
+      var update = JSON.parse(e.data);
+      board.style.width = update.width + 'px';
+      board.style.height = update.height + 'px';
+      board.innerHTML = update.board;
+      document.body.style.background = update.background;
+      document.title = update.title;
+    };
+    socket.onclose = function () {
+      status.textContent = 'Reconnecting…';
+      setTimeout(connect, RECONNECT_DELAY);
+    };
+  }
+  connect();
+})();
+";
+const LIVE_STYLE: &str = "position: fixed; left: 8px; bottom: 8px; font: 12px sans-serif; color: #666; background: rgba(255, 255, 255, 0.8); padding: 2px 6px; border-radius: 4px;";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ShareServerInfo {
+  // The address to give viewers, on this machine's LAN address
+  pub url: String,
+  pub port: u16,
+  // The window whose board is shared
+  pub window: String,
+  // Pages following the board right now
+  pub viewers: usize,
+}
+
+// The board as last reported, ready to serve
+struct Board {
+  page: String,
+  // None for an empty board
+  svg: Option<String>,
+  // The WebSocket message with the new board
+  update: String,
+}
+
+struct Viewer {
+  id: u64,
+  stream: Arc<Mutex<TcpStream>>,
+}
+
+struct Shared {
+  token: String,
+  board: Mutex<Board>,
+  viewers: Mutex<Vec<Viewer>>,
+  next_viewer: AtomicU64,
+  // Open connections, for MAX_CONNECTIONS
+  connections: AtomicUsize,
+  // Held while sending an update, so viewers get updates in order
+  sending: Mutex<()>,
+  stopped: AtomicBool,
+}
+
+// A running server; clones are handles to the same one
+#[derive(Clone)]
+pub struct ShareServer {
+  shared: Arc<Shared>,
+  url: String,
+  port: u16,
+  pub window: String,
+}
+
+impl std::fmt::Debug for ShareServer {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ShareServer").field("port", &self.port).field("window", &self.window).finish()
+  }
+}
+
+fn render_board(doc: &model::BoardDocument) -> Board {
+  let doc = layers::filter_layers(&confidential::without_locked(doc), &layers::visible_layers(doc));
+  let extra = format!(
+    "<div id=\"live\" style=\"{}\">Connecting…</div>\n<script>\n{}</script>\n",
+    LIVE_STYLE, LIVE_SCRIPT.replace("RECONNECT_DELAY", &RECONNECT_DELAY_MS.to_string())
+  );
+  let (board, region) = static_html::board_html(&doc);
+  let update = serde_json::json!({
+    "title": static_html::page_title(&doc, None),
+    "background": static_html::background(&doc),
+    "width": region.w,
+    "height": region.h,
+    "board": board,
+  });
+  Board {
+    page: static_html::page(&doc, None, &extra),
+    svg: raster::plan(&doc, &raster::RasterOptions::default()).ok().map(|plan| render::to_svg(&doc, &plan.region)),
+    update: update.to_string(),
+  }
+}
+
+// Random and long enough that the link can't be guessed
+fn new_token() -> String {
+  let mut bytes = [0u8; 16];
+  OsRng.fill_bytes(&mut bytes);
+  base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// The address other machines reach this one at: the one the system would
+// send from to leave the network (connecting a UDP socket sends nothing),
+// else loopback
+fn lan_address() -> String {
+  UdpSocket::bind("0.0.0.0:0")
+    .and_then(|socket| {
+      socket.connect("192.0.2.1:80")?;
+      socket.local_addr()
+    })
+    .map(|address| address.ip())
+    .ok()
+    .filter(|ip| !ip.is_unspecified())
+    .map(|ip| ip.to_string())
+    .unwrap_or_else(|| "127.0.0.1".to_string())
+}
+
+fn write_frame(stream: &Mutex<TcpStream>, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+  let mut frame = vec![0x80 | opcode];
+  match payload.len() {
+    len if len < 126 => frame.push(len as u8),
+    len if len <= u16::MAX as usize => {
+      frame.push(126);
+      frame.extend_from_slice(&(len as u16).to_be_bytes());
+    },
+    len => {
+      frame.push(127);
+      frame.extend_from_slice(&(len as u64).to_be_bytes());
+    },
+  }
+  frame.extend_from_slice(payload);
+  let mut stream = stream.lock().map_err(|_| std::io::Error::other("Viewer connection lock poisoned"))?;
+  stream.write_all(&frame)
+}
+
+// Answer the viewer's frames until it leaves or the connection drops
+fn read_frames(reader: &mut TcpStream, writer: &Mutex<TcpStream>) -> std::io::Result<()> {
+  loop {
+    let mut head = [0u8; 2];
+    reader.read_exact(&mut head)?;
+    let opcode = head[0] & 0x0F;
+    let mut len = (head[1] & 0x7F) as u64;
+    if len == 126 {
+      let mut extended = [0u8; 2];
+      reader.read_exact(&mut extended)?;
+      len = u16::from_be_bytes(extended) as u64;
+    } else if len == 127 {
+      let mut extended = [0u8; 8];
+      reader.read_exact(&mut extended)?;
+      len = u64::from_be_bytes(extended);
+    }
+    // Browsers always mask what they send
+    if len > MAX_FRAME_BYTES || head[1] & 0x80 == 0 {
+      return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Unexpected frame from viewer"));
+    }
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask)?;
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+      *byte ^= mask[i % 4];
+    }
+    match opcode {
+      OP_CLOSE => {
+        let _ = write_frame(writer, OP_CLOSE, &payload[..payload.len().min(2)]);
+        return Ok(());
+      },
+      OP_PING => write_frame(writer, OP_PONG, &payload)?,
+      _ => {},
+    }
+  }
+}
+
+struct Request {
+  method: String,
+  path: String,
+  // Lowercase names
+  headers: HashMap<String, String>,
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request, String> {
+  let deadline = Instant::now() + REQUEST_TIMEOUT;
+  let mut data = Vec::new();
+  let mut buffer = [0u8; 1024];
+  while !data.windows(4).any(|window| window == b"\r\n\r\n") {
+    if data.len() > MAX_REQUEST_BYTES {
+      return Err("Request too large".to_string());
+    }
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+      return Err("Request took too long".to_string());
+    }
+    stream.set_read_timeout(Some(remaining)).map_err(|e| e.to_string())?;
+    let read = stream.read(&mut buffer).map_err(|e| format!("Failed to read request: {}", e))?;
+    if read == 0 {
+      return Err("Connection closed before the request ended".to_string());
+    }
+    data.extend_from_slice(&buffer[..read]);
+  }
+  let text = String::from_utf8_lossy(&data);
+  let mut lines = text.split("\r\n");
+  let mut request_line = lines.next().unwrap_or("").split_whitespace();
+  let (method, path) = (request_line.next().unwrap_or("").to_string(), request_line.next().unwrap_or("").to_string());
+  let headers = lines
+    .take_while(|line| !line.is_empty())
+    .filter_map(|line| line.split_once(':'))
+    .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+    .collect();
+  Ok(Request { method, path, headers })
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8], extra_headers: &str) -> Result<(), String> {
+  // Links on the board lead elsewhere; the token mustn't go with them
+  let head = format!(
+    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nReferrer-Policy: no-referrer\r\nX-Content-Type-Options: nosniff\r\n{}Connection: close\r\n\r\n",
+    status, content_type, body.len(), extra_headers
+  );
+  stream.write_all(head.as_bytes())
+    .and_then(|_| stream.write_all(body))
+    .map_err(|e| format!("Failed to send response: {}", e))
+}
+
+fn not_found(stream: &mut TcpStream) -> Result<(), String> {
+  respond(stream, "404 Not Found", "text/plain; charset=utf-8", b"Not found", "")
+}
+
+// Take the connection as a viewer until it leaves
+fn serve_viewer(mut stream: TcpStream, request: &Request, shared: &Shared) -> Result<(), String> {
+  let key = request.headers.get("sec-websocket-key").ok_or("Missing WebSocket key")?;
+  let full = shared.viewers.lock().map(|viewers| viewers.len() >= MAX_VIEWERS).unwrap_or(true);
+  if full {
+    return respond(&mut stream, "503 Service Unavailable", "text/plain; charset=utf-8", b"Too many viewers", "");
+  }
+  let accept = base64::engine::general_purpose::STANDARD.encode(Sha1::digest(format!("{}{}", key, WEBSOCKET_GUID)));
+  let handshake = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept);
+  stream.write_all(handshake.as_bytes()).map_err(|e| format!("Failed to accept viewer: {}", e))?;
+  stream.set_read_timeout(None).map_err(|e| e.to_string())?;
+  let writer = Arc::new(Mutex::new(stream.try_clone().map_err(|e| e.to_string())?));
+  let id = shared.next_viewer.fetch_add(1, Ordering::Relaxed);
+
+  // Under the board's lock, so no update can come between the board sent
+  // here and the viewer joining
+  {
+    let board = shared.board.lock().map_err(|_| "Shared board lock poisoned")?;
+    write_frame(&writer, OP_TEXT, board.update.as_bytes()).map_err(|e| format!("Failed to send board: {}", e))?;
+    shared.viewers.lock().map_err(|_| "Viewer list lock poisoned")?.push(Viewer { id, stream: writer.clone() });
+  }
+  let result = read_frames(&mut stream, &writer);
+  if let Ok(mut viewers) = shared.viewers.lock() {
+    viewers.retain(|viewer| viewer.id != id);
+  }
+  let _ = stream.shutdown(Shutdown::Both);
+  result.map_err(|e| format!("Viewer left: {}", e))
+}
+
+fn handle(mut stream: TcpStream, shared: &Shared) -> Result<(), String> {
+  // Accepted connections may inherit the listener's non-blocking mode
+  stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+  stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|e| e.to_string())?;
+  let request = read_request(&mut stream)?;
+  let path = request.path.split(['?', '#']).next().unwrap_or("");
+  let Some(rest) = path.strip_prefix('/').and_then(|path| path.strip_prefix(shared.token.as_str())) else {
+    return not_found(&mut stream);
+  };
+  if request.method != "GET" {
+    return respond(&mut stream, "405 Method Not Allowed", "text/plain; charset=utf-8", b"Viewing only", "Allow: GET\r\n");
+  }
+  let upgrade = request.headers.get("upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+  match rest {
+    // Relative addresses on the page need the trailing slash
+    "" => respond(&mut stream, "302 Found", "text/plain; charset=utf-8", b"", &format!("Location: /{}/\r\n", shared.token)),
+    "/" => {
+      let page = shared.board.lock().map(|board| board.page.clone()).map_err(|_| "Shared board lock poisoned")?;
+      respond(&mut stream, "200 OK", "text/html; charset=utf-8", page.as_bytes(), "")
+    },
+    "/board.svg" => match shared.board.lock().map_err(|_| "Shared board lock poisoned")?.svg.clone() {
+      Some(svg) => respond(&mut stream, "200 OK", "image/svg+xml", svg.as_bytes(), ""),
+      None => not_found(&mut stream),
+    },
+    "/live" if upgrade => serve_viewer(stream, &request, shared),
+    _ => not_found(&mut stream),
+  }
+}
+
+fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+  while !shared.stopped.load(Ordering::Relaxed) {
+    match listener.accept() {
+      Ok((stream, _)) => {
+        if shared.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+          shared.connections.fetch_sub(1, Ordering::SeqCst);
+          let _ = stream.shutdown(Shutdown::Both);
+          continue;
+        }
+        let handling = shared.clone();
+        let spawned = std::thread::Builder::new().spawn(move || {
+          if let Err(e) = handle(stream, &handling) {
+            log::debug!("Share server connection: {}", e);
+          }
+          handling.connections.fetch_sub(1, Ordering::SeqCst);
+        });
+        if let Err(e) = spawned {
+          shared.connections.fetch_sub(1, Ordering::SeqCst);
+          log::warn!("Share server failed to handle a connection: {}", e);
+        }
+      },
+      Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(ACCEPT_POLL),
+      Err(e) => {
+        log::warn!("Share server failed to accept a connection: {}", e);
+        std::thread::sleep(ACCEPT_POLL);
+      },
+    }
+  }
+}
+
+// Start serving `doc` on `port` (0 for any free one) for window `window`
+pub fn start(doc: &model::BoardDocument, port: u16, window: &str) -> Result<ShareServer, String> {
+  if port != 0 && port < 1024 {
+    return Err(format!("Invalid port {}: must be 1024 or above, or 0 for any free port", port));
+  }
+  let listener = TcpListener::bind(("0.0.0.0", port))
+    .map_err(|e| format!("Cannot share on port {}: {}", port, e))?;
+  let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+  listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+  let shared = Arc::new(Shared {
+    token: new_token(),
+    board: Mutex::new(render_board(doc)),
+    viewers: Mutex::new(Vec::new()),
+    next_viewer: AtomicU64::new(0),
+    connections: AtomicUsize::new(0),
+    sending: Mutex::new(()),
+    stopped: AtomicBool::new(false),
+  });
+  let url = format!("http://{}:{}/{}/", lan_address(), port, shared.token);
+  let accepting = shared.clone();
+  std::thread::Builder::new()
+    .name("share-server".to_string())
+    .spawn(move || accept_loop(listener, accepting))
+    .map_err(|e| format!("Failed to start the share server: {}", e))?;
+  log::info!("Sharing board of window '{}' on port {}", window, port);
+  Ok(ShareServer { shared, url, port, window: window.to_string() })
+}
+
+impl ShareServer {
+  pub fn info(&self) -> ShareServerInfo {
+    ShareServerInfo {
+      url: self.url.clone(),
+      port: self.port,
+      window: self.window.clone(),
+      viewers: self.shared.viewers.lock().map(|viewers| viewers.len()).unwrap_or(0),
+    }
+  }
+
+  // Serve `doc` from now on and send it to every viewer; viewers that don't
+  // take it are dropped. Sending happens outside the board and viewer locks
+  // and to all viewers at once, so a slow viewer holds up neither new viewers
+  // nor the others, and an update takes at most WRITE_TIMEOUT.
+  pub fn update(&self, doc: &model::BoardDocument) -> Result<(), String> {
+    let rendered = render_board(doc);
+    let _sending = self.shared.sending.lock().map_err(|_| "Share server lock poisoned")?;
+    // Swapped together with taking the viewer list, so a viewer joining now
+    // gets either this board or the previous one and then this one
+    let (update, recipients) = {
+      let mut board = self.shared.board.lock().map_err(|_| "Shared board lock poisoned")?;
+      *board = rendered;
+      let viewers = self.shared.viewers.lock().map_err(|_| "Viewer list lock poisoned")?;
+      let recipients: Vec<(u64, Arc<Mutex<TcpStream>>)> = viewers.iter().map(|viewer| (viewer.id, viewer.stream.clone())).collect();
+      (board.update.clone(), recipients)
+    };
+
+    let failed: Vec<u64> = std::thread::scope(|scope| {
+      let sends: Vec<_> = recipients.iter()
+        .map(|(id, stream)| {
+          let update = update.as_bytes();
+          (*id, scope.spawn(move || write_frame(stream, OP_TEXT, update).is_ok()))
+        })
+        .collect();
+      sends.into_iter()
+        .filter_map(|(id, send)| (!matches!(send.join(), Ok(true))).then_some(id))
+        .collect()
+    });
+    if failed.is_empty() {
+      return Ok(());
+    }
+    let mut viewers = self.shared.viewers.lock().map_err(|_| "Viewer list lock poisoned")?;
+    viewers.retain(|viewer| {
+      if !failed.contains(&viewer.id) {
+        return true;
+      }
+      if let Ok(stream) = viewer.stream.lock() {
+        let _ = stream.shutdown(Shutdown::Both);
+      }
+      false
+    });
+    Ok(())
+  }
+
+  // Stop accepting and close every viewer's connection
+  pub fn stop(&self) {
+    self.shared.stopped.store(true, Ordering::Relaxed);
+    if let Ok(mut viewers) = self.shared.viewers.lock() {
+      for viewer in viewers.drain(..) {
+        let _ = write_frame(&viewer.stream, OP_CLOSE, &1001u16.to_be_bytes());
+        if let Ok(stream) = viewer.stream.lock() {
+          let _ = stream.shutdown(Shutdown::Both);
+        }
+      }
+    }
+    log::info!("Stopped sharing on port {}", self.port);
+  }
+}
@@ -0,0 +1,99 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::{library, model, text_stats};
+
+// "You already have a note like this": fuzzy matching of a note being typed
+// against the board's notes and, optionally, the titles and tags of boards in
+// the library index. Texts are compared by their character trigrams (Dice
+// coefficient), which tolerates typos, reordering and different endings.
+
+// Below this, matches are noise
+const DEFAULT_MIN_SCORE: f64 = 0.4;
+const DEFAULT_LIMIT: usize = 5;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SimilarNote {
+  pub id: model::ID,
+  // First line of the note
+  pub text: String,
+  // 0..1, 1 for the same text
+  pub score: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SimilarBoard {
+  pub path: String,
+  pub title: String,
+  pub score: f64,
+}
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SimilarSuggestions {
+  pub notes: Vec<SimilarNote>,
+  pub boards: Vec<SimilarBoard>,
+}
+
+// Trigrams of the lowercased words, each padded so word starts and ends count
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+  let mut grams = HashSet::new();
+  for word in text_stats::words(text) {
+    let chars: Vec<char> = format!("  {} ", word.to_lowercase()).chars().collect();
+    grams.extend(chars.windows(3).map(|w| [w[0], w[1], w[2]]));
+  }
+  grams
+}
+
+fn dice(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+  if a.is_empty() || b.is_empty() {
+    return 0.0;
+  }
+  2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+fn first_line(text: &str) -> String {
+  text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default().to_string()
+}
+
+// Best matches first, at most `limit` of each kind. `exclude` leaves out the
+// note being edited.
+pub fn suggest_similar(
+  doc: &model::BoardDocument,
+  text: &str,
+  limit: Option<usize>,
+  min_score: Option<f64>,
+  exclude: Option<&str>,
+  library_boards: &[library::LibraryBoard],
+) -> SimilarSuggestions {
+  let query = trigrams(text);
+  if query.is_empty() {
+    return SimilarSuggestions::default();
+  }
+  let limit = limit.unwrap_or(DEFAULT_LIMIT);
+  let min_score = min_score.unwrap_or(DEFAULT_MIN_SCORE);
+
+  let mut notes: Vec<SimilarNote> = doc.notes.iter()
+    .filter(|note| exclude != Some(note.id.as_str()))
+    .filter_map(|note| {
+      let note_text = text_stats::note_text(note);
+      let score = dice(&query, &trigrams(&note_text));
+      (score >= min_score).then(|| SimilarNote { id: note.id.clone(), text: first_line(&note_text), score })
+    })
+    .collect();
+  // Stable, so equal scores keep document order
+  notes.sort_by(|a, b| b.score.total_cmp(&a.score));
+  notes.truncate(limit);
+
+  let mut boards: Vec<SimilarBoard> = library_boards.iter()
+    .filter(|board| board.error.is_none())
+    .filter_map(|board| {
+      let board_text = format!("{} {}", board.title, board.tags.join(" "));
+      let score = dice(&query, &trigrams(&board_text));
+      (score >= min_score).then(|| SimilarBoard { path: board.path.clone(), title: board.title.clone(), score })
+    })
+    .collect();
+  boards.sort_by(|a, b| b.score.total_cmp(&a.score));
+  boards.truncate(limit);
+
+  SimilarSuggestions { notes, boards }
+}
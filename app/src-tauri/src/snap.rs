@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::{layers, model};
+
+// Snapping while dragging: the edges and centers of nearby notes, and the
+// document grid when snapping to it is on, attract a moving note's frame.
+// The notes are bucketed into a uniform grid once per drag
+// (`prepare_snapping`), so each pointer move only looks at the cells around
+// the moving frame however big the board is.
+
+const CELL_SIZE: f64 = 512.0;
+// Notes further than this from the moving frame don't produce guides
+const SEARCH_DISTANCE: f64 = 800.0;
+const DEFAULT_THRESHOLD: f64 = 6.0;
+const MAX_CANDIDATES: usize = 8;
+// Lines closer than this count as aligned
+const EPSILON: f64 = 0.5;
+
+#[derive(Debug, Default)]
+pub struct SnapIndex {
+  notes: Vec<(model::ID, model::Rect)>,
+  cells: HashMap<(i64, i64), Vec<usize>>,
+  grid: Option<model::GridStyle>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SnapRequest {
+  // Where the dragged note would be without snapping
+  pub frame: model::Rect,
+  // Notes that move along (the selection); they never attract
+  #[serde(default)]
+  pub exclude_ids: Vec<model::ID>,
+  // Snap distance in board pixels; defaults to 6
+  pub threshold: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SnapCandidate {
+  // "x" or "y"
+  pub axis: String,
+  // Frame origin coordinate on that axis after snapping
+  pub value: f64,
+  pub distance: f64,
+  // "note" or "grid"
+  pub source: String,
+  pub note_id: Option<model::ID>,
+}
+
+// A guide line to draw: vertical at `position` for the x axis, horizontal for y
+#[derive(Serialize, Debug, Clone)]
+pub struct SnapGuide {
+  pub axis: String,
+  pub position: f64,
+  pub start: f64,
+  pub end: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SnapResult {
+  pub frame: model::Rect,
+  pub snapped_x: bool,
+  pub snapped_y: bool,
+  // Closest first, per axis
+  pub candidates: Vec<SnapCandidate>,
+  pub guides: Vec<SnapGuide>,
+}
+
+fn cell_range(start: f64, end: f64) -> std::ops::RangeInclusive<i64> {
+  (start / CELL_SIZE).floor() as i64..=(end / CELL_SIZE).floor() as i64
+}
+
+// Start, center and end of a frame along an axis
+fn lines(frame: &model::Rect, axis: usize) -> [f64; 3] {
+  let (start, size) = if axis == 0 { (frame.x, frame.w) } else { (frame.y, frame.h) };
+  [start, start + size / 2.0, start + size]
+}
+
+impl SnapIndex {
+  // Visible notes only; hidden layers don't attract
+  pub fn build(doc: &model::BoardDocument) -> SnapIndex {
+    let visible = layers::filter_layers(doc, &layers::visible_layers(doc));
+    let mut index = SnapIndex {
+      grid: doc.document_style.as_ref().and_then(|s| s.grid.clone()),
+      ..SnapIndex::default()
+    };
+    for note in visible.notes {
+      let slot = index.notes.len();
+      for cx in cell_range(note.frame.x, note.frame.x + note.frame.w) {
+        for cy in cell_range(note.frame.y, note.frame.y + note.frame.h) {
+          index.cells.entry((cx, cy)).or_default().push(slot);
+        }
+      }
+      index.notes.push((note.id, note.frame));
+    }
+    index
+  }
+
+  // Notes whose frame meets the area, each once
+  fn query(&self, area: &model::Rect) -> Vec<&(model::ID, model::Rect)> {
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for cx in cell_range(area.x, area.x + area.w) {
+      for cy in cell_range(area.y, area.y + area.h) {
+        for &slot in self.cells.get(&(cx, cy)).into_iter().flatten() {
+          let (_, frame) = &self.notes[slot];
+          let meets = frame.x <= area.x + area.w && frame.x + frame.w >= area.x
+            && frame.y <= area.y + area.h && frame.y + frame.h >= area.y;
+          if meets && seen.insert(slot) {
+            found.push(&self.notes[slot]);
+          }
+        }
+      }
+    }
+    found
+  }
+
+  pub fn snap(&self, request: &SnapRequest) -> SnapResult {
+    let frame = &request.frame;
+    let threshold = request.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    let area = model::Rect {
+      x: frame.x - SEARCH_DISTANCE,
+      y: frame.y - SEARCH_DISTANCE,
+      w: frame.w + 2.0 * SEARCH_DISTANCE,
+      h: frame.h + 2.0 * SEARCH_DISTANCE,
+    };
+    let nearby: Vec<&(model::ID, model::Rect)> = self.query(&area).into_iter()
+      .filter(|(id, _)| !request.exclude_ids.contains(id))
+      .collect();
+
+    let mut candidates = Vec::new();
+    let mut snapped = [None, None];
+    for (axis, name) in [(0, "x"), (1, "y")] {
+      let origin = if axis == 0 { frame.x } else { frame.y };
+      let moving = lines(frame, axis);
+      let mut found: Vec<SnapCandidate> = Vec::new();
+      for (id, other) in &nearby {
+        for target in lines(other, axis) {
+          for line in moving {
+            let distance = (target - line).abs();
+            if distance <= threshold {
+              found.push(SnapCandidate {
+                axis: name.to_string(),
+                value: origin + target - line,
+                distance,
+                source: "note".to_string(),
+                note_id: Some(id.clone()),
+              });
+            }
+          }
+        }
+      }
+      if let Some(grid) = self.grid.as_ref().filter(|g| g.snap && g.size > 0.0) {
+        let value = (origin / grid.size).round() * grid.size;
+        let distance = (value - origin).abs();
+        if distance <= threshold {
+          found.push(SnapCandidate { axis: name.to_string(), value, distance, source: "grid".to_string(), note_id: None });
+        }
+      }
+      // Stable, so notes win ties over the grid
+      found.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+      let mut values: Vec<f64> = Vec::new();
+      found.retain(|c| {
+        let new = !values.iter().any(|v| (v - c.value).abs() < EPSILON);
+        values.push(c.value);
+        new
+      });
+      found.truncate(MAX_CANDIDATES);
+      snapped[axis] = found.first().map(|c| c.value);
+      candidates.extend(found);
+    }
+
+    let result_frame = model::Rect {
+      x: snapped[0].unwrap_or(frame.x),
+      y: snapped[1].unwrap_or(frame.y),
+      ..frame.clone()
+    };
+
+    // Guides along every line the snapped frame shares with a nearby note,
+    // spanning both
+    let mut guides: Vec<SnapGuide> = Vec::new();
+    for (axis, name) in [(0, "x"), (1, "y")] {
+      if snapped[axis].is_none() {
+        continue;
+      }
+      let cross = 1 - axis;
+      for line in lines(&result_frame, axis) {
+        let [start, _, end] = lines(&result_frame, cross);
+        let mut span: Option<(f64, f64)> = None;
+        for (_, other) in &nearby {
+          if lines(other, axis).iter().any(|target| (target - line).abs() < EPSILON) {
+            let [other_start, _, other_end] = lines(other, cross);
+            let (lo, hi) = span.unwrap_or((start, end));
+            span = Some((lo.min(other_start), hi.max(other_end)));
+          }
+        }
+        if let Some((start, end)) = span {
+          guides.push(SnapGuide { axis: name.to_string(), position: line, start, end });
+        }
+      }
+    }
+
+    SnapResult {
+      frame: result_frame,
+      snapped_x: snapped[0].is_some(),
+      snapped_y: snapped[1].is_some(),
+      candidates,
+      guides,
+    }
+  }
+}
@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use crate::{archive, confidential, encryption, lazy_media, model, sync_write};
+
+// Snapshots are named copies of a board kept inside its own .fim container,
+// for going back to how it was days ago, long after undo has forgotten. Each
+// is stored as `versions/<id>.json`, the whole board with its images inline,
+// listed in `versions/index.json`. They travel with the file, are encrypted
+// along with a password-protected board, and are carried over each time the
+// board is saved to the same file. Past the retention limit the oldest go
+// first.
+
+pub const VERSIONS_DIR: &str = "versions/";
+const INDEX_ENTRY: &str = "versions/index.json";
+// Boards with their images inline, so larger than board.json may get
+const MAX_SNAPSHOT_SIZE: u64 = 512 * 1024 * 1024;
+const MAX_INDEX_SIZE: u64 = 1024 * 1024;
+const MAX_LABEL_LENGTH: usize = 200;
+// Kept at most, whatever the `keepSnapshots` setting says
+pub const MAX_KEEP_SNAPSHOTS: usize = 200;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+  pub id: model::ID,
+  pub label: String,
+  #[serde(rename = "createdAt")]
+  pub created_at: DateTime<Utc>,
+  #[serde(rename = "noteCount")]
+  pub note_count: usize,
+  #[serde(rename = "connectionCount")]
+  pub connection_count: usize,
+}
+
+// The snapshots of one board, oldest first, with their stored boards
+#[derive(Debug, Clone, Default)]
+pub struct History {
+  pub snapshots: Vec<Snapshot>,
+  boards: HashMap<model::ID, Vec<u8>>,
+}
+
+fn board_entry(id: &str) -> String {
+  format!("{}{}.json", VERSIONS_DIR, id)
+}
+
+// The container at `path`, decrypted when it is password-protected and its
+// key is unlocked
+fn open_container(path: &Path) -> Result<zip::ZipArchive<Cursor<Vec<u8>>>, String> {
+  if encryption::is_encrypted(path) {
+    return encryption::open_archive(path);
+  }
+  let data = std::fs::read(path)
+    .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+  let mut zip = zip::ZipArchive::new(Cursor::new(data))
+    .map_err(|e| format!("'{}' is not a .fim board: {}", path.display(), e))?;
+  archive::validate_archive(&mut zip)?;
+  Ok(zip)
+}
+
+impl History {
+  pub fn read<R: Read + Seek>(zip: &mut zip::ZipArchive<R>) -> Result<History, String> {
+    if zip.by_name(INDEX_ENTRY).is_err() {
+      return Ok(History::default());
+    }
+    let index = archive::read_entry_limited(zip, INDEX_ENTRY, MAX_INDEX_SIZE)?;
+    let listed: Vec<Snapshot> = serde_json::from_slice(&index)
+      .map_err(|e| format!("Invalid snapshot index: {}", e))?;
+
+    let mut history = History::default();
+    for snapshot in listed {
+      // A snapshot whose board is missing can't be restored, so it is dropped
+      match archive::read_entry_limited(zip, &board_entry(&snapshot.id), MAX_SNAPSHOT_SIZE) {
+        Ok(board) => {
+          history.boards.insert(snapshot.id.clone(), board);
+          history.snapshots.push(snapshot);
+        },
+        Err(e) => log::warn!("Dropping snapshot '{}': {}", snapshot.id, String::from(e)),
+      }
+    }
+    Ok(history)
+  }
+
+  // The snapshots in the .fim board at `path`; none when there is no such
+  // file yet or it can't be read, so saving over it isn't held up
+  pub fn read_file(path: &Path) -> History {
+    if !path.exists() {
+      return History::default();
+    }
+    match open_container(path).and_then(|mut zip| History::read(&mut zip)) {
+      Ok(history) => history,
+      Err(e) => {
+        log::warn!("Not keeping snapshots of '{}': {}", path.display(), e);
+        History::default()
+      },
+    }
+  }
+
+  pub fn write<W: Write + Seek>(&self, zip: &mut zip::ZipWriter<W>) -> Result<(), String> {
+    if self.snapshots.is_empty() {
+      return Ok(());
+    }
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let index = serde_json::to_vec_pretty(&self.snapshots)
+      .map_err(|e| format!("Failed to serialize snapshot index: {}", e))?;
+    zip.start_file(INDEX_ENTRY, options)
+      .map_err(|e| format!("Failed to create {} in zip: {}", INDEX_ENTRY, e))?;
+    zip.write_all(&index)
+      .map_err(|e| format!("Failed to write {}: {}", INDEX_ENTRY, e))?;
+    for snapshot in &self.snapshots {
+      let entry = board_entry(&snapshot.id);
+      zip.start_file(&entry, options)
+        .map_err(|e| format!("Failed to create {} in zip: {}", entry, e))?;
+      zip.write_all(&self.boards[&snapshot.id])
+        .map_err(|e| format!("Failed to write {}: {}", entry, e))?;
+    }
+    Ok(())
+  }
+
+  // Keep `doc` as it is now. Beyond `keep` snapshots (0 for no limit) the
+  // oldest are dropped.
+  pub fn add(&mut self, doc: &model::BoardDocument, label: &str, keep: usize, now: DateTime<Utc>) -> Result<Snapshot, String> {
+    let label = label.trim();
+    if label.chars().count() > MAX_LABEL_LENGTH {
+      return Err(format!("Snapshot label is longer than {} characters", MAX_LABEL_LENGTH));
+    }
+    // Confidential notes stay encrypted, as in board.json
+    let mut board = confidential::seal(doc)?;
+    lazy_media::load(&mut board)?;
+    let board = serde_json::to_vec(&board)
+      .map_err(|e| format!("Failed to serialize snapshot: {}", e))?;
+    let snapshot = Snapshot {
+      id: model::new_id("snapshot"),
+      label: label.to_string(),
+      created_at: now,
+      note_count: doc.notes.len(),
+      connection_count: doc.connections.len(),
+    };
+    self.boards.insert(snapshot.id.clone(), board);
+    self.snapshots.push(snapshot.clone());
+
+    let keep = if keep == 0 { MAX_KEEP_SNAPSHOTS } else { keep.min(MAX_KEEP_SNAPSHOTS) };
+    while self.snapshots.len() > keep {
+      let dropped = self.snapshots.remove(0);
+      self.boards.remove(&dropped.id);
+    }
+    Ok(snapshot)
+  }
+
+  // The stored board.json of a snapshot, to be parsed (and migrated) like one
+  pub fn board(&self, id: &str) -> Result<&[u8], String> {
+    self.boards.get(id).map(Vec::as_slice).ok_or_else(|| format!("Snapshot '{}' not found", id))
+  }
+}
+
+// Snapshots of the .fim board at `path`, newest first
+pub fn list(path: &Path) -> Result<Vec<Snapshot>, String> {
+  let mut snapshots = History::read(&mut open_container(path)?)?.snapshots;
+  snapshots.reverse();
+  Ok(snapshots)
+}
+
+pub fn read_board(path: &Path, id: &str) -> Result<Vec<u8>, String> {
+  Ok(History::read(&mut open_container(path)?)?.board(id)?.to_vec())
+}
+
+// Add a snapshot of `doc` to the .fim board at `path`, leaving the rest of
+// the container as it is on disk
+pub fn add_to_file(
+  path: &Path,
+  doc: &model::BoardDocument,
+  label: &str,
+  keep: usize,
+  options: sync_write::WriteOptions,
+) -> Result<Snapshot, String> {
+  let mut source = open_container(path)?;
+  let mut history = History::read(&mut source)?;
+  let snapshot = history.add(doc, label, keep, Utc::now())?;
+
+  let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+  for index in 0..source.len() {
+    let entry = source.by_index_raw(index)
+      .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    if entry.name().starts_with(VERSIONS_DIR) {
+      continue;
+    }
+    let name = entry.name().to_string();
+    zip.raw_copy_file(entry)
+      .map_err(|e| format!("Failed to copy {} in zip: {}", name, e))?;
+  }
+  history.write(&mut zip)?;
+  let plain = zip.finish()
+    .map_err(|e| format!("Failed to finalize zip file: {}", e))?
+    .into_inner();
+
+  let data = match encryption::key_for(path) {
+    Some(key) => encryption::encrypt(&key, &plain)?,
+    None => plain,
+  };
+  sync_write::write_with(path, options, |target| {
+    std::fs::write(target, &data).map_err(|e| format!("Failed to write file '{}': {}", path.display(), e))
+  })?;
+  Ok(snapshot)
+}
@@ -0,0 +1,181 @@
+use serde::Deserialize;
+
+use crate::{highlights, model, outline};
+
+// Splitting a long paste (an essay, an article, meeting notes) into notes of
+// a readable size instead of one note holding all of it. Hard-wrapped lines
+// within a paragraph are joined, since the wrapping came from the source and
+// not from the author.
+
+const DEFAULT_MAX_LENGTH: usize = 280;
+const MIN_MAX_LENGTH: usize = 40;
+const ROW_GAP: f64 = 16.0;
+// Words that end in a period without ending the sentence, lowercased
+const ABBREVIATIONS: &[&str] = &[
+  "e.g", "i.e", "etc", "vs", "cf", "approx", "fig", "no", "nr", "p", "pp", "vol", "ch",
+  "mr", "mrs", "ms", "dr", "prof", "st", "jr", "sr", "inc", "ltd", "co",
+];
+// Closing quotes and brackets that belong to the sentence they follow
+const CLOSING: &[char] = &['"', '\'', '”', '’', ')', ']', '»'];
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SplitOptions {
+  // "paragraphs" (default), "sentences" or "length"
+  pub strategy: Option<String>,
+  // Longest note for the "length" strategy, in characters
+  pub max_length: Option<usize>,
+  // "column" (default): notes one below the other; "stack": one stack
+  pub layout: Option<String>,
+  // Top-left of the first note; defaults to the board origin
+  pub origin: Option<model::Point>,
+}
+
+fn paragraphs(text: &str) -> Vec<String> {
+  let mut paragraphs = Vec::new();
+  let mut current: Vec<&str> = Vec::new();
+  for line in text.lines().map(str::trim).chain([""]) {
+    if line.is_empty() {
+      if !current.is_empty() {
+        paragraphs.push(current.join(" "));
+        current.clear();
+      }
+    } else {
+      current.push(line);
+    }
+  }
+  paragraphs
+}
+
+// Whether the period at the end of `before` ends a sentence: not after an
+// abbreviation or an initial
+fn ends_sentence(before: &str) -> bool {
+  let word = before.rsplit(char::is_whitespace).next().unwrap_or("");
+  let word = word.trim_start_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+  let single_letter = word.chars().count() == 1 && word.chars().all(char::is_alphabetic);
+  !single_letter && !ABBREVIATIONS.contains(&word.as_str())
+}
+
+fn sentences(paragraph: &str) -> Vec<String> {
+  let chars: Vec<(usize, char)> = paragraph.char_indices().collect();
+  let mut sentences = Vec::new();
+  let mut start = 0;
+  let mut i = 0;
+  while i < chars.len() {
+    let (index, c) = chars[i];
+    i += 1;
+    if !matches!(c, '.' | '!' | '?' | '…') {
+      continue;
+    }
+    // Runs such as "?!" or "..." end where the last one does
+    while i < chars.len() && (matches!(chars[i].1, '.' | '!' | '?' | '…') || CLOSING.contains(&chars[i].1)) {
+      i += 1;
+    }
+    let end = chars.get(i).map_or(paragraph.len(), |(index, _)| *index);
+    let next = chars[i..].iter().map(|(_, c)| *c).find(|c| !c.is_whitespace());
+    let at_break = i == chars.len() || chars[i].1.is_whitespace();
+    if !at_break || next.is_some_and(char::is_lowercase) || (c == '.' && !ends_sentence(&paragraph[start..index])) {
+      continue;
+    }
+    let sentence = paragraph[start..end].trim();
+    if !sentence.is_empty() {
+      sentences.push(sentence.to_string());
+    }
+    start = end;
+  }
+  let rest = paragraph[start..].trim();
+  if !rest.is_empty() {
+    sentences.push(rest.to_string());
+  }
+  sentences
+}
+
+// Joins `pieces` into chunks of at most `max` characters, filled greedily
+fn pack<'a>(pieces: impl IntoIterator<Item = &'a str>, max: usize, separator: &str, chunks: &mut Vec<String>) {
+  let mut current = String::new();
+  for piece in pieces {
+    let length = current.chars().count();
+    if !current.is_empty() && length + separator.len() + piece.chars().count() <= max {
+      current.push_str(separator);
+      current.push_str(piece);
+      continue;
+    }
+    if !current.is_empty() {
+      chunks.push(std::mem::take(&mut current));
+    }
+    if piece.chars().count() <= max {
+      current = piece.to_string();
+    } else if piece.contains(char::is_whitespace) {
+      pack(piece.split_whitespace(), max, " ", chunks);
+    } else {
+      // A single word longer than a note
+      let chars: Vec<char> = piece.chars().collect();
+      chunks.extend(chars.chunks(max).map(|chunk| chunk.iter().collect::<String>()));
+    }
+  }
+  if !current.is_empty() {
+    chunks.push(current);
+  }
+}
+
+// Paragraphs that fit stay whole; longer ones are packed sentence by sentence,
+// and sentences longer than a note word by word
+fn by_length(text: &str, max: usize) -> Vec<String> {
+  let mut chunks = Vec::new();
+  for paragraph in paragraphs(text) {
+    if paragraph.chars().count() <= max {
+      chunks.push(paragraph);
+    } else {
+      let sentences = sentences(&paragraph);
+      pack(sentences.iter().map(String::as_str), max, " ", &mut chunks);
+    }
+  }
+  chunks
+}
+
+pub fn split_text(text: &str, strategy: &str, max_length: Option<usize>) -> Result<Vec<String>, String> {
+  match strategy {
+    "paragraphs" => Ok(paragraphs(text)),
+    "sentences" => Ok(paragraphs(text).iter().flat_map(|p| sentences(p)).collect()),
+    "length" => Ok(by_length(text, max_length.unwrap_or(DEFAULT_MAX_LENGTH).max(MIN_MAX_LENGTH))),
+    other => Err(format!("Unsupported split strategy '{}'. Must be one of: paragraphs, sentences, length", other)),
+  }
+}
+
+// Notes for the pieces of `text`, sized to their text and placed in a column
+// or a stack
+pub fn to_notes(text: &str, options: &SplitOptions) -> Result<outline::OutlineImport, String> {
+  let as_stack = match options.layout.as_deref().unwrap_or("column") {
+    "column" => false,
+    "stack" => true,
+    other => return Err(format!("Unsupported split layout '{}'. Must be one of: column, stack", other)),
+  };
+  let pieces = split_text(text, options.strategy.as_deref().unwrap_or("paragraphs"), options.max_length)?;
+  if pieces.is_empty() {
+    return Err("The text is empty".to_string());
+  }
+  let origin = options.origin.clone().unwrap_or(model::Point { x: 0.0, y: 0.0 });
+
+  let mut result = outline::OutlineImport::default();
+  let mut y = origin.y;
+  for piece in &pieces {
+    let h = highlights::note_height(piece);
+    result.notes.push(outline::imported_note(piece, model::Rect { x: origin.x, y, w: highlights::NOTE_WIDTH, h }));
+    y += h + ROW_GAP;
+  }
+
+  if as_stack {
+    let stack_id = model::new_id("stack");
+    for note in &mut result.notes {
+      note.stack_id = Some(stack_id.clone());
+    }
+    result.stacks.push(model::Stack {
+      id: stack_id,
+      note_ids: result.notes.iter().map(|n| n.id.clone()).collect(),
+      orientation: Some("vertical".to_string()),
+      spacing: Some(ROW_GAP),
+      indent_levels: None,
+      aligned_width: None,
+    });
+  }
+  Ok(result)
+}
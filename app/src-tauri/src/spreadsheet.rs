@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{csv, highlights, model, outline, tags};
+
+// Notes as rows of a CSV or TSV table, for boards that go back and forth
+// between the canvas and a spreadsheet. Exports have one row per note with
+// the columns below; imports take any table with a text column (else the
+// first column is the text) and lay its rows out as new notes in a grid, so
+// positions, sizes and stacks in the table aren't restored. Columns other
+// than the known ones become note properties.
+
+pub const COLUMNS: &[&str] = &["index", "text", "x", "y", "w", "h", "tags", "faded", "stack"];
+const TEXT_HEADERS: &[&str] = &["text", "note", "content", "title"];
+// Columns of an export that say nothing about a new note
+const LAYOUT_HEADERS: &[&str] = &["index", "x", "y", "w", "h", "stack"];
+const GAP: f64 = 30.0;
+// Spreadsheets run cells starting with these as formulas
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@'];
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct SpreadsheetImport {
+  pub notes: Vec<model::Note>,
+  // Rows without text
+  pub skipped: usize,
+}
+
+// Tab for .tsv/.tab files, otherwise comma
+pub fn delimiter(extension: &str) -> char {
+  match extension.to_ascii_lowercase().as_str() {
+    "tsv" | "tab" => '\t',
+    _ => ',',
+  }
+}
+
+// Text that would run as a formula gets a leading apostrophe, which
+// spreadsheets hide and import strips again
+fn cell_text(text: &str) -> String {
+  match text.starts_with(FORMULA_PREFIXES) {
+    true => format!("'{}", text),
+    false => text.to_string(),
+  }
+}
+
+fn number(value: f64) -> String {
+  format!("{}", (value * 100.0).round() / 100.0)
+}
+
+// One row per note of `ordered`, numbered in that order. Stacks are numbered
+// in the board's order.
+pub fn export(doc: &model::BoardDocument, ordered: &[model::Note], delimiter: char) -> String {
+  let stack_numbers: HashMap<&str, usize> = doc.stacks.iter().enumerate()
+    .map(|(i, stack)| (stack.id.as_str(), i + 1))
+    .collect();
+  let mut rows: Vec<Vec<String>> = vec![COLUMNS.iter().map(|c| c.to_string()).collect()];
+  for (i, note) in ordered.iter().enumerate() {
+    let stack = doc.stacks.iter()
+      .find(|stack| stack.note_ids.contains(&note.id))
+      .map(|stack| stack.id.as_str())
+      .or(note.stack_id.as_deref())
+      .and_then(|id| stack_numbers.get(id));
+    rows.push(vec![
+      (i + 1).to_string(),
+      cell_text(&note.text),
+      number(note.frame.x),
+      number(note.frame.y),
+      number(note.frame.w),
+      number(note.frame.h),
+      cell_text(&tags::note_tags(note).join(", ")),
+      note.faded.unwrap_or(false).to_string(),
+      stack.map(|n| n.to_string()).unwrap_or_default(),
+    ]);
+  }
+  csv::write(&rows, delimiter)
+}
+
+// New notes for the rows of a table, in a roughly square grid from `origin`
+pub fn import(text: &str, delimiter: char, origin: Option<model::Point>) -> Result<SpreadsheetImport, String> {
+  let rows = csv::parse(text, delimiter);
+  let Some(first) = rows.first() else {
+    return Err("The table has no rows".to_string());
+  };
+  let headers: Vec<String> = first.iter().map(|h| h.trim().to_lowercase()).collect();
+  let text_column = TEXT_HEADERS.iter().find_map(|name| headers.iter().position(|h| h == name));
+  // Without a text column the first row is data too
+  let (text_column, body, named) = match text_column {
+    Some(column) => (column, &rows[1..], true),
+    None => (0, &rows[..], false),
+  };
+  let column = |name: &str| named.then(|| headers.iter().position(|h| h == name)).flatten();
+  let (tags_column, faded_column) = (column("tags"), column("faded"));
+  let extras: Vec<(usize, &str)> = match named {
+    true => first.iter().enumerate()
+      .filter(|(i, header)| {
+        let header = header.trim().to_lowercase();
+        *i != text_column && Some(*i) != tags_column && Some(*i) != faded_column
+          && !header.is_empty() && !LAYOUT_HEADERS.contains(&header.as_str())
+      })
+      .map(|(i, header)| (i, header.trim()))
+      .collect(),
+    false => Vec::new(),
+  };
+
+  let cell = |row: &[String], index: Option<usize>| -> Option<String> {
+    let value = row.get(index?)?.trim();
+    let value = match value.strip_prefix('\'') {
+      Some(rest) if rest.starts_with(FORMULA_PREFIXES) => rest,
+      _ => value,
+    };
+    (!value.is_empty()).then(|| value.to_string())
+  };
+
+  let mut result = SpreadsheetImport::default();
+  for row in body {
+    let Some(text) = cell(row, Some(text_column)) else {
+      result.skipped += 1;
+      continue;
+    };
+    let mut note = outline::imported_note(&text, model::Rect { x: 0.0, y: 0.0, w: highlights::NOTE_WIDTH, h: highlights::note_height(&text) });
+    let note_tags: Vec<String> = cell(row, tags_column).unwrap_or_default()
+      .split([',', ';'])
+      .filter_map(tags::normalize)
+      .collect();
+    note.tags = (!note_tags.is_empty()).then_some(note_tags);
+    note.faded = cell(row, faded_column)
+      .filter(|value| matches!(value.to_lowercase().as_str(), "true" | "yes" | "1" | "x"))
+      .map(|_| true);
+    let properties: HashMap<String, serde_json::Value> = extras.iter()
+      .filter_map(|(index, name)| Some((name.to_string(), serde_json::Value::String(cell(row, Some(*index))?))))
+      .collect();
+    note.properties = (!properties.is_empty()).then_some(properties);
+    result.notes.push(note);
+  }
+  if result.notes.is_empty() {
+    return Err("The table has no rows with text".to_string());
+  }
+
+  // Rows of the grid are as tall as their tallest note
+  let origin = origin.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  let columns = (result.notes.len() as f64).sqrt().ceil() as usize;
+  let mut y = origin.y;
+  for row in result.notes.chunks_mut(columns) {
+    let height = row.iter().map(|note| note.frame.h).fold(0.0, f64::max);
+    for (i, note) in row.iter_mut().enumerate() {
+      note.frame.x = origin.x + i as f64 * (highlights::NOTE_WIDTH + GAP);
+      note.frame.y = y;
+    }
+    y += height + GAP;
+  }
+  Ok(result)
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{layers, model};
+
+// Stack suggestions for boards laid out by hand or imported: runs of notes
+// whose left edges line up and that sit closely one below the other become
+// proposed stacks. Nothing on the board changes; each suggestion carries the
+// stack and its notes already laid out, for the frontend to apply as is.
+
+// Matches the canvas: indent levels are drawn 20px apart
+const INDENT_WIDTH: f64 = 20.0;
+const MAX_INDENT_LEVEL: u32 = 4;
+const DEFAULT_MAX_GAP: f64 = 40.0;
+const DEFAULT_ALIGN_TOLERANCE: f64 = 12.0;
+// Notes may overlap this much vertically and still count as one below the other
+const OVERLAP_TOLERANCE: f64 = 4.0;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct StackSuggestionOptions {
+  // Largest vertical gap between consecutive notes; defaults to 40px
+  pub max_gap: Option<f64>,
+  // How far left edges may differ, beyond whole indent levels; defaults to 12px
+  pub align_tolerance: Option<f64>,
+  // Smallest stack worth suggesting; defaults to 2
+  pub min_notes: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StackSuggestion {
+  pub stack: model::Stack,
+  // The members in stack order, moved into place and joined to the stack
+  pub notes: Vec<model::Note>,
+}
+
+// Left edges line up, either exactly or a whole number of indent levels apart
+fn indent_level(offset: f64, tolerance: f64) -> Option<u32> {
+  if offset < -tolerance {
+    return None;
+  }
+  let level = (offset / INDENT_WIDTH).round().max(0.0);
+  ((offset - level * INDENT_WIDTH).abs() <= tolerance && level as u32 <= MAX_INDENT_LEVEL).then_some(level as u32)
+}
+
+fn median(values: &mut [f64]) -> f64 {
+  values.sort_by(f64::total_cmp);
+  match values.len() {
+    0 => 0.0,
+    n if n % 2 == 1 => values[n / 2],
+    n => (values[n / 2 - 1] + values[n / 2]) / 2.0,
+  }
+}
+
+pub fn suggest_stacks(doc: &model::BoardDocument, options: &StackSuggestionOptions) -> Vec<StackSuggestion> {
+  let max_gap = options.max_gap.unwrap_or(DEFAULT_MAX_GAP);
+  let tolerance = options.align_tolerance.unwrap_or(DEFAULT_ALIGN_TOLERANCE);
+  let min_notes = options.min_notes.unwrap_or(2).max(2);
+
+  let locked = layers::locked_ids(doc);
+  let mut free: Vec<&model::Note> = doc.notes.iter()
+    .filter(|n| n.stack_id.is_none() && !locked.contains(&n.id))
+    .filter(|n| !doc.stacks.iter().any(|s| s.note_ids.contains(&n.id)))
+    .collect();
+  free.sort_by(|a, b| a.frame.y.total_cmp(&b.frame.y).then(a.frame.x.total_cmp(&b.frame.x)));
+
+  // Grow runs top to bottom: a note continues the run whose last note it sits
+  // right under, aligned with the run's first note
+  let mut runs: Vec<Vec<&model::Note>> = Vec::new();
+  for note in free {
+    let continues = runs.iter_mut()
+      .filter(|run| {
+        let (first, last) = (run[0], run[run.len() - 1]);
+        let gap = note.frame.y - (last.frame.y + last.frame.h);
+        (-OVERLAP_TOLERANCE..=max_gap).contains(&gap)
+          && indent_level(note.frame.x - first.frame.x, tolerance).is_some()
+      })
+      // The closest run above wins
+      .min_by(|a, b| {
+        let bottom = |run: &Vec<&model::Note>| run[run.len() - 1].frame.y + run[run.len() - 1].frame.h;
+        (note.frame.y - bottom(a)).total_cmp(&(note.frame.y - bottom(b)))
+      });
+    match continues {
+      Some(run) => run.push(note),
+      None => runs.push(vec![note]),
+    }
+  }
+
+  runs.into_iter()
+    .filter(|run| run.len() >= min_notes)
+    .map(|run| {
+      let mut gaps: Vec<f64> = run.windows(2).map(|w| (w[1].frame.y - w[0].frame.y - w[0].frame.h).max(0.0)).collect();
+      let spacing = median(&mut gaps).round();
+      let (x, mut y) = (run[0].frame.x, run[0].frame.y);
+      let stack_id = model::new_id("stack");
+      let mut indent_levels = HashMap::new();
+      let notes: Vec<model::Note> = run.iter()
+        .map(|&source| {
+          let level = indent_level(source.frame.x - x, tolerance).unwrap_or(0);
+          if level > 0 {
+            indent_levels.insert(source.id.clone(), level);
+          }
+          let mut note = source.clone();
+          note.frame.x = x + level as f64 * INDENT_WIDTH;
+          note.frame.y = y;
+          note.stack_id = Some(stack_id.clone());
+          y += note.frame.h + spacing;
+          note
+        })
+        .collect();
+      StackSuggestion {
+        stack: model::Stack {
+          id: stack_id,
+          note_ids: notes.iter().map(|n| n.id.clone()).collect(),
+          orientation: Some("vertical".to_string()),
+          spacing: Some(spacing),
+          indent_levels: (!indent_levels.is_empty()).then_some(indent_levels),
+          aligned_width: None,
+        },
+        notes,
+      }
+    })
+    .collect()
+}
@@ -0,0 +1,266 @@
+use crate::{checklist, icons, layers, links, markdown, model, outline, raster, render, rich_text, tables, title};
+
+// A board as one self-contained web page, for sharing with people who don't
+// have the app. Background, shapes, connections (with their bend points,
+// arrows and labels) and ink are one inline SVG drawn by render.rs; notes are
+// absolutely positioned boxes on top of it with their style, rich text,
+// checklist, table and images, so their text can be selected and their links
+// followed. Images are inlined as data URIs and a small script lets the page
+// be panned and zoomed; nothing is loaded from elsewhere. Links to other
+// notes jump to them, and clicking a note puts its anchor in the address.
+
+// Room around the content, for connection labels and arrows past the notes
+const PADDING: f64 = 40.0;
+const FALLBACK_TITLE: &str = "Board";
+
+const STYLE: &str = "\
+html, body { margin: 0; height: 100%; overflow: hidden; font-family: sans-serif; }
+#viewport { position: fixed; inset: 0; cursor: grab; touch-action: none; }
+#viewport.dragging { cursor: grabbing; }
+#board { position: absolute; left: 0; top: 0; transform-origin: 0 0; }
+#board > svg { position: absolute; left: 0; top: 0; }
+.note { position: absolute; box-sizing: border-box; overflow: hidden; overflow-wrap: break-word; }
+.note .text { white-space: pre-wrap; }
+.note img { display: block; max-width: 100%; margin: 0 auto 8px; }
+.note ul.checklist { list-style: none; margin: 4px 0 0; padding: 0; }
+.note table { border-collapse: collapse; margin-top: 4px; font-size: 0.9em; }
+.note th, .note td { border: 1px solid rgba(0, 0, 0, 0.25); padding: 2px 4px; }
+.note .links { margin-top: 4px; font-size: 0.85em; }
+.note .links a { display: block; }
+.note:target { outline: 3px solid #3b82f6; outline-offset: 2px; }
+#help { position: fixed; right: 8px; bottom: 8px; font-size: 12px; color: #666; background: rgba(255, 255, 255, 0.8); padding: 2px 6px; border-radius: 4px; }
+";
+
+// Pan by dragging, zoom with the wheel or +/-, 0 fits the board. A note
+// link or #note-… in the address centers its note.
+const SCRIPT: &str = "\
+(function () {
+  var viewport = document.getElementById('viewport');
+  var board = document.getElementById('board');
+  var scale = 1, x = 0, y = 0, drag = null;
+  function apply() { board.style.transform = 'translate(' + x + 'px, ' + y + 'px) scale(' + scale + ')'; }
+  function zoomAt(factor, cx, cy) {
+    var next = Math.min(8, Math.max(0.05, scale * factor));
+    x = cx - (cx - x) * next / scale;
+    y = cy - (cy - y) * next / scale;
+    scale = next;
+    apply();
+  }
+  // The board's size is read each time, since a live page swaps its content
+  function fit() {
+    var width = board.offsetWidth, height = board.offsetHeight;
+    scale = Math.min(window.innerWidth / width, window.innerHeight / height, 1) || 1;
+    x = (window.innerWidth - width * scale) / 2;
+    y = (window.innerHeight - height * scale) / 2;
+    apply();
+  }
+  function show(id) {
+    var note = id && document.getElementById(id);
+    if (!note || !note.classList.contains('note')) return;
+    scale = Math.max(scale, 1);
+    x = window.innerWidth / 2 - (note.offsetLeft + note.offsetWidth / 2) * scale;
+    y = window.innerHeight / 2 - (note.offsetTop + note.offsetHeight / 2) * scale;
+    apply();
+  }
+  viewport.addEventListener('wheel', function (e) {
+    e.preventDefault();
+    zoomAt(Math.exp(-e.deltaY / 500), e.clientX, e.clientY);
+  }, { passive: false });
+  viewport.addEventListener('pointerdown', function (e) {
+    if (e.button !== 0 || e.target.closest('a')) return;
+    drag = { x: e.clientX, y: e.clientY, fromX: x, fromY: y, moved: false };
+  });
+  window.addEventListener('pointermove', function (e) {
+    if (!drag) return;
+    if (Math.abs(e.clientX - drag.x) + Math.abs(e.clientY - drag.y) > 3) {
+      drag.moved = true;
+      viewport.classList.add('dragging');
+    }
+    if (!drag.moved) return;
+    x = drag.fromX + e.clientX - drag.x;
+    y = drag.fromY + e.clientY - drag.y;
+    apply();
+  });
+  window.addEventListener('pointerup', function (e) {
+    if (drag && !drag.moved) {
+      var note = e.target.closest('.note');
+      if (note) history.replaceState(null, '', '#' + note.id);
+    }
+    drag = null;
+    viewport.classList.remove('dragging');
+  });
+  window.addEventListener('hashchange', function () { show(decodeURIComponent(location.hash.slice(1))); });
+  document.addEventListener('keydown', function (e) {
+    if (e.key === '0') fit();
+    else if (e.key === '+' || e.key === '=') zoomAt(1.25, window.innerWidth / 2, window.innerHeight / 2);
+    else if (e.key === '-') zoomAt(0.8, window.innerWidth / 2, window.innerHeight / 2);
+  });
+  fit();
+  show(decodeURIComponent(location.hash.slice(1)));
+})();
+";
+
+// A style color as a CSS color; `#rrggbbaa` keeps its alpha
+fn css_color(value: Option<&str>, fallback: &str) -> String {
+  let (color, opacity) = render::color(value, fallback);
+  if opacity >= 1.0 {
+    return color;
+  }
+  let channel = |i: usize| u8::from_str_radix(&color[i..i + 2], 16).unwrap_or(0);
+  format!("rgba({}, {}, {}, {:.3})", channel(1), channel(3), channel(5), opacity)
+}
+
+// Font names go into a style attribute, so only keep what a name needs
+fn font_family(font: &str) -> String {
+  font.chars().filter(|c| c.is_alphanumeric() || " -_".contains(*c)).collect::<String>().trim().to_string()
+}
+
+fn note_css(doc: &model::BoardDocument, note: &model::Note, region: &model::Rect) -> String {
+  let style = doc.note_style(note);
+  let text_style = style.map(|s| &s.text_style);
+  let border = style.and_then(|s| s.border.as_ref());
+  let f = &note.frame;
+
+  let mut css = format!(
+    "left: {:.2}px; top: {:.2}px; width: {:.2}px; height: {:.2}px; padding: {}px; background: {}; border-radius: {:.2}px;",
+    f.x - region.x, f.y - region.y, f.w, f.h, render::NOTE_PADDING,
+    css_color(style.and_then(|s| s.fill.as_deref()), render::DEFAULT_NOTE_FILL),
+    style.and_then(|s| s.corner_radius).unwrap_or(4.0).max(0.0)
+  );
+  let border_width = border.and_then(|b| b.width).unwrap_or(1.0);
+  let border_style = match border.and_then(|b| b.style.as_deref()) {
+    Some("dashed") => "dashed",
+    Some("dotted") => "dotted",
+    Some("none") => "none",
+    _ => "solid",
+  };
+  if border_style != "none" && border_width > 0.0 {
+    css += &format!(
+      " border: {:.2}px {} {};",
+      border_width, border_style, css_color(border.and_then(|b| b.color.as_deref()), render::DEFAULT_NOTE_BORDER)
+    );
+  }
+  if style.and_then(|s| s.shadow).unwrap_or(false) {
+    css += " box-shadow: 2px 3px 0 rgba(0, 0, 0, 0.15);";
+  }
+
+  let size = text_style.map(|t| t.size).filter(|s| s.is_finite() && *s > 0.0).unwrap_or(render::DEFAULT_FONT_SIZE);
+  let font = text_style.map(|t| font_family(&t.font)).filter(|font| !font.is_empty());
+  css += &format!(
+    " font-size: {:.2}px; line-height: {}; font-family: {}{}; color: {};",
+    size, render::LINE_HEIGHT,
+    font.map(|font| format!("'{}', ", font)).unwrap_or_default(), render::DEFAULT_FONT,
+    css_color(text_style.and_then(|t| t.color.as_deref()), render::DEFAULT_TEXT_COLOR)
+  );
+  if let Some(align @ ("center" | "right")) = text_style.and_then(|t| t.align.as_deref()) {
+    css += &format!(" text-align: {};", align);
+  }
+  if let Some(weight) = text_style.and_then(|t| t.weight) {
+    css += &format!(" font-weight: {};", weight);
+  }
+  if text_style.and_then(|t| t.italic).unwrap_or(false) {
+    css += " font-style: italic;";
+  }
+  if note.faded.unwrap_or(false) {
+    css += &format!(" opacity: {};", render::FADED_OPACITY);
+  }
+  css
+}
+
+fn note_html(doc: &model::BoardDocument, note: &model::Note, region: &model::Rect) -> String {
+  let mut html = format!(
+    "<div class=\"note\" id=\"{}\" style=\"{}\">",
+    outline::html_escape(&links::anchor_id(&note.id)), note_css(doc, note, region)
+  );
+  for uri in note.images.iter().flatten().filter_map(|id| render::image_data_uri(doc, id)) {
+    html += &format!("<img src=\"{}\" alt=\"\">", uri);
+  }
+
+  let prefix = outline::html_escape(&icons::note_prefix(note));
+  if note.is_markdown() {
+    html += &format!("<div class=\"markdown\">{}{}</div>", prefix, markdown::to_html(&note.text));
+  } else if !note.text.is_empty() || !prefix.is_empty() {
+    let spans = note.spans.clone().unwrap_or_default();
+    html += &format!("<div class=\"text\">{}{}</div>", prefix, rich_text::to_html(&note.text, &spans, outline::html_escape));
+  }
+
+  let items = checklist::ordered_items(note);
+  if !items.is_empty() {
+    html += "<ul class=\"checklist\">";
+    for item in items {
+      html += &format!("<li>{} {}</li>", checklist::checkbox(item.done), outline::html_escape(&item.text));
+    }
+    html += "</ul>";
+  }
+  if let Some(table) = &note.table {
+    html += &tables::to_html(table, outline::html_escape);
+  }
+  let note_links: Vec<&String> = note.links.iter().flatten().collect();
+  if !note_links.is_empty() {
+    html += "<div class=\"links\">";
+    for link in note_links {
+      let target = links::note_link_target(link)
+        .and_then(|id| doc.notes.iter().find(|n| n.id == id))
+        .map(|n| n.text.lines().next().unwrap_or_default().to_string())
+        .filter(|text| !text.trim().is_empty())
+        .unwrap_or_else(|| link.clone());
+      html += &format!("<a href=\"{}\">{}</a>", outline::html_escape(&links::href(link)), outline::html_escape(&target));
+    }
+    html += "</div>";
+  }
+  html + "</div>\n"
+}
+
+// `title`, else the board's suggested title
+pub fn page_title(doc: &model::BoardDocument, title: Option<&str>) -> String {
+  title.map(str::trim).filter(|t| !t.is_empty()).map(str::to_string)
+    .or_else(|| title::suggest_titles(doc).into_iter().next().map(|s| s.title))
+    .unwrap_or_else(|| FALLBACK_TITLE.to_string())
+}
+
+pub fn background(doc: &model::BoardDocument) -> String {
+  let background = doc.document_style.as_ref().and_then(|s| s.background.as_ref()).and_then(|b| b.color.as_deref());
+  css_color(background, render::DEFAULT_BACKGROUND)
+}
+
+// What goes inside #board (the backdrop SVG and the notes), and the size of
+// the board it is laid out on
+pub fn board_html(doc: &model::BoardDocument) -> (String, model::Rect) {
+  let content = raster::content_bounds(doc).unwrap_or(model::Rect { x: 0.0, y: 0.0, w: 0.0, h: 0.0 });
+  let region = model::Rect {
+    x: content.x - PADDING,
+    y: content.y - PADDING,
+    w: content.w + PADDING * 2.0,
+    h: content.h + PADDING * 2.0,
+  };
+  let mut html = render::backdrop_svg(doc, &region);
+  for (_, note) in layers::paint_order(&doc.notes) {
+    html += &note_html(doc, note, &region);
+  }
+  (html, region)
+}
+
+// The page for `doc`; `title` defaults to the board's suggested title
+pub fn to_html(doc: &model::BoardDocument, title: Option<&str>) -> String {
+  page(doc, title, "")
+}
+
+// The page with `extra` markup at the end of its body; see share_server.rs
+pub fn page(doc: &model::BoardDocument, title: Option<&str>, extra: &str) -> String {
+  let title = page_title(doc, title);
+  let (board, region) = board_html(doc);
+
+  let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+  html += "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n";
+  html += &format!("<title>{}</title>\n", outline::html_escape(&title));
+  html += &format!("<style>\n{}body {{ background: {}; }}\n</style>\n", STYLE, background(doc));
+  html += "</head>\n<body>\n<div id=\"viewport\">\n";
+  html += &format!("<div id=\"board\" style=\"width: {:.2}px; height: {:.2}px;\">\n", region.w, region.h);
+  html += &board;
+  html += "</div>\n</div>\n";
+  html += "<div id=\"help\">Drag to pan · scroll to zoom · 0 to fit</div>\n";
+  html += &format!("<script>\n{}</script>\n", SCRIPT);
+  html += extra;
+  html += "</body>\n</html>\n";
+  html
+}
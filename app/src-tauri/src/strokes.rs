@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model;
+
+// Freehand ink strokes can carry thousands of points, so board.json stores
+// them delta-encoded: the first point absolute, then per-point offsets, all
+// quantized to 1/100 px. Pressure is stored separately and only when some
+// point was drawn with less than full pressure.
+
+const QUANTUM: f64 = 100.0;
+
+// Maximum deviation (px) allowed when simplifying strokes on save
+pub const SIMPLIFY_TOLERANCE: f64 = 0.5;
+
+fn quantize(value: f64) -> f64 {
+  (value * QUANTUM).round() / QUANTUM
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EncodedStroke {
+  id: model::ID,
+  color: Option<String>,
+  width: f64,
+  // x0, y0, then dx, dy for every following point
+  points: Vec<f64>,
+  #[serde(skip_serializing_if = "Option::is_none", default)]
+  pressure: Option<Vec<f64>>,
+  #[serde(rename = "layerId", skip_serializing_if = "Option::is_none", default)]
+  layer_id: Option<model::ID>,
+}
+
+impl From<model::Stroke> for EncodedStroke {
+  fn from(stroke: model::Stroke) -> Self {
+    let mut points = Vec::with_capacity(stroke.points.len() * 2);
+    let (mut last_x, mut last_y) = (0.0, 0.0);
+    for point in &stroke.points {
+      // Deltas are taken between quantized positions so rounding never accumulates
+      let (x, y) = (quantize(point.x), quantize(point.y));
+      points.push(quantize(x - last_x));
+      points.push(quantize(y - last_y));
+      (last_x, last_y) = (x, y);
+    }
+
+    let pressure = stroke.points.iter().any(|p| p.pressure < 1.0)
+      .then(|| stroke.points.iter().map(|p| quantize(p.pressure)).collect());
+
+    EncodedStroke {
+      id: stroke.id,
+      color: stroke.color,
+      width: stroke.width,
+      points,
+      pressure,
+      layer_id: stroke.layer_id,
+    }
+  }
+}
+
+impl TryFrom<EncodedStroke> for model::Stroke {
+  type Error = String;
+
+  fn try_from(encoded: EncodedStroke) -> Result<Self, String> {
+    if encoded.points.len() % 2 != 0 {
+      return Err(format!("Stroke '{}' has an odd number of coordinates", encoded.id));
+    }
+    let count = encoded.points.len() / 2;
+    if encoded.pressure.as_ref().is_some_and(|p| p.len() != count) {
+      return Err(format!("Stroke '{}' has {} points but a different number of pressure values", encoded.id, count));
+    }
+
+    let (mut x, mut y) = (0.0, 0.0);
+    let points = encoded.points.chunks_exact(2).enumerate()
+      .map(|(i, delta)| {
+        x = quantize(x + delta[0]);
+        y = quantize(y + delta[1]);
+        let pressure = encoded.pressure.as_ref().map_or(1.0, |p| p[i].clamp(0.0, 1.0));
+        model::StrokePoint { x, y, pressure }
+      })
+      .collect();
+
+    Ok(model::Stroke {
+      id: encoded.id,
+      points,
+      color: encoded.color,
+      width: encoded.width,
+      layer_id: encoded.layer_id,
+    })
+  }
+}
+
+fn distance_to_segment(p: &model::StrokePoint, a: &model::StrokePoint, b: &model::StrokePoint) -> f64 {
+  let (dx, dy) = (b.x - a.x, b.y - a.y);
+  let length_sq = dx * dx + dy * dy;
+  if length_sq == 0.0 {
+    return (p.x - a.x).hypot(p.y - a.y);
+  }
+  let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / length_sq).clamp(0.0, 1.0);
+  (p.x - (a.x + t * dx)).hypot(p.y - (a.y + t * dy))
+}
+
+// Ramer–Douglas–Peucker: drop points that deviate less than `tolerance` from
+// the simplified line. Iterative so long strokes can't overflow the stack.
+pub fn simplify(points: &[model::StrokePoint], tolerance: f64) -> Vec<model::StrokePoint> {
+  if points.len() < 3 {
+    return points.to_vec();
+  }
+
+  let mut keep = vec![false; points.len()];
+  keep[0] = true;
+  keep[points.len() - 1] = true;
+  let mut ranges = vec![(0, points.len() - 1)];
+  while let Some((start, end)) = ranges.pop() {
+    let farthest = (start + 1..end)
+      .map(|i| (i, distance_to_segment(&points[i], &points[start], &points[end])))
+      .max_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((index, distance)) = farthest {
+      if distance > tolerance {
+        keep[index] = true;
+        ranges.push((start, index));
+        ranges.push((index, end));
+      }
+    }
+  }
+
+  points.iter().zip(keep).filter(|(_, keep)| *keep).map(|(p, _)| p.clone()).collect()
+}
+
+pub fn simplify_document(doc: &mut model::BoardDocument, tolerance: f64) {
+  for stroke in doc.strokes.iter_mut().flatten() {
+    stroke.points = simplify(&stroke.points, tolerance);
+  }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::{model, settings};
+
+// Style themes (.fimtheme): a board's note styles and document style in a
+// file of their own, applied to other boards to keep them consistent. Theme
+// styles replace the board's styles they correspond to — by role first (the
+// default note and shape styles), then by name, then by ID — so notes keep
+// their `styleId`; the rest are added. The board keeps its own label palette,
+// since notes refer to labels by ID.
+//
+// The style library keeps note styles in the app data folder, outside any
+// board, so a palette made once can be inserted into every board. Inserted
+// styles get fresh IDs; the board's copy is independent of the library.
+
+pub const THEME_EXTENSION: &str = "fimtheme";
+const STYLE_LIBRARY_FILE: &str = "styles.json";
+const THEME_FORMAT: &str = "fimtheme";
+const THEME_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StyleTheme {
+  pub format: String,
+  pub version: u32,
+  pub name: Option<String>,
+  #[serde(rename = "noteStyles")]
+  pub note_styles: Vec<model::NoteStyle>,
+  #[serde(rename = "documentStyle")]
+  pub document_style: Option<model::DocumentStyle>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ThemeApplication {
+  pub document: model::BoardDocument,
+  // Board styles that took on a theme style
+  pub updated: Vec<model::ID>,
+  pub added: Vec<model::ID>,
+}
+
+pub fn export_theme(doc: &model::BoardDocument, name: Option<String>) -> StyleTheme {
+  let document_style = doc.document_style.clone().map(|style| model::DocumentStyle { labels: None, ..style });
+  StyleTheme {
+    format: THEME_FORMAT.to_string(),
+    version: THEME_VERSION,
+    name,
+    note_styles: doc.note_styles.clone(),
+    document_style,
+  }
+}
+
+pub fn parse_theme(json: &str) -> Result<StyleTheme, String> {
+  let theme: StyleTheme = serde_json::from_str(json)
+    .map_err(|e| format!("Invalid theme file: {}", e))?;
+  if theme.format != THEME_FORMAT {
+    return Err(format!("Invalid theme file: unknown format '{}'", theme.format));
+  }
+  if theme.version > THEME_VERSION {
+    return Err(format!("Unsupported theme version {}; this version of the app reads up to {}", theme.version, THEME_VERSION));
+  }
+  Ok(theme)
+}
+
+fn same_name(a: &model::NoteStyle, b: &model::NoteStyle) -> bool {
+  match (a.name.as_deref(), b.name.as_deref()) {
+    (Some(a), Some(b)) => a.trim().eq_ignore_ascii_case(b.trim()),
+    _ => false,
+  }
+}
+
+pub fn apply_theme(doc: &model::BoardDocument, theme: &StyleTheme) -> ThemeApplication {
+  let mut document = doc.clone();
+  let board_defaults = doc.document_style.as_ref()
+    .map(|s| (s.default_note_style_id.clone(), s.default_shape_style_id.clone()))
+    .unwrap_or_default();
+  let theme_defaults = theme.document_style.as_ref()
+    .map(|s| (s.default_note_style_id.clone(), s.default_shape_style_id.clone()))
+    .unwrap_or_default();
+  let role_target = |style: &model::NoteStyle| -> Option<model::ID> {
+    if theme_defaults.0.as_ref() == Some(&style.id) {
+      board_defaults.0.clone()
+    } else if theme_defaults.1.as_ref() == Some(&style.id) {
+      board_defaults.1.clone()
+    } else {
+      None
+    }
+  };
+
+  let mut updated = Vec::new();
+  let mut added = Vec::new();
+  // Theme style ID → board style ID, for the document style's defaults
+  let mut mapped = HashMap::new();
+  let mut taken: HashSet<model::ID> = HashSet::new();
+  for style in &theme.note_styles {
+    let target = role_target(style)
+      .filter(|id| !taken.contains(id) && document.note_styles.iter().any(|s| &s.id == id))
+      .or_else(|| document.note_styles.iter().find(|s| !taken.contains(&s.id) && same_name(s, style)).map(|s| s.id.clone()))
+      .or_else(|| document.note_styles.iter().find(|s| !taken.contains(&s.id) && s.id == style.id).map(|s| s.id.clone()));
+
+    let id = match target {
+      Some(id) => {
+        if let Some(existing) = document.note_styles.iter_mut().find(|s| s.id == id) {
+          *existing = model::NoteStyle { id: id.clone(), name: style.name.clone().or(existing.name.take()), ..style.clone() };
+        }
+        updated.push(id.clone());
+        id
+      },
+      None => {
+        let id = if document.note_styles.iter().any(|s| s.id == style.id) { model::new_id("style") } else { style.id.clone() };
+        document.note_styles.push(model::NoteStyle { id: id.clone(), ..style.clone() });
+        added.push(id.clone());
+        id
+      },
+    };
+    taken.insert(id.clone());
+    mapped.insert(style.id.clone(), id);
+  }
+
+  if let Some(theme_style) = &theme.document_style {
+    let board_style = document.document_style.take();
+    let keep = |board: Option<&model::ID>, theme: Option<&model::ID>| {
+      board.cloned().or_else(|| theme.and_then(|id| mapped.get(id).cloned()))
+    };
+    document.document_style = Some(model::DocumentStyle {
+      background: theme_style.background.clone().or_else(|| board_style.as_ref().and_then(|s| s.background.clone())),
+      default_note_style_id: keep(board_defaults.0.as_ref(), theme_style.default_note_style_id.as_ref()),
+      default_shape_style_id: keep(board_defaults.1.as_ref(), theme_style.default_shape_style_id.as_ref()),
+      grid: theme_style.grid.clone().or_else(|| board_style.as_ref().and_then(|s| s.grid.clone())),
+      labels: board_style.and_then(|s| s.labels),
+    });
+  }
+
+  ThemeApplication { document, updated, added }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryStyle {
+  // The style as saved; `style.id` is the library entry's ID
+  pub style: model::NoteStyle,
+  #[serde(rename = "savedAt")]
+  pub saved_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StyleLibrary {
+  pub styles: Vec<LibraryStyle>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StyleInsertion {
+  pub document: model::BoardDocument,
+  // IDs of the inserted styles, in the order requested
+  pub added: Vec<model::ID>,
+}
+
+fn style_library_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(STYLE_LIBRARY_FILE))
+}
+
+impl StyleLibrary {
+  pub fn load() -> StyleLibrary {
+    let Some(path) = style_library_path() else {
+      return StyleLibrary::default();
+    };
+    match std::fs::read_to_string(&path) {
+      Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("Ignoring invalid style library '{}': {}", path.display(), e);
+        StyleLibrary::default()
+      }),
+      Err(_) => StyleLibrary::default(),
+    }
+  }
+
+  pub fn save(&self) -> Result<(), String> {
+    let path = style_library_path().ok_or("Could not determine the app data directory")?;
+    if let Some(parent) = path.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(self)
+      .map_err(|e| format!("Failed to serialize style library: {}", e))?;
+    std::fs::write(&path, json)
+      .map_err(|e| format!("Failed to write style library '{}': {}", path.display(), e))
+  }
+
+  // Save a board style under `name` (default: the style's own name). A
+  // library style with the same name is replaced.
+  pub fn add(&mut self, doc: &model::BoardDocument, style_id: &str, name: Option<String>) -> Result<LibraryStyle, String> {
+    let style = doc.note_styles.iter().find(|s| s.id == style_id)
+      .ok_or_else(|| format!("Style '{}' not found", style_id))?;
+    let name = name.or_else(|| style.name.clone())
+      .map(|n| n.trim().to_string())
+      .filter(|n| !n.is_empty())
+      .ok_or("A library style needs a name")?;
+
+    let existing = self.styles.iter().position(|s| s.style.name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(&name)));
+    let id = existing.map_or_else(|| model::new_id("style"), |i| self.styles[i].style.id.clone());
+    let entry = LibraryStyle {
+      style: model::NoteStyle { id, name: Some(name), ..style.clone() },
+      saved_at: chrono::Utc::now(),
+    };
+    match existing {
+      Some(i) => self.styles[i] = entry.clone(),
+      None => self.styles.push(entry.clone()),
+    }
+    Ok(entry)
+  }
+
+  pub fn remove(&mut self, id: &str) -> Result<(), String> {
+    let before = self.styles.len();
+    self.styles.retain(|s| s.style.id != id);
+    if self.styles.len() == before {
+      return Err(format!("Library style '{}' not found", id));
+    }
+    Ok(())
+  }
+
+  // Copies of library styles added to the board under new IDs
+  pub fn insert(&self, doc: &model::BoardDocument, ids: &[model::ID]) -> Result<StyleInsertion, String> {
+    let mut document = doc.clone();
+    let mut added = Vec::new();
+    for id in ids {
+      let entry = self.styles.iter().find(|s| &s.style.id == id)
+        .ok_or_else(|| format!("Library style '{}' not found", id))?;
+      let style = model::NoteStyle { id: model::new_id("style"), ..entry.style.clone() };
+      added.push(style.id.clone());
+      document.note_styles.push(style);
+    }
+    Ok(StyleInsertion { document, added })
+  }
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::{confidential, highlights, language, model, outline, settings};
+
+// Summaries of a selection of notes through a provider the user configures: a
+// local command, a llama.cpp server or an OpenAI-compatible chat API. Only the
+// selected notes' text is sent, never the rest of the board, and confidential
+// notes are left out. The summary comes back as a draft note below the
+// selection for the user to edit or discard.
+
+const SUMMARY_GAP: f64 = 40.0;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(120);
+// Upper bound on the summary length for llama.cpp, in tokens
+const LLAMA_MAX_TOKENS: u32 = 512;
+const DEFAULT_INSTRUCTIONS: &str =
+  "Summarize the following notes in a few sentences. Answer with the summary only.";
+// Between notes in the text sent to the provider
+const NOTE_SEPARATOR: &str = "\n\n---\n\n";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SummaryDraft {
+  // Not yet on the board
+  pub note: model::Note,
+  // Notes whose text was sent
+  pub sources: Vec<model::ID>,
+  // Notes left out: no text, or confidential
+  pub skipped: Vec<model::ID>,
+}
+
+pub trait SummaryProvider: Send {
+  // A summary of `notes`, following `instructions`
+  fn summarize(&self, instructions: &str, notes: &str) -> Result<String, String>;
+}
+
+// Programs the "command" provider can run, by engine name, looked up on PATH.
+// Settings only name an engine and a model, so a script in the webview can't
+// make the backend run anything else.
+const COMMAND_ENGINES: &[&str] = &["llm", "ollama"];
+
+// Runs the engine's program with the instructions and notes on stdin
+struct CommandProvider {
+  program: &'static str,
+  args: Vec<String>,
+}
+
+impl SummaryProvider for CommandProvider {
+  fn summarize(&self, instructions: &str, notes: &str) -> Result<String, String> {
+    let mut child = Command::new(self.program)
+      .args(&self.args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .map_err(|e| format!("Failed to start summarization command '{}': {}", self.program, e))?;
+    // Dropped at the end of the block, closing stdin so the program sees EOF
+    if let Some(mut stdin) = child.stdin.take() {
+      stdin.write_all(format!("{}\n\n{}", instructions, notes).as_bytes())
+        .map_err(|e| format!("Failed to write to summarization command '{}': {}", self.program, e))?;
+    }
+    let output = child.wait_with_output()
+      .map_err(|e| format!("Summarization command '{}' failed: {}", self.program, e))?;
+    if !output.status.success() {
+      return Err(format!("Summarization command '{}' failed ({}): {}",
+        self.program, output.status, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+  }
+}
+
+// llama.cpp's built-in server (`llama-server`), through its /completion endpoint
+struct LlamaProvider {
+  endpoint: String,
+}
+
+#[derive(Serialize)]
+struct LlamaRequest<'a> {
+  prompt: &'a str,
+  n_predict: u32,
+  stream: bool,
+}
+
+#[derive(Deserialize)]
+struct LlamaResponse {
+  content: String,
+}
+
+impl SummaryProvider for LlamaProvider {
+  fn summarize(&self, instructions: &str, notes: &str) -> Result<String, String> {
+    let url = format!("{}/completion", self.endpoint.trim_end_matches('/'));
+    let prompt = format!("{}\n\n{}\n\nSummary:", instructions, notes);
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    let response: LlamaResponse = agent.post(&url)
+      .send_json(LlamaRequest { prompt: &prompt, n_predict: LLAMA_MAX_TOKENS, stream: false })
+      .map_err(|e| format!("Failed to fetch summary from '{}': {}", url, e))?
+      .into_json()
+      .map_err(|e| format!("Invalid summary response from '{}': {}", url, e))?;
+    Ok(response.content.trim().to_string())
+  }
+}
+
+// Chat completions API as OpenAI defines it, which Ollama, LM Studio, vLLM
+// and most hosted services also speak
+struct HttpProvider {
+  endpoint: String,
+  api_key: Option<String>,
+  model: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+  role: &'a str,
+  content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  model: Option<&'a str>,
+  messages: [ChatMessage<'a>; 2],
+  stream: bool,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+  choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+  message: ChatReply,
+}
+
+#[derive(Deserialize)]
+struct ChatReply {
+  content: Option<String>,
+}
+
+impl SummaryProvider for HttpProvider {
+  fn summarize(&self, instructions: &str, notes: &str) -> Result<String, String> {
+    let agent = ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build();
+    let mut request = agent.post(&self.endpoint);
+    if let Some(key) = self.api_key.as_deref().filter(|k| !k.is_empty()) {
+      request = request.set("Authorization", &format!("Bearer {}", key));
+    }
+    let response: ChatResponse = request
+      .send_json(ChatRequest {
+        model: self.model.as_deref(),
+        messages: [
+          ChatMessage { role: "system", content: instructions },
+          ChatMessage { role: "user", content: notes },
+        ],
+        stream: false,
+      })
+      .map_err(|e| format!("Failed to fetch summary from '{}': {}", self.endpoint, e))?
+      .into_json()
+      .map_err(|e| format!("Invalid summary response from '{}': {}", self.endpoint, e))?;
+    response.choices.into_iter()
+      .find_map(|choice| choice.message.content)
+      .map(|content| content.trim().to_string())
+      .ok_or_else(|| format!("Invalid summary response from '{}': no summary in it", self.endpoint))
+  }
+}
+
+// Model names such as "llama3.1:8b" or "mlx-community/Qwen2.5-7B"; a leading
+// '-' would be read as an option
+fn is_model_name(model: &str) -> bool {
+  model.len() <= 128
+    && model.starts_with(|c: char| c.is_ascii_alphanumeric())
+    && model.chars().all(|c| c.is_ascii_alphanumeric() || "-_.:/@".contains(c))
+}
+
+pub fn provider(settings: &settings::SummarizationSettings) -> Result<Box<dyn SummaryProvider>, String> {
+  let endpoint = || settings.endpoint.clone().filter(|e| !e.trim().is_empty())
+    .ok_or("Invalid summarization settings: no endpoint configured");
+  match settings.provider.as_str() {
+    "command" => {
+      let engine = settings.engine.as_deref().map(str::trim).filter(|e| !e.is_empty())
+        .ok_or("Invalid summarization settings: no engine configured")?;
+      let model = settings.model.as_deref().map(str::trim).filter(|m| !m.is_empty());
+      if let Some(model) = model.filter(|m| !is_model_name(m)) {
+        return Err(format!("Invalid summarization settings: invalid model name '{}'", model));
+      }
+      let (program, args) = match (engine, model) {
+        ("llm", None) => ("llm", Vec::new()),
+        ("llm", Some(model)) => ("llm", vec!["-m".to_string(), model.to_string()]),
+        ("ollama", Some(model)) => ("ollama", vec!["run".to_string(), model.to_string()]),
+        ("ollama", None) => return Err("Invalid summarization settings: the ollama engine needs a model".to_string()),
+        _ => return Err(format!("Unsupported summarization engine '{}'. Must be one of: {}", engine, COMMAND_ENGINES.join(", "))),
+      };
+      Ok(Box::new(CommandProvider { program, args }))
+    },
+    "llama" => Ok(Box::new(LlamaProvider { endpoint: endpoint()? })),
+    "http" => Ok(Box::new(HttpProvider {
+      endpoint: endpoint()?,
+      api_key: settings.api_key.clone(),
+      model: settings.model.clone().filter(|m| !m.trim().is_empty()),
+    })),
+    "" | "none" => Err("Summarization is not configured; choose a summarization provider in settings".to_string()),
+    other => Err(format!("Unsupported summarization provider '{}'. Must be one of: command, llama, http", other)),
+  }
+}
+
+// A draft note summarizing the given notes, placed below them. It records
+// the notes it summarizes in its properties.
+pub fn summarize_notes(
+  doc: &model::BoardDocument,
+  note_ids: &[model::ID],
+  instructions: Option<&str>,
+  provider: &dyn SummaryProvider,
+) -> Result<SummaryDraft, String> {
+  let mut sources = Vec::new();
+  let mut skipped = Vec::new();
+  for note in doc.notes.iter().filter(|n| note_ids.contains(&n.id)) {
+    if confidential::is_confidential(note) || note.text.trim().is_empty() {
+      skipped.push(note.id.clone());
+    } else {
+      sources.push(note);
+    }
+  }
+  if sources.is_empty() {
+    return Err("None of the selected notes has text that can be summarized".to_string());
+  }
+
+  let texts: Vec<String> = sources.iter().map(|n| language::note_text(n)).collect();
+  let instructions = instructions.map(str::trim).filter(|i| !i.is_empty()).unwrap_or(DEFAULT_INSTRUCTIONS);
+  let text = provider.summarize(instructions, &texts.join(NOTE_SEPARATOR))?;
+  if text.is_empty() {
+    return Err("The summarization provider returned an empty summary".to_string());
+  }
+
+  let left = sources.iter().map(|n| n.frame.x).fold(f64::INFINITY, f64::min);
+  let bottom = sources.iter().map(|n| n.frame.y + n.frame.h).fold(f64::NEG_INFINITY, f64::max);
+  let source_ids: Vec<model::ID> = sources.iter().map(|n| n.id.clone()).collect();
+  let mut properties = std::collections::HashMap::new();
+  properties.insert(
+    "summaryOf".to_string(),
+    serde_json::Value::Array(source_ids.iter().cloned().map(serde_json::Value::String).collect()),
+  );
+  let frame = model::Rect { x: left, y: bottom + SUMMARY_GAP, w: highlights::NOTE_WIDTH, h: highlights::note_height(&text) };
+  let mut note = outline::imported_note(&text, frame);
+  note.layer_id = sources[0].layer_id.clone();
+  note.properties = Some(properties);
+
+  Ok(SummaryDraft { note, sources: source_ids, skipped })
+}
@@ -0,0 +1,247 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// Boards are never written in place: a crash or a full disk halfway through
+// would leave a corrupt file and lose the previous version with it. Instead
+// the board goes to a temporary file, is flushed to disk and then renamed over
+// the target in one step, so the target is always either the old board or the
+// new one. The file replaced can be kept as `name.fim.bak1`, with older ones
+// shifted up to `.bakN` (the `keepVersions` setting).
+//
+// Saving into a folder a sync client watches (Dropbox, iCloud Drive, OneDrive,
+// Google Drive) races with the client: it may upload the temporary file, or
+// hold the target open while uploading so that the rename fails. In sync-safe
+// mode the temporary file is written outside the synced folder instead, and
+// moved over the target once the sync client has let go of it. The
+// `writeMode` setting picks the mode: "auto" (sync-safe inside folders that
+// look synced), "syncSafe" or "direct" (temporary file next to the target).
+
+// Names of the folders sync clients keep in sync
+const SYNC_FOLDER_MARKERS: &[&str] = &[
+  "dropbox", "icloud drive", "mobile documents", "com~apple~clouddocs", "onedrive",
+  "google drive", "googledrive", "my drive", "box sync", "pcloud drive", "nextcloud", "owncloud",
+];
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(250);
+// How long to hold off while the sync client has the target open
+const LOCK_TIMEOUT: Duration = Duration::from_secs(15);
+const TEMP_DIR: &str = "fim-save";
+const MAX_KEEP_VERSIONS: usize = 20;
+// Tells apart the temporary files of saves running at the same time
+static TEMP_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+  pub sync_safe: bool,
+  // Replaced versions to keep next to the target, newest as `.bak1`
+  pub keep_versions: usize,
+}
+
+impl WriteOptions {
+  // Options for writing `path` under the `writeMode` and `keepVersions` settings
+  pub fn new(mode: &str, keep_versions: usize, path: &Path) -> Self {
+    WriteOptions {
+      sync_safe: is_sync_safe(mode, path),
+      keep_versions: keep_versions.min(MAX_KEEP_VERSIONS),
+    }
+  }
+}
+
+pub fn in_synced_folder(path: &Path) -> bool {
+  path.ancestors().skip(1).filter_map(Path::file_name).any(|name| {
+    let name = name.to_string_lossy().to_lowercase();
+    // Clients add the account to the name: "Dropbox (Company)",
+    // "OneDrive - Company", "GoogleDrive-user@example.com"
+    SYNC_FOLDER_MARKERS.iter().any(|marker| {
+      name.strip_prefix(marker).is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '-']))
+    })
+  })
+}
+
+// Whether saves to `path` go through a temporary file under `mode`
+pub fn is_sync_safe(mode: &str, path: &Path) -> bool {
+  match mode {
+    "direct" => false,
+    "syncSafe" => true,
+    _ => in_synced_folder(path),
+  }
+}
+
+fn temp_path(target: &Path) -> PathBuf {
+  let millis = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis())
+    .unwrap_or(0);
+  let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+  std::env::temp_dir().join(TEMP_DIR).join(format!("{}-{}-{}", std::process::id(), millis, name))
+}
+
+// Hidden file next to the target, so the rename stays on one file system.
+// Unique per process and save, so concurrent saves never share one.
+fn local_temp_path(target: &Path) -> PathBuf {
+  let name = target.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "board".to_string());
+  let sequence = TEMP_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+  target.with_file_name(format!(".{}.{}-{}.saving", name, std::process::id(), sequence))
+}
+
+fn backup_path(target: &Path, n: usize) -> PathBuf {
+  let mut name = target.file_name().unwrap_or_default().to_os_string();
+  name.push(format!(".bak{}", n));
+  target.with_file_name(name)
+}
+
+// Delete the target's `.bakN` copies numbered `from` and up: the oldest one
+// about to be pushed out, and any left from a higher `keep_versions`
+fn remove_backups_from(target: &Path, from: usize) {
+  let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+    return;
+  };
+  let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+  let Ok(entries) = std::fs::read_dir(dir) else {
+    return;
+  };
+  let prefix = format!("{}.bak", name.to_string_lossy());
+  for entry in entries.flatten() {
+    let file_name = entry.file_name().to_string_lossy().to_string();
+    let number = file_name.strip_prefix(&prefix).and_then(|n| n.parse::<usize>().ok());
+    if number.is_some_and(|n| n >= from) {
+      if let Err(e) = std::fs::remove_file(entry.path()) {
+        log::warn!("Failed to delete old version '{}': {}", entry.path().display(), e);
+      }
+    }
+  }
+}
+
+// Keep the file about to be replaced as `.bak1`, shifting older copies up and
+// dropping the oldest. A hard link keeps it without copying the data, as the
+// rename that follows only swaps which file the target name points to.
+fn keep_previous(target: &Path, keep: usize) -> Result<(), String> {
+  remove_backups_from(target, keep.max(1));
+  if keep == 0 || !target.is_file() {
+    return Ok(());
+  }
+  for n in (1..keep).rev() {
+    let from = backup_path(target, n);
+    if from.exists() {
+      let to = backup_path(target, n + 1);
+      std::fs::rename(&from, &to)
+        .map_err(|e| format!("Failed to move '{}' to '{}': {}", from.display(), to.display(), e))?;
+    }
+  }
+  let newest = backup_path(target, 1);
+  let _ = std::fs::remove_file(&newest);
+  if std::fs::hard_link(target, &newest).is_err() {
+    std::fs::copy(target, &newest)
+      .map_err(|e| format!("Failed to keep the previous version as '{}': {}", newest.display(), e))?;
+  }
+  Ok(())
+}
+
+// A file the sync client has open can't be opened for writing on Windows
+// (sharing or lock violation); elsewhere sync clients don't lock files
+fn is_locked(target: &Path) -> bool {
+  match std::fs::OpenOptions::new().append(true).open(target) {
+    Ok(_) => false,
+    Err(e) => cfg!(windows) && matches!(e.raw_os_error(), Some(32) | Some(33)),
+  }
+}
+
+fn wait_until_unlocked(target: &Path) -> Result<(), String> {
+  let start = Instant::now();
+  while is_locked(target) {
+    if start.elapsed() >= LOCK_TIMEOUT {
+      return Err(format!(
+        "'{}' is still in use, probably by a sync client; try saving again once syncing has finished",
+        target.display()
+      ));
+    }
+    std::thread::sleep(LOCK_RETRY_DELAY);
+  }
+  Ok(())
+}
+
+// Opened for writing: Windows' FlushFileBuffers refuses read-only handles
+fn sync_file(path: &Path) -> Result<(), String> {
+  std::fs::OpenOptions::new().write(true).open(path)
+    .and_then(|file| file.sync_all())
+    .map_err(|e| format!("Failed to flush '{}' to disk: {}", path.display(), e))
+}
+
+// Make the rename itself durable
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+  if let Err(e) = std::fs::File::open(dir).and_then(|d| d.sync_all()) {
+    log::warn!("Failed to flush folder '{}': {}", dir.display(), e);
+  }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
+
+// Move the finished temporary file over `target`. Across file systems a
+// rename isn't possible, so the file is first copied next to the target under
+// a hidden name and renamed from there.
+fn commit(temp: &Path, target: &Path) -> Result<(), String> {
+  let dir = target.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+  if std::fs::rename(temp, target).is_err() {
+    let staged = local_temp_path(target);
+    let staging = std::fs::copy(temp, &staged)
+      .map_err(|e| format!("Failed to write '{}': {}", staged.display(), e))
+      .and_then(|_| sync_file(&staged))
+      .and_then(|_| std::fs::rename(&staged, target).map_err(|e| format!("Failed to replace '{}': {}", target.display(), e)));
+    let _ = std::fs::remove_file(temp);
+    if staging.is_err() {
+      let _ = std::fs::remove_file(&staged);
+    }
+    staging?;
+  }
+  sync_dir(dir);
+  Ok(())
+}
+
+// Temporary files left behind by saves that never finished, e.g. when the
+// app crashed mid-save: from another process and older than `min_age`
+pub fn stale_temp_files(min_age: Duration) -> Vec<PathBuf> {
+  let own_prefix = format!("{}-", std::process::id());
+  let Ok(entries) = std::fs::read_dir(std::env::temp_dir().join(TEMP_DIR)) else {
+    return Vec::new();
+  };
+  entries.flatten()
+    .filter(|entry| !entry.file_name().to_string_lossy().starts_with(&own_prefix))
+    .filter(|entry| {
+      entry.metadata().and_then(|m| m.modified()).ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age >= min_age)
+    })
+    .map(|entry| entry.path())
+    .collect()
+}
+
+// Run `write` against a temporary file that then replaces `target`
+pub fn write_with(target: &Path, options: WriteOptions, write: impl FnOnce(&Path) -> Result<(), String>) -> Result<(), String> {
+  let temp = if options.sync_safe {
+    let temp = temp_path(target);
+    if let Some(parent) = temp.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create temporary folder '{}': {}", parent.display(), e))?;
+    }
+    temp
+  } else {
+    local_temp_path(target)
+  };
+  let result = write(&temp).and_then(|_| {
+    sync_file(&temp)?;
+    if options.sync_safe {
+      wait_until_unlocked(target)?;
+    }
+    // Losing the backup mustn't lose the save too
+    if let Err(e) = keep_previous(target, options.keep_versions) {
+      log::warn!("{}", e);
+    }
+    commit(&temp, target)
+  });
+  if result.is_err() && temp.exists() {
+    let _ = std::fs::remove_file(&temp);
+  }
+  result
+}
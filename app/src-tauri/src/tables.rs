@@ -0,0 +1,113 @@
+use crate::model;
+
+// Table notes carry a grid of cell text next to (or instead of) their prose.
+// Rows may be ragged in hand-edited files, so every renderer pads them out to
+// the widest row.
+
+pub fn column_count(table: &model::NoteTable) -> usize {
+  table.rows.iter().map(|r| r.len()).chain(std::iter::once(table.columns.len())).max().unwrap_or(0)
+}
+
+// Header row followed by the body rows, all padded to the same width, with
+// cell text flattened onto a single line.
+fn grid(table: &model::NoteTable) -> Vec<Vec<String>> {
+  let columns = column_count(table);
+  let header = (!table.columns.is_empty()).then_some(&table.columns);
+  header.into_iter().chain(&table.rows)
+    .map(|row| (0..columns)
+      .map(|i| row.get(i).map(|cell| cell.split_whitespace().collect::<Vec<_>>().join(" ")).unwrap_or_default())
+      .collect())
+    .collect()
+}
+
+// Plain-text table with space-aligned columns and a rule under the header
+pub fn to_aligned_text(table: &model::NoteTable) -> String {
+  let rows = grid(table);
+  let mut widths = vec![0; column_count(table)];
+  for row in &rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.chars().count());
+    }
+  }
+
+  let format_row = |row: &Vec<String>| {
+    let cells: Vec<String> = row.iter().zip(&widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+    cells.join("  ").trim_end().to_string()
+  };
+
+  let mut lines = Vec::new();
+  for (index, row) in rows.iter().enumerate() {
+    lines.push(format_row(row));
+    if index == 0 && !table.columns.is_empty() {
+      lines.push(widths.iter().map(|w| "-".repeat(*w)).collect::<Vec<_>>().join("  "));
+    }
+  }
+  lines.join("\n")
+}
+
+pub fn to_markdown(table: &model::NoteTable) -> String {
+  let columns = column_count(table);
+  if columns == 0 {
+    return String::new();
+  }
+
+  let escape = |cell: &String| cell.replace('|', "\\|");
+  let format_row = |row: &Vec<String>| format!("| {} |", row.iter().map(escape).collect::<Vec<_>>().join(" | "));
+
+  // Markdown tables require a header row; use an empty one if the table has none
+  let mut rows = grid(table);
+  if table.columns.is_empty() {
+    rows.insert(0, vec![String::new(); columns]);
+  }
+
+  let mut lines = vec![format_row(&rows[0]), format!("|{}|", vec![" --- "; columns].join("|"))];
+  lines.extend(rows[1..].iter().map(format_row));
+  lines.join("\n")
+}
+
+pub fn to_html(table: &model::NoteTable, escape: fn(&str) -> String) -> String {
+  let mut rows = grid(table).into_iter();
+  let mut html = "<table>".to_string();
+  if !table.columns.is_empty() {
+    if let Some(header) = rows.next() {
+      html += "<tr>";
+      for cell in header {
+        html += &format!("<th>{}</th>", escape(&cell));
+      }
+      html += "</tr>";
+    }
+  }
+  for row in rows {
+    html += "<tr>";
+    for cell in row {
+      html += &format!("<td>{}</td>", escape(&cell));
+    }
+    html += "</tr>";
+  }
+  html += "</table>";
+  html
+}
+
+// RTF table rows; `escape` is the exporter's RTF escaping
+pub fn to_rtf(table: &model::NoteTable, escape: fn(&str) -> String) -> String {
+  const CELL_WIDTH_TWIPS: usize = 2000;
+  let columns = column_count(table);
+  let mut rtf = String::new();
+  for (index, row) in grid(table).iter().enumerate() {
+    rtf += "\\trowd\\trgaph108";
+    for column in 1..=columns {
+      rtf += &format!("\\cellx{}", column * CELL_WIDTH_TWIPS);
+    }
+    let bold = index == 0 && !table.columns.is_empty();
+    for cell in row {
+      if bold {
+        rtf += &format!("\\pard\\intbl\\b {}\\b0\\cell", escape(cell));
+      } else {
+        rtf += &format!("\\pard\\intbl {}\\cell", escape(cell));
+      }
+    }
+    rtf += "\\row";
+  }
+  rtf += "\\pard";
+  rtf
+}
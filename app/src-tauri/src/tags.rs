@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::model;
+
+// Tags are free-form keywords on notes, for boards with too many notes to
+// organize by color or by the label palette alone. Unlike labels they need no
+// definition: a tag exists while some note carries it. Tags are compared
+// without a leading '#', surrounding whitespace or case, so "#Research",
+// "research" and " Research " are the same tag; the first spelling found is
+// the one shown.
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TagFilter {
+  pub tags: Vec<String>,
+  // "any" (default): note carries at least one of the tags; "all": every one
+  pub mode: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TagCount {
+  pub tag: String,
+  pub count: usize,
+}
+
+// `tag` as stored, or None when nothing is left of it
+pub fn normalize(tag: &str) -> Option<String> {
+  let tag = tag.trim().trim_start_matches('#').split_whitespace().collect::<Vec<_>>().join(" ");
+  (!tag.is_empty()).then_some(tag)
+}
+
+fn key(tag: &str) -> Option<String> {
+  normalize(tag).map(|tag| tag.to_lowercase())
+}
+
+// The note's tags, normalized, without duplicates
+pub fn note_tags(note: &model::Note) -> Vec<String> {
+  let mut seen = HashSet::new();
+  note.tags.iter().flatten()
+    .filter_map(|tag| normalize(tag))
+    .filter(|tag| seen.insert(tag.to_lowercase()))
+    .collect()
+}
+
+// Every tag on the board with the number of notes carrying it, by name
+pub fn all_tags(doc: &model::BoardDocument) -> Vec<TagCount> {
+  let mut counts: BTreeMap<String, TagCount> = BTreeMap::new();
+  for tag in doc.notes.iter().flat_map(note_tags) {
+    counts.entry(tag.to_lowercase())
+      .or_insert_with(|| TagCount { tag, count: 0 })
+      .count += 1;
+  }
+  counts.into_values().collect()
+}
+
+pub fn filter_note_ids(doc: &model::BoardDocument, filter: &TagFilter) -> Result<Vec<model::ID>, String> {
+  let wanted: HashSet<String> = filter.tags.iter().filter_map(|tag| key(tag)).collect();
+  if wanted.is_empty() {
+    return Err("No tags to filter by".to_string());
+  }
+  let require_all = match filter.mode.as_deref() {
+    None | Some("any") => false,
+    Some("all") => true,
+    Some(other) => return Err(format!("Unsupported tag filter mode '{}'. Must be one of: any, all", other)),
+  };
+
+  Ok(doc.notes.iter()
+    .filter(|note| {
+      let tags: HashSet<String> = note_tags(note).iter().map(|tag| tag.to_lowercase()).collect();
+      if require_all {
+        wanted.is_subset(&tags)
+      } else {
+        !wanted.is_disjoint(&tags)
+      }
+    })
+    .map(|note| note.id.clone())
+    .collect())
+}
+
+// `#tag` as note-taking apps write it; characters they don't allow in tags
+// become '-'
+pub fn hashtag(tag: &str) -> String {
+  let body: String = tag.chars()
+    .map(|c| if c.is_alphanumeric() || matches!(c, '_' | '-' | '/') { c } else { '-' })
+    .collect();
+  format!("#{}", body)
+}
@@ -0,0 +1,85 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Registry of long-running operations (exports, folder imports, ...) so they
+// can be listed and cancelled while they run. Each task is identified by the
+// operation ID of its progress events; cancelling sets a flag the operation
+// polls at safe points, after which it fails with CANCELLED.
+
+// Error of a cancelled task
+pub const CANCELLED: &str = "Task cancelled";
+// Finished tasks kept for `list_tasks`
+const MAX_FINISHED_TASKS: usize = 20;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TaskInfo {
+  pub id: String,
+  // What the task does, e.g. "export-walkthrough"
+  pub kind: String,
+  // "running", "cancelling", "completed", "failed" or "cancelled"
+  pub status: String,
+  pub started_at: chrono::DateTime<chrono::Utc>,
+  pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+  // Oldest first
+  tasks: Vec<(TaskInfo, Arc<AtomicBool>)>,
+}
+
+impl TaskRegistry {
+  pub fn start(&mut self, id: &str, kind: &str, cancelled: Arc<AtomicBool>) {
+    let info = TaskInfo {
+      id: id.to_string(),
+      kind: kind.to_string(),
+      status: "running".to_string(),
+      started_at: chrono::Utc::now(),
+      finished_at: None,
+      error: None,
+    };
+    self.tasks.push((info, cancelled));
+  }
+
+  pub fn finish(&mut self, id: &str, error: Option<&str>) {
+    if let Some((info, _)) = self.tasks.iter_mut().find(|(info, _)| info.id == id) {
+      info.status = match error {
+        None => "completed",
+        Some(CANCELLED) => "cancelled",
+        Some(_) => "failed",
+      }.to_string();
+      info.error = error.map(str::to_string);
+      info.finished_at = Some(chrono::Utc::now());
+    }
+
+    let finished = self.tasks.iter().filter(|(info, _)| info.finished_at.is_some()).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED_TASKS);
+    self.tasks.retain(|(info, _)| {
+      if excess > 0 && info.finished_at.is_some() {
+        excess -= 1;
+        return false;
+      }
+      true
+    });
+  }
+
+  pub fn cancel(&mut self, id: &str) -> Result<(), String> {
+    let (info, cancelled) = self.tasks.iter_mut().find(|(info, _)| info.id == id)
+      .ok_or_else(|| format!("No task with ID '{}'", id))?;
+    if info.finished_at.is_some() {
+      return Err(format!("Task '{}' has already finished", id));
+    }
+    cancelled.store(true, Ordering::Relaxed);
+    info.status = "cancelling".to_string();
+    Ok(())
+  }
+
+  // Running tasks first, then finished ones, newest first within each
+  pub fn list(&self) -> Vec<TaskInfo> {
+    let mut tasks: Vec<TaskInfo> = self.tasks.iter().rev().map(|(info, _)| info.clone()).collect();
+    tasks.sort_by_key(|info| info.finished_at.is_some());
+    tasks
+  }
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{model, rich_text};
+
+// Note templates are blueprints stored in the document: ordinary notes whose
+// text (and checklist/table cells) may contain `{{placeholder}}` markers and
+// whose frames are relative to the point the template is inserted at.
+
+// Placeholders filled in automatically unless the caller supplies a value
+const BUILTIN_DATE: &str = "date";
+
+// A placeholder replaced in the text: where it was in the template text and
+// where its value is in the filled-in text, in UTF-16 code units like spans
+struct Replacement {
+  old_start: usize,
+  old_len: usize,
+  new_start: usize,
+  new_len: usize,
+}
+
+// Call `f` for each `{{name}}` in `text` and splice in what it returns
+fn substitute(text: &str, mut f: impl FnMut(&str) -> Option<String>) -> (String, Vec<Replacement>) {
+  let mut output = String::new();
+  let mut replacements = Vec::new();
+  let mut rest = text;
+  while let Some(start) = rest.find("{{") {
+    let Some(length) = rest[start + 2..].find("}}") else {
+      break;
+    };
+    let name = rest[start + 2..start + 2 + length].trim();
+    output += &rest[..start];
+    let marker = &rest[start..start + length + 4];
+    match f(name) {
+      Some(value) => {
+        replacements.push(Replacement {
+          old_start: rich_text::utf16_len(&text[..text.len() - rest.len() + start]),
+          old_len: rich_text::utf16_len(marker),
+          new_start: rich_text::utf16_len(&output),
+          new_len: rich_text::utf16_len(&value),
+        });
+        output += &value;
+      },
+      None => output += marker,
+    }
+    rest = &rest[start + length + 4..];
+  }
+  output += rest;
+  (output, replacements)
+}
+
+// Where a span boundary in the template text lands in the filled-in text.
+// A boundary inside a placeholder moves to the start of its value, or to the
+// end for the end of a span, so the span covers the whole value.
+fn shift_offset(offset: usize, replacements: &[Replacement], is_end: bool) -> usize {
+  let mut delta = 0isize;
+  for replacement in replacements {
+    if offset <= replacement.old_start {
+      break;
+    }
+    if offset < replacement.old_start + replacement.old_len {
+      return replacement.new_start + if is_end { replacement.new_len } else { 0 };
+    }
+    delta = (replacement.new_start + replacement.new_len) as isize - (replacement.old_start + replacement.old_len) as isize;
+  }
+  (offset as isize + delta).max(0) as usize
+}
+
+// Spans of the template text moved along with the text around them, clamped
+// to the filled-in text; spans left empty are dropped
+fn shift_spans(spans: Vec<model::TextSpan>, replacements: &[Replacement], text_len: usize) -> Vec<model::TextSpan> {
+  spans.into_iter()
+    .filter_map(|span| {
+      let start = shift_offset(span.start, replacements, false).min(text_len);
+      let end = shift_offset(span.end, replacements, true).min(text_len);
+      (start < end).then_some(model::TextSpan { start, end, ..span })
+    })
+    .collect()
+}
+
+fn note_texts(note: &model::Note) -> Vec<&str> {
+  let mut texts = vec![note.text.as_str()];
+  texts.extend(note.checklist.iter().flatten().map(|item| item.text.as_str()));
+  if let Some(table) = &note.table {
+    texts.extend(table.columns.iter().chain(table.rows.iter().flatten()).map(String::as_str));
+  }
+  texts
+}
+
+// Placeholder names used by a template, sorted
+pub fn placeholders(template: &model::NoteTemplate) -> Vec<String> {
+  let mut names = BTreeSet::new();
+  for text in template.notes.iter().flat_map(note_texts) {
+    substitute(text, |name| {
+      names.insert(name.to_string());
+      None
+    });
+  }
+  names.into_iter().collect()
+}
+
+// Produce filled-in notes with fresh IDs at `position`. Notes without a style
+// of their own get the document's default note style.
+pub fn instantiate(
+  doc: &model::BoardDocument,
+  template_id: &str,
+  values: &HashMap<String, String>,
+  position: &model::Point,
+) -> Result<Vec<model::Note>, String> {
+  let template = doc.templates.iter().flatten().find(|t| t.id == template_id)
+    .ok_or_else(|| format!("Template '{}' not found", template_id))?;
+
+  let mut values = values.clone();
+  values.entry(BUILTIN_DATE.to_string())
+    .or_insert_with(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+
+  let missing: Vec<String> = placeholders(template).into_iter().filter(|name| !values.contains_key(name)).collect();
+  if !missing.is_empty() {
+    return Err(format!("Missing values for placeholders: {}", missing.join(", ")));
+  }
+  let fill = |text: &str| substitute(text, |name| values.get(name).cloned());
+
+  let default_style = doc.document_style.as_ref().and_then(|s| s.default_note_style_id.clone());
+  Ok(template.notes.iter()
+    .map(|blueprint| {
+      let mut note = blueprint.clone();
+      note.id = model::new_id("note");
+      // Legacy formatting in richAttrs becomes spans, which follow the text
+      rich_text::migrate_rich_attrs(&mut note);
+      let (text, replacements) = fill(&note.text);
+      note.spans = note.spans.take()
+        .map(|spans| shift_spans(spans, &replacements, rich_text::utf16_len(&text)))
+        .filter(|spans| !spans.is_empty());
+      note.text = text;
+      for item in note.checklist.iter_mut().flatten() {
+        item.text = fill(&item.text).0;
+      }
+      if let Some(table) = note.table.as_mut() {
+        for cell in table.columns.iter_mut().chain(table.rows.iter_mut().flatten()) {
+          *cell = fill(cell).0;
+        }
+      }
+      // Sealed content is bound to the blueprint's id and can't be opened
+      // under the copy's, so a locked confidential note arrives empty
+      note.sealed_text = None;
+      note.frame.x += position.x;
+      note.frame.y += position.y;
+      note.style_id = note.style_id.or_else(|| default_style.clone());
+      // Membership and layering belong to the board the note lands on
+      note.stack_id = None;
+      note.connections = None;
+      note.z_index = None;
+      note
+    })
+    .collect())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn span(start: usize, end: usize) -> model::TextSpan {
+    model::TextSpan { start, end, bold: Some(true), italic: None, underline: None, strike: None, color: None, link: None }
+  }
+
+  fn document_with(note: model::Note) -> model::BoardDocument {
+    let mut doc = model::BoardDocument::empty();
+    doc.templates = Some(vec![model::NoteTemplate { id: "template".to_string(), name: "Meeting".to_string(), notes: vec![note] }]);
+    doc
+  }
+
+  fn instantiate_one(note: model::Note, values: &[(&str, &str)]) -> model::Note {
+    let values = values.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    instantiate(&document_with(note), "template", &values, &model::Point { x: 0.0, y: 0.0 }).unwrap().remove(0)
+  }
+
+  #[test]
+  fn spans_follow_the_text_around_placeholders() {
+    // "Meeting", "{{who}}" and "Monday" are bold
+    let mut note = crate::outline::imported_note("Meeting with {{who}} on Monday", model::Rect { x: 0.0, y: 0.0, w: 200.0, h: 60.0 });
+    note.spans = Some(vec![span(0, 7), span(13, 20), span(24, 30)]);
+    let note = instantiate_one(note, &[("who", "Ann")]);
+    assert_eq!(note.text, "Meeting with Ann on Monday");
+    let ranges: Vec<(usize, usize)> = note.spans.unwrap().iter().map(|s| (s.start, s.end)).collect();
+    assert_eq!(ranges, vec![(0, 7), (13, 16), (20, 26)]);
+  }
+
+  #[test]
+  fn spans_are_clamped_to_shorter_text() {
+    let mut note = crate::outline::imported_note("{{title}}", model::Rect { x: 0.0, y: 0.0, w: 200.0, h: 60.0 });
+    note.spans = Some(vec![span(0, 9), span(5, 9)]);
+    let note = instantiate_one(note, &[("title", "Hi")]);
+    let ranges: Vec<(usize, usize)> = note.spans.unwrap().iter().map(|s| (s.start, s.end)).collect();
+    assert_eq!(ranges, vec![(0, 2), (0, 2)]);
+  }
+
+  #[test]
+  fn sealed_content_is_not_copied() {
+    let mut note = crate::outline::imported_note("", model::Rect { x: 0.0, y: 0.0, w: 200.0, h: 60.0 });
+    note.confidential = Some(true);
+    note.sealed_text = Some("c2VhbGVk".to_string());
+    let note = instantiate_one(note, &[]);
+    assert_eq!(note.sealed_text, None);
+  }
+}
@@ -0,0 +1,177 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::{markdown, model, shapes};
+
+// Word counts and reading time for the insights panel and text exports.
+// A note's text is its rendered text (Markdown converted) plus checklist items
+// and table cells. Words are runs of letters and digits; apostrophes inside a
+// word keep it together ("don't").
+
+// Typical silent reading speed
+const WORDS_PER_MINUTE: f64 = 200.0;
+const TOP_TERMS: usize = 20;
+// Shorter terms are rarely interesting on their own
+const MIN_TERM_LENGTH: usize = 3;
+
+const STOPWORDS: &[&str] = &[
+  "about", "above", "after", "again", "against", "all", "also", "and", "any", "are", "because", "been",
+  "before", "being", "below", "between", "both", "but", "can", "could", "did", "does", "doing", "don't",
+  "down", "during", "each", "few", "for", "from", "further", "had", "has", "have", "having", "her",
+  "here", "hers", "herself", "him", "himself", "his", "how", "into", "it's", "its", "itself", "just",
+  "more", "most", "not", "now", "off", "once", "only", "other", "our", "ours", "ourselves", "out",
+  "over", "own", "same", "she", "should", "some", "such", "than", "that", "the", "their", "theirs",
+  "them", "themselves", "then", "there", "these", "they", "this", "those", "through", "too", "under",
+  "until", "very", "was", "were", "what", "when", "where", "which", "while", "who", "whom", "why",
+  "will", "with", "would", "you", "your", "yours", "yourself", "yourselves",
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TextStats {
+  pub notes: usize,
+  // Notes without any text
+  pub empty_notes: usize,
+  pub words: usize,
+  pub characters: usize,
+  pub average_words_per_note: f64,
+  pub reading_minutes: f64,
+  // Most frequent words outside the stopword list, most frequent first
+  pub top_terms: Vec<TermCount>,
+  pub per_note: Vec<NoteTextStats>,
+  // Notes whose center lies inside each shape; shapes without notes are left out
+  pub shapes: Vec<GroupTextStats>,
+  pub stacks: Vec<GroupTextStats>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TermCount {
+  pub term: String,
+  pub count: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct NoteTextStats {
+  pub id: model::ID,
+  pub words: usize,
+  pub characters: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct GroupTextStats {
+  pub id: model::ID,
+  // Shape label or the first line of the stack's first note
+  pub label: Option<String>,
+  pub notes: usize,
+  pub words: usize,
+  pub reading_minutes: f64,
+}
+
+// Rendered text, checklist items and table cells of a note
+pub fn note_text(note: &model::Note) -> String {
+  let mut parts = vec![markdown::note_rich_text(note).0];
+  parts.extend(note.checklist.iter().flatten().map(|item| item.text.clone()));
+  if let Some(table) = &note.table {
+    parts.extend(table.columns.iter().chain(table.rows.iter().flatten()).cloned());
+  }
+  parts.join("\n")
+}
+
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+  text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’'))
+    .map(|word| word.trim_matches(|c| c == '\'' || c == '’'))
+    .filter(|word| !word.is_empty())
+}
+
+// The word normalized for counting (lowercase, typographic apostrophes
+// straightened); None for stopwords, numbers and very short words
+pub fn term(word: &str) -> Option<String> {
+  let term = word.to_lowercase().replace('’', "'");
+  let counts = term.chars().count() >= MIN_TERM_LENGTH
+    && !term.chars().all(|c| c.is_numeric())
+    && !STOPWORDS.contains(&term.as_str());
+  counts.then_some(term)
+}
+
+fn reading_minutes(words: usize) -> f64 {
+  words as f64 / WORDS_PER_MINUTE
+}
+
+fn group_stats(id: &model::ID, label: Option<String>, members: &[&NoteTextStats]) -> GroupTextStats {
+  let words = members.iter().map(|n| n.words).sum();
+  GroupTextStats { id: id.clone(), label, notes: members.len(), words, reading_minutes: reading_minutes(words) }
+}
+
+pub fn text_stats(doc: &model::BoardDocument) -> TextStats {
+  let mut term_counts: HashMap<String, usize> = HashMap::new();
+  let mut per_note = Vec::with_capacity(doc.notes.len());
+  for note in &doc.notes {
+    let text = note_text(note);
+    let mut count = 0;
+    for word in words(&text) {
+      count += 1;
+      if let Some(term) = term(word) {
+        *term_counts.entry(term).or_default() += 1;
+      }
+    }
+    per_note.push(NoteTextStats { id: note.id.clone(), words: count, characters: text.trim().chars().count() });
+  }
+
+  let words: usize = per_note.iter().map(|n| n.words).sum();
+  let mut top_terms: Vec<TermCount> = term_counts.into_iter().map(|(term, count)| TermCount { term, count }).collect();
+  // Ties alphabetically, so the list is stable between runs
+  top_terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+  top_terms.truncate(TOP_TERMS);
+
+  let by_id: HashMap<&str, &NoteTextStats> = per_note.iter().map(|n| (n.id.as_str(), n)).collect();
+  let shape_stats = doc.shapes.iter()
+    .filter_map(|shape| {
+      let members: Vec<&NoteTextStats> = doc.notes.iter()
+        .filter(|note| shapes::contains_note(shape, note))
+        .filter_map(|note| by_id.get(note.id.as_str()).copied())
+        .collect();
+      (!members.is_empty()).then(|| group_stats(&shape.id, shape.label.clone(), &members))
+    })
+    .collect();
+  let stack_stats = doc.stacks.iter()
+    .map(|stack| {
+      let members: Vec<&NoteTextStats> = stack.note_ids.iter().filter_map(|id| by_id.get(id.as_str()).copied()).collect();
+      let label = stack.note_ids.first()
+        .and_then(|id| doc.notes.iter().find(|n| &n.id == id))
+        .and_then(|note| note.text.lines().map(str::trim).find(|line| !line.is_empty()))
+        .map(str::to_string);
+      group_stats(&stack.id, label, &members)
+    })
+    .collect();
+
+  TextStats {
+    notes: per_note.len(),
+    empty_notes: per_note.iter().filter(|n| n.characters == 0).count(),
+    words,
+    characters: per_note.iter().map(|n| n.characters).sum(),
+    average_words_per_note: if per_note.is_empty() { 0.0 } else { words as f64 / per_note.len() as f64 },
+    reading_minutes: reading_minutes(words),
+    top_terms,
+    per_note,
+    shapes: shape_stats,
+    stacks: stack_stats,
+  }
+}
+
+// Plain-text summary appended to text exports
+pub fn summary(stats: &TextStats) -> String {
+  let mut output = format!(
+    "{} words in {} notes (average {:.1} per note), about {} min reading\n",
+    stats.words, stats.notes, stats.average_words_per_note, stats.reading_minutes.ceil() as u64,
+  );
+  if !stats.top_terms.is_empty() {
+    let terms: Vec<String> = stats.top_terms.iter().take(10).map(|t| format!("{} ({})", t.term, t.count)).collect();
+    output += &format!("Frequent terms: {}\n", terms.join(", "));
+  }
+  for (kind, groups) in [("Shape", &stats.shapes), ("Stack", &stats.stacks)] {
+    for group in groups {
+      output += &format!("{} \"{}\": {} notes, {} words\n",
+        kind, group.label.as_deref().unwrap_or("Untitled"), group.notes, group.words);
+    }
+  }
+  output
+}
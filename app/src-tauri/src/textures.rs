@@ -0,0 +1,119 @@
+use base64::Engine;
+use std::path::Path;
+
+use crate::{lazy_media, media, model};
+
+// Background textures. A texture is one of the board's embedded images that
+// the document background refers to by `textureId`, tiled from the board
+// origin at `textureScale` board pixels per image pixel and drawn over the
+// background color at `textureOpacity`. Like every image it is stored under
+// `media/` in a .fim container; see media.rs.
+
+const DEFAULT_SCALE: f64 = 1.0;
+const DEFAULT_OPACITY: f64 = 1.0;
+// Tiles smaller than this on the board would be drawn millions of times
+const MIN_TILE_SIZE: f64 = 4.0;
+
+// A decoded texture, ready to tile
+pub struct Texture {
+  pub rgba: Vec<u8>,
+  pub width: u32,
+  pub height: u32,
+  pub scale: f64,
+  pub opacity: f64,
+}
+
+fn background(doc: &model::BoardDocument) -> Option<&model::BackgroundStyle> {
+  doc.document_style.as_ref().and_then(|s| s.background.as_ref())
+}
+
+// Add the image at `path` to the board and make it the background texture
+pub fn import_texture(doc: &model::BoardDocument, path: &Path, scale: Option<f64>, opacity: Option<f64>) -> Result<model::BoardDocument, String> {
+  if scale.is_some_and(|s| !s.is_finite() || s <= 0.0) {
+    return Err("Texture scale must be a positive number".to_string());
+  }
+  let (mut document, texture_id) = media::add_image(doc, path)?;
+
+  let style = document.document_style.get_or_insert(model::DocumentStyle {
+    background: None,
+    default_note_style_id: None,
+    default_shape_style_id: None,
+    grid: None,
+    labels: None,
+  });
+  let previous = style.background.as_ref().and_then(|b| b.texture_id.clone());
+  let background = style.background.get_or_insert(model::BackgroundStyle {
+    color: None,
+    texture_id: None,
+    texture_scale: None,
+    texture_opacity: None,
+  });
+  background.texture_id = Some(texture_id);
+  background.texture_scale = scale.or(background.texture_scale);
+  background.texture_opacity = opacity.map(|o| o.clamp(0.0, 1.0)).or(background.texture_opacity);
+  if let Some(previous) = previous {
+    drop_unused_image(&mut document, &previous);
+  }
+  Ok(document)
+}
+
+// Clear the background texture, dropping its image unless notes show it too
+pub fn remove_texture(doc: &model::BoardDocument) -> model::BoardDocument {
+  let mut document = doc.clone();
+  let removed = document.document_style.as_mut()
+    .and_then(|s| s.background.as_mut())
+    .and_then(|b| b.texture_id.take());
+  if let Some(id) = removed {
+    drop_unused_image(&mut document, &id);
+  }
+  document
+}
+
+fn drop_unused_image(doc: &mut model::BoardDocument, id: &model::ID) {
+  let in_use = background(doc).and_then(|b| b.texture_id.as_ref()) == Some(id)
+    || doc.notes.iter().any(|n| n.images.iter().flatten().any(|i| i == id));
+  if !in_use {
+    if let Some(images) = doc.images.as_mut() {
+      images.retain(|i| &i.id != id);
+    }
+  }
+}
+
+// The board's texture, decoded for the native renderers. Only PNG textures
+// can be decoded here; others are drawn by the frontend alone.
+pub fn load(doc: &model::BoardDocument) -> Option<Texture> {
+  let background = background(doc)?;
+  let id = background.texture_id.as_ref()?;
+  let image = doc.images.iter().flatten().find(|i| &i.id == id)?;
+  let data = match lazy_media::is_deferred(image) {
+    true => lazy_media::read(id).ok()?,
+    false => base64::engine::general_purpose::STANDARD.decode(image.data_base64.as_deref()?).ok()?,
+  };
+  if media::sniff_image_mime(&data) != Some("image/png") {
+    return None;
+  }
+  let (rgba, width, height) = media::decode_png_rgba(&data).ok()?;
+  let scale = background.texture_scale.filter(|s| s.is_finite() && *s > 0.0).unwrap_or(DEFAULT_SCALE);
+  if width == 0 || height == 0 || (width.min(height) as f64) * scale < MIN_TILE_SIZE {
+    return None;
+  }
+  Some(Texture {
+    rgba,
+    width,
+    height,
+    scale,
+    opacity: background.texture_opacity.unwrap_or(DEFAULT_OPACITY).clamp(0.0, 1.0),
+  })
+}
+
+impl Texture {
+  // Color of the texture at a board point
+  pub fn sample(&self, x: f64, y: f64) -> [u8; 4] {
+    let tx = ((x / self.scale).floor() as i64).rem_euclid(self.width as i64) as usize;
+    let ty = ((y / self.scale).floor() as i64).rem_euclid(self.height as i64) as usize;
+    let offset = (ty * self.width as usize + tx) * 4;
+    let mut color = [self.rgba[offset], self.rgba[offset + 1], self.rgba[offset + 2], self.rgba[offset + 3]];
+    color[3] = (color[3] as f64 * self.opacity).round() as u8;
+    color
+  }
+}
@@ -0,0 +1,153 @@
+use crate::{model, preview, relations};
+
+// Export-time theme overrides. A board drawn for dark mode prints poorly on
+// white paper, so exports can swap the background and remap every color the
+// document sets. Remapping inverts lightness while keeping hue, saturation and
+// alpha, which keeps text/fill and line/background contrast intact.
+
+// Board background when the document doesn't set one (the app's dark canvas)
+pub const DEFAULT_BACKGROUND: &str = "#202124";
+const LIGHT_BACKGROUND: &str = "#ffffff";
+const DARK_BACKGROUND: &str = "#202124";
+// Connections without a color are drawn translucent white on the dark canvas
+const DEFAULT_CONNECTION_COLOR: [u8; 4] = [255, 255, 255, 153];
+
+type Rgba = [u8; 4];
+
+pub fn to_hex(color: Rgba) -> String {
+  if color[3] == 255 {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+  } else {
+    format!("#{:02x}{:02x}{:02x}{:02x}", color[0], color[1], color[2], color[3])
+  }
+}
+
+// Relative luminance (WCAG)
+fn luminance(color: Rgba) -> f64 {
+  let channel = |c: u8| {
+    let c = c as f64 / 255.0;
+    if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+  };
+  0.2126 * channel(color[0]) + 0.7152 * channel(color[1]) + 0.0722 * channel(color[2])
+}
+
+fn is_dark(color: Rgba) -> bool {
+  luminance(color) < 0.18
+}
+
+// Flip lightness in HSL space, keeping hue, saturation and alpha
+fn invert_lightness(color: Rgba) -> Rgba {
+  let [r, g, b] = [color[0], color[1], color[2]].map(|c| c as f64 / 255.0);
+  let max = r.max(g).max(b);
+  let min = r.min(g).min(b);
+  let lightness = (max + min) / 2.0;
+  let delta = max - min;
+
+  let (hue, saturation) = if delta == 0.0 {
+    (0.0, 0.0)
+  } else {
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+      ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+      (b - r) / delta + 2.0
+    } else {
+      (r - g) / delta + 4.0
+    };
+    (hue * 60.0, saturation)
+  };
+
+  let lightness = 1.0 - lightness;
+  let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+  let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+  let (r, g, b) = match (hue / 60.0) as u32 {
+    0 => (chroma, x, 0.0),
+    1 => (x, chroma, 0.0),
+    2 => (0.0, chroma, x),
+    3 => (0.0, x, chroma),
+    4 => (x, 0.0, chroma),
+    _ => (chroma, 0.0, x),
+  };
+  let m = lightness - chroma / 2.0;
+  let channel = |c: f64| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+  [channel(r), channel(g), channel(b), color[3]]
+}
+
+fn remap(value: &mut Option<String>) {
+  if let Some(color) = value.as_deref().and_then(preview::parse_hex_color) {
+    *value = Some(to_hex(invert_lightness(color)));
+  }
+}
+
+// Copy of `doc` restyled for `theme` ("light", "dark" or "custom" with a
+// `background` color). Colors are only remapped when the target background's
+// darkness differs from the board's; otherwise just the background changes.
+pub fn apply_export_theme(doc: &model::BoardDocument, theme: &str, background: Option<&str>) -> Result<model::BoardDocument, String> {
+  let target = match theme {
+    "light" => LIGHT_BACKGROUND.to_string(),
+    "dark" => DARK_BACKGROUND.to_string(),
+    "custom" => background.ok_or("A custom export theme needs a background color")?.to_string(),
+    other => return Err(format!("Invalid export theme '{}'. Must be one of: light, dark, custom", other)),
+  };
+  let target_color = preview::parse_hex_color(&target)
+    .ok_or_else(|| format!("Invalid background color '{}'", target))?;
+
+  let mut themed = doc.clone();
+  let style = themed.document_style.get_or_insert(model::DocumentStyle {
+    background: None,
+    default_note_style_id: None,
+    default_shape_style_id: None,
+    grid: None,
+    labels: None,
+  });
+  let background = style.background.get_or_insert(model::BackgroundStyle {
+    color: None,
+    texture_id: None,
+    texture_scale: None,
+    texture_opacity: None,
+  });
+  let source_color = background.color.as_deref()
+    .and_then(preview::parse_hex_color)
+    .or_else(|| preview::parse_hex_color(DEFAULT_BACKGROUND))
+    .unwrap_or(target_color);
+  background.color = Some(to_hex(target_color));
+  // Textures are drawn for the original theme
+  background.texture_id = None;
+
+  if is_dark(source_color) == is_dark(target_color) {
+    return Ok(themed);
+  }
+
+  for note_style in &mut themed.note_styles {
+    remap(&mut note_style.fill);
+    remap(&mut note_style.text_style.color);
+    if let Some(border) = note_style.border.as_mut() {
+      remap(&mut border.color);
+    }
+  }
+  for connection in &mut themed.connections {
+    // Pin the renderer's default color first so it gets remapped too
+    if relations::effective_style(doc, connection).and_then(|s| s.color).is_none() {
+      let style = connection.style.get_or_insert(model::ConnectionStyle {
+        kind: None,
+        arrows: None,
+        color: None,
+        width: None,
+        curve_type: None,
+      });
+      style.color = Some(to_hex(DEFAULT_CONNECTION_COLOR));
+    }
+    if let Some(style) = connection.style.as_mut() {
+      remap(&mut style.color);
+    }
+  }
+  for relation in themed.relation_types.iter_mut().flatten() {
+    if let Some(style) = relation.style.as_mut() {
+      remap(&mut style.color);
+    }
+  }
+  for stroke in themed.strokes.iter_mut().flatten() {
+    remap(&mut stroke.color);
+  }
+  Ok(themed)
+}
@@ -0,0 +1,136 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::{markdown, model, properties, text_stats};
+
+// Title suggestions for a board, for the save dialog's file name and the
+// document's `title` property. Candidates come from the board's own `title`
+// property, the most connected root notes (no incoming connections, like
+// outline roots) and the terms that best characterize the board, scored
+// TF-IDF style with every note as a document: terms used often, but not in
+// every note, rank highest.
+
+const MAX_SUGGESTIONS: usize = 5;
+const MAX_ROOT_SUGGESTIONS: usize = 3;
+const MAX_TITLE_CHARS: usize = 60;
+// Terms combined into a keyword title
+const TITLE_TERMS: usize = 3;
+const FALLBACK_FILE_STEM: &str = "untitled";
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TitleSuggestion {
+  pub title: String,
+  // "info", "property", "root" or "terms"
+  pub source: String,
+}
+
+fn first_line(note: &model::Note) -> Option<String> {
+  let text = markdown::note_rich_text(note).0;
+  let line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+  let mut title: String = line.chars().take(MAX_TITLE_CHARS).collect();
+  if line.chars().count() > MAX_TITLE_CHARS {
+    // Cut at the last whole word
+    if let Some(space) = title.rfind(' ') {
+      title.truncate(space);
+    }
+    title += "…";
+  }
+  Some(title)
+}
+
+// Roots with connections, most connected first
+fn root_titles(doc: &model::BoardDocument) -> Vec<String> {
+  let mut degree: HashMap<&str, usize> = HashMap::new();
+  let mut has_parent = HashSet::new();
+  for c in doc.connections.iter().filter(|c| c.src_note_id != c.dst_note_id) {
+    *degree.entry(c.src_note_id.as_str()).or_default() += 1;
+    *degree.entry(c.dst_note_id.as_str()).or_default() += 1;
+    has_parent.insert(c.dst_note_id.as_str());
+  }
+  let mut roots: Vec<(&model::Note, usize)> = doc.notes.iter()
+    .filter(|n| !has_parent.contains(n.id.as_str()))
+    .filter_map(|n| Some((n, *degree.get(n.id.as_str())?)))
+    .collect();
+  // Stable, so equally connected roots keep document order
+  roots.sort_by_key(|(_, degree)| std::cmp::Reverse(*degree));
+  roots.into_iter().filter_map(|(note, _)| first_line(note)).take(MAX_ROOT_SUGGESTIONS).collect()
+}
+
+fn capitalize(term: &str) -> String {
+  let mut chars = term.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+fn terms_title(doc: &model::BoardDocument) -> Option<String> {
+  let mut frequency: HashMap<String, usize> = HashMap::new();
+  let mut note_frequency: HashMap<String, usize> = HashMap::new();
+  let mut documents = 0;
+  for note in &doc.notes {
+    let text = text_stats::note_text(note);
+    let terms: Vec<String> = text_stats::words(&text).filter_map(text_stats::term).collect();
+    if terms.is_empty() {
+      continue;
+    }
+    documents += 1;
+    for term in &terms {
+      *frequency.entry(term.clone()).or_default() += 1;
+    }
+    for term in terms.into_iter().collect::<HashSet<_>>() {
+      *note_frequency.entry(term).or_default() += 1;
+    }
+  }
+
+  let mut scored: Vec<(String, f64)> = frequency.into_iter()
+    .map(|(term, count)| {
+      let idf = (1.0 + documents as f64 / note_frequency[&term] as f64).ln();
+      (term, count as f64 * idf)
+    })
+    .collect();
+  // Ties alphabetically, so suggestions don't change between calls
+  scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  let terms: Vec<String> = scored.into_iter().take(TITLE_TERMS).map(|(term, _)| capitalize(&term)).collect();
+  match terms.as_slice() {
+    [] => None,
+    [only] => Some(only.clone()),
+    [init @ .., last] => Some(format!("{} and {}", init.join(", "), last)),
+  }
+}
+
+pub fn suggest_titles(doc: &model::BoardDocument) -> Vec<TitleSuggestion> {
+  let info = doc.info.as_ref()
+    .and_then(|info| info.title.as_deref())
+    .map(|title| title.trim().to_string())
+    .filter(|title| !title.is_empty());
+  let property = doc.properties.as_ref()
+    .and_then(|p| p.get("title"))
+    .map(properties::value_text)
+    .map(|title| title.trim().to_string())
+    .filter(|title| !title.is_empty());
+
+  let candidates = info.into_iter().map(|title| (title, "info"))
+    .chain(property.into_iter().map(|title| (title, "property")))
+    .chain(root_titles(doc).into_iter().map(|title| (title, "root")))
+    .chain(terms_title(doc).into_iter().map(|title| (title, "terms")));
+
+  let mut seen = HashSet::new();
+  candidates
+    .filter(|(title, _)| seen.insert(title.to_lowercase()))
+    .take(MAX_SUGGESTIONS)
+    .map(|(title, source)| TitleSuggestion { title, source: source.to_string() })
+    .collect()
+}
+
+// File name (without extension) for the save dialog
+pub fn file_stem(doc: &model::BoardDocument) -> String {
+  let Some(suggestion) = suggest_titles(doc).into_iter().next() else {
+    return FALLBACK_FILE_STEM.to_string();
+  };
+  let stem: String = suggestion.title.chars()
+    .filter(|c| !"/\\:*?\"<>|…".contains(*c) && !c.is_control())
+    .collect();
+  let stem = stem.trim().trim_start_matches('.').trim().to_string();
+  if stem.is_empty() { FALLBACK_FILE_STEM.to_string() } else { stem }
+}
@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::model;
+
+// The document's trash: deleted notes and connections stay in
+// `BoardDocument.trash` with their deletion time until restored or emptied.
+// Everything that reads `notes` and `connections` (exports, search, the
+// canvas) ignores the trash without doing anything special; copies of the
+// document handed to others drop it through `without_trash`.
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RestoreResult {
+  pub document: model::BoardDocument,
+  pub restored: Vec<model::ID>,
+  // Requested connections whose notes are still deleted, and IDs that
+  // aren't in the trash
+  pub skipped: Vec<model::ID>,
+}
+
+pub fn move_to_trash(doc: &mut model::BoardDocument, notes: Vec<model::Note>, connections: Vec<model::Connection>) {
+  if notes.is_empty() && connections.is_empty() {
+    return;
+  }
+  let deleted_at = chrono::Utc::now();
+  let trash = doc.trash.get_or_insert_with(model::Trash::default);
+  trash.notes.extend(notes.into_iter().map(|note| model::TrashedNote { note, deleted_at }));
+  trash.connections.extend(connections.into_iter().map(|connection| model::TrashedConnection { connection, deleted_at }));
+}
+
+// Put notes and connections back by ID. A restored note brings back the
+// trashed connections between it and notes on the board, and rejoins its
+// stack if the stack still exists.
+pub fn restore(mut doc: model::BoardDocument, ids: &[model::ID]) -> RestoreResult {
+  let requested: HashSet<&str> = ids.iter().map(|id| id.as_str()).collect();
+  let mut trash = doc.trash.take().unwrap_or_default();
+  let mut restored = Vec::new();
+
+  let (notes, kept): (Vec<_>, Vec<_>) = trash.notes.into_iter()
+    .partition(|trashed| requested.contains(trashed.note.id.as_str()));
+  trash.notes = kept;
+  let restored_notes: HashSet<model::ID> = notes.iter().map(|t| t.note.id.clone()).collect();
+  for model::TrashedNote { mut note, .. } in notes {
+    match note.stack_id.as_deref().and_then(|id| doc.stacks.iter_mut().find(|s| s.id == id)) {
+      Some(stack) => stack.note_ids.push(note.id.clone()),
+      None => note.stack_id = None,
+    }
+    restored.push(note.id.clone());
+    doc.notes.push(note);
+  }
+
+  let on_board: HashSet<model::ID> = doc.notes.iter().map(|n| n.id.clone()).collect();
+  let (connections, kept): (Vec<_>, Vec<_>) = trash.connections.into_iter()
+    .partition(|trashed| {
+      let c = &trashed.connection;
+      let wanted = requested.contains(c.id.as_str())
+        || restored_notes.contains(&c.src_note_id)
+        || restored_notes.contains(&c.dst_note_id);
+      wanted && on_board.contains(&c.src_note_id) && on_board.contains(&c.dst_note_id)
+    });
+  trash.connections = kept;
+  for model::TrashedConnection { connection, .. } in connections {
+    for note in doc.notes.iter_mut().filter(|n| n.id == connection.src_note_id || n.id == connection.dst_note_id) {
+      if let Some(refs) = note.connections.as_mut() {
+        if !refs.contains(&connection.id) {
+          refs.push(connection.id.clone());
+        }
+      }
+    }
+    restored.push(connection.id.clone());
+    doc.connections.push(connection);
+  }
+
+  let done: HashSet<&str> = restored.iter().map(|id| id.as_str()).collect();
+  let skipped = ids.iter().filter(|id| !done.contains(id.as_str())).cloned().collect();
+  doc.trash = (!trash.notes.is_empty() || !trash.connections.is_empty()).then_some(trash);
+  RestoreResult { document: doc, restored, skipped }
+}
+
+// Drop trashed items for good: all of them, or those deleted before
+// `older_than`. Returns how many were removed.
+pub fn empty(doc: &mut model::BoardDocument, older_than: Option<chrono::DateTime<chrono::Utc>>) -> usize {
+  let Some(trash) = doc.trash.as_mut() else {
+    return 0;
+  };
+  let expired = |deleted_at: &chrono::DateTime<chrono::Utc>| older_than.map_or(true, |cutoff| *deleted_at < cutoff);
+  let before = trash.notes.len() + trash.connections.len();
+  trash.notes.retain(|t| !expired(&t.deleted_at));
+  trash.connections.retain(|t| !expired(&t.deleted_at));
+  let removed = before - trash.notes.len() - trash.connections.len();
+  if trash.notes.is_empty() && trash.connections.is_empty() {
+    doc.trash = None;
+  }
+  removed
+}
+
+// The document as shared or exported, without what was deleted
+pub fn without_trash(doc: &model::BoardDocument) -> model::BoardDocument {
+  model::BoardDocument { trash: None, ..doc.clone() }
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::{model, settings};
+
+// Local usage statistics: time spent per board, notes created per day and
+// save counts. Opt-in (the `usageStats` setting) and never sent anywhere;
+// the numbers live in app data for a "what did I work on" review.
+//
+// Time is counted from activity (opening, edits, saves, autosaves): the gap
+// since the previous activity on the same board counts, up to IDLE_GAP, so a
+// board left open overnight doesn't add a night of work.
+
+const USAGE_FILE: &str = "usage-stats.json";
+const IDLE_GAP_SECS: u64 = 5 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DocumentUsage {
+  pub seconds: u64,
+  pub saves: u64,
+  #[serde(rename = "lastActive")]
+  pub last_active: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DayUsage {
+  pub seconds: u64,
+  #[serde(rename = "notesCreated")]
+  pub notes_created: u64,
+  pub saves: u64,
+  // Boards worked on that day
+  #[serde(default)]
+  pub documents: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UsageStats {
+  // Keyed by document path
+  pub documents: BTreeMap<String, DocumentUsage>,
+  // Keyed by local date, YYYY-MM-DD
+  pub days: BTreeMap<String, DayUsage>,
+}
+
+// What `get_usage_stats` returns
+#[derive(Serialize, Debug, Clone)]
+pub struct UsageReport {
+  pub enabled: bool,
+  pub documents: Vec<DocumentReport>,
+  pub days: Vec<DayReport>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DocumentReport {
+  pub path: String,
+  pub display_name: String,
+  pub seconds: u64,
+  pub saves: u64,
+  pub last_active: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct DayReport {
+  pub date: String,
+  pub seconds: u64,
+  pub notes_created: u64,
+  pub saves: u64,
+  pub documents: Vec<String>,
+}
+
+// In-memory side of the statistics, kept in the app state
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+  stats: Option<UsageStats>,
+  // Board and time of the last activity
+  last_activity: Option<(String, Instant)>,
+  // Note IDs each board had when last opened or saved
+  known_notes: HashMap<String, HashSet<model::ID>>,
+}
+
+fn store_path() -> Option<PathBuf> {
+  settings::data_dir().map(|dir| dir.join(USAGE_FILE))
+}
+
+fn today() -> String {
+  chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+pub fn load_stats() -> UsageStats {
+  let Some(path) = store_path() else {
+    return UsageStats::default();
+  };
+  match std::fs::read_to_string(&path) {
+    Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+      log::warn!("Ignoring invalid usage statistics '{}': {}", path.display(), e);
+      UsageStats::default()
+    }),
+    Err(_) => UsageStats::default(),
+  }
+}
+
+fn save_stats(stats: &UsageStats) -> Result<(), String> {
+  let path = store_path().ok_or("Could not determine the app data directory")?;
+  if let Some(parent) = path.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|e| format!("Failed to create app data directory '{}': {}", parent.display(), e))?;
+  }
+  let json = serde_json::to_string_pretty(stats)
+    .map_err(|e| format!("Failed to serialize usage statistics: {}", e))?;
+  std::fs::write(&path, json)
+    .map_err(|e| format!("Failed to write usage statistics '{}': {}", path.display(), e))
+}
+
+impl UsageTracker {
+  fn stats(&mut self) -> &mut UsageStats {
+    self.stats.get_or_insert_with(load_stats)
+  }
+
+  // Count the time since the last activity on the same board
+  fn activity(&mut self, path: &str) {
+    let now = Instant::now();
+    let elapsed = match &self.last_activity {
+      Some((last_path, at)) if last_path == path => now.duration_since(*at).as_secs().min(IDLE_GAP_SECS),
+      _ => 0,
+    };
+    self.last_activity = Some((path.to_string(), now));
+
+    let stats = self.stats();
+    let document = stats.documents.entry(path.to_string()).or_default();
+    document.seconds += elapsed;
+    document.last_active = Some(chrono::Utc::now());
+    let day = stats.days.entry(today()).or_default();
+    day.seconds += elapsed;
+    if !day.documents.iter().any(|d| d == path) {
+      day.documents.push(path.to_string());
+    }
+  }
+
+  pub fn opened(&mut self, path: &Path, doc: &model::BoardDocument) {
+    let path = path.to_string_lossy().to_string();
+    self.known_notes.insert(path.clone(), doc.notes.iter().map(|n| n.id.clone()).collect());
+    self.activity(&path);
+    self.persist();
+  }
+
+  pub fn edited(&mut self, path: &str) {
+    self.activity(path);
+  }
+
+  // `explicit` is false for autosaves, which count time and notes but not saves
+  pub fn saved(&mut self, path: &Path, doc: &model::BoardDocument, explicit: bool) {
+    let path = path.to_string_lossy().to_string();
+    self.activity(&path);
+
+    let ids: HashSet<model::ID> = doc.notes.iter().map(|n| n.id.clone()).collect();
+    // A board saved for the first time counts all its notes as new
+    let created = match self.known_notes.get(&path) {
+      Some(known) => ids.difference(known).count(),
+      None => ids.len(),
+    } as u64;
+    self.known_notes.insert(path.clone(), ids);
+
+    let stats = self.stats();
+    let day = stats.days.entry(today()).or_default();
+    day.notes_created += created;
+    if explicit {
+      day.saves += 1;
+      stats.documents.entry(path).or_default().saves += 1;
+    }
+    self.persist();
+  }
+
+  fn persist(&mut self) {
+    if let Err(e) = save_stats(self.stats()) {
+      log::warn!("{}", e);
+    }
+  }
+
+  pub fn clear(&mut self) -> Result<(), String> {
+    self.stats = Some(UsageStats::default());
+    self.last_activity = None;
+    save_stats(self.stats())
+  }
+
+  // Boards by time spent and the days from `since` (YYYY-MM-DD) on, newest first
+  pub fn report(&mut self, enabled: bool, since: Option<&str>) -> UsageReport {
+    let stats = self.stats().clone();
+    let mut documents: Vec<DocumentReport> = stats.documents.into_iter()
+      .map(|(path, usage)| DocumentReport {
+        display_name: Path::new(&path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| path.clone()),
+        path,
+        seconds: usage.seconds,
+        saves: usage.saves,
+        last_active: usage.last_active,
+      })
+      .collect();
+    documents.sort_by_key(|d| std::cmp::Reverse(d.seconds));
+    let days = stats.days.into_iter().rev()
+      .filter(|(date, _)| since.map_or(true, |since| date.as_str() >= since))
+      .map(|(date, usage)| DayReport {
+        date,
+        seconds: usage.seconds,
+        notes_created: usage.notes_created,
+        saves: usage.saves,
+        documents: usage.documents,
+      })
+      .collect();
+    UsageReport { enabled, documents, days }
+  }
+}
@@ -0,0 +1,345 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::{curves, icons, links, model, relations, shapes, tables};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ValidationIssue {
+  pub severity: Severity,
+  pub message: String,
+  // The note, connection, shape, ... the issue is about, if any
+  pub entity_id: Option<model::ID>,
+}
+
+impl ValidationIssue {
+  fn error(entity_id: Option<&str>, message: String) -> Self {
+    ValidationIssue { severity: Severity::Error, message, entity_id: entity_id.map(str::to_string) }
+  }
+
+  fn warning(entity_id: Option<&str>, message: String) -> Self {
+    ValidationIssue { severity: Severity::Warning, message, entity_id: entity_id.map(str::to_string) }
+  }
+}
+
+// Check a document for structural problems. Errors describe data other code
+// can't make sense of; warnings are tolerated but probably unintended.
+pub fn validate_document(doc: &model::BoardDocument) -> Vec<ValidationIssue> {
+  let mut issues = Vec::new();
+  check_ids(doc, &mut issues);
+  check_references(doc, &mut issues);
+  check_frames(doc, &mut issues);
+  check_relation_types(doc, &mut issues);
+  check_connection_geometry(doc, &mut issues);
+  check_tables(doc, &mut issues);
+  check_text_spans(doc, &mut issues);
+  check_note_links(doc, &mut issues);
+  check_icons(doc, &mut issues);
+  check_shape_kinds(doc, &mut issues);
+  check_layers(doc, &mut issues);
+  issues
+}
+
+// IDs must be unique within each kind of entity; references to a duplicated
+// ID reach only one of them
+fn check_ids(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  let kinds: [(&str, Vec<&str>); 8] = [
+    ("note", doc.notes.iter().map(|n| n.id.as_str()).collect()),
+    ("connection", doc.connections.iter().map(|c| c.id.as_str()).collect()),
+    ("shape", doc.shapes.iter().map(|s| s.id.as_str()).collect()),
+    ("stack", doc.stacks.iter().map(|s| s.id.as_str()).collect()),
+    ("style", doc.note_styles.iter().map(|s| s.id.as_str()).collect()),
+    ("image", doc.images.iter().flatten().map(|i| i.id.as_str()).collect()),
+    ("group", doc.groups.iter().flatten().map(|g| g.id.as_str()).collect()),
+    ("stroke", doc.strokes.iter().flatten().map(|s| s.id.as_str()).collect()),
+  ];
+  for (kind, ids) in kinds {
+    let mut seen = HashSet::new();
+    let mut reported = HashSet::new();
+    for id in ids {
+      if id.is_empty() {
+        issues.push(ValidationIssue::error(None, format!("A {} has an empty ID", kind)));
+      } else if !seen.insert(id) && reported.insert(id) {
+        issues.push(ValidationIssue::error(Some(id), format!("Duplicate {} ID '{}'", kind, id)));
+      }
+    }
+  }
+}
+
+fn check_references(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  let notes: HashSet<&str> = doc.notes.iter().map(|n| n.id.as_str()).collect();
+  let shapes: HashSet<&str> = doc.shapes.iter().map(|s| s.id.as_str()).collect();
+  let stacks: HashSet<&str> = doc.stacks.iter().map(|s| s.id.as_str()).collect();
+  let styles: HashSet<&str> = doc.note_styles.iter().map(|s| s.id.as_str()).collect();
+  let images: HashSet<&str> = doc.images.iter().flatten().map(|i| i.id.as_str()).collect();
+
+  for connection in &doc.connections {
+    for (end, note_id) in [("source", &connection.src_note_id), ("target", &connection.dst_note_id)] {
+      if !notes.contains(note_id.as_str()) {
+        issues.push(ValidationIssue::error(
+          Some(&connection.id),
+          format!("Connection '{}' has missing {} note '{}'", connection.id, end, note_id),
+        ));
+      }
+    }
+  }
+
+  for note in &doc.notes {
+    if let Some(stack_id) = note.stack_id.as_deref().filter(|id| !stacks.contains(id)) {
+      issues.push(ValidationIssue::error(
+        Some(&note.id),
+        format!("Note '{}' is in unknown stack '{}'", note.id, stack_id),
+      ));
+    }
+    // Unknown styles fall back to the default style
+    if let Some(style_id) = note.style_id.as_deref().filter(|id| !styles.contains(id)) {
+      issues.push(ValidationIssue::warning(
+        Some(&note.id),
+        format!("Note '{}' uses unknown style '{}'", note.id, style_id),
+      ));
+    }
+    for image_id in note.images.iter().flatten().filter(|id| !images.contains(id.as_str())) {
+      issues.push(ValidationIssue::error(
+        Some(&note.id),
+        format!("Note '{}' shows image '{}', which the document doesn't contain", note.id, image_id),
+      ));
+    }
+  }
+
+  for shape in &doc.shapes {
+    if let Some(style_id) = shape.style_id.as_deref().filter(|id| !styles.contains(id)) {
+      issues.push(ValidationIssue::warning(
+        Some(&shape.id),
+        format!("Shape '{}' uses unknown style '{}'", shape.id, style_id),
+      ));
+    }
+  }
+
+  for stack in &doc.stacks {
+    for note_id in stack.note_ids.iter().filter(|id| !notes.contains(id.as_str())) {
+      issues.push(ValidationIssue::error(
+        Some(&stack.id),
+        format!("Stack '{}' lists missing note '{}'", stack.id, note_id),
+      ));
+    }
+  }
+
+  for group in doc.groups.iter().flatten() {
+    let missing = group.note_ids.iter().filter(|id| !notes.contains(id.as_str()))
+      .chain(group.shape_ids.iter().filter(|id| !shapes.contains(id.as_str())));
+    for id in missing {
+      issues.push(ValidationIssue::warning(
+        Some(&group.id),
+        format!("Group '{}' lists missing note or shape '{}'", group.id, id),
+      ));
+    }
+  }
+
+  let document_style = doc.document_style.as_ref();
+  let defaults = [
+    ("note", document_style.and_then(|s| s.default_note_style_id.as_deref())),
+    ("shape", document_style.and_then(|s| s.default_shape_style_id.as_deref())),
+  ];
+  for (kind, style_id) in defaults {
+    if let Some(style_id) = style_id.filter(|id| !styles.contains(id)) {
+      issues.push(ValidationIssue::warning(None, format!("The default {} style '{}' doesn't exist", kind, style_id)));
+    }
+  }
+}
+
+// Sizes that are not a number, infinite or negative make the entity, and in
+// some renderers the whole board, draw as nothing
+fn check_frames(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  let frames = doc.notes.iter().map(|n| ("Note", &n.id, &n.frame))
+    .chain(doc.shapes.iter().map(|s| ("Shape", &s.id, &s.frame)));
+  for (kind, id, frame) in frames {
+    if [frame.x, frame.y, frame.w, frame.h].iter().any(|v| !v.is_finite()) {
+      issues.push(ValidationIssue::error(
+        Some(id),
+        format!("{} '{}' has an invalid frame ({}, {}, {} × {})", kind, id, frame.x, frame.y, frame.w, frame.h),
+      ));
+    } else if frame.w < 0.0 || frame.h < 0.0 {
+      issues.push(ValidationIssue::error(
+        Some(id),
+        format!("{} '{}' has a negative size ({} × {})", kind, id, frame.w, frame.h),
+      ));
+    } else if frame.w == 0.0 || frame.h == 0.0 {
+      issues.push(ValidationIssue::warning(
+        Some(id),
+        format!("{} '{}' has no area ({} × {})", kind, id, frame.w, frame.h),
+      ));
+    }
+  }
+
+  for image in doc.images.iter().flatten() {
+    if !image.width.is_finite() || !image.height.is_finite() || image.width < 0.0 || image.height < 0.0 {
+      issues.push(ValidationIssue::error(
+        Some(&image.id),
+        format!("Image '{}' has an invalid size ({} × {})", image.id, image.width, image.height),
+      ));
+    }
+  }
+}
+
+fn check_relation_types(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  let mut ids = HashSet::new();
+  for relation in relations::relation_types(doc) {
+    if !ids.insert(relation.id.as_str()) {
+      issues.push(ValidationIssue::error(Some(&relation.id), format!("Duplicate relation type ID '{}'", relation.id)));
+    }
+    if relation.name.trim().is_empty() {
+      issues.push(ValidationIssue::warning(Some(&relation.id), format!("Relation type '{}' has no name", relation.id)));
+    }
+  }
+
+  for connection in &doc.connections {
+    let Some(relation_id) = connection.relation_type.as_deref() else {
+      continue;
+    };
+    if !ids.contains(relation_id) {
+      issues.push(ValidationIssue::error(
+        Some(&connection.id),
+        format!("Connection '{}' uses unknown relation type '{}'", connection.id, relation_id),
+      ));
+      continue;
+    }
+
+    let arrows = relations::effective_style(doc, connection).and_then(|s| s.arrows);
+    if !relations::is_directed(doc, connection) && matches!(arrows.as_deref(), Some("src" | "dst")) {
+      issues.push(ValidationIssue::warning(
+        Some(&connection.id),
+        format!("Connection '{}' has a one-way arrow but relation type '{}' is undirected", connection.id, relation_id),
+      ));
+    }
+  }
+}
+
+fn check_connection_geometry(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  for connection in &doc.connections {
+    let style = relations::effective_style(doc, connection);
+    let curve_type = style.as_ref().and_then(|s| s.curve_type.as_deref());
+    if let Some(curve_type) = curve_type.filter(|c| !curves::CURVE_TYPES.contains(c)) {
+      issues.push(ValidationIssue::warning(
+        Some(&connection.id),
+        format!("Connection '{}' has unknown curve type '{}'", connection.id, curve_type),
+      ));
+    }
+
+    if curve_type == Some("cubic") {
+      let expected = curves::expected_control_points(connection);
+      let actual = connection.control_points.as_ref().map_or(0, |c| c.len());
+      if actual != expected {
+        issues.push(ValidationIssue::warning(
+          Some(&connection.id),
+          format!("Cubic connection '{}' has {} control points, expected {}", connection.id, actual, expected),
+        ));
+      }
+    }
+  }
+}
+
+fn check_tables(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  for note in &doc.notes {
+    let Some(table) = &note.table else {
+      continue;
+    };
+    let columns = tables::column_count(table);
+    if table.rows.iter().any(|row| row.len() != columns) || (!table.columns.is_empty() && table.columns.len() != columns) {
+      issues.push(ValidationIssue::warning(
+        Some(&note.id),
+        format!("Table in note '{}' has rows of different lengths; short rows are padded", note.id),
+      ));
+    }
+  }
+}
+
+fn check_text_spans(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  for note in &doc.notes {
+    let length: usize = note.text.chars().map(char::len_utf16).sum();
+    for span in note.spans.iter().flatten() {
+      if span.start > span.end || span.end > length {
+        issues.push(ValidationIssue::warning(
+          Some(&note.id),
+          format!("Text span {}..{} in note '{}' is outside its {} characters", span.start, span.end, note.id, length),
+        ));
+      }
+    }
+  }
+}
+
+fn check_note_links(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  for (source, target) in links::broken_note_links(doc) {
+    issues.push(ValidationIssue::warning(
+      Some(&source),
+      format!("Note '{}' links to missing note '{}'", source, target),
+    ));
+  }
+}
+
+// Plain words are treated as icon names; anything else is taken as an emoji
+fn check_icons(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  for note in &doc.notes {
+    let Some(icon) = note.icon.as_deref() else {
+      continue;
+    };
+    let is_word = !icon.is_empty() && icon.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if is_word && !icons::is_named_icon(icon) {
+      issues.push(ValidationIssue::warning(
+        Some(&note.id),
+        format!("Note '{}' uses unknown icon name '{}'", note.id, icon),
+      ));
+    }
+  }
+}
+
+fn check_shape_kinds(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  for shape in &doc.shapes {
+    let kind = shapes::kind(shape);
+    let points = shape.points.as_ref().map_or(0, |p| p.len());
+    match kind {
+      "polygon" if points < 3 => issues.push(ValidationIssue::error(
+        Some(&shape.id),
+        format!("Polygon shape '{}' needs at least 3 points, has {}", shape.id, points),
+      )),
+      "line" if points != 0 && points != 2 => issues.push(ValidationIssue::warning(
+        Some(&shape.id),
+        format!("Line shape '{}' should have 2 points, has {}", shape.id, points),
+      )),
+      _ if !shapes::SHAPE_KINDS.contains(&kind) => issues.push(ValidationIssue::warning(
+        Some(&shape.id),
+        format!("Shape '{}' has unknown kind '{}' and is drawn as a rectangle", shape.id, kind),
+      )),
+      _ => {},
+    }
+  }
+}
+
+fn check_layers(doc: &model::BoardDocument, issues: &mut Vec<ValidationIssue>) {
+  let mut ids = HashSet::new();
+  for layer in doc.layers.iter().flatten() {
+    if !ids.insert(layer.id.as_str()) {
+      issues.push(ValidationIssue::error(Some(&layer.id), format!("Duplicate layer ID '{}'", layer.id)));
+    }
+    if layer.name.trim().is_empty() {
+      issues.push(ValidationIssue::warning(Some(&layer.id), format!("Layer '{}' has no name", layer.id)));
+    }
+  }
+
+  let references = doc.notes.iter().map(|n| (&n.id, &n.layer_id))
+    .chain(doc.shapes.iter().map(|s| (&s.id, &s.layer_id)))
+    .chain(doc.strokes.iter().flatten().map(|s| (&s.id, &s.layer_id)));
+  for (entity_id, layer_id) in references {
+    if let Some(layer_id) = layer_id.as_deref().filter(|id| !ids.contains(id)) {
+      issues.push(ValidationIssue::error(
+        Some(entity_id),
+        format!("'{}' is on unknown layer '{}'", entity_id, layer_id),
+      ));
+    }
+  }
+}
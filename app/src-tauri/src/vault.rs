@@ -0,0 +1,563 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::{markdown, model, outline, raster};
+
+// Markdown vaults: a board written out as a folder of .md files that Obsidian,
+// Logseq and friends can open, and read back after they've been edited.
+//
+// "notes" mode writes one file per note: its text, then a `## Connections`
+// list of [[wiki-links]] to the files of the notes it connects to. Syncing
+// updates note text, turns new files into notes and makes each note's
+// outgoing connections match the wiki-links in its file.
+//
+// "subtrees" mode writes one file per root of the connection tree: the root's
+// text, then its descendants as a nested list whose items carry their note ID
+// in an HTML comment. Syncing updates text and turns new list items into
+// child notes.
+//
+// A manifest in the folder maps note IDs to file names, so files keep their
+// names when notes are renamed.
+
+const MANIFEST_FILE: &str = ".fim-vault.json";
+const CONNECTIONS_HEADING: &str = "## Connections";
+// Separates the root's text from its descendants in subtree files
+const CHILDREN_MARKER: &str = "<!-- fim:children -->";
+const MAX_NAME_CHARS: usize = 60;
+// Index of starred notes; reserved, so no note file takes its name, and
+// skipped when syncing
+const HIGHLIGHTS_FILE: &str = "Highlights.md";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct Manifest {
+  mode: String,
+  // Note ID → file name
+  files: BTreeMap<model::ID, String>,
+  // Whether the export wrote HIGHLIGHTS_FILE
+  #[serde(default)]
+  highlights: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VaultExport {
+  pub mode: String,
+  pub written: Vec<String>,
+  // Files of an earlier export whose notes are gone
+  pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct VaultSync {
+  pub document: model::BoardDocument,
+  pub updated: Vec<model::ID>,
+  pub created: Vec<model::ID>,
+  pub connections_added: usize,
+  pub connections_removed: usize,
+  pub warnings: Vec<String>,
+}
+
+fn read_manifest(dir: &Path) -> Option<Manifest> {
+  let data = std::fs::read(dir.join(MANIFEST_FILE)).ok()?;
+  serde_json::from_slice(&data).ok()
+}
+
+// File-system- and wiki-link-safe name from the note's first line
+fn base_name(note: &model::Note) -> String {
+  let text = markdown::note_rich_text(note).0;
+  let first_line = text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("");
+  let name: String = first_line.chars()
+    .filter(|c| !"/\\:*?\"<>|#^[]".contains(*c) && !c.is_control())
+    .take(MAX_NAME_CHARS)
+    .collect();
+  let name = name.trim().trim_start_matches('.').trim().to_string();
+  if name.is_empty() { "Untitled".to_string() } else { name }
+}
+
+// Keep the names of notes exported before, name the rest from their text
+fn assign_names(notes: &[&model::Note], previous: &Manifest) -> BTreeMap<model::ID, String> {
+  let mut names = BTreeMap::new();
+  let mut taken = HashSet::from([HIGHLIGHTS_FILE.to_lowercase()]);
+  for note in notes {
+    if let Some(name) = previous.files.get(&note.id) {
+      if taken.insert(name.to_lowercase()) {
+        names.insert(note.id.clone(), name.clone());
+      }
+    }
+  }
+  let unnamed: Vec<&&model::Note> = notes.iter().filter(|n| !names.contains_key(&n.id)).collect();
+  for note in unnamed {
+    let base = base_name(note);
+    let mut name = format!("{}.md", base);
+    let mut counter = 2;
+    while !taken.insert(name.to_lowercase()) {
+      name = format!("{} {}.md", base, counter);
+      counter += 1;
+    }
+    names.insert(note.id.clone(), name);
+  }
+  names
+}
+
+fn wiki_link(file_name: &str) -> String {
+  format!("[[{}]]", file_name.strip_suffix(".md").unwrap_or(file_name))
+}
+
+fn front_matter(id: &str) -> String {
+  format!("---\nfim-id: {}\n---\n\n", id)
+}
+
+fn note_file(note: &model::Note, doc: &model::BoardDocument, names: &BTreeMap<model::ID, String>) -> String {
+  let mut content = front_matter(&note.id);
+  content += note.text.trim_end();
+  content.push('\n');
+
+  let links: Vec<String> = doc.connections.iter()
+    .filter(|c| c.src_note_id == note.id && c.dst_note_id != note.id)
+    .filter_map(|c| {
+      let link = wiki_link(names.get(&c.dst_note_id)?);
+      Some(match c.label.as_deref().filter(|l| !l.is_empty()) {
+        Some(label) => format!("- {}: {}", label, link),
+        None => format!("- {}", link),
+      })
+    })
+    .collect();
+  if !links.is_empty() {
+    content += &format!("\n{}\n\n{}\n", CONNECTIONS_HEADING, links.join("\n"));
+  }
+  content
+}
+
+// Starred notes: links to their files, or their first line when they are
+// part of a subtree file
+fn highlights_file(doc: &model::BoardDocument, names: &BTreeMap<model::ID, String>) -> String {
+  let mut content = "# Highlights\n\n".to_string();
+  for note in doc.notes.iter().filter(|n| n.is_starred()) {
+    match names.get(&note.id) {
+      Some(name) => content += &format!("- {}\n", wiki_link(name)),
+      None => content += &format!("- {}\n", note.text.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("")),
+    }
+  }
+  content
+}
+
+fn write_items(nodes: &[outline::OutlineNode], depth: usize, content: &mut String) {
+  let indent = "  ".repeat(depth);
+  for node in nodes {
+    let mut lines = node.text.lines();
+    let first = lines.next().unwrap_or("");
+    match (&node.id, node.done) {
+      (Some(id), _) => *content += &format!("{}- {} <!-- fim:{} -->\n", indent, first, id),
+      // Checklist items are written for reading; they aren't synced back
+      (None, Some(done)) => *content += &format!("{}- {} {}\n", indent, if done { "[x]" } else { "[ ]" }, first),
+      (None, None) => *content += &format!("{}- {}\n", indent, first),
+    }
+    for line in lines {
+      *content += &format!("{}  {}\n", indent, line);
+    }
+    write_items(&node.children, depth + 1, content);
+  }
+}
+
+fn subtree_file(root: &outline::OutlineNode) -> String {
+  let mut content = front_matter(root.id.as_deref().unwrap_or(""));
+  content += root.text.trim_end();
+  content += &format!("\n\n{}\n\n", CHILDREN_MARKER);
+  write_items(&root.children, 0, &mut content);
+  content
+}
+
+// Write the board into `dir`. `check_write` vets every path before writing.
+pub fn export(
+  doc: &model::BoardDocument,
+  dir: &Path,
+  mode: &str,
+  check_write: &dyn Fn(&Path) -> Result<PathBuf, String>,
+) -> Result<VaultExport, String> {
+  if !dir.is_dir() {
+    return Err(format!("'{}' is not a folder", dir.display()));
+  }
+  let previous = read_manifest(dir).unwrap_or_default();
+
+  let files: Vec<(model::ID, String)> = match mode {
+    "notes" => {
+      let notes: Vec<&model::Note> = doc.notes.iter().collect();
+      let names = assign_names(&notes, &previous);
+      doc.notes.iter()
+        .map(|note| (note.id.clone(), note_file(note, doc, &names)))
+        .collect()
+    },
+    "subtrees" => {
+      let roots = outline::build_outline(doc, &doc.notes);
+      roots.iter()
+        .filter_map(|root| Some((root.id.clone()?, subtree_file(root))))
+        .collect()
+    },
+    other => return Err(format!("Unsupported vault mode '{}'. Must be one of: notes, subtrees", other)),
+  };
+
+  let exported: Vec<&model::Note> = files.iter()
+    .filter_map(|(id, _)| doc.notes.iter().find(|n| &n.id == id))
+    .collect();
+  let names = assign_names(&exported, &previous);
+  let mut written = Vec::new();
+  let mut removed = Vec::new();
+  for (id, content) in &files {
+    let name = &names[id];
+    let path = check_write(&dir.join(name))?;
+    std::fs::write(&path, content)
+      .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    written.push(name.clone());
+  }
+
+  let highlights = doc.notes.iter().any(|n| n.is_starred());
+  let highlights_path = dir.join(HIGHLIGHTS_FILE);
+  if highlights {
+    let path = check_write(&highlights_path)?;
+    std::fs::write(&path, highlights_file(doc, &names))
+      .map_err(|e| format!("Failed to write '{}': {}", path.display(), e))?;
+    written.push(HIGHLIGHTS_FILE.to_string());
+  } else if previous.highlights && highlights_path.is_file() {
+    let path = check_write(&highlights_path)?;
+    std::fs::remove_file(&path)
+      .map_err(|e| format!("Failed to remove '{}': {}", path.display(), e))?;
+    removed.push(HIGHLIGHTS_FILE.to_string());
+  }
+
+  // Files we wrote last time for notes that are gone (or renamed away)
+  let current: HashSet<&String> = names.values().collect();
+  for name in previous.files.values().filter(|name| !current.contains(name)) {
+    let path = dir.join(name);
+    if path.is_file() {
+      let path = check_write(&path)?;
+      std::fs::remove_file(&path)
+        .map_err(|e| format!("Failed to remove '{}': {}", path.display(), e))?;
+      removed.push(name.clone());
+    }
+  }
+
+  let manifest = Manifest { mode: mode.to_string(), files: names, highlights };
+  let manifest_path = check_write(&dir.join(MANIFEST_FILE))?;
+  let json = serde_json::to_string_pretty(&manifest)
+    .map_err(|e| format!("Failed to serialize vault manifest: {}", e))?;
+  std::fs::write(&manifest_path, json)
+    .map_err(|e| format!("Failed to write '{}': {}", manifest_path.display(), e))?;
+
+  Ok(VaultExport { mode: mode.to_string(), written, removed })
+}
+
+// (`fim-id` from the front matter, the rest of the file)
+fn split_front_matter(content: &str) -> (Option<String>, &str) {
+  let Some(rest) = content.strip_prefix("---\n").or_else(|| content.strip_prefix("---\r\n")) else {
+    return (None, content);
+  };
+  let Some(end) = rest.find("\n---") else {
+    return (None, content);
+  };
+  let id = rest[..end].lines()
+    .find_map(|line| line.strip_prefix("fim-id:"))
+    .map(|id| id.trim().to_string())
+    .filter(|id| !id.is_empty());
+  let body = rest[end + 4..].trim_start_matches(['-', '\r']).trim_start_matches('\n');
+  (id, body)
+}
+
+// Targets of [[wiki-links]], without aliases and heading anchors, lowercased
+fn wiki_links(text: &str) -> Vec<(String, Option<String>)> {
+  let mut links = Vec::new();
+  for line in text.lines() {
+    // `- label: [[Target]]` lines keep their label
+    let label = line.trim().strip_prefix("- ")
+      .and_then(|item| item.split_once(": [["))
+      .map(|(label, _)| label.trim().to_string());
+    let mut rest = line;
+    while let Some(start) = rest.find("[[") {
+      let Some(end) = rest[start..].find("]]") else {
+        break;
+      };
+      let target = &rest[start + 2..start + end];
+      let target = target.split(['|', '#']).next().unwrap_or("").trim();
+      if !target.is_empty() {
+        links.push((target.to_lowercase(), label.clone()));
+      }
+      rest = &rest[start + end + 2..];
+    }
+  }
+  links
+}
+
+struct VaultFile {
+  name: String,
+  id: Option<model::ID>,
+  body: String,
+}
+
+fn read_vault_files(dir: &Path) -> Result<Vec<VaultFile>, String> {
+  let entries = std::fs::read_dir(dir)
+    .map_err(|e| format!("Failed to read folder '{}': {}", dir.display(), e))?;
+  let mut files = Vec::new();
+  for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+    let is_markdown = path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("md"));
+    let is_highlights = path.file_name().is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(HIGHLIGHTS_FILE));
+    if !path.is_file() || !is_markdown || is_highlights {
+      continue;
+    }
+    let content = std::fs::read_to_string(&path)
+      .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let (id, body) = split_front_matter(&content);
+    files.push(VaultFile {
+      name: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+      id,
+      body: body.to_string(),
+    });
+  }
+  files.sort_by(|a, b| a.name.cmp(&b.name));
+  Ok(files)
+}
+
+struct Sync<'a> {
+  doc: model::BoardDocument,
+  updated: Vec<model::ID>,
+  created: Vec<model::ID>,
+  connections_added: usize,
+  connections_removed: usize,
+  warnings: Vec<String>,
+  // Where the next new note goes
+  next_position: model::Point,
+  manifest: &'a mut Manifest,
+}
+
+impl Sync<'_> {
+  fn set_text(&mut self, id: &str, text: &str, source: &str) {
+    let Some(note) = self.doc.notes.iter_mut().find(|n| n.id == id) else {
+      return;
+    };
+    if note.text.trim_end() == text.trim_end() {
+      return;
+    }
+    if note.is_locked() {
+      self.warnings.push(format!("'{}': the note is locked, so its edits weren't applied", source));
+      return;
+    }
+    note.text = text.trim_end().to_string();
+    // Formatting runs no longer match the new text
+    note.spans = None;
+    if !self.updated.iter().any(|u| u == id) {
+      self.updated.push(id.to_string());
+    }
+  }
+
+  fn create_note(&mut self, text: &str, frame: Option<model::Rect>) -> model::ID {
+    let frame = frame.unwrap_or_else(|| {
+      let frame = model::Rect {
+        x: self.next_position.x,
+        y: self.next_position.y,
+        w: outline::IMPORT_NOTE_WIDTH,
+        h: outline::IMPORT_NOTE_HEIGHT,
+      };
+      self.next_position.x += outline::IMPORT_COLUMN_WIDTH;
+      frame
+    });
+    let note = outline::imported_note(text.trim(), frame);
+    let id = note.id.clone();
+    self.doc.notes.push(note);
+    self.created.push(id.clone());
+    id
+  }
+
+  fn connect(&mut self, src: &str, dst: &str, label: Option<String>) {
+    self.doc.connections.push(model::Connection {
+      id: model::new_id("conn"),
+      src_note_id: src.to_string(),
+      dst_note_id: dst.to_string(),
+      style: None,
+      label,
+      bend_points: None,
+      control_points: None,
+      relation_type: None,
+    });
+    self.connections_added += 1;
+  }
+
+  // Make the note's connections to other vault notes match its wiki-links
+  fn sync_links(&mut self, id: &str, links: &[(String, Option<String>)], file_name: &str) {
+    let by_name: BTreeMap<String, model::ID> = self.manifest.files.iter()
+      .map(|(id, name)| (name.strip_suffix(".md").unwrap_or(name).to_lowercase(), id.clone()))
+      .collect();
+    let mut wanted: Vec<(model::ID, Option<String>)> = Vec::new();
+    for (target, label) in links {
+      match by_name.get(target) {
+        Some(target_id) if target_id != id => {
+          if !wanted.iter().any(|(t, _)| t == target_id) {
+            wanted.push((target_id.clone(), label.clone()));
+          }
+        },
+        Some(_) => {},
+        None => self.warnings.push(format!("'{}': no note for the link [[{}]]", file_name, target)),
+      }
+    }
+
+    let vault_ids: HashSet<&model::ID> = self.manifest.files.keys().collect();
+    let before = self.doc.connections.len();
+    let locked = self.doc.notes.iter().find(|n| n.id == id).is_some_and(|n| n.is_locked());
+    if !locked {
+      self.doc.connections.retain(|c| {
+        c.src_note_id != id || !vault_ids.contains(&c.dst_note_id) || wanted.iter().any(|(t, _)| *t == c.dst_note_id)
+      });
+    }
+    self.connections_removed += before - self.doc.connections.len();
+
+    for (target, label) in wanted {
+      let exists = self.doc.connections.iter().any(|c| c.src_note_id == id && c.dst_note_id == target);
+      if !exists {
+        self.connect(id, &target, label);
+      }
+    }
+  }
+
+  fn sync_note_file(&mut self, file: &VaultFile) {
+    let (body, links) = match file.body.rfind(CONNECTIONS_HEADING) {
+      Some(index) => (&file.body[..index], wiki_links(&file.body)),
+      None => (file.body.as_str(), wiki_links(&file.body)),
+    };
+    let id = file.id.clone()
+      .filter(|id| self.doc.notes.iter().any(|n| &n.id == id))
+      .or_else(|| self.manifest.files.iter().find(|(_, name)| **name == file.name).map(|(id, _)| id.clone()));
+    let id = match id {
+      Some(id) => {
+        self.set_text(&id, body, &file.name);
+        id
+      },
+      None => self.create_note(body, None),
+    };
+    self.manifest.files.insert(id.clone(), file.name.clone());
+    self.sync_links(&id, &links, &file.name);
+  }
+
+  fn sync_subtree_file(&mut self, file: &VaultFile) {
+    let (root_text, list) = file.body.split_once(CHILDREN_MARKER).unwrap_or((file.body.as_str(), ""));
+    let root_id = match file.id.clone().filter(|id| self.doc.notes.iter().any(|n| &n.id == id)) {
+      Some(id) => {
+        self.set_text(&id, root_text, &file.name);
+        id
+      },
+      None => self.create_note(root_text, None),
+    };
+    self.manifest.files.insert(root_id.clone(), file.name.clone());
+
+    // (indent, note ID, text) per list item; continuation lines join the item
+    let mut items: Vec<(usize, Option<model::ID>, String, bool)> = Vec::new();
+    for line in list.lines() {
+      let indent = line.len() - line.trim_start().len();
+      let trimmed = line.trim();
+      if let Some(item) = trimmed.strip_prefix("- ") {
+        let (text, id) = match item.rsplit_once("<!-- fim:") {
+          Some((text, rest)) => (text.trim_end(), rest.strip_suffix("-->").map(|id| id.trim().to_string())),
+          None => (item, None),
+        };
+        // Checklist lines are written read-only
+        let checklist = id.is_none() && (text.starts_with("[ ] ") || text.starts_with("[x] "));
+        items.push((indent, id, text.to_string(), checklist));
+      } else if let Some(last) = items.last_mut().filter(|_| !trimmed.is_empty()) {
+        last.2 += "\n";
+        last.2 += trimmed;
+      }
+    }
+
+    // Parents by indentation: the nearest earlier item indented less
+    let mut resolved: Vec<(usize, model::ID)> = Vec::new();
+    for (indent, id, text, checklist) in items {
+      while resolved.last().is_some_and(|(open, _)| *open >= indent) {
+        resolved.pop();
+      }
+      if checklist {
+        continue;
+      }
+      let parent = resolved.last().map(|(_, id)| id.clone()).unwrap_or_else(|| root_id.clone());
+      let id = match id.filter(|id| self.doc.notes.iter().any(|n| &n.id == id)) {
+        Some(id) => {
+          self.set_text(&id, &text, &file.name);
+          id
+        },
+        None => {
+          // Next to the parent, below its existing children
+          let frame = self.doc.notes.iter().find(|n| n.id == parent).map(|p| {
+            let children = self.doc.connections.iter().filter(|c| c.src_note_id == parent).count() as f64;
+            model::Rect {
+              x: p.frame.x + outline::IMPORT_COLUMN_WIDTH,
+              y: p.frame.y + children * (outline::IMPORT_NOTE_HEIGHT + outline::IMPORT_ROW_GAP),
+              w: outline::IMPORT_NOTE_WIDTH,
+              h: outline::IMPORT_NOTE_HEIGHT,
+            }
+          });
+          let id = self.create_note(&text, frame);
+          self.connect(&parent, &id, None);
+          id
+        },
+      };
+      resolved.push((indent, id));
+    }
+  }
+}
+
+// Read edits made in `dir` back into `doc`. Returns the updated document and
+// what changed; the manifest is rewritten to include new files.
+pub fn sync(
+  doc: &model::BoardDocument,
+  dir: &Path,
+  check_write: &dyn Fn(&Path) -> Result<PathBuf, String>,
+) -> Result<VaultSync, String> {
+  let mut manifest = read_manifest(dir)
+    .ok_or_else(|| format!("'{}' isn't an exported vault (no {})", dir.display(), MANIFEST_FILE))?;
+  let files = read_vault_files(dir)?;
+
+  let mut warnings = Vec::new();
+  let present: HashSet<&str> = files.iter().map(|f| f.name.as_str()).collect();
+  for (id, name) in &manifest.files {
+    if !present.contains(name.as_str()) && doc.notes.iter().any(|n| &n.id == id) {
+      warnings.push(format!("'{}' was deleted; its note was kept", name));
+    }
+  }
+
+  let bounds = raster::content_bounds(doc);
+  let next_position = model::Point {
+    x: bounds.as_ref().map(|b| b.x).unwrap_or(0.0),
+    y: bounds.as_ref().map(|b| b.y + b.h + outline::IMPORT_COLUMN_WIDTH / 2.0).unwrap_or(0.0),
+  };
+  let mode = manifest.mode.clone();
+  let mut sync = Sync {
+    doc: doc.clone(),
+    updated: Vec::new(),
+    created: Vec::new(),
+    connections_added: 0,
+    connections_removed: 0,
+    warnings,
+    next_position,
+    manifest: &mut manifest,
+  };
+
+  match mode.as_str() {
+    "subtrees" => {
+      for file in &files {
+        sync.sync_subtree_file(file);
+      }
+    },
+    _ => {
+      // New files first, so links to them resolve
+      let (known, new): (Vec<&VaultFile>, Vec<&VaultFile>) = files.iter()
+        .partition(|f| sync.manifest.files.values().any(|name| *name == f.name));
+      for file in new.into_iter().chain(known) {
+        sync.sync_note_file(file);
+      }
+    },
+  }
+
+  let Sync { doc, updated, created, connections_added, connections_removed, warnings, .. } = sync;
+  let manifest_path = check_write(&dir.join(MANIFEST_FILE))?;
+  let json = serde_json::to_string_pretty(&manifest)
+    .map_err(|e| format!("Failed to serialize vault manifest: {}", e))?;
+  std::fs::write(&manifest_path, json)
+    .map_err(|e| format!("Failed to write '{}': {}", manifest_path.display(), e))?;
+
+  Ok(VaultSync { document: doc, updated, created, connections_added, connections_removed, warnings })
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{media, model};
+
+// Animated walkthroughs: a camera that visits chosen notes in order, holding
+// on each and easing (pan plus zoom) between them. The backend plans the
+// camera regions frame by frame; rendered frames come back to be encoded as
+// a looping GIF, or are recorded to WebM by the webview.
+
+const DEFAULT_WIDTH: u32 = 960;
+const DEFAULT_HEIGHT: u32 = 540;
+const MAX_EDGE: u32 = 1920;
+const DEFAULT_FPS: u32 = 12;
+const MAX_FPS: u32 = 30;
+const DEFAULT_HOLD_MS: u32 = 1200;
+const DEFAULT_TRAVEL_MS: u32 = 1000;
+const DEFAULT_PADDING: f64 = 60.0;
+const MAX_FRAMES: usize = 1500;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct WalkthroughOptions {
+  // Notes to visit, in order
+  pub note_ids: Vec<model::ID>,
+  // Output size in pixels
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+  pub fps: Option<u32>,
+  // Time spent on each note and moving between notes
+  pub hold_ms: Option<u32>,
+  pub travel_ms: Option<u32>,
+  // Board units kept around each note
+  pub padding: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalkthroughFrame {
+  // Board-space region shown; always has the output's aspect ratio
+  pub region: model::Rect,
+  // How long the frame stays on screen
+  pub delay_ms: u32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WalkthroughPlan {
+  pub width: u32,
+  pub height: u32,
+  pub fps: u32,
+  pub frames: Vec<WalkthroughFrame>,
+}
+
+// The note's padded frame, grown to the output aspect ratio around its center
+fn camera_for(frame: &model::Rect, padding: f64, aspect: f64) -> model::Rect {
+  let (cx, cy) = (frame.x + frame.w / 2.0, frame.y + frame.h / 2.0);
+  let mut w = frame.w.max(1.0) + padding * 2.0;
+  let mut h = frame.h.max(1.0) + padding * 2.0;
+  if w / h < aspect {
+    w = h * aspect;
+  } else {
+    h = w / aspect;
+  }
+  model::Rect { x: cx - w / 2.0, y: cy - h / 2.0, w, h }
+}
+
+fn ease_in_out(t: f64) -> f64 {
+  if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+}
+
+// Pan linearly in center position and zoom geometrically, so moving between
+// differently sized notes doesn't appear to speed up or slow down
+fn interpolate(from: &model::Rect, to: &model::Rect, t: f64) -> model::Rect {
+  let t = ease_in_out(t);
+  let center = |r: &model::Rect| (r.x + r.w / 2.0, r.y + r.h / 2.0);
+  let ((fx, fy), (tx, ty)) = (center(from), center(to));
+  let (cx, cy) = (fx + (tx - fx) * t, fy + (ty - fy) * t);
+  let w = from.w * (to.w / from.w).powf(t);
+  let h = from.h * (to.h / from.h).powf(t);
+  model::Rect { x: cx - w / 2.0, y: cy - h / 2.0, w, h }
+}
+
+pub fn plan(doc: &model::BoardDocument, options: &WalkthroughOptions) -> Result<WalkthroughPlan, String> {
+  let width = options.width.unwrap_or(DEFAULT_WIDTH);
+  let height = options.height.unwrap_or(DEFAULT_HEIGHT);
+  if width == 0 || height == 0 || width > MAX_EDGE || height > MAX_EDGE {
+    return Err(format!("Walkthrough size must be between 1 and {} px per edge", MAX_EDGE));
+  }
+  let fps = options.fps.unwrap_or(DEFAULT_FPS);
+  if !(1..=MAX_FPS).contains(&fps) {
+    return Err(format!("Frame rate must be between 1 and {} fps", MAX_FPS));
+  }
+  let padding = options.padding.unwrap_or(DEFAULT_PADDING);
+  if !padding.is_finite() || padding < 0.0 {
+    return Err("Padding must be zero or more".to_string());
+  }
+  if options.note_ids.is_empty() {
+    return Err("Choose at least one note for the walkthrough".to_string());
+  }
+
+  let aspect = width as f64 / height as f64;
+  let cameras = options.note_ids.iter()
+    .map(|id| {
+      let note = doc.notes.iter().find(|n| &n.id == id)
+        .ok_or_else(|| format!("Note '{}' not found", id))?;
+      Ok(camera_for(&note.frame, padding, aspect))
+    })
+    .collect::<Result<Vec<_>, String>>()?;
+
+  let hold_ms = options.hold_ms.unwrap_or(DEFAULT_HOLD_MS);
+  let travel_ms = options.travel_ms.unwrap_or(DEFAULT_TRAVEL_MS);
+  let frame_ms = 1000 / fps;
+  let travel_frames = (travel_ms / frame_ms).max(1) as usize;
+
+  // Holds are a single frame with a long delay; GIF stores that for free and
+  // the WebM recorder just waits
+  let mut frames = Vec::new();
+  for (index, camera) in cameras.iter().enumerate() {
+    if index > 0 {
+      let previous = &cameras[index - 1];
+      for step in 1..travel_frames {
+        frames.push(WalkthroughFrame {
+          region: interpolate(previous, camera, step as f64 / travel_frames as f64),
+          delay_ms: frame_ms,
+        });
+      }
+    }
+    frames.push(WalkthroughFrame { region: camera.clone(), delay_ms: hold_ms.max(frame_ms) });
+  }
+  if frames.len() > MAX_FRAMES {
+    return Err(format!(
+      "The walkthrough needs {} frames (at most {}); visit fewer notes or lower the frame rate",
+      frames.len(), MAX_FRAMES
+    ));
+  }
+
+  Ok(WalkthroughPlan { width, height, fps, frames })
+}
+
+// Crop or pad a rendered frame to exactly width×height; renderers round
+// canvas sizes up, so frames can come back a pixel larger
+fn fit_frame(rgba: &[u8], frame_width: u32, frame_height: u32, width: u32, height: u32) -> Vec<u8> {
+  let mut fitted = vec![0u8; (width * height * 4) as usize];
+  let copy_width = width.min(frame_width) as usize * 4;
+  for y in 0..height.min(frame_height) as usize {
+    let src = y * frame_width as usize * 4;
+    let dst = y * width as usize * 4;
+    fitted[dst..dst + copy_width].copy_from_slice(&rgba[src..src + copy_width]);
+  }
+  fitted
+}
+
+// Encode one rendered PNG per planned frame as a looping GIF
+// `on_frame` is called with the number of frames encoded so far; an error
+// from it stops encoding
+pub fn encode_gif(
+  plan: &WalkthroughPlan,
+  frames: &[Vec<u8>],
+  on_frame: &mut dyn FnMut(usize) -> Result<(), String>,
+) -> Result<Vec<u8>, String> {
+  if frames.len() != plan.frames.len() {
+    return Err(format!("Expected {} walkthrough frames, got {}", plan.frames.len(), frames.len()));
+  }
+
+  let (width, height) = (plan.width as u16, plan.height as u16);
+  let mut data = Vec::new();
+  {
+    let mut encoder = gif::Encoder::new(&mut data, width, height, &[])
+      .map_err(|e| format!("Failed to start GIF: {}", e))?;
+    encoder.set_repeat(gif::Repeat::Infinite)
+      .map_err(|e| format!("Failed to write GIF header: {}", e))?;
+
+    for (index, (png, planned)) in frames.iter().zip(&plan.frames).enumerate() {
+      let (rgba, frame_width, frame_height) = media::decode_png_rgba(png)
+        .map_err(|e| format!("Walkthrough frame {}: {}", index + 1, e))?;
+      let mut rgba = fit_frame(&rgba, frame_width, frame_height, plan.width, plan.height);
+      let mut frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+      // GIF delays are in centiseconds
+      frame.delay = (planned.delay_ms / 10).min(u16::MAX as u32) as u16;
+      encoder.write_frame(&frame)
+        .map_err(|e| format!("Failed to write GIF frame {}: {}", index + 1, e))?;
+      on_frame(index + 1)?;
+    }
+  }
+  Ok(data)
+}
+
+// WebM files start with an EBML header
+pub fn is_webm(data: &[u8]) -> bool {
+  data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3])
+}
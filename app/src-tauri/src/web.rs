@@ -0,0 +1,235 @@
+use base64::Engine;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::time::Duration;
+
+use crate::{html, media, model, outline};
+
+// Link cards: fetch a page, read its title, description and preview image
+// (Open Graph tags first, then <title> and <meta name="description">) and
+// turn them into a note. Fetching is bounded in time and size because the
+// URL usually comes straight from the clipboard. The same metadata, with the
+// site's icon, dresses up links that stay links (`fetch_link_metadata`).
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const TOTAL_TIMEOUT: Duration = Duration::from_secs(15);
+const MAX_REDIRECTS: u32 = 5;
+// Metadata lives in <head>; pages are cut off after this much
+const MAX_PAGE_BYTES: u64 = 2 * 1024 * 1024;
+const MAX_PREVIEW_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_FAVICON_BYTES: u64 = 100 * 1024;
+const USER_AGENT: &str = concat!("FIM/", env!("CARGO_PKG_VERSION"), " (link preview)");
+
+const CARD_WIDTH: f64 = 280.0;
+const CARD_TEXT_HEIGHT: f64 = 100.0;
+const CARD_IMAGE_HEIGHT: f64 = 150.0;
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct PageMetadata {
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub site_name: Option<String>,
+  // Absolute URL of the preview image
+  pub image_url: Option<String>,
+  // Absolute URL of the icon the page declares
+  pub favicon_url: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LinkMetadata {
+  pub url: String,
+  // After redirects
+  pub final_url: String,
+  // The page's title, else its host
+  pub title: String,
+  pub metadata: PageMetadata,
+  // The site's icon as a data URI; none when it has none or it couldn't be fetched
+  pub favicon: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct UrlImport {
+  pub note: model::Note,
+  // The preview image the note shows, to add to the document's images
+  pub image: Option<model::EmbeddedImage>,
+  pub metadata: PageMetadata,
+  // Problems that didn't prevent the card, e.g. a preview image that failed
+  pub warnings: Vec<String>,
+}
+
+fn clean(text: &str) -> Option<String> {
+  let text = html::decode_entities(text).split_whitespace().collect::<Vec<_>>().join(" ");
+  (!text.is_empty()).then_some(text)
+}
+
+pub fn parse_metadata(page: &str, base: &tauri::Url) -> PageMetadata {
+  let mut meta: HashMap<String, String> = HashMap::new();
+  let mut title = None;
+  // `rel="icon"` (or "shortcut icon") over apple-touch-icon
+  let (mut icon, mut touch_icon) = (None, None);
+  let lower = page.to_ascii_lowercase();
+  let mut offset = 0;
+
+  while let Some(start) = lower[offset..].find('<').map(|s| offset + s + 1) {
+    let end = lower[start..].find('>').map(|e| start + e).unwrap_or(lower.len());
+    let tag = &page[start..end];
+    offset = end;
+    let name = tag.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("").to_ascii_lowercase();
+    match name.as_str() {
+      "meta" => {
+        let key = html::attribute(tag, "property").or_else(|| html::attribute(tag, "name"));
+        if let (Some(key), Some(content)) = (key, html::attribute(tag, "content")) {
+          // The first occurrence wins, like in link unfurlers
+          meta.entry(key.to_ascii_lowercase()).or_insert(content);
+        }
+      },
+      "link" => {
+        let rel = html::attribute(tag, "rel").unwrap_or_default().to_ascii_lowercase();
+        let href = html::attribute(tag, "href");
+        if rel.split_whitespace().any(|r| r == "icon") {
+          icon = icon.or(href);
+        } else if rel.split_whitespace().any(|r| r.starts_with("apple-touch-icon")) {
+          touch_icon = touch_icon.or(href);
+        }
+      },
+      "title" if title.is_none() => {
+        let text_end = lower[end..].find("</title").map(|e| end + e).unwrap_or(lower.len());
+        title = clean(page.get(end + 1..text_end).unwrap_or(""));
+      },
+      // Everything we read is in <head>
+      "/head" | "body" => break,
+      _ => {},
+    }
+  }
+
+  let get = |keys: &[&str]| keys.iter().find_map(|key| meta.get(*key).and_then(|v| clean(v)));
+  let absolute = |url: String| base.join(url.trim()).ok()
+    .filter(|url| matches!(url.scheme(), "http" | "https"))
+    .map(|url| url.to_string());
+  PageMetadata {
+    title: get(&["og:title", "twitter:title"]).or(title),
+    description: get(&["og:description", "twitter:description", "description"]),
+    site_name: get(&["og:site_name", "application-name"]),
+    image_url: get(&["og:image", "og:image:url", "og:image:secure_url", "twitter:image"])
+      .and_then(absolute),
+    favicon_url: icon.or(touch_icon).and_then(absolute),
+  }
+}
+
+fn agent() -> ureq::Agent {
+  ureq::AgentBuilder::new()
+    .timeout_connect(CONNECT_TIMEOUT)
+    .timeout(TOTAL_TIMEOUT)
+    .redirects(MAX_REDIRECTS)
+    .user_agent(USER_AGENT)
+    .build()
+}
+
+// Body of `url`, cut off at `limit` bytes, with the content type and the URL
+// after redirects
+fn fetch(agent: &ureq::Agent, url: &str, limit: u64) -> Result<(Vec<u8>, String, String), String> {
+  let response = agent.get(url).call()
+    .map_err(|e| format!("Failed to fetch '{}': {}", url, e))?;
+  let content_type = response.header("content-type").unwrap_or("").to_ascii_lowercase();
+  let final_url = response.get_url().to_string();
+  let mut body = Vec::new();
+  response.into_reader().take(limit).read_to_end(&mut body)
+    .map_err(|e| format!("Failed to read '{}': {}", url, e))?;
+  Ok((body, content_type, final_url))
+}
+
+fn preview_image(agent: &ureq::Agent, url: &str) -> Result<model::EmbeddedImage, String> {
+  // Read one byte past the cap to tell a complete image from a truncated one
+  let (data, _, _) = fetch(agent, url, MAX_PREVIEW_IMAGE_BYTES + 1)?;
+  if data.len() as u64 > MAX_PREVIEW_IMAGE_BYTES {
+    return Err(format!("Preview image '{}' is larger than {} bytes", url, MAX_PREVIEW_IMAGE_BYTES));
+  }
+  media::image_from_bytes(&data).map_err(|e| format!("Preview image '{}': {}", url, e))
+}
+
+// An icon small enough to show next to a link, as a data URI
+fn favicon(agent: &ureq::Agent, url: &str) -> Result<String, String> {
+  let (data, content_type, _) = fetch(agent, url, MAX_FAVICON_BYTES + 1)?;
+  if data.len() as u64 > MAX_FAVICON_BYTES {
+    return Err(format!("Icon '{}' is larger than {} bytes", url, MAX_FAVICON_BYTES));
+  }
+  // Servers send .ico files under all sorts of types
+  let mime = match content_type.split(';').next().unwrap_or("").trim() {
+    mime if mime.starts_with("image/") => mime.to_string(),
+    _ => media::sniff_image_mime(&data).ok_or_else(|| format!("'{}' is not an image", url))?.to_string(),
+  };
+  Ok(format!("data:{};base64,{}", mime, base64::engine::general_purpose::STANDARD.encode(&data)))
+}
+
+fn parse_http_url(url: &str) -> Result<tauri::Url, String> {
+  let parsed = tauri::Url::parse(url.trim()).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+  if !matches!(parsed.scheme(), "http" | "https") {
+    return Err(format!("Only http and https links can be fetched, not '{}'", parsed.scheme()));
+  }
+  Ok(parsed)
+}
+
+// Title and icon of the page at `url`, for showing a link nicely. Sites
+// without a declared icon are asked for /favicon.ico; the icon is best
+// effort, so failing to get it isn't an error.
+pub fn fetch_link_metadata(url: &str) -> Result<LinkMetadata, String> {
+  let parsed = parse_http_url(url)?;
+  let agent = agent();
+  let (body, content_type, final_url) = fetch(&agent, parsed.as_str(), MAX_PAGE_BYTES)?;
+  let base = tauri::Url::parse(&final_url).unwrap_or_else(|_| parsed.clone());
+  let metadata = match content_type.contains("html") || content_type.is_empty() {
+    true => parse_metadata(&String::from_utf8_lossy(&body), &base),
+    false => PageMetadata::default(),
+  };
+  let favicon_url = metadata.favicon_url.clone()
+    .or_else(|| base.join("/favicon.ico").ok().map(|url| url.to_string()));
+  let favicon = favicon_url.and_then(|favicon_url| favicon(&agent, &favicon_url).ok());
+  let title = metadata.title.clone().unwrap_or_else(|| base.host_str().unwrap_or(base.as_str()).to_string());
+  Ok(LinkMetadata { url: parsed.to_string(), final_url: base.to_string(), title, metadata, favicon })
+}
+
+// Fetch a page and build a link card for it. The note links to the page;
+// its text is the title with the description below.
+pub fn import_url(url: &str, origin: Option<model::Point>) -> Result<UrlImport, String> {
+  let parsed = parse_http_url(url)?;
+  let agent = agent();
+  let (body, content_type, final_url) = fetch(&agent, parsed.as_str(), MAX_PAGE_BYTES)?;
+  let base = tauri::Url::parse(&final_url).unwrap_or_else(|_| parsed.clone());
+  let mut warnings = Vec::new();
+
+  let (metadata, image) = if content_type.starts_with("image/") {
+    // A direct link to a picture: the picture is the preview
+    let image = media::image_from_bytes(&body).map_err(|e| format!("'{}': {}", url, e))?;
+    (PageMetadata::default(), Some(image))
+  } else {
+    let metadata = parse_metadata(&String::from_utf8_lossy(&body), &base);
+    let image = match metadata.image_url.as_deref().map(|image_url| preview_image(&agent, image_url)) {
+      Some(Ok(image)) => Some(image),
+      Some(Err(e)) => {
+        warnings.push(e);
+        None
+      },
+      None => None,
+    };
+    (metadata, image)
+  };
+
+  let title = metadata.title.clone().unwrap_or_else(|| parsed.host_str().unwrap_or(parsed.as_str()).to_string());
+  let text = match &metadata.description {
+    Some(description) => format!("{}\n\n{}", title, description),
+    None => title,
+  };
+  let origin = origin.unwrap_or(model::Point { x: 0.0, y: 0.0 });
+  let height = CARD_TEXT_HEIGHT + if image.is_some() { CARD_IMAGE_HEIGHT } else { 0.0 };
+  let mut note = outline::imported_note(&text, model::Rect { x: origin.x, y: origin.y, w: CARD_WIDTH, h: height });
+  note.links = Some(vec![parsed.to_string()]);
+  note.images = image.as_ref().map(|image| vec![image.id.clone()]);
+  let mut properties = HashMap::new();
+  if let Some(site_name) = &metadata.site_name {
+    properties.insert("site".to_string(), serde_json::Value::String(site_name.clone()));
+  }
+  note.properties = (!properties.is_empty()).then_some(properties);
+
+  Ok(UrlImport { note, image, metadata, warnings })
+}
@@ -1,40 +1,672 @@
+export type ErrorCode =
+  | 'cancelled'
+  | 'permission_denied'
+  | 'not_found'
+  | 'corrupt_file'
+  | 'unsupported'
+  | 'invalid_input'
+  | 'network'
+  | 'read_only'
+  | 'conflict'
+  | 'already_open'
+  | 'password_required'
+  | 'wrong_password'
+  | 'io'
+  | 'error'
+
+// What a failed command rejects with; `message` is meant for the user and
+// `hint` suggests what to do about it. For `already_open`, `window` is the
+// label of the window that has the board; pass it to `focusDocumentWindow`.
+// For `password_required`, `path` is the protected board; ask for its password,
+// `unlockDocument` it and try again.
+export class CommandError extends Error {
+  code: ErrorCode
+  recoverable: boolean
+  hint: string | null
+  window: string | null
+  path: string | null
+
+  constructor(payload: { code: ErrorCode; message: string; recoverable: boolean; hint: string | null; window?: string; path?: string }) {
+    super(payload.message)
+    this.name = 'CommandError'
+    this.code = payload.code
+    this.recoverable = payload.recoverable
+    this.hint = payload.hint
+    this.window = payload.window ?? null
+    this.path = payload.path ?? null
+  }
+}
+
 export async function invoke<T = unknown>(cmd: string, args?: Record<string, any>): Promise<T> {
   const w = window as any
   if (w.__TAURI__ && typeof w.__TAURI__.invoke === 'function') {
-    return w.__TAURI__.invoke(cmd, args)
+    try {
+      return await w.__TAURI__.invoke(cmd, args)
+    } catch (e) {
+      if (e && typeof e === 'object' && typeof (e as any).code === 'string') {
+        throw new CommandError(e as any)
+      }
+      throw e
+    }
   }
   throw new Error('Not running inside Tauri environment')
 }
 
-import type { BoardDocument } from '../model/types'
+import type { BackgroundShape, BoardDocument, Connection, DocumentInfo, EmbeddedImage, Note, NoteStyle, Point, Rect, Stack } from '../model/types'
+
+// Progress of long opens, saves and exports, streamed while the command runs
+export interface ProgressEvent {
+  operation_id: string
+  phase: string
+  percent: number
+}
+
+export type ProgressHandler = (event: ProgressEvent) => void
+
+// Long-running operations; a task's id is the operation_id of its progress events
+export interface TaskInfo {
+  id: string
+  kind: string
+  status: 'running' | 'cancelling' | 'completed' | 'failed' | 'cancelled'
+  started_at: string
+  finished_at: string | null
+  error: string | null
+}
+
+export async function listTasks(): Promise<TaskInfo[]> {
+  return invoke('list_tasks')
+}
+
+// The task then fails with "Task cancelled"
+export async function cancelTask(id: string): Promise<void> {
+  return invoke('cancel_task', { id })
+}
+
+// Local usage statistics (opt-in through the `usageStats` setting); nothing
+// leaves the machine
+export interface UsageReport {
+  enabled: boolean
+  // Most time spent first
+  documents: { path: string; display_name: string; seconds: number; saves: number; last_active: string | null }[]
+  // Newest first; date is YYYY-MM-DD in local time
+  days: { date: string; seconds: number; notes_created: number; saves: number; documents: string[] }[]
+}
+
+export async function getUsageStats(since?: string): Promise<UsageReport> {
+  return invoke('get_usage_stats', { since })
+}
+
+export async function clearUsageStats(): Promise<void> {
+  return invoke('clear_usage_stats')
+}
+
+// A Tauri channel delivering progress events to `onProgress`; undefined when
+// there is no handler (or no channel support), which the backend treats as
+// "don't report"
+function progressChannel(onProgress?: ProgressHandler): unknown {
+  const Channel = (window as any).__TAURI__?.core?.Channel
+  if (!onProgress || typeof Channel !== 'function') return undefined
+  const channel = new Channel()
+  channel.onmessage = onProgress
+  return channel
+}
+
+// Events the backend emits to this window; resolves to a function that stops
+// listening. Outside Tauri nothing arrives.
+export async function listen<T>(event: string, handler: (payload: T) => void): Promise<() => void> {
+  const listenTo = (window as any).__TAURI__?.event?.listen
+  if (typeof listenTo !== 'function') return () => {}
+  return listenTo(event, (e: { payload: T }) => handler(e.payload))
+}
 
 // Document operations
-export async function openDocument(): Promise<BoardDocument> {
-  return invoke('open_document')
+export async function openDocument(onProgress?: ProgressHandler): Promise<BoardDocument> {
+  return invoke('open_document', { onProgress: progressChannel(onProgress) })
+}
+
+// Pick an OPML file and build a board from its outline
+export async function importOPML(): Promise<BoardDocument> {
+  return invoke('import_opml')
+}
+
+// Build a board from an .opml or Markdown outline; without `path`, asks for one
+export async function importDocument(path?: string, mode?: 'connections' | 'stack'): Promise<BoardDocument> {
+  return invoke('import_document', { args: { path: path ?? null, mode: mode ?? null } })
+}
+
+// Indented text or a bulleted/numbered list as notes laid out left to right
+// with parent → child connections, or as one indented stack
+export async function importTextOutline(
+  text: string,
+  options: { mode?: 'connections' | 'stack'; origin?: Point } = {}
+): Promise<{ notes: Note[]; connections: Connection[]; stacks: Stack[] }> {
+  return invoke('import_text_outline', { args: { text, ...options } })
+}
+
+// Pasted text split into positioned notes; `format` is the structure detected
+// ("numbered", "bullets", "indented", "tsv" or "paragraphs")
+export async function parseClipboardOutline(
+  text: string,
+  options: { mode?: 'connections' | 'stack'; origin?: Point } = {}
+): Promise<{ format: string; notes: Note[]; connections: Connection[]; stacks: Stack[] }> {
+  return invoke('parse_clipboard_outline', { args: { text, ...options } })
+}
+
+// A long paste as several notes sized to their text, split by paragraphs,
+// sentences or `max_length` characters (default 280)
+export async function splitTextToNotes(
+  text: string,
+  options: {
+    strategy?: 'paragraphs' | 'sentences' | 'length'
+    max_length?: number
+    layout?: 'column' | 'stack'
+    origin?: Point
+  } = {}
+): Promise<{ notes: Note[]; connections: Connection[]; stacks: Stack[] }> {
+  return invoke('split_text_to_notes', { args: { text, ...options } })
+}
+
+// Copy notes to the system clipboard, in reading order: "plain" as text,
+// "markdown" as Markdown, "rtf" as formatted HTML with a Markdown fallback
+export async function copyNotesToClipboard(doc: BoardDocument, noteIds: string[], format: 'plain' | 'rtf' | 'markdown'): Promise<void> {
+  return invoke('copy_notes_to_clipboard', { args: { doc, note_ids: noteIds, format } })
+}
+
+// Put a rendered PNG on the clipboard as an image
+export async function copyPngToClipboard(png: Uint8Array): Promise<void> {
+  return invoke('copy_png_to_clipboard', { pngData: Array.from(png) })
+}
+
+// An item of a pasted outline, with its nested items
+export interface OutlineNode {
+  id?: string
+  text: string
+  icon?: string
+  // `text` is Markdown source
+  markdown?: boolean
+  done?: boolean
+  tags?: string[]
+  children: OutlineNode[]
+}
+
+// What the clipboard holds, classified for insertion on the board
+export type ClipboardContent =
+  | { kind: 'image'; image: EmbeddedImage }
+  | { kind: 'url'; url: string }
+  | { kind: 'outline'; items: OutlineNode[] }
+  | { kind: 'text'; text: string; contentFormat: 'plain' | 'markdown' }
+  | { kind: 'empty' }
+
+export async function readClipboard(): Promise<ClipboardContent> {
+  return invoke('read_clipboard')
+}
+
+// One image note per picture in `dir` (asks for a folder when omitted)
+export async function importImageFolder(dir?: string, origin?: Point, onProgress?: ProgressHandler): Promise<{ notes: Note[]; images: EmbeddedImage[]; warnings: string[] }> {
+  return invoke('import_image_folder', { args: { dir, origin }, onProgress: progressChannel(onProgress) })
+}
+
+// Pick a browser bookmarks export; folders become parent notes or background shapes
+export async function importBookmarks(
+  options: { mode?: 'connections' | 'stack' | 'shapes'; origin?: Point } = {}
+): Promise<{ notes: Note[]; connections: Connection[]; stacks: Stack[]; shapes: BackgroundShape[] }> {
+  return invoke('import_bookmarks', { args: options })
 }
 
-export async function openSpecificDocument(filePath: string): Promise<BoardDocument> {
-  return invoke('open_specific_document', { filePath })
+// Pick a Readwise/Kindle highlights CSV; one stack of highlight notes per book
+export async function importHighlightsCSV(origin?: Point): Promise<{ notes: Note[]; stacks: Stack[] }> {
+  return invoke('import_highlights_csv', { args: { origin } })
 }
 
-export async function saveDocument(doc: BoardDocument): Promise<string> {
-  return invoke('save_document', { args: { doc } })
+// One note per row of a CSV or TSV table, laid out in a grid (asks for a
+// file when `path` is omitted); columns other than text, tags and faded
+// become note properties
+export async function importNotesFromCSV(path?: string, origin?: Point): Promise<{ notes: Note[]; skipped: number }> {
+  return invoke('import_notes_from_csv', { args: { path, origin } })
+}
+
+// One citation note per BibTeX entry (asks for a file when `path` is omitted)
+export async function importBibtex(path?: string, origin?: Point): Promise<{ notes: Note[]; warnings: string[] }> {
+  return invoke('import_bibtex', { args: { path, origin } })
+}
+
+// A link card note for a URL, with the page's title, description and preview image
+export interface UrlImport {
+  note: Note
+  image: EmbeddedImage | null
+  metadata: PageMetadata
+  warnings: string[]
+}
+
+export interface PageMetadata {
+  title: string | null
+  description: string | null
+  site_name: string | null
+  image_url: string | null
+  favicon_url: string | null
+}
+
+export async function importUrl(url: string, origin?: Point): Promise<UrlImport> {
+  return invoke('import_url', { args: { url, origin } })
+}
+
+// Title and icon (as a data URI) of a linked page, for showing the link
+export interface LinkMetadata {
+  url: string
+  final_url: string
+  title: string
+  metadata: PageMetadata
+  favicon: string | null
+}
+
+export async function fetchLinkMetadata(url: string): Promise<LinkMetadata> {
+  return invoke('fetch_link_metadata', { url })
+}
+
+// Web links checked over the network, plus links to notes that are gone
+export type LinkCheckStatus = 'ok' | 'dead' | 'blocked' | 'timeout' | 'error'
+
+export interface LinkStatus {
+  url: string
+  note_ids: string[]
+  status: LinkCheckStatus
+  http_status: number | null
+  redirected_to: string | null
+  error: string | null
+}
+
+export interface LinkValidation {
+  links: LinkStatus[]
+  dead: number
+  // [source note ID, missing target ID]
+  broken_note_links: [string, string][]
+  skipped: number
+  offline: boolean
+}
+
+export async function validateLinks(doc: BoardDocument): Promise<LinkValidation> {
+  return invoke('validate_links', { doc })
+}
+
+// Markdown vaults: the board as a folder of .md files with wiki-links.
+// `dir` is asked for with a folder dialog when omitted.
+export type VaultMode = 'notes' | 'subtrees'
+
+export interface VaultExport {
+  mode: VaultMode
+  written: string[]
+  removed: string[]
+}
+
+export interface VaultSync {
+  document: BoardDocument
+  updated: string[]
+  created: string[]
+  connections_added: number
+  connections_removed: number
+  warnings: string[]
+}
+
+export async function exportMarkdownVault(doc: BoardDocument, mode: VaultMode, dir?: string, onProgress?: ProgressHandler): Promise<VaultExport> {
+  return invoke('export_markdown_vault', { args: { doc, dir, mode }, onProgress: progressChannel(onProgress) })
+}
+
+export async function syncMarkdownVault(doc: BoardDocument, dir?: string): Promise<VaultSync> {
+  return invoke('sync_markdown_vault', { args: { doc, dir } })
+}
+
+// Every .fim board in a folder exported into another, one file per board
+// named after it. Folders are asked for when omitted; boards that fail are
+// reported per file and don't stop the others.
+export type BatchExportFormat = 'txt' | 'md' | 'opml' | 'pdf' | 'png'
+
+export interface BatchExport {
+  format: BatchExportFormat
+  output_dir: string
+  items: { board: string; output: string | null; error: string | null }[]
+  exported: number
+  failed: number
+}
+
+export async function batchExport(
+  format: BatchExportFormat,
+  inputDir?: string,
+  outputDir?: string,
+  onProgress?: ProgressHandler,
+  ordering?: string,
+  localeOptions: ExportLocaleOptions = {},
+): Promise<BatchExport> {
+  return invoke('batch_export', {
+    args: { input_dir: inputDir, format, output_dir: outputDir, ordering, ...localeOptions },
+    onProgress: progressChannel(onProgress),
+  })
+}
+
+// Read-only view of the window's board for others on the LAN. Send the board
+// to updateShareServer after changes so viewers follow along; null when this
+// window's board isn't the one shared.
+export interface ShareServerInfo {
+  url: string
+  port: number
+  window: string
+  viewers: number
+}
+
+export async function startShareServer(doc: BoardDocument, port?: number): Promise<ShareServerInfo> {
+  return invoke('start_share_server', { args: { doc, port } })
+}
+
+export async function updateShareServer(doc: BoardDocument): Promise<ShareServerInfo | null> {
+  return invoke('update_share_server', { doc })
+}
+
+export async function stopShareServer(): Promise<boolean> {
+  return invoke('stop_share_server')
+}
+
+// Notes and images from PDFs or images exported by Apple Freeform
+export interface FreeformImport {
+  notes: Note[]
+  images: EmbeddedImage[]
+  warnings: string[]
+}
+
+export async function importFreeformAssets(origin?: Point, onProgress?: ProgressHandler): Promise<FreeformImport> {
+  return invoke('import_freeform_assets', { args: { origin }, onProgress: progressChannel(onProgress) })
+}
+
+export async function openSpecificDocument(filePath: string, onProgress?: ProgressHandler): Promise<BoardDocument> {
+  return invoke('open_specific_document', { filePath, onProgress: progressChannel(onProgress) })
+}
+
+// Open a board for reference; edits and saving over it fail with code `read_only`
+export async function openReadonly(filePath: string, onProgress?: ProgressHandler): Promise<BoardDocument> {
+  return invoke('open_readonly', { filePath, onProgress: progressChannel(onProgress) })
+}
+
+export async function isReadOnly(): Promise<boolean> {
+  return invoke('is_read_only')
+}
+
+// Sent with `document-migrated` events ({ path, report }) when opening a board
+// upgraded it from an older schema version, or found it was written by a newer
+// version of the app, in which case it is open read-only
+export interface MigrationReport {
+  from_version: number
+  to_version: number
+  applied: string[]
+  newer: boolean
+}
+
+export async function onDocumentMigrated(handler: (event: { path: string; report: MigrationReport }) => void): Promise<() => void> {
+  return listen('document-migrated', handler)
+}
+
+// A board the OS asked us to open: double-clicked in Finder/Explorer, passed
+// on the command line, a fim:// link or a board for a new window. `document`
+// is null when it failed to load, with `error` saying why.
+export interface OpenDocumentEvent {
+  path: string
+  document: BoardDocument | null
+  error: string | null
+  focus_note_id: string | null // note to reveal once the board is shown
+  migration: MigrationReport | null
+}
+
+// Boards that arrived before this window's frontend was listening; call once
+// on mount, after onOpenDocument, and later ones come as events
+export async function takePendingOpenDocuments(): Promise<OpenDocumentEvent[]> {
+  return invoke('take_pending_open_documents')
+}
+
+export async function onOpenDocument(handler: (event: OpenDocumentEvent) => void): Promise<() => void> {
+  return listen('open-document', handler)
+}
+
+// Files dropped onto a window, sorted by the backend: boards to open, images
+// ready to embed, and what couldn't be used. Positions are in CSS pixels
+// from the window's top-left corner, not board coordinates.
+export interface DroppedImage {
+  image: EmbeddedImage
+  position: Point
+}
+
+export interface FilesDroppedEvent {
+  position: Point
+  documents: OpenDocumentEvent[]
+  images: DroppedImage[]
+  errors: { path: string; error: string }[]
+}
+
+export async function onFilesDropped(handler: (event: FilesDroppedEvent) => void): Promise<() => void> {
+  return listen('files-dropped', handler)
+}
+
+// A fim:// link pointed at a note of the board this window already shows;
+// the board isn't reloaded, so unsaved edits stay
+export async function onFocusNote(handler: (event: { note_id: string }) => void): Promise<() => void> {
+  return listen('focus-note', handler)
+}
+
+// What lenient opening had to repair, e.g. path `notes[3].zIndex`, expected `i64`
+export interface LoadDiagnostic {
+  path: string
+  expected: string | null
+  message: string
+}
+
+export async function openDocumentLenient(filePath: string): Promise<{ document: BoardDocument; diagnostics: LoadDiagnostic[] }> {
+  return invoke('open_document_lenient', { filePath })
+}
+
+// Problems that keep a board from loading or drawing as expected: dangling
+// references, duplicate IDs, invalid frames, ...
+export interface ValidationIssue {
+  severity: 'error' | 'warning'
+  message: string
+  entity_id: string | null
+}
+
+export async function validateDocument(doc: BoardDocument): Promise<ValidationIssue[]> {
+  return invoke('validate_document', { doc })
+}
+
+// Registers the fonts embedded in a board (default: the open one) so the
+// canvas and exports render with them; resolves to the families added
+export async function registerEmbeddedFonts(filePath?: string): Promise<string[]> {
+  const fonts: { family: string; file: string; data: string }[] = await invoke('get_embedded_fonts', { path: filePath ?? null })
+  const families: string[] = []
+  for (const font of fonts) {
+    const bytes = Uint8Array.from(atob(font.data), c => c.charCodeAt(0))
+    const face = new FontFace(font.family, bytes)
+    await face.load()
+    document.fonts.add(face)
+    families.push(font.family)
+  }
+  return families
+}
+
+// Save As: asks for a file unless `outputPath` is given. Exports take the same
+// optional `outputPath` so scripts and tests can run them without a dialog;
+// it must lie in a folder the app may write to.
+// `protection` sets a password for the .fim file or removes it; without it a
+// protected board stays protected, also when saved under a new name
+export async function saveDocument(
+  doc: BoardDocument,
+  onProgress?: ProgressHandler,
+  outputPath?: string,
+  protection: { password?: string; removePassword?: boolean } = {}
+): Promise<string> {
+  return invoke('save_document', {
+    args: { doc, output_path: outputPath, password: protection.password, remove_password: protection.removePassword },
+    onProgress: progressChannel(onProgress),
+  })
+}
+
+// Like saveCurrentDocument without `force`: fails with code `conflict` when the
+// file's content changed on disk since it was opened or last saved. The backend
+// watches the open board's file and sends `document-changed-externally` events
+// ({ path, exists }) as soon as that happens, so the user can reload or save a copy.
+export async function saveDocumentWithConflictCheck(doc: BoardDocument, onProgress?: ProgressHandler): Promise<string> {
+  return invoke('save_document_with_conflict_check', { doc, onProgress: progressChannel(onProgress) })
+}
+
+export async function onDocumentChangedExternally(handler: (event: { path: string; exists: boolean }) => void): Promise<() => void> {
+  return listen('document-changed-externally', handler)
+}
+
+// Check a protected board's password and keep its key for this session; fails
+// with code `wrong_password` when it doesn't fit
+export async function unlockDocument(filePath: string, password: string): Promise<void> {
+  return invoke('unlock_document', { args: { file_path: filePath, password } })
+}
+
+// Save to the open board's file without a dialog. Fails with code `conflict`
+// when the file changed on disk since it was opened, unless `force` is set.
+export async function saveCurrentDocument(doc: BoardDocument, force?: boolean, onProgress?: ProgressHandler): Promise<string> {
+  return invoke('save_current_document', { args: { doc, force }, onProgress: progressChannel(onProgress) })
+}
+
+// Append a note to a board without opening it (the inbox board by default).
+// If the board is open in a window that is listening, that window gets the
+// note through onQuickCaptureNote instead and `delivered` is true.
+export interface CapturedNote {
+  path: string
+  note_id: string
+  delivered: boolean
+}
+
+export async function quickCapture(text: string, targetPath?: string): Promise<CapturedNote> {
+  return invoke('quick_capture', { text, targetPath })
+}
+
+export async function onQuickCaptureNote(handler: (event: { note: Note }) => void): Promise<() => void> {
+  return listen('quick-capture-note', handler)
 }
 
 // Recent files operations
-export async function getRecentFiles(): Promise<string[]> {
+export interface RecentFileEntry {
+  path: string
+  display_name: string
+  last_opened: string
+  // null when the file no longer exists
+  size: number | null
+  pinned: boolean
+  // Fetch it with getDocumentThumbnail
+  thumbnail_available: boolean
+}
+
+// The thumbnail stored in a .fim board (base64 PNG, at most 512px on its
+// longer side), read without loading the board. Boards saved before
+// thumbnails existed give their schematic preview instead.
+export async function getDocumentThumbnail(path: string): Promise<string> {
+  return invoke('get_document_thumbnail', { path })
+}
+
+// Pinned entries first, then most recently opened
+export async function getRecentFiles(): Promise<RecentFileEntry[]> {
   return invoke('get_recent_files')
 }
 
+export async function setRecentFilePinned(path: string, pinned: boolean): Promise<void> {
+  return invoke('set_recent_file_pinned', { path, pinned })
+}
+
+export async function pinRecentFile(path: string): Promise<void> {
+  return invoke('pin_recent_file', { path })
+}
+
+export async function removeRecentFile(path: string): Promise<void> {
+  return invoke('remove_recent_file', { path })
+}
+
+// Also forgets the last session
 export async function clearRecentFiles(): Promise<void> {
   return invoke('clear_recent_files')
 }
 
+// Kept across launches; paths that no longer exist are null
+export interface LastSession {
+  last_document: string | null
+  last_save_path: string | null
+}
+
+export async function getLastSession(): Promise<LastSession> {
+  return invoke('get_last_session')
+}
+
+// Finished boards, zipped with their sidecar and recovery files into the app's
+// archive folder and listed in the library index
+export interface ArchivedBoard {
+  id: string
+  title: string
+  originalPath: string
+  archivePath: string
+  files: string[]
+  archivedAt: string
+  size: number
+}
+
+export async function archiveBoard(path: string): Promise<ArchivedBoard> {
+  return invoke('archive_board', { path })
+}
+
+// Restores to the original location unless `destination` (a folder) is given;
+// resolves to the board's path
+export async function unarchiveBoard(id: string, destination?: string): Promise<string> {
+  return invoke('unarchive_board', { id, destination })
+}
+
+export async function listArchivedBoards(query?: string): Promise<ArchivedBoard[]> {
+  return invoke('list_archived_boards', { query })
+}
+
+// A board found by the library scanner; `error` is set for unreadable files
+export interface LibraryBoard {
+  path: string
+  title: string
+  tags: string[]
+  note_count: number
+  info: DocumentInfo | null
+  modified: string | null
+  size: number
+  thumbnail_available: boolean
+  error: string | null
+}
+
+// What a .fim file's metadata entry holds; readable without decrypting anything
+export interface BoardMetadata {
+  title: string | null
+  tags: string[]
+  noteCount: number
+  info: DocumentInfo | null
+}
+
+export async function readDocumentInfo(filePath: string): Promise<BoardMetadata> {
+  return invoke('read_document_info', { filePath })
+}
+
+// Boards under the given folders (default: Documents), most recently modified first
+export async function scanLibrary(rootDirs?: string[]): Promise<LibraryBoard[]> {
+  return invoke('scan_library', { rootDirs })
+}
+
 // Autosave operations  
+// Queues the snapshot; the backend writes the latest one once editing pauses
+// and reports how that went with onAutosaveCompleted / onAutosaveFailed
 export async function autosaveDocument(doc: BoardDocument, filePath: string): Promise<void> {
   return invoke('autosave_document', { args: { doc, file_path: filePath } })
 }
 
+// A queued snapshot was written to its recovery file at `timestamp`
+export async function onAutosaveCompleted(handler: (info: { original_path: string; recovery_path: string; timestamp: string }) => void): Promise<() => void> {
+  return listen('autosave-completed', handler)
+}
+
+export async function onAutosaveFailed(handler: (event: { path: string; error: string }) => void): Promise<() => void> {
+  return listen('autosave-failed', handler)
+}
+
 export async function setDocumentDirty(isDirty: boolean): Promise<void> {
   return invoke('set_document_dirty', { isDirty })
 }
@@ -51,28 +683,1228 @@ export async function checkRecoveryFiles(): Promise<any[]> {
   return invoke('check_recovery_files')
 }
 
-export async function recoverFromAutosave(recoveryPath: string): Promise<any> {
-  return invoke('recover_from_autosave', { recoveryPath })
+// Per-user preferences kept in settings.json in the config folder
+export interface AppSettings {
+  secureCleanup: boolean
+  auditLog: boolean
+  quickCaptureShortcut: string
+  inboxPath: string | null
+  searchSidecar: boolean
+  simplifyStrokes: boolean
+  embedFonts: boolean
+  usageStats: boolean
+  translation: { provider: string; engine: string | null; endpoint: string | null; apiKey: string | null }
+  summarization: {
+    provider: string
+    engine: string | null
+    endpoint: string | null
+    apiKey: string | null
+    model: string | null
+    instructions: string | null
+  }
+  backup: { enabled: boolean; directory: string | null; intervalMinutes: number; keep: number }
+  writeMode: 'auto' | 'syncSafe' | 'direct'
+  keepVersions: number
+  keepSnapshots: number
+  recoveryLocations: string[]
+  publish: { confluenceUrl: string | null; confluenceUser: string | null }
+  exportLocale: string
+  exportTimezone: string
+  authorName: string
+  lazyMediaMb: number
+}
+
+// Fields to change; nested groups such as `backup` may list only some of
+// their fields
+export type SettingsChanges = {
+  [K in keyof AppSettings]?: AppSettings[K] extends unknown[] ? AppSettings[K]
+    : AppSettings[K] extends object ? Partial<AppSettings[K]> : AppSettings[K]
+}
+
+export async function getSettings(): Promise<AppSettings> {
+  return invoke('get_settings')
+}
+
+// Change the given settings, keeping the rest; returns all settings
+export async function updateSettings(changes: SettingsChanges): Promise<AppSettings> {
+  return invoke('update_settings', { changes })
+}
+
+// Folders searched for recovery files; autosave writes to the first usable one
+export async function getRecoveryLocations(): Promise<string[]> {
+  return invoke('get_recovery_locations')
+}
+
+// Without `path`, asks for a folder; a given one must be inside a folder
+// picked earlier or the Documents, Desktop or Downloads folder
+export async function addRecoveryLocation(path?: string): Promise<string[]> {
+  return invoke('add_recovery_location', { path: path ?? null })
+}
+
+export async function removeRecoveryLocation(path: string): Promise<string[]> {
+  return invoke('remove_recovery_location', { path })
+}
+
+export async function recoverFromAutosave(recoveryPath: string, onProgress?: ProgressHandler): Promise<any> {
+  return invoke('recover_from_autosave', { recoveryPath, onProgress: progressChannel(onProgress) })
+}
+
+// How a recovery file differs from the board it belongs to
+export interface ChangedNote {
+  id: string
+  change: 'added' | 'changed' | 'removed'
+  before?: string // start of the note's text on disk
+  after?: string // and in the recovery file
+}
+
+export interface RecoveryPreview {
+  original_path: string
+  recovery_path: string
+  timestamp: string
+  original_exists: boolean
+  notes_added: number
+  notes_changed: number
+  notes_removed: number
+  other_changes: number // connections, stacks and shapes
+  identical: boolean
+  sample: ChangedNote[]
+}
+
+export async function previewRecovery(recoveryPath: string): Promise<RecoveryPreview> {
+  return invoke('preview_recovery', { recoveryPath })
+}
+
+// Deletes the recovery file and its metadata, both or neither
+export async function discardRecovery(recoveryPath: string): Promise<void> {
+  return invoke('discard_recovery', { recoveryPath })
+}
+
+// Language and time zone of exported headers and dates; both default to the
+// `exportLocale` / `exportTimezone` settings
+export interface ExportLocaleOptions {
+  locale?: string // e.g. "de" or "en-US"
+  timezone?: string // "local", "UTC" or an offset such as "+02:00"
+}
+
+// What text exports leave out or add
+// Only notes with these labels: any of them (default) or all of them
+export interface LabelFilter {
+  labels: string[]
+  mode?: 'any' | 'all'
+}
+
+export interface TextExportOptions {
+  scrubMetadata?: boolean // no timestamps, style names or note IDs, for anonymous sharing
+  labelFilter?: LabelFilter
+  includeProperties?: boolean // note properties as OPML attributes
+  layers?: string[] // layer IDs to include; the visible ones by default
+}
+
+function textExportArgs(options: TextExportOptions) {
+  return {
+    scrub_metadata: options.scrubMetadata,
+    label_filter: options.labelFilter,
+    include_properties: options.includeProperties,
+    layers: options.layers,
+  }
+}
+
+// Export operations; `format` is txt, md, rtf, opml, dot (Graphviz), mermaid,
+// csv or tsv.
+// `includeStats` appends a statistics section to TXT, Markdown and RTF exports. With `noteIds`
+// only those notes are exported, with the connections between them and the
+// shapes they are in
+export async function exportDocumentAsText(
+  doc: BoardDocument,
+  format: string,
+  ordering?: string,
+  onProgress?: ProgressHandler,
+  includeStats?: boolean,
+  localeOptions: ExportLocaleOptions = {},
+  outputPath?: string,
+  noteIds?: string[],
+  options: TextExportOptions = {},
+): Promise<string> {
+  return invoke('export_document_as_text', {
+    args: { doc, format, ordering, include_stats: includeStats, ...localeOptions, output_path: outputPath, note_ids: noteIds, ...textExportArgs(options) },
+    onProgress: progressChannel(onProgress),
+  })
+}
+
+// JSON Canvas (.canvas) for Obsidian and other canvas apps; .canvas files
+// also open with openDocument and save back in that format. Scapple (.scap)
+// boards open too, but are saved as .fim.
+export async function exportDocumentAsCanvas(doc: BoardDocument, layers?: string[], outputPath?: string): Promise<string> {
+  return invoke('export_document_as_canvas', { args: { doc, layers, output_path: outputPath } })
+}
+
+// One self-contained .html page of the board (or just the selected notes) that
+// pans and zooms in any browser
+export async function exportDocumentAsHTML(
+  doc: BoardDocument,
+  options: { layers?: string[]; noteIds?: string[]; title?: string; outputPath?: string } = {},
+  onProgress?: ProgressHandler,
+): Promise<string> {
+  const args = { doc, layers: options.layers, note_ids: options.noteIds, title: options.title, output_path: options.outputPath }
+  return invoke('export_document_as_html', { args, onProgress: progressChannel(onProgress) })
+}
+
+// What an export would produce, for a preview pane: text for txt/md/rtf/opml/dot/mermaid/csv/tsv,
+// a small PNG of the board for png/pdf
+export interface ExportPreview {
+  format: string
+  content: string | null
+  truncated: boolean
+  png: number[] | null
+}
+
+export async function previewExport(
+  doc: BoardDocument,
+  format: string,
+  ordering?: string,
+  includeStats?: boolean,
+  localeOptions: ExportLocaleOptions = {},
+  noteIds?: string[],
+  options: TextExportOptions = {},
+): Promise<ExportPreview> {
+  return invoke('preview_export', { args: { doc, format, ordering, include_stats: includeStats, ...localeOptions, note_ids: noteIds, ...textExportArgs(options) } })
+}
+
+// Word counts and reading time for the insights panel
+export interface GroupTextStats {
+  id: string
+  label: string | null
+  notes: number
+  words: number
+  reading_minutes: number
+}
+
+export interface TextStats {
+  notes: number
+  empty_notes: number
+  words: number
+  characters: number
+  average_words_per_note: number
+  reading_minutes: number
+  top_terms: { term: string; count: number }[]
+  per_note: { id: string; words: number; characters: number }[]
+  shapes: GroupTextStats[]
+  stacks: GroupTextStats[]
+}
+
+export async function textStats(doc: BoardDocument): Promise<TextStats> {
+  return invoke('text_stats', { doc })
+}
+
+// Board totals for word counts without exporting; `media_bytes` is the
+// decoded size of the images stored in the board
+export interface DocumentStats {
+  notes: number
+  words: number
+  characters: number
+  reading_minutes: number
+  connections: number
+  faded_notes: number
+  shapes: GroupTextStats[]
+  stacks: GroupTextStats[]
+  bounds: Rect | null
+  images: number
+  media_bytes: number
+  linked_images: number
+}
+
+export async function getDocumentStats(doc: BoardDocument): Promise<DocumentStats> {
+  return invoke('get_document_stats', { doc })
+}
+
+export interface NoteMetrics {
+  id: string
+  label: string
+  in_degree: number
+  out_degree: number
+  degree: number
+  betweenness: number
+  closeness: number
+  component: number
+}
+
+export interface GraphReport {
+  notes: number
+  connections: number
+  density: number
+  degree_distribution: { degree: number; notes: number }[]
+  components: string[][]
+  metrics: NoteMetrics[]
+}
+
+// With `exportCsv`, also asks where to save the per-note metrics
+export async function analyzeGraph(
+  doc: BoardDocument,
+  exportCsv = false,
+  outputPath?: string
+): Promise<{ report: GraphReport; csv_path: string | null }> {
+  return invoke('analyze_graph', { args: { doc, export_csv: exportCsv, output_path: outputPath } })
+}
+
+// `connection_ids[i]` leads from `note_ids[i]` to the next note; the last
+// one closes the cycle
+export interface GraphCycle {
+  note_ids: string[]
+  connection_ids: string[]
+}
+
+export async function findCycles(doc: BoardDocument): Promise<GraphCycle[]> {
+  return invoke('find_cycles', { doc })
+}
+
+export async function findOrphans(doc: BoardDocument): Promise<string[]> {
+  return invoke('find_orphans', { doc })
+}
+
+// analyzeGraph's report together with findOrphans and findCycles, computed
+// off the UI thread in one call
+export interface DocumentAnalysis extends GraphReport {
+  orphans: string[]
+  cycles: GraphCycle[]
+}
+
+export async function analyzeDocument(doc: BoardDocument): Promise<DocumentAnalysis> {
+  return invoke('analyze_document', { doc })
 }
 
-// Export operations
-export async function exportDocumentAsText(doc: BoardDocument, format: string, ordering?: string): Promise<string> {
-  return invoke('export_document_as_text', { args: { doc, format, ordering } })
+export interface SearchOptions {
+  case_sensitive?: boolean
+  whole_word?: boolean
+  regex?: boolean
+  limit?: number // most matches per board (default 500)
 }
 
-export async function exportDocumentAsPNG(scale: number): Promise<string> {
-  return invoke('export_document_as_png', { scale })
+// `highlights` are [start, end) ranges in `snippet`, in UTF-16 units
+export interface NoteMatch {
+  note_id: string
+  snippet: string
+  highlights: [number, number][]
+  match_count: number
+  frame: Rect
+  stack_id: string | null
+  shape_id: string | null
 }
 
-export async function savePngToFile(filePath: string, pngData: Uint8Array): Promise<void> {
-  return invoke('save_png_to_file', { filePath, pngData })
+export interface SearchResults {
+  matches: NoteMatch[]
+  documents: { path: string; display_name: string; matches: NoteMatch[] }[] // with includeRecent
+  skipped: string[]
 }
 
-export async function exportDocumentAsPDF(pageSize: string, orientation: string): Promise<string> {
-  return invoke('export_document_as_pdf', { pageSize, orientation })
+// Note text, checklists and tables; `includeRecent` also searches the boards
+// in the recent files list
+export async function searchNotes(doc: BoardDocument, query: string, options: SearchOptions = {}, includeRecent = false): Promise<SearchResults> {
+  return invoke('search_notes', { args: { doc, query, ...options, include_recent: includeRecent } })
 }
 
-export async function savePdfToFile(filePath: string, pdfData: Uint8Array): Promise<void> {
-  return invoke('save_pdf_to_file', { filePath, pdfData })
+// Every tag on the board with how many notes carry it, by name
+export async function getAllTags(doc: BoardDocument): Promise<{ tag: string; count: number }[]> {
+  return invoke('get_all_tags', { doc })
 }
 
+// IDs of the notes carrying any (or, with mode 'all', every one) of `tags`
+export async function filterNotesByTag(doc: BoardDocument, tags: string[], mode: 'any' | 'all' = 'any'): Promise<string[]> {
+  return invoke('filter_notes_by_tag', { args: { doc, filter: { tags, mode } } })
+}
+
+export interface StackSuggestionOptions {
+  maxGap?: number
+  alignTolerance?: number
+  minNotes?: number
+}
+
+// Each suggestion's notes are laid out and joined to its stack already
+export async function suggestStacks(
+  doc: BoardDocument,
+  options: StackSuggestionOptions = {}
+): Promise<{ stack: Stack; notes: Note[] }[]> {
+  return invoke('suggest_stacks', {
+    args: {
+      doc,
+      max_gap: options.maxGap ?? null,
+      align_tolerance: options.alignTolerance ?? null,
+      min_notes: options.minNotes ?? null
+    }
+  })
+}
+
+export interface LayoutOptions {
+  algorithm?: 'force' | 'tree' | 'grid'
+  shapeId?: string // only the notes inside this shape
+  spacing?: number
+  direction?: 'right' | 'down' // tree layouts
+  iterations?: number // force layouts
+}
+
+// Frames for the notes laid out; `shape` is the constraining shape grown to
+// fit, when the layout outgrew it
+export async function layoutDocument(
+  doc: BoardDocument,
+  options: LayoutOptions = {}
+): Promise<{ notes: { id: string; frame: Rect }[]; bounds: Rect | null; shape: { id: string; frame: Rect } | null }> {
+  return invoke('layout_document', {
+    args: {
+      doc,
+      algorithm: options.algorithm ?? null,
+      shape_id: options.shapeId ?? null,
+      spacing: options.spacing ?? null,
+      direction: options.direction ?? null,
+      iterations: options.iterations ?? null
+    }
+  })
+}
+
+export interface EntityChanges {
+  added: string[]
+  removed: string[]
+  modified: { id: string; fields: string[] }[]
+}
+
+export interface DocumentDiff {
+  notes: EntityChanges
+  connections: EntityChanges
+  stacks: EntityChanges
+  shapes: EntityChanges
+  identical: boolean
+}
+
+export interface MergeConflict {
+  kind: 'note' | 'connection' | 'stack' | 'shape' | 'stroke'
+  id: string
+  reason: 'both_changed' | 'changed_and_removed'
+  fields: string[]
+  ours: Record<string, unknown> | null
+  theirs: Record<string, unknown> | null
+}
+
+export interface MergeReport {
+  added: { kind: string; id: string }[]
+  updated: { kind: string; id: string }[]
+  removed: { kind: string; id: string }[]
+  conflicts: MergeConflict[]
+  dropped_connections: string[]
+}
+
+// What changed from the board at `pathA` to the one at `pathB`
+export async function diffDocuments(pathA: string, pathB: string): Promise<DocumentDiff> {
+  return invoke('diff_documents', { args: { path_a: pathA, path_b: pathB } })
+}
+
+// The board at `pathB` merged into the one at `pathA`, three-way when
+// `basePath` is the copy both started from. Nothing is saved.
+export async function mergeDocuments(
+  pathA: string,
+  pathB: string,
+  basePath?: string
+): Promise<{ document: BoardDocument; report: MergeReport }> {
+  return invoke('merge_documents', { args: { path_a: pathA, path_b: pathB, base_path: basePath ?? null } })
+}
+
+export interface ShapeMembership {
+  shape_id: string
+  magnetic: boolean
+  note_ids: string[]
+}
+
+export async function computeShapeMembership(doc: BoardDocument): Promise<ShapeMembership[]> {
+  return invoke('compute_shape_membership', { doc })
+}
+
+// New frames for the shape and, when it's magnetic, the notes it carries
+export async function moveShape(
+  doc: BoardDocument,
+  shapeId: string,
+  dx: number,
+  dy: number
+): Promise<{ shape: { id: string; frame: Rect }; notes: { id: string; frame: Rect }[] }> {
+  return invoke('move_shape', { args: { doc, shape_id: shapeId, dx, dy } })
+}
+
+// Stacking order: the notes and shapes among `ids` go above or below the
+// others, keeping their order among themselves. Returns the board with
+// `zIndex` renumbered and its notes and shapes in paint order.
+export async function bringToFront(doc: BoardDocument, ids: string[]): Promise<BoardDocument> {
+  return invoke('bring_to_front', { args: { doc, ids } })
+}
+
+export async function sendToBack(doc: BoardDocument, ids: string[]): Promise<BoardDocument> {
+  return invoke('send_to_back', { args: { doc, ids } })
+}
+
+// Groups move as a unit; a note or shape belongs to at most one. Locked items
+// stay put when a group moves and are listed in `skipped_locked`.
+export async function createGroup(doc: BoardDocument, noteIds: string[], shapeIds: string[], label?: string): Promise<{ document: BoardDocument; group_id: string }> {
+  return invoke('create_group', { args: { doc, note_ids: noteIds, shape_ids: shapeIds, label } })
+}
+
+export async function dissolveGroup(doc: BoardDocument, groupId: string): Promise<BoardDocument> {
+  return invoke('dissolve_group', { args: { doc, group_id: groupId } })
+}
+
+export async function moveGroup(doc: BoardDocument, groupId: string, dx: number, dy: number): Promise<{ document: BoardDocument; skipped_locked: string[] }> {
+  return invoke('move_group', { args: { doc, group_id: groupId, dx, dy } })
+}
+
+export interface SnapResult {
+  frame: Rect
+  snapped_x: boolean
+  snapped_y: boolean
+  candidates: { axis: 'x' | 'y'; value: number; distance: number; source: 'note' | 'grid'; note_id: string | null }[]
+  guides: { axis: 'x' | 'y'; position: number; start: number; end: number }[]
+}
+
+// Call once when a drag starts; snapFrame then works off the indexed notes
+export async function prepareSnapping(doc: BoardDocument): Promise<void> {
+  return invoke('prepare_snapping', { doc })
+}
+
+export async function snapFrame(frame: Rect, excludeIds: string[] = [], threshold?: number): Promise<SnapResult> {
+  return invoke('snap_frame', { args: { frame, exclude_ids: excludeIds, threshold: threshold ?? null } })
+}
+
+// Saves the board's styles as a .fimtheme file; resolves to its path
+export async function exportStyleTheme(doc: BoardDocument, name?: string, outputPath?: string): Promise<string> {
+  return invoke('export_style_theme', { args: { doc, name: name ?? null, output_path: outputPath } })
+}
+
+// Without `path`, asks for the theme file
+export async function applyStyleTheme(
+  doc: BoardDocument,
+  path?: string
+): Promise<{ document: BoardDocument; updated: string[]; added: string[] }> {
+  return invoke('apply_style_theme', { args: { doc, path: path ?? null } })
+}
+
+export interface LibraryStyle {
+  style: NoteStyle
+  savedAt: string
+}
+
+// Saves a board style to the app-wide library under `name` (default: the style's name)
+export async function saveStyleToLibrary(doc: BoardDocument, styleId: string, name?: string): Promise<LibraryStyle> {
+  return invoke('save_style_to_library', { args: { doc, style_id: styleId, name: name ?? null } })
+}
+
+export async function listLibraryStyles(): Promise<LibraryStyle[]> {
+  return invoke('list_library_styles')
+}
+
+// Adds copies of library styles under new IDs
+export async function insertLibraryStyles(doc: BoardDocument, ids: string[]): Promise<{ document: BoardDocument; added: string[] }> {
+  return invoke('insert_library_styles', { args: { doc, ids } })
+}
+
+export async function removeLibraryStyle(id: string): Promise<void> {
+  return invoke('remove_library_style', { id })
+}
+
+// Makes an image the background texture; without `path`, asks for one
+export async function importTexture(
+  doc: BoardDocument,
+  path?: string,
+  scale?: number,
+  opacity?: number
+): Promise<BoardDocument> {
+  return invoke('import_texture', { args: { doc, path: path ?? null, scale: scale ?? null, opacity: opacity ?? null } })
+}
+
+export async function removeTexture(doc: BoardDocument): Promise<BoardDocument> {
+  return invoke('remove_texture', { doc })
+}
+
+// Adds a picture to the board's images (stored under media/ in .fim files);
+// without `path`, asks for one
+export async function addImageToDocument(doc: BoardDocument, path?: string): Promise<{ document: BoardDocument; image_id: string }> {
+  return invoke('add_image_to_document', { args: { doc, path: path ?? null } })
+}
+
+// Reads one image of a board on disk; defaults to the open board's file
+export async function getImageData(imageId: string, filePath?: string): Promise<{ id: string; mime: string; data_base64: string }> {
+  return invoke('get_image_data', { args: { image_id: imageId, file_path: filePath ?? null } })
+}
+
+// Reads an image of the open board that was left in its file when opened
+// (it has a `path` and no `dataBase64`)
+export async function readMedia(imageId: string): Promise<{ id: string; mime: string; data_base64: string }> {
+  return invoke('read_media', { imageId })
+}
+
+// An image being added to the board: shrunk so neither side is longer than
+// `maxDimension`, encoded again as WebP (default) or PNG when that makes it
+// smaller, and matched against the board's images. `image` is null when the
+// board already has it; use `id` either way.
+export interface IngestedImage {
+  id: string
+  width: number
+  height: number
+  image: EmbeddedImage | null
+  bytes: number
+  original_bytes: number
+}
+
+export async function ingestImage(
+  doc: BoardDocument,
+  data: Uint8Array,
+  maxDimension?: number,
+  format?: 'webp' | 'png',
+): Promise<IngestedImage> {
+  return invoke('ingest_image', { args: { doc, data: Array.from(data), max_dimension: maxDimension, format } })
+}
+
+export interface PaletteColor {
+  color: string
+  count: number
+  usages: string[]
+}
+
+export async function extractPalette(doc: BoardDocument): Promise<PaletteColor[]> {
+  return invoke('extract_palette', { doc })
+}
+
+// A `from` without alpha matches the color at any opacity
+export async function replaceColor(doc: BoardDocument, from: string, to: string): Promise<{ document: BoardDocument; replaced: number }> {
+  return invoke('replace_color', { args: { doc, from, to } })
+}
+
+export interface RecentNote {
+  id: string
+  text: string
+  changed_at: string
+  created: boolean
+}
+
+export async function recentlyEdited(doc: BoardDocument, since: Date): Promise<RecentNote[]> {
+  return invoke('recently_edited', { args: { doc, since: since.toISOString() } })
+}
+
+// Splits off the branches untouched since `cutoff`; with `saveArchive`, asks
+// where to save them
+export async function splitByActivity(
+  doc: BoardDocument,
+  cutoff: Date,
+  saveArchive = false,
+  outputPath?: string
+): Promise<{ active: BoardDocument; archived: BoardDocument; archived_note_ids: string[]; archive_path: string | null }> {
+  return invoke('split_by_activity', { args: { doc, cutoff: cutoff.toISOString(), save_archive: saveArchive, output_path: outputPath } })
+}
+
+export interface BackupEntry {
+  path: string
+  source_name: string
+  created_at: string
+  size: number
+}
+
+// Backs up the boards that changed since their last backup, whatever the schedule
+export async function runBackupNow(): Promise<{ source: string; backup: string }[]> {
+  return invoke('run_backup_now')
+}
+
+// Newest first; only the backups of `path` when given
+export async function listBackups(path?: string): Promise<BackupEntry[]> {
+  return invoke('list_backups', { path: path ?? null })
+}
+
+// Named copies of a board kept inside its .fim file
+export interface Snapshot {
+  id: string
+  label: string
+  createdAt: string
+  noteCount: number
+  connectionCount: number
+}
+
+// Adds a snapshot of `doc` to the open board's file (or `path`), without
+// saving the board itself
+export async function createSnapshot(doc: BoardDocument, label?: string, path?: string): Promise<Snapshot> {
+  return invoke('create_snapshot', { args: { doc, label: label ?? null, path: path ?? null } })
+}
+
+// Newest first
+export async function listSnapshots(path: string): Promise<Snapshot[]> {
+  return invoke('list_snapshots', { path })
+}
+
+// The board as it was; nothing is written until it is saved
+export async function restoreSnapshot(path: string, snapshotId: string): Promise<BoardDocument> {
+  return invoke('restore_snapshot', { path, snapshotId })
+}
+
+// Notes from one of the board's note templates, with `{{placeholders}}`
+// filled in from `values` ({{date}} defaults to today) and frames placed
+// relative to `position`
+export async function instantiateTemplate(doc: BoardDocument, templateId: string, values: Record<string, string>, position?: Point): Promise<Note[]> {
+  return invoke('instantiate_template', { args: { doc, template_id: templateId, values, position: position ?? null } })
+}
+
+// Placeholder names a note template uses, sorted
+export async function getTemplatePlaceholders(doc: BoardDocument, templateId: string): Promise<string[]> {
+  return invoke('get_template_placeholders', { doc, templateId })
+}
+
+// Copies of the notes with fresh IDs, moved by `offset` (default 20, 20),
+// with the connections among them, their stacks and image entries; with
+// `includeDescendants` also the notes reachable through outgoing connections
+export interface Duplication {
+  notes: Note[]
+  connections: Connection[]
+  stacks: Stack[]
+  images: EmbeddedImage[]
+  id_map: Record<string, string>
+}
+
+export async function duplicateNotes(doc: BoardDocument, noteIds: string[], includeDescendants = false, offset?: Point): Promise<Duplication> {
+  return invoke('duplicate_notes', { args: { doc, note_ids: noteIds, include_descendants: includeDescendants, offset: offset ?? null } })
+}
+
+// Board templates kept in the app data folder, for starting new boards
+export interface BoardTemplate {
+  name: string
+  path: string
+  modified_at: string | null
+  size: number
+}
+
+// Without `keepContent` only the notes' layout and look are kept; fails with
+// a conflict when the name is taken, unless `overwrite`
+export async function saveAsTemplate(doc: BoardDocument, name: string, keepContent = false, overwrite = false): Promise<BoardTemplate> {
+  return invoke('save_as_template', { args: { doc, name, keep_content: keepContent, overwrite } })
+}
+
+export async function listTemplates(): Promise<BoardTemplate[]> {
+  return invoke('list_templates')
+}
+
+// A new, unsaved board set up like the template
+export async function createFromTemplate(name: string): Promise<BoardDocument> {
+  return invoke('create_from_template', { name })
+}
+
+export async function deleteTemplate(name: string): Promise<void> {
+  return invoke('delete_template', { name })
+}
+
+export interface NotePath {
+  note_ids: string[]
+  connection_ids: string[]
+}
+
+export interface SimilarSuggestions {
+  notes: { id: string; text: string; score: number }[]
+  boards: { path: string; title: string; score: number }[]
+}
+
+// Existing notes like `text`, best first; `excludeNoteId` skips the note being edited
+export async function suggestSimilar(
+  doc: BoardDocument,
+  text: string,
+  options: { limit?: number; minScore?: number; excludeNoteId?: string; includeLibrary?: boolean } = {}
+): Promise<SimilarSuggestions> {
+  return invoke('suggest_similar', {
+    args: {
+      doc,
+      text,
+      limit: options.limit ?? null,
+      min_score: options.minScore ?? null,
+      exclude_note_id: options.excludeNoteId ?? null,
+      include_library: options.includeLibrary ?? null
+    }
+  })
+}
+
+// Shortest path first, then alternatives; empty when the notes aren't connected
+export async function findPath(
+  doc: BoardDocument,
+  fromNoteId: string,
+  toNoteId: string,
+  options: { directed?: boolean; alternatives?: number } = {}
+): Promise<NotePath[]> {
+  return invoke('find_path', {
+    args: {
+      doc,
+      from_note_id: fromNoteId,
+      to_note_id: toNoteId,
+      directed: options.directed ?? null,
+      alternatives: options.alternatives ?? null
+    }
+  })
+}
+
+// IDs of starred notes, in document order
+export async function listStarred(doc: BoardDocument): Promise<string[]> {
+  return invoke('list_starred', { doc })
+}
+
+export interface RestoreResult {
+  document: BoardDocument
+  restored: string[]
+  skipped: string[]
+}
+
+// Puts trashed notes/connections back; a restored note brings its
+// connections to notes still on the board
+export async function restoreFromTrash(doc: BoardDocument, ids: string[]): Promise<RestoreResult> {
+  return invoke('restore_from_trash', { args: { doc, ids } })
+}
+
+// Removes trashed items for good; `olderThan` (ISO date) keeps newer ones
+export async function emptyTrash(doc: BoardDocument, olderThan?: string): Promise<{ document: BoardDocument; removed: number }> {
+  return invoke('empty_trash', { args: { doc, older_than: olderThan ?? null } })
+}
+
+// Candidate board titles, best first; the save dialog is pre-filled with the first
+export interface TitleSuggestion {
+  title: string
+  source: 'info' | 'property' | 'root' | 'terms'
+}
+
+export async function suggestTitle(doc: BoardDocument): Promise<TitleSuggestion[]> {
+  return invoke('suggest_title', { doc })
+}
+
+// Who made the board and when, as stamped on save, for the document info panel
+export interface DocumentSummary {
+  title: string | null
+  author: string | null
+  owner: string | null
+  created_at: string | null
+  updated_at: string | null
+  app_version: string | null
+  note_count: number
+  connection_count: number
+  first_note_at: string | null
+  last_note_at: string | null
+  undated_notes: number
+  path: string | null
+}
+
+export async function getDocumentInfo(doc: BoardDocument): Promise<DocumentSummary> {
+  return invoke('get_document_info', { doc })
+}
+
+// Per-note language (ISO 639-3 code) detected offline
+export interface NoteLanguage {
+  id: string
+  language: string | null
+  name: string | null
+  confidence: number
+  reliable: boolean
+}
+
+export async function detectLanguages(doc: BoardDocument): Promise<NoteLanguage[]> {
+  return invoke('detect_languages', { doc })
+}
+
+// Translated copies placed next to their source notes, through the provider in
+// the `translation` setting; notes without text come back in `skipped`
+export async function translateNotes(
+  doc: BoardDocument,
+  noteIds: string[],
+  targetLang: string,
+): Promise<{ notes: Note[]; skipped: string[] }> {
+  return invoke('translate_notes', { args: { doc, note_ids: noteIds, target_lang: targetLang } })
+}
+
+// A draft note summarizing the selected notes, through the provider in the
+// `summarization` setting; only their text is sent. Confidential notes and
+// notes without text come back in `skipped`.
+export async function summarizeNotes(
+  doc: BoardDocument,
+  noteIds: string[],
+): Promise<{ note: Note; sources: string[]; skipped: string[] }> {
+  return invoke('summarize_notes', { args: { doc, note_ids: noteIds } })
+}
+
+// Confidential notes: content encrypted at rest with a per-board passphrase
+export async function setNotesPassphrase(doc: BoardDocument, passphrase: string, remember = false): Promise<BoardDocument> {
+  return invoke('set_notes_passphrase', { args: { doc, passphrase, remember } })
+}
+
+// Without a passphrase, uses a key unlocked earlier or remembered in the keychain
+export async function unlockConfidentialNotes(doc: BoardDocument, passphrase?: string, remember = false): Promise<BoardDocument> {
+  return invoke('unlock_confidential_notes', { args: { doc, passphrase: passphrase ?? null, remember } })
+}
+
+export async function lockConfidentialNotes(doc: BoardDocument, forget = false): Promise<BoardDocument> {
+  return invoke('lock_confidential_notes', { args: { doc, forget } })
+}
+
+export async function setNotesConfidential(doc: BoardDocument, noteIds: string[], confidential: boolean): Promise<BoardDocument> {
+  return invoke('set_notes_confidential', { args: { doc, note_ids: noteIds, confidential } })
+}
+
+export type PublishTarget = 'confluence' | 'notion'
+
+export interface PublishOptions {
+  space?: string // Confluence space key
+  page?: string // parent page id; required for Notion
+  path?: string // the board's file, so re-publishing updates the same page
+  image?: string // base64 PNG render; a preview thumbnail is used when missing
+}
+
+export interface PublishResult {
+  page_id: string
+  url: string
+  updated: boolean
+}
+
+// Tokens go to the OS keychain; an empty token removes it
+export async function setPublishToken(target: PublishTarget, token: string): Promise<void> {
+  return invoke('set_publish_token', { target, token })
+}
+
+export async function hasPublishToken(target: PublishTarget): Promise<boolean> {
+  return invoke('has_publish_token', { target })
+}
+
+export async function publishBoard(doc: BoardDocument, target: PublishTarget, options: PublishOptions = {}): Promise<PublishResult> {
+  return invoke('publish_board', {
+    args: {
+      doc,
+      target,
+      space: options.space ?? null,
+      page: options.page ?? null,
+      path: options.path ?? null,
+      image: options.image ?? null,
+    },
+  })
+}
+
+export interface EmailExportOptions extends ExportLocaleOptions {
+  to?: string[]
+  subject?: string // defaults to the board's suggested title
+  png?: Uint8Array // renders to attach; a preview thumbnail when neither is given
+  pdf?: Uint8Array
+  layers?: string[] // layers in the outline; defaults to the visible ones
+}
+
+// Writes an .eml draft and opens it in the default mail client; `opened` is
+// false when no mail client could be started
+export async function exportAsEmail(doc: BoardDocument, options: EmailExportOptions = {}): Promise<{ path: string; opened: boolean }> {
+  return invoke('export_as_email', {
+    args: {
+      doc,
+      to: options.to ?? null,
+      subject: options.subject ?? null,
+      png: options.png ? Array.from(options.png) : null,
+      pdf: options.pdf ? Array.from(options.pdf) : null,
+      layers: options.layers ?? null,
+      locale: options.locale ?? null,
+      timezone: options.timezone ?? null,
+    },
+  })
+}
+
+// Raster export options; the backend validates them and returns the board
+// region and scale to render alongside the chosen path
+export interface PngExportOptions {
+  scale?: number
+  dpi?: number
+  region?: Rect
+  padding?: number
+  note_ids?: string[] // only these notes, their connections and the shapes they are in
+}
+
+export interface PngExportTarget {
+  path: string
+  plan: { scale: number; region: Rect; width: number; height: number }
+  selection: BoardDocument | null // what to render when `note_ids` was given
+}
+
+export async function exportDocumentAsPNG(doc: BoardDocument, options: PngExportOptions, outputPath?: string): Promise<PngExportTarget> {
+  return invoke('export_document_as_png', { args: { doc, ...options, output_path: outputPath } })
+}
+
+// The board drawn by the backend rather than the webview, with no canvas
+// size limit; visible layers only. `scrubMetadata` strips EXIF and such from
+// the images embedded in it
+export async function renderDocumentToSVG(doc: BoardDocument, options: PngExportOptions = {}, scrubMetadata?: boolean): Promise<string> {
+  return invoke('render_document_to_svg', { args: { doc, ...options, scrub_metadata: scrubMetadata } })
+}
+
+export async function renderDocumentToPNG(doc: BoardDocument, options: PngExportOptions = {}): Promise<Uint8Array> {
+  const bytes: number[] = await invoke('render_document_to_png', { args: { doc, ...options } })
+  return Uint8Array.from(bytes)
+}
+
+// Watermark and header/footer lines stamped onto PNG/PDF exports by the
+// backend; header and footer accept {title}, {date}, {page} and {pages}
+export interface ExportMarkings extends ExportLocaleOptions {
+  title?: string
+  header?: string
+  footer?: string
+  watermark_text?: string
+  watermark_image?: string
+  watermark_opacity?: number
+  qr_code?: string // encoded as a QR code in the bottom-right corner
+}
+
+// fim:// link to the current (saved) document, optionally to one of its notes
+export async function getNoteLink(noteId?: string): Promise<string> {
+  return invoke('get_note_link', { noteId: noteId ?? null })
+}
+
+// PNG of a QR code for `data`, `moduleSize` pixels per module (default 8)
+export async function generateQr(data: string, moduleSize?: number): Promise<Uint8Array> {
+  const bytes: number[] = await invoke('generate_qr', { data, moduleSize: moduleSize ?? null })
+  return Uint8Array.from(bytes)
+}
+
+export async function savePngToFile(filePath: string, pngData: Uint8Array, markings?: ExportMarkings): Promise<void> {
+  return invoke('save_png_to_file', { filePath, pngData, markings })
+}
+
+export type ShareFormat = 'fim' | 'json' | 'txt' | 'md' | 'rtf' | 'opml' | 'dot' | 'mermaid' | 'csv' | 'tsv' | 'png' | 'pdf'
+
+// Export into a temporary file and hand it to the OS share sheet; PNG and PDF
+// need the frontend's render as `data`. `presented` is false when there is no
+// share sheet and the file was revealed instead
+export async function shareExport(
+  doc: BoardDocument,
+  format: ShareFormat,
+  options: { data?: Uint8Array; layers?: string[]; locale?: string; timezone?: string } = {}
+): Promise<{ path: string; presented: boolean }> {
+  return invoke('share_export', {
+    args: {
+      doc,
+      format,
+      data: options.data ? Array.from(options.data) : null,
+      layers: options.layers ?? null,
+      locale: options.locale ?? null,
+      timezone: options.timezone ?? null,
+    }
+  })
+}
+
+// Without `render` this only picks the path, and the frontend saves its own
+// PDF there with savePdfToFile; with it the backend writes the PDF itself
+export interface PdfRenderOptions {
+  doc: BoardDocument
+  note_ids?: string[] // only these notes, their connections and the shapes they are in
+  mode?: 'fit' | 'tile' // tile: true scale across pages, with crop marks
+  dpi?: number // resolution of the page images, 72–600 (default 150)
+  scale?: number // tile mode print scale
+  overlap_mm?: number
+  crop_marks?: boolean
+  markings?: ExportMarkings
+  scrub_metadata?: boolean // leave out document information and XMP metadata
+  protection?: PdfProtection
+}
+
+// Encrypts the PDF (128-bit AES). Viewers ask for `user_password` when it is
+// opened and keep printing/copying off unless opened with `owner_password`;
+// without an owner password nobody can lift the restrictions
+export interface PdfProtection {
+  user_password?: string
+  owner_password?: string
+  no_print?: boolean
+  no_copy?: boolean
+}
+
+export async function exportDocumentAsPDF(
+  pageSize: string,
+  orientation: string,
+  outputPath?: string,
+  render?: PdfRenderOptions,
+  onProgress?: ProgressHandler,
+): Promise<string> {
+  return invoke('export_document_as_pdf', { pageSize, orientation, outputPath, render, onProgress: progressChannel(onProgress) })
+}
+
+// Printing through the system instead of window.print(), which clips the
+// canvas; pages are laid out as for native PDF exports
+export interface PrintOptions {
+  doc: BoardDocument
+  note_ids?: string[]
+  page_size: string // a3, a4, a5, letter, legal
+  orientation: string // auto, portrait, landscape
+  mode?: 'fit' | 'tile'
+  dpi?: number
+  scale?: number
+  overlap_mm?: number
+  crop_marks?: boolean
+  printer?: string // the default printer when omitted
+  copies?: number // 1–99
+  show_dialog?: boolean // open the PDF in the default viewer to print from there
+}
+
+export interface PrintJob {
+  path: string
+  pages: number
+  printer?: string
+  job_id?: string // CUPS request ID
+  opened: boolean
+}
+
+export async function printDocument(args: PrintOptions, onProgress?: ProgressHandler): Promise<PrintJob> {
+  return invoke('print_document', { args, onProgress: progressChannel(onProgress) })
+}
+
+// PDF outline entry; `frame` is the note's board-space frame
+export interface PdfBookmark {
+  title: string
+  note_id: string
+  frame: Rect
+  children: PdfBookmark[]
+}
+
+export async function getPdfBookmarks(doc: BoardDocument): Promise<PdfBookmark[]> {
+  return invoke('get_pdf_bookmarks', { doc })
+}
+
+// Page grid for poster-mode PDF exports; tile regions are in board space
+export interface PosterTile {
+  row: number
+  column: number
+  label: string
+  region: Rect
+}
+
+export interface PosterPlan {
+  page_width_mm: number
+  page_height_mm: number
+  orientation: 'portrait' | 'landscape'
+  margin_mm: number
+  overlap_mm: number
+  mm_per_unit: number
+  columns: number
+  rows: number
+  content: Rect
+  tiles: PosterTile[]
+}
+
+export async function planPosterExport(
+  doc: BoardDocument,
+  options: { page_size: string; orientation: string; scale?: number; overlap_mm?: number }
+): Promise<PosterPlan> {
+  return invoke('plan_poster_export', { args: { doc, ...options } })
+}
+
+// Copy of the document restyled for a light, dark or custom export background
+export async function applyExportTheme(
+  doc: BoardDocument,
+  exportTheme: 'light' | 'dark' | 'custom',
+  background?: string
+): Promise<BoardDocument> {
+  return invoke('apply_export_theme', { args: { doc, export_theme: exportTheme, background } })
+}
+
+// One slide of a presentation export; `region` is the board area to render
+export interface PresentationSlide {
+  title: string
+  region: Rect
+  item_ids: string[]
+  speaker_notes: string
+}
+
+// Slides from the board's stacks, or from `path` (note/shape IDs in order)
+export async function planPresentation(doc: BoardDocument, path?: string[]): Promise<PresentationSlide[]> {
+  return invoke('plan_presentation', { args: { doc, path } })
+}
+
+// Package one rendered PNG per planned slide as a PPTX or reveal.js deck
+export async function exportPresentation(
+  doc: BoardDocument,
+  format: 'pptx' | 'html',
+  images: Uint8Array[],
+  options: { path?: string[]; title?: string; outputPath?: string } = {},
+  onProgress?: ProgressHandler
+): Promise<string> {
+  const { outputPath, ...rest } = options
+  return invoke('export_presentation', {
+    args: { doc, format, images: images.map(image => Array.from(image)), ...rest, output_path: outputPath },
+    onProgress: progressChannel(onProgress)
+  })
+}
+
+// Camera path for an animated walkthrough; every frame region has the
+// output's aspect ratio
+export interface WalkthroughPlan {
+  width: number
+  height: number
+  fps: number
+  frames: { region: Rect; delay_ms: number }[]
+}
+
+export interface WalkthroughOptions {
+  note_ids: string[]
+  width?: number
+  height?: number
+  fps?: number
+  hold_ms?: number
+  travel_ms?: number
+  padding?: number
+}
+
+export async function planWalkthrough(doc: BoardDocument, options: WalkthroughOptions): Promise<WalkthroughPlan> {
+  return invoke('plan_walkthrough', { args: { doc, ...options } })
+}
+
+// GIFs are encoded by the backend from one PNG per planned frame; WebM is
+// recorded in the webview and only saved by the backend
+export async function exportWalkthrough(
+  doc: BoardDocument,
+  options: WalkthroughOptions,
+  output: { format: 'gif'; frames: Uint8Array[] } | { format: 'webm'; video: Uint8Array },
+  onProgress?: ProgressHandler,
+  outputPath?: string
+): Promise<string> {
+  const payload = output.format === 'gif'
+    ? { format: 'gif', frames: output.frames.map(frame => Array.from(frame)) }
+    : { format: 'webm', video: Array.from(output.video) }
+  return invoke('export_walkthrough', { args: { doc, ...options, ...payload, output_path: outputPath }, onProgress: progressChannel(onProgress) })
+}
+
+// `scrubMetadata` removes the producer, dates and other document information
+// the webview's PDF writer puts in
+export async function savePdfToFile(
+  filePath: string,
+  pdfData: Uint8Array,
+  markings?: ExportMarkings,
+  onProgress?: ProgressHandler,
+  scrubMetadata?: boolean,
+): Promise<void> {
+  return invoke('save_pdf_to_file', { filePath, pdfData, markings, scrubMetadata, onProgress: progressChannel(onProgress) })
+}
+
+
+// The end of the diagnostics log (oldest line first) and the folder holding
+// the log files, for attaching to bug reports
+export async function getRecentLogs(limit?: number): Promise<{ directory: string; lines: string[] }> {
+  return invoke('get_recent_logs', { limit })
+}
+
+export type HealthStatus = 'ok' | 'warning' | 'error'
+
+export interface HealthCheck {
+  name: 'config' | 'data' | 'recovery' | 'backup' | 'documents' | 'disk_space' | 'temp_files' | 'settings'
+  status: HealthStatus
+  message: string
+  path: string | null
+}
+
+// Whether the folders the app writes to are usable; run on first launch or
+// after a save fails. `status` is the worst of the checks.
+export async function runHealthCheck(): Promise<{ status: HealthStatus; checks: HealthCheck[]; checked_at: string }> {
+  return invoke('run_health_check')
+}
+
+export async function focusDocumentWindow(label: string): Promise<void> {
+  return invoke('focus_document_window', { label })
+}
+
+// Opens the board in a window of its own and returns that window's label; a
+// board already open somewhere brings that window forward instead
+export async function newWindowWithDocument(path: string): Promise<string> {
+  return invoke('new_window_with_document', { path })
+}
+
+// `<prefix>_<ULID>` IDs, sortable by creation time; prefix defaults to "id"
+export async function generateIds(count: number, prefix?: string): Promise<string[]> {
+  return invoke('generate_ids', { count, prefix })
+}
@@ -1,5 +1,6 @@
-import type { BoardDocument, Note, Connection, Stack } from '../model/types'
+import type { BoardDocument, Note, Connection, Stack, Rect } from '../model/types'
 import { jsPDF } from 'jspdf'
+import type { PdfBookmark, PosterPlan } from '../bridge/tauri'
 
 export interface ExportOptions {
   format: 'png' | 'pdf' | 'txt' | 'rtf' | 'opml'
@@ -11,6 +12,8 @@ export interface ExportOptions {
   pageSize?: 'a3' | 'a4' | 'a5' | 'letter' | 'legal'
   orientation?: 'auto' | 'portrait' | 'landscape'
   quality?: 'low' | 'medium' | 'high'
+  region?: Rect // board-space area to render instead of the content bounds + margin
+  bookmarks?: PdfBookmark[] // PDF outline entries, from the backend
 }
 
 export async function exportToPNG(
@@ -23,24 +26,32 @@ export async function exportToPNG(
   
   // Calculate content bounds
   const bounds = calculateContentBounds(document.notes)
+  const region = options.region || {
+    x: bounds.minX - margin,
+    y: bounds.minY - margin,
+    w: bounds.width + margin * 2,
+    h: bounds.height + margin * 2,
+  }
   
   // Create offscreen canvas
   const canvas = document.createElement('canvas')
   const ctx = canvas.getContext('2d')!
   
   // Set canvas size
-  const canvasWidth = (bounds.width + margin * 2) * scale
-  const canvasHeight = (bounds.height + margin * 2) * scale
-  canvas.width = canvasWidth
-  canvas.height = canvasHeight
+  canvas.width = Math.ceil(region.w * scale)
+  canvas.height = Math.ceil(region.h * scale)
   
   // Set up rendering context
   ctx.scale(scale, scale)
-  ctx.translate(margin - bounds.minX, margin - bounds.minY)
+  ctx.translate(-region.x, -region.y)
   
   // Clear background
   ctx.fillStyle = background
-  ctx.fillRect(bounds.minX - margin, bounds.minY - margin, bounds.width + margin * 2, bounds.height + margin * 2)
+  ctx.fillRect(region.x, region.y, region.w, region.h)
+  const texture = await loadTexture(document)
+  if (texture) {
+    renderTexture(ctx, texture, region)
+  }
   
   // Render connections first (behind notes)
   for (const connection of document.connections) {
@@ -444,6 +455,12 @@ export async function exportToPDF(
   const transformY = (y: number) => offsetY + (y + margin - bounds.minY) * finalScale
   const transformScale = (length: number) => length * finalScale
 
+  const texture = await loadTexture(document)
+  if (texture) {
+    const area = { x: bounds.minX - margin, y: bounds.minY - margin, w: bounds.width + margin * 2, h: bounds.height + margin * 2 }
+    renderTextureVector(pdf, texture, area, transformX, transformY, transformScale)
+  }
+
   // Render connections first (behind notes)
   for (const connection of document.connections) {
     renderConnectionVector(pdf, connection, document.notes, transformX, transformY, transformScale)
@@ -459,11 +476,133 @@ export async function exportToPDF(
   // Restore graphics state
   pdf.restoreGraphicsState()
 
+  // Outline (bookmarks panel); the whole board is on one page, so every
+  // entry targets page 1
+  const addBookmarks = (entries: PdfBookmark[], parent: unknown) => {
+    for (const entry of entries) {
+      const item = (pdf as any).outline.add(parent, entry.title || 'Untitled', { pageNumber: 1 })
+      addBookmarks(entry.children, item)
+    }
+  }
+  addBookmarks(options.bookmarks || [], null)
+
   // Convert PDF to blob
   const pdfBlob = pdf.output('blob')
   return pdfBlob
 }
 
+// Poster mode: one page per tile of the backend's plan, printed at the plan's
+// scale with crop marks, followed by an index page showing how tiles assemble
+export async function exportToPosterPDF(
+  document: BoardDocument,
+  plan: PosterPlan,
+  options: ExportOptions = { format: 'pdf' }
+): Promise<Blob> {
+  const background = options.background || '#202124'
+  const pdf = new jsPDF({
+    orientation: plan.orientation,
+    unit: 'mm',
+    format: [plan.page_width_mm, plan.page_height_mm]
+  })
+
+  const margin = plan.margin_mm
+  const printableWidth = plan.page_width_mm - margin * 2
+  const printableHeight = plan.page_height_mm - margin * 2
+  const markLength = Math.min(6, margin * 0.8)
+  const texture = await loadTexture(document)
+
+  plan.tiles.forEach((tile, index) => {
+    if (index > 0) pdf.addPage()
+
+    const transformX = (x: number) => margin + (x - tile.region.x) * plan.mm_per_unit
+    const transformY = (y: number) => margin + (y - tile.region.y) * plan.mm_per_unit
+    const transformScale = (length: number) => length * plan.mm_per_unit
+
+    pdf.saveGraphicsState()
+    pdf.rect(margin, margin, printableWidth, printableHeight)
+    pdf.clip()
+    if (background && background !== 'transparent') {
+      pdf.setFillColor(background)
+      pdf.rect(margin, margin, printableWidth, printableHeight, 'F')
+    }
+    if (texture) {
+      renderTextureVector(pdf, texture, tile.region, transformX, transformY, transformScale)
+    }
+    for (const connection of document.connections) {
+      renderConnectionVector(pdf, connection, document.notes, transformX, transformY, transformScale)
+    }
+    for (const note of document.notes) {
+      if (!note.faded || options.includeFaded !== false) {
+        renderNoteVector(pdf, note, transformX, transformY, transformScale)
+      }
+    }
+    pdf.restoreGraphicsState()
+
+    // Crop marks in the margin at each corner of the printed area
+    pdf.setDrawColor(0, 0, 0)
+    pdf.setLineWidth(0.1)
+    pdf.setLineDashPattern([], 0)
+    const right = margin + printableWidth
+    const bottom = margin + printableHeight
+    for (const [x, y, dx, dy] of [[margin, margin, -1, -1], [right, margin, 1, -1], [margin, bottom, -1, 1], [right, bottom, 1, 1]]) {
+      pdf.line(x + dx, y, x + dx * markLength, y)
+      pdf.line(x, y + dy, x, y + dy * markLength)
+    }
+
+    // Overlap guides where the next tile to the right / below repeats this edge
+    pdf.setLineDashPattern([1, 1], 0)
+    if (tile.column < plan.columns - 1 && plan.overlap_mm > 0) {
+      pdf.line(right - plan.overlap_mm, margin, right - plan.overlap_mm, bottom)
+    }
+    if (tile.row < plan.rows - 1 && plan.overlap_mm > 0) {
+      pdf.line(margin, bottom - plan.overlap_mm, right, bottom - plan.overlap_mm)
+    }
+    pdf.setLineDashPattern([], 0)
+
+    pdf.setTextColor(0, 0, 0)
+    pdf.setFontSize(8)
+    pdf.text(`${tile.label}  (${index + 1}/${plan.tiles.length})`, margin, margin - 2)
+  })
+
+  // Index page: the whole board scaled to fit, with the tile grid on top
+  pdf.addPage()
+  const indexScale = Math.min(
+    printableWidth / plan.content.w,
+    (printableHeight - 10) / plan.content.h
+  )
+  const indexX = (x: number) => margin + (x - plan.content.x) * indexScale
+  const indexY = (y: number) => margin + 10 + (y - plan.content.y) * indexScale
+  const indexLength = (length: number) => length * indexScale
+
+  pdf.setTextColor(0, 0, 0)
+  pdf.setFontSize(12)
+  pdf.text(`Assembly map: ${plan.rows} × ${plan.columns} pages`, margin, margin + 4)
+  if (background && background !== 'transparent') {
+    pdf.setFillColor(background)
+    pdf.rect(indexX(plan.content.x), indexY(plan.content.y), indexLength(plan.content.w), indexLength(plan.content.h), 'F')
+  }
+  for (const connection of document.connections) {
+    renderConnectionVector(pdf, connection, document.notes, indexX, indexY, indexLength)
+  }
+  for (const note of document.notes) {
+    if (!note.faded || options.includeFaded !== false) {
+      renderNoteVector(pdf, note, indexX, indexY, indexLength)
+    }
+  }
+  pdf.setDrawColor(220, 38, 38)
+  pdf.setTextColor(220, 38, 38)
+  pdf.setLineWidth(0.3)
+  pdf.setFontSize(10)
+  for (const tile of plan.tiles) {
+    const x = indexX(tile.region.x)
+    const y = indexY(tile.region.y)
+    pdf.rect(x, y, indexLength(tile.region.w), indexLength(tile.region.h))
+    pdf.text(tile.label, x + 2, y + 5)
+  }
+
+  return pdf.output('blob')
+}
+
 interface ContentBounds {
   minX: number
   minY: number
@@ -473,6 +612,91 @@ interface ContentBounds {
   height: number
 }
 
+// Background texture tiles above this count are left out of PDFs
+const MAX_PDF_TEXTURE_TILES = 2000
+
+interface Texture {
+  image: HTMLImageElement
+  dataUrl: string
+  format: string
+  // Size of one tile on the board
+  tileWidth: number
+  tileHeight: number
+  opacity: number
+}
+
+// The document's background texture, loaded; null when it has none
+async function loadTexture(document: BoardDocument): Promise<Texture | null> {
+  const background = document.documentStyle?.background
+  const image = document.images?.find(i => i.id === background?.textureId)
+  if (!background || !image?.dataBase64) return null
+
+  const dataUrl = `data:${image.mime};base64,${image.dataBase64}`
+  const element = new Image()
+  element.src = dataUrl
+  try {
+    await element.decode()
+  } catch {
+    return null
+  }
+  const scale = background.textureScale && background.textureScale > 0 ? background.textureScale : 1
+  return {
+    image: element,
+    dataUrl,
+    format: image.mime.replace('image/', '').toUpperCase(),
+    tileWidth: element.naturalWidth * scale,
+    tileHeight: element.naturalHeight * scale,
+    opacity: Math.min(Math.max(background.textureOpacity ?? 1, 0), 1),
+  }
+}
+
+// Tiles the texture from the board origin across `area`
+function renderTexture(ctx: CanvasRenderingContext2D, texture: Texture, area: Rect) {
+  const pattern = ctx.createPattern(texture.image, 'repeat')
+  if (!pattern) return
+  pattern.setTransform(new DOMMatrix().scale(
+    texture.tileWidth / texture.image.naturalWidth,
+    texture.tileHeight / texture.image.naturalHeight
+  ))
+  ctx.save()
+  ctx.globalAlpha = texture.opacity
+  ctx.fillStyle = pattern
+  ctx.fillRect(area.x, area.y, area.w, area.h)
+  ctx.restore()
+}
+
+function renderTextureVector(
+  pdf: jsPDF,
+  texture: Texture,
+  area: Rect,
+  transformX: (x: number) => number,
+  transformY: (y: number) => number,
+  transformScale: (length: number) => number
+) {
+  const firstColumn = Math.floor(area.x / texture.tileWidth)
+  const firstRow = Math.floor(area.y / texture.tileHeight)
+  const columns = Math.ceil((area.x + area.w) / texture.tileWidth) - firstColumn
+  const rows = Math.ceil((area.y + area.h) / texture.tileHeight) - firstRow
+  if (columns * rows > MAX_PDF_TEXTURE_TILES) return
+
+  pdf.saveGraphicsState()
+  pdf.setGState(new (pdf as any).GState({ opacity: texture.opacity }))
+  try {
+    for (let row = firstRow; row < firstRow + rows; row++) {
+      for (let column = firstColumn; column < firstColumn + columns; column++) {
+        const x = column * texture.tileWidth
+        const y = row * texture.tileHeight
+        // The alias makes every tile reuse one embedded image
+        pdf.addImage(texture.dataUrl, texture.format, transformX(x), transformY(y),
+          transformScale(texture.tileWidth), transformScale(texture.tileHeight), 'background-texture')
+      }
+    }
+  } catch (error) {
+    console.warn('Background texture left out of the PDF:', error)
+  }
+  pdf.restoreGraphicsState()
+}
+
 function calculateContentBounds(notes: Note[]): ContentBounds {
   if (notes.length === 0) {
     return { minX: 0, minY: 0, maxX: 200, maxY: 100, width: 200, height: 100 }
@@ -638,6 +862,35 @@ function wrapText(ctx: CanvasRenderingContext2D, text: string, x: number, y: num
   }
 }
 
+// Play rendered frames onto a fixed-size canvas and record it as WebM; each
+// frame stays on screen for its delay
+export async function recordWebM(
+  frames: { image: Blob; delayMs: number }[],
+  width: number,
+  height: number,
+  fps: number
+): Promise<Blob> {
+  const canvas = document.createElement('canvas')
+  canvas.width = width
+  canvas.height = height
+  const ctx = canvas.getContext('2d')!
+  const recorder = new MediaRecorder(canvas.captureStream(fps), { mimeType: 'video/webm' })
+  const chunks: Blob[] = []
+  recorder.ondataavailable = (event) => chunks.push(event.data)
+  const stopped = new Promise<void>(resolve => { recorder.onstop = () => resolve() })
+
+  recorder.start()
+  for (const frame of frames) {
+    const bitmap = await createImageBitmap(frame.image)
+    ctx.drawImage(bitmap, 0, 0, width, height)
+    bitmap.close()
+    await new Promise(resolve => setTimeout(resolve, frame.delayMs))
+  }
+  recorder.stop()
+  await stopped
+  return new Blob(chunks, { type: 'video/webm' })
+}
+
 export async function downloadFile(blob: Blob, filename: string) {
   const url = URL.createObjectURL(blob)
   const a = document.createElement('a')
@@ -74,18 +74,12 @@ export function useAutosave(
 
     try {
       console.log('Autosaving document...')
-      const result: AutosaveInfo = await autosaveDocument(doc, currentFilePathRef.current!)
+      await autosaveDocument(doc, currentFilePathRef.current!)
       lastAutosaveDocRef.current = currentDocJson
 
-      console.log('Autosave completed:', result)
-
-      // Dispatch event for UI to show autosave indicator
-      window.dispatchEvent(new CustomEvent('autosave-completed', {
-        detail: {
-          timestamp: result.timestamp,
-          recoveryPath: result.recovery_path
-        }
-      }))
+      // The backend only queued the snapshot; the indicator shows it pending
+      // until the backend says it was written
+      window.dispatchEvent(new CustomEvent('autosave-queued'))
 
       return true
     } catch (error) {
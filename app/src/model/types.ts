@@ -6,6 +6,7 @@ export interface Rect extends Point, Size {}
 
 export interface NoteStyle {
   id: ID
+  name?: string
   textStyle: {
     font: string
     size: number
@@ -23,10 +24,18 @@ export interface NoteStyle {
 }
 
 export interface DocumentStyle {
-  background?: { color?: string; textureId?: ID }
+  // textureScale: board pixels per texture pixel; textureOpacity: 0..1
+  background?: { color?: string; textureId?: ID; textureScale?: number; textureOpacity?: number }
   defaultNoteStyleId?: ID
   defaultShapeStyleId?: ID
   grid?: { visible: boolean; snap: boolean; size: number }
+  labels?: LabelDef[]
+}
+
+export interface LabelDef {
+  id: ID
+  name: string
+  color?: string
 }
 
 export interface EmbeddedImage {
@@ -51,6 +60,7 @@ export interface ConnectionStyle {
   arrows?: 'none' | 'src' | 'dst' | 'both'
   color?: string
   width?: number
+  curveType?: 'straight' | 'polyline' | 'smooth' | 'cubic'
 }
 
 export interface Connection {
@@ -60,15 +70,49 @@ export interface Connection {
   style?: ConnectionStyle
   label?: string
   bendPoints?: Point[]
+  controlPoints?: Point[]
+  relationType?: ID
+}
+
+export interface RelationType {
+  id: ID
+  name: string
+  style?: ConnectionStyle
+  directed?: boolean
 }
 
 export interface BackgroundShape {
   id: ID
+  kind?: 'rect' | 'ellipse' | 'line' | 'polygon'
   frame: Rect
+  points?: Point[] // relative to frame origin
   radius?: number
   magnetic?: boolean
   styleId?: ID
   label?: string
+  zIndex?: number
+  locked?: boolean
+  layerId?: ID
+}
+
+// Freehand ink stroke as stored in board.json: `points` holds x0, y0 and then
+// dx, dy per following point (1/100 px precision); `pressure` (0..1, one per
+// point) is omitted when every point has full pressure
+export interface Stroke {
+  id: ID
+  color?: string
+  width: number
+  points: number[]
+  pressure?: number[]
+  layerId?: ID
+}
+
+// Named layer; entities without a layerId are on the always-visible base layer
+export interface Layer {
+  id: ID
+  name: string
+  visible: boolean
+  locked: boolean
 }
 
 export interface Stack {
@@ -80,9 +124,42 @@ export interface Stack {
   alignedWidth?: number
 }
 
+export interface ChecklistItem {
+  text: string
+  done: boolean
+  order: number
+}
+
+// Inline formatting over [start, end) of the note text, in UTF-16 code units
+export interface TextSpan {
+  start: number
+  end: number
+  bold?: boolean
+  italic?: boolean
+  underline?: boolean
+  strike?: boolean
+  color?: string
+  link?: string
+}
+
+export interface NoteTable {
+  columns: string[]
+  rows: string[][]
+}
+
+export interface Group {
+  id: ID
+  noteIds: ID[]
+  shapeIds: ID[]
+  label?: string
+  collapsed?: boolean
+}
+
 export interface Note {
   id: ID
   text: string
+  contentFormat?: 'plain' | 'markdown'
+  spans?: TextSpan[]
   richAttrs?: Record<string, unknown>
   frame: Rect
   styleId?: ID
@@ -91,6 +168,34 @@ export interface Note {
   links?: string[]
   images?: ID[]
   connections?: ID[]
+  zIndex?: number
+  locked?: boolean
+  layerId?: ID
+  checklist?: ChecklistItem[]
+  labels?: ID[]
+  // Free-form keywords, compared case-insensitively without a leading '#'
+  tags?: string[]
+  table?: NoteTable
+  icon?: string
+  // Free-form key/value metadata, like Markdown frontmatter
+  properties?: Record<string, unknown>
+  // Flagged as a key idea; listed under Highlights in exports
+  starred?: boolean
+  // ISO timestamps, stamped by the command stack
+  createdAt?: string
+  updatedAt?: string
+  // Content encrypted at rest; while locked, `text` is empty and the
+  // content is in `sealedText`
+  confidential?: boolean
+  sealedText?: string
+}
+
+// Reusable note blueprint; frames are relative to the insertion point and
+// text may contain {{placeholders}}
+export interface NoteTemplate {
+  id: ID
+  name: string
+  notes: Note[]
 }
 
 export interface BoardDocument {
@@ -103,7 +208,41 @@ export interface BoardDocument {
   shapeStyles: ShapeStyle[]
   documentStyle?: DocumentStyle
   images?: EmbeddedImage[]
+  groups?: Group[]
+  relationTypes?: RelationType[]
+  strokes?: Stroke[]
+  layers?: Layer[]
+  templates?: NoteTemplate[]
+  properties?: Record<string, unknown>
+  // Deleted notes and connections, until restored or emptied
+  trash?: Trash
+  // Passphrase salt and verifier for confidential notes
+  noteEncryption?: NoteEncryption
+  info?: DocumentInfo
+}
+
+export interface NoteEncryption {
+  keyId: string
+  salt: string
+  verifier: string
+}
+
+// Stored unencrypted in the file's metadata too, so keep secrets out of it
+export interface DocumentInfo {
+  title?: string
+  owner?: string
+  passwordHint?: string
+  // Stamped by the backend on save
+  author?: string
+  createdAt?: string
+  updatedAt?: string
+  appVersion?: string
+}
+
+export interface Trash {
+  notes: { note: Note; deletedAt: string }[]
+  connections: { connection: Connection; deletedAt: string }[]
 }
 
-export const currentSchemaVersion = 1
+export const currentSchemaVersion = 2
 
@@ -1,4 +1,4 @@
-import type { BoardDocument, Note, Connection, BackgroundShape, Stack } from '../model/types'
+import type { BoardDocument, Note, Connection, BackgroundShape, Stack, EmbeddedImage } from '../model/types'
 
 // Base command interface
 export interface Command {
@@ -9,6 +9,41 @@ export interface Command {
   coalesce?(command: Command): Command
 }
 
+// Deleted notes and connections go to the document's trash; undoing the
+// delete takes them out again
+function moveToTrash(doc: BoardDocument, notes: Note[], connections: Connection[]): BoardDocument['trash'] {
+  const deletedAt = new Date().toISOString()
+  const trash = doc.trash ?? { notes: [], connections: [] }
+  return {
+    notes: [...trash.notes, ...notes.map(note => ({ note, deletedAt }))],
+    connections: [...trash.connections, ...connections.map(connection => ({ connection, deletedAt }))]
+  }
+}
+
+function takeFromTrash(doc: BoardDocument, noteIds: Set<string>, connectionIds: Set<string>): BoardDocument['trash'] {
+  if (!doc.trash) return undefined
+  const notes = doc.trash.notes.filter(t => !noteIds.has(t.note.id))
+  const connections = doc.trash.connections.filter(t => !connectionIds.has(t.connection.id))
+  return notes.length || connections.length ? { notes, connections } : undefined
+}
+
+// Notes a command created or changed get a fresh `updatedAt` (and new ones a
+// `createdAt`); the backend's activity review and splitting rely on them.
+// Commands replace the notes they touch, so untouched notes keep their identity.
+function stampActivity(before: BoardDocument, after: BoardDocument): BoardDocument {
+  if (after.notes === before.notes) return after
+  const previous = new Map(before.notes.map(note => [note.id, note]))
+  const now = new Date().toISOString()
+  let changed = false
+  const notes = after.notes.map(note => {
+    const old = previous.get(note.id)
+    if (old === note) return note
+    changed = true
+    return { ...note, createdAt: note.createdAt ?? old?.createdAt ?? now, updatedAt: now }
+  })
+  return changed ? { ...after, notes } : after
+}
+
 // Command stack for undo/redo functionality
 export class CommandStack {
   private undoStack: Command[] = []
@@ -22,11 +57,11 @@ export class CommandStack {
     if (lastCommand && command.canCoalesceWith?.(lastCommand)) {
       const coalesced = command.coalesce!(lastCommand)
       this.undoStack[this.undoStack.length - 1] = coalesced
-      return coalesced.execute(currentDoc)
+      return stampActivity(currentDoc, coalesced.execute(currentDoc))
     }
 
     // Execute the command
-    const newDoc = command.execute(currentDoc)
+    const newDoc = stampActivity(currentDoc, command.execute(currentDoc))
 
     // Add to undo stack and clear redo stack
     this.undoStack.push(command)
@@ -45,7 +80,7 @@ export class CommandStack {
     const command = this.undoStack.pop()
     if (!command) return null
 
-    const undoneDoc = command.undo(currentDoc)
+    const undoneDoc = stampActivity(currentDoc, command.undo(currentDoc))
     this.redoStack.push(command)
     return undoneDoc
   }
@@ -55,7 +90,7 @@ export class CommandStack {
     const command = this.redoStack.pop()
     if (!command) return null
 
-    const redoneDoc = command.execute(currentDoc)
+    const redoneDoc = stampActivity(currentDoc, command.execute(currentDoc))
     this.undoStack.push(command)
     return redoneDoc
   }
@@ -91,6 +126,28 @@ export class CommandStack {
 
 // Concrete command implementations
 
+// An edit the backend made to a copy of the board; undo puts the board back
+// as it was when the edit was asked for
+export class ApplyDocumentCommand implements Command {
+  readonly description: string
+  private readonly before: BoardDocument
+  private readonly after: BoardDocument
+
+  constructor(description: string, before: BoardDocument, after: BoardDocument) {
+    this.description = description
+    this.before = before
+    this.after = after
+  }
+
+  execute(): BoardDocument {
+    return this.after
+  }
+
+  undo(): BoardDocument {
+    return this.before
+  }
+}
+
 export class CreateNotesCommand implements Command {
   description = 'Create notes'
   private readonly newNotes: Note[]
@@ -115,6 +172,45 @@ export class CreateNotesCommand implements Command {
   }
 }
 
+// Notes showing images new to the board, e.g. dropped from the file
+// manager; images the board already has (same content, same id) are shared
+export class AddImageNotesCommand implements Command {
+  description = 'Add images'
+  private readonly images: EmbeddedImage[]
+  private readonly newNotes: Note[]
+  private addedImageIds: Set<string> = new Set()
+
+  constructor(images: EmbeddedImage[], notes: Note[]) {
+    this.images = images
+    this.newNotes = notes
+  }
+
+  execute(doc: BoardDocument): BoardDocument {
+    const known = new Set((doc.images ?? []).map(i => i.id))
+    const added: EmbeddedImage[] = []
+    for (const image of this.images) {
+      if (known.has(image.id)) continue
+      known.add(image.id)
+      added.push(image)
+    }
+    this.addedImageIds = new Set(added.map(i => i.id))
+    return {
+      ...doc,
+      images: [...(doc.images ?? []), ...added],
+      notes: [...doc.notes, ...this.newNotes]
+    }
+  }
+
+  undo(doc: BoardDocument): BoardDocument {
+    const newIds = new Set(this.newNotes.map(n => n.id))
+    return {
+      ...doc,
+      images: (doc.images ?? []).filter(i => !this.addedImageIds.has(i.id)),
+      notes: doc.notes.filter(n => !newIds.has(n.id))
+    }
+  }
+}
+
 export class DeleteNotesCommand implements Command {
   description = 'Delete notes'
   private readonly deletedNotes: Note[]
@@ -132,7 +228,8 @@ export class DeleteNotesCommand implements Command {
       notes: doc.notes.filter(n => !deletedIds.has(n.id)),
       connections: doc.connections.filter(c =>
         !deletedIds.has(c.srcNoteId) && !deletedIds.has(c.dstNoteId)
-      )
+      ),
+      trash: moveToTrash(doc, this.deletedNotes, this.deletedConnections)
     }
   }
 
@@ -140,7 +237,12 @@ export class DeleteNotesCommand implements Command {
     return {
       ...doc,
       notes: [...doc.notes, ...this.deletedNotes],
-      connections: [...doc.connections, ...this.deletedConnections]
+      connections: [...doc.connections, ...this.deletedConnections],
+      trash: takeFromTrash(
+        doc,
+        new Set(this.deletedNotes.map(n => n.id)),
+        new Set(this.deletedConnections.map(c => c.id))
+      )
     }
   }
 }
@@ -333,14 +435,16 @@ export class DeleteConnectionCommand implements Command {
   execute(doc: BoardDocument): BoardDocument {
     return {
       ...doc,
-      connections: doc.connections.filter(c => c.id !== this.connection.id)
+      connections: doc.connections.filter(c => c.id !== this.connection.id),
+      trash: moveToTrash(doc, [], [this.connection])
     }
   }
 
   undo(doc: BoardDocument): BoardDocument {
     return {
       ...doc,
-      connections: [...doc.connections, this.connection]
+      connections: [...doc.connections, this.connection],
+      trash: takeFromTrash(doc, new Set(), new Set([this.connection.id]))
     }
   }
 }
@@ -2,6 +2,7 @@ import React from 'react'
 import { Canvas } from './canvas/Canvas'
 import { Inspector } from './Inspector'
 import { HelpOverlay } from './HelpOverlay'
+import { SettingsDialog } from './SettingsDialog'
 import { SearchDialog } from './SearchDialog'
 import { IncrementalSearch } from './IncrementalSearch'
 import { RecentFiles } from './RecentFiles'
@@ -10,13 +11,13 @@ import { AutosaveIndicator } from './AutosaveIndicator'
 import { ModernToolbar } from './components/ModernToolbar'
 import { MagicalCanvas } from './components/MagicalCanvas'
 import { ModernInspector } from './components/ModernInspector'
-import type { BoardDocument, BackgroundShape } from '../model/types'
+import type { BoardDocument, BackgroundShape, Note, NoteTemplate, Point } from '../model/types'
 import { makeEmptyDoc } from '../state'
 import { useCommandStack } from '../hooks/useCommandStack'
 import { useAutosave } from '../hooks/useAutosave'
-import { openDocument, openSpecificDocument, saveDocument, checkRecoveryFiles, exportDocumentAsText, exportDocumentAsPNG, savePngToFile, exportDocumentAsPDF, savePdfToFile } from '../bridge/tauri'
-import { exportToPNG, exportToTXT, exportToPDF, exportToRTF, exportToOPML, downloadFile, downloadText } from '../export/canvasExport'
-import { UpdateNotesCommand, UpdateConnectionsCommand, CreateShapesCommand, UpdateShapesCommand, SearchCommand, AlignNotesCommand, DistributeNotesCommand, ResizeNotesCommand } from '../state/commands'
+import { openDocument, openSpecificDocument, onOpenDocument, onFilesDropped, onQuickCaptureNote, onFocusNote, onDocumentChangedExternally, onDocumentMigrated, type MigrationReport, takePendingOpenDocuments, newWindowWithDocument, type OpenDocumentEvent, registerEmbeddedFonts, importOPML, saveDocument, checkRecoveryFiles, exportDocumentAsText, exportDocumentAsPNG, savePngToFile, shareExport, exportDocumentAsPDF, savePdfToFile, getPdfBookmarks, planPosterExport, applyExportTheme, planPresentation, exportPresentation, planWalkthrough, exportWalkthrough, type ExportMarkings, type TextExportOptions, copyNotesToClipboard, copyPngToClipboard, readClipboard, importTextOutline, type OutlineNode, instantiateTemplate, getTemplatePlaceholders, duplicateNotes, bringToFront, sendToBack, createGroup, dissolveGroup } from '../bridge/tauri'
+import { exportToPNG, exportToTXT, exportToPDF, exportToPosterPDF, recordWebM, exportToRTF, exportToOPML, downloadFile, downloadText } from '../export/canvasExport'
+import { ApplyDocumentCommand, AddImageNotesCommand, CreateNotesCommand, UpdateNotesCommand, UpdateConnectionsCommand, CreateShapesCommand, UpdateShapesCommand, SearchCommand, AlignNotesCommand, DistributeNotesCommand, ResizeNotesCommand } from '../state/commands'
 import { SearchResult, findConnectedCluster } from '../utils/search'
 
 interface AutosaveInfo {
@@ -25,6 +26,45 @@ interface AutosaveInfo {
   timestamp: string
 }
 
+// Selecting part of a group selects all of it, so the group moves as a unit
+function withGroupMembers(doc: BoardDocument, ids: string[]): string[] {
+  const selected = new Set(ids)
+  for (const group of doc.groups ?? []) {
+    const members = [...group.noteIds, ...group.shapeIds]
+    if (members.some(id => selected.has(id))) members.forEach(id => selected.add(id))
+  }
+  return [...selected]
+}
+
+// Where pasted notes go: below the selected notes, else right of the board
+function pasteOrigin(doc: BoardDocument, selection: string[]): Point {
+  const selected = doc.notes.filter(n => selection.includes(n.id))
+  if (selected.length > 0) {
+    return {
+      x: Math.min(...selected.map(n => n.frame.x)),
+      y: Math.max(...selected.map(n => n.frame.y + n.frame.h)) + 20
+    }
+  }
+  if (doc.notes.length === 0) return { x: 100, y: 100 }
+  return {
+    x: Math.max(...doc.notes.map(n => n.frame.x + n.frame.w)) + 40,
+    y: Math.min(...doc.notes.map(n => n.frame.y))
+  }
+}
+
+// A pasted outline as indented text, for the backend's outline import
+function outlineText(items: OutlineNode[], depth = 0): string {
+  return items
+    .map(item => ['  '.repeat(depth) + item.text, outlineText(item.children, depth + 1)].filter(Boolean).join('\n'))
+    .join('\n')
+}
+
+function describeMigration(report: MigrationReport): string {
+  return report.newer
+    ? `This board was saved by a newer version (format ${report.from_version}) and is open read-only.`
+    : `This board was upgraded from format ${report.from_version} to ${report.to_version}; saving keeps the new format.`
+}
+
 export function App() {
   const initialDoc: BoardDocument = React.useMemo(() => ({
     ...makeEmptyDoc(),
@@ -59,6 +99,7 @@ export function App() {
 
   const [selection, setSelection] = React.useState<string[]>([])
   const [showHelp, setShowHelp] = React.useState(false)
+  const [showSettings, setShowSettings] = React.useState(false)
   const [showSearch, setShowSearch] = React.useState(false)
   const [incrementalSearchActive, setIncrementalSearchActive] = React.useState(false)
   const [highlightedSearchResult, setHighlightedSearchResult] = React.useState<SearchResult | null>(null)
@@ -67,9 +108,14 @@ export function App() {
   const [showRecoveryDialog, setShowRecoveryDialog] = React.useState(false)
   const [isDirty, setIsDirty] = React.useState(false)
   const [textOrdering, setTextOrdering] = React.useState<'spatial' | 'connections' | 'hierarchical'>('spatial')
+  const [scrubMetadata, setScrubMetadata] = React.useState(false)
   const [pdfPageSize, setPdfPageSize] = React.useState<'a3' | 'a4' | 'a5' | 'letter' | 'legal'>('a4')
   const [pdfOrientation, setPdfOrientation] = React.useState<'auto' | 'portrait' | 'landscape'>('auto')
+  const [pdfLayout, setPdfLayout] = React.useState<'fit' | 'poster'>('fit')
+  const [exportTheme, setExportTheme] = React.useState<'board' | 'light' | 'dark'>('board')
+  const [exportMarkings, setExportMarkings] = React.useState<'none' | 'page-numbers' | 'confidential' | 'draft'>('none')
   const [pngDPI, setPngDPI] = React.useState<1 | 2 | 3>(2)
+  const [migrationNotice, setMigrationNotice] = React.useState<string | null>(null)
 
   // Initialize autosave functionality
   const {
@@ -94,6 +140,151 @@ export function App() {
     checkRecovery()
   }, [checkForRecoveryFiles])
 
+  // Whether replacing the board now would throw away edits
+  const hasUnsavedEdits = React.useRef(false)
+  React.useEffect(() => {
+    hasUnsavedEdits.current = isDirty && canUndo
+  }, [isDirty, canUndo])
+
+  // Boards the OS hands us (Finder/Explorer, command line, fim:// links, a new
+  // window's board): the ones queued before we listened, then as they come.
+  // Boards dropped on the window open the same way; the first replaces this
+  // board, the rest get windows of their own. Dropped images are the canvas's.
+  // Quick-capture notes for this board are added like any edit; the backend
+  // only sends them once we have taken the queued boards, so listen first.
+  React.useEffect(() => {
+    let cancelled = false
+    let stopListening: (() => void)[] = []
+    let stopDropListening: (() => void) | undefined
+
+    const onOpened = async (event: OpenDocumentEvent) => {
+      if (!event.document) {
+        console.warn(`Failed to open ${event.path}:`, event.error)
+        return
+      }
+      // A board opened from another launch while this one has unsaved edits
+      // gets a window of its own
+      if (hasUnsavedEdits.current) {
+        await newWindowWithDocument(event.path).catch(e => console.warn(`Failed to open ${event.path} in a new window:`, e))
+        return
+      }
+      await registerEmbeddedFonts(event.path).catch(e => console.warn('Failed to load embedded fonts', e))
+      setDocument(event.document)
+      setCurrentFilePath(event.path)
+      setIsDirty(false)
+      if (event.focus_note_id) setSelection([event.focus_note_id])
+      setMigrationNotice(event.migration ? describeMigration(event.migration) : null)
+    }
+
+    Promise.all([
+      onOpenDocument(onOpened),
+      onQuickCaptureNote(({ note }) => executeCommand(new CreateNotesCommand([note])))
+    ])
+      .then(async stops => {
+        if (cancelled) return stops.forEach(stop => stop())
+        stopListening = stops
+        for (const event of await takePendingOpenDocuments()) await onOpened(event)
+      })
+      .catch(e => console.warn('Failed to receive opened documents', e))
+
+    onFilesDropped(async event => {
+      for (const { path, error } of event.errors) console.warn(`Cannot use dropped file ${path}:`, error)
+      const [first, ...rest] = event.documents
+      if (first) await onOpened(first)
+      for (const other of rest) {
+        if (!other.document) {
+          console.warn(`Failed to open ${other.path}:`, other.error)
+          continue
+        }
+        await newWindowWithDocument(other.path).catch(e => console.warn(`Failed to open ${other.path} in a new window:`, e))
+      }
+    })
+      .then(stop => {
+        if (cancelled) stop()
+        else stopDropListening = stop
+      })
+      .catch(e => console.warn('Failed to receive dropped files', e))
+
+    return () => {
+      cancelled = true
+      stopListening.forEach(stop => stop())
+      stopDropListening?.()
+    }
+  }, [setDocument, executeCommand])
+
+  // Say so when an opened board was upgraded, or is read-only because a newer
+  // version of the app wrote it
+  React.useEffect(() => {
+    let cancelled = false
+    let stopListening: (() => void) | undefined
+
+    onDocumentMigrated(({ report }) => setMigrationNotice(describeMigration(report)))
+      .then(stop => {
+        if (cancelled) stop()
+        else stopListening = stop
+      })
+      .catch(e => console.warn('Failed to receive board upgrades', e))
+
+    return () => {
+      cancelled = true
+      stopListening?.()
+    }
+  }, [])
+
+  // fim:// links to a note of the board already open here just select it
+  React.useEffect(() => {
+    let cancelled = false
+    let stopListening: (() => void) | undefined
+
+    onFocusNote(({ note_id }) => setSelection([note_id]))
+      .then(stop => {
+        if (cancelled) stop()
+        else stopListening = stop
+      })
+      .catch(e => console.warn('Failed to receive note links', e))
+
+    return () => {
+      cancelled = true
+      stopListening?.()
+    }
+  }, [])
+
+  // The board's file changed on disk (a sync client, another editor): reload
+  // it, asking first when that would throw away edits. Saving over a changed
+  // file is refused, so keeping the edits means saving a copy.
+  React.useEffect(() => {
+    if (!currentFilePath) return
+    let cancelled = false
+    let stopListening: (() => void) | undefined
+
+    onDocumentChangedExternally(async ({ path, exists }) => {
+      if (path !== currentFilePath) return
+      if (!exists) {
+        console.warn(`${path} was moved or deleted; save the board to keep it`)
+        setIsDirty(true)
+        return
+      }
+      if (hasUnsavedEdits.current && !window.confirm(`${path} changed on disk. Reload it and lose your unsaved changes?`)) return
+      try {
+        const reloaded = await openSpecificDocument(path)
+        setDocument(reloaded)
+        setIsDirty(false)
+      } catch (e) {
+        console.warn(`Failed to reload ${path}:`, e)
+      }
+    })
+      .then(stop => {
+        if (cancelled) stop()
+        else stopListening = stop
+      })
+      .catch(e => console.warn('Failed to watch the board for changes', e))
+
+    return () => {
+      cancelled = true
+      stopListening?.()
+    }
+  }, [currentFilePath, setDocument])
+
   // Clear temp state when document changes via commands
   React.useEffect(() => {
     setTempDoc(null)
@@ -101,8 +292,10 @@ export function App() {
   }, [doc])
 
   const onOpen = async () => {
+    setMigrationNotice(null)
     try {
       const opened = await openDocument()
+      await registerEmbeddedFonts().catch(e => console.warn('Failed to load embedded fonts', e))
       setDocument(opened)
       setCurrentFilePath(null) // Reset file path since we used "Open" dialog
       setIsDirty(false)
@@ -111,9 +304,22 @@ export function App() {
     }
   }
 
+  const onImportOPML = async () => {
+    try {
+      const imported = await importOPML()
+      setDocument(imported)
+      setCurrentFilePath(null)
+      setIsDirty(true)
+    } catch (e) {
+      console.warn('OPML import cancelled or failed', e)
+    }
+  }
+
   const onOpenRecentFile = async (filePath: string) => {
+    setMigrationNotice(null)
     try {
       const opened = await openSpecificDocument(filePath)
+      await registerEmbeddedFonts(filePath).catch(e => console.warn('Failed to load embedded fonts', e))
       setDocument(opened)
       setCurrentFilePath(filePath)
       setIsDirty(false)
@@ -132,20 +338,191 @@ export function App() {
     }
   }
 
+  // Run an edit the backend makes on a copy of the board and put the result on
+  // the undo stack
+  const applyBackendEdit = async (description: string, edit: (doc: BoardDocument) => Promise<BoardDocument>) => {
+    const before = doc
+    try {
+      executeCommand(new ApplyDocumentCommand(description, before, await edit(before)))
+    } catch (e) {
+      console.warn(`${description} failed:`, e)
+    }
+  }
+
+  const onCopy = async (format: 'rtf' | 'markdown') => {
+    const noteIds = selection.filter(id => doc.notes.some(n => n.id === id))
+    if (noteIds.length === 0) return
+    try {
+      await copyNotesToClipboard(doc, noteIds, format)
+    } catch (e) {
+      console.warn('Copy failed:', e)
+    }
+  }
+
+  const onCopyImage = async () => {
+    try {
+      const blob = await exportToPNG(doc, { format: 'png', scale: 2 })
+      await copyPngToClipboard(new Uint8Array(await blob.arrayBuffer()))
+    } catch (e) {
+      console.warn('Copy as image failed:', e)
+    }
+  }
+
+  // Insert what the clipboard holds: pictures as image notes, links and text
+  // as a note (as connected notes by indentation with `asOutline`), outlines
+  // as connected notes
+  const onPaste = async (asOutline = false) => {
+    const origin = pasteOrigin(doc, selection)
+    const id = () => `note_${Date.now()}_${Math.random().toString(36).substring(2, 9)}`
+    const insertOutline = async (text: string) => {
+      const pasted = await importTextOutline(text, { origin })
+      executeCommand(new ApplyDocumentCommand('Paste', doc, {
+        ...doc,
+        notes: [...doc.notes, ...pasted.notes],
+        connections: [...doc.connections, ...pasted.connections],
+        stacks: [...doc.stacks, ...pasted.stacks]
+      }))
+      setSelection(pasted.notes.map(n => n.id))
+    }
+    try {
+      const content = await readClipboard()
+      if (asOutline && content.kind === 'text') {
+        await insertOutline(content.text)
+        return
+      }
+      switch (content.kind) {
+        case 'image': {
+          const { image } = content
+          const scale = Math.min(1, 240 / Math.max(image.width, image.height, 1))
+          const note: Note = {
+            id: id(),
+            text: '',
+            frame: { ...origin, w: Math.max(1, image.width * scale), h: Math.max(1, image.height * scale) },
+            images: [image.id]
+          }
+          executeCommand(new AddImageNotesCommand([image], [note]))
+          setSelection([note.id])
+          break
+        }
+        case 'url':
+        case 'text': {
+          const note: Note = content.kind === 'url'
+            ? { id: id(), text: content.url, links: [content.url], frame: { ...origin, w: 200, h: 80 } }
+            : { id: id(), text: content.text, contentFormat: content.contentFormat, frame: { ...origin, w: 200, h: 80 } }
+          executeCommand(new CreateNotesCommand([note]))
+          setSelection([note.id])
+          break
+        }
+        case 'outline':
+          await insertOutline(outlineText(content.items))
+          break
+      }
+    } catch (e) {
+      console.warn('Paste failed:', e)
+    }
+  }
+
+  // Copy the selected notes in place, slightly offset, and select the copies
+  const onDuplicate = async (includeDescendants: boolean) => {
+    const noteIds = selection.filter(id => doc.notes.some(n => n.id === id))
+    if (noteIds.length === 0) return
+    try {
+      const copies = await duplicateNotes(doc, noteIds, includeDescendants)
+      executeCommand(new ApplyDocumentCommand('Duplicate', doc, {
+        ...doc,
+        notes: [...doc.notes, ...copies.notes],
+        connections: [...doc.connections, ...copies.connections],
+        stacks: [...doc.stacks, ...copies.stacks],
+        images: [...(doc.images ?? []), ...copies.images]
+      }))
+      setSelection(copies.notes.map(n => n.id))
+    } catch (e) {
+      console.warn('Duplicate failed:', e)
+    }
+  }
+
+  // Keep the selected notes as a note template of the board, with frames
+  // relative to their top-left corner
+  const onSaveNoteTemplate = () => {
+    const notes = doc.notes.filter(n => selection.includes(n.id))
+    if (notes.length === 0) return
+    const name = window.prompt('Template name')
+    if (!name) return
+    const left = Math.min(...notes.map(n => n.frame.x))
+    const top = Math.min(...notes.map(n => n.frame.y))
+    const template: NoteTemplate = {
+      id: `template_${Date.now()}_${Math.random().toString(36).substring(2, 9)}`,
+      name,
+      notes: notes.map(note => ({
+        ...note,
+        stackId: undefined,
+        connections: undefined,
+        frame: { ...note.frame, x: note.frame.x - left, y: note.frame.y - top }
+      }))
+    }
+    executeCommand(new ApplyDocumentCommand('Save note template', doc, { ...doc, templates: [...(doc.templates ?? []), template] }))
+  }
+
+  // Insert a note template, asking for its placeholder values
+  const onInsertNoteTemplate = async () => {
+    const templates = doc.templates ?? []
+    if (templates.length === 0) {
+      console.warn('This board has no note templates')
+      return
+    }
+    const name = templates.length === 1
+      ? templates[0].name
+      : window.prompt(`Insert which template? (${templates.map(t => t.name).join(', ')})`, templates[0].name)
+    const template = templates.find(t => t.name === name)
+    if (!template) return
+    try {
+      const values: Record<string, string> = {}
+      for (const placeholder of await getTemplatePlaceholders(doc, template.id)) {
+        if (placeholder === 'date') continue
+        const value = window.prompt(`Value for {{${placeholder}}}`)
+        if (value === null) return
+        values[placeholder] = value
+      }
+      const notes = await instantiateTemplate(doc, template.id, values, pasteOrigin(doc, selection))
+      executeCommand(new CreateNotesCommand(notes))
+      setSelection(notes.map(n => n.id))
+    } catch (e) {
+      console.warn('Insert template failed:', e)
+    }
+  }
+
+  // Document as it should look in PNG/PDF exports, with the export theme applied
+  const themedForExport = async () => {
+    if (exportTheme === 'board') return doc
+    return applyExportTheme(doc, exportTheme)
+  }
+
+  // Watermark and header/footer preset stamped onto PNG/PDF exports
+  const markingsForExport = (): ExportMarkings | undefined => {
+    if (exportMarkings === 'none') return undefined
+    const title = currentFilePath?.split(/[\\/]/).pop()?.replace(/\.fim$/i, '')
+    const footer = 'Page {page} of {pages} · {date}'
+    if (exportMarkings === 'page-numbers') return { title, footer }
+    const watermark_text = exportMarkings === 'confidential' ? 'CONFIDENTIAL' : 'DRAFT'
+    return { title, header: '{title}', footer, watermark_text }
+  }
+
   const onExportPNG = async () => {
     try {
-      // Get file path from native dialog
-      const filePath = await exportDocumentAsPNG(pngDPI)
+      // Get file path from native dialog, plus the region and scale to render
+      const { path: filePath, plan } = await exportDocumentAsPNG(doc, { scale: pngDPI })
 
       // Generate PNG data using existing export function
-      const blob = await exportToPNG(doc, { format: 'png', scale: pngDPI })
+      const exportDoc = await themedForExport()
+      const background = exportDoc.documentStyle?.background?.color
+      const blob = await exportToPNG(exportDoc, { format: 'png', scale: plan.scale, region: plan.region, background })
 
       // Convert blob to Uint8Array
       const arrayBuffer = await blob.arrayBuffer()
       const pngData = new Uint8Array(arrayBuffer)
 
       // Save PNG data to file using Tauri
-      await savePngToFile(filePath, pngData)
+      await savePngToFile(filePath, pngData, markingsForExport())
 
       console.log('PNG exported:', filePath, `at ${pngDPI}x DPI`)
     } catch (e) {
@@ -153,9 +530,25 @@ export function App() {
     }
   }
 
+  // Hand a render of the board to the OS share sheet
+  const onShare = async () => {
+    try {
+      const exportDoc = await themedForExport()
+      const background = exportDoc.documentStyle?.background?.color
+      const blob = await exportToPNG(exportDoc, { format: 'png', scale: pngDPI, background })
+      const shared = await shareExport(doc, 'png', { data: new Uint8Array(await blob.arrayBuffer()) })
+      console.log(shared.presented ? 'Shared:' : 'No share sheet; revealed:', shared.path)
+    } catch (e) {
+      console.warn('Share failed', e)
+    }
+  }
+
+  // What the text exports leave out
+  const textExportOptions = (): TextExportOptions => ({ scrubMetadata })
+
   const onExportTXT = async (ordering = 'spatial') => {
     try {
-      const savedPath = await exportDocumentAsText(doc, 'txt', ordering)
+      const savedPath = await exportDocumentAsText(doc, 'txt', ordering, undefined, undefined, {}, undefined, undefined, textExportOptions())
       console.log('TXT exported:', savedPath)
     } catch (e) {
       console.warn('TXT export failed', e)
@@ -164,7 +557,7 @@ export function App() {
 
   const onExportRTF = async (ordering = 'spatial') => {
     try {
-      const savedPath = await exportDocumentAsText(doc, 'rtf', ordering)
+      const savedPath = await exportDocumentAsText(doc, 'rtf', ordering, undefined, undefined, {}, undefined, undefined, textExportOptions())
       console.log('RTF exported:', savedPath)
     } catch (e) {
       console.warn('RTF export failed', e)
@@ -173,13 +566,63 @@ export function App() {
 
   const onExportOPML = async (ordering = 'spatial') => {
     try {
-      const savedPath = await exportDocumentAsText(doc, 'opml', ordering)
+      const savedPath = await exportDocumentAsText(doc, 'opml', ordering, undefined, undefined, {}, undefined, undefined, textExportOptions())
       console.log('OPML exported:', savedPath)
     } catch (e) {
       console.warn('OPML export failed', e)
     }
   }
 
+  const onExportPresentation = async (format: 'pptx' | 'html') => {
+    try {
+      // One slide per stack; each slide image is the stack's region of the board
+      const slides = await planPresentation(doc)
+      const exportDoc = await themedForExport()
+      const background = exportDoc.documentStyle?.background?.color
+      const images: Uint8Array[] = []
+      for (const slide of slides) {
+        const blob = await exportToPNG(exportDoc, { format: 'png', scale: 2, region: slide.region, background })
+        images.push(new Uint8Array(await blob.arrayBuffer()))
+      }
+      const title = currentFilePath?.split(/[\\/]/).pop()?.replace(/\.fim$/i, '')
+      const savedPath = await exportPresentation(doc, format, images, { title })
+      console.log('Presentation exported:', savedPath, `(${slides.length} slides)`)
+    } catch (e) {
+      console.warn('Presentation export failed', e)
+    }
+  }
+
+  const onExportWalkthrough = async (format: 'gif' | 'webm') => {
+    try {
+      // Visit the selected notes in the order they were selected
+      const noteIds = selection.filter(id => doc.notes.some(n => n.id === id))
+      const options = { note_ids: noteIds }
+      const plan = await planWalkthrough(doc, options)
+      const exportDoc = await themedForExport()
+      const background = exportDoc.documentStyle?.background?.color
+      const images: Blob[] = []
+      for (const frame of plan.frames) {
+        images.push(await exportToPNG(exportDoc, { format: 'png', scale: plan.width / frame.region.w, region: frame.region, background }))
+      }
+
+      const savedPath = format === 'gif'
+        ? await exportWalkthrough(doc, options, {
+            format: 'gif',
+            frames: await Promise.all(images.map(async image => new Uint8Array(await image.arrayBuffer())))
+          })
+        : await exportWalkthrough(doc, options, {
+            format: 'webm',
+            video: new Uint8Array(await (await recordWebM(
+              images.map((image, i) => ({ image, delayMs: plan.frames[i].delay_ms })),
+              plan.width, plan.height, plan.fps
+            )).arrayBuffer())
+          })
+      console.log('Walkthrough exported:', savedPath)
+    } catch (e) {
+      console.warn('Walkthrough export failed', e)
+    }
+  }
+
   const onCreateShape = () => {
     const newShape = {
       id: `shape_${Date.now()}_${Math.random().toString(36).substring(2, 9)}`,
@@ -194,17 +637,29 @@ export function App() {
   const onExportPDF = async () => {
     try {
       const filePath = await exportDocumentAsPDF(pdfPageSize, pdfOrientation)
-      const blob = await exportToPDF(doc, {
-        format: 'pdf',
-        pageSize: pdfPageSize,
-        orientation: pdfOrientation,
-        quality: 'high',
-        includeFaded: true,
-        margin: 50
-      })
+      const exportDoc = await themedForExport()
+      const background = exportDoc.documentStyle?.background?.color
+      let blob: Blob
+      if (pdfLayout === 'poster') {
+        // True-scale tiles across as many pages as the board needs
+        const plan = await planPosterExport(doc, { page_size: pdfPageSize, orientation: pdfOrientation })
+        blob = await exportToPosterPDF(exportDoc, plan, { format: 'pdf', includeFaded: true, background })
+      } else {
+        const bookmarks = await getPdfBookmarks(doc)
+        blob = await exportToPDF(exportDoc, {
+          format: 'pdf',
+          background,
+          pageSize: pdfPageSize,
+          orientation: pdfOrientation,
+          quality: 'high',
+          includeFaded: true,
+          margin: 50,
+          bookmarks
+        })
+      }
       const arrayBuffer = await blob.arrayBuffer()
       const uint8Array = new Uint8Array(arrayBuffer)
-      await savePdfToFile(filePath, uint8Array)
+      await savePdfToFile(filePath, uint8Array, markingsForExport(), undefined, scrubMetadata)
       console.log('PDF exported:', filePath, `(${pdfPageSize} ${pdfOrientation})`)
     } catch (e) {
       console.warn('PDF export failed', e)
@@ -315,6 +770,65 @@ export function App() {
         setShowHelp(true)
       }
 
+      // Clipboard: Ctrl/Cmd + C copies the selected notes (Alt: as Markdown),
+      // Ctrl/Cmd + Shift + C copies the board as an image, Ctrl/Cmd + V pastes
+      // (Alt: text as an indented outline)
+      if ((e.ctrlKey || e.metaKey) && e.code === 'KeyC') {
+        e.preventDefault()
+        if (e.shiftKey) onCopyImage()
+        else onCopy(e.altKey ? 'markdown' : 'rtf')
+      }
+      if ((e.ctrlKey || e.metaKey) && !e.shiftKey && e.code === 'KeyV') {
+        e.preventDefault()
+        onPaste(e.altKey)
+      }
+
+      // Duplicate (Ctrl/Cmd + D; with Alt, also the notes they lead to)
+      if ((e.ctrlKey || e.metaKey) && !e.shiftKey && e.code === 'KeyD') {
+        e.preventDefault()
+        onDuplicate(e.altKey)
+      }
+
+      // Note templates: Ctrl/Cmd + Shift + T inserts one, Ctrl/Cmd + Alt +
+      // Shift + T keeps the selection as one
+      if ((e.ctrlKey || e.metaKey) && e.shiftKey && e.code === 'KeyT') {
+        e.preventDefault()
+        if (e.altKey) onSaveNoteTemplate()
+        else onInsertNoteTemplate()
+      }
+
+      // Stacking order (Ctrl/Cmd + Shift + ] / [)
+      if ((e.ctrlKey || e.metaKey) && e.shiftKey && selection.length > 0) {
+        if (e.code === 'BracketRight') {
+          e.preventDefault()
+          applyBackendEdit('Bring to front', doc => bringToFront(doc, selection))
+        } else if (e.code === 'BracketLeft') {
+          e.preventDefault()
+          applyBackendEdit('Send to back', doc => sendToBack(doc, selection))
+        }
+      }
+
+      // Group the selection (Ctrl/Cmd + Alt + G) or dissolve its groups
+      // (Ctrl/Cmd + Alt + Shift + G)
+      if ((e.ctrlKey || e.metaKey) && e.altKey && e.code === 'KeyG' && selection.length > 0) {
+        e.preventDefault()
+        if (e.shiftKey) {
+          const groupIds = (doc.groups ?? [])
+            .filter(group => [...group.noteIds, ...group.shapeIds].some(id => selection.includes(id)))
+            .map(group => group.id)
+          if (groupIds.length > 0) {
+            applyBackendEdit('Ungroup', async doc => {
+              for (const groupId of groupIds) doc = await dissolveGroup(doc, groupId)
+              return doc
+            })
+          }
+        } else if (selection.length >= 2) {
+          const noteIds = selection.filter(id => doc.notes.some(n => n.id === id))
+          const shapeIds = selection.filter(id => doc.shapes.some(s => s.id === id))
+          applyBackendEdit('Group', doc => createGroup(doc, noteIds, shapeIds).then(created => created.document))
+        }
+      }
+
       // Alignment shortcuts (Ctrl/Cmd + Shift + Arrow keys)
       if ((e.ctrlKey || e.metaKey) && e.shiftKey && selection.length >= 2) {
         switch (e.code) {
@@ -394,7 +908,7 @@ export function App() {
 
     window.addEventListener('keydown', handleKeyDown)
     return () => window.removeEventListener('keydown', handleKeyDown)
-  }, [onSave, onOpen, onForceAutosave, selection, executeCommand])
+  }, [onSave, onOpen, onForceAutosave, applyBackendEdit, onCopy, onCopyImage, onPaste, onDuplicate, onSaveNoteTemplate, onInsertNoteTemplate, doc, selection, executeCommand])
 
   return (
     <div style={{ 
@@ -406,6 +920,7 @@ export function App() {
     }}>
       <ModernToolbar
         onOpen={onOpen}
+        onImportOPML={onImportOPML}
         onSave={onSave}
         onForceAutosave={currentFilePath ? onForceAutosave : undefined}
         showAutosave={Boolean(currentFilePath)}
@@ -424,17 +939,45 @@ export function App() {
         setPdfPageSize={setPdfPageSize}
         pdfOrientation={pdfOrientation}
         setPdfOrientation={setPdfOrientation}
+        pdfLayout={pdfLayout}
+        setPdfLayout={setPdfLayout}
+        exportTheme={exportTheme}
+        setExportTheme={setExportTheme}
+        exportMarkings={exportMarkings}
+        setExportMarkings={setExportMarkings}
         textOrdering={textOrdering}
         setTextOrdering={setTextOrdering}
+        scrubMetadata={scrubMetadata}
+        setScrubMetadata={setScrubMetadata}
         onExportTXT={() => onExportTXT(textOrdering)}
         onExportRTF={() => onExportRTF(textOrdering)}
         onExportOPML={() => onExportOPML(textOrdering)}
+        onShare={onShare}
+        onExportPPTX={() => onExportPresentation('pptx')}
+        onExportSlidesHTML={() => onExportPresentation('html')}
+        onExportGIF={() => onExportWalkthrough('gif')}
+        onExportWebM={() => onExportWalkthrough('webm')}
         currentFilePath={currentFilePath}
         isDirty={isDirty}
         schemaVersion={doc.schemaVersion}
         onShowHelp={() => setShowHelp(true)}
+        onShowSettings={() => setShowSettings(true)}
         recentFilesComponent={<RecentFiles onOpenRecentFile={onOpenRecentFile} />}
       />
+      {migrationNotice && (
+        <div style={{
+          display: 'flex',
+          alignItems: 'center',
+          gap: '12px',
+          padding: '6px 12px',
+          background: 'rgba(74, 163, 255, 0.15)',
+          color: '#e2e8f0',
+          fontSize: '13px'
+        }}>
+          <span style={{ flex: 1 }}>{migrationNotice}</span>
+          <button onClick={() => setMigrationNotice(null)} style={{ background: 'none', border: 'none', color: 'inherit', cursor: 'pointer' }}>✕</button>
+        </div>
+      )}
       <div style={{ 
         flex: 1, 
         display: 'flex',
@@ -449,7 +992,7 @@ export function App() {
               shapes={currentDoc.shapes}
               stacks={currentDoc.stacks}
               selectedIds={selection}
-              onSelectionChange={setSelection}
+              onSelectionChange={ids => setSelection(withGroupMembers(currentDoc, ids))}
               onExecuteCommand={executeCommand}
               onNotesChange={(notes) => {
                 // Update temporary state for continuous operations
@@ -555,6 +1098,7 @@ export function App() {
         </ModernInspector>
       </div>
       <HelpOverlay isVisible={showHelp} onClose={() => setShowHelp(false)} />
+      <SettingsDialog isVisible={showSettings} onClose={() => setShowSettings(false)} />
       <SearchDialog
         isVisible={showSearch}
         onClose={() => setShowSearch(false)}
@@ -1,4 +1,5 @@
 import React, { useState, useEffect } from 'react'
+import { onAutosaveCompleted, onAutosaveFailed } from '../bridge/tauri'
 
 interface AutosaveIndicatorProps {
   style?: React.CSSProperties
@@ -10,9 +11,9 @@ export function AutosaveIndicator({ style }: AutosaveIndicatorProps) {
   const [visible, setVisible] = useState<boolean>(false)
 
   useEffect(() => {
-    const handleAutosaveCompleted = (event: CustomEvent) => {
+    const handleAutosaveCompleted = (timestamp: string) => {
       setStatus('saved')
-      setMessage(`Autosaved at ${new Date(event.detail.timestamp).toLocaleTimeString()}`)
+      setMessage(`Autosaved at ${new Date(timestamp).toLocaleTimeString()}`)
       setVisible(true)
 
       // Hide after 3 seconds
@@ -21,9 +22,9 @@ export function AutosaveIndicator({ style }: AutosaveIndicatorProps) {
       }, 3000)
     }
 
-    const handleAutosaveFailed = (event: CustomEvent) => {
+    const handleAutosaveFailed = (error: string) => {
       setStatus('error')
-      setMessage(`Autosave failed: ${event.detail.error}`)
+      setMessage(`Autosave failed: ${error}`)
       setVisible(true)
 
       // Hide after 5 seconds for errors
@@ -38,9 +39,25 @@ export function AutosaveIndicator({ style }: AutosaveIndicatorProps) {
       setVisible(true)
     }
 
-    // Listen for autosave events
-    window.addEventListener('autosave-completed', handleAutosaveCompleted as EventListener)
-    window.addEventListener('autosave-failed', handleAutosaveFailed as EventListener)
+    // useAutosave reports queued snapshots and refused ones; the backend
+    // reports whether the write itself worked
+    const handleQueued = () => handleAutosaveStart()
+    const handleRefused = (event: CustomEvent) => handleAutosaveFailed(event.detail.error)
+    window.addEventListener('autosave-queued', handleQueued)
+    window.addEventListener('autosave-failed', handleRefused as EventListener)
+
+    let cancelled = false
+    const stopListening: (() => void)[] = []
+    const keep = (stop: () => void) => {
+      if (cancelled) stop()
+      else stopListening.push(stop)
+    }
+    onAutosaveCompleted(info => handleAutosaveCompleted(info.timestamp))
+      .then(keep)
+      .catch(e => console.warn('Failed to receive autosave results', e))
+    onAutosaveFailed(event => handleAutosaveFailed(event.error))
+      .then(keep)
+      .catch(e => console.warn('Failed to receive autosave results', e))
 
     // We'll need to dispatch a custom event when autosave starts
     // For now, we'll use a mutation observer approach
@@ -59,8 +76,10 @@ export function AutosaveIndicator({ style }: AutosaveIndicatorProps) {
     }
 
     return () => {
-      window.removeEventListener('autosave-completed', handleAutosaveCompleted as EventListener)
-      window.removeEventListener('autosave-failed', handleAutosaveFailed as EventListener)
+      window.removeEventListener('autosave-queued', handleQueued)
+      window.removeEventListener('autosave-failed', handleRefused as EventListener)
+      cancelled = true
+      stopListening.forEach(stop => stop())
       // Restore original function if we replaced it
       if (originalAutosave) {
         window.autosaveDocument = originalAutosave
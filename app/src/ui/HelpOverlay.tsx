@@ -14,6 +14,18 @@ export function HelpOverlay({ isVisible, onClose }: HelpOverlayProps) {
       { key: 'Enter', action: 'Edit selected note' },
       { key: 'Esc', action: 'Finish editing' },
       { key: 'Delete/Backspace', action: 'Delete selected notes' },
+      { key: 'Ctrl/Cmd+C', action: 'Copy selected notes (Alt: as Markdown)' },
+      { key: 'Ctrl/Cmd+Shift+C', action: 'Copy board as image' },
+      { key: 'Ctrl/Cmd+V', action: 'Paste' },
+      { key: 'Ctrl/Cmd+Alt+V', action: 'Paste text as an outline' },
+      { key: 'Ctrl/Cmd+D', action: 'Duplicate selected notes' },
+      { key: 'Ctrl/Cmd+Alt+D', action: 'Duplicate with connected descendants' },
+      { key: 'Ctrl/Cmd+Shift+T', action: 'Insert note template' },
+      { key: 'Ctrl/Cmd+Alt+Shift+T', action: 'Save selection as note template' },
+      { key: 'Ctrl/Cmd+Shift+]', action: 'Bring to front' },
+      { key: 'Ctrl/Cmd+Shift+[', action: 'Send to back' },
+      { key: 'Ctrl/Cmd+Alt+G', action: 'Group selected items' },
+      { key: 'Ctrl/Cmd+Alt+Shift+G', action: 'Ungroup' },
     ]},
     { category: 'Selection', items: [
       { key: 'Click', action: 'Select note' },
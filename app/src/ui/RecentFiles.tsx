@@ -1,5 +1,5 @@
 import React, { useState, useEffect } from 'react'
-import { getRecentFiles, clearRecentFiles } from '../bridge/tauri'
+import { getRecentFiles, clearRecentFiles, type RecentFileEntry } from '../bridge/tauri'
 import { ModernButton } from './components/ModernButton'
 
 interface RecentFilesProps {
@@ -7,7 +7,7 @@ interface RecentFilesProps {
 }
 
 export function RecentFiles({ onOpenRecentFile }: RecentFilesProps) {
-  const [recentFiles, setRecentFiles] = useState<string[]>([])
+  const [recentFiles, setRecentFiles] = useState<RecentFileEntry[]>([])
   const [isOpen, setIsOpen] = useState(false)
   const [isLoading, setIsLoading] = useState(false)
 
@@ -31,7 +31,8 @@ export function RecentFiles({ onOpenRecentFile }: RecentFilesProps) {
   const handleClearRecentFiles = async () => {
     try {
       await clearRecentFiles()
-      setRecentFiles([])
+      // Pinned files survive clearing
+      setRecentFiles(recentFiles.filter(file => file.pinned))
       setIsOpen(false)
     } catch (error) {
       console.warn('Failed to clear recent files:', error)
@@ -43,12 +44,6 @@ export function RecentFiles({ onOpenRecentFile }: RecentFilesProps) {
     setIsOpen(false)
   }
 
-  // Extract filename from path for display
-  const getDisplayName = (filePath: string) => {
-    const parts = filePath.split(/[/\\]/)
-    return parts[parts.length - 1] || filePath
-  }
-
   // Format file path for display (shorten if too long)
   const formatPath = (filePath: string) => {
     const maxLength = 60
@@ -190,17 +185,17 @@ export function RecentFiles({ onOpenRecentFile }: RecentFilesProps) {
               </div>
             ) : (
               <div style={{ maxHeight: 300, overflowY: 'auto' }}>
-                {recentFiles.map((filePath, index) => (
+                {recentFiles.map((file, index) => (
                   <button
-                    key={filePath}
-                    onClick={() => handleOpenRecentFile(filePath)}
+                    key={file.path}
+                    onClick={() => handleOpenRecentFile(file.path)}
                     style={{
                       ...menuButtonStyle,
                       borderBottom: index === recentFiles.length - 1 ? 'none' : '1px solid #333',
                       textAlign: 'left',
                       width: '100%'
                     }}
-                    title={filePath}
+                    title={file.path}
                   >
                     <div style={{
                       display: 'flex',
@@ -212,14 +207,14 @@ export function RecentFiles({ onOpenRecentFile }: RecentFilesProps) {
                         fontWeight: '500',
                         color: '#fff'
                       }}>
-                        {getDisplayName(filePath)}
+                        {file.pinned ? '📌 ' : ''}{file.display_name}
                       </div>
                       <div style={{
                         fontSize: '10px',
                         color: '#999',
                         fontFamily: 'monospace'
                       }}>
-                        {formatPath(filePath)}
+                        {formatPath(file.path)}
                       </div>
                     </div>
                   </button>
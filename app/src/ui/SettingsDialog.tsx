@@ -0,0 +1,193 @@
+import React from 'react'
+import { getSettings, updateSettings, type AppSettings, type SettingsChanges } from '../bridge/tauri'
+
+interface SettingsDialogProps {
+  isVisible: boolean
+  onClose: () => void
+}
+
+// Opt-in preferences; each change is saved right away
+export function SettingsDialog({ isVisible, onClose }: SettingsDialogProps) {
+  const [settings, setSettings] = React.useState<AppSettings | null>(null)
+  const [error, setError] = React.useState<string | null>(null)
+
+  React.useEffect(() => {
+    if (!isVisible) return
+    setError(null)
+    getSettings()
+      .then(setSettings)
+      .catch(err => setError(String(err)))
+  }, [isVisible])
+
+  const change = async (changes: SettingsChanges) => {
+    setError(null)
+    try {
+      setSettings(await updateSettings(changes))
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  if (!isVisible) {
+    return null
+  }
+
+  const rowStyle: React.CSSProperties = {
+    display: 'flex',
+    alignItems: 'center',
+    justifyContent: 'space-between',
+    gap: 12,
+    padding: '6px 0'
+  }
+  const inputStyle: React.CSSProperties = {
+    backgroundColor: '#3e3e42',
+    color: '#fff',
+    border: '1px solid #555',
+    borderRadius: 4,
+    padding: '4px 8px',
+    width: 140
+  }
+
+  const toggle = (label: string, checked: boolean, changes: (checked: boolean) => SettingsChanges) => (
+    <label style={rowStyle}>
+      <span>{label}</span>
+      <input type="checkbox" checked={checked} onChange={e => change(changes(e.target.checked))} />
+    </label>
+  )
+
+  const number = (label: string, value: number, changes: (value: number) => SettingsChanges) => (
+    <label style={rowStyle}>
+      <span>{label}</span>
+      <input
+        type="number"
+        min={0}
+        value={value}
+        onChange={e => {
+          const parsed = parseInt(e.target.value, 10)
+          if (!Number.isNaN(parsed) && parsed >= 0) change(changes(parsed))
+        }}
+        style={inputStyle}
+      />
+    </label>
+  )
+
+  const text = (label: string, value: string, changes: (value: string) => SettingsChanges, placeholder?: string) => (
+    <label style={rowStyle}>
+      <span>{label}</span>
+      <input
+        type="text"
+        defaultValue={value}
+        placeholder={placeholder}
+        onBlur={e => {
+          if (e.target.value !== value) change(changes(e.target.value))
+        }}
+        style={inputStyle}
+      />
+    </label>
+  )
+
+  const heading = (title: string) => (
+    <h3 style={{ margin: '16px 0 4px 0', color: '#ffffff', fontSize: '14px' }}>{title}</h3>
+  )
+
+  return (
+    <div style={{
+      position: 'fixed',
+      top: 0,
+      left: 0,
+      right: 0,
+      bottom: 0,
+      backgroundColor: 'rgba(0, 0, 0, 0.7)',
+      display: 'flex',
+      alignItems: 'center',
+      justifyContent: 'center',
+      zIndex: 1000
+    }} onClick={onClose}>
+      <div style={{
+        backgroundColor: '#2d2d30',
+        border: '1px solid #3e3e42',
+        borderRadius: 8,
+        padding: 24,
+        maxWidth: 480,
+        width: '90%',
+        maxHeight: '80vh',
+        overflow: 'auto',
+        color: '#cccccc'
+      }} onClick={e => e.stopPropagation()}>
+        <h2 style={{ margin: '0 0 8px 0', color: '#ffffff' }}>
+          ⚙️ Settings
+        </h2>
+
+        {error && (
+          <div style={{
+            backgroundColor: '#4d1f1f',
+            border: '1px solid #8b3333',
+            borderRadius: 4,
+            padding: 12,
+            margin: '8px 0',
+            color: '#ff6b6b'
+          }}>
+            ❌ {error}
+          </div>
+        )}
+
+        {settings && (
+          <>
+            {heading('Privacy')}
+            {toggle('Overwrite temporary files before deleting them', settings.secureCleanup, secureCleanup => ({ secureCleanup }))}
+            {toggle('Keep an audit log of opens, saves and exports', settings.auditLog, auditLog => ({ auditLog }))}
+            {toggle('Keep local usage statistics', settings.usageStats, usageStats => ({ usageStats }))}
+
+            {heading('Saving')}
+            <label style={rowStyle}>
+              <span>Write mode</span>
+              <select
+                value={settings.writeMode}
+                onChange={e => change({ writeMode: e.target.value as AppSettings['writeMode'] })}
+                style={inputStyle}
+              >
+                <option value="auto">Automatic</option>
+                <option value="syncSafe">Sync-folder safe</option>
+                <option value="direct">Direct</option>
+              </select>
+            </label>
+            {number('Previous versions to keep', settings.keepVersions, keepVersions => ({ keepVersions }))}
+            {number('Snapshots to keep (0: all)', settings.keepSnapshots, keepSnapshots => ({ keepSnapshots }))}
+            {toggle('Write a search index file next to boards', settings.searchSidecar, searchSidecar => ({ searchSidecar }))}
+            {toggle('Simplify ink strokes', settings.simplifyStrokes, simplifyStrokes => ({ simplifyStrokes }))}
+            {toggle('Embed fonts in boards', settings.embedFonts, embedFonts => ({ embedFonts }))}
+            {number('Load images on demand above (MiB, 0: never)', settings.lazyMediaMb, lazyMediaMb => ({ lazyMediaMb }))}
+
+            {heading('Backups')}
+            {toggle('Back up open boards periodically', settings.backup.enabled, enabled => ({ backup: { enabled } }))}
+            {number('Interval (minutes)', settings.backup.intervalMinutes, intervalMinutes => ({ backup: { intervalMinutes } }))}
+            {number('Backups kept per board', settings.backup.keep, keep => ({ backup: { keep } }))}
+
+            {heading('Quick capture and exports')}
+            {text('Quick capture shortcut', settings.quickCaptureShortcut, quickCaptureShortcut => ({ quickCaptureShortcut }), 'Off')}
+            {text('Export language', settings.exportLocale, exportLocale => ({ exportLocale }), 'System')}
+            {text('Export time zone', settings.exportTimezone, exportTimezone => ({ exportTimezone }), 'local')}
+            {text('Author name', settings.authorName, authorName => ({ authorName }), 'Account name')}
+          </>
+        )}
+
+        <div style={{ display: 'flex', justifyContent: 'flex-end', marginTop: 20 }}>
+          <button
+            onClick={onClose}
+            style={{
+              backgroundColor: '#555',
+              color: '#fff',
+              border: 'none',
+              borderRadius: 4,
+              padding: '8px 16px',
+              cursor: 'pointer',
+              fontSize: '14px'
+            }}
+          >
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  )
+}
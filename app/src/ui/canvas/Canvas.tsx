@@ -3,6 +3,7 @@ import type { Note, Rect, Point, Connection, BackgroundShape, Stack, ID } from '
 import type { Command } from '../../state/commands'
 import { parseMarkdown, type MarkdownSegment } from '../../utils/markdown'
 import { SearchResult, findConnectedCluster } from '../../utils/search'
+import { onFilesDropped } from '../../bridge/tauri'
 import {
   CreateNotesCommand,
   DeleteNotesCommand,
@@ -24,7 +25,8 @@ import {
   AddSiblingNoteCommand,
   ChangeIndentCommand,
   CreateStackCommand,
-  UnstackCommand
+  UnstackCommand,
+  AddImageNotesCommand
 } from '../../state/commands'
 
 type Props = {
@@ -67,6 +69,9 @@ function rectToScreen(t: Transform, r: Rect) {
 export function Canvas({ notes, connections = [], shapes = [], stacks = [], selectedIds = [], onSelectionChange, onExecuteCommand, onNotesChange, onConnectionsChange, onShapesChange, onDragEnd, background = '#202124', highlightedSearchResult }: Props) {
   const canvasRef = useRef<HTMLCanvasElement | null>(null)
   const [transform, setTransform] = useState<Transform>({ scale: 1, tx: 0, ty: 0 })
+  // Read by event listeners that outlive a render
+  const transformRef = useRef(transform)
+  transformRef.current = transform
   const [panning, setPanning] = useState(false)
   const [spacePan, setSpacePan] = useState(false)
   const panStart = useRef<Point | null>(null)
@@ -437,8 +442,8 @@ export function Canvas({ notes, connections = [], shapes = [], stacks = [], sele
         return
       }
 
-      // Select Connected Cluster (Ctrl/Cmd + G)
-      if ((e.ctrlKey || e.metaKey) && e.code === 'KeyG' && !editing && !editingConnection) {
+      // Select Connected Cluster (Ctrl/Cmd + G; with Alt it groups)
+      if ((e.ctrlKey || e.metaKey) && !e.altKey && e.code === 'KeyG' && !editing && !editingConnection) {
         e.preventDefault()
         if (selectedIds.length === 1) {
           const selectedNoteId = selectedIds[0]
@@ -1095,6 +1100,41 @@ export function Canvas({ notes, connections = [], shapes = [], stacks = [], sele
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [notes, connections, shapes, transform, selectedIds, movementMode, magneticActive, magneticAffectedNotes])
 
+  // Images dropped from the file manager become notes where they were
+  // dropped, scaled down to at most DROPPED_IMAGE_SIZE on their longer side
+  useEffect(() => {
+    let cancelled = false
+    let stopListening: (() => void) | undefined
+
+    onFilesDropped(event => {
+      const c = canvasRef.current
+      if (!c || event.images.length === 0) return
+      const rect = c.getBoundingClientRect()
+      const newNotes: Note[] = event.images.map(({ image, position }, i) => {
+        const world = invTransform(transformRef.current, { x: position.x - rect.left, y: position.y - rect.top })
+        const scale = Math.min(1, DROPPED_IMAGE_SIZE / Math.max(image.width, image.height, 1))
+        return {
+          id: `note_${Date.now()}_${i}_${Math.random().toString(36).substring(2, 9)}`,
+          text: '',
+          frame: { x: world.x, y: world.y, w: Math.max(1, image.width * scale), h: Math.max(1, image.height * scale) },
+          images: [image.id]
+        }
+      })
+      onExecuteCommand(new AddImageNotesCommand(event.images.map(d => d.image), newNotes))
+      onSelectionChange?.(newNotes.map(n => n.id))
+    })
+      .then(stop => {
+        if (cancelled) stop()
+        else stopListening = stop
+      })
+      .catch(e => console.warn('Failed to receive dropped files', e))
+
+    return () => {
+      cancelled = true
+      stopListening?.()
+    }
+  }, [onExecuteCommand, onSelectionChange])
+
   // Mouse interactions
   useEffect(() => {
     const c = canvasRef.current
@@ -2241,6 +2281,7 @@ function hitTestShapeResizeHandle(shape: BackgroundShape, pWorld: Point, toleran
 }
 
 // Magnetic shape functionality
+const DROPPED_IMAGE_SIZE = 240 // Longest side of a dropped image's note, in world coordinates
 const MAGNETIC_DISTANCE = 30 // Distance threshold for magnetic behavior in world coordinates
 const MAGNETIC_OVERLAP_THRESHOLD = 0.5 // Minimum overlap ratio for magnetic group translation (0.5 = 50% overlap)
 
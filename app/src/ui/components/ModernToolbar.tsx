@@ -5,6 +5,7 @@ import { ModernSelect } from './ModernSelect'
 interface ModernToolbarProps {
   // File operations
   onOpen: () => void
+  onImportOPML: () => void
   onSave: () => void
   onForceAutosave?: () => void
   showAutosave?: boolean
@@ -31,13 +32,28 @@ interface ModernToolbarProps {
   setPdfPageSize: (size: 'a3' | 'a4' | 'a5' | 'letter' | 'legal') => void
   pdfOrientation: 'auto' | 'portrait' | 'landscape'
   setPdfOrientation: (orientation: 'auto' | 'portrait' | 'landscape') => void
+  pdfLayout: 'fit' | 'poster'
+  setPdfLayout: (layout: 'fit' | 'poster') => void
+  exportTheme: 'board' | 'light' | 'dark'
+  setExportTheme: (theme: 'board' | 'light' | 'dark') => void
+  exportMarkings: 'none' | 'page-numbers' | 'confidential' | 'draft'
+  setExportMarkings: (markings: 'none' | 'page-numbers' | 'confidential' | 'draft') => void
   
   // Text export
   textOrdering: 'spatial' | 'connections' | 'hierarchical'
   setTextOrdering: (ordering: 'spatial' | 'connections' | 'hierarchical') => void
+  scrubMetadata: boolean
+  setScrubMetadata: (scrub: boolean) => void
   onExportTXT: () => void
   onExportRTF: () => void
   onExportOPML: () => void
+  onShare: () => void
+
+  // Presentation export
+  onExportPPTX: () => void
+  onExportSlidesHTML: () => void
+  onExportGIF: () => void
+  onExportWebM: () => void
   
   // Status
   currentFilePath?: string | null
@@ -46,6 +62,7 @@ interface ModernToolbarProps {
   
   // Help
   onShowHelp: () => void
+  onShowSettings: () => void
   
   // Recent files component
   recentFilesComponent?: React.ReactNode
@@ -108,6 +125,9 @@ export function ModernToolbar(props: ModernToolbarProps) {
   const ImageIcon = () => <span style={{ fontSize: '14px' }}>🖼️</span>
   const PdfIcon = () => <span style={{ fontSize: '14px' }}>📑</span>
   const TextIcon = () => <span style={{ fontSize: '14px' }}>📝</span>
+  const SlidesIcon = () => <span style={{ fontSize: '14px' }}>📽️</span>
+  const ShareIcon = () => <span style={{ fontSize: '14px' }}>📤</span>
+  const SettingsIcon = () => <span style={{ fontSize: '14px' }}>⚙️</span>
   const HelpIcon = () => <span style={{ fontSize: '14px' }}>❓</span>
 
   return (
@@ -123,6 +143,16 @@ export function ModernToolbar(props: ModernToolbarProps) {
         >
           Open
         </ModernButton>
+
+        <ModernButton
+          onClick={props.onImportOPML}
+          variant="ghost"
+          size="sm"
+          icon={<TextIcon />}
+          title="Import an OPML outline as a new board"
+        >
+          Import OPML
+        </ModernButton>
         
         {props.recentFilesComponent}
         
@@ -249,6 +279,39 @@ export function ModernToolbar(props: ModernToolbarProps) {
           ]}
           title="PDF Orientation"
         />
+        
+        <ModernSelect
+          value={props.pdfLayout}
+          onChange={(value) => props.setPdfLayout(value as any)}
+          options={[
+            { value: 'fit', label: 'Fit to Page' },
+            { value: 'poster', label: 'Poster (Tiled)' }
+          ]}
+          title="PDF Layout: fit the board on one page, or tile it at true scale"
+        />
+        
+        <ModernSelect
+          value={props.exportTheme}
+          onChange={(value) => props.setExportTheme(value as any)}
+          options={[
+            { value: 'board', label: 'Board Colors' },
+            { value: 'light', label: 'Light Theme' },
+            { value: 'dark', label: 'Dark Theme' }
+          ]}
+          title="Colors used for PNG and PDF exports"
+        />
+
+        <ModernSelect
+          value={props.exportMarkings}
+          onChange={(value) => props.setExportMarkings(value as any)}
+          options={[
+            { value: 'none', label: 'No Markings' },
+            { value: 'page-numbers', label: 'Page Numbers' },
+            { value: 'confidential', label: 'Confidential' },
+            { value: 'draft', label: 'Draft' }
+          ]}
+          title="Watermark and header/footer for PNG and PDF exports"
+        />
       </section>
 
       <section style={sectionStyle}>
@@ -262,6 +325,16 @@ export function ModernToolbar(props: ModernToolbarProps) {
           ]}
           title="Text Ordering Heuristic"
         />
+
+        <ModernSelect
+          value={props.scrubMetadata ? 'strip' : 'keep'}
+          onChange={(value) => props.setScrubMetadata(value === 'strip')}
+          options={[
+            { value: 'keep', label: 'Keep Metadata' },
+            { value: 'strip', label: 'Strip Metadata' }
+          ]}
+          title="Leave timestamps, style names and IDs out of text and PDF exports, for anonymous sharing"
+        />
         
         <ModernButton
           onClick={props.onExportTXT}
@@ -289,6 +362,58 @@ export function ModernToolbar(props: ModernToolbarProps) {
         >
           OPML
         </ModernButton>
+
+        <ModernButton
+          onClick={props.onShare}
+          variant="secondary"
+          size="sm"
+          icon={<ShareIcon />}
+          title="Share the board as an image through the system share sheet"
+        >
+          Share
+        </ModernButton>
+      </section>
+
+      <section style={sectionStyle}>
+        <ModernButton
+          onClick={props.onExportPPTX}
+          variant="secondary"
+          size="sm"
+          icon={<SlidesIcon />}
+          title="Export each stack as a slide (PowerPoint)"
+        >
+          PPTX
+        </ModernButton>
+
+        <ModernButton
+          onClick={props.onExportSlidesHTML}
+          variant="secondary"
+          size="sm"
+          icon={<SlidesIcon />}
+          title="Export each stack as a slide (reveal.js HTML)"
+        >
+          Slides
+        </ModernButton>
+
+        <ModernButton
+          onClick={props.onExportGIF}
+          variant="secondary"
+          size="sm"
+          icon={<SlidesIcon />}
+          title="Animated walkthrough of the selected notes, in selection order (GIF)"
+        >
+          GIF
+        </ModernButton>
+
+        <ModernButton
+          onClick={props.onExportWebM}
+          variant="secondary"
+          size="sm"
+          icon={<SlidesIcon />}
+          title="Animated walkthrough of the selected notes, in selection order (WebM)"
+        >
+          WebM
+        </ModernButton>
       </section>
 
       {/* Status */}
@@ -302,6 +427,16 @@ export function ModernToolbar(props: ModernToolbarProps) {
         >
           Help
         </ModernButton>
+
+        <ModernButton
+          onClick={props.onShowSettings}
+          variant="ghost"
+          size="sm"
+          icon={<SettingsIcon />}
+          title="Settings"
+        >
+          Settings
+        </ModernButton>
         
         <span>v{props.schemaVersion || 1}</span>
         